@@ -0,0 +1,66 @@
+//! Parser benchmarks over the documents of `workload::standard`
+//!
+//! Run with `cargo bench`; `cargo bench -- twitter` selects one workload.
+//! criterion keeps the previous run's results in `target/criterion` and
+//! reports changes against them.
+
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use json_parser_rs::push::{PushParser, PushStatus};
+use json_parser_rs::{parse_json, parse_json_reader, parse_json_ref, workload};
+use std::hint::black_box;
+
+fn parse(c: &mut Criterion) {
+    let mut group = c.benchmark_group("parse");
+    for workload in workload::standard() {
+        let input = workload.input.as_str();
+        group.throughput(Throughput::Bytes(workload.len() as u64));
+        group.bench_with_input(
+            BenchmarkId::new("parse_json", workload.name),
+            input,
+            |b, input| b.iter(|| parse_json(black_box(input)).unwrap()),
+        );
+        group.bench_with_input(
+            BenchmarkId::new("parse_json_ref", workload.name),
+            input,
+            |b, input| b.iter(|| parse_json_ref(black_box(input)).unwrap()),
+        );
+        group.bench_with_input(
+            BenchmarkId::new("parse_json_reader", workload.name),
+            input,
+            |b, input| b.iter(|| parse_json_reader(black_box(input.as_bytes())).unwrap()),
+        );
+        group.bench_with_input(
+            BenchmarkId::new("push_parser", workload.name),
+            input,
+            |b, input| {
+                b.iter(|| {
+                    let mut parser = PushParser::new();
+                    for chunk in black_box(input.as_bytes()).chunks(8 * 1024) {
+                        if let PushStatus::Value(value) = parser.feed(chunk).unwrap() {
+                            return value;
+                        }
+                    }
+                    parser.finish().unwrap().unwrap()
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+fn serialize(c: &mut Criterion) {
+    let mut group = c.benchmark_group("serialize");
+    for workload in workload::standard() {
+        let value = workload.parse().unwrap();
+        group.throughput(Throughput::Bytes(workload.len() as u64));
+        group.bench_with_input(
+            BenchmarkId::new("to_json_string", workload.name),
+            &value,
+            |b, value| b.iter(|| black_box(value).to_json_string()),
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, parse, serialize);
+criterion_main!(benches);
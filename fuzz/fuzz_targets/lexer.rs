@@ -0,0 +1,27 @@
+//! Tokenize arbitrary text with the strict and the JSON5 lexer
+#![no_main]
+
+use json_parser_rs::ParserOptions;
+use json_parser_rs::lexer::{Lexer, Token};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|input: &str| {
+    for options in [ParserOptions::default(), ParserOptions::json5()] {
+        let mut lexer = Lexer::with_options(input, &options);
+        // Every token consumes input, so this ends after at most len + 1 tokens
+        for _ in 0..=input.len() {
+            match lexer.next_spanned_token() {
+                Ok(spanned) => {
+                    assert!(spanned.start <= spanned.end && spanned.end <= input.len());
+                    if spanned.token == Token::Eof {
+                        break;
+                    }
+                }
+                Err(error) => {
+                    assert!(error.position() <= input.len());
+                    break;
+                }
+            }
+        }
+    }
+});
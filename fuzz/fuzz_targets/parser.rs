@@ -0,0 +1,27 @@
+//! Parse arbitrary text with every parser; they must agree on what is valid
+#![no_main]
+
+use json_parser_rs::push::PushParser;
+use json_parser_rs::{parse_json, parse_json_reader, parse_json_ref, parse_json5};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|input: &str| {
+    let value = parse_json(input);
+    let _ = parse_json5(input);
+
+    let borrowed = parse_json_ref(input).map(|value| value.into_owned());
+    assert_eq!(borrowed.is_ok(), value.is_ok());
+    let read = parse_json_reader(input.as_bytes());
+    assert_eq!(read.is_ok(), value.is_ok());
+    if let (Ok(value), Ok(borrowed), Ok(read)) = (&value, &borrowed, &read) {
+        assert_eq!(value, borrowed);
+        assert_eq!(value, read);
+    }
+
+    // Feed the push parser in two pieces so values split across chunks
+    let mut parser = PushParser::new();
+    let (head, tail) = input.as_bytes().split_at(input.len() / 2);
+    let _ = parser.feed(head);
+    let _ = parser.feed(tail);
+    let _ = parser.finish();
+});
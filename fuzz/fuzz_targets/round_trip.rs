@@ -0,0 +1,17 @@
+//! Whatever parses must serialize to text that parses to the same value
+#![no_main]
+
+use json_parser_rs::parse_json;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|input: &str| {
+    let Ok(value) = parse_json(input) else {
+        return;
+    };
+    let minified = value.to_json_string();
+    assert_eq!(parse_json(&minified).unwrap(), value, "{}", minified);
+    let pretty = value.to_pretty_string();
+    assert_eq!(parse_json(&pretty).unwrap(), value, "{}", pretty);
+    // Serializing is deterministic, so a second round gives the same text
+    assert_eq!(parse_json(&minified).unwrap().to_json_string(), minified);
+});
@@ -0,0 +1,13 @@
+//! Round-trip generated documents instead of raw text, reaching deep
+//! structures and unusual strings and numbers faster than byte mutation
+#![no_main]
+
+use json_parser_rs::{JsonValue, parse_json, parse_json_ref};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|value: JsonValue| {
+    let text = value.to_json_string();
+    assert_eq!(parse_json(&text).unwrap(), value, "{}", text);
+    assert_eq!(parse_json_ref(&text).unwrap().into_owned(), value, "{}", text);
+    assert_eq!(parse_json(&value.to_pretty_string()).unwrap(), value);
+});
@@ -0,0 +1,374 @@
+//! Arena DOM - parsing into a caller-provided bump allocator
+//!
+//! A document with a million values built by `parse_json` makes a million
+//! allocations for its `Vec`s, `String`s and object maps, and as many
+//! deallocations when it is dropped. `parse_json_in` places every node and
+//! string in a [`bumpalo::Bump`] instead: allocation is a pointer bump,
+//! containers are allocated once at their final size, and dropping (or
+//! `reset`ting) the arena releases the whole document at once. An
+//! [`ArenaValue`] owns no heap memory itself, so it is `Copy` and never
+//! needs dropping.
+
+use std::borrow::Cow;
+use std::collections::HashSet;
+
+use bumpalo::Bump;
+
+use crate::error::ParseError;
+use crate::json::JsonValue;
+use crate::number::JsonNumber;
+use crate::object::JsonObject;
+use crate::options::ParserOptions;
+use crate::parser::{Builder, Parser, Scalar, lossy_string};
+
+/// JSON value allocated in an arena
+///
+/// Objects keep their members in document order. Under the default
+/// `DuplicateKeyPolicy::Last` repeated keys are kept too and
+/// [`ArenaValue::get`] returns the last occurrence, the value `parse_json`
+/// keeps.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ArenaValue<'a> {
+    Null,
+    Boolean(bool),
+    Number(JsonNumber),
+    String(&'a str),
+    Array(&'a [ArenaValue<'a>]),
+    Object(&'a [(&'a str, ArenaValue<'a>)]),
+}
+
+impl<'a> ArenaValue<'a> {
+    pub fn is_null(&self) -> bool {
+        matches!(self, ArenaValue::Null)
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            ArenaValue::Boolean(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    pub fn as_number(&self) -> Option<&JsonNumber> {
+        match self {
+            ArenaValue::Number(n) => Some(n),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        self.as_number().map(JsonNumber::as_f64)
+    }
+
+    /// Number as `i64`, only if it is integral and within range
+    pub fn as_i64(&self) -> Option<i64> {
+        self.as_number()?.as_i64()
+    }
+
+    /// Number as `u64`, only if it is integral and within range
+    pub fn as_u64(&self) -> Option<u64> {
+        self.as_number()?.as_u64()
+    }
+
+    pub fn as_str(&self) -> Option<&'a str> {
+        match self {
+            ArenaValue::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&'a [ArenaValue<'a>]> {
+        match self {
+            ArenaValue::Array(elements) => Some(elements),
+            _ => None,
+        }
+    }
+
+    /// Object members in document order, including repeated keys
+    pub fn as_object(&self) -> Option<&'a [(&'a str, ArenaValue<'a>)]> {
+        match self {
+            ArenaValue::Object(members) => Some(members),
+            _ => None,
+        }
+    }
+
+    /// Look up an object member by key (the last one if the key repeats)
+    pub fn get(&self, key: &str) -> Option<&'a ArenaValue<'a>> {
+        self.as_object()?
+            .iter()
+            .rev()
+            .find(|(k, _)| *k == key)
+            .map(|(_, value)| value)
+    }
+
+    /// Look up an array element by index
+    pub fn get_index(&self, index: usize) -> Option<&'a ArenaValue<'a>> {
+        self.as_array()?.get(index)
+    }
+
+    /// Copy out of the arena into an owned `JsonValue`, the same tree
+    /// `parse_json` builds
+    pub fn to_json_value(&self) -> JsonValue {
+        match *self {
+            ArenaValue::Null => JsonValue::Null,
+            ArenaValue::Boolean(b) => JsonValue::Boolean(b),
+            ArenaValue::Number(n) => JsonValue::Number(n),
+            ArenaValue::String(s) => JsonValue::String(s.to_string()),
+            ArenaValue::Array(elements) => {
+                JsonValue::Array(elements.iter().map(Self::to_json_value).collect())
+            }
+            ArenaValue::Object(members) => {
+                let mut object = JsonObject::with_capacity(members.len());
+                for (key, value) in members {
+                    object.insert(key.to_string(), value.to_json_value());
+                }
+                JsonValue::Object(object)
+            }
+        }
+    }
+}
+
+impl From<ArenaValue<'_>> for JsonValue {
+    fn from(value: ArenaValue<'_>) -> Self {
+        value.to_json_value()
+    }
+}
+
+/// Parse strict JSON into `arena`
+///
+/// Accepts exactly what `parse_json` accepts and reports the same errors.
+/// Strings are copied into the arena, so the result does not borrow
+/// `input`.
+///
+/// # Examples
+///
+/// ```
+/// use bumpalo::Bump;
+/// use json_parser_rs::arena::parse_json_in;
+///
+/// let mut arena = Bump::new();
+/// for input in [r#"{"id": 1, "tags": ["a", "b"]}"#, r#"{"id": 2, "tags": []}"#] {
+///     let value = parse_json_in(input, &arena).unwrap();
+///     let tags = value.get("tags").and_then(|tags| tags.as_array()).unwrap();
+///     assert!(tags.iter().all(|tag| tag.as_str().is_some()));
+///     // Release the whole document at once and reuse the memory
+///     arena.reset();
+/// }
+/// ```
+pub fn parse_json_in<'a>(input: &str, arena: &'a Bump) -> Result<ArenaValue<'a>, ParseError> {
+    parse_json_in_with_options(input, arena, ParserOptions::default())
+}
+
+/// Parse into `arena` with explicit limits and relaxations
+///
+/// The input is read by the same `Parser` as `parse_json`, so every option
+/// applies as it does there; unpaired surrogates kept by
+/// `preserve_lone_surrogates` are replaced by U+FFFD.
+///
+/// # Examples
+///
+/// ```
+/// use bumpalo::Bump;
+/// use json_parser_rs::ParserOptions;
+/// use json_parser_rs::arena::parse_json_in_with_options;
+///
+/// let arena = Bump::new();
+/// let value = parse_json_in_with_options("[1, 2,]", &arena, ParserOptions::json5()).unwrap();
+/// assert_eq!(value.as_array().map(<[_]>::len), Some(2));
+/// ```
+pub fn parse_json_in_with_options<'a>(
+    input: &str,
+    arena: &'a Bump,
+    options: ParserOptions,
+) -> Result<ArenaValue<'a>, ParseError> {
+    let mut builder = ArenaBuilder {
+        arena,
+        elements: Vec::new(),
+        members: Vec::new(),
+    };
+    Parser::with_options(input, options)?.parse_with(&mut builder)
+}
+
+/// Builds `ArenaValue` trees for the `Parser`
+///
+/// Elements and members of the open containers are collected on shared
+/// scratch stacks and copied into the arena in one piece when a container
+/// closes, so the arena holds no abandoned partial arrays.
+struct ArenaBuilder<'a> {
+    arena: &'a Bump,
+    elements: Vec<ArenaValue<'a>>,
+    members: Vec<(&'a str, ArenaValue<'a>)>,
+}
+
+/// Object under construction, whose members start at `start` in
+/// `ArenaBuilder::members`
+struct OpenObject<'a> {
+    start: usize,
+    /// Its keys, collected on the first duplicate check
+    keys: Option<HashSet<&'a str>>,
+}
+
+impl<'a, 'i> Builder<'i> for ArenaBuilder<'a> {
+    type Value = ArenaValue<'a>;
+    /// Where the array's elements start in `elements`
+    type Array = usize;
+    type Object = OpenObject<'a>;
+
+    fn scalar(&mut self, scalar: Scalar<'i>) -> ArenaValue<'a> {
+        match scalar {
+            Scalar::Null => ArenaValue::Null,
+            Scalar::Boolean(b) => ArenaValue::Boolean(b),
+            Scalar::Number(n) => ArenaValue::Number(n),
+            Scalar::String(s) => ArenaValue::String(self.arena.alloc_str(&s)),
+            Scalar::RawString(raw) => ArenaValue::String(self.arena.alloc_str(&lossy_string(raw))),
+        }
+    }
+
+    fn begin_array(&mut self) -> usize {
+        self.elements.len()
+    }
+
+    fn push_element(&mut self, _: &mut usize, element: ArenaValue<'a>) {
+        self.elements.push(element);
+    }
+
+    fn end_array(&mut self, start: usize) -> ArenaValue<'a> {
+        let elements = self.arena.alloc_slice_copy(&self.elements[start..]);
+        self.elements.truncate(start);
+        ArenaValue::Array(elements)
+    }
+
+    fn begin_object(&mut self) -> OpenObject<'a> {
+        OpenObject {
+            start: self.members.len(),
+            keys: None,
+        }
+    }
+
+    fn object_len(&self, object: &OpenObject<'a>) -> usize {
+        self.members.len() - object.start
+    }
+
+    fn contains_key(&mut self, object: &mut OpenObject<'a>, key: &str) -> bool {
+        let members = &self.members[object.start..];
+        object
+            .keys
+            .get_or_insert_with(|| members.iter().map(|(key, _)| *key).collect())
+            .contains(key)
+    }
+
+    fn insert_member(
+        &mut self,
+        object: &mut OpenObject<'a>,
+        key: Cow<'i, str>,
+        value: ArenaValue<'a>,
+    ) {
+        let key = &*self.arena.alloc_str(&key);
+        if let Some(keys) = &mut object.keys {
+            keys.insert(key);
+        }
+        // Repeated keys are kept; `get` finds the last one
+        self.members.push((key, value));
+    }
+
+    fn end_object(&mut self, object: OpenObject<'a>) -> ArenaValue<'a> {
+        let members = self.arena.alloc_slice_copy(&self.members[object.start..]);
+        self.members.truncate(object.start);
+        ArenaValue::Object(members)
+    }
+
+    fn key_size(len: usize) -> usize {
+        std::mem::size_of::<&str>() + len
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ErrorKind;
+    use crate::object::DuplicateKeyPolicy;
+    use crate::options::DEFAULT_MAX_DEPTH;
+    use crate::parse_json;
+
+    #[test]
+    fn test_matches_owned_parser() {
+        let inputs = [
+            r#"{"a": [1, -2, 3.5e2, true, false, null], "b": {"c": "d\né"}, "e": []}"#,
+            r#"[{}, [[]], "x", {"dup": 1, "dup": 2}]"#,
+            "  42  ",
+        ];
+        let arena = Bump::new();
+        for input in inputs {
+            let value = parse_json_in(input, &arena).unwrap();
+            assert_eq!(
+                value.to_json_value(),
+                parse_json(input).unwrap(),
+                "{}",
+                input
+            );
+        }
+        let value = parse_json_in(inputs[1], &arena).unwrap();
+        assert_eq!(
+            value.get_index(3).unwrap().get("dup").unwrap().as_i64(),
+            Some(2)
+        );
+        assert_eq!(value.get_index(3).unwrap().as_object().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_errors_match_owned_parser() {
+        let deep = "[".repeat(DEFAULT_MAX_DEPTH + 1);
+        let inputs = [
+            "",
+            "[1,]",
+            "{\"a\" 1}",
+            "{1: 2}",
+            "[1] x",
+            "[1 2",
+            "\"open",
+            &deep,
+        ];
+        let arena = Bump::new();
+        for input in inputs {
+            let error = parse_json_in(input, &arena).unwrap_err();
+            let expected = parse_json(input).unwrap_err();
+            assert_eq!(
+                (error.kind(), error.position()),
+                (expected.kind(), expected.position()),
+                "{}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_options_apply() {
+        let arena = Bump::new();
+        let parse = |input, options| parse_json_in_with_options(input, &arena, options);
+        let shallow = ParserOptions::default().max_depth(Some(2));
+        assert!(parse("[[1]]", shallow).is_ok());
+        let error = parse("[[[1]]]", shallow).unwrap_err();
+        assert_eq!(error.message(), "Maximum nesting depth of 2 exceeded");
+
+        let input = "{a: [1, 'x',], // note\n b: {c: null,},}";
+        assert!(parse_json_in(input, &arena).is_err());
+        let value = parse(input, ParserOptions::json5()).unwrap();
+        assert_eq!(value.to_json_value(), crate::parse_json5(input).unwrap());
+
+        let input = r#"[{"a": 1, "b": [2], "a": 3}]"#;
+        let first = ParserOptions::default().duplicate_keys(DuplicateKeyPolicy::First);
+        let object = *parse(input, first).unwrap().get_index(0).unwrap();
+        assert_eq!(object.as_object().unwrap().len(), 2);
+        assert_eq!(object.get("a").and_then(ArenaValue::as_i64), Some(1));
+        let error = parse(input, ParserOptions::hardened()).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::DuplicateKey);
+        assert_eq!(error.position(), 20);
+
+        let few = ParserOptions::default().max_elements(Some(2));
+        let error = parse(r#"{"a": 1, "b": 2, "c": 3}"#, few).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::TooManyElements);
+        let lone = ParserOptions::default().preserve_lone_surrogates(true);
+        let value = parse(r#""\udc00""#, lone).unwrap();
+        assert_eq!(value.as_str(), Some("\u{fffd}"));
+    }
+}
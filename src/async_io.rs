@@ -0,0 +1,548 @@
+//! Async input - parsing from a tokio `AsyncRead`
+//!
+//! Services reading request bodies or sockets on an async executor should
+//! neither block a worker thread on I/O nor collect the whole input into a
+//! `String` first. [`parse_json_async`] and [`JsonStream`] read the input in
+//! chunks and feed them to a [`PushParser`], so only unfinished tokens and
+//! partly built values are held in memory.
+//!
+//! In the other direction [`JsonStreamWriter`] writes NDJSON or one JSON
+//! array value by value and holds producers back while a slow client has
+//! not taken the bytes already buffered.
+
+use std::future::poll_fn;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite};
+
+use crate::error::{ErrorKind, ParseError};
+use crate::json::JsonValue;
+use crate::options::ParserOptions;
+use crate::push::{PushParser, PushStatus};
+
+/// Size of the chunks read from the underlying reader
+const CHUNK_SIZE: usize = 8 * 1024;
+
+/// Read a single JSON document from `reader`
+///
+/// Fails like `parse_json` on malformed or empty input and on anything but
+/// whitespace after the value. I/O errors are reported with
+/// [`ErrorKind::Io`].
+///
+/// # Examples
+///
+/// ```
+/// use json_parser_rs::{async_io::parse_json_async, json};
+///
+/// # tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+/// let body: &[u8] = br#"{"id": 7, "tags": ["a", "b"]}"#;
+/// let value = parse_json_async(body).await.unwrap();
+/// assert_eq!(value, json!({"id": 7, "tags": ["a", "b"]}));
+///
+/// assert!(parse_json_async(&b"[1] [2]"[..]).await.is_err());
+/// # });
+/// ```
+pub async fn parse_json_async<R: AsyncRead + Unpin>(reader: R) -> Result<JsonValue, ParseError> {
+    parse_json_async_with_options(reader, ParserOptions::default()).await
+}
+
+/// [`parse_json_async`] with explicit parser options
+pub async fn parse_json_async_with_options<R: AsyncRead + Unpin>(
+    mut reader: R,
+    options: ParserOptions,
+) -> Result<JsonValue, ParseError> {
+    let mut parser = PushParser::with_options(options);
+    let mut buffer = vec![0; CHUNK_SIZE];
+    let mut value = None;
+    loop {
+        let read = read_chunk(&mut reader, &mut buffer, &parser).await?;
+        if read == 0 {
+            break;
+        }
+        match value {
+            // Once the value is complete the rest is only checked, not parsed
+            Some(_) => parser.buffer(&buffer[..read])?,
+            None => {
+                if let PushStatus::Value(parsed) = parser.feed(&buffer[..read])? {
+                    value = Some(parsed);
+                }
+            }
+        }
+        if value.is_some() {
+            parser.check_trailing()?;
+        }
+    }
+
+    let value = match value {
+        Some(value) => value,
+        None => match parser.finish()? {
+            Some(value) => value,
+            None => {
+                let error = parser.error_at_end("Unexpected token: end of input");
+                return Err(error.with_kind(ErrorKind::UnexpectedEof));
+            }
+        },
+    };
+    parser.check_trailing()?;
+    Ok(value)
+}
+
+/// Sequence of JSON values read from an async reader
+///
+/// Values may be separated by whitespace or newlines (NDJSON) or simply
+/// follow each other (`{"a":1}{"a":2}`), as in a stream of events.
+///
+/// # Examples
+///
+/// ```
+/// use json_parser_rs::{async_io::JsonStream, json};
+///
+/// # tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+/// let events: &[u8] = b"{\"event\": \"start\"}\n{\"event\": \"stop\"}\n";
+/// let mut stream = JsonStream::new(events);
+/// while let Some(event) = stream.next_value().await.unwrap() {
+///     assert!(event["event"].is_string());
+/// }
+/// # });
+/// ```
+pub struct JsonStream<R> {
+    reader: R,
+    parser: PushParser,
+    buffer: Vec<u8>,
+    eof: bool,
+}
+
+impl<R: AsyncRead + Unpin> JsonStream<R> {
+    pub fn new(reader: R) -> Self {
+        Self::with_options(reader, ParserOptions::default())
+    }
+
+    pub fn with_options(reader: R, options: ParserOptions) -> Self {
+        Self {
+            reader,
+            parser: PushParser::with_options(options),
+            buffer: vec![0; CHUNK_SIZE],
+            eof: false,
+        }
+    }
+
+    /// The next complete value, reading more input as needed; `None` at the
+    /// end of the input
+    ///
+    /// After an error every further call returns the same error.
+    pub async fn next_value(&mut self) -> Result<Option<JsonValue>, ParseError> {
+        loop {
+            if let PushStatus::Value(value) = self.parser.poll()? {
+                return Ok(Some(value));
+            }
+            if self.eof {
+                return self.parser.finish();
+            }
+            let read = read_chunk(&mut self.reader, &mut self.buffer, &self.parser).await?;
+            if read == 0 {
+                self.eof = true;
+            } else {
+                self.parser.buffer(&self.buffer[..read])?;
+            }
+        }
+    }
+
+    /// Total number of bytes read so far
+    pub fn bytes_read(&self) -> usize {
+        self.parser.bytes_fed()
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+/// Layout of the output of a [`JsonStreamWriter`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StreamFormat {
+    /// One minified value per line
+    #[default]
+    Ndjson,
+    /// A single array holding all values, closed by
+    /// [`JsonStreamWriter::finish`]
+    Array,
+}
+
+/// Default limit for the bytes buffered before [`JsonStreamWriter::ready`]
+/// waits for the writer, 64 KiB
+pub const DEFAULT_HIGH_WATER_MARK: usize = 64 * 1024;
+
+/// Writes a sequence of values to an async writer with backpressure
+///
+/// Values are serialized into a buffer. Once it holds `high_water_mark`
+/// bytes, [`JsonStreamWriter::ready`] (or [`JsonStreamWriter::poll_ready`]
+/// in hand-written futures and `Sink` adapters) does not complete until the
+/// writer has taken enough of them, so a slow client slows the producer
+/// down instead of letting the buffer grow. The buffer therefore never
+/// holds much more than the high-water mark plus one value.
+///
+/// With a flush interval, [`JsonStreamWriter::send`] also flushes when that
+/// much time has passed since the last flush, so a client sees records
+/// while the stream is still being produced. The interval is checked on
+/// each send; a producer that goes idle should call
+/// [`JsonStreamWriter::flush`] itself.
+///
+/// # Examples
+///
+/// ```
+/// use json_parser_rs::async_io::{JsonStreamWriter, StreamFormat};
+/// use json_parser_rs::json;
+///
+/// # tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+/// let mut writer = JsonStreamWriter::new(Vec::new(), StreamFormat::Array).high_water_mark(1024);
+/// for id in 1..=3 {
+///     writer.send(&json!({"id": id})).await.unwrap();
+/// }
+/// let body = writer.finish().await.unwrap();
+/// assert_eq!(body, br#"[{"id":1},{"id":2},{"id":3}]"#);
+/// # });
+/// ```
+#[derive(Debug)]
+pub struct JsonStreamWriter<W> {
+    writer: W,
+    format: StreamFormat,
+    buffer: Vec<u8>,
+    /// Bytes at the start of `buffer` already handed to the writer
+    written: usize,
+    high_water_mark: usize,
+    flush_interval: Option<Duration>,
+    last_flush: Instant,
+    count: usize,
+}
+
+impl<W: AsyncWrite + Unpin> JsonStreamWriter<W> {
+    pub fn new(writer: W, format: StreamFormat) -> Self {
+        Self {
+            writer,
+            format,
+            buffer: Vec::new(),
+            written: 0,
+            high_water_mark: DEFAULT_HIGH_WATER_MARK,
+            flush_interval: None,
+            last_flush: Instant::now(),
+            count: 0,
+        }
+    }
+
+    /// Number of buffered bytes at which [`JsonStreamWriter::ready`] waits
+    pub fn high_water_mark(mut self, bytes: usize) -> Self {
+        self.high_water_mark = bytes;
+        self
+    }
+
+    /// Flush from [`JsonStreamWriter::send`] once `interval` has passed since
+    /// the last flush; `None` (the default) leaves flushing to the caller
+    pub fn flush_interval(mut self, interval: Option<Duration>) -> Self {
+        self.flush_interval = interval;
+        self
+    }
+
+    /// `Ready` once fewer than `high_water_mark` bytes are buffered, writing
+    /// buffered bytes until then
+    pub fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.poll_write_buffer(cx, self.high_water_mark.saturating_sub(1))
+    }
+
+    /// Serialize `value` into the buffer without waiting; call after
+    /// [`JsonStreamWriter::poll_ready`] returned `Ready(Ok(()))` to respect
+    /// the high-water mark
+    pub fn start_send(&mut self, value: &JsonValue) -> io::Result<()> {
+        if self.format == StreamFormat::Array {
+            self.buffer.push(if self.count == 0 { b'[' } else { b',' });
+        }
+        value.write_json_minified(&mut self.buffer)?;
+        if self.format == StreamFormat::Ndjson {
+            self.buffer.push(b'\n');
+        }
+        self.count += 1;
+        Ok(())
+    }
+
+    /// Write everything buffered and flush the writer
+    pub fn poll_flush(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.poll_write_buffer(cx, 0) {
+            Poll::Ready(Ok(())) => {}
+            other => return other,
+        }
+        let result = Pin::new(&mut self.writer).poll_flush(cx);
+        if result.is_ready() {
+            self.last_flush = Instant::now();
+        }
+        result
+    }
+
+    /// Wait until the buffer is below the high-water mark
+    pub async fn ready(&mut self) -> io::Result<()> {
+        poll_fn(|cx| self.poll_ready(cx)).await
+    }
+
+    /// Wait for room, buffer `value` and flush if the flush interval has
+    /// passed
+    pub async fn send(&mut self, value: &JsonValue) -> io::Result<()> {
+        self.ready().await?;
+        self.start_send(value)?;
+        if self
+            .flush_interval
+            .is_some_and(|interval| self.last_flush.elapsed() >= interval)
+        {
+            self.flush().await?;
+        }
+        Ok(())
+    }
+
+    pub async fn flush(&mut self) -> io::Result<()> {
+        poll_fn(|cx| self.poll_flush(cx)).await
+    }
+
+    /// Close the array (an empty one if nothing was sent), flush and return
+    /// the writer
+    ///
+    /// The writer is not shut down, so it can carry more data afterwards.
+    pub async fn finish(mut self) -> io::Result<W> {
+        if self.format == StreamFormat::Array {
+            if self.count == 0 {
+                self.buffer.push(b'[');
+            }
+            self.buffer.push(b']');
+        }
+        self.flush().await?;
+        Ok(self.writer)
+    }
+
+    /// Number of values sent so far
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    /// Bytes serialized but not yet taken by the writer
+    pub fn buffered_len(&self) -> usize {
+        self.buffer.len() - self.written
+    }
+
+    pub fn get_ref(&self) -> &W {
+        &self.writer
+    }
+
+    /// Hand buffered bytes to the writer until at most `limit` remain
+    fn poll_write_buffer(&mut self, cx: &mut Context<'_>, limit: usize) -> Poll<io::Result<()>> {
+        while self.buffered_len() > limit {
+            let pending = &self.buffer[self.written..];
+            match Pin::new(&mut self.writer).poll_write(cx, pending) {
+                Poll::Ready(Ok(0)) => {
+                    return Poll::Ready(Err(io::Error::new(
+                        io::ErrorKind::WriteZero,
+                        "writer accepted no more bytes",
+                    )));
+                }
+                Poll::Ready(Ok(written)) => self.written += written,
+                Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+        self.buffer.drain(..self.written);
+        self.written = 0;
+        Poll::Ready(Ok(()))
+    }
+}
+
+async fn read_chunk<R: AsyncRead + Unpin>(
+    reader: &mut R,
+    buffer: &mut [u8],
+    parser: &PushParser,
+) -> Result<usize, ParseError> {
+    loop {
+        match reader.read(buffer).await {
+            Ok(read) => return Ok(read),
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => {
+                return Err(parser
+                    .error_at_end(format!("Error reading input: {}", e))
+                    .with_kind(ErrorKind::Io));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json;
+    use tokio::io::ReadBuf;
+
+    /// Reader handing out at most `chunk` bytes per read, then failing if
+    /// `fail` is set
+    struct Chunked<'a> {
+        data: &'a [u8],
+        chunk: usize,
+        fail: bool,
+    }
+
+    impl AsyncRead for Chunked<'_> {
+        fn poll_read(
+            mut self: Pin<&mut Self>,
+            _: &mut Context<'_>,
+            buf: &mut ReadBuf<'_>,
+        ) -> Poll<std::io::Result<()>> {
+            if self.data.is_empty() && self.fail {
+                return Poll::Ready(Err(std::io::Error::other("connection reset")));
+            }
+            let n = self.chunk.min(self.data.len()).min(buf.remaining());
+            buf.put_slice(&self.data[..n]);
+            self.data = &self.data[n..];
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    fn chunked(data: &str, chunk: usize) -> Chunked<'_> {
+        Chunked {
+            data: data.as_bytes(),
+            chunk,
+            fail: false,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_parse_json_async_chunked() {
+        let input = r#"{"name": "Zoë", "values": [1, 2.5, true, null], "nested": {"a": "é"}}"#;
+        for chunk in [1, 2, 3, 7, 64] {
+            let value = parse_json_async(chunked(input, chunk)).await.unwrap();
+            assert_eq!(value, crate::parse_json(input).unwrap());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_parse_json_async_errors() {
+        let cases = [
+            ("", ErrorKind::UnexpectedEof, 0),
+            ("  [1, 2", ErrorKind::UnexpectedEof, 7),
+            ("[1] 2", ErrorKind::TrailingCharacters, 4),
+            ("{}\n  x", ErrorKind::TrailingCharacters, 5),
+        ];
+        for (input, kind, position) in cases {
+            let error = parse_json_async(chunked(input, 2)).await.unwrap_err();
+            assert_eq!(
+                (error.kind(), error.position),
+                (kind, position),
+                "{:?}",
+                input
+            );
+        }
+
+        let reader = Chunked {
+            data: b"[1, ",
+            chunk: 4,
+            fail: true,
+        };
+        let error = parse_json_async(reader).await.unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::Io);
+        assert_eq!(error.message, "Error reading input: connection reset");
+    }
+
+    #[tokio::test]
+    async fn test_json_stream() {
+        let input = "{\"a\": 1}\n[true]{\"b\": \"x\"}\n 42 ";
+        let mut stream = JsonStream::new(chunked(input, 3));
+        let mut values = Vec::new();
+        while let Some(value) = stream.next_value().await.unwrap() {
+            values.push(value);
+        }
+        assert_eq!(
+            values,
+            [json!({"a": 1}), json!([true]), json!({"b": "x"}), json!(42)]
+        );
+        assert_eq!(stream.bytes_read(), input.len());
+        assert_eq!(stream.next_value().await, Ok(None));
+
+        let mut stream = JsonStream::new(chunked("[1] [2", 4));
+        assert_eq!(stream.next_value().await, Ok(Some(json!([1]))));
+        let error = stream.next_value().await.unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::UnexpectedEof);
+        assert_eq!(stream.next_value().await, Err(error));
+    }
+
+    /// Writer taking at most `chunk` bytes per call and answering every
+    /// other call with `Pending`, like a slow client
+    #[derive(Default)]
+    struct SlowClient {
+        data: Vec<u8>,
+        chunk: usize,
+        stall: bool,
+        flushes: usize,
+    }
+
+    impl AsyncWrite for SlowClient {
+        fn poll_write(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<io::Result<usize>> {
+            self.stall = !self.stall;
+            if self.stall {
+                cx.waker().wake_by_ref();
+                return Poll::Pending;
+            }
+            let n = self.chunk.min(buf.len());
+            self.data.extend_from_slice(&buf[..n]);
+            Poll::Ready(Ok(n))
+        }
+
+        fn poll_flush(mut self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+            self.flushes += 1;
+            Poll::Ready(Ok(()))
+        }
+
+        fn poll_shutdown(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_stream_writer_backpressure() {
+        let client = SlowClient {
+            chunk: 7,
+            ..SlowClient::default()
+        };
+        let mut writer = JsonStreamWriter::new(client, StreamFormat::Ndjson).high_water_mark(64);
+        let mut expected = String::new();
+        for i in 0..100u32 {
+            let value = json!({"seq": i, "payload": "x".repeat(i as usize % 13)});
+            writer.send(&value).await.unwrap();
+            assert!(writer.buffered_len() <= 64 + value.to_minified_string().len());
+            expected.push_str(&value.to_minified_string());
+            expected.push('\n');
+        }
+        assert_eq!(writer.count(), 100);
+        assert_eq!(writer.get_ref().flushes, 0);
+        let client = writer.finish().await.unwrap();
+        assert_eq!(String::from_utf8(client.data).unwrap(), expected);
+        assert_eq!(client.flushes, 1);
+    }
+
+    #[tokio::test]
+    async fn test_stream_writer_array_and_flush_interval() {
+        let client = SlowClient {
+            chunk: 3,
+            ..SlowClient::default()
+        };
+        let writer = JsonStreamWriter::new(client, StreamFormat::Array);
+        let client = writer.finish().await.unwrap();
+        assert_eq!(client.data, b"[]");
+
+        let mut writer =
+            JsonStreamWriter::new(client, StreamFormat::Array).flush_interval(Some(Duration::ZERO));
+        writer.send(&json!(1)).await.unwrap();
+        writer.send(&json!([true])).await.unwrap();
+        assert_eq!(writer.buffered_len(), 0);
+        assert_eq!(writer.get_ref().flushes, 3);
+        let client = writer.finish().await.unwrap();
+        assert_eq!(client.data, b"[][1,[true]]");
+    }
+}
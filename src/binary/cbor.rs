@@ -0,0 +1,369 @@
+//! CBOR (RFC 8949) encoding of `JsonValue`
+//!
+//! Encoding uses definite lengths, the shortest integer heads and 64 bit
+//! floats; decoding also accepts indefinite lengths, half and single
+//! precision floats and `undefined` (read as `null`).
+
+use super::{Cursor, bytes_value, key_text};
+use crate::convert::{ConvertError, Format};
+use crate::json::JsonValue;
+use crate::object::JsonObject;
+
+const UNSIGNED: u8 = 0;
+const NEGATIVE: u8 = 1;
+const BYTES: u8 = 2;
+const TEXT: u8 = 3;
+const ARRAY: u8 = 4;
+const MAP: u8 = 5;
+#[cfg(feature = "tagged")]
+const TAG: u8 = 6;
+/// Additional information announcing an indefinite length
+const INDEFINITE: u8 = 31;
+const BREAK: u8 = 0xff;
+
+impl JsonValue {
+    /// Encode as CBOR
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use json_parser_rs::{JsonValue, json};
+    ///
+    /// let value = json!({"id": 7, "tags": ["a"]});
+    /// let bytes = value.to_cbor();
+    /// assert_eq!(bytes, b"\xA2bid\x07dtags\x81aa");
+    /// assert_eq!(JsonValue::from_cbor(&bytes).unwrap(), value);
+    /// ```
+    pub fn to_cbor(&self) -> Vec<u8> {
+        let mut output = Vec::new();
+        encode(self, &mut output);
+        output
+    }
+
+    /// Decode a single CBOR data item; trailing bytes are an error
+    pub fn from_cbor(bytes: &[u8]) -> Result<JsonValue, ConvertError> {
+        let mut input = Cursor::new(bytes, "CBOR");
+        let value = decode(&mut input)?;
+        input.finish(value)
+    }
+}
+
+fn write_head(major: u8, n: u64, output: &mut Vec<u8>) {
+    let major = major << 5;
+    if n < 24 {
+        output.push(major | n as u8);
+    } else if let Ok(n) = u8::try_from(n) {
+        output.extend([major | 24, n]);
+    } else if let Ok(n) = u16::try_from(n) {
+        output.push(major | 25);
+        output.extend(n.to_be_bytes());
+    } else if let Ok(n) = u32::try_from(n) {
+        output.push(major | 26);
+        output.extend(n.to_be_bytes());
+    } else {
+        output.push(major | 27);
+        output.extend(n.to_be_bytes());
+    }
+}
+
+fn encode_text(s: &str, output: &mut Vec<u8>) {
+    write_head(TEXT, s.len() as u64, output);
+    output.extend(s.as_bytes());
+}
+
+fn encode(value: &JsonValue, output: &mut Vec<u8>) {
+    match value {
+        JsonValue::Null => output.push(0xf6),
+        JsonValue::Boolean(false) => output.push(0xf4),
+        JsonValue::Boolean(true) => output.push(0xf5),
+        JsonValue::Number(n) if n.is_f64() => {
+            output.push(0xfb);
+            output.extend(n.as_f64().to_be_bytes());
+        }
+        JsonValue::Number(n) => match (n.as_u64(), n.as_i64()) {
+            (Some(n), _) => write_head(UNSIGNED, n, output),
+            // -1 - m for m = !n, which is non-negative for negative n
+            (None, Some(n)) => write_head(NEGATIVE, !n as u64, output),
+            (None, None) => unreachable!("integers fit u64 or i64"),
+        },
+        string @ (JsonValue::String(_) | JsonValue::RawString(_)) => {
+            encode_text(&string.to_str_lossy().unwrap_or_default(), output);
+        }
+        JsonValue::Array(elements) => {
+            write_head(ARRAY, elements.len() as u64, output);
+            for element in elements {
+                encode(element, output);
+            }
+        }
+        JsonValue::Object(object) => {
+            write_head(MAP, object.len() as u64, output);
+            for (key, member) in object {
+                encode_text(key, output);
+                encode(member, output);
+            }
+        }
+        JsonValue::Extension(tag, payload) => {
+            write_head(MAP, 1, output);
+            encode_text(tag, output);
+            encode(payload, output);
+        }
+        #[cfg(feature = "tagged")]
+        JsonValue::Tagged(tag, payload) => {
+            if let Ok(tag) = tag.parse::<u64>() {
+                write_head(TAG, tag, output);
+            }
+            encode(payload, output);
+        }
+    }
+}
+
+/// Argument of a head: the value itself, a length or a tag number;
+/// `None` for an indefinite length
+fn read_argument(input: &mut Cursor<'_>, info: u8) -> Result<Option<u64>, ConvertError> {
+    let argument = match info {
+        0..=23 => u64::from(info),
+        24 => u64::from(input.u8()?),
+        25 => u64::from(u16::from_be_bytes(input.array()?)),
+        26 => u64::from(u32::from_be_bytes(input.array()?)),
+        27 => u64::from_be_bytes(input.array()?),
+        INDEFINITE => return Ok(None),
+        _ => return Err(input.error(format!("reserved additional information {}", info))),
+    };
+    Ok(Some(argument))
+}
+
+fn decode(input: &mut Cursor<'_>) -> Result<JsonValue, ConvertError> {
+    let initial = input.u8()?;
+    let (major, info) = (initial >> 5, initial & 0x1f);
+    if major == 7 {
+        return decode_simple(input, info);
+    }
+    let argument = read_argument(input, info)?;
+    let value = match (major, argument) {
+        (UNSIGNED, Some(n)) => JsonValue::from(n),
+        (NEGATIVE, Some(n)) => match i64::try_from(n) {
+            Ok(n) => JsonValue::from(-1 - n),
+            Err(_) => JsonValue::from(-1.0 - n as f64),
+        },
+        (BYTES, _) => bytes_value(&read_chunks(input, BYTES, argument)?),
+        (TEXT, _) => {
+            let bytes = read_chunks(input, TEXT, argument)?;
+            JsonValue::String(
+                String::from_utf8(bytes)
+                    .map_err(|e| input.error(format!("text is not UTF-8: {}", e)))?,
+            )
+        }
+        (ARRAY, len) => {
+            input.enter()?;
+            let mut elements = Vec::new();
+            match len {
+                Some(len) => {
+                    let len = input.count(len, 1)?;
+                    elements.reserve(len);
+                    for _ in 0..len {
+                        elements.push(decode(input)?);
+                    }
+                }
+                None => {
+                    while !at_break(input)? {
+                        elements.push(decode(input)?);
+                    }
+                }
+            }
+            input.leave();
+            JsonValue::Array(elements)
+        }
+        (MAP, len) => {
+            input.enter()?;
+            let mut object = JsonObject::new();
+            match len {
+                Some(len) => {
+                    let len = input.count(len, 2)?;
+                    for _ in 0..len {
+                        decode_member(input, &mut object)?;
+                    }
+                }
+                None => {
+                    while !at_break(input)? {
+                        decode_member(input, &mut object)?;
+                    }
+                }
+            }
+            input.leave();
+            JsonValue::Object(object)
+        }
+        (_, Some(tag)) => {
+            // Major type 6: the tagged data item follows
+            input.enter()?;
+            let value = decode(input)?;
+            input.leave();
+            #[cfg(feature = "tagged")]
+            let value = JsonValue::tagged(tag.to_string(), value);
+            #[cfg(not(feature = "tagged"))]
+            let _ = tag;
+            value
+        }
+        (_, None) => return Err(input.error("indefinite length on a number or tag")),
+    };
+    Ok(value)
+}
+
+fn decode_simple(input: &mut Cursor<'_>, info: u8) -> Result<JsonValue, ConvertError> {
+    let value = match info {
+        20 => JsonValue::Boolean(false),
+        21 => JsonValue::Boolean(true),
+        22 | 23 => JsonValue::Null,
+        25 => JsonValue::from(half_to_f64(u16::from_be_bytes(input.array()?))),
+        26 => JsonValue::from(f64::from(f32::from_be_bytes(input.array()?))),
+        27 => JsonValue::from(f64::from_be_bytes(input.array()?)),
+        31 => return Err(input.error("break outside an indefinite-length item")),
+        _ => return Err(input.error(format!("unsupported simple value {}", info))),
+    };
+    Ok(value)
+}
+
+fn decode_member(input: &mut Cursor<'_>, object: &mut JsonObject) -> Result<(), ConvertError> {
+    let start = input.position;
+    let key = key_text(decode(input)?).ok_or_else(|| {
+        input.position = start;
+        input.error("map keys must be text, integers or booleans")
+    })?;
+    let member = decode(input)?;
+    object.insert(key, member);
+    Ok(())
+}
+
+/// Consume a break byte if one is next
+fn at_break(input: &mut Cursor<'_>) -> Result<bool, ConvertError> {
+    if input.bytes.get(input.position) == Some(&BREAK) {
+        input.position += 1;
+        return Ok(true);
+    }
+    if input.position >= input.bytes.len() {
+        return Err(input.error("unexpected end of data"));
+    }
+    Ok(false)
+}
+
+/// Content of a byte or text string, joining the chunks of an
+/// indefinite-length one
+fn read_chunks(
+    input: &mut Cursor<'_>,
+    major: u8,
+    len: Option<u64>,
+) -> Result<Vec<u8>, ConvertError> {
+    if let Some(len) = len {
+        let len = input.count(len, 1)?;
+        return Ok(input.take(len)?.to_vec());
+    }
+    let mut content = Vec::new();
+    while !at_break(input)? {
+        let initial = input.u8()?;
+        if initial >> 5 != major {
+            return Err(input.error("chunk of a different type in an indefinite-length string"));
+        }
+        let len = read_argument(input, initial & 0x1f)?
+            .ok_or_else(|| input.error("nested indefinite-length string"))?;
+        let len = input.count(len, 1)?;
+        content.extend(input.take(len)?);
+    }
+    Ok(content)
+}
+
+/// IEEE 754 half precision to double
+fn half_to_f64(half: u16) -> f64 {
+    let sign = if half & 0x8000 != 0 { -1.0 } else { 1.0 };
+    let exponent = i32::from((half >> 10) & 0x1f);
+    let mantissa = f64::from(half & 0x3ff);
+    sign * match exponent {
+        0 => mantissa * 2f64.powi(-24),
+        31 if mantissa == 0.0 => f64::INFINITY,
+        31 => f64::NAN,
+        _ => (1.0 + mantissa / 1024.0) * 2f64.powi(exponent - 15),
+    }
+}
+
+/// CBOR data (`.cbor`)
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Cbor;
+
+impl Format for Cbor {
+    fn name(&self) -> &str {
+        "cbor"
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["cbor"]
+    }
+
+    fn decode(&self, input: &[u8]) -> Result<JsonValue, ConvertError> {
+        JsonValue::from_cbor(input)
+    }
+
+    fn encode(&self, value: &JsonValue) -> Result<Vec<u8>, ConvertError> {
+        Ok(value.to_cbor())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json;
+
+    #[test]
+    fn test_cbor_round_trip() {
+        let value = json!({
+            "numbers": [0, 23, 24, 256, u64::MAX, -1, -25, i64::MIN, 1.5],
+            "text": "x".repeat(40),
+            "flags": [true, false, null],
+            "nested": {"empty": [], "map": {}}
+        });
+        let bytes = value.to_cbor();
+        assert_eq!(JsonValue::from_cbor(&bytes).unwrap(), value);
+        assert_eq!(json!(-25).to_cbor(), [0x38, 0x18]);
+        assert_eq!(json!(i64::MIN).to_cbor()[0], 0x3b);
+    }
+
+    #[test]
+    fn test_cbor_decoding_extras() {
+        // [_ h'01' h'02'] inside an indefinite array, half float 1.5, undefined
+        let bytes = [
+            0x9f, 0x5f, 0x41, 1, 0x41, 2, 0xff, 0xf9, 0x3e, 0x00, 0xf7, 0xff,
+        ];
+        assert_eq!(
+            JsonValue::from_cbor(&bytes).unwrap(),
+            json!([[1, 2], 1.5, null])
+        );
+        // {_ 1: "a"}
+        let bytes = [0xbf, 0x01, 0x61, b'a', 0xff];
+        assert_eq!(JsonValue::from_cbor(&bytes).unwrap(), json!({"1": "a"}));
+        // -2^64
+        let value =
+            JsonValue::from_cbor(&[0x3b, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff]).unwrap();
+        assert_eq!(value.as_f64(), Some(-18446744073709551616.0));
+    }
+
+    #[test]
+    fn test_cbor_invalid_input() {
+        let error = JsonValue::from_cbor(&[0x82, 0x01]).unwrap_err();
+        assert_eq!(
+            error.message(),
+            "Invalid CBOR at byte 1: length 2 exceeds the input"
+        );
+        assert!(JsonValue::from_cbor(&[0x9f, 0x01]).is_err());
+        assert!(JsonValue::from_cbor(&[0xff]).is_err());
+        assert!(JsonValue::from_cbor(&[0x1c]).is_err());
+        assert!(JsonValue::from_cbor(&[0x62, 0xff, 0xfe]).is_err());
+        assert!(JsonValue::from_cbor(&[0x81; 1000]).is_err());
+    }
+
+    #[cfg(feature = "tagged")]
+    #[test]
+    fn test_cbor_tags() {
+        // 1(1363896240): epoch-based date/time
+        let bytes = [0xc1, 0x1a, 0x51, 0x4b, 0x67, 0xb0];
+        let value = JsonValue::from_cbor(&bytes).unwrap();
+        assert_eq!(value, JsonValue::tagged("1", json!(1363896240)));
+        assert_eq!(value.to_cbor(), bytes);
+    }
+}
@@ -0,0 +1,123 @@
+//! Binary encodings - `JsonValue` as MessagePack or CBOR
+//!
+//! Both formats map JSON's value model one to one, so documents can be
+//! cached or sent compactly without a second value type. Each lives behind
+//! its own feature flag (`msgpack`, `cbor`) and adds a pair of methods to
+//! `JsonValue` plus a [`Format`](crate::convert::Format) implementation.
+//!
+//! Values outside the JSON model are mapped as follows when decoding:
+//! byte strings become arrays of byte values, NaN and infinities become
+//! `null` (as with `JsonValue::from(f64)`), integer and boolean map keys
+//! become their decimal text. MessagePack extension types and CBOR tags are
+//! kept as `JsonValue::Tagged` with the `tagged` feature, named by their
+//! number, and dropped otherwise; encoding turns such tags back into
+//! extension types or tags.
+
+#[cfg(feature = "cbor")]
+pub mod cbor;
+#[cfg(feature = "msgpack")]
+pub mod msgpack;
+
+use crate::convert::ConvertError;
+use crate::json::JsonValue;
+use crate::options::DEFAULT_MAX_DEPTH;
+
+/// Read position in encoded input
+struct Cursor<'a> {
+    bytes: &'a [u8],
+    position: usize,
+    /// Format name for error messages
+    format: &'static str,
+    depth: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn new(bytes: &'a [u8], format: &'static str) -> Self {
+        Self {
+            bytes,
+            position: 0,
+            format,
+            depth: 0,
+        }
+    }
+
+    fn error(&self, message: impl std::fmt::Display) -> ConvertError {
+        ConvertError::decode(format!(
+            "Invalid {} at byte {}: {}",
+            self.format, self.position, message
+        ))
+    }
+
+    fn take(&mut self, count: usize) -> Result<&'a [u8], ConvertError> {
+        let end = self
+            .position
+            .checked_add(count)
+            .filter(|&end| end <= self.bytes.len())
+            .ok_or_else(|| self.error("unexpected end of data"))?;
+        let bytes = &self.bytes[self.position..end];
+        self.position = end;
+        Ok(bytes)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], ConvertError> {
+        let bytes = self.take(N)?;
+        Ok(bytes.try_into().expect("took N bytes"))
+    }
+
+    fn u8(&mut self) -> Result<u8, ConvertError> {
+        Ok(self.array::<1>()?[0])
+    }
+
+    /// Declared element count, checked against the remaining input so a
+    /// corrupt length cannot trigger a huge allocation
+    fn count(&self, count: u64, min_item_size: usize) -> Result<usize, ConvertError> {
+        let remaining = (self.bytes.len() - self.position) / min_item_size.max(1);
+        usize::try_from(count)
+            .ok()
+            .filter(|&count| count <= remaining)
+            .ok_or_else(|| self.error(format!("length {} exceeds the input", count)))
+    }
+
+    fn enter(&mut self) -> Result<(), ConvertError> {
+        self.depth += 1;
+        if self.depth > DEFAULT_MAX_DEPTH {
+            return Err(self.error(format!("nesting deeper than {} levels", DEFAULT_MAX_DEPTH)));
+        }
+        Ok(())
+    }
+
+    fn leave(&mut self) {
+        self.depth -= 1;
+    }
+
+    fn finish(&self, value: JsonValue) -> Result<JsonValue, ConvertError> {
+        if self.position != self.bytes.len() {
+            return Err(self.error("trailing bytes after the value"));
+        }
+        Ok(value)
+    }
+}
+
+/// Text used for a non-string map key, if the key has one
+fn key_text(key: JsonValue) -> Option<String> {
+    match key {
+        JsonValue::String(key) => Some(key),
+        JsonValue::Number(n) => Some(n.to_string()),
+        JsonValue::Boolean(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+fn bytes_value(bytes: &[u8]) -> JsonValue {
+    JsonValue::Array(bytes.iter().map(|&byte| JsonValue::from(byte)).collect())
+}
+
+/// Bytes of an array holding only integers 0 to 255
+#[cfg(feature = "tagged")]
+fn value_bytes(value: &JsonValue) -> Option<Vec<u8>> {
+    value
+        .as_array()?
+        .iter()
+        .map(|byte| byte.as_u64().and_then(|byte| u8::try_from(byte).ok()))
+        .collect()
+}
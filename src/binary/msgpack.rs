@@ -0,0 +1,389 @@
+//! MessagePack encoding of `JsonValue`
+//!
+//! Integers use the smallest encoding that holds them, floats are written
+//! as float 64 so they read back unchanged.
+
+use super::{Cursor, bytes_value, key_text};
+use crate::convert::{ConvertError, Format};
+use crate::json::JsonValue;
+use crate::object::JsonObject;
+
+impl JsonValue {
+    /// Encode as MessagePack
+    ///
+    /// # Panics
+    ///
+    /// If a string, array or object is longer than `u32::MAX`, which
+    /// MessagePack cannot express.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use json_parser_rs::{JsonValue, json};
+    ///
+    /// let value = json!({"id": 7, "tags": ["a"]});
+    /// let bytes = value.to_msgpack();
+    /// assert_eq!(bytes, b"\x82\xA2id\x07\xA4tags\x91\xA1a");
+    /// assert_eq!(JsonValue::from_msgpack(&bytes).unwrap(), value);
+    /// ```
+    pub fn to_msgpack(&self) -> Vec<u8> {
+        let mut output = Vec::new();
+        encode(self, &mut output);
+        output
+    }
+
+    /// Decode a single MessagePack value; trailing bytes are an error
+    pub fn from_msgpack(bytes: &[u8]) -> Result<JsonValue, ConvertError> {
+        let mut input = Cursor::new(bytes, "MessagePack");
+        let value = decode(&mut input)?;
+        input.finish(value)
+    }
+}
+
+fn encode(value: &JsonValue, output: &mut Vec<u8>) {
+    match value {
+        JsonValue::Null => output.push(0xc0),
+        JsonValue::Boolean(false) => output.push(0xc2),
+        JsonValue::Boolean(true) => output.push(0xc3),
+        JsonValue::Number(n) if n.is_f64() => {
+            output.push(0xcb);
+            output.extend(n.as_f64().to_be_bytes());
+        }
+        JsonValue::Number(n) => match (n.as_u64(), n.as_i64()) {
+            (Some(n), _) => encode_unsigned(n, output),
+            (None, Some(n)) => encode_negative(n, output),
+            (None, None) => unreachable!("integers fit u64 or i64"),
+        },
+        string @ (JsonValue::String(_) | JsonValue::RawString(_)) => {
+            encode_str(&string.to_str_lossy().unwrap_or_default(), output);
+        }
+        JsonValue::Array(elements) => {
+            write_length(
+                elements.len(),
+                0x90,
+                15,
+                [None, Some(0xdc), Some(0xdd)],
+                output,
+            );
+            for element in elements {
+                encode(element, output);
+            }
+        }
+        JsonValue::Object(object) => {
+            write_length(
+                object.len(),
+                0x80,
+                15,
+                [None, Some(0xde), Some(0xdf)],
+                output,
+            );
+            for (key, member) in object {
+                encode_str(key, output);
+                encode(member, output);
+            }
+        }
+        JsonValue::Extension(tag, payload) => {
+            output.push(0x81);
+            encode_str(tag, output);
+            encode(payload, output);
+        }
+        #[cfg(feature = "tagged")]
+        JsonValue::Tagged(tag, payload) => match (tag.parse::<i8>(), super::value_bytes(payload)) {
+            (Ok(kind), Some(data)) => {
+                match data.len() {
+                    1 => output.push(0xd4),
+                    2 => output.push(0xd5),
+                    4 => output.push(0xd6),
+                    8 => output.push(0xd7),
+                    16 => output.push(0xd8),
+                    len => write_length(len, 0, 0, [Some(0xc7), Some(0xc8), Some(0xc9)], output),
+                }
+                output.push(kind as u8);
+                output.extend(data);
+            }
+            _ => encode(payload, output),
+        },
+    }
+}
+
+fn encode_unsigned(n: u64, output: &mut Vec<u8>) {
+    if n <= 0x7f {
+        output.push(n as u8);
+    } else if let Ok(n) = u8::try_from(n) {
+        output.extend([0xcc, n]);
+    } else if let Ok(n) = u16::try_from(n) {
+        output.push(0xcd);
+        output.extend(n.to_be_bytes());
+    } else if let Ok(n) = u32::try_from(n) {
+        output.push(0xce);
+        output.extend(n.to_be_bytes());
+    } else {
+        output.push(0xcf);
+        output.extend(n.to_be_bytes());
+    }
+}
+
+fn encode_negative(n: i64, output: &mut Vec<u8>) {
+    if n >= -32 {
+        output.push(n as u8);
+    } else if let Ok(n) = i8::try_from(n) {
+        output.extend([0xd0, n as u8]);
+    } else if let Ok(n) = i16::try_from(n) {
+        output.push(0xd1);
+        output.extend(n.to_be_bytes());
+    } else if let Ok(n) = i32::try_from(n) {
+        output.push(0xd2);
+        output.extend(n.to_be_bytes());
+    } else {
+        output.push(0xd3);
+        output.extend(n.to_be_bytes());
+    }
+}
+
+fn encode_str(s: &str, output: &mut Vec<u8>) {
+    write_length(
+        s.len(),
+        0xa0,
+        31,
+        [Some(0xd9), Some(0xda), Some(0xdb)],
+        output,
+    );
+    output.extend(s.as_bytes());
+}
+
+/// Write a length as fix-format (`fix_base | len` up to `fix_max`) or with
+/// the 8, 16 or 32 bit marker, whichever is available and fits first
+fn write_length(
+    len: usize,
+    fix_base: u8,
+    fix_max: usize,
+    markers: [Option<u8>; 3],
+    output: &mut Vec<u8>,
+) {
+    match markers {
+        _ if fix_max > 0 && len <= fix_max => output.push(fix_base | len as u8),
+        [Some(marker), _, _] if len <= usize::from(u8::MAX) => output.extend([marker, len as u8]),
+        [_, Some(marker), _] if len <= usize::from(u16::MAX) => {
+            output.push(marker);
+            output.extend((len as u16).to_be_bytes());
+        }
+        [_, _, Some(marker)] => {
+            let len = u32::try_from(len).expect("MessagePack lengths are limited to u32::MAX");
+            output.push(marker);
+            output.extend(len.to_be_bytes());
+        }
+        _ => unreachable!("every length has a 32 bit form"),
+    }
+}
+
+fn decode(input: &mut Cursor<'_>) -> Result<JsonValue, ConvertError> {
+    let marker = input.u8()?;
+    let value = match marker {
+        0x00..=0x7f => JsonValue::from(marker),
+        0x80..=0x8f => decode_map(input, usize::from(marker & 0x0f))?,
+        0x90..=0x9f => decode_array(input, usize::from(marker & 0x0f))?,
+        0xa0..=0xbf => decode_str(input, usize::from(marker & 0x1f))?,
+        0xc0 => JsonValue::Null,
+        0xc2 => JsonValue::Boolean(false),
+        0xc3 => JsonValue::Boolean(true),
+        0xc4 => {
+            let len = input.u8()?;
+            bytes_value(input.take(usize::from(len))?)
+        }
+        0xc5 => {
+            let len = u16::from_be_bytes(input.array()?);
+            bytes_value(input.take(usize::from(len))?)
+        }
+        0xc6 => {
+            let len = u32::from_be_bytes(input.array()?);
+            let len = input.count(len.into(), 1)?;
+            bytes_value(input.take(len)?)
+        }
+        0xc7 => {
+            let len = input.u8()?;
+            decode_ext(input, usize::from(len))?
+        }
+        0xc8 => {
+            let len = u16::from_be_bytes(input.array()?);
+            decode_ext(input, usize::from(len))?
+        }
+        0xc9 => {
+            let len = u32::from_be_bytes(input.array()?);
+            let len = input.count(len.into(), 1)?;
+            decode_ext(input, len)?
+        }
+        0xca => JsonValue::from(f64::from(f32::from_be_bytes(input.array()?))),
+        0xcb => JsonValue::from(f64::from_be_bytes(input.array()?)),
+        0xcc => JsonValue::from(input.u8()?),
+        0xcd => JsonValue::from(u16::from_be_bytes(input.array()?)),
+        0xce => JsonValue::from(u32::from_be_bytes(input.array()?)),
+        0xcf => JsonValue::from(u64::from_be_bytes(input.array()?)),
+        0xd0 => JsonValue::from(i8::from_be_bytes(input.array()?)),
+        0xd1 => JsonValue::from(i16::from_be_bytes(input.array()?)),
+        0xd2 => JsonValue::from(i32::from_be_bytes(input.array()?)),
+        0xd3 => JsonValue::from(i64::from_be_bytes(input.array()?)),
+        0xd4 => decode_ext(input, 1)?,
+        0xd5 => decode_ext(input, 2)?,
+        0xd6 => decode_ext(input, 4)?,
+        0xd7 => decode_ext(input, 8)?,
+        0xd8 => decode_ext(input, 16)?,
+        0xd9 => {
+            let len = input.u8()?;
+            decode_str(input, usize::from(len))?
+        }
+        0xda => {
+            let len = u16::from_be_bytes(input.array()?);
+            decode_str(input, usize::from(len))?
+        }
+        0xdb => {
+            let len = u32::from_be_bytes(input.array()?);
+            let len = input.count(len.into(), 1)?;
+            decode_str(input, len)?
+        }
+        0xdc => {
+            let len = u16::from_be_bytes(input.array()?);
+            decode_array(input, usize::from(len))?
+        }
+        0xdd => {
+            let len = u32::from_be_bytes(input.array()?);
+            decode_array(input, input.count(len.into(), 1)?)?
+        }
+        0xde => {
+            let len = u16::from_be_bytes(input.array()?);
+            decode_map(input, usize::from(len))?
+        }
+        0xdf => {
+            let len = u32::from_be_bytes(input.array()?);
+            decode_map(input, input.count(len.into(), 2)?)?
+        }
+        0xe0..=0xff => JsonValue::from(marker as i8),
+        0xc1 => {
+            input.position -= 1;
+            return Err(input.error("0xc1 is never used"));
+        }
+    };
+    Ok(value)
+}
+
+fn decode_str(input: &mut Cursor<'_>, len: usize) -> Result<JsonValue, ConvertError> {
+    let bytes = input.take(len)?;
+    std::str::from_utf8(bytes)
+        .map(|s| JsonValue::String(s.to_string()))
+        .map_err(|e| input.error(format!("string is not UTF-8: {}", e)))
+}
+
+fn decode_array(input: &mut Cursor<'_>, len: usize) -> Result<JsonValue, ConvertError> {
+    input.enter()?;
+    let mut elements = Vec::with_capacity(input.count(len as u64, 1)?);
+    for _ in 0..len {
+        elements.push(decode(input)?);
+    }
+    input.leave();
+    Ok(JsonValue::Array(elements))
+}
+
+fn decode_map(input: &mut Cursor<'_>, len: usize) -> Result<JsonValue, ConvertError> {
+    input.enter()?;
+    let mut object = JsonObject::with_capacity(input.count(len as u64, 2)?);
+    for _ in 0..len {
+        let start = input.position;
+        let key = key_text(decode(input)?).ok_or_else(|| {
+            input.position = start;
+            input.error("map keys must be strings, integers or booleans")
+        })?;
+        let member = decode(input)?;
+        object.insert(key, member);
+    }
+    input.leave();
+    Ok(JsonValue::Object(object))
+}
+
+fn decode_ext(input: &mut Cursor<'_>, len: usize) -> Result<JsonValue, ConvertError> {
+    let kind = input.u8()? as i8;
+    let data = bytes_value(input.take(len)?);
+    #[cfg(feature = "tagged")]
+    return Ok(JsonValue::tagged(kind.to_string(), data));
+    #[cfg(not(feature = "tagged"))]
+    {
+        let _ = kind;
+        Ok(data)
+    }
+}
+
+/// MessagePack data (`.msgpack`, `.mpk`)
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MessagePack;
+
+impl Format for MessagePack {
+    fn name(&self) -> &str {
+        "msgpack"
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["msgpack", "mpk"]
+    }
+
+    fn decode(&self, input: &[u8]) -> Result<JsonValue, ConvertError> {
+        JsonValue::from_msgpack(input)
+    }
+
+    fn encode(&self, value: &JsonValue) -> Result<Vec<u8>, ConvertError> {
+        Ok(value.to_msgpack())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json;
+
+    #[test]
+    fn test_msgpack_round_trip() {
+        let value = json!({
+            "small": [0, 127, 128, 65536, u64::MAX, -1, -33, -129, i64::MIN],
+            "float": 1.5,
+            "text": "x".repeat(40),
+            "flags": [true, false, null],
+            "nested": {"empty": [], "map": {}}
+        });
+        let bytes = value.to_msgpack();
+        assert_eq!(JsonValue::from_msgpack(&bytes).unwrap(), value);
+        assert_eq!(json!(-33).to_msgpack(), [0xd0, 0xdf]);
+        assert_eq!(json!(65536).to_msgpack(), [0xce, 0, 1, 0, 0]);
+    }
+
+    #[test]
+    fn test_msgpack_foreign_values() {
+        // {1: bin[1, 2], "f": float32 0.5}
+        let bytes = [
+            0x82, 0x01, 0xc4, 0x02, 1, 2, 0xa1, b'f', 0xca, 0x3f, 0, 0, 0,
+        ];
+        assert_eq!(
+            JsonValue::from_msgpack(&bytes).unwrap(),
+            json!({"1": [1, 2], "f": 0.5})
+        );
+    }
+
+    #[test]
+    fn test_msgpack_invalid_input() {
+        let error = JsonValue::from_msgpack(&[0x92, 0x01]).unwrap_err();
+        assert_eq!(
+            error.message(),
+            "Invalid MessagePack at byte 1: length 2 exceeds the input"
+        );
+        assert!(JsonValue::from_msgpack(&[0xdd, 0xff, 0xff, 0xff, 0xff]).is_err());
+        assert!(JsonValue::from_msgpack(&[0x01, 0x02]).is_err());
+        assert!(JsonValue::from_msgpack(&[0xc1]).is_err());
+        assert!(JsonValue::from_msgpack(&[0x81, 0x90, 0x01]).is_err());
+        assert!(JsonValue::from_msgpack(&[0x91; 1000]).is_err());
+    }
+
+    #[cfg(feature = "tagged")]
+    #[test]
+    fn test_msgpack_extension_types() {
+        let bytes = [0xd5, 0x05, 0xab, 0xcd];
+        let value = JsonValue::from_msgpack(&bytes).unwrap();
+        assert_eq!(value, JsonValue::tagged("5", json!([0xab, 0xcd])));
+        assert_eq!(value.to_msgpack(), bytes);
+    }
+}
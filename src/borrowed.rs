@@ -0,0 +1,345 @@
+//! Borrowed DOM - zero-copy parsing into `JsonValueRef<'a>`
+//!
+//! `parse_json` allocates a `String` for every key and string value. For
+//! large payloads most strings contain no escape sequences, so
+//! `parse_json_ref` hands them out as slices of the input and only allocates
+//! when an escape has to be decoded.
+
+use std::borrow::Cow;
+use std::collections::HashSet;
+
+use crate::error::ParseError;
+use crate::json::JsonValue;
+use crate::number::JsonNumber;
+use crate::object::JsonObject;
+use crate::options::ParserOptions;
+use crate::parser::{Builder, Parser, Scalar, lossy_string};
+
+/// JSON value whose strings borrow from the parsed input where possible
+///
+/// Objects keep their members in document order. Under the default
+/// `DuplicateKeyPolicy::Last` repeated keys are kept too and
+/// [`JsonValueRef::get`] returns the last occurrence, the value the owned
+/// parser keeps.
+#[derive(Debug, Clone, PartialEq)]
+pub enum JsonValueRef<'a> {
+    Null,
+    Boolean(bool),
+    Number(JsonNumber),
+    String(Cow<'a, str>),
+    Array(Vec<JsonValueRef<'a>>),
+    Object(Vec<(Cow<'a, str>, JsonValueRef<'a>)>),
+}
+
+impl<'a> JsonValueRef<'a> {
+    pub fn is_null(&self) -> bool {
+        matches!(self, JsonValueRef::Null)
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            JsonValueRef::Boolean(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    pub fn as_number(&self) -> Option<&JsonNumber> {
+        match self {
+            JsonValueRef::Number(n) => Some(n),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        self.as_number().map(JsonNumber::as_f64)
+    }
+
+    /// Number as `i64`, only if it is integral and within range
+    pub fn as_i64(&self) -> Option<i64> {
+        self.as_number()?.as_i64()
+    }
+
+    /// Number as `u64`, only if it is integral and within range
+    pub fn as_u64(&self) -> Option<u64> {
+        self.as_number()?.as_u64()
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            JsonValueRef::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[JsonValueRef<'a>]> {
+        match self {
+            JsonValueRef::Array(elements) => Some(elements),
+            _ => None,
+        }
+    }
+
+    /// Object members in document order, including repeated keys
+    pub fn as_object(&self) -> Option<&[(Cow<'a, str>, JsonValueRef<'a>)]> {
+        match self {
+            JsonValueRef::Object(members) => Some(members),
+            _ => None,
+        }
+    }
+
+    /// Look up an object member by key (the last one if the key repeats)
+    pub fn get(&self, key: &str) -> Option<&JsonValueRef<'a>> {
+        self.as_object()?
+            .iter()
+            .rev()
+            .find(|(k, _)| k == key)
+            .map(|(_, value)| value)
+    }
+
+    /// Look up an array element by index
+    pub fn get_index(&self, index: usize) -> Option<&JsonValueRef<'a>> {
+        self.as_array()?.get(index)
+    }
+
+    /// Convert into an owned `JsonValue`, the same tree `parse_json` builds
+    pub fn into_owned(self) -> JsonValue {
+        match self {
+            JsonValueRef::Null => JsonValue::Null,
+            JsonValueRef::Boolean(b) => JsonValue::Boolean(b),
+            JsonValueRef::Number(n) => JsonValue::Number(n),
+            JsonValueRef::String(s) => JsonValue::String(s.into_owned()),
+            JsonValueRef::Array(elements) => {
+                JsonValue::Array(elements.into_iter().map(Self::into_owned).collect())
+            }
+            JsonValueRef::Object(members) => {
+                let mut object = JsonObject::new();
+                for (key, value) in members {
+                    object.insert(key.into_owned(), value.into_owned());
+                }
+                JsonValue::Object(object)
+            }
+        }
+    }
+}
+
+impl<'a> From<JsonValueRef<'a>> for JsonValue {
+    fn from(value: JsonValueRef<'a>) -> Self {
+        value.into_owned()
+    }
+}
+
+/// Parse strict JSON without copying unescaped strings
+///
+/// Accepts exactly what `parse_json` accepts and reports the same errors.
+///
+/// # Examples
+///
+/// ```
+/// use std::borrow::Cow;
+/// use json_parser_rs::{JsonValueRef, parse_json_ref};
+///
+/// let value = parse_json_ref(r#"{"name": "Ada", "quote": "say \"hi\""}"#).unwrap();
+/// // No escapes: a slice of the input
+/// assert!(matches!(value.get("name"), Some(JsonValueRef::String(Cow::Borrowed("Ada")))));
+/// // Escapes: decoded into an owned string
+/// assert!(matches!(value.get("quote"), Some(JsonValueRef::String(Cow::Owned(_)))));
+/// assert_eq!(value.get("quote").and_then(JsonValueRef::as_str), Some("say \"hi\""));
+/// ```
+pub fn parse_json_ref(input: &str) -> Result<JsonValueRef<'_>, ParseError> {
+    parse_json_ref_with_options(input, ParserOptions::default())
+}
+
+/// Parse into a borrowed DOM with explicit limits and relaxations
+///
+/// The input is read by the same `Parser` as `parse_json`, so every option
+/// applies as it does there. Unpaired surrogates kept by
+/// `preserve_lone_surrogates` are replaced by U+FFFD, since a
+/// `JsonValueRef` has no raw string value.
+///
+/// # Examples
+///
+/// ```
+/// use json_parser_rs::borrowed::parse_json_ref_with_options;
+/// use json_parser_rs::{ErrorKind, ParserOptions};
+///
+/// let value = parse_json_ref_with_options("{a: [1, 2,]}", ParserOptions::json5()).unwrap();
+/// assert_eq!(value.get("a").and_then(|a| a.get_index(1)).unwrap().as_u64(), Some(2));
+///
+/// let error = parse_json_ref_with_options(r#"{"a": 1, "a": 2}"#, ParserOptions::hardened());
+/// assert_eq!(error.unwrap_err().kind(), ErrorKind::DuplicateKey);
+/// ```
+pub fn parse_json_ref_with_options(
+    input: &str,
+    options: ParserOptions,
+) -> Result<JsonValueRef<'_>, ParseError> {
+    Parser::with_options(input, options)?.parse_with(&mut RefBuilder)
+}
+
+/// Builds `JsonValueRef` trees for the `Parser`
+struct RefBuilder;
+
+/// Object under construction
+struct Members<'a> {
+    members: Vec<(Cow<'a, str>, JsonValueRef<'a>)>,
+    /// Keys of `members`, collected on the first duplicate check
+    keys: Option<HashSet<Cow<'a, str>>>,
+}
+
+impl<'a> Builder<'a> for RefBuilder {
+    type Value = JsonValueRef<'a>;
+    type Array = Vec<JsonValueRef<'a>>;
+    type Object = Members<'a>;
+
+    fn scalar(&mut self, scalar: Scalar<'a>) -> JsonValueRef<'a> {
+        match scalar {
+            Scalar::Null => JsonValueRef::Null,
+            Scalar::Boolean(b) => JsonValueRef::Boolean(b),
+            Scalar::Number(n) => JsonValueRef::Number(n),
+            Scalar::String(s) => JsonValueRef::String(s),
+            Scalar::RawString(raw) => JsonValueRef::String(Cow::Owned(lossy_string(raw))),
+        }
+    }
+
+    fn begin_array(&mut self) -> Vec<JsonValueRef<'a>> {
+        Vec::new()
+    }
+
+    fn push_element(&mut self, array: &mut Vec<JsonValueRef<'a>>, element: JsonValueRef<'a>) {
+        array.push(element);
+    }
+
+    fn end_array(&mut self, array: Vec<JsonValueRef<'a>>) -> JsonValueRef<'a> {
+        JsonValueRef::Array(array)
+    }
+
+    fn begin_object(&mut self) -> Members<'a> {
+        Members {
+            members: Vec::new(),
+            keys: None,
+        }
+    }
+
+    fn object_len(&self, object: &Members<'a>) -> usize {
+        object.members.len()
+    }
+
+    fn contains_key(&mut self, object: &mut Members<'a>, key: &str) -> bool {
+        let members = &object.members;
+        object
+            .keys
+            .get_or_insert_with(|| members.iter().map(|(key, _)| key.clone()).collect())
+            .contains(key)
+    }
+
+    fn insert_member(&mut self, object: &mut Members<'a>, key: Cow<'a, str>, value: Self::Value) {
+        if let Some(keys) = &mut object.keys {
+            keys.insert(key.clone());
+        }
+        // Repeated keys are kept; `get` finds the last one
+        object.members.push((key, value));
+    }
+
+    fn end_object(&mut self, object: Members<'a>) -> JsonValueRef<'a> {
+        JsonValueRef::Object(object.members)
+    }
+
+    fn key_size(len: usize) -> usize {
+        std::mem::size_of::<Cow<str>>() + len
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ErrorKind;
+    use crate::object::DuplicateKeyPolicy;
+    use crate::options::DEFAULT_MAX_DEPTH;
+    use crate::parse_json;
+
+    #[test]
+    fn test_matches_owned_parser() {
+        let input = r#"{"a": [1, -2.5, true, false, null], "b": {"c": "dé"}, "a": "last"}"#;
+        let borrowed = parse_json_ref(input).unwrap();
+        assert_eq!(borrowed.as_object().unwrap().len(), 3);
+        assert_eq!(
+            borrowed.get("a").and_then(JsonValueRef::as_str),
+            Some("last")
+        );
+        assert_eq!(borrowed.into_owned(), parse_json(input).unwrap());
+    }
+
+    #[test]
+    fn test_strings_borrow_unless_escaped() {
+        let value = parse_json_ref(r#"["plain", "tab\t", {"key": 1, "k\u0065y2": 2}]"#).unwrap();
+        let elements = value.as_array().unwrap();
+        assert!(matches!(
+            elements[0],
+            JsonValueRef::String(Cow::Borrowed("plain"))
+        ));
+        assert!(matches!(&elements[1], JsonValueRef::String(Cow::Owned(s)) if s == "tab\t"));
+        let members = elements[2].as_object().unwrap();
+        assert!(matches!(members[0].0, Cow::Borrowed("key")));
+        assert!(matches!(&members[1].0, Cow::Owned(k) if k == "key2"));
+    }
+
+    #[test]
+    fn test_errors_match_owned_parser() {
+        for input in [
+            "[1, 2,]",
+            "{\"a\" 1}",
+            "{1: 2}",
+            "[1] 2",
+            "[1 2]",
+            "{\"a\": }",
+            "\"open",
+            "[",
+        ] {
+            let expected = parse_json(input).unwrap_err();
+            let actual = parse_json_ref(input).unwrap_err();
+            assert_eq!(actual.kind(), expected.kind(), "{}", input);
+            assert_eq!(actual.message, expected.message, "{}", input);
+        }
+        let deep = "[".repeat(DEFAULT_MAX_DEPTH + 1);
+        assert_eq!(
+            parse_json_ref(&deep).unwrap_err().kind(),
+            ErrorKind::DepthLimitExceeded
+        );
+    }
+
+    #[test]
+    fn test_options_apply() {
+        let parse = parse_json_ref_with_options;
+        let shallow = ParserOptions::default().max_depth(Some(2));
+        assert!(parse("[[1]]", shallow).is_ok());
+        let error = parse("[[[1]]]", shallow).unwrap_err();
+        assert_eq!(error.message(), "Maximum nesting depth of 2 exceeded");
+
+        let input = "{a: [1, 'x',], // note\n b: 2,}";
+        assert!(parse_json_ref(input).is_err());
+        let value = parse(input, ParserOptions::json5()).unwrap();
+        assert_eq!(value.into_owned(), crate::parse_json5(input).unwrap());
+
+        let input = r#"{"a": 1, "b": 2, "a": 3}"#;
+        let first = ParserOptions::default().duplicate_keys(DuplicateKeyPolicy::First);
+        let value = parse(input, first).unwrap();
+        assert_eq!(value.as_object().unwrap().len(), 2);
+        assert_eq!(value.get("a").and_then(JsonValueRef::as_i64), Some(1));
+        let error = parse(input, ParserOptions::hardened()).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::DuplicateKey);
+        assert_eq!(error.position(), 17);
+
+        let error = parse("\"a\u{1}\"", ParserOptions::hardened()).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::ControlCharacter);
+        let small = ParserOptions::default().max_elements(Some(2));
+        let error = parse("[1, 2, 3]", small).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::TooManyElements);
+        let small = ParserOptions::default().max_input_size(Some(4));
+        assert_eq!(
+            parse("[1, 2]", small).unwrap_err().kind(),
+            ErrorKind::InputTooLarge
+        );
+
+        let lone = ParserOptions::default().preserve_lone_surrogates(true);
+        let value = parse(r#"["a\ud800b"]"#, lone).unwrap();
+        assert_eq!(value.get_index(0).and_then(JsonValueRef::as_str), Some("a\u{fffd}b"));
+    }
+}
@@ -0,0 +1,159 @@
+//! `check --schema <schema> <file>... [--report human|json]`
+//!
+//! Validates many documents against one JSON Schema, e.g. a data directory
+//! in CI. Every violation is listed with its file and location, followed
+//! by a summary of how often each rule (schema keyword) failed. Files that
+//! do not parse count as invalid. The exit code is 1 if any file is
+//! invalid. `json` writes the whole report as one object instead.
+
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+
+use json_parser_rs::{JsonValue, Schema, SchemaViolation, json, parse_json};
+
+use super::{CliResult, read_file, take_option};
+
+const USAGE: &str = "Usage: check --schema <schema_file> <file_path>... [--report human|json]";
+
+/// Outcome for one input file
+struct FileReport<'a> {
+    path: &'a str,
+    /// Why the file could not be validated at all
+    error: Option<String>,
+    violations: Vec<SchemaViolation>,
+}
+
+impl FileReport<'_> {
+    fn is_valid(&self) -> bool {
+        self.error.is_none() && self.violations.is_empty()
+    }
+}
+
+pub fn run(args: &[String]) -> CliResult {
+    let mut args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let schema_path = take_option(&mut args, "--schema")?.ok_or_else(|| USAGE.to_string())?;
+    let as_json = match take_option(&mut args, "--report")?.as_deref() {
+        None | Some("human") => false,
+        Some("json") => true,
+        Some(other) => return Err(format!("Unknown report format '{}'\n{}", other, USAGE)),
+    };
+    if args.is_empty() {
+        return Err(USAGE.to_string());
+    }
+
+    let content = read_file(&schema_path)?;
+    let schema = Schema::parse(&content)
+        .map_err(|e| format!("{}: {}", schema_path, e.display_with_source(&content)))?;
+
+    let reports: Vec<FileReport> = args.iter().map(|path| check_file(&schema, path)).collect();
+    let mut by_rule: BTreeMap<&str, usize> = BTreeMap::new();
+    for violation in reports.iter().flat_map(|report| &report.violations) {
+        *by_rule.entry(&violation.keyword).or_default() += 1;
+    }
+    let invalid = reports.iter().filter(|report| !report.is_valid()).count();
+
+    let mut out = io::stdout().lock();
+    let written = if as_json {
+        writeln!(
+            out,
+            "{}",
+            to_json(&reports, &by_rule, invalid).to_pretty_string()
+        )
+    } else {
+        write_human(&mut out, &reports, &by_rule, invalid)
+    };
+    written.map_err(|e| format!("Error writing output: {}", e))?;
+    if invalid > 0 {
+        return Err(format!("{} of {} files invalid", invalid, reports.len()));
+    }
+    Ok(())
+}
+
+fn check_file<'a>(schema: &Schema, path: &'a str) -> FileReport<'a> {
+    let mut report = FileReport {
+        path,
+        error: None,
+        violations: Vec::new(),
+    };
+    let content = match read_file(path) {
+        Ok(content) => content,
+        Err(message) => {
+            report.error = Some(message);
+            return report;
+        }
+    };
+    match parse_json(&content) {
+        Ok(value) => report.violations = schema.validate(&value).err().unwrap_or_default(),
+        Err(e) => report.error = Some(e.display_with_source(&content).to_string()),
+    }
+    report
+}
+
+fn write_human(
+    out: &mut impl Write,
+    reports: &[FileReport],
+    by_rule: &BTreeMap<&str, usize>,
+    invalid: usize,
+) -> io::Result<()> {
+    for report in reports {
+        if let Some(error) = &report.error {
+            writeln!(out, "{}: {}", report.path, error)?;
+        }
+        for violation in &report.violations {
+            writeln!(out, "{}: {}", report.path, violation)?;
+        }
+    }
+    writeln!(
+        out,
+        "{} files checked, {} valid, {} invalid",
+        reports.len(),
+        reports.len() - invalid,
+        invalid
+    )?;
+    for (rule, count) in by_rule {
+        writeln!(out, "  {:<20} {}", rule, count)?;
+    }
+    Ok(())
+}
+
+fn to_json(reports: &[FileReport], by_rule: &BTreeMap<&str, usize>, invalid: usize) -> JsonValue {
+    let files: JsonValue = reports
+        .iter()
+        .map(|report| {
+            let violations: JsonValue = report
+                .violations
+                .iter()
+                .map(|violation| {
+                    json!({
+                        "instance_path": violation.instance_path.to_string(),
+                        "schema_path": violation.schema_path.to_string(),
+                        "keyword": violation.keyword.as_str(),
+                        "message": violation.message.as_str(),
+                    })
+                })
+                .collect();
+            let mut file = json!({
+                "file": report.path,
+                "valid": report.is_valid(),
+                "violations": violations,
+            });
+            if let Some(error) = &report.error {
+                file["error"] = JsonValue::from(error.as_str());
+            }
+            file
+        })
+        .collect();
+    let rules: JsonValue = by_rule
+        .iter()
+        .map(|(rule, count)| (*rule, JsonValue::from(*count as u64)))
+        .collect();
+    json!({
+        "files": files,
+        "summary": {
+            "files": reports.len() as u64,
+            "valid": (reports.len() - invalid) as u64,
+            "invalid": invalid as u64,
+            "violations_by_rule": rules,
+        },
+    })
+}
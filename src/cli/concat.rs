@@ -0,0 +1,198 @@
+//! `concat <file>... [--out <file>] [--as-array|--deep-merge]`
+//!
+//! Combines several documents into one, the inverse of `split`. By default
+//! every input must be an array and the output is one array holding all of
+//! their elements. `--as-array` instead makes each whole input one element
+//! of the output array; `--deep-merge` merges the inputs (objects key by
+//! key, later inputs winning) into a single value. Inputs are read one at a
+//! time and, except for `--deep-merge`, written out as they are parsed. The
+//! result goes to stdout unless `--out` names a file, which is only
+//! replaced once the result is complete and may be one of the inputs.
+
+use std::fs;
+use std::io::{self, BufWriter, Write};
+
+use json_parser_rs::stream::ElementStream;
+use json_parser_rs::{JsonValue, parse_json};
+
+use super::{CliResult, create_temporary, read_file, take_flag, take_option};
+
+const USAGE: &str = "Usage: concat <file_path>... [--out <file_path>] [--as-array|--deep-merge]";
+
+pub fn run(args: &[String]) -> CliResult {
+    let mut args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let out_path = take_option(&mut args, "--out")?;
+    let as_array = take_flag(&mut args, "--as-array");
+    let deep_merge = take_flag(&mut args, "--deep-merge");
+    if args.is_empty() || (as_array && deep_merge) {
+        return Err(USAGE.to_string());
+    }
+    let mode = match (as_array, deep_merge) {
+        (true, _) => Mode::AsArray,
+        (_, true) => Mode::DeepMerge,
+        _ => Mode::Elements,
+    };
+
+    let Some(out_path) = out_path else {
+        return concat(&args, mode, &mut io::stdout().lock());
+    };
+    // Written next to the output and moved over it at the end, so that an
+    // output that is also an input is still intact while it is read
+    let (temporary, file) = create_temporary(&out_path)?;
+    concat(&args, mode, &mut BufWriter::new(file))
+        .and_then(|()| {
+            fs::rename(&temporary, &out_path)
+                .map_err(|e| format!("Error writing file '{}': {}", out_path, e))
+        })
+        .inspect_err(|_| {
+            let _ = fs::remove_file(&temporary);
+        })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Mode {
+    /// Concatenate the elements of array inputs
+    Elements,
+    AsArray,
+    DeepMerge,
+}
+
+/// Combine the documents at `paths` into `out`
+fn concat(paths: &[&str], mode: Mode, out: &mut impl Write) -> CliResult {
+    let write_error = |e: io::Error| format!("Error writing output: {}", e);
+
+    if mode == Mode::DeepMerge {
+        let mut merged: Option<JsonValue> = None;
+        for path in paths {
+            let value = parse(path, &read_file(path)?)?;
+            match &mut merged {
+                Some(merged) => merged.deep_merge(value),
+                None => merged = Some(value),
+            }
+        }
+        let merged = merged.expect("at least one input");
+        return merged
+            .write_json(&mut *out)
+            .and_then(|()| writeln!(out))
+            .and_then(|()| out.flush())
+            .map_err(write_error);
+    }
+
+    let mut written = 0;
+    for path in paths {
+        let content = read_file(path)?;
+        let mut write_element = |value: &JsonValue| {
+            let separator: &[u8] = if written == 0 { b"[" } else { b", " };
+            written += 1;
+            out.write_all(separator)
+                .and_then(|()| value.write_json(&mut *out))
+                .map_err(write_error)
+        };
+        if mode == Mode::AsArray {
+            write_element(&parse(path, &content)?)?;
+            continue;
+        }
+        let stream = ElementStream::new(&content).map_err(|e| located(path, &content, e))?;
+        if stream.is_object() {
+            return Err(format!(
+                "'{}' is not an array; use --as-array or --deep-merge",
+                path
+            ));
+        }
+        for element in stream {
+            write_element(&element.map_err(|e| located(path, &content, e))?.value)?;
+        }
+    }
+    let open = if written == 0 { "[" } else { "" };
+    writeln!(out, "{}]", open)
+        .and_then(|()| out.flush())
+        .map_err(write_error)
+}
+
+fn parse(path: &str, content: &str) -> Result<JsonValue, String> {
+    parse_json(content).map_err(|e| located(path, content, e))
+}
+
+/// Error message naming the input it occurred in
+fn located(path: &str, content: &str, error: json_parser_rs::ParseError) -> String {
+    format!("{}: {}", path, error.display_with_source(content))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Write `files` to a fresh directory and return their paths
+    fn inputs(name: &str, files: &[&str]) -> (std::path::PathBuf, Vec<String>) {
+        let dir = std::env::temp_dir().join(format!("json-parser-{}-{}", name, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let paths = files
+            .iter()
+            .enumerate()
+            .map(|(i, content)| {
+                let path = dir.join(format!("{}.json", i));
+                fs::write(&path, content).unwrap();
+                path.to_string_lossy().into_owned()
+            })
+            .collect();
+        (dir, paths)
+    }
+
+    fn concat_to_string(paths: &[String], mode: Mode) -> Result<String, String> {
+        let paths: Vec<&str> = paths.iter().map(String::as_str).collect();
+        let mut out = Vec::new();
+        concat(&paths, mode, &mut out)?;
+        Ok(String::from_utf8(out).unwrap())
+    }
+
+    #[test]
+    fn test_modes() {
+        let (dir, paths) = inputs(
+            "concat-modes",
+            &[r#"[1, {"a": [2]}]"#, "[]", r#"[{"b": 3}]"#],
+        );
+        let elements = concat_to_string(&paths, Mode::Elements);
+        let as_array = concat_to_string(&paths, Mode::AsArray);
+        let (objects_dir, objects) = inputs(
+            "concat-merge",
+            &[
+                r#"{"a": {"x": 1, "y": 2}, "b": 1}"#,
+                r#"{"a": {"y": 3}, "c": [4]}"#,
+            ],
+        );
+        let merged = concat_to_string(&objects, Mode::DeepMerge);
+        let not_array = concat_to_string(&objects, Mode::Elements);
+        fs::remove_dir_all(&dir).unwrap();
+        fs::remove_dir_all(&objects_dir).unwrap();
+
+        assert_eq!(elements.unwrap(), "[1, {\"a\": [2]}, {\"b\": 3}]\n");
+        assert_eq!(as_array.unwrap(), "[[1, {\"a\": [2]}], [], [{\"b\": 3}]]\n");
+        assert_eq!(
+            merged.unwrap(),
+            "{\"a\": {\"x\": 1, \"y\": 3}, \"b\": 1, \"c\": [4]}\n"
+        );
+        let error = not_array.unwrap_err();
+        assert!(error.ends_with("is not an array; use --as-array or --deep-merge"));
+    }
+
+    #[test]
+    fn test_out_may_name_an_input() {
+        let (dir, paths) = inputs("concat-out", &["[1, 2]", "[3]"]);
+        let mut args = paths.clone();
+        args.extend(["--out".to_string(), paths[0].clone()]);
+        let result = run(&args);
+        let written = fs::read_to_string(&paths[0]);
+        fs::write(&paths[1], "[3,").unwrap();
+        let broken = run(&args);
+        let after_error = fs::read_to_string(&paths[0]);
+        let leftovers = fs::read_dir(&dir).unwrap().count();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(result, Ok(()));
+        assert_eq!(written.unwrap(), "[1, 2, 3]\n");
+        assert!(broken.is_err());
+        // A failed run leaves the output as it was
+        assert_eq!(after_error.unwrap(), "[1, 2, 3]\n");
+        assert_eq!(leftovers, 2);
+    }
+}
@@ -0,0 +1,172 @@
+//! `convert <input> <output> [--from <format>] [--to <format>]`
+//!
+//! Converts a document between the formats of a [`FormatRegistry`], taken
+//! from the file extensions unless `--from`/`--to` override them. `-` reads
+//! standard input or writes standard output; the format then defaults to
+//! `json`. Extensions of formats whose feature is not compiled in are
+//! reported as such rather than as unknown.
+
+use std::fs;
+use std::io::{self, Read, Write};
+
+use json_parser_rs::{Format, FormatRegistry};
+
+use super::{CliResult, take_option};
+
+const USAGE: &str =
+    "Usage: convert <input_file>|- <output_file>|- [--from <format>] [--to <format>]";
+
+/// Extensions and names of the formats behind cargo features, with the
+/// feature enabling each
+const FEATURE_FORMATS: &[(&[&str], &str)] = &[
+    (&["msgpack", "mpk"], "msgpack"),
+    (&["cbor"], "cbor"),
+    (&["toml"], "toml"),
+    (&["yaml", "yml"], "yaml"),
+];
+
+/// Error for a format that exists but was not compiled in
+fn missing_feature(name: &str) -> Option<String> {
+    let name = name.to_ascii_lowercase();
+    FEATURE_FORMATS
+        .iter()
+        .find(|(names, _)| names.contains(&name.as_str()))
+        .map(|(_, feature)| {
+            format!(
+                "The {} format is not supported by this build (enable the '{}' feature)",
+                feature, feature
+            )
+        })
+}
+
+/// Format named on the command line, else implied by `path`
+fn choose<'a>(
+    formats: &'a FormatRegistry,
+    name: Option<String>,
+    path: &str,
+) -> Result<&'a dyn Format, String> {
+    let found = match &name {
+        Some(name) => formats.get(name),
+        None if path == "-" => formats.get("json"),
+        None => formats.for_path(path),
+    };
+    found.map_err(|e| {
+        let requested = name.or_else(|| {
+            let extension = std::path::Path::new(path).extension()?;
+            Some(extension.to_string_lossy().into_owned())
+        });
+        requested
+            .and_then(|requested| missing_feature(&requested))
+            .unwrap_or_else(|| e.to_string())
+    })
+}
+
+pub fn run(args: &[String]) -> CliResult {
+    let mut args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let from = take_option(&mut args, "--from")?;
+    let to = take_option(&mut args, "--to")?;
+    let [input_path, output_path] = args[..] else {
+        return Err(USAGE.to_string());
+    };
+    let formats = FormatRegistry::new();
+    let from = choose(&formats, from, input_path)?;
+    let to = choose(&formats, to, output_path)?;
+
+    let input = if input_path == "-" {
+        let mut input = Vec::new();
+        io::stdin()
+            .read_to_end(&mut input)
+            .map_err(|e| format!("Error reading standard input: {}", e))?;
+        input
+    } else {
+        fs::read(input_path).map_err(|e| format!("Error reading file '{}': {}", input_path, e))?
+    };
+    let value = from
+        .decode(&input)
+        .map_err(|e| format!("{}: {}", input_path, e))?;
+    let output = to.encode(&value).map_err(|e| e.to_string())?;
+
+    if output_path == "-" {
+        io::stdout()
+            .lock()
+            .write_all(&output)
+            .map_err(|e| format!("Error writing output: {}", e))
+    } else {
+        fs::write(output_path, output)
+            .map_err(|e| format!("Error writing file '{}': {}", output_path, e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_from_extension() {
+        let formats = FormatRegistry::new();
+        let chosen = |name: Option<&str>, path: &str| {
+            choose(&formats, name.map(String::from), path).map(|format| format.name())
+        };
+        let name = |path: &str| chosen(None, path);
+        assert_eq!(name("data.json"), Ok("json"));
+        assert_eq!(name("settings.JSONC"), Ok("json5"));
+        assert_eq!(name("logs/events.jsonl"), Ok("ndjson"));
+        assert_eq!(name("table.csv"), Ok("csv"));
+        assert_eq!(name("-"), Ok("json"));
+        // --from/--to win over the extension
+        assert_eq!(chosen(Some("ndjson"), "data.json"), Ok("ndjson"));
+
+        let error = name("notes.txt").unwrap_err();
+        assert_eq!(
+            error,
+            "Cannot tell the format of 'notes.txt' from its extension"
+        );
+        assert!(name("Makefile").is_err());
+        let error = chosen(Some("xml"), "data.json").unwrap_err();
+        assert!(error.starts_with("Unknown format 'xml'"), "{}", error);
+
+        #[cfg(feature = "yaml")]
+        assert_eq!(name("config.yml"), Ok("yaml"));
+        #[cfg(not(feature = "yaml"))]
+        assert_eq!(
+            name("config.yml"),
+            Err(
+                "The yaml format is not supported by this build (enable the 'yaml' feature)"
+                    .to_string()
+            )
+        );
+        #[cfg(not(feature = "msgpack"))]
+        assert!(
+            chosen(Some("msgpack"), "-")
+                .unwrap_err()
+                .contains("feature")
+        );
+    }
+
+    #[test]
+    fn test_convert_files() {
+        let dir = std::env::temp_dir().join(format!("json-parser-convert-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = |name: &str| dir.join(name).to_string_lossy().into_owned();
+        fs::write(path("in.jsonl"), "{\"a\": 1}\n{\"a\": 2}\n").unwrap();
+        let run_args = |args: &[&str]| run(&args.iter().map(|a| a.to_string()).collect::<Vec<_>>());
+
+        let to_json = run_args(&[&path("in.jsonl"), &path("out.json")]);
+        let json = fs::read_to_string(path("out.json"));
+        let to_csv = run_args(&[&path("out.json"), &path("out.txt"), "--to", "csv"]);
+        let csv = fs::read_to_string(path("out.txt"));
+        let unknown = run_args(&[&path("in.jsonl"), &path("out.xml")]);
+        let written = fs::exists(path("out.xml"));
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(to_json, Ok(()));
+        assert_eq!(
+            json_parser_rs::parse_json(&json.unwrap()).unwrap(),
+            json_parser_rs::json!([{"a": 1}, {"a": 2}])
+        );
+        assert_eq!(to_csv, Ok(()));
+        assert_eq!(csv.unwrap().lines().collect::<Vec<_>>(), ["a", "1", "2"]);
+        assert!(unknown.unwrap_err().contains("out.xml"));
+        assert!(!written.unwrap());
+    }
+}
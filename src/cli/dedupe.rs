@@ -0,0 +1,207 @@
+//! `dedupe [<file>|-] [--key <pointer>] [--lru <n>]`
+//!
+//! Streams NDJSON records and drops every record whose key was seen before,
+//! writing the others unchanged. The key is the value at the JSON Pointer
+//! given with `--key` (records without it are always kept), or the whole
+//! record. By default every key is remembered, so duplicates are removed
+//! exactly; `--lru <n>` bounds memory by remembering only the `n` most
+//! recently seen keys, which catches duplicates that appear close together.
+//!
+//! Keys are compared by value: member order, whitespace and the spelling of
+//! numbers (`1`, `1.0`, `1e0`) do not make two keys different.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt::Write as _;
+use std::io::{self, Write};
+
+use json_parser_rs::json::escape;
+use json_parser_rs::{JsonNumber, JsonPointer, JsonValue};
+
+use super::{CliResult, ndjson_records, open_input, take_option};
+
+const USAGE: &str = "Usage: dedupe [<file_path>|-] [--key <pointer>] [--lru <entries>]";
+
+pub fn run(args: &[String]) -> CliResult {
+    let mut args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let key = take_option(&mut args, "--key")?
+        .map(|key| JsonPointer::parse(&key))
+        .transpose()
+        .map_err(|e| e.to_string())?;
+    let mut seen = match take_option(&mut args, "--lru")? {
+        None => Seen::Exact(HashSet::new()),
+        Some(n) => match n.parse() {
+            Ok(capacity) if capacity > 0 => Seen::Recent(Lru::new(capacity)),
+            _ => return Err(format!("--lru must be a positive number, got '{}'", n)),
+        },
+    };
+    let file_path = match args[..] {
+        [] => "-",
+        [path] => path,
+        _ => return Err(USAGE.to_string()),
+    };
+
+    let input = open_input(file_path)?;
+
+    let mut out = io::stdout().lock();
+    for record in ndjson_records(input) {
+        let (line, record) = record?;
+        let key = match &key {
+            Some(pointer) => match record.get_many(&[pointer])[0] {
+                Some(value) => canonical(value),
+                None => String::new(),
+            },
+            None => canonical(&record),
+        };
+        if key.is_empty() || seen.insert(key) {
+            writeln!(out, "{}", line).map_err(|e| format!("Error writing output: {}", e))?;
+        }
+    }
+    Ok(())
+}
+
+/// Compact JSON for `value` that is the same for all equal values: members
+/// sorted by key and numbers in one spelling per value
+fn canonical(value: &JsonValue) -> String {
+    let mut out = String::new();
+    write_canonical(value, &mut out);
+    out
+}
+
+fn write_canonical(value: &JsonValue, out: &mut String) {
+    match value {
+        JsonValue::Number(n) => write_number(n, out),
+        JsonValue::Array(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_canonical(item, out);
+            }
+            out.push(']');
+        }
+        JsonValue::Object(members) => {
+            let mut members: Vec<_> = members.iter().collect();
+            members.sort_unstable_by_key(|(key, _)| *key);
+            out.push('{');
+            for (i, (key, value)) in members.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                let _ = write!(out, "\"{}\":", escape(key));
+                write_canonical(value, out);
+            }
+            out.push('}');
+        }
+        other => out.push_str(&other.to_json_string()),
+    }
+}
+
+/// Integers exactly; floats with an integral value as that integer, others
+/// as the shortest text that reads back as the same `f64`
+fn write_number(n: &JsonNumber, out: &mut String) {
+    let integer = match (n.is_f64(), n.as_i64(), n.as_u64()) {
+        (false, Some(i), _) => Some(i128::from(i)),
+        (false, _, Some(u)) => Some(i128::from(u)),
+        _ => None,
+    };
+    let f = n.as_f64();
+    let _ = match integer {
+        Some(i) => write!(out, "{}", i),
+        // Below 2^64 every integral f64 fits an i128 exactly
+        None if f.fract() == 0.0 && f.abs() < 18_446_744_073_709_551_616.0 => {
+            write!(out, "{}", f as i128)
+        }
+        None => write!(out, "{:e}", f),
+    };
+}
+
+/// Keys seen so far
+enum Seen {
+    Exact(HashSet<String>),
+    Recent(Lru),
+}
+
+impl Seen {
+    /// Record `key`, returning whether it is new
+    fn insert(&mut self, key: String) -> bool {
+        match self {
+            Seen::Exact(keys) => keys.insert(key),
+            Seen::Recent(keys) => keys.insert(key),
+        }
+    }
+}
+
+/// Set of the `capacity` most recently used keys
+struct Lru {
+    capacity: usize,
+    /// Key to the tick of its last use
+    ticks: HashMap<String, u64>,
+    /// Tick of last use to key, oldest first
+    order: BTreeMap<u64, String>,
+    tick: u64,
+}
+
+impl Lru {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            ticks: HashMap::new(),
+            order: BTreeMap::new(),
+            tick: 0,
+        }
+    }
+
+    /// Mark `key` as most recently used, returning whether it was absent
+    fn insert(&mut self, key: String) -> bool {
+        self.tick += 1;
+        if let Some(last) = self.ticks.insert(key.clone(), self.tick) {
+            self.order.remove(&last);
+            self.order.insert(self.tick, key);
+            return false;
+        }
+        self.order.insert(self.tick, key);
+        if self.ticks.len() > self.capacity
+            && let Some((_, oldest)) = self.order.pop_first()
+        {
+            self.ticks.remove(&oldest);
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keys_compare_by_value() {
+        let key = |json: &str| canonical(&json_parser_rs::parse_json(json).unwrap());
+        assert_eq!(
+            key(r#"{"b": [1, {"y": 2, "x": 1}], "a": "s"}"#),
+            key(r#"{"a":"s","b":[1.0,{"x":1e0,"y":20e-1}]}"#)
+        );
+        assert_eq!(key(r#"{"b": 1, "a": 2}"#), r#"{"a":2,"b":1}"#);
+        assert_eq!(key("-0.0"), key("0"));
+        assert_eq!(key("0.5"), key("5e-1"));
+        assert_eq!(key("18446744073709551615"), "18446744073709551615");
+        assert_eq!(key(r#""a\u0062""#), key(r#""ab""#));
+
+        assert_ne!(key("1"), key(r#""1""#));
+        assert_ne!(key("[1, 2]"), key("[2, 1]"));
+        assert_ne!(key("9007199254740993"), key("9007199254740992"));
+        assert_ne!(key(r#"{"a": 1}"#), key(r#"{"a": 1, "b": null}"#));
+    }
+
+    #[test]
+    fn test_lru_forgets_least_recently_used() {
+        let mut lru = Lru::new(2);
+        assert!(lru.insert("a".to_string()));
+        assert!(lru.insert("b".to_string()));
+        // Touching "a" makes "b" the oldest
+        assert!(!lru.insert("a".to_string()));
+        assert!(lru.insert("c".to_string()));
+        assert!(lru.insert("b".to_string()));
+        assert!(!lru.insert("c".to_string()));
+    }
+}
@@ -0,0 +1,63 @@
+//! `diff <old> <new> [--no-color]`
+//!
+//! Prints the structural differences between two documents, one per line:
+//! `+` for added, `-` for removed and `~` for changed values, each with its
+//! JSON Pointer. Lines are colored when writing to a terminal, unless
+//! `--no-color` is given or `NO_COLOR` is set. Like `diff(1)`, the exit
+//! code is 1 when the documents differ.
+
+use std::env;
+use std::io::{self, IsTerminal, Write};
+
+use json_parser_rs::json::{self, DiffEntry};
+use json_parser_rs::{JsonValue, parse_json};
+
+use super::{CliResult, read_file, take_flag};
+
+const USAGE: &str = "Usage: diff <old_file> <new_file> [--no-color]";
+
+const GREEN: &str = "\x1b[32m";
+const RED: &str = "\x1b[31m";
+const YELLOW: &str = "\x1b[33m";
+const RESET: &str = "\x1b[0m";
+
+pub fn run(args: &[String]) -> CliResult {
+    let mut args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let no_color = take_flag(&mut args, "--no-color");
+    let [old_path, new_path] = args[..] else {
+        return Err(USAGE.to_string());
+    };
+    if old_path == "-" && new_path == "-" {
+        return Err("Only one input can be read from standard input".to_string());
+    }
+
+    let old = load(old_path)?;
+    let new = load(new_path)?;
+    let entries = json::diff(&old, &new);
+    if entries.is_empty() {
+        return Ok(());
+    }
+
+    let mut out = io::stdout().lock();
+    let color = !no_color && env::var_os("NO_COLOR").is_none() && out.is_terminal();
+    for entry in &entries {
+        let written = if color {
+            let start = match entry {
+                DiffEntry::Added { .. } => GREEN,
+                DiffEntry::Removed { .. } => RED,
+                DiffEntry::Changed { .. } => YELLOW,
+            };
+            writeln!(out, "{}{}{}", start, entry, RESET)
+        } else {
+            writeln!(out, "{}", entry)
+        };
+        written.map_err(|e| format!("Error writing output: {}", e))?;
+    }
+    let plural = if entries.len() == 1 { "" } else { "s" };
+    Err(format!("{} difference{}", entries.len(), plural))
+}
+
+fn load(path: &str) -> Result<JsonValue, String> {
+    let content = read_file(path)?;
+    parse_json(&content).map_err(|e| format!("{}: {}", path, e.display_with_source(&content)))
+}
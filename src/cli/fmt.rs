@@ -0,0 +1,131 @@
+//! `fmt [<file>|-] [--indent <n>] [--minify] [--sort-keys] [--in-place]`
+//!
+//! Re-formats a document: pretty-printed with `n` spaces per level (2 by
+//! default) or, with `--minify`, without any insignificant whitespace.
+//! `--sort-keys` writes object members in key order instead of document
+//! order. The result goes to stdout unless `--in-place` rewrites the file.
+//! Without a file, or with `-`, the document is read from stdin.
+
+use std::io::{self, Write};
+
+use json_parser_rs::parse_json;
+use json_parser_rs::writer::{KeyOrder, PrettyPrinter};
+
+use super::{CliResult, read_file, replace_file, take_flag, take_option};
+
+const USAGE: &str =
+    "Usage: fmt [<file_path>|-] [--indent <spaces>] [--minify] [--sort-keys] [--in-place]";
+
+pub fn run(args: &[String]) -> CliResult {
+    let mut args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let indent = match take_option(&mut args, "--indent")? {
+        None => 2,
+        Some(n) => n
+            .parse()
+            .map_err(|_| format!("--indent must be a number, got '{}'", n))?,
+    };
+    let minify = take_flag(&mut args, "--minify");
+    let key_order = if take_flag(&mut args, "--sort-keys") {
+        KeyOrder::Sorted
+    } else {
+        KeyOrder::Insertion
+    };
+    let in_place = take_flag(&mut args, "--in-place");
+    let file_path = match args[..] {
+        [] => "-",
+        [path] => path,
+        _ => return Err(USAGE.to_string()),
+    };
+    if in_place && file_path == "-" {
+        return Err("--in-place needs a file, not standard input".to_string());
+    }
+
+    let content = read_file(file_path)?;
+    let printer = PrettyPrinter::new()
+        .indent(indent)
+        .minify(minify)
+        .key_order(key_order);
+    let output = format(&content, &printer)?;
+
+    if in_place {
+        return replace_file(file_path, &output);
+    }
+    io::stdout()
+        .lock()
+        .write_all(output.as_bytes())
+        .map_err(|e| format!("Error writing output: {}", e))
+}
+
+/// `content` re-printed by `printer`, with a final newline
+fn format(content: &str, printer: &PrettyPrinter) -> Result<String, String> {
+    let value = parse_json(content).map_err(|e| e.display_with_source(content).to_string())?;
+    let mut output = printer.print(&value);
+    output.push('\n');
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    const INPUT: &str = r#"{"b": [1, {"c": null}], "a": "x"}"#;
+
+    #[test]
+    fn test_indent_minify_and_key_order() {
+        let printer = PrettyPrinter::new().indent(4);
+        let expected = [
+            "{",
+            "    \"b\": [",
+            "        1,",
+            "        {",
+            "            \"c\": null",
+            "        }",
+            "    ],",
+            "    \"a\": \"x\"",
+            "}",
+        ];
+        assert_eq!(format(INPUT, &printer).unwrap(), expected.join("\n") + "\n");
+        let printer = PrettyPrinter::new().minify(true);
+        assert_eq!(
+            format(INPUT, &printer).unwrap(),
+            "{\"b\":[1,{\"c\":null}],\"a\":\"x\"}\n"
+        );
+        let printer = printer.key_order(KeyOrder::Sorted);
+        assert_eq!(
+            format(INPUT, &printer).unwrap(),
+            "{\"a\":\"x\",\"b\":[1,{\"c\":null}]}\n"
+        );
+        let error = format("{\"a\": }", &printer).unwrap_err();
+        assert!(error.contains("^"), "{}", error);
+    }
+
+    fn run_args(args: &[&str]) -> CliResult {
+        run(&args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn test_in_place() {
+        let stdin_error = Err("--in-place needs a file, not standard input".to_string());
+        assert_eq!(run_args(&["--in-place"]), stdin_error);
+        assert_eq!(run_args(&["-", "--in-place"]), stdin_error);
+        assert!(run_args(&["--indent", "two"]).is_err());
+
+        let path =
+            std::env::temp_dir().join(format!("json-parser-fmt-{}.json", std::process::id()));
+        fs::write(&path, INPUT).unwrap();
+        let path_arg = path.to_string_lossy().into_owned();
+        let result = run_args(&[&path_arg, "--minify", "--sort-keys", "--in-place"]);
+        let rewritten = fs::read_to_string(&path);
+        fs::write(&path, "[1,").unwrap();
+        let broken = run_args(&[&path_arg, "--in-place"]);
+        let unchanged = fs::read_to_string(&path);
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(result, Ok(()));
+        assert_eq!(rewritten.unwrap(), "{\"a\":\"x\",\"b\":[1,{\"c\":null}]}\n");
+        // Invalid input is reported and the file left alone
+        assert!(broken.is_err());
+        assert_eq!(unchanged.unwrap(), "[1,");
+    }
+}
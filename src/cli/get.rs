@@ -0,0 +1,135 @@
+//! `get <pointer-or-path> [<file>|-] [--raw]`
+//!
+//! Prints the value at a location in a document, pretty-printed. The
+//! location is a JSON Pointer (`/users/0/name`), a key path
+//! (`users[0].name` or `users.0.name`) or a JSONPath (`$.users[*].name`, one match per line).
+//! `--raw` prints strings without quotes and escapes. The exit code is 1 if
+//! the location selects nothing and 2 for any other failure, so scripts can
+//! tell a missing value from a broken input.
+
+use std::io::{self, Write};
+
+use json_parser_rs::{JsonPath, JsonPointer, JsonValue, KeyPath, parse_json};
+
+use super::{read_file, take_flag};
+
+const USAGE: &str = "Usage: get <pointer-or-path> [<file_path>|-] [--raw]";
+
+/// Exit code when the location selects nothing
+const NOT_FOUND: i32 = 1;
+/// Exit code for any other failure: usage, unreadable or invalid input
+const FAILED: i32 = 2;
+
+/// Run `get` and return the exit code
+pub fn run(args: &[String]) -> i32 {
+    match get(args) {
+        Ok(()) => 0,
+        Err((code, message)) => {
+            eprintln!("{}", message);
+            code
+        }
+    }
+}
+
+fn get(args: &[String]) -> Result<(), (i32, String)> {
+    let failed = |message: String| (FAILED, message);
+    let mut args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let raw = take_flag(&mut args, "--raw");
+    let (location, file_path) = match args[..] {
+        [location] => (location, "-"),
+        [location, path] => (location, path),
+        _ => return Err(failed(USAGE.to_string())),
+    };
+
+    let content = read_file(file_path).map_err(failed)?;
+    let value =
+        parse_json(&content).map_err(|e| failed(e.display_with_source(&content).to_string()))?;
+    let found = select(&value, location).map_err(failed)?;
+    if found.is_empty() {
+        return Err((NOT_FOUND, format!("Nothing found at '{}'", location)));
+    }
+
+    let mut out = io::stdout().lock();
+    for value in found {
+        let written = match value.as_str() {
+            Some(s) if raw => writeln!(out, "{}", s),
+            _ => writeln!(out, "{}", value.to_pretty_string()),
+        };
+        written.map_err(|e| failed(format!("Error writing output: {}", e)))?;
+    }
+    Ok(())
+}
+
+/// Values at `location`, read as a JSONPath if it starts with `$`, as a
+/// JSON Pointer if it is empty or starts with `/` and as a key path
+/// otherwise
+fn select<'v>(value: &'v JsonValue, location: &str) -> Result<Vec<&'v JsonValue>, String> {
+    if location.starts_with('$') {
+        let path = JsonPath::compile(location).map_err(|e| e.to_string())?;
+        return Ok(value.query(&path));
+    }
+    let pointer = if location.is_empty() || location.starts_with('/') {
+        JsonPointer::parse(location).map_err(|e| e.to_string())?
+    } else {
+        let path = location.strip_prefix('.').unwrap_or(location);
+        KeyPath::parse(path)
+            .map_err(|e| e.to_string())?
+            .to_pointer()
+    };
+    Ok(value.get_many(&[&pointer]).into_iter().flatten().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn select_json(location: &str) -> Result<Vec<String>, String> {
+        let value =
+            parse_json(r#"{"users": [{"name": "Ada", "a/b": 1}, {"name": "Bob"}]}"#).unwrap();
+        let found = select(&value, location)?;
+        Ok(found.iter().map(|value| value.to_json_string()).collect())
+    }
+
+    #[test]
+    fn test_select_location_forms() {
+        assert_eq!(select_json("/users/1/name").unwrap(), ["\"Bob\""]);
+        assert_eq!(select_json("/users/0/a~1b").unwrap(), ["1"]);
+        assert_eq!(select_json("users[0].name").unwrap(), ["\"Ada\""]);
+        assert_eq!(select_json("users.1.name").unwrap(), ["\"Bob\""]);
+        assert_eq!(select_json(".users.1.name").unwrap(), ["\"Bob\""]);
+        assert_eq!(
+            select_json("$.users[*].name").unwrap(),
+            ["\"Ada\"", "\"Bob\""]
+        );
+        // The empty pointer is the whole document
+        let whole = select_json("").unwrap();
+        assert_eq!(whole.len(), 1);
+        assert!(whole[0].starts_with("{\"users\": "));
+
+        assert!(select_json("/users/2").unwrap().is_empty());
+        assert!(select_json("users.0.age").unwrap().is_empty());
+        assert!(select_json("$.nobody[*]").unwrap().is_empty());
+        assert!(select_json("/users/~2").is_err());
+        assert!(select_json("$.users[").is_err());
+    }
+
+    #[test]
+    fn test_exit_codes() {
+        let path =
+            std::env::temp_dir().join(format!("json-parser-get-{}.json", std::process::id()));
+        std::fs::write(&path, r#"{"a": 1}"#).unwrap();
+        let path = path.to_string_lossy().into_owned();
+        let code = |args: &[&str]| run(&args.iter().map(|a| a.to_string()).collect::<Vec<_>>());
+        let found = code(&["/a", &path]);
+        let missing = code(&["/b", &path]);
+        let bad_pointer = code(&["/a/~9", &path]);
+        let unreadable = code(&["/a", "/nonexistent/input.json"]);
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(found, 0);
+        assert_eq!(missing, NOT_FOUND);
+        assert_eq!(bad_pointer, FAILED);
+        assert_eq!(unreadable, FAILED);
+        assert_eq!(code(&[]), FAILED);
+    }
+}
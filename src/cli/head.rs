@@ -0,0 +1,78 @@
+//! `head [<file>|-] [-n <count>] [--ndjson]`
+//!
+//! Prints the first `count` (10 by default) elements of a top-level array,
+//! or the first records of NDJSON. Input starting with `[` is taken as an
+//! array unless `--ndjson` is given. Only the requested prefix is read and
+//! parsed, so this is instant even on huge files.
+
+use std::io::{self, Write};
+
+use json_parser_rs::JsonValue;
+use json_parser_rs::stream::ElementStream;
+
+use super::{CliResult, ndjson_records, open_input, starts_with_array, take_flag, take_option};
+
+pub(super) const DEFAULT_COUNT: usize = 10;
+
+pub fn run(args: &[String]) -> CliResult {
+    let (file_path, count, ndjson) = parse_args(args, "head")?;
+    let mut input = open_input(file_path)?;
+    let mut out = io::stdout().lock();
+    if ndjson || !starts_with_array(&mut input)? {
+        for record in ndjson_records(input).take(count) {
+            let (line, _) = record?;
+            writeln!(out, "{}", line).map_err(write_error)?;
+        }
+        return Ok(());
+    }
+
+    let stream = ElementStream::from_reader(input).map_err(|e| e.to_string())?;
+    let mut elements = Vec::new();
+    for element in stream.take(count) {
+        elements.push(element.map_err(|e| e.to_string())?.value);
+    }
+    write_array(&mut out, &elements)
+}
+
+/// Arguments shared with `tail`: the input, the count and `--ndjson`
+pub(super) fn parse_args<'a>(
+    args: &'a [String],
+    command: &str,
+) -> Result<(&'a str, usize, bool), String> {
+    let mut args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let count = match take_option(&mut args, "-n")? {
+        None => DEFAULT_COUNT,
+        Some(n) => n
+            .parse()
+            .map_err(|_| format!("-n must be a number, got '{}'", n))?,
+    };
+    let ndjson = take_flag(&mut args, "--ndjson");
+    match args[..] {
+        [] => Ok(("-", count, ndjson)),
+        [path] => Ok((path, count, ndjson)),
+        _ => Err(format!(
+            "Usage: {} [<file_path>|-] [-n <count>] [--ndjson]",
+            command
+        )),
+    }
+}
+
+/// Write `elements` as an array with one compact element per line
+pub(super) fn write_array<'v>(
+    out: &mut impl Write,
+    elements: impl IntoIterator<Item = &'v JsonValue>,
+) -> CliResult {
+    let mut first = true;
+    for element in elements {
+        out.write_all(if first { b"[\n  " } else { b",\n  " })
+            .and_then(|()| element.write_json(out))
+            .map_err(write_error)?;
+        first = false;
+    }
+    let close = if first { "[]" } else { "\n]" };
+    writeln!(out, "{}", close).map_err(write_error)
+}
+
+pub(super) fn write_error(error: io::Error) -> String {
+    format!("Error writing output: {}", error)
+}
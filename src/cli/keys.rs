@@ -0,0 +1,184 @@
+//! `keys [<file>|-] [--max-depth <n>] [--counts]`
+//!
+//! Lists the distinct paths in a document as JSON Pointers, in the order
+//! they first appear. Array indices are collapsed to `*`, so a list of
+//! records contributes each of its fields once (`/users/*/name`).
+//! `--max-depth` stops descending after `n` levels; `--counts` prefixes
+//! every path with the number of values found there. The top-level
+//! container is streamed, so only one of its elements is in memory at a
+//! time. A scalar document has no paths and prints nothing.
+
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+
+use json_parser_rs::pointer::escape_token;
+use json_parser_rs::stream::ElementStream;
+use json_parser_rs::{JsonValue, parse_json_reader};
+
+use super::{CliResult, first_byte, open_input, take_flag, take_option};
+
+const USAGE: &str = "Usage: keys [<file_path>|-] [--max-depth <levels>] [--counts]";
+
+pub fn run(args: &[String]) -> CliResult {
+    let mut args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let max_depth = match take_option(&mut args, "--max-depth")? {
+        None => usize::MAX,
+        Some(n) => match n.parse() {
+            Ok(n) if n > 0 => n,
+            _ => {
+                return Err(format!(
+                    "--max-depth must be a positive number, got '{}'",
+                    n
+                ));
+            }
+        },
+    };
+    let counts = take_flag(&mut args, "--counts");
+    let file_path = match args[..] {
+        [] => "-",
+        [path] => path,
+        _ => return Err(USAGE.to_string()),
+    };
+
+    let input = open_input(file_path)?;
+    keys(input, max_depth, counts, &mut io::stdout().lock())
+}
+
+/// Write the key paths of the document in `input` to `out`
+fn keys(
+    mut input: Box<dyn BufRead + Send>,
+    max_depth: usize,
+    counts: bool,
+    out: &mut impl Write,
+) -> CliResult {
+    let mut paths = KeyPaths::new(max_depth);
+    if let Some(b'[' | b'{') = first_byte(&mut input)? {
+        let stream = ElementStream::from_reader(input).map_err(|e| e.to_string())?;
+        let mut path = String::new();
+        for element in stream {
+            let element = element.map_err(|e| e.to_string())?;
+            let token = match &element.key {
+                Some(key) => escape_token(key),
+                None => "*".to_string(),
+            };
+            paths.descend(&mut path, &token, &element.value, 1);
+        }
+    } else {
+        // Nothing to stream; the scalar is only checked for validity
+        parse_json_reader(input).map_err(|e| e.to_string())?;
+    }
+
+    let width = paths
+        .found
+        .iter()
+        .map(|(_, n)| n.to_string().len())
+        .max()
+        .unwrap_or(0);
+    for (path, count) in &paths.found {
+        let written = if counts {
+            writeln!(out, "{:>width$}  {}", count, path)
+        } else {
+            writeln!(out, "{}", path)
+        };
+        written.map_err(|e| format!("Error writing output: {}", e))?;
+    }
+    out.flush()
+        .map_err(|e| format!("Error writing output: {}", e))
+}
+
+/// Distinct paths with their number of occurrences, in first-seen order
+struct KeyPaths {
+    max_depth: usize,
+    found: Vec<(String, usize)>,
+    index: HashMap<String, usize>,
+}
+
+impl KeyPaths {
+    fn new(max_depth: usize) -> Self {
+        Self {
+            max_depth,
+            found: Vec::new(),
+            index: HashMap::new(),
+        }
+    }
+
+    /// Record `value` at `path` + `/token` (at nesting `depth`) and
+    /// everything below it; `path` is restored afterwards
+    fn descend(&mut self, path: &mut String, token: &str, value: &JsonValue, depth: usize) {
+        let len = path.len();
+        path.push('/');
+        path.push_str(token);
+        match self.index.get(path.as_str()) {
+            Some(&i) => self.found[i].1 += 1,
+            None => {
+                self.index.insert(path.clone(), self.found.len());
+                self.found.push((path.clone(), 1));
+            }
+        }
+        if depth < self.max_depth {
+            if let Some(members) = value.as_object() {
+                for (key, member) in members {
+                    self.descend(path, &escape_token(key), member, depth + 1);
+                }
+            } else if let Some(elements) = value.as_array() {
+                for element in elements {
+                    self.descend(path, "*", element, depth + 1);
+                }
+            }
+        }
+        path.truncate(len);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use json_parser_rs::parse_json;
+
+    #[test]
+    fn test_paths_collapse_indices() {
+        let value = parse_json(r#"[{"id": 1, "tags": ["a", "b"]}, {"id": 2, "a/b": {"c": null}}]"#)
+            .unwrap();
+        let mut paths = KeyPaths::new(usize::MAX);
+        for element in value.as_array().unwrap() {
+            paths.descend(&mut String::new(), "*", element, 1);
+        }
+        let found: Vec<(&str, usize)> = paths.found.iter().map(|(p, n)| (p.as_str(), *n)).collect();
+        assert_eq!(
+            found,
+            [
+                ("/*", 2),
+                ("/*/id", 2),
+                ("/*/tags", 1),
+                ("/*/tags/*", 2),
+                ("/*/a~1b", 1),
+                ("/*/a~1b/c", 1)
+            ]
+        );
+
+        let mut shallow = KeyPaths::new(2);
+        shallow.descend(&mut String::new(), "*", &value[1], 1);
+        assert_eq!(shallow.found.len(), 3);
+    }
+
+    #[test]
+    fn test_scalar_and_container_roots() {
+        let keys_of = |input: &str, counts: bool| {
+            let input: Box<dyn BufRead + Send> = Box::new(io::Cursor::new(input.to_string()));
+            let mut out = Vec::new();
+            keys(input, usize::MAX, counts, &mut out).map(|()| String::from_utf8(out).unwrap())
+        };
+        for scalar in ["42", " \"x\"\n", "null", "true", "-1.5e3"] {
+            assert_eq!(keys_of(scalar, false), Ok(String::new()), "{}", scalar);
+        }
+        assert!(keys_of("42 43", false).is_err());
+        assert!(keys_of("nul", false).is_err());
+        assert!(keys_of("", false).is_err());
+
+        assert_eq!(
+            keys_of(r#"{"a": {"b": 1}, "c": [2]}"#, false).unwrap(),
+            "/a\n/a/b\n/c\n/c/*\n"
+        );
+        assert_eq!(keys_of("[1, [2, 3]]", true).unwrap(), "2  /*\n2  /*/*\n");
+    }
+}
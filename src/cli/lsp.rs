@@ -0,0 +1,24 @@
+//! `lsp`
+//!
+//! Runs a language server on stdin and stdout that publishes parse errors
+//! as diagnostics, for editors without a JSON plugin of their own; see
+//! [`json_parser_rs::lsp`]. It takes no arguments and runs until the editor
+//! sends `exit`.
+
+use std::io;
+
+use json_parser_rs::lsp::serve;
+
+use super::CliResult;
+
+pub fn run(args: &[String]) -> CliResult {
+    if !args.is_empty() {
+        return Err("Usage: lsp".to_string());
+    }
+    let code = serve(io::stdin().lock(), io::stdout().lock())
+        .map_err(|e| format!("Language server error: {}", e))?;
+    match code {
+        0 => Ok(()),
+        _ => Err("Language server exited without shutdown".to_string()),
+    }
+}
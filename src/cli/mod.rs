@@ -0,0 +1,231 @@
+//! Subcommands of the command line tool
+//!
+//! Without a subcommand, `main` validates the files given, see [`validate`];
+//! any first argument naming a subcommand is dispatched here instead. Each
+//! subcommand lives in its own module with a `run` function taking the
+//! arguments after the subcommand name.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use json_parser_rs::{JsonValue, parse_json};
+
+mod check;
+mod concat;
+mod convert;
+mod dedupe;
+mod diff;
+mod fmt;
+mod get;
+mod head;
+mod keys;
+mod lsp;
+mod patch;
+mod query;
+mod repl;
+mod sample;
+mod sort;
+mod split;
+mod stats;
+mod tail;
+mod validate;
+
+pub use validate::run as validate;
+
+/// Outcome of a subcommand; the error is the message printed to stderr
+type CliResult = Result<(), String>;
+
+/// Run the subcommand named by `args[1]` and return the exit code, or
+/// `None` if `args[1]` is not a subcommand
+pub fn run(args: &[String]) -> Option<i32> {
+    let command = args.get(1)?;
+    let rest = &args[2..];
+    let result = match command.as_str() {
+        "check" => check::run(rest),
+        "concat" => concat::run(rest),
+        "convert" => convert::run(rest),
+        "dedupe" => dedupe::run(rest),
+        "diff" => diff::run(rest),
+        "fmt" => fmt::run(rest),
+        "get" => return Some(get::run(rest)),
+        "head" => head::run(rest),
+        "keys" => keys::run(rest),
+        "lsp" => lsp::run(rest),
+        "patch" => patch::run(rest),
+        "query" => query::run(rest),
+        "repl" => repl::run(rest),
+        "sample" => sample::run(rest),
+        "sort" => sort::run(rest),
+        "split" => split::run(rest),
+        "stats" => stats::run(rest),
+        "tail" => tail::run(rest),
+        _ => return None,
+    };
+    Some(match result {
+        Ok(()) => 0,
+        Err(message) => {
+            eprintln!("{}", message);
+            1
+        }
+    })
+}
+
+/// Read a whole input file; `-` reads standard input
+fn read_file(path: &str) -> Result<String, String> {
+    if path == "-" {
+        let mut content = String::new();
+        io::stdin()
+            .read_to_string(&mut content)
+            .map_err(|e| format!("Error reading standard input: {}", e))?;
+        return Ok(content);
+    }
+    fs::read_to_string(path).map_err(|e| format!("Error reading file '{}': {}", path, e))
+}
+
+/// Write `content` next to `path`, then move it over the original, so a
+/// failed write leaves the original untouched
+fn replace_file(path: &str, content: &str) -> CliResult {
+    let (temporary, mut file) = create_temporary(path)?;
+    file.write_all(content.as_bytes())
+        .and_then(|()| fs::rename(&temporary, path))
+        .map_err(|e| {
+            let _ = fs::remove_file(&temporary);
+            format!("Error writing file '{}': {}", path, e)
+        })
+}
+
+/// Create a new file next to `path` to write its replacement into
+///
+/// The name is unique to this process and the file must not exist yet: a
+/// file or symlink planted under the expected name is skipped rather than
+/// truncated or written through.
+fn create_temporary(path: &str) -> Result<(String, File), String> {
+    let mut attempt = 0;
+    loop {
+        let temporary = format!(
+            "{}.{}-{}.tmp",
+            path,
+            process::id(),
+            TEMPORARY_FILES.fetch_add(1, Ordering::Relaxed)
+        );
+        match OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&temporary)
+        {
+            Ok(file) => return Ok((temporary, file)),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists && attempt < 100 => attempt += 1,
+            Err(e) => return Err(format!("Error creating file '{}': {}", temporary, e)),
+        }
+    }
+}
+
+/// Temporary files created by this process so far, numbering their names
+static TEMPORARY_FILES: AtomicUsize = AtomicUsize::new(0);
+
+/// Open an input for incremental reading; `-` reads standard input
+fn open_input(path: &str) -> Result<Box<dyn BufRead + Send>, String> {
+    if path == "-" {
+        return Ok(Box::new(BufReader::new(io::stdin())));
+    }
+    let file = File::open(path).map_err(|e| format!("Error reading file '{}': {}", path, e))?;
+    Ok(Box::new(BufReader::new(file)))
+}
+
+/// Parsed NDJSON records of `input` with their line text, skipping blank
+/// lines; errors name the line they occurred on
+fn ndjson_records(
+    input: Box<dyn BufRead + Send>,
+) -> impl Iterator<Item = Result<(String, JsonValue), String>> {
+    input
+        .lines()
+        .enumerate()
+        .filter_map(|(number, line)| match line {
+            Ok(line) if line.trim().is_empty() => None,
+            Ok(line) => Some(
+                parse_json(&line)
+                    .map(|record| (line, record))
+                    .map_err(|e| format!("Line {}: {}", number + 1, e)),
+            ),
+            Err(e) => Some(Err(format!("Error reading input: {}", e))),
+        })
+}
+
+/// Whether `input` holds one JSON array rather than NDJSON, judged by its
+/// first non-whitespace character; the whitespace is consumed
+fn starts_with_array(input: &mut dyn BufRead) -> Result<bool, String> {
+    Ok(first_byte(input)? == Some(b'['))
+}
+
+/// The first non-whitespace byte of `input`, left unread; the whitespace is
+/// consumed
+fn first_byte(input: &mut dyn BufRead) -> Result<Option<u8>, String> {
+    loop {
+        let buffer = input
+            .fill_buf()
+            .map_err(|e| format!("Error reading input: {}", e))?;
+        let Some(&first) = buffer.first() else {
+            return Ok(None);
+        };
+        if !first.is_ascii_whitespace() {
+            return Ok(Some(first));
+        }
+        input.consume(1);
+    }
+}
+
+/// Remove `--name value` from `args`, returning the value
+fn take_option(args: &mut Vec<&str>, name: &str) -> Result<Option<String>, String> {
+    let Some(at) = args.iter().position(|arg| *arg == name) else {
+        return Ok(None);
+    };
+    if at + 1 >= args.len() {
+        return Err(format!("Missing value for {}", name));
+    }
+    let value = args.remove(at + 1).to_string();
+    args.remove(at);
+    Ok(Some(value))
+}
+
+/// Remove `name` from `args`, returning whether it was present
+fn take_flag(args: &mut Vec<&str>, name: &str) -> bool {
+    let before = args.len();
+    args.retain(|arg| *arg != name);
+    args.len() != before
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_replace_file_leaves_other_files_alone() {
+        let dir = std::env::temp_dir().join(format!("json-parser-replace-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("doc.json").to_string_lossy().into_owned();
+        fs::write(&path, "old").unwrap();
+        // A file under the old fixed name, and one under the next unique name
+        let fixed = format!("{}.tmp", path);
+        fs::write(&fixed, "unrelated").unwrap();
+        let next = format!(
+            "{}.{}-{}.tmp",
+            path,
+            process::id(),
+            TEMPORARY_FILES.load(Ordering::Relaxed)
+        );
+        fs::write(&next, "planted").unwrap();
+
+        let result = replace_file(&path, "new");
+        let content = fs::read_to_string(&path);
+        let others = [fs::read_to_string(&fixed), fs::read_to_string(&next)];
+        let files = fs::read_dir(&dir).unwrap().count();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(result, Ok(()));
+        assert_eq!(content.unwrap(), "new");
+        assert_eq!(others.map(Result::unwrap), ["unrelated", "planted"]);
+        assert_eq!(files, 3);
+    }
+}
@@ -0,0 +1,145 @@
+//! `patch <target> <patch> [--merge-patch] [--in-place] [--dry-run]`
+//!
+//! Applies an RFC 6902 JSON Patch (or, with `--merge-patch`, an RFC 7386
+//! merge patch) to a document and prints the result pretty-printed.
+//! `--in-place` rewrites the target file instead. `--dry-run` prints the
+//! resulting changes, as `diff` would, without writing anything. Patches
+//! are atomic: if one operation fails, nothing is changed.
+
+use std::io::{self, Write};
+
+use json_parser_rs::{JsonValue, json, parse_json};
+
+use super::{CliResult, read_file, replace_file, take_flag};
+
+const USAGE: &str =
+    "Usage: patch <target_file> <patch_file> [--merge-patch] [--in-place] [--dry-run]";
+
+pub fn run(args: &[String]) -> CliResult {
+    let mut args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let merge = take_flag(&mut args, "--merge-patch");
+    let in_place = take_flag(&mut args, "--in-place");
+    let dry_run = take_flag(&mut args, "--dry-run");
+    let [target_path, patch_path] = args[..] else {
+        return Err(USAGE.to_string());
+    };
+    if target_path == "-" && patch_path == "-" {
+        return Err("Only one input can be read from standard input".to_string());
+    }
+    if in_place && target_path == "-" {
+        return Err("--in-place needs a file, not standard input".to_string());
+    }
+
+    let original = load(target_path)?;
+    let patch = load(patch_path)?;
+    let patched = apply(&original, &patch, merge).map_err(|e| format!("{}: {}", patch_path, e))?;
+
+    let mut out = io::stdout().lock();
+    let write_error = |e: io::Error| format!("Error writing output: {}", e);
+    if dry_run {
+        for entry in json::diff(&original, &patched) {
+            writeln!(out, "{}", entry).map_err(write_error)?;
+        }
+        return Ok(());
+    }
+    let output = format!("{}\n", patched.to_pretty_string());
+    if in_place {
+        return replace_file(target_path, &output);
+    }
+    out.write_all(output.as_bytes()).map_err(write_error)
+}
+
+/// `original` with `patch` applied as a merge patch if `merge` is set, as a
+/// JSON Patch otherwise
+fn apply(original: &JsonValue, patch: &JsonValue, merge: bool) -> Result<JsonValue, String> {
+    let mut patched = original.clone();
+    if merge {
+        patched.merge_patch(patch);
+    } else if patch.is_object() {
+        // A JSON Patch is an array of operations; an object is most likely
+        // a merge patch given without the flag
+        return Err("a JSON Patch must be an array; use --merge-patch for a merge patch".into());
+    } else {
+        patched.apply_patch(patch).map_err(|e| e.to_string())?;
+    }
+    Ok(patched)
+}
+
+fn load(path: &str) -> Result<JsonValue, String> {
+    let content = read_file(path)?;
+    parse_json(&content).map_err(|e| format!("{}: {}", path, e.display_with_source(&content)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_json_patch_and_merge_patch() {
+        let original = json!({"name": "svc", "replicas": 2, "env": {"DEBUG": "1"}});
+        let operations = json!([
+            {"op": "replace", "path": "/replicas", "value": 3},
+            {"op": "remove", "path": "/env/DEBUG"},
+        ]);
+        assert_eq!(
+            apply(&original, &operations, false).unwrap(),
+            json!({"name": "svc", "replicas": 3, "env": {}})
+        );
+        let merge = json!({"replicas": 3, "env": {"DEBUG": null}});
+        assert_eq!(
+            apply(&original, &merge, true).unwrap(),
+            json!({"name": "svc", "replicas": 3, "env": {}})
+        );
+
+        // An object without --merge-patch is not taken for a JSON Patch
+        let error = apply(&original, &merge, false).unwrap_err();
+        assert!(error.contains("use --merge-patch"), "{}", error);
+        // Failing operations leave nothing half applied
+        let failing = json!([
+            {"op": "replace", "path": "/replicas", "value": 3},
+            {"op": "test", "path": "/name", "value": "db"},
+        ]);
+        assert!(apply(&original, &failing, false).is_err());
+        // As a merge patch, an array replaces the whole document
+        assert_eq!(apply(&original, &operations, true).unwrap(), operations);
+    }
+
+    #[test]
+    fn test_in_place() {
+        let dir = std::env::temp_dir().join(format!("json-parser-patch-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let target = dir.join("config.json").to_string_lossy().into_owned();
+        let patch = dir.join("patch.json").to_string_lossy().into_owned();
+        fs::write(&target, r#"{"a": 1, "b": [true]}"#).unwrap();
+        fs::write(&patch, r#"[{"op": "add", "path": "/b/-", "value": false}]"#).unwrap();
+        let run_args = |args: &[&str]| run(&args.iter().map(|a| a.to_string()).collect::<Vec<_>>());
+
+        let dry_run = run_args(&[&target, &patch, "--in-place", "--dry-run"]);
+        let after_dry_run = fs::read_to_string(&target);
+        let result = run_args(&[&target, &patch, "--in-place"]);
+        let rewritten = fs::read_to_string(&target);
+        fs::write(&patch, r#"[{"op": "remove", "path": "/c"}]"#).unwrap();
+        let failed = run_args(&[&target, &patch, "--in-place"]);
+        let after_failure = fs::read_to_string(&target);
+        let stdin = run_args(&["-", &patch, "--in-place"]);
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(dry_run, Ok(()));
+        assert_eq!(after_dry_run.unwrap(), r#"{"a": 1, "b": [true]}"#);
+        assert_eq!(result, Ok(()));
+        assert_eq!(
+            parse_json(&rewritten.unwrap()).unwrap(),
+            json!({"a": 1, "b": [true, false]})
+        );
+        assert!(failed.unwrap_err().starts_with(&patch));
+        assert_eq!(
+            parse_json(&after_failure.unwrap()).unwrap(),
+            json!({"a": 1, "b": [true, false]})
+        );
+        assert_eq!(
+            stdin,
+            Err("--in-place needs a file, not standard input".to_string())
+        );
+    }
+}
@@ -0,0 +1,97 @@
+//! `query <file>|- <jsonpath> [--output json|ndjson]`
+//!
+//! Prints the values a JSONPath expression selects. `json` (the default)
+//! prints them as one array once the query is complete; `ndjson` writes each
+//! match on its own line as soon as it is found, so a pipeline can start
+//! consuming before a large file has been read to the end.
+//!
+//! Paths whose first segment picks top-level elements by one name, index
+//! or wildcard (`$.items[*]`, `$[3]`, `$.*.id`) are evaluated while the
+//! input is read, holding one top-level element in memory at a time. Other
+//! paths (`$..id`, `$['a', 'b']`, filters using `$`) load the whole
+//! document first.
+
+use std::io::{self, BufRead, Write};
+
+use json_parser_rs::JsonPath;
+
+use super::{CliResult, open_input, take_option};
+
+const USAGE: &str = "Usage: query <file_path>|- <jsonpath> [--output json|ndjson]";
+
+pub fn run(args: &[String]) -> CliResult {
+    let mut args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let ndjson = match take_option(&mut args, "--output")?.as_deref() {
+        None | Some("json") => false,
+        Some("ndjson") => true,
+        Some(other) => return Err(format!("Unknown output format '{}'\n{}", other, USAGE)),
+    };
+    let [file_path, path] = args[..] else {
+        return Err(USAGE.to_string());
+    };
+
+    let path = JsonPath::compile(path).map_err(|e| e.to_string())?;
+    let input = open_input(file_path)?;
+    query(&path, input, ndjson, &mut io::stdout().lock())
+}
+
+/// Write the matches of `path` in `input` to `out`
+fn query(
+    path: &JsonPath,
+    input: Box<dyn BufRead + Send>,
+    ndjson: bool,
+    out: &mut impl Write,
+) -> CliResult {
+    let mut first = true;
+    for found in path.stream_reader(input) {
+        let value = found.map_err(|e| e.to_string())?;
+        let separator = match (ndjson, first) {
+            (true, _) => "",
+            (false, true) => "[",
+            (false, false) => ", ",
+        };
+        write!(out, "{}", separator).map_err(write_error)?;
+        value.write_json(&mut *out).map_err(write_error)?;
+        if ndjson {
+            // Line-buffered stdout pushes every match out immediately
+            writeln!(out).map_err(write_error)?;
+        }
+        first = false;
+    }
+    if !ndjson {
+        writeln!(out, "{}]", if first { "[" } else { "" }).map_err(write_error)?;
+    }
+    out.flush().map_err(write_error)
+}
+
+fn write_error(error: io::Error) -> String {
+    format!("Error writing output: {}", error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn query_to_string(path: &str, input: &str, ndjson: bool) -> Result<String, String> {
+        let path = JsonPath::compile(path).unwrap();
+        let input: Box<dyn BufRead + Send> = Box::new(io::Cursor::new(input.to_string()));
+        let mut out = Vec::new();
+        query(&path, input, ndjson, &mut out)?;
+        Ok(String::from_utf8(out).unwrap())
+    }
+
+    #[test]
+    fn test_output_forms() {
+        let doc = r#"{"items": [{"id": 1}, {"id": 2}], "other": {"id": 3}}"#;
+        assert_eq!(
+            query_to_string("$.items[*].id", doc, false).unwrap(),
+            "[1, 2]\n"
+        );
+        assert_eq!(query_to_string("$..id", doc, true).unwrap(), "1\n2\n3\n");
+        assert_eq!(query_to_string("$.missing", doc, false).unwrap(), "[]\n");
+        assert_eq!(query_to_string("$", "5", false).unwrap(), "[5]\n");
+
+        let error = query_to_string("$[*]", "[1, 2", true).unwrap_err();
+        assert!(error.starts_with("Parse error at line 1"), "{}", error);
+    }
+}
@@ -0,0 +1,301 @@
+//! `repl <file>`
+//!
+//! Loads a document and evaluates expressions against it interactively,
+//! printing each result pretty-printed. An expression is a JSON Pointer
+//! (`/users/0`), a JSONPath (`$.users[*].name`) or a jq-style filter
+//! (`.users[] | .name`). `:history` lists earlier expressions, `!n` runs
+//! entry `n` again and `!!` the last one; `:help` explains the syntax and
+//! `:quit` (or end of input) leaves.
+
+use std::io::{self, BufRead, IsTerminal, Write};
+
+use json_parser_rs::{JsonPath, JsonValue, parse_json};
+
+use super::{CliResult, read_file};
+
+const USAGE: &str = "Usage: repl <file_path>";
+
+const HELP: &str = "\
+Expressions:
+  /users/0/name          JSON Pointer
+  $.users[*].name        JSONPath
+  .users[0].name         jq-style path; .[] iterates, | pipes, and
+  .users | length        keys, length and type are available as filters
+Commands:
+  :history               list earlier expressions
+  !n, !!                 run expression n or the last one again
+  :help, :quit";
+
+pub fn run(args: &[String]) -> CliResult {
+    let [file_path] = args else {
+        return Err(USAGE.to_string());
+    };
+    let content = read_file(file_path)?;
+    let document = parse_json(&content).map_err(|e| e.display_with_source(&content).to_string())?;
+
+    let interactive = io::stdin().is_terminal();
+    let mut out = io::stdout().lock();
+    let write_error = |e: io::Error| format!("Error writing output: {}", e);
+    let mut history: Vec<String> = Vec::new();
+    let mut lines = io::stdin().lock().lines();
+    loop {
+        if interactive {
+            write!(out, "> ")
+                .and_then(|()| out.flush())
+                .map_err(write_error)?;
+        }
+        let Some(line) = lines.next() else {
+            break;
+        };
+        let line = line.map_err(|e| format!("Error reading standard input: {}", e))?;
+        let mut expression = line.trim().to_string();
+        if expression.is_empty() {
+            continue;
+        }
+
+        if let Some(entry) = expression.strip_prefix('!') {
+            let recalled = match entry {
+                "!" => history.last(),
+                n => n
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(|n| history.get(n.wrapping_sub(1))),
+            };
+            let Some(recalled) = recalled else {
+                writeln!(out, "No history entry '{}'", entry).map_err(write_error)?;
+                continue;
+            };
+            expression = recalled.clone();
+            writeln!(out, "{}", expression).map_err(write_error)?;
+        }
+        match expression.as_str() {
+            ":quit" | ":q" => break,
+            ":help" => writeln!(out, "{}", HELP).map_err(write_error)?,
+            ":history" => {
+                for (i, entry) in history.iter().enumerate() {
+                    writeln!(out, "{:>4}  {}", i + 1, entry).map_err(write_error)?;
+                }
+            }
+            _ => {
+                match evaluate(&document, &expression) {
+                    Ok(results) if results.is_empty() => writeln!(out, "(no results)"),
+                    Ok(results) => results
+                        .iter()
+                        .try_for_each(|value| writeln!(out, "{}", value.to_pretty_string())),
+                    Err(message) => writeln!(out, "Error: {}", message),
+                }
+                .map_err(write_error)?;
+                history.push(expression);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Results of `expression`, dispatched on its first character
+fn evaluate(document: &JsonValue, expression: &str) -> Result<Vec<JsonValue>, String> {
+    if expression.starts_with('$') {
+        let path = JsonPath::compile(expression).map_err(|e| e.to_string())?;
+        return Ok(document.query(&path).into_iter().cloned().collect());
+    }
+    if expression.starts_with('/') {
+        return Ok(document.pointer(expression).into_iter().cloned().collect());
+    }
+    let mut values = vec![document.clone()];
+    for stage in expression.split('|') {
+        let stage = parse_stage(stage.trim())?;
+        values = values
+            .into_iter()
+            .map(|value| stage.apply(value))
+            .collect::<Result<Vec<_>, _>>()?
+            .into_iter()
+            .flatten()
+            .collect();
+    }
+    Ok(values)
+}
+
+/// One `|`-separated part of a jq-style filter
+enum Stage {
+    Path(Vec<Step>),
+    Keys,
+    Length,
+    Type,
+}
+
+enum Step {
+    Key(String),
+    Index(i64),
+    Iterate,
+}
+
+fn parse_stage(text: &str) -> Result<Stage, String> {
+    match text {
+        "keys" => return Ok(Stage::Keys),
+        "length" => return Ok(Stage::Length),
+        "type" => return Ok(Stage::Type),
+        _ => {}
+    }
+    if !text.starts_with('.') {
+        return Err(format!("Unknown filter '{}'", text));
+    }
+
+    let mut steps = Vec::new();
+    let mut rest = text;
+    while !rest.is_empty() {
+        if let Some(after) = rest.strip_prefix('[') {
+            let (inner, after) = after
+                .split_once(']')
+                .ok_or_else(|| format!("Missing ']' in '{}'", text))?;
+            steps.push(match inner.trim() {
+                "" => Step::Iterate,
+                index => Step::Index(
+                    index
+                        .parse()
+                        .map_err(|_| format!("Invalid index '{}'", index))?,
+                ),
+            });
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix('.') {
+            let after = after.trim_start();
+            if let Some(quoted) = after.strip_prefix('"') {
+                let (key, after) = quoted
+                    .split_once('"')
+                    .ok_or_else(|| format!("Unterminated key in '{}'", text))?;
+                steps.push(Step::Key(key.to_string()));
+                rest = after;
+            } else {
+                let end = after
+                    .find(|ch: char| !(ch.is_alphanumeric() || ch == '_'))
+                    .unwrap_or(after.len());
+                if end > 0 {
+                    steps.push(Step::Key(after[..end].to_string()));
+                }
+                rest = &after[end..];
+            }
+        } else {
+            return Err(format!("Unexpected '{}' in '{}'", rest, text));
+        }
+    }
+    Ok(Stage::Path(steps))
+}
+
+impl Stage {
+    fn apply(&self, value: JsonValue) -> Result<Vec<JsonValue>, String> {
+        match self {
+            Stage::Path(steps) => {
+                let mut values = vec![value];
+                for step in steps {
+                    values = values
+                        .into_iter()
+                        .map(|value| step.apply(value))
+                        .collect::<Result<Vec<_>, _>>()?
+                        .into_iter()
+                        .flatten()
+                        .collect();
+                }
+                Ok(values)
+            }
+            Stage::Keys => match value {
+                JsonValue::Object(members) => {
+                    // Sorted, as in jq
+                    let mut keys: Vec<&str> = members.keys().map(String::as_str).collect();
+                    keys.sort_unstable();
+                    Ok(vec![keys.into_iter().map(JsonValue::from).collect()])
+                }
+                JsonValue::Array(elements) => Ok(vec![
+                    (0..elements.len() as u64).map(JsonValue::from).collect(),
+                ]),
+                other => Err(format!("{} has no keys", type_name(&other))),
+            },
+            Stage::Length => {
+                let length = match &value {
+                    JsonValue::Null => 0,
+                    JsonValue::String(s) => s.chars().count(),
+                    JsonValue::Array(elements) => elements.len(),
+                    JsonValue::Object(members) => members.len(),
+                    other => return Err(format!("{} has no length", type_name(other))),
+                };
+                Ok(vec![JsonValue::from(length as u64)])
+            }
+            Stage::Type => Ok(vec![JsonValue::from(type_name(&value))]),
+        }
+    }
+}
+
+impl Step {
+    fn apply(&self, value: JsonValue) -> Result<Vec<JsonValue>, String> {
+        match (self, value) {
+            (Step::Key(_) | Step::Index(_), JsonValue::Null) => Ok(vec![JsonValue::Null]),
+            (Step::Key(key), JsonValue::Object(mut members)) => {
+                Ok(vec![members.remove(key).unwrap_or(JsonValue::Null)])
+            }
+            (Step::Index(i), JsonValue::Array(mut elements)) => {
+                let len = elements.len() as i64;
+                let i = if *i < 0 { len + i } else { *i };
+                let found = (0..len)
+                    .contains(&i)
+                    .then(|| elements.swap_remove(i as usize));
+                Ok(vec![found.unwrap_or(JsonValue::Null)])
+            }
+            (Step::Iterate, JsonValue::Array(elements)) => Ok(elements),
+            (Step::Iterate, JsonValue::Object(members)) => {
+                Ok(members.into_iter().map(|(_, value)| value).collect())
+            }
+            (Step::Key(key), other) => Err(format!(
+                "Cannot index {} with \"{}\"",
+                type_name(&other),
+                key
+            )),
+            (Step::Index(i), other) => {
+                Err(format!("Cannot index {} with {}", type_name(&other), i))
+            }
+            (Step::Iterate, other) => Err(format!("Cannot iterate over {}", type_name(&other))),
+        }
+    }
+}
+
+fn type_name(value: &JsonValue) -> &'static str {
+    if value.is_null() {
+        "null"
+    } else if value.as_bool().is_some() {
+        "boolean"
+    } else if value.is_number() {
+        "number"
+    } else if value.as_str().is_some() {
+        "string"
+    } else if value.is_array() {
+        "array"
+    } else {
+        "object"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_jq_filters() {
+        let document =
+            parse_json(r#"{"users": [{"name": "Ada", "tags": ["x"]}, {"name": "Linus"}], "n": 1}"#)
+                .unwrap();
+        let eval = |expression| {
+            evaluate(&document, expression)
+                .map(|values| values.iter().map(ToString::to_string).collect::<Vec<_>>())
+        };
+        assert_eq!(eval(".users[].name").unwrap(), ["\"Ada\"", "\"Linus\""]);
+        assert_eq!(eval(".users[-1] | .name").unwrap(), ["\"Linus\""]);
+        assert_eq!(eval(".users[1].tags").unwrap(), ["null"]);
+        assert_eq!(eval(".users | length").unwrap(), ["2"]);
+        assert_eq!(eval(". | keys").unwrap(), ["[\"n\", \"users\"]"]);
+        assert_eq!(
+            eval(".users[0] | .\"name\" | type").unwrap(),
+            ["\"string\""]
+        );
+        assert_eq!(eval("/users/0/tags/0").unwrap(), ["\"x\""]);
+        assert_eq!(eval("$..name").unwrap().len(), 2);
+        assert_eq!(eval(".n.x").unwrap_err(), "Cannot index number with \"x\"");
+        assert!(eval("nonsense").is_err());
+    }
+}
@@ -0,0 +1,96 @@
+//! `sample [<file>|-] --n <records> [--seed <number>]`
+//!
+//! Picks `n` NDJSON records uniformly at random in a single pass (reservoir
+//! sampling), so only the sample is held in memory however large the input
+//! is. The chosen records are written unchanged and in input order. The
+//! same `--seed` on the same input always yields the same sample.
+
+use std::io::{self, Write};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::{CliResult, ndjson_records, open_input, take_option};
+
+const USAGE: &str = "Usage: sample [<file_path>|-] --n <records> [--seed <number>]";
+
+pub fn run(args: &[String]) -> CliResult {
+    let mut args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let n = take_option(&mut args, "--n")?.ok_or_else(|| USAGE.to_string())?;
+    let n: usize = n
+        .parse()
+        .map_err(|_| format!("--n must be a number, got '{}'", n))?;
+    let seed = match take_option(&mut args, "--seed")? {
+        Some(seed) => seed
+            .parse()
+            .map_err(|_| format!("--seed must be a number, got '{}'", seed))?,
+        None => SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_nanos() as u64),
+    };
+    let file_path = match args[..] {
+        [] => "-",
+        [path] => path,
+        _ => return Err(USAGE.to_string()),
+    };
+
+    let mut random = SplitMix64(seed);
+    // (position in the input, line)
+    let mut reservoir: Vec<(usize, String)> = Vec::with_capacity(n);
+    for (seen, record) in ndjson_records(open_input(file_path)?).enumerate() {
+        let (line, _) = record?;
+        if reservoir.len() < n {
+            reservoir.push((seen, line));
+        } else {
+            // Keep the new record with probability n / (seen + 1)
+            let slot = random.below(seen as u64 + 1) as usize;
+            if slot < n {
+                reservoir[slot] = (seen, line);
+            }
+        }
+    }
+
+    reservoir.sort_unstable_by_key(|&(position, _)| position);
+    let mut out = io::stdout().lock();
+    for (_, line) in reservoir {
+        writeln!(out, "{}", line).map_err(|e| format!("Error writing output: {}", e))?;
+    }
+    Ok(())
+}
+
+/// SplitMix64, a small fast generator that is plenty for sampling
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform number in `0..bound` (without modulo bias)
+    fn below(&mut self, bound: u64) -> u64 {
+        let zone = u64::MAX - u64::MAX % bound;
+        loop {
+            let value = self.next();
+            if value < zone {
+                return value % bound;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_below_is_roughly_uniform() {
+        let mut random = SplitMix64(7);
+        let mut counts = [0usize; 4];
+        for _ in 0..40_000 {
+            counts[random.below(4) as usize] += 1;
+        }
+        assert!(counts.iter().all(|&count| (9_000..11_000).contains(&count)));
+    }
+}
@@ -0,0 +1,355 @@
+//! `sort [<file>|-] --key <pointer> [--reverse] [--chunk <records>]`
+//!
+//! Sorts NDJSON records by the value at a JSON Pointer, writing them
+//! unchanged in the new order. Inputs of any size are handled with an
+//! external merge sort: records are sorted in chunks of `--chunk` records
+//! (100000 by default), each sorted chunk is spilled to a temporary file and
+//! the files are merged. The sort is stable.
+//!
+//! Keys compare by type first (missing < null < booleans < numbers <
+//! strings < arrays < objects), then by value.
+
+use std::cmp::{Ordering, Reverse};
+use std::collections::BinaryHeap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::PathBuf;
+use std::process;
+use std::sync::atomic::{self, AtomicUsize};
+
+use json_parser_rs::{JsonNumber, JsonPointer, JsonValue, parse_json};
+
+use super::{CliResult, ndjson_records, open_input, take_flag, take_option};
+
+const USAGE: &str = "Usage: sort [<file_path>|-] --key <pointer> [--reverse] [--chunk <records>]";
+const DEFAULT_CHUNK: usize = 100_000;
+
+pub fn run(args: &[String]) -> CliResult {
+    let mut args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let key = take_option(&mut args, "--key")?.ok_or_else(|| USAGE.to_string())?;
+    let key = JsonPointer::parse(&key).map_err(|e| e.to_string())?;
+    let reverse = take_flag(&mut args, "--reverse");
+    let chunk = match take_option(&mut args, "--chunk")? {
+        None => DEFAULT_CHUNK,
+        Some(n) => match n.parse() {
+            Ok(n) if n > 0 => n,
+            _ => return Err(format!("--chunk must be a positive number, got '{}'", n)),
+        },
+    };
+    let file_path = match args[..] {
+        [] => "-",
+        [path] => path,
+        _ => return Err(USAGE.to_string()),
+    };
+
+    let input = open_input(file_path)?;
+    let order = KeyOrder { reverse };
+    sort(
+        input,
+        &key,
+        order,
+        chunk,
+        &mut BufWriter::new(io::stdout().lock()),
+    )
+}
+
+/// Sort the records of `input` into `out`, spilling every `chunk` records
+fn sort(
+    input: Box<dyn BufRead + Send>,
+    key: &JsonPointer,
+    order: KeyOrder,
+    chunk: usize,
+    out: &mut impl Write,
+) -> CliResult {
+    let mut runs = Runs::default();
+    let mut records: Vec<Record> = Vec::new();
+    for record in ndjson_records(input) {
+        let (line, record) = record?;
+        let key = record.get_many(&[key])[0].cloned();
+        records.push(Record { key, line });
+        if records.len() == chunk {
+            runs.spill(&mut records, order)?;
+        }
+    }
+
+    let written = if runs.paths.is_empty() {
+        // Everything fit into one chunk: no temporary files needed
+        records.sort_by(|a, b| order.compare(&a.key, &b.key));
+        records
+            .iter()
+            .try_for_each(|record| writeln!(out, "{}", record.line))
+            .and_then(|()| out.flush())
+    } else {
+        runs.spill(&mut records, order)?;
+        runs.merge(out, order)
+    };
+    written.map_err(|e| format!("Error writing output: {}", e))
+}
+
+struct Record {
+    key: Option<JsonValue>,
+    line: String,
+}
+
+#[derive(Clone, Copy)]
+struct KeyOrder {
+    reverse: bool,
+}
+
+impl KeyOrder {
+    fn compare(self, a: &Option<JsonValue>, b: &Option<JsonValue>) -> Ordering {
+        let ordering = match (a, b) {
+            (Some(a), Some(b)) => compare_values(a, b),
+            (a, b) => a.is_some().cmp(&b.is_some()),
+        };
+        if self.reverse {
+            ordering.reverse()
+        } else {
+            ordering
+        }
+    }
+}
+
+fn rank(value: &JsonValue) -> u8 {
+    match value {
+        JsonValue::Null => 0,
+        JsonValue::Boolean(_) => 1,
+        JsonValue::Number(_) => 2,
+        JsonValue::String(_) | JsonValue::RawString(_) => 3,
+        JsonValue::Array(_) => 4,
+        _ => 5,
+    }
+}
+
+fn compare_values(a: &JsonValue, b: &JsonValue) -> Ordering {
+    match (a, b) {
+        (JsonValue::Boolean(a), JsonValue::Boolean(b)) => a.cmp(b),
+        (JsonValue::Number(a), JsonValue::Number(b)) => match (integer(a), integer(b)) {
+            // Exact, since `f64` cannot tell large integers apart
+            (Some(a), Some(b)) => a.cmp(&b),
+            _ => a.as_f64().total_cmp(&b.as_f64()),
+        },
+        (JsonValue::Array(a), JsonValue::Array(b)) => a
+            .iter()
+            .zip(b)
+            .map(|(a, b)| compare_values(a, b))
+            .find(|ordering| ordering.is_ne())
+            .unwrap_or_else(|| a.len().cmp(&b.len())),
+        _ if rank(a) == rank(b) && rank(a) == 3 => a.to_str_lossy().cmp(&b.to_str_lossy()),
+        _ if rank(a) == rank(b) && rank(a) == 5 => a.to_json_string().cmp(&b.to_json_string()),
+        _ => rank(a).cmp(&rank(b)),
+    }
+}
+
+/// The value of an integer literal; `None` for numbers written as floats
+fn integer(n: &JsonNumber) -> Option<i128> {
+    if n.is_f64() {
+        return None;
+    }
+    n.as_i64()
+        .map(i128::from)
+        .or_else(|| n.as_u64().map(i128::from))
+}
+
+/// Sorted runs spilled to temporary files, removed when dropped
+#[derive(Default)]
+struct Runs {
+    paths: Vec<PathBuf>,
+}
+
+impl Runs {
+    /// Sort `records` and move them into a new run file
+    ///
+    /// Each line holds the key (compact JSON, empty if missing), a tab and
+    /// the record; compact JSON never contains a raw tab.
+    fn spill(&mut self, records: &mut Vec<Record>, order: KeyOrder) -> CliResult {
+        records.sort_by(|a, b| order.compare(&a.key, &b.key));
+        let (path, file) = create_run_file()?;
+        self.paths.push(path.clone());
+        let write_error = |e: io::Error| format!("Error writing '{}': {}", path.display(), e);
+        let mut file = BufWriter::new(file);
+        for record in records.drain(..) {
+            let key = record
+                .key
+                .map(|key| key.to_json_string())
+                .unwrap_or_default();
+            writeln!(file, "{}\t{}", key, record.line).map_err(write_error)?;
+        }
+        file.flush().map_err(write_error)
+    }
+
+    /// k-way merge of all runs into `out`; ties go to the earlier run, which
+    /// keeps the sort stable
+    fn merge(&self, out: &mut impl Write, order: KeyOrder) -> io::Result<()> {
+        let mut readers = Vec::new();
+        for path in &self.paths {
+            readers.push(BufReader::new(File::open(path)?).lines());
+        }
+        let mut heads = BinaryHeap::new();
+        for (run, reader) in readers.iter_mut().enumerate() {
+            if let Some(head) = Head::read(reader, run, order)? {
+                heads.push(Reverse(head));
+            }
+        }
+        while let Some(Reverse(head)) = heads.pop() {
+            writeln!(out, "{}", head.line)?;
+            if let Some(next) = Head::read(&mut readers[head.run], head.run, order)? {
+                heads.push(Reverse(next));
+            }
+        }
+        out.flush()
+    }
+}
+
+/// Create a new file in the temporary directory
+///
+/// The file must not exist yet: in a shared directory, a file or symlink
+/// planted under the expected name is skipped rather than written through.
+fn create_run_file() -> Result<(PathBuf, File), String> {
+    let mut attempt = 0;
+    loop {
+        let path = std::env::temp_dir().join(format!(
+            "json-parser-sort-{}-{}.ndjson",
+            process::id(),
+            RUN_FILES.fetch_add(1, atomic::Ordering::Relaxed)
+        ));
+        match OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(file) => return Ok((path, file)),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists && attempt < 100 => attempt += 1,
+            Err(e) => return Err(format!("Error creating '{}': {}", path.display(), e)),
+        }
+    }
+}
+
+/// Run files created by this process so far, numbering their names
+static RUN_FILES: AtomicUsize = AtomicUsize::new(0);
+
+impl Drop for Runs {
+    fn drop(&mut self) {
+        for path in &self.paths {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+/// Next record of a run during the merge
+struct Head {
+    key: Option<JsonValue>,
+    line: String,
+    run: usize,
+    order: KeyOrder,
+}
+
+impl Head {
+    fn read(
+        lines: &mut io::Lines<BufReader<File>>,
+        run: usize,
+        order: KeyOrder,
+    ) -> io::Result<Option<Self>> {
+        let Some(line) = lines.next().transpose()? else {
+            return Ok(None);
+        };
+        let (key, line) = line.split_once('\t').unwrap_or(("", &line));
+        let key = if key.is_empty() {
+            None
+        } else {
+            Some(parse_json(key).map_err(io::Error::other)?)
+        };
+        Ok(Some(Self {
+            key,
+            line: line.to_string(),
+            run,
+            order,
+        }))
+    }
+}
+
+impl Ord for Head {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.order
+            .compare(&self.key, &other.key)
+            .then(self.run.cmp(&other.run))
+    }
+}
+
+impl PartialOrd for Head {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Head {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other).is_eq()
+    }
+}
+
+impl Eq for Head {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_order_by_type_then_value() {
+        let keys: Vec<Option<JsonValue>> = [
+            "{}", "[1, 2]", "[1]", "\"b\"", "\"a\"", "10", "2.5", "true", "false", "null",
+        ]
+        .iter()
+        .map(|key| Some(parse_json(key).unwrap()))
+        .chain([None])
+        .collect();
+        let mut sorted = keys.clone();
+        sorted.sort_by(|a, b| KeyOrder { reverse: false }.compare(a, b));
+        let expected: Vec<_> = keys.into_iter().rev().collect();
+        assert_eq!(sorted, expected);
+
+        // Integers beyond 2^53 keep their order
+        let order = KeyOrder { reverse: false };
+        let [a, b, c] = [
+            "1700000000000000001",
+            "1700000000000000002",
+            "18446744073709551615",
+        ]
+        .map(|key| Some(parse_json(key).unwrap()));
+        assert_eq!(order.compare(&a, &b), Ordering::Less);
+        assert_eq!(order.compare(&b, &c), Ordering::Less);
+        let negative = Some(parse_json("-1").unwrap());
+        assert_eq!(order.compare(&negative, &c), Ordering::Less);
+    }
+
+    fn sort_lines(input: &str, chunk: usize, reverse: bool) -> Result<String, String> {
+        let input: Box<dyn BufRead + Send> = Box::new(io::Cursor::new(input.to_string()));
+        let key = JsonPointer::parse("/t").unwrap();
+        let mut out = Vec::new();
+        sort(input, &key, KeyOrder { reverse }, chunk, &mut out)?;
+        Ok(String::from_utf8(out).unwrap())
+    }
+
+    #[test]
+    fn test_merge_of_spilled_runs() {
+        let records: Vec<String> = [5, 3, 9, 1, 3, 7, 2, 8, 3]
+            .iter()
+            .enumerate()
+            .map(|(i, t)| format!("{{\"t\": {}, \"i\": {}}}", t, i))
+            .chain(["{\"i\": 9}".to_string()])
+            .collect();
+        let input = records.join("\n");
+        let order = |output: String| -> Vec<String> {
+            output
+                .lines()
+                .map(|line| parse_json(line).unwrap()["i"].to_string())
+                .collect()
+        };
+
+        // Five runs of two records; equal keys keep their input order
+        let merged = order(sort_lines(&input, 2, false).unwrap());
+        assert_eq!(merged, ["9", "3", "6", "1", "4", "8", "0", "5", "7", "2"]);
+        assert_eq!(order(sort_lines(&input, 100, false).unwrap()), merged);
+        let reversed = order(sort_lines(&input, 3, true).unwrap());
+        assert_eq!(reversed, ["2", "7", "5", "0", "1", "4", "8", "6", "3", "9"]);
+
+        let error = sort_lines("{\"t\": 1}\n{\"t\": 2}\n{\"t\":", 1, false).unwrap_err();
+        assert!(error.starts_with("Line 3:"), "{}", error);
+    }
+}
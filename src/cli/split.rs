@@ -0,0 +1,156 @@
+//! `split <file> --by <n> --out <pattern>`
+//!
+//! Shards a top-level array into files of at most `n` elements each, so the
+//! pieces can be processed in parallel. `{}` in the pattern is replaced by
+//! the shard number, starting at 0. The input is read incrementally and
+//! elements are streamed, so only one of them is in memory at a time; the
+//! written paths are printed one per line.
+
+use std::fs::File;
+use std::io::{self, BufRead, BufWriter, Write};
+
+use json_parser_rs::stream::ElementStream;
+
+use super::{CliResult, open_input, take_option};
+
+const USAGE: &str = "Usage: split <file_path> --by <elements> --out <pattern with {}>";
+
+pub fn run(args: &[String]) -> CliResult {
+    let mut args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let by = take_option(&mut args, "--by")?.ok_or_else(|| USAGE.to_string())?;
+    let by: usize = match by.parse() {
+        Ok(n) if n > 0 => n,
+        _ => return Err(format!("--by must be a positive number, got '{}'", by)),
+    };
+    let pattern = take_option(&mut args, "--out")?.ok_or_else(|| USAGE.to_string())?;
+    if !pattern.contains("{}") {
+        return Err(format!("Output pattern '{}' must contain {{}}", pattern));
+    }
+    let [file_path] = args[..] else {
+        return Err(USAGE.to_string());
+    };
+
+    let input = open_input(file_path)?;
+    split(input, by, &pattern, &mut io::stdout().lock())
+}
+
+/// Write the elements of the array in `input` to shards of `by` elements
+/// named after `pattern`, listing each path in `paths` once it is complete
+fn split(
+    input: Box<dyn BufRead + Send>,
+    by: usize,
+    pattern: &str,
+    paths: &mut impl Write,
+) -> CliResult {
+    let stream = ElementStream::from_reader(input).map_err(|e| e.to_string())?;
+    if stream.is_object() {
+        return Err("split needs a top-level array".to_string());
+    }
+
+    let mut shard: Option<(String, BufWriter<File>)> = None;
+    let mut shards = 0;
+    let mut in_shard = 0;
+    for element in stream {
+        let element = element.map_err(|e| e.to_string())?;
+        let (path, out) = match &mut shard {
+            Some(open) => open,
+            None => {
+                let path = pattern.replace("{}", &shards.to_string());
+                let file = File::create(&path)
+                    .map_err(|e| format!("Error creating file '{}': {}", path, e))?;
+                shards += 1;
+                shard.insert((path, BufWriter::new(file)))
+            }
+        };
+        let separator: &[u8] = if in_shard == 0 { b"[" } else { b", " };
+        out.write_all(separator)
+            .and_then(|()| element.value.write_json(out))
+            .map_err(|e| write_error(path, e))?;
+        in_shard += 1;
+        if in_shard == by {
+            close(shard.take(), paths)?;
+            in_shard = 0;
+        }
+    }
+    close(shard, paths)
+}
+
+/// Terminate the array of a finished shard and report its path
+fn close(shard: Option<(String, BufWriter<File>)>, paths: &mut impl Write) -> CliResult {
+    let Some((path, mut out)) = shard else {
+        return Ok(());
+    };
+    writeln!(out, "]")
+        .and_then(|()| out.flush())
+        .map_err(|e| write_error(&path, e))?;
+    writeln!(paths, "{}", path).map_err(|e| format!("Error writing output: {}", e))
+}
+
+fn write_error(path: &str, error: io::Error) -> String {
+    format!("Error writing file '{}': {}", path, error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    /// Split `input` into a fresh directory; the listed paths and the
+    /// contents of the shards
+    fn split_into_dir(name: &str, input: &str, by: usize) -> (String, Vec<String>) {
+        let dir = std::env::temp_dir().join(format!("json-parser-{}-{}", name, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let pattern = dir.join("part-{}.json").to_string_lossy().into_owned();
+        let input: Box<dyn BufRead + Send> = Box::new(io::Cursor::new(input.to_string()));
+        let mut listing = Vec::new();
+        split(input, by, &pattern, &mut listing).unwrap();
+        let listing = String::from_utf8(listing).unwrap();
+        let shards = listing
+            .lines()
+            .map(|path| fs::read_to_string(path).unwrap())
+            .collect();
+        let files = fs::read_dir(&dir).unwrap().count();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(files, listing.lines().count());
+        (listing, shards)
+    }
+
+    #[test]
+    fn test_shard_boundaries() {
+        let (listing, shards) = split_into_dir("split-uneven", "[1, {\"a\": [2]}, \"3\", 4, 5]", 2);
+        assert_eq!(shards, ["[1, {\"a\": [2]}]\n", "[\"3\", 4]\n", "[5]\n"]);
+        assert!(listing.lines().next().unwrap().ends_with("part-0.json"));
+        assert!(listing.lines().last().unwrap().ends_with("part-2.json"));
+
+        // A full last shard is not followed by an empty one
+        let (_, shards) = split_into_dir("split-even", "[1, 2, 3, 4]", 2);
+        assert_eq!(shards, ["[1, 2]\n", "[3, 4]\n"]);
+        let (_, shards) = split_into_dir("split-one", "[1, 2]", 5);
+        assert_eq!(shards, ["[1, 2]\n"]);
+    }
+
+    #[test]
+    fn test_empty_array_writes_no_shards() {
+        let (listing, shards) = split_into_dir("split-empty", " [ ] ", 3);
+        assert_eq!(listing, "");
+        assert!(shards.is_empty());
+    }
+
+    #[test]
+    fn test_invalid_arguments_and_input() {
+        let args = |args: &[&str]| run(&args.iter().map(|a| a.to_string()).collect::<Vec<_>>());
+        assert_eq!(
+            args(&["in.json", "--by", "2", "--out", "part.json"]),
+            Err("Output pattern 'part.json' must contain {}".to_string())
+        );
+        assert!(args(&["in.json", "--by", "0", "--out", "{}.json"]).is_err());
+        assert_eq!(
+            args(&["in.json", "--out", "{}.json"]),
+            Err(USAGE.to_string())
+        );
+
+        let input: Box<dyn BufRead + Send> = Box::new(io::Cursor::new("{\"a\": 1}"));
+        let error = split(input, 1, "unused-{}.json", &mut Vec::new()).unwrap_err();
+        assert_eq!(error, "split needs a top-level array");
+    }
+}
@@ -0,0 +1,153 @@
+//! `stats [<file>|-] [--output human|json]`
+//!
+//! Prints the shape of a document: value counts per type, nesting depth,
+//! the minified size of each top-level member and how fast the input
+//! parsed. `json` output is a single object for scripts and dashboards.
+
+use std::io::{self, Write};
+use std::time::Instant;
+
+use json_parser_rs::{JsonValue, ValueStats, json, parse_json};
+
+use super::{CliResult, read_file, take_option};
+
+const USAGE: &str = "Usage: stats [<file_path>|-] [--output human|json]";
+
+pub fn run(args: &[String]) -> CliResult {
+    let mut args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let as_json = match take_option(&mut args, "--output")?.as_deref() {
+        None | Some("human") => false,
+        Some("json") => true,
+        Some(other) => return Err(format!("Unknown output format '{}'\n{}", other, USAGE)),
+    };
+    let file_path = match args[..] {
+        [] => "-",
+        [path] => path,
+        _ => return Err(USAGE.to_string()),
+    };
+
+    let content = read_file(file_path)?;
+    let started = Instant::now();
+    let value = parse_json(&content).map_err(|e| e.display_with_source(&content).to_string())?;
+    let seconds = started.elapsed().as_secs_f64();
+    let stats = value.stats();
+
+    let mut out = io::stdout().lock();
+    let written = if as_json {
+        writeln!(
+            out,
+            "{}",
+            to_json(&stats, content.len(), seconds).to_pretty_string()
+        )
+    } else {
+        write_human(&mut out, &stats, content.len(), seconds)
+    };
+    written.map_err(|e| format!("Error writing output: {}", e))
+}
+
+fn write_human(
+    out: &mut impl Write,
+    stats: &ValueStats,
+    bytes: usize,
+    seconds: f64,
+) -> io::Result<()> {
+    writeln!(
+        out,
+        "size:      {} ({} minified)",
+        human_size(bytes as f64),
+        human_size(stats.size as f64)
+    )?;
+    writeln!(
+        out,
+        "parsed in: {:.3} ms ({}/s)",
+        seconds * 1000.0,
+        human_size(throughput(bytes, seconds))
+    )?;
+    writeln!(out, "nodes:     {}", stats.nodes)?;
+    writeln!(out, "max depth: {}", stats.max_depth)?;
+    writeln!(out, "types:")?;
+    for (name, count) in type_counts(stats) {
+        if count > 0 {
+            writeln!(out, "  {:<10} {}", name, count)?;
+        }
+    }
+    if !stats.member_sizes.is_empty() {
+        writeln!(out, "size by top-level key:")?;
+        let width = stats
+            .member_sizes
+            .iter()
+            .map(|(key, _)| key.chars().count())
+            .max()
+            .unwrap_or(0);
+        for (key, size) in &stats.member_sizes {
+            let share = *size as f64 * 100.0 / stats.size.max(1) as f64;
+            writeln!(
+                out,
+                "  {:<width$}  {:>10}  {:5.1}%",
+                key,
+                human_size(*size as f64),
+                share
+            )?;
+        }
+    }
+    Ok(())
+}
+
+fn to_json(stats: &ValueStats, bytes: usize, seconds: f64) -> JsonValue {
+    let types: JsonValue = type_counts(stats)
+        .into_iter()
+        .map(|(name, count)| (name, JsonValue::from(count as u64)))
+        .collect();
+    let members: JsonValue = stats
+        .member_sizes
+        .iter()
+        .map(|(key, size)| (key.as_str(), JsonValue::from(*size as u64)))
+        .collect();
+    json!({
+        "bytes": bytes as u64,
+        "minified_bytes": stats.size as u64,
+        "parse_seconds": seconds,
+        "bytes_per_second": throughput(bytes, seconds),
+        "nodes": stats.nodes as u64,
+        "max_depth": stats.max_depth as u64,
+        "types": types,
+        "member_sizes": members,
+    })
+}
+
+fn type_counts(stats: &ValueStats) -> [(&'static str, usize); 7] {
+    let types = &stats.types;
+    [
+        ("object", types.object),
+        ("array", types.array),
+        ("string", types.string),
+        ("number", types.number),
+        ("boolean", types.boolean),
+        ("null", types.null),
+        ("extension", types.extension),
+    ]
+}
+
+fn throughput(bytes: usize, seconds: f64) -> f64 {
+    if seconds > 0.0 {
+        bytes as f64 / seconds
+    } else {
+        0.0
+    }
+}
+
+/// `bytes` with a binary unit, e.g. `12.3 KiB`
+fn human_size(bytes: f64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut size = bytes;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", size as u64)
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
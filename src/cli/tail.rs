@@ -0,0 +1,146 @@
+//! `tail [<file>|-] [-n <count>] [--ndjson]`
+//!
+//! Prints the last `count` (10 by default) elements of a top-level array,
+//! or the last records of NDJSON. NDJSON files are read backwards from the
+//! end, so only the requested records are read; arrays and standard input
+//! are streamed, keeping just the last `count` values in memory.
+
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+
+use json_parser_rs::parse_json;
+use json_parser_rs::stream::ElementStream;
+
+use super::head::{parse_args, write_array, write_error};
+use super::{CliResult, ndjson_records, open_input, starts_with_array};
+
+/// Bytes read per step when scanning a file backwards
+const BLOCK_SIZE: u64 = 64 * 1024;
+
+pub fn run(args: &[String]) -> CliResult {
+    let (file_path, count, ndjson) = parse_args(args, "tail")?;
+    let mut input = open_input(file_path)?;
+    let array = !ndjson && starts_with_array(&mut input)?;
+    let mut out = io::stdout().lock();
+
+    if array {
+        let stream = ElementStream::from_reader(input).map_err(|e| e.to_string())?;
+        let mut last = VecDeque::with_capacity(count);
+        for element in stream {
+            let value = element.map_err(|e| e.to_string())?.value;
+            if count > 0 && last.len() == count {
+                last.pop_front();
+            }
+            if count > 0 {
+                last.push_back(value);
+            }
+        }
+        return write_array(&mut out, &last);
+    }
+
+    let lines = if file_path == "-" {
+        let mut last = VecDeque::with_capacity(count);
+        for record in ndjson_records(input) {
+            let (line, _) = record?;
+            if count > 0 && last.len() == count {
+                last.pop_front();
+            }
+            if count > 0 {
+                last.push_back(line);
+            }
+        }
+        Vec::from(last)
+    } else {
+        let mut file = File::open(file_path)
+            .map_err(|e| format!("Error reading file '{}': {}", file_path, e))?;
+        let lines = last_lines(&mut file, count, BLOCK_SIZE)
+            .map_err(|e| format!("Error reading file '{}': {}", file_path, e))?;
+        for line in &lines {
+            parse_json(line).map_err(|e| format!("{}\n  in record: {}", e, line))?;
+        }
+        lines
+    };
+    for line in lines {
+        writeln!(out, "{}", line).map_err(write_error)?;
+    }
+    Ok(())
+}
+
+/// The last `count` non-blank lines of `file`, read backwards from the end
+/// in blocks
+fn last_lines(file: &mut File, count: usize, block_size: u64) -> io::Result<Vec<String>> {
+    let mut position = file.seek(SeekFrom::End(0))?;
+    let mut tail: Vec<u8> = Vec::new();
+    loop {
+        // The first line may be cut off unless the start of the file has
+        // been reached, so it does not count
+        let complete = tail.split(|&b| b == b'\n').skip(usize::from(position > 0));
+        let found = complete
+            .filter(|line| !line.trim_ascii().is_empty())
+            .count();
+        if found >= count || position == 0 {
+            break;
+        }
+        let step = block_size.min(position);
+        position -= step;
+        file.seek(SeekFrom::Start(position))?;
+        let mut block = vec![0; step as usize];
+        file.read_exact(&mut block)?;
+        block.extend_from_slice(&tail);
+        tail = block;
+    }
+
+    // Split before decoding: a block boundary may fall inside a multibyte
+    // character of the dropped first line
+    let mut lines: Vec<&[u8]> = tail
+        .split(|&b| b == b'\n')
+        .skip(usize::from(position > 0))
+        .filter(|line| !line.trim_ascii().is_empty())
+        .collect();
+    let skip = lines.len().saturating_sub(count);
+    lines.drain(..skip);
+    lines
+        .into_iter()
+        .map(|line| {
+            let line = line.strip_suffix(b"\r").unwrap_or(line);
+            String::from_utf8(line.to_vec())
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_last_lines_across_blocks() {
+        let path =
+            std::env::temp_dir().join(format!("json-parser-tail-{}.ndjson", std::process::id()));
+        std::fs::write(&path, "{\"a\": 1}\n\n[2]\r\n\"three\"\n4\n\n").unwrap();
+        let mut file = File::open(&path).unwrap();
+        let last = last_lines(&mut file, 3, 4).unwrap();
+        let all = last_lines(&mut file, 10, 4).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(last, ["[2]", "\"three\"", "4"]);
+        assert_eq!(all, ["{\"a\": 1}", "[2]", "\"three\"", "4"]);
+    }
+
+    #[test]
+    fn test_block_boundary_inside_a_character() {
+        let path = std::env::temp_dir().join(format!(
+            "json-parser-tail-utf8-{}.ndjson",
+            std::process::id()
+        ));
+        // "é" is two bytes; with blocks of 5 bytes, the second and third
+        // reads start in the middle of one
+        std::fs::write(&path, "\"éééé\"\n\"éé\"\n1\n22\n").unwrap();
+        let mut file = File::open(&path).unwrap();
+        let last = last_lines(&mut file, 2, 5);
+        let more = last_lines(&mut file, 3, 5);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(last.unwrap(), ["1", "22"]);
+        assert_eq!(more.unwrap(), ["\"éé\"", "1", "22"]);
+    }
+}
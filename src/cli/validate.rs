@@ -0,0 +1,354 @@
+//! `[--ndjson] [<file>|-]... [--output human|json]`, the default mode
+//! without a subcommand
+//!
+//! Validates each input and prints one line per file: `OK`, or the parse
+//! errors with their source snippets on stderr. Without a file, or with
+//! `-`, standard input is read. Arguments with `*` or `?` that name no
+//! existing file are expanded like shell globs, for shells (and hook
+//! runners) that pass them through unexpanded. The exit code is the
+//! number of files that failed, capped at [`MAX_EXIT_CODE`], so scripts
+//! can both test for success and tell how bad it is.
+//!
+//! `--output json` writes one object to stdout instead, for CI systems and
+//! editor plugins: `files` holds `{file, valid, error}` per input, with
+//! `error` giving the `line`, `column`, `kind` and `message` of the first
+//! error, and `summary` the counts. With `--ndjson`, every invalid record
+//! is listed under `errors` as well.
+
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+
+use json_parser_rs::{ErrorKind, JsonValue, ParseError, json, parse_json, parse_ndjson};
+
+use super::{read_file, take_flag, take_option};
+
+/// Exit codes above 125 have special meanings in POSIX shells
+const MAX_EXIT_CODE: usize = 125;
+
+/// Outcome for one input
+struct FileReport {
+    name: String,
+    /// Why the input could not be read
+    read_error: Option<String>,
+    /// Number of records, for NDJSON
+    records: Option<usize>,
+    errors: Vec<ParseError>,
+}
+
+impl FileReport {
+    fn is_valid(&self) -> bool {
+        self.read_error.is_none() && self.errors.is_empty()
+    }
+}
+
+/// Validate the inputs named in `args` and return the exit code, or `None`
+/// if `args` holds an unknown option
+pub fn run(args: &[String]) -> Option<i32> {
+    let mut args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let ndjson = take_flag(&mut args, "--ndjson");
+    let as_json = match take_option(&mut args, "--output").ok()?.as_deref() {
+        None | Some("human") => false,
+        Some("json") => true,
+        Some(_) => return None,
+    };
+    if args.iter().any(|arg| arg.starts_with('-') && *arg != "-") {
+        return None;
+    }
+    if args.is_empty() {
+        args.push("-");
+    }
+
+    let mut reports = Vec::new();
+    for arg in args {
+        match expand_glob(arg) {
+            Ok(paths) => {
+                for path in paths {
+                    let report = validate_file(&path, ndjson, !as_json);
+                    reports.push(report);
+                }
+            }
+            // Patterns matching no file count as failures too
+            Err(message) => {
+                if !as_json {
+                    eprintln!("{}", message);
+                }
+                reports.push(FileReport {
+                    name: arg.to_string(),
+                    read_error: Some(message),
+                    records: None,
+                    errors: Vec::new(),
+                });
+            }
+        }
+    }
+
+    let invalid = reports.iter().filter(|report| !report.is_valid()).count();
+    if as_json {
+        let written = writeln!(
+            io::stdout().lock(),
+            "{}",
+            to_json(&reports, invalid).to_pretty_string()
+        );
+        if let Err(e) = written {
+            eprintln!("Error writing output: {}", e);
+            return Some(1);
+        }
+    } else if reports.len() > 1 {
+        println!(
+            "{} files checked, {} valid, {} invalid",
+            reports.len(),
+            reports.len() - invalid,
+            invalid
+        );
+    }
+    Some(invalid.min(MAX_EXIT_CODE) as i32)
+}
+
+/// Check one input; with `print`, its result line and errors are printed
+/// as they are found
+fn validate_file(path: &str, ndjson: bool, print: bool) -> FileReport {
+    let name = if path == "-" { "<stdin>" } else { path };
+    let mut report = FileReport {
+        name: name.to_string(),
+        read_error: None,
+        records: None,
+        errors: Vec::new(),
+    };
+    let content = match read_file(path) {
+        Ok(content) => content,
+        Err(message) => {
+            if print {
+                eprintln!("{}", message);
+            }
+            report.read_error = Some(message);
+            return report;
+        }
+    };
+
+    if ndjson {
+        let mut records = 0;
+        for result in parse_ndjson(&content) {
+            records += 1;
+            if let Err(e) = result {
+                if print {
+                    eprintln!("{}: {}", name, e.display_with_source(&content));
+                }
+                report.errors.push(e);
+            }
+        }
+        report.records = Some(records);
+        if print {
+            match report.errors.len() {
+                0 => println!("{}: OK ({} records)", name, records),
+                invalid => eprintln!("{}: {} of {} records invalid", name, invalid, records),
+            }
+        }
+        return report;
+    }
+
+    match parse_json(&content) {
+        Ok(_) if print => println!("{}: OK", name),
+        Ok(_) => {}
+        Err(e) => {
+            if print {
+                eprintln!("{}: {}", name, e.display_with_source(&content));
+            }
+            report.errors.push(e);
+        }
+    }
+    report
+}
+
+fn to_json(reports: &[FileReport], invalid: usize) -> JsonValue {
+    let files: JsonValue = reports
+        .iter()
+        .map(|report| {
+            let mut file = json!({"file": report.name.as_str(), "valid": report.is_valid()});
+            if let Some(message) = &report.read_error {
+                file["error"] =
+                    json!({"kind": ErrorKind::Io.as_str(), "message": message.as_str()});
+            } else if let Some(error) = report.errors.first() {
+                file["error"] = error_to_json(error);
+            }
+            if let Some(records) = report.records {
+                file["records"] = JsonValue::from(records as u64);
+                file["errors"] = report.errors.iter().map(error_to_json).collect();
+            }
+            file
+        })
+        .collect();
+    json!({
+        "files": files,
+        "summary": {
+            "files": reports.len() as u64,
+            "valid": (reports.len() - invalid) as u64,
+            "invalid": invalid as u64,
+        },
+    })
+}
+
+fn error_to_json(error: &ParseError) -> JsonValue {
+    json!({
+        "line": error.line as u64,
+        "column": error.column as u64,
+        "kind": error.kind().as_str(),
+        "message": error.message.as_str(),
+    })
+}
+
+/// Paths matching `pattern`, sorted; `pattern` itself if it has no
+/// wildcards or names an existing file
+fn expand_glob(pattern: &str) -> Result<Vec<String>, String> {
+    if !pattern.contains(['*', '?']) || Path::new(pattern).exists() {
+        return Ok(vec![pattern.to_string()]);
+    }
+    let (mut paths, components) = match pattern.strip_prefix('/') {
+        Some(rest) => (vec!["/".to_string()], rest),
+        None => (vec![String::new()], pattern),
+    };
+    for component in components.split('/').filter(|c| !c.is_empty()) {
+        let mut next = Vec::new();
+        for base in &paths {
+            if !component.contains(['*', '?']) {
+                next.push(join(base, component));
+                continue;
+            }
+            let dir = if base.is_empty() { "." } else { base.as_str() };
+            let Ok(entries) = fs::read_dir(dir) else {
+                continue;
+            };
+            for entry in entries.flatten() {
+                let name = entry.file_name().to_string_lossy().into_owned();
+                // Like shells, wildcards do not match hidden files
+                if !name.starts_with('.') && wildcard_matches(component, &name) {
+                    next.push(join(base, &name));
+                }
+            }
+        }
+        paths = next;
+    }
+    paths.retain(|path| Path::new(path).is_file());
+    paths.sort();
+    if paths.is_empty() {
+        return Err(format!("No files match '{}'", pattern));
+    }
+    Ok(paths)
+}
+
+fn join(base: &str, name: &str) -> String {
+    match base {
+        "" => name.to_string(),
+        "/" => format!("/{}", name),
+        _ => format!("{}/{}", base, name),
+    }
+}
+
+/// Whether `name` matches `pattern`, where `*` stands for any run of
+/// characters and `?` for exactly one
+fn wildcard_matches(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    // Position after the last `*` and the name position it was tried at
+    let mut star: Option<(usize, usize)> = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p + 1, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                // Let the last `*` swallow one more character
+                Some((after, tried)) => {
+                    star = Some((after, tried + 1));
+                    p = after;
+                    n = tried + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wildcard_matches() {
+        assert!(wildcard_matches("*.json", "a.json"));
+        assert!(wildcard_matches("*.json", ".json"));
+        assert!(wildcard_matches("a?c*", "abc"));
+        assert!(wildcard_matches("*a*b", "xaayb"));
+        assert!(!wildcard_matches("*.json", "a.jsonl"));
+        assert!(!wildcard_matches("a?c", "ac"));
+    }
+
+    #[test]
+    fn test_json_report() {
+        let path =
+            std::env::temp_dir().join(format!("json-parser-report-{}.ndjson", std::process::id()));
+        fs::write(&path, "{\"a\": 1}\n[1,]\n2\n").unwrap();
+        let path = path.to_string_lossy().into_owned();
+        let ndjson = validate_file(&path, true, false);
+        let json = validate_file(&path, false, false);
+        fs::remove_file(&path).unwrap();
+        let missing = validate_file("missing.json", false, false);
+
+        let report = to_json(&[ndjson, json, missing], 3);
+        let files = report["files"].as_array().unwrap();
+        let error = json!({
+            "line": 2,
+            "column": 4,
+            "kind": "trailing_comma",
+            "message": "Trailing comma not allowed"
+        });
+        assert_eq!(
+            files[0],
+            json!({
+                "file": path.as_str(),
+                "valid": false,
+                "error": error.clone(),
+                "records": 3,
+                "errors": [error]
+            })
+        );
+        assert_eq!(
+            files[1]["error"]["kind"].as_str(),
+            Some("trailing_characters")
+        );
+        assert_eq!(files[2]["error"]["kind"].as_str(), Some("io"));
+        assert_eq!(
+            report["summary"],
+            json!({"files": 3, "valid": 0, "invalid": 3})
+        );
+    }
+
+    #[test]
+    fn test_expand_glob() {
+        let dir = std::env::temp_dir().join(format!("json-parser-glob-{}", std::process::id()));
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        for name in ["b.json", "a.json", ".hidden.json", "c.txt", "sub/d.json"] {
+            fs::write(dir.join(name), "{}").unwrap();
+        }
+        let base = dir.to_string_lossy().into_owned();
+        let top = expand_glob(&format!("{}/*.json", base));
+        let nested = expand_glob(&format!("{}/*/?.json", base));
+        let none = expand_glob(&format!("{}/*.yaml", base));
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(
+            top.unwrap(),
+            [format!("{}/a.json", base), format!("{}/b.json", base)]
+        );
+        assert_eq!(nested.unwrap(), [format!("{}/sub/d.json", base)]);
+        assert!(none.unwrap_err().starts_with("No files match"));
+        assert_eq!(expand_glob("plain.json").unwrap(), ["plain.json"]);
+    }
+}
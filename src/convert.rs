@@ -0,0 +1,392 @@
+//! Format conversion - pluggable encoders and decoders for `JsonValue`
+//!
+//! A [`Format`] turns bytes into a `JsonValue` and back. The built-in
+//! formats cover the JSON family; other crates add their own by
+//! implementing the trait and registering it with a [`FormatRegistry`],
+//! which looks formats up by name or by file extension.
+
+use std::fmt;
+use std::io::Write;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::error::ParseError;
+use crate::json::JsonValue;
+use crate::ndjson::parse_ndjson;
+use crate::options::ParserOptions;
+use crate::parser::Parser;
+
+/// Encoder and decoder between bytes and `JsonValue`
+///
+/// Formats are shared through `Arc`, so they must be `Send + Sync`.
+///
+/// # Examples
+///
+/// ```
+/// use json_parser_rs::convert::{ConvertError, Format, FormatRegistry};
+/// use json_parser_rs::{JsonValue, json};
+/// use std::sync::Arc;
+///
+/// /// One string per line
+/// struct Lines;
+///
+/// impl Format for Lines {
+///     fn name(&self) -> &str {
+///         "lines"
+///     }
+///
+///     fn extensions(&self) -> &[&str] {
+///         &["txt"]
+///     }
+///
+///     fn decode(&self, input: &[u8]) -> Result<JsonValue, ConvertError> {
+///         let text = std::str::from_utf8(input).map_err(ConvertError::decode)?;
+///         Ok(JsonValue::Array(text.lines().map(JsonValue::from).collect()))
+///     }
+///
+///     fn encode(&self, value: &JsonValue) -> Result<Vec<u8>, ConvertError> {
+///         let lines = value.as_array().ok_or(ConvertError::encode("expected an array"))?;
+///         let lines: Option<Vec<&str>> = lines.iter().map(JsonValue::as_str).collect();
+///         let lines = lines.ok_or(ConvertError::encode("expected strings"))?;
+///         Ok(lines.iter().map(|line| format!("{}\n", line)).collect::<String>().into_bytes())
+///     }
+/// }
+///
+/// let mut formats = FormatRegistry::new();
+/// formats.register(Arc::new(Lines));
+/// let lines = formats.for_path("notes.txt").unwrap();
+/// assert_eq!(lines.decode(b"a\nb\n").unwrap(), json!(["a", "b"]));
+/// ```
+pub trait Format: Send + Sync {
+    /// Name used to select the format, e.g. `"json"`
+    fn name(&self) -> &str;
+
+    /// File extensions (without the dot, lowercase) that imply this format
+    fn extensions(&self) -> &[&str] {
+        &[]
+    }
+
+    fn decode(&self, input: &[u8]) -> Result<JsonValue, ConvertError>;
+
+    fn encode(&self, value: &JsonValue) -> Result<Vec<u8>, ConvertError>;
+}
+
+/// Why a conversion failed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConvertError {
+    kind: ConvertErrorKind,
+    message: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ConvertErrorKind {
+    /// The input is not valid in the source format
+    Decode,
+    /// The value cannot be represented in the target format
+    Encode,
+    /// No registered format has the requested name or extension
+    UnknownFormat,
+}
+
+impl ConvertError {
+    pub fn new(kind: ConvertErrorKind, message: impl fmt::Display) -> Self {
+        Self {
+            kind,
+            message: message.to_string(),
+        }
+    }
+
+    pub fn decode(message: impl fmt::Display) -> Self {
+        Self::new(ConvertErrorKind::Decode, message)
+    }
+
+    pub fn encode(message: impl fmt::Display) -> Self {
+        Self::new(ConvertErrorKind::Encode, message)
+    }
+
+    pub fn kind(&self) -> ConvertErrorKind {
+        self.kind
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl fmt::Display for ConvertError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for ConvertError {}
+
+impl From<ParseError> for ConvertError {
+    fn from(error: ParseError) -> Self {
+        Self::decode(error)
+    }
+}
+
+fn utf8(input: &[u8]) -> Result<&str, ConvertError> {
+    std::str::from_utf8(input)
+        .map_err(|e| ConvertError::decode(format!("Input is not UTF-8: {}", e)))
+}
+
+/// Standard JSON, written pretty-printed
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Json;
+
+impl Format for Json {
+    fn name(&self) -> &str {
+        "json"
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["json"]
+    }
+
+    fn decode(&self, input: &[u8]) -> Result<JsonValue, ConvertError> {
+        Ok(crate::parse_json(utf8(input)?)?)
+    }
+
+    fn encode(&self, value: &JsonValue) -> Result<Vec<u8>, ConvertError> {
+        Ok(format!("{}\n", value.to_pretty_string()).into_bytes())
+    }
+}
+
+/// JSON5 and JSON with comments; written as plain pretty JSON, which both accept
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Json5;
+
+impl Format for Json5 {
+    fn name(&self) -> &str {
+        "json5"
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["json5", "jsonc"]
+    }
+
+    fn decode(&self, input: &[u8]) -> Result<JsonValue, ConvertError> {
+        Ok(Parser::with_options(utf8(input)?, ParserOptions::json5())?.parse()?)
+    }
+
+    fn encode(&self, value: &JsonValue) -> Result<Vec<u8>, ConvertError> {
+        Json.encode(value)
+    }
+}
+
+/// Newline-delimited JSON; the records form an array
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Ndjson;
+
+impl Format for Ndjson {
+    fn name(&self) -> &str {
+        "ndjson"
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["ndjson", "jsonl"]
+    }
+
+    fn decode(&self, input: &[u8]) -> Result<JsonValue, ConvertError> {
+        let records = parse_ndjson(utf8(input)?).collect::<Result<Vec<_>, _>>()?;
+        Ok(JsonValue::Array(records))
+    }
+
+    fn encode(&self, value: &JsonValue) -> Result<Vec<u8>, ConvertError> {
+        let records = value.as_array().ok_or(ConvertError::encode(
+            "NDJSON output needs an array of records",
+        ))?;
+        let mut output = Vec::new();
+        for record in records {
+            record
+                .write_json_minified(&mut output)
+                .and_then(|()| output.write_all(b"\n"))
+                .map_err(ConvertError::encode)?;
+        }
+        Ok(output)
+    }
+}
+
+/// Formats available for lookup by name or file extension
+///
+/// Later registrations take precedence, so a registered format may replace
+/// a built-in one with the same name or extension.
+#[derive(Clone)]
+pub struct FormatRegistry {
+    formats: Vec<Arc<dyn Format>>,
+}
+
+impl FormatRegistry {
+    /// Registry holding the built-in formats, including those of enabled
+    /// interop features
+    pub fn new() -> Self {
+        let formats: Vec<Arc<dyn Format>> = vec![
+            Arc::new(Json),
+            Arc::new(Json5),
+            Arc::new(Ndjson),
+            Arc::new(crate::csv::Csv::default()),
+            #[cfg(feature = "msgpack")]
+            Arc::new(crate::binary::msgpack::MessagePack),
+            #[cfg(feature = "cbor")]
+            Arc::new(crate::binary::cbor::Cbor),
+            #[cfg(feature = "toml")]
+            Arc::new(crate::interop::toml::Toml),
+            #[cfg(feature = "yaml")]
+            Arc::new(crate::interop::yaml::Yaml),
+        ];
+        Self { formats }
+    }
+
+    /// Registry without any formats
+    pub fn empty() -> Self {
+        Self {
+            formats: Vec::new(),
+        }
+    }
+
+    pub fn register(&mut self, format: Arc<dyn Format>) {
+        self.formats.push(format);
+    }
+
+    /// Format registered under `name`
+    pub fn get(&self, name: &str) -> Result<&dyn Format, ConvertError> {
+        self.formats
+            .iter()
+            .rev()
+            .find(|format| format.name() == name)
+            .map(|format| format.as_ref())
+            .ok_or_else(|| {
+                ConvertError::new(
+                    ConvertErrorKind::UnknownFormat,
+                    format!(
+                        "Unknown format '{}' (known: {})",
+                        name,
+                        self.names().join(", ")
+                    ),
+                )
+            })
+    }
+
+    /// Format implied by the extension of `path` (compared case-insensitively)
+    pub fn for_path(&self, path: impl AsRef<Path>) -> Result<&dyn Format, ConvertError> {
+        let path = path.as_ref();
+        let unknown = || {
+            ConvertError::new(
+                ConvertErrorKind::UnknownFormat,
+                format!(
+                    "Cannot tell the format of '{}' from its extension",
+                    path.display()
+                ),
+            )
+        };
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .ok_or_else(unknown)?
+            .to_ascii_lowercase();
+        self.formats
+            .iter()
+            .rev()
+            .find(|format| format.extensions().contains(&extension.as_str()))
+            .map(|format| format.as_ref())
+            .ok_or_else(unknown)
+    }
+
+    /// Names of the registered formats, in registration order
+    pub fn names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = Vec::new();
+        for format in &self.formats {
+            if !names.contains(&format.name()) {
+                names.push(format.name());
+            }
+        }
+        names
+    }
+
+    /// Decode `input` as `from` and encode the result as `to`
+    pub fn convert(&self, input: &[u8], from: &str, to: &str) -> Result<Vec<u8>, ConvertError> {
+        let value = self.get(from)?.decode(input)?;
+        self.get(to)?.encode(&value)
+    }
+}
+
+impl Default for FormatRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for FormatRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FormatRegistry")
+            .field("formats", &self.names())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json;
+
+    #[test]
+    fn test_builtin_formats() {
+        let formats = FormatRegistry::new();
+        assert_eq!(&formats.names()[..3], ["json", "json5", "ndjson"]);
+        assert_eq!(formats.for_path("a/b.JSONL").unwrap().name(), "ndjson");
+        assert_eq!(
+            formats.for_path("notes.txt").err().map(|e| e.kind()),
+            Some(ConvertErrorKind::UnknownFormat)
+        );
+
+        let output = formats
+            .convert(b"{a: [1, 2,], // c\n}", "json5", "json")
+            .unwrap();
+        assert_eq!(output, b"{\n  \"a\": [\n    1,\n    2\n  ]\n}\n");
+        let output = formats
+            .convert(b"[{\"a\":1},{\"a\":2}]", "json", "ndjson")
+            .unwrap();
+        assert_eq!(output, b"{\"a\":1}\n{\"a\":2}\n");
+        assert_eq!(
+            Ndjson.decode(b"{\"a\":1}\n2\n").unwrap(),
+            json!([{"a": 1}, 2])
+        );
+
+        let error = formats.convert(b"{}", "json", "ndjson").unwrap_err();
+        assert_eq!(error.kind(), ConvertErrorKind::Encode);
+        let error = formats.convert(b"[1,]", "json", "json").unwrap_err();
+        assert_eq!(error.kind(), ConvertErrorKind::Decode);
+    }
+
+    #[test]
+    fn test_registered_format_overrides_builtin() {
+        struct Compact;
+
+        impl Format for Compact {
+            fn name(&self) -> &str {
+                "json"
+            }
+
+            fn extensions(&self) -> &[&str] {
+                &["json"]
+            }
+
+            fn decode(&self, input: &[u8]) -> Result<JsonValue, ConvertError> {
+                Json.decode(input)
+            }
+
+            fn encode(&self, value: &JsonValue) -> Result<Vec<u8>, ConvertError> {
+                Ok(value.to_minified_string().into_bytes())
+            }
+        }
+
+        let mut formats = FormatRegistry::new();
+        formats.register(Arc::new(Compact));
+        assert_eq!(formats.names(), FormatRegistry::new().names());
+        let output = formats.convert(b"[1, 2]", "json5", "json").unwrap();
+        assert_eq!(output, b"[1,2]");
+    }
+}
@@ -0,0 +1,578 @@
+//! CST - a lossless syntax tree for formatters and refactoring tools
+//!
+//! `parse_json` keeps only the data. Tools that rewrite documents also need
+//! to know where every value was written and what surrounded it, so
+//! `parse_json_cst` returns a tree whose nodes carry their byte spans and
+//! the whitespace and comments (trivia) around them. Concatenating the
+//! source text of all spans and trivia reproduces the input exactly.
+
+use std::mem;
+
+use crate::error::{ErrorKind, ParseError};
+use crate::json::JsonValue;
+use crate::lexer::{Lexer, Token};
+use crate::number::JsonNumber;
+use crate::object::{DuplicateKeyPolicy, JsonObject};
+use crate::options::ParserOptions;
+use crate::pointer::{JsonPointer, parse_index};
+
+/// Byte range `start..end` of the input
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+
+    /// Whether `offset` lies within the span
+    pub fn contains(&self, offset: usize) -> bool {
+        (self.start..self.end).contains(&offset)
+    }
+
+    /// The spanned source text
+    ///
+    /// # Panics
+    ///
+    /// Panics if the span does not fit `input`.
+    pub fn text<'a>(&self, input: &'a str) -> &'a str {
+        &input[self.start..self.end]
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum TriviaKind {
+    /// A run of whitespace, including line breaks
+    Whitespace,
+    /// `// ...` up to (not including) the line break
+    LineComment,
+    /// `/* ... */`
+    BlockComment,
+}
+
+/// Whitespace or a comment between tokens
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Trivia {
+    pub kind: TriviaKind,
+    pub span: Span,
+}
+
+impl Trivia {
+    pub fn is_comment(&self) -> bool {
+        self.kind != TriviaKind::Whitespace
+    }
+}
+
+/// A value in the syntax tree
+///
+/// `leading` holds the trivia right before the value, `trailing` the trivia
+/// after it up to the next `,`, closing bracket or the end of the input.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CstNode {
+    pub kind: CstKind,
+    /// Source range of the value itself, brackets included
+    pub span: Span,
+    pub leading: Vec<Trivia>,
+    pub trailing: Vec<Trivia>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum CstKind {
+    Null,
+    Boolean(bool),
+    Number(JsonNumber),
+    String(String),
+    /// String with unpaired surrogates, see `JsonValue::RawString`
+    RawString(String),
+    /// `dangling` is trivia not attached to an element: the contents of an
+    /// empty array, or what follows a trailing comma
+    Array {
+        elements: Vec<CstNode>,
+        trailing_comma: bool,
+        dangling: Vec<Trivia>,
+    },
+    /// Members in source order, duplicates included
+    Object {
+        members: Vec<CstMember>,
+        trailing_comma: bool,
+        dangling: Vec<Trivia>,
+    },
+}
+
+/// An object member
+#[derive(Debug, Clone, PartialEq)]
+pub struct CstMember {
+    pub key: String,
+    /// Source range of the key, quotes included
+    pub key_span: Span,
+    /// Trivia before the key
+    pub leading: Vec<Trivia>,
+    /// Trivia between the key and the colon
+    pub key_trailing: Vec<Trivia>,
+    /// The value; its `leading` trivia follows the colon
+    pub value: CstNode,
+}
+
+impl CstNode {
+    /// The plain value, as `parse_json` returns it; of duplicate keys the
+    /// last one wins
+    pub fn to_value(&self) -> JsonValue {
+        match &self.kind {
+            CstKind::Null => JsonValue::Null,
+            CstKind::Boolean(b) => JsonValue::Boolean(*b),
+            CstKind::Number(n) => JsonValue::Number(*n),
+            CstKind::String(s) => JsonValue::String(s.clone()),
+            CstKind::RawString(raw) => JsonValue::RawString(raw.clone()),
+            CstKind::Array { elements, .. } => {
+                JsonValue::Array(elements.iter().map(CstNode::to_value).collect())
+            }
+            CstKind::Object { members, .. } => {
+                let mut object = JsonObject::new();
+                for member in members {
+                    object.insert(member.key.clone(), member.value.to_value());
+                }
+                JsonValue::Object(object)
+            }
+        }
+    }
+
+    /// Resolve an RFC 6901 JSON Pointer to the node it names
+    ///
+    /// With duplicate keys, the last member wins. Returns `None` if the
+    /// pointer is malformed or does not resolve.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use json_parser_rs::{ParserOptions, parse_json_cst};
+    ///
+    /// let input = "{\n  // port to listen on\n  \"port\": 8080\n}";
+    /// let root = parse_json_cst(input, ParserOptions::default().allow_comments(true)).unwrap();
+    /// let port = root.pointer("/port").unwrap();
+    /// assert_eq!(port.span.text(input), "8080");
+    /// ```
+    pub fn pointer(&self, pointer: &str) -> Option<&CstNode> {
+        let pointer = JsonPointer::parse(pointer).ok()?;
+        let mut current = self;
+        for token in pointer.tokens() {
+            current = match &current.kind {
+                CstKind::Object { members, .. } => {
+                    &members.iter().rfind(|member| member.key == *token)?.value
+                }
+                CstKind::Array { elements, .. } => elements.get(parse_index(token)?)?,
+                _ => return None,
+            };
+        }
+        Some(current)
+    }
+
+    /// The innermost node whose span contains `offset`, e.g. the value under
+    /// an editor's cursor
+    pub fn node_at(&self, offset: usize) -> Option<&CstNode> {
+        if !self.span.contains(offset) {
+            return None;
+        }
+        let inner = match &self.kind {
+            CstKind::Array { elements, .. } => {
+                elements.iter().find_map(|element| element.node_at(offset))
+            }
+            CstKind::Object { members, .. } => members
+                .iter()
+                .find_map(|member| member.value.node_at(offset)),
+            _ => None,
+        };
+        Some(inner.unwrap_or(self))
+    }
+}
+
+/// Parse `input` into a syntax tree that keeps spans, whitespace and comments
+///
+/// Accepts exactly what `Parser::with_options` accepts with the same
+/// options, including its error messages and kinds. Of the limits, only
+/// `max_depth` and `max_input_size` apply; duplicate keys are kept as
+/// written unless `DuplicateKeyPolicy::Error` is set.
+///
+/// # Examples
+///
+/// ```
+/// use json_parser_rs::cst::{CstKind, TriviaKind};
+/// use json_parser_rs::{ParserOptions, parse_json_cst};
+///
+/// let input = "[1, /* two */ 2]";
+/// let root = parse_json_cst(input, ParserOptions::relaxed()).unwrap();
+/// let CstKind::Array { elements, .. } = &root.kind else { unreachable!() };
+/// assert_eq!(elements[1].span.text(input), "2");
+/// let comment = elements[1].leading[1];
+/// assert_eq!(comment.kind, TriviaKind::BlockComment);
+/// assert_eq!(comment.span.text(input), "/* two */");
+/// ```
+pub fn parse_json_cst(input: &str, options: ParserOptions) -> Result<CstNode, ParseError> {
+    if let Some(max) = options.max_input_size
+        && input.len() > max
+    {
+        return Err(ParseError::new(
+            format!(
+                "Input size of {} bytes exceeds the limit of {}",
+                input.len(),
+                max
+            ),
+            0,
+            input,
+        )
+        .with_kind(ErrorKind::InputTooLarge));
+    }
+
+    let mut parser = CstParser {
+        lexer: Lexer::with_options(input, &options),
+        current: Token::Eof,
+        span: Span::default(),
+        leading: Vec::new(),
+        options,
+        depth: 0,
+    };
+    parser.advance()?;
+    let mut root = parser.parse_value()?;
+    if parser.current != Token::Eof {
+        return Err(parser
+            .error(format!(
+                "Unexpected token after JSON value: {}",
+                parser.current
+            ))
+            .with_kind(ErrorKind::TrailingCharacters));
+    }
+    root.trailing = mem::take(&mut parser.leading);
+    Ok(root)
+}
+
+/// Recursive descent over the lexer's tokens, keeping the trivia in front
+/// of the current token until a node claims it
+struct CstParser<'a> {
+    lexer: Lexer<'a>,
+    current: Token,
+    span: Span,
+    /// Trivia before the current token
+    leading: Vec<Trivia>,
+    options: ParserOptions,
+    depth: usize,
+}
+
+impl<'a> CstParser<'a> {
+    /// Move to the next token; the trivia of the current one must have been
+    /// taken already
+    fn advance(&mut self) -> Result<(), ParseError> {
+        self.current = self.lexer.next_token_with_trivia(&mut self.leading)?;
+        self.span = Span {
+            start: self.lexer.token_start(),
+            end: self.lexer.offset(),
+        };
+        Ok(())
+    }
+
+    fn take_leading(&mut self) -> Vec<Trivia> {
+        mem::take(&mut self.leading)
+    }
+
+    fn error(&self, message: impl Into<String>) -> ParseError {
+        ParseError::new(message, self.span.start, self.lexer.input())
+    }
+
+    fn unexpected(&self, message: impl Into<String>) -> ParseError {
+        self.error(message)
+            .with_kind(ErrorKind::unexpected(&self.current))
+    }
+
+    fn enter_nested(&mut self) -> Result<(), ParseError> {
+        self.depth += 1;
+        match self.options.max_depth {
+            Some(max) if self.depth > max => Err(self
+                .error(format!("Maximum nesting depth of {} exceeded", max))
+                .with_kind(ErrorKind::DepthLimitExceeded)),
+            _ => Ok(()),
+        }
+    }
+
+    fn parse_value(&mut self) -> Result<CstNode, ParseError> {
+        let leading = self.take_leading();
+        let start = self.span.start;
+        let kind = match &self.current {
+            Token::String(s) => CstKind::String(s.clone()),
+            Token::RawString(raw) => CstKind::RawString(raw.clone()),
+            Token::Number(n) => CstKind::Number(*n),
+            Token::Boolean(b) => CstKind::Boolean(*b),
+            Token::Null => CstKind::Null,
+            Token::LeftBrace | Token::LeftBracket => {
+                self.enter_nested()?;
+                let kind = if self.current == Token::LeftBrace {
+                    self.parse_object()?
+                } else {
+                    self.parse_array()?
+                };
+                self.depth -= 1;
+                kind
+            }
+            token => return Err(self.unexpected(format!("Unexpected token: {}", token))),
+        };
+        // Containers end on their closing bracket, which is still current
+        let end = self.span.end;
+        self.advance()?;
+        Ok(CstNode {
+            kind,
+            span: Span { start, end },
+            leading,
+            trailing: Vec::new(),
+        })
+    }
+
+    /// Parse the members of the object opened by the current `{`, stopping
+    /// on its `}`
+    fn parse_object(&mut self) -> Result<CstKind, ParseError> {
+        self.advance()?;
+        let mut members: Vec<CstMember> = Vec::new();
+        loop {
+            if self.current == Token::RightBrace {
+                return Ok(CstKind::Object {
+                    trailing_comma: !members.is_empty(),
+                    members,
+                    dangling: self.take_leading(),
+                });
+            }
+            let leading = self.take_leading();
+            let key_span = self.span;
+            let key = self.parse_key()?;
+            if self.options.duplicate_keys == DuplicateKeyPolicy::Error
+                && members.iter().any(|member| member.key == key)
+            {
+                return Err(ParseError::new(
+                    format!("Duplicate key: \"{}\"", key),
+                    key_span.start,
+                    self.lexer.input(),
+                )
+                .with_kind(ErrorKind::DuplicateKey));
+            }
+            self.advance()?;
+            let key_trailing = self.take_leading();
+            if self.current != Token::Colon {
+                return Err(self.unexpected(format!(
+                    "Expected {}, found {}",
+                    Token::Colon,
+                    self.current
+                )));
+            }
+            self.advance()?;
+            let mut value = self.parse_value()?;
+            value.trailing = self.take_leading();
+            members.push(CstMember {
+                key,
+                key_span,
+                leading,
+                key_trailing,
+                value,
+            });
+
+            match self.current {
+                Token::Comma => {
+                    self.advance()?;
+                    if self.current == Token::RightBrace && !self.options.allow_trailing_commas {
+                        return Err(self
+                            .error("Trailing comma not allowed")
+                            .with_kind(ErrorKind::TrailingComma));
+                    }
+                }
+                Token::RightBrace => {
+                    return Ok(CstKind::Object {
+                        members,
+                        trailing_comma: false,
+                        dangling: Vec::new(),
+                    });
+                }
+                _ => {
+                    return Err(
+                        self.unexpected(format!("Expected ',' or '}}', found {}", self.current))
+                    );
+                }
+            }
+        }
+    }
+
+    /// The current token as an object key, mirroring `Parser::parse_key`
+    fn parse_key(&self) -> Result<String, ParseError> {
+        let unquoted = self.options.allow_unquoted_keys;
+        match &self.current {
+            Token::String(s) => Ok(s.clone()),
+            Token::Identifier(name) if unquoted => Ok(name.clone()),
+            Token::Boolean(b) if unquoted => Ok(b.to_string()),
+            Token::Null if unquoted => Ok("null".to_string()),
+            Token::RawString(_) => Err(self
+                .error("Unpaired surrogate in object key")
+                .with_kind(ErrorKind::InvalidEscape)),
+            Token::Eof => Err(self
+                .error("Object key must be a string")
+                .with_kind(ErrorKind::UnexpectedEof)),
+            _ => Err(self
+                .error("Object key must be a string")
+                .with_kind(ErrorKind::KeyMustBeString)),
+        }
+    }
+
+    /// Parse the elements of the array opened by the current `[`, stopping
+    /// on its `]`
+    fn parse_array(&mut self) -> Result<CstKind, ParseError> {
+        self.advance()?;
+        let mut elements = Vec::new();
+        loop {
+            if self.current == Token::RightBracket {
+                return Ok(CstKind::Array {
+                    trailing_comma: !elements.is_empty(),
+                    elements,
+                    dangling: self.take_leading(),
+                });
+            }
+
+            let mut element = self.parse_value()?;
+            element.trailing = self.take_leading();
+            elements.push(element);
+
+            match self.current {
+                Token::Comma => {
+                    self.advance()?;
+                    if self.current == Token::RightBracket && !self.options.allow_trailing_commas {
+                        return Err(self
+                            .error("Trailing comma not allowed")
+                            .with_kind(ErrorKind::TrailingComma));
+                    }
+                }
+                Token::RightBracket => {
+                    return Ok(CstKind::Array {
+                        elements,
+                        trailing_comma: false,
+                        dangling: Vec::new(),
+                    });
+                }
+                _ => {
+                    return Err(
+                        self.unexpected(format!("Expected ',' or ']', found {}", self.current))
+                    );
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_json;
+
+    /// Rebuild the source from the tree: every byte is covered by exactly
+    /// one span or trivia
+    fn rebuild(node: &CstNode, input: &str, out: &mut String) {
+        let trivia = |list: &[Trivia], out: &mut String| {
+            for t in list {
+                out.push_str(t.span.text(input));
+            }
+        };
+        trivia(&node.leading, out);
+        match &node.kind {
+            CstKind::Array {
+                elements,
+                trailing_comma,
+                dangling,
+            } => {
+                out.push('[');
+                for (i, element) in elements.iter().enumerate() {
+                    if i > 0 {
+                        out.push(',');
+                    }
+                    rebuild(element, input, out);
+                }
+                if *trailing_comma {
+                    out.push(',');
+                }
+                trivia(dangling, out);
+                out.push(']');
+            }
+            CstKind::Object {
+                members,
+                trailing_comma,
+                dangling,
+            } => {
+                out.push('{');
+                for (i, member) in members.iter().enumerate() {
+                    if i > 0 {
+                        out.push(',');
+                    }
+                    trivia(&member.leading, out);
+                    out.push_str(member.key_span.text(input));
+                    trivia(&member.key_trailing, out);
+                    out.push(':');
+                    rebuild(&member.value, input, out);
+                }
+                if *trailing_comma {
+                    out.push(',');
+                }
+                trivia(dangling, out);
+                out.push('}');
+            }
+            _ => out.push_str(node.span.text(input)),
+        }
+        trivia(&node.trailing, out);
+    }
+
+    #[test]
+    fn test_lossless_round_trip() {
+        let input = "// config\n{\n  \"a\" /* key */ : [1, 2, /* end */],\n  b: [3,],\n  \"c\": { /* empty */ }\n}\n";
+        let root = parse_json_cst(input, ParserOptions::json5()).unwrap();
+        let mut rebuilt = String::new();
+        rebuild(&root, input, &mut rebuilt);
+        assert_eq!(rebuilt, input);
+        assert_eq!(
+            root.span,
+            Span {
+                start: 10,
+                end: input.len() - 1
+            }
+        );
+        assert_eq!(root.leading[0].kind, TriviaKind::LineComment);
+        assert_eq!(
+            root.to_value(),
+            parse_json(r#"{"a": [1, 2], "b": [3], "c": {}}"#).unwrap()
+        );
+
+        let CstKind::Object { members, .. } = &root.kind else {
+            unreachable!()
+        };
+        assert_eq!(members[0].key_span.text(input), "\"a\"");
+        assert_eq!(members[0].key_trailing[1].span.text(input), "/* key */");
+        let CstKind::Array { dangling, .. } = &members[0].value.kind else {
+            unreachable!()
+        };
+        assert!(dangling.iter().any(Trivia::is_comment));
+    }
+
+    #[test]
+    fn test_node_lookup_and_errors() {
+        let input = r#"{"list": [10, {"x": true}]}"#;
+        let root = parse_json_cst(input, ParserOptions::default()).unwrap();
+        let x = root.pointer("/list/1/x").unwrap();
+        assert_eq!(x.span.text(input), "true");
+        assert_eq!(root.node_at(x.span.start + 1), Some(x));
+        assert_eq!(root.node_at(11).unwrap().span.text(input), "10");
+        assert_eq!(root.node_at(input.len()), None);
+
+        for bad in ["[1,]", "{\"a\" 1}", "[1] 2", "{\"a\": 1, }", "[1 2]"] {
+            let expected = parse_json(bad).unwrap_err();
+            let error = parse_json_cst(bad, ParserOptions::default()).unwrap_err();
+            assert_eq!(error.message(), expected.message(), "{}", bad);
+            assert_eq!(error.kind(), expected.kind(), "{}", bad);
+        }
+    }
+}
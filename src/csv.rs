@@ -0,0 +1,674 @@
+//! CSV bridge - arrays of flat objects to and from comma-separated values
+//!
+//! Each object becomes one row; the header is the union of all keys in
+//! first-seen order. Nested arrays and objects are either flattened into
+//! dotted columns (`owner.name`, `tags.0`) or written inline as JSON text,
+//! and [`from_csv`] reverses either form, optionally inferring numbers,
+//! booleans and nulls. Quoting follows RFC 4180.
+
+use std::borrow::Cow;
+use std::io::Read;
+
+use crate::convert::{ConvertError, Format};
+use crate::json::JsonValue;
+use crate::number::JsonNumber;
+use crate::object::JsonObject;
+
+/// How nested arrays and objects are represented in CSV cells
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NestedValues {
+    /// One column per leaf, named by the dotted path (`owner.name`, `tags.0`)
+    #[default]
+    Flatten,
+    /// One column per top-level key holding the minified JSON text
+    Inline,
+}
+
+/// Character encoding of CSV input
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CsvEncoding {
+    /// UTF-8 or UTF-16 as announced by a byte order mark; without one UTF-8,
+    /// falling back to Windows-1252 when the input is not valid UTF-8
+    #[default]
+    Detect,
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+    /// The Western European code page many spreadsheet exports still use
+    Windows1252,
+}
+
+/// Settings for CSV export and import
+///
+/// # Examples
+///
+/// ```
+/// use json_parser_rs::csv::{CsvOptions, NestedValues, from_csv, to_csv_with_options};
+/// use json_parser_rs::json;
+///
+/// let rows = json!([{"id": 1, "tags": ["a", "b"]}]);
+/// let options = CsvOptions::default()
+///     .delimiter(';')
+///     .nested(NestedValues::Inline)
+///     .infer_types(true);
+/// let csv = to_csv_with_options(&rows, options).unwrap();
+/// assert_eq!(csv, "id;tags\n1;\"[\"\"a\"\",\"\"b\"\"]\"\n");
+/// assert_eq!(from_csv(csv.as_bytes(), options).unwrap(), rows);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct CsvOptions {
+    /// Field separator (default `,`)
+    pub delimiter: char,
+    /// Representation of nested values (default: flattened)
+    pub nested: NestedValues,
+    /// When reading, turn empty cells into `null` and cells spelling a JSON
+    /// number, `true`, `false` or `null` into those values (default: off,
+    /// every cell is a string). Numbers with leading zeros such as `007`
+    /// stay strings.
+    pub infer_types: bool,
+    /// Encoding of input read by [`from_csv`] (default: detected)
+    pub encoding: CsvEncoding,
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        Self {
+            delimiter: ',',
+            nested: NestedValues::Flatten,
+            infer_types: false,
+            encoding: CsvEncoding::Detect,
+        }
+    }
+}
+
+impl CsvOptions {
+    pub fn delimiter(mut self, delimiter: char) -> Self {
+        self.delimiter = delimiter;
+        self
+    }
+
+    pub fn nested(mut self, nested: NestedValues) -> Self {
+        self.nested = nested;
+        self
+    }
+
+    pub fn infer_types(mut self, infer: bool) -> Self {
+        self.infer_types = infer;
+        self
+    }
+
+    pub fn encoding(mut self, encoding: CsvEncoding) -> Self {
+        self.encoding = encoding;
+        self
+    }
+}
+
+/// Write an array of objects as CSV with default options
+///
+/// # Examples
+///
+/// ```
+/// use json_parser_rs::{json, to_csv};
+///
+/// let rows = json!([
+///     {"name": "Ann", "owner": {"id": 7}},
+///     {"name": "Bob, Jr.", "active": true},
+/// ]);
+/// assert_eq!(
+///     to_csv(&rows).unwrap(),
+///     "name,owner.id,active\nAnn,7,\n\"Bob, Jr.\",,true\n"
+/// );
+/// ```
+pub fn to_csv(value: &JsonValue) -> Result<String, ConvertError> {
+    to_csv_with_options(value, CsvOptions::default())
+}
+
+/// Write an array of objects as CSV
+///
+/// Missing keys and `null` both give empty cells.
+pub fn to_csv_with_options(value: &JsonValue, options: CsvOptions) -> Result<String, ConvertError> {
+    let elements = value
+        .as_array()
+        .ok_or(ConvertError::encode("CSV export needs an array of objects"))?;
+    let mut header: Vec<String> = Vec::new();
+    let mut rows = Vec::with_capacity(elements.len());
+    for (index, element) in elements.iter().enumerate() {
+        let object = element
+            .as_object()
+            .ok_or_else(|| ConvertError::encode(format!("Element {} is not an object", index)))?;
+        let mut row = JsonObject::new();
+        for (key, member) in object {
+            match options.nested {
+                NestedValues::Flatten => flatten(key.clone(), member, &mut row),
+                NestedValues::Inline => {
+                    row.insert(key.clone(), member.clone());
+                }
+            }
+        }
+        for key in row.keys() {
+            if !header.contains(key) {
+                header.push(key.clone());
+            }
+        }
+        rows.push(row);
+    }
+
+    let mut csv = String::new();
+    if header.is_empty() {
+        return Ok(csv);
+    }
+    write_record(
+        &mut csv,
+        header.iter().map(String::as_str),
+        options.delimiter,
+    );
+    for row in &rows {
+        let cells: Vec<String> = header
+            .iter()
+            .map(|key| row.get(key).map(cell_text).unwrap_or_default())
+            .collect();
+        write_record(
+            &mut csv,
+            cells.iter().map(String::as_str),
+            options.delimiter,
+        );
+    }
+    Ok(csv)
+}
+
+/// Insert the leaves of `value` into `row` under dotted paths below `path`
+fn flatten(path: String, value: &JsonValue, row: &mut JsonObject) {
+    match value.untagged() {
+        JsonValue::Array(elements) if !elements.is_empty() => {
+            for (index, element) in elements.iter().enumerate() {
+                flatten(format!("{}.{}", path, index), element, row);
+            }
+        }
+        JsonValue::Object(object) if !object.is_empty() => {
+            for (key, member) in object {
+                flatten(format!("{}.{}", path, key), member, row);
+            }
+        }
+        _ => {
+            row.insert(path, value.clone());
+        }
+    }
+}
+
+fn cell_text(value: &JsonValue) -> String {
+    match value.untagged() {
+        JsonValue::Null => String::new(),
+        JsonValue::Boolean(b) => b.to_string(),
+        JsonValue::Number(n) => n.to_string(),
+        string @ (JsonValue::String(_) | JsonValue::RawString(_)) => {
+            string.to_str_lossy().unwrap_or_default().into_owned()
+        }
+        nested => nested.to_minified_string(),
+    }
+}
+
+fn write_record<'a>(csv: &mut String, cells: impl Iterator<Item = &'a str>, delimiter: char) {
+    for (index, cell) in cells.enumerate() {
+        if index > 0 {
+            csv.push(delimiter);
+        }
+        if cell.contains([delimiter, '"', '\n', '\r']) {
+            csv.push('"');
+            csv.push_str(&cell.replace('"', "\"\""));
+            csv.push('"');
+        } else {
+            csv.push_str(cell);
+        }
+    }
+    csv.push('\n');
+}
+
+/// Read CSV with a header row into an array of objects
+///
+/// The input is decoded as `options.encoding` says. Cells become strings
+/// unless `options.infer_types` is set; dotted columns are rebuilt into
+/// nested objects, or arrays when their keys are `0`, `1`, ... in order.
+///
+/// # Examples
+///
+/// ```
+/// use json_parser_rs::{CsvOptions, from_csv, json};
+///
+/// let input = "name,age,tags.0,tags.1\nAnn,42,a,\n";
+/// let rows = from_csv(input.as_bytes(), CsvOptions::default().infer_types(true)).unwrap();
+/// assert_eq!(rows, json!([{"name": "Ann", "age": 42, "tags": ["a", null]}]));
+/// ```
+pub fn from_csv<R: Read>(mut reader: R, options: CsvOptions) -> Result<JsonValue, ConvertError> {
+    let mut input = Vec::new();
+    reader
+        .read_to_end(&mut input)
+        .map_err(|e| ConvertError::decode(format!("Error reading CSV input: {}", e)))?;
+    from_csv_str(&decode_text(&input, options.encoding)?, options)
+}
+
+/// Read CSV text with a header row into an array of objects
+///
+/// Like [`from_csv`] for input that is already a string; with
+/// [`NestedValues::Inline`], cells holding a JSON array or object are parsed
+/// back.
+pub fn from_csv_str(input: &str, options: CsvOptions) -> Result<JsonValue, ConvertError> {
+    let mut records = Records::new(input, options.delimiter);
+    let Some((_, header)) = records.next().transpose()? else {
+        return Ok(JsonValue::Array(Vec::new()));
+    };
+    let columns: Vec<Vec<&str>> = match options.nested {
+        NestedValues::Flatten => header
+            .iter()
+            .map(|name| name.split('.').collect())
+            .collect(),
+        NestedValues::Inline => header.iter().map(|name| vec![name.as_str()]).collect(),
+    };
+
+    let mut rows = Vec::new();
+    for record in records {
+        let (line, cells) = record?;
+        if cells.len() != header.len() {
+            return Err(ConvertError::decode(format!(
+                "Line {}: expected {} fields, found {}",
+                line,
+                header.len(),
+                cells.len()
+            )));
+        }
+        let mut row = Node::Branch(Vec::new());
+        for (column, cell) in columns.iter().zip(cells) {
+            let value = match options.nested {
+                NestedValues::Inline if cell.starts_with(['[', '{']) => {
+                    crate::parse_json(&cell).unwrap_or(JsonValue::String(cell))
+                }
+                _ if options.infer_types => infer_type(cell),
+                _ => JsonValue::String(cell),
+            };
+            row.insert(column, value).map_err(|()| {
+                ConvertError::decode(format!(
+                    "Column '{}' conflicts with another column",
+                    column.join(".")
+                ))
+            })?;
+        }
+        rows.push(row.into_value(true));
+    }
+    Ok(JsonValue::Array(rows))
+}
+
+/// Value a cell spells, or the cell as a string
+fn infer_type(cell: String) -> JsonValue {
+    match cell.as_str() {
+        "" | "null" => JsonValue::Null,
+        "true" => JsonValue::Boolean(true),
+        "false" => JsonValue::Boolean(false),
+        text if is_json_number(text) => {
+            JsonNumber::from_literal(text).map_or(JsonValue::String(cell), JsonValue::Number)
+        }
+        _ => JsonValue::String(cell),
+    }
+}
+
+/// `text` follows the JSON number grammar (no leading zeros, `+`, or bare `.`)
+fn is_json_number(text: &str) -> bool {
+    fn digits(bytes: &[u8]) -> usize {
+        bytes.iter().take_while(|b| b.is_ascii_digit()).count()
+    }
+    let bytes = text.strip_prefix('-').unwrap_or(text).as_bytes();
+    let integer = digits(bytes);
+    if integer == 0 || (integer > 1 && bytes[0] == b'0') {
+        return false;
+    }
+    let mut rest = &bytes[integer..];
+    if let Some(fraction) = rest.strip_prefix(b".") {
+        let count = digits(fraction);
+        if count == 0 {
+            return false;
+        }
+        rest = &fraction[count..];
+    }
+    if let Some(exponent) = rest.strip_prefix(b"e").or_else(|| rest.strip_prefix(b"E")) {
+        let exponent = exponent
+            .strip_prefix(b"+")
+            .or_else(|| exponent.strip_prefix(b"-"))
+            .unwrap_or(exponent);
+        let count = digits(exponent);
+        if count == 0 {
+            return false;
+        }
+        rest = &exponent[count..];
+    }
+    rest.is_empty()
+}
+
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+const UTF16_LE_BOM: &[u8] = b"\xFF\xFE";
+const UTF16_BE_BOM: &[u8] = b"\xFE\xFF";
+
+/// Characters for the Windows-1252 bytes 0x80 to 0x9F; the five unassigned
+/// bytes map to the C1 control with the same value, as in Latin-1
+const WINDOWS_1252_HIGH: [char; 32] = [
+    '\u{20AC}', '\u{81}', '\u{201A}', '\u{192}', '\u{201E}', '\u{2026}', '\u{2020}', '\u{2021}',
+    '\u{2C6}', '\u{2030}', '\u{160}', '\u{2039}', '\u{152}', '\u{8D}', '\u{17D}', '\u{8F}',
+    '\u{90}', '\u{2018}', '\u{2019}', '\u{201C}', '\u{201D}', '\u{2022}', '\u{2013}', '\u{2014}',
+    '\u{2DC}', '\u{2122}', '\u{161}', '\u{203A}', '\u{153}', '\u{9D}', '\u{17E}', '\u{178}',
+];
+
+fn decode_text(input: &[u8], encoding: CsvEncoding) -> Result<Cow<'_, str>, ConvertError> {
+    let encoding = match encoding {
+        CsvEncoding::Detect if input.starts_with(UTF8_BOM) => CsvEncoding::Utf8,
+        CsvEncoding::Detect if input.starts_with(UTF16_LE_BOM) => CsvEncoding::Utf16Le,
+        CsvEncoding::Detect if input.starts_with(UTF16_BE_BOM) => CsvEncoding::Utf16Be,
+        CsvEncoding::Detect if std::str::from_utf8(input).is_ok() => CsvEncoding::Utf8,
+        CsvEncoding::Detect => CsvEncoding::Windows1252,
+        encoding => encoding,
+    };
+    match encoding {
+        CsvEncoding::Utf8 => {
+            let input = input.strip_prefix(UTF8_BOM).unwrap_or(input);
+            std::str::from_utf8(input)
+                .map(Cow::Borrowed)
+                .map_err(|e| ConvertError::decode(format!("Input is not UTF-8: {}", e)))
+        }
+        CsvEncoding::Utf16Le | CsvEncoding::Utf16Be => {
+            let (bom, from_bytes): (_, fn([u8; 2]) -> u16) = match encoding {
+                CsvEncoding::Utf16Le => (UTF16_LE_BOM, u16::from_le_bytes),
+                _ => (UTF16_BE_BOM, u16::from_be_bytes),
+            };
+            let input = input.strip_prefix(bom).unwrap_or(input);
+            if input.len() % 2 != 0 {
+                return Err(ConvertError::decode(
+                    "UTF-16 input has an odd number of bytes",
+                ));
+            }
+            let units = input
+                .chunks_exact(2)
+                .map(|pair| from_bytes([pair[0], pair[1]]));
+            char::decode_utf16(units)
+                .collect::<Result<String, _>>()
+                .map(Cow::Owned)
+                .map_err(|e| ConvertError::decode(format!("Input is not UTF-16: {}", e)))
+        }
+        CsvEncoding::Windows1252 | CsvEncoding::Detect => Ok(Cow::Owned(
+            input
+                .iter()
+                .map(|&byte| match byte {
+                    0x80..=0x9F => WINDOWS_1252_HIGH[usize::from(byte - 0x80)],
+                    _ => char::from(byte),
+                })
+                .collect(),
+        )),
+    }
+}
+
+/// Row being rebuilt from dotted columns
+enum Node {
+    Leaf(JsonValue),
+    Branch(Vec<(String, Node)>),
+}
+
+impl Node {
+    fn insert(&mut self, path: &[&str], value: JsonValue) -> Result<(), ()> {
+        let Node::Branch(children) = self else {
+            return Err(());
+        };
+        let [first, rest @ ..] = path else {
+            return Err(());
+        };
+        let position = children.iter().position(|(key, _)| key == first);
+        match (position, rest.is_empty()) {
+            (Some(_), true) => Err(()),
+            (Some(position), false) => children[position].1.insert(rest, value),
+            (None, true) => {
+                children.push((first.to_string(), Node::Leaf(value)));
+                Ok(())
+            }
+            (None, false) => {
+                let mut child = Node::Branch(Vec::new());
+                child.insert(rest, value)?;
+                children.push((first.to_string(), child));
+                Ok(())
+            }
+        }
+    }
+
+    /// Branches keyed `0`, `1`, ... become arrays, except the row itself
+    fn into_value(self, is_row: bool) -> JsonValue {
+        match self {
+            Node::Leaf(value) => value,
+            Node::Branch(children) => {
+                let is_array = !is_row
+                    && children
+                        .iter()
+                        .enumerate()
+                        .all(|(index, (key, _))| *key == index.to_string());
+                if is_array {
+                    JsonValue::Array(
+                        children
+                            .into_iter()
+                            .map(|(_, child)| child.into_value(false))
+                            .collect(),
+                    )
+                } else {
+                    JsonValue::Object(
+                        children
+                            .into_iter()
+                            .map(|(key, child)| (key, child.into_value(false)))
+                            .collect(),
+                    )
+                }
+            }
+        }
+    }
+}
+
+/// RFC 4180 records with the line each starts on
+struct Records<'a> {
+    input: &'a str,
+    position: usize,
+    line: usize,
+    delimiter: char,
+}
+
+impl<'a> Records<'a> {
+    fn new(input: &'a str, delimiter: char) -> Self {
+        Self {
+            input: input.strip_prefix('\u{feff}').unwrap_or(input),
+            position: 0,
+            line: 1,
+            delimiter,
+        }
+    }
+
+    fn read_record(&mut self) -> Result<Vec<String>, ConvertError> {
+        let mut cells = Vec::new();
+        let mut cell = String::new();
+        let mut chars = self.input[self.position..].char_indices().peekable();
+        let start = self.position;
+        let mut quoted = false;
+        let mut was_quoted = false;
+        loop {
+            let Some((offset, c)) = chars.next() else {
+                if quoted {
+                    return Err(ConvertError::decode(format!(
+                        "Line {}: unterminated quoted field",
+                        self.line
+                    )));
+                }
+                self.position = self.input.len();
+                break;
+            };
+            match c {
+                '"' if quoted => {
+                    if chars.next_if(|&(_, next)| next == '"').is_some() {
+                        cell.push('"');
+                    } else {
+                        quoted = false;
+                    }
+                }
+                '"' if cell.is_empty() && !was_quoted => {
+                    quoted = true;
+                    was_quoted = true;
+                }
+                '\n' if quoted => {
+                    self.line += 1;
+                    cell.push(c);
+                }
+                c if quoted => cell.push(c),
+                c if c == self.delimiter => {
+                    cells.push(std::mem::take(&mut cell));
+                    was_quoted = false;
+                }
+                '\r' if chars.peek().is_some_and(|&(_, next)| next == '\n') => {}
+                '\n' => {
+                    self.position = start + offset + 1;
+                    self.line += 1;
+                    break;
+                }
+                c => cell.push(c),
+            }
+        }
+        cells.push(cell);
+        Ok(cells)
+    }
+}
+
+impl Iterator for Records<'_> {
+    type Item = Result<(usize, Vec<String>), ConvertError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // Blank lines (including the end of the last record) hold no record
+        while self.input[self.position..].starts_with(['\n', '\r']) {
+            if self.input[self.position..].starts_with('\n') {
+                self.line += 1;
+            }
+            self.position += 1;
+        }
+        if self.position >= self.input.len() {
+            return None;
+        }
+        let line = self.line;
+        Some(self.read_record().map(|cells| (line, cells)))
+    }
+}
+
+/// CSV tables (`.csv`)
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Csv {
+    options: CsvOptions,
+}
+
+impl Csv {
+    pub fn with_options(options: CsvOptions) -> Self {
+        Self { options }
+    }
+}
+
+impl Format for Csv {
+    fn name(&self) -> &str {
+        "csv"
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["csv"]
+    }
+
+    fn decode(&self, input: &[u8]) -> Result<JsonValue, ConvertError> {
+        from_csv(input, self.options)
+    }
+
+    fn encode(&self, value: &JsonValue) -> Result<Vec<u8>, ConvertError> {
+        to_csv_with_options(value, self.options).map(String::into_bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json;
+
+    #[test]
+    fn test_csv_round_trip() {
+        let rows = json!([
+            {"id": "1", "note": "say \"hi\"\nthen leave", "owner": {"name": "Ann"}},
+            {"id": "2", "tags": ["x", "y"], "owner": {"name": "Bob"}}
+        ]);
+        let csv = to_csv(&rows).unwrap();
+        assert_eq!(
+            csv,
+            "id,note,owner.name,tags.0,tags.1\n\
+             1,\"say \"\"hi\"\"\nthen leave\",Ann,,\n\
+             2,,Bob,x,y\n"
+        );
+        assert_eq!(
+            from_csv_str(&csv, CsvOptions::default()).unwrap(),
+            json!([
+                {"id": "1", "note": "say \"hi\"\nthen leave", "owner": {"name": "Ann"}, "tags": ["", ""]},
+                {"id": "2", "note": "", "owner": {"name": "Bob"}, "tags": ["x", "y"]}
+            ])
+        );
+    }
+
+    fn read(input: &str) -> Result<JsonValue, ConvertError> {
+        from_csv_str(input, CsvOptions::default())
+    }
+
+    #[test]
+    fn test_csv_errors() {
+        assert!(to_csv(&json!({"a": 1})).is_err());
+        assert_eq!(
+            to_csv(&json!([{"a": 1}, 2])).unwrap_err().message(),
+            "Element 1 is not an object"
+        );
+        assert_eq!(
+            read("a,b\r\n1,2\r\n\r\n3\r\n").unwrap_err().message(),
+            "Line 4: expected 2 fields, found 1"
+        );
+        assert_eq!(
+            read("a,a.b\n1,2\n").unwrap_err().message(),
+            "Column 'a.b' conflicts with another column"
+        );
+        assert!(read("a\n\"open\n").is_err());
+        assert_eq!(read("").unwrap(), json!([]));
+    }
+
+    #[test]
+    fn test_type_inference() {
+        let options = CsvOptions::default().infer_types(true);
+        let input = "a,b,c,d,e,f\n-1.5e3,true,,null,007,1 \n";
+        assert_eq!(
+            from_csv_str(input, options).unwrap(),
+            json!([{"a": -1.5e3, "b": true, "c": null, "d": null, "e": "007", "f": "1 "}])
+        );
+    }
+
+    #[test]
+    fn test_encoding_detection() {
+        let expected = json!([{"name": "Zoë €"}]);
+        let utf8 = "\u{feff}name\nZoë €\n";
+        assert_eq!(
+            from_csv(utf8.as_bytes(), CsvOptions::default()).unwrap(),
+            expected
+        );
+
+        let mut utf16 = vec![0xFF, 0xFE];
+        utf16.extend(
+            utf8.trim_start_matches('\u{feff}')
+                .encode_utf16()
+                .flat_map(u16::to_le_bytes),
+        );
+        assert_eq!(
+            from_csv(&utf16[..], CsvOptions::default()).unwrap(),
+            expected
+        );
+
+        let windows_1252 = b"name\nZo\xEB \x80\n";
+        assert_eq!(
+            from_csv(&windows_1252[..], CsvOptions::default()).unwrap(),
+            expected
+        );
+        let strict = CsvOptions::default().encoding(CsvEncoding::Utf8);
+        assert!(from_csv(&windows_1252[..], strict).is_err());
+    }
+}
@@ -0,0 +1,596 @@
+//! serde Deserializer driven directly by the lexer
+//!
+//! `from_str::<T>()` pulls tokens from the `Lexer` and hands them to serde
+//! visitors as they arrive, so typed data is built without an intermediate
+//! `JsonValue` tree. The `Deserialize` impl for `JsonValue` lives here as well.
+
+use std::borrow::Cow;
+
+use serde::de::{self, DeserializeSeed, IntoDeserializer, MapAccess, SeqAccess, Visitor};
+
+use crate::error::{ErrorKind, ParseError};
+use crate::json::JsonValue;
+use crate::lexer::{Lexer, Token, TokenRef};
+use crate::number::JsonNumber;
+use crate::object::JsonObject;
+use crate::options::DEFAULT_MAX_DEPTH;
+
+/// Deserialize an instance of `T` from JSON text
+///
+/// Strings without escape sequences are lent to `T` straight from `input`,
+/// so fields of type `&str` work as long as their values need no
+/// unescaping.
+///
+/// # Examples
+///
+/// ```
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize)]
+/// struct User {
+///     name: String,
+///     tags: Vec<u32>,
+/// }
+///
+/// let user: User = json_parser_rs::from_str(r#"{"name": "Ada", "tags": [1, 2]}"#).unwrap();
+/// assert_eq!(user.name, "Ada");
+/// assert_eq!(user.tags, [1, 2]);
+///
+/// #[derive(Deserialize)]
+/// struct Borrowed<'a> {
+///     name: &'a str,
+/// }
+///
+/// let input = String::from(r#"{"name": "Ada"}"#);
+/// let user: Borrowed = json_parser_rs::from_str(&input).unwrap();
+/// assert_eq!(user.name, "Ada");
+/// ```
+pub fn from_str<'de, T: de::Deserialize<'de>>(input: &'de str) -> Result<T, ParseError> {
+    let mut deserializer = Deserializer::new(input);
+    let value = T::deserialize(&mut deserializer).map_err(|e| deserializer.locate(e))?;
+    match deserializer.next()? {
+        TokenRef::Token(Token::Eof) => Ok(value),
+        token => Err(deserializer
+            .error(format!("Unexpected token after JSON value: {}", token))
+            .with_kind(ErrorKind::TrailingCharacters)),
+    }
+}
+
+impl de::Error for ParseError {
+    fn custom<T: std::fmt::Display>(msg: T) -> Self {
+        ParseError::without_position(msg.to_string())
+    }
+}
+
+/// Token-level serde Deserializer with one token of lookahead
+pub struct Deserializer<'de> {
+    lexer: Lexer<'de>,
+    /// Token read ahead by `peek`; kept here rather than in the lexer so
+    /// that its string still borrows from the input
+    peeked: Option<TokenRef<'de>>,
+    depth: usize,
+}
+
+impl<'de> Deserializer<'de> {
+    pub fn new(input: &'de str) -> Self {
+        Self {
+            lexer: Lexer::new(input),
+            peeked: None,
+            depth: 0,
+        }
+    }
+
+    /// Whether the next token is `token`, without consuming it
+    fn peek_is(&mut self, token: Token) -> Result<bool, ParseError> {
+        if self.peeked.is_none() {
+            self.peeked = Some(self.lexer.next_token_ref()?);
+        }
+        Ok(matches!(&self.peeked, Some(TokenRef::Token(peeked)) if *peeked == token))
+    }
+
+    fn next(&mut self) -> Result<TokenRef<'de>, ParseError> {
+        match self.peeked.take() {
+            Some(token) => Ok(token),
+            None => self.lexer.next_token_ref(),
+        }
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<(), ParseError> {
+        match self.next()? {
+            TokenRef::Token(token) if token == expected => Ok(()),
+            token => Err(self.unexpected(
+                format!("Expected {}, found {}", expected, token),
+                token,
+            )),
+        }
+    }
+
+    /// Error for `token` turning up where it does not belong
+    fn unexpected(&self, message: String, token: TokenRef<'de>) -> ParseError {
+        let kind = match token {
+            TokenRef::Str(s) => ErrorKind::unexpected(&Token::String(s.into_owned())),
+            TokenRef::Token(token) => ErrorKind::unexpected(&token),
+        };
+        self.error(message).with_kind(kind)
+    }
+
+    fn error(&self, message: impl Into<String>) -> ParseError {
+        ParseError::new(message, self.lexer.token_start, self.lexer.input())
+    }
+
+    /// Count a sequence or map being entered; serde recurses once per
+    /// level, so the parser's default depth limit also bounds the stack here
+    fn enter_nested(&mut self) -> Result<(), ParseError> {
+        self.depth += 1;
+        if self.depth > DEFAULT_MAX_DEPTH {
+            return Err(self
+                .error(format!(
+                    "Maximum nesting depth of {} exceeded",
+                    DEFAULT_MAX_DEPTH
+                ))
+                .with_kind(ErrorKind::DepthLimitExceeded));
+        }
+        Ok(())
+    }
+
+    /// Attach the current position to errors raised by visitors
+    fn locate(&self, error: ParseError) -> ParseError {
+        if error.has_position() {
+            error
+        } else {
+            let kind = error.kind();
+            self.error(error.message).with_kind(kind)
+        }
+    }
+
+    fn visit_number<V: Visitor<'de>>(n: JsonNumber, visitor: V) -> Result<V::Value, ParseError> {
+        if n.is_u64() {
+            visitor.visit_u64(n.as_u64().unwrap())
+        } else if n.is_i64() {
+            visitor.visit_i64(n.as_i64().unwrap())
+        } else {
+            visitor.visit_f64(n.as_f64())
+        }
+    }
+}
+
+impl<'de> de::Deserializer<'de> for &mut Deserializer<'de> {
+    type Error = ParseError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ParseError> {
+        let value = match self.next()? {
+            TokenRef::Str(Cow::Borrowed(s)) => visitor.visit_borrowed_str(s),
+            TokenRef::Str(Cow::Owned(s)) => visitor.visit_string(s),
+            TokenRef::Token(Token::Null) => visitor.visit_unit(),
+            TokenRef::Token(Token::Boolean(b)) => visitor.visit_bool(b),
+            TokenRef::Token(Token::Number(n)) => Deserializer::visit_number(n, visitor),
+            TokenRef::Token(Token::LeftBracket) => {
+                self.enter_nested()?;
+                let value = visitor.visit_seq(Sequence::new(self))?;
+                self.expect(Token::RightBracket)?;
+                self.depth -= 1;
+                Ok(value)
+            }
+            TokenRef::Token(Token::LeftBrace) => {
+                self.enter_nested()?;
+                let value = visitor.visit_map(Members::new(self))?;
+                self.expect(Token::RightBrace)?;
+                self.depth -= 1;
+                Ok(value)
+            }
+            token => {
+                return Err(self.unexpected(format!("Unexpected token: {}", token), token));
+            }
+        };
+        value.map_err(|e| self.locate(e))
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ParseError> {
+        if self.peek_is(Token::Null)? {
+            self.next()?;
+            visitor.visit_none()
+        } else {
+            visitor.visit_some(self)
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, ParseError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    /// Unit variants are strings, all others `{"Variant": content}`
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, ParseError> {
+        match self.next()? {
+            TokenRef::Str(variant) => visitor.visit_enum(variant.into_deserializer()),
+            TokenRef::Token(Token::LeftBrace) => {
+                self.enter_nested()?;
+                let value = visitor.visit_enum(Variant { de: &mut *self })?;
+                self.expect(Token::RightBrace)?;
+                self.depth -= 1;
+                Ok(value)
+            }
+            token => Err(self.unexpected(
+                format!("Expected string or object for enum, found {}", token),
+                token,
+            )),
+        }
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple tuple_struct map struct
+        identifier ignored_any
+    }
+}
+
+/// Array elements, separated by commas
+struct Sequence<'a, 'de> {
+    de: &'a mut Deserializer<'de>,
+    first: bool,
+}
+
+impl<'a, 'de> Sequence<'a, 'de> {
+    fn new(de: &'a mut Deserializer<'de>) -> Self {
+        Self { de, first: true }
+    }
+}
+
+impl<'de> SeqAccess<'de> for Sequence<'_, 'de> {
+    type Error = ParseError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, ParseError> {
+        if self.de.peek_is(Token::RightBracket)? {
+            return Ok(None);
+        }
+        if !self.first {
+            self.de.expect(Token::Comma)?;
+            if self.de.peek_is(Token::RightBracket)? {
+                return Err(self
+                    .de
+                    .error("Trailing comma not allowed")
+                    .with_kind(ErrorKind::TrailingComma));
+            }
+        }
+        self.first = false;
+        seed.deserialize(&mut *self.de).map(Some)
+    }
+}
+
+/// Object members, separated by commas
+struct Members<'a, 'de> {
+    de: &'a mut Deserializer<'de>,
+    first: bool,
+}
+
+impl<'a, 'de> Members<'a, 'de> {
+    fn new(de: &'a mut Deserializer<'de>) -> Self {
+        Self { de, first: true }
+    }
+}
+
+impl<'de> MapAccess<'de> for Members<'_, 'de> {
+    type Error = ParseError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, ParseError> {
+        if self.de.peek_is(Token::RightBrace)? {
+            return Ok(None);
+        }
+        if !self.first {
+            self.de.expect(Token::Comma)?;
+            if self.de.peek_is(Token::RightBrace)? {
+                return Err(self
+                    .de
+                    .error("Trailing comma not allowed")
+                    .with_kind(ErrorKind::TrailingComma));
+            }
+        }
+        self.first = false;
+        match self.de.next()? {
+            TokenRef::Str(key) => seed
+                .deserialize(MapKey(key))
+                .map(Some)
+                .map_err(|e| self.de.locate(e)),
+            TokenRef::Token(Token::Eof) => Err(self
+                .de
+                .error("Object key must be a string")
+                .with_kind(ErrorKind::UnexpectedEof)),
+            _ => Err(self
+                .de
+                .error("Object key must be a string")
+                .with_kind(ErrorKind::KeyMustBeString)),
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> Result<V::Value, ParseError> {
+        self.de.expect(Token::Colon)?;
+        seed.deserialize(&mut *self.de)
+    }
+}
+
+/// Object keys are always strings; numeric key types parse them
+struct MapKey<'de>(Cow<'de, str>);
+
+macro_rules! deserialize_numeric_key {
+    ($($method:ident => $visit:ident),*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ParseError> {
+                match self.0.parse() {
+                    Ok(n) => visitor.$visit(n),
+                    Err(_) => Err(de::Error::custom(format!("Invalid numeric key: {}", self.0))),
+                }
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for MapKey<'de> {
+    type Error = ParseError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ParseError> {
+        match self.0 {
+            Cow::Borrowed(key) => visitor.visit_borrowed_str(key),
+            Cow::Owned(key) => visitor.visit_string(key),
+        }
+    }
+
+    deserialize_numeric_key! {
+        deserialize_i8 => visit_i8, deserialize_i16 => visit_i16,
+        deserialize_i32 => visit_i32, deserialize_i64 => visit_i64,
+        deserialize_u8 => visit_u8, deserialize_u16 => visit_u16,
+        deserialize_u32 => visit_u32, deserialize_u64 => visit_u64,
+        deserialize_bool => visit_bool
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, ParseError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, ParseError> {
+        visitor.visit_enum(self.0.into_deserializer())
+    }
+
+    serde::forward_to_deserialize_any! {
+        i128 u128 f32 f64 char str string bytes byte_buf option unit unit_struct
+        seq tuple tuple_struct map struct identifier ignored_any
+    }
+}
+
+/// `{"Variant": content}` form of a non-unit enum variant
+struct Variant<'a, 'de> {
+    de: &'a mut Deserializer<'de>,
+}
+
+impl<'de> de::EnumAccess<'de> for Variant<'_, 'de> {
+    type Error = ParseError;
+    type Variant = Self;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(
+        self,
+        seed: V,
+    ) -> Result<(V::Value, Self), ParseError> {
+        let variant = match self.de.next()? {
+            TokenRef::Str(variant) => seed.deserialize(MapKey(variant))?,
+            token => {
+                return Err(self
+                    .de
+                    .unexpected("Expected variant name".to_string(), token));
+            }
+        };
+        self.de.expect(Token::Colon)?;
+        Ok((variant, self))
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for Variant<'_, 'de> {
+    type Error = ParseError;
+
+    fn unit_variant(self) -> Result<(), ParseError> {
+        de::Deserialize::deserialize(self.de)
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(
+        self,
+        seed: T,
+    ) -> Result<T::Value, ParseError> {
+        seed.deserialize(self.de)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, ParseError> {
+        de::Deserializer::deserialize_seq(self.de, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, ParseError> {
+        de::Deserializer::deserialize_map(self.de, visitor)
+    }
+}
+
+impl<'de> de::Deserialize<'de> for JsonValue {
+    fn deserialize<D: de::Deserializer<'de>>(deserializer: D) -> Result<JsonValue, D::Error> {
+        deserializer.deserialize_any(JsonValueVisitor)
+    }
+}
+
+struct JsonValueVisitor;
+
+impl<'de> Visitor<'de> for JsonValueVisitor {
+    type Value = JsonValue;
+
+    fn expecting(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("any JSON value")
+    }
+
+    fn visit_unit<E>(self) -> Result<JsonValue, E> {
+        Ok(JsonValue::Null)
+    }
+
+    fn visit_none<E>(self) -> Result<JsonValue, E> {
+        Ok(JsonValue::Null)
+    }
+
+    fn visit_some<D: de::Deserializer<'de>>(self, deserializer: D) -> Result<JsonValue, D::Error> {
+        de::Deserialize::deserialize(deserializer)
+    }
+
+    fn visit_bool<E>(self, b: bool) -> Result<JsonValue, E> {
+        Ok(JsonValue::Boolean(b))
+    }
+
+    fn visit_i64<E>(self, n: i64) -> Result<JsonValue, E> {
+        Ok(JsonValue::Number(n.into()))
+    }
+
+    fn visit_u64<E>(self, n: u64) -> Result<JsonValue, E> {
+        Ok(JsonValue::Number(n.into()))
+    }
+
+    fn visit_f64<E>(self, f: f64) -> Result<JsonValue, E> {
+        Ok(JsonNumber::from_f64(f).map_or(JsonValue::Null, JsonValue::Number))
+    }
+
+    fn visit_str<E>(self, s: &str) -> Result<JsonValue, E> {
+        Ok(JsonValue::String(s.to_string()))
+    }
+
+    fn visit_string<E>(self, s: String) -> Result<JsonValue, E> {
+        Ok(JsonValue::String(s))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<JsonValue, A::Error> {
+        let mut elements = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(element) = seq.next_element()? {
+            elements.push(element);
+        }
+        Ok(JsonValue::Array(elements))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<JsonValue, A::Error> {
+        let mut object = JsonObject::with_capacity(map.size_hint().unwrap_or(0));
+        while let Some((key, value)) = map.next_entry::<String, JsonValue>()? {
+            object.insert(key, value);
+        }
+        Ok(JsonValue::Object(object))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+    use std::collections::HashMap;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    enum Shape {
+        Point,
+        Circle(f64),
+        Rect { w: u32, h: u32 },
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Doc {
+        id: u64,
+        label: Option<String>,
+        shapes: Vec<Shape>,
+        weights: HashMap<u8, i32>,
+    }
+
+    #[test]
+    fn test_from_str_struct() {
+        let doc: Doc = from_str(
+            r#"{"id": 18446744073709551615, "label": null,
+                "shapes": ["Point", {"Circle": 1.5}, {"Rect": {"w": 2, "h": 3}}],
+                "weights": {"7": -1}}"#,
+        )
+        .unwrap();
+        assert_eq!(doc.id, u64::MAX);
+        assert_eq!(doc.label, None);
+        assert_eq!(
+            doc.shapes,
+            [Shape::Point, Shape::Circle(1.5), Shape::Rect { w: 2, h: 3 }]
+        );
+        assert_eq!(doc.weights[&7], -1);
+    }
+
+    #[test]
+    fn test_from_str_errors_have_positions() {
+        let err = from_str::<Vec<u8>>("[1,\n 300]").unwrap_err();
+        assert_eq!(err.line, 2);
+        assert!(err.message.contains("300"), "{}", err);
+
+        assert!(from_str::<Vec<u8>>("[1, 2,]").is_err());
+        assert!(from_str::<Vec<u8>>("[1] 2").is_err());
+
+        let deep = "[".repeat(100_000);
+        let err = from_str::<JsonValue>(&deep).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::DepthLimitExceeded);
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Borrowed<'a> {
+        name: &'a str,
+        aliases: Vec<&'a str>,
+        nickname: Option<&'a str>,
+        #[serde(borrow)]
+        note: Cow<'a, str>,
+    }
+
+    #[test]
+    fn test_from_str_borrows_strings() {
+        let input = r#"{"name": "Ada", "aliases": ["A", "L"], "nickname": "Countess",
+                        "note": "line\nbreak"}"#;
+        let doc: Borrowed = from_str(input).unwrap();
+        assert_eq!(doc.name, "Ada");
+        assert_eq!(doc.aliases, ["A", "L"]);
+        assert_eq!(doc.nickname, Some("Countess"));
+        assert_eq!(doc.note, "line\nbreak");
+        assert!(matches!(doc.note, Cow::Owned(_)));
+        assert!(input.as_bytes().as_ptr_range().contains(&doc.name.as_ptr()));
+
+        // Escaped text cannot be lent out, as with serde_json
+        let err = from_str::<Borrowed>(r#"{"name": "A\u0064a", "aliases": [], "note": ""}"#);
+        assert!(err.unwrap_err().message.contains("borrowed"));
+    }
+
+    #[test]
+    fn test_json_value_deserialize() {
+        let value: JsonValue = from_str(r#"{"a": [1, -2, 0.5, "x", null, true]}"#).unwrap();
+        assert_eq!(
+            value.to_json_string(),
+            r#"{"a": [1, -2, 0.5, "x", null, true]}"#
+        );
+    }
+}
@@ -0,0 +1,102 @@
+//! Double-encoded JSON - documents embedded as strings inside documents
+//!
+//! Many APIs serialize a payload and store it in a string field of another
+//! payload (`{"body": "{\"id\": 1}"}`). These helpers parse such strings
+//! either on demand or for the whole tree at once.
+
+use crate::error::ParseError;
+use crate::json::JsonValue;
+use crate::parse_json;
+use crate::pointer::pattern_matches;
+
+impl JsonValue {
+    /// Parse the string found at `pointer` as JSON
+    ///
+    /// Returns `None` if the pointer does not resolve to a string.
+    pub fn parse_embedded(&self, pointer: &str) -> Option<Result<JsonValue, ParseError>> {
+        self.pointer(pointer)?.as_str().map(parse_json)
+    }
+
+    /// Replace every string that holds a serialized object or array with the
+    /// parsed value, recursively (so doubly nested encodings unfold as well)
+    ///
+    /// Strings that merely look like scalars (`"42"`, `"true"`) are left alone,
+    /// as are strings that fail to parse.
+    pub fn expand_embedded_json(&mut self) {
+        self.expand_embedded_json_except(&[]);
+    }
+
+    /// Like [`JsonValue::expand_embedded_json`], but leaves strings alone whose
+    /// location matches one of the pointer patterns in `skip`
+    ///
+    /// Patterns are JSON Pointers where a `*` token matches any single key or
+    /// index, e.g. `/events/*/raw`.
+    pub fn expand_embedded_json_except(&mut self, skip: &[&str]) {
+        let mut path = Vec::new();
+        expand(self, &mut path, skip);
+    }
+}
+
+fn expand(value: &mut JsonValue, path: &mut Vec<String>, skip: &[&str]) {
+    match value.untagged_mut() {
+        JsonValue::String(s) => {
+            if skip.iter().any(|pattern| pattern_matches(pattern, path)) {
+                return;
+            }
+            let trimmed = s.trim_start();
+            if !(trimmed.starts_with('{') || trimmed.starts_with('[')) {
+                return;
+            }
+            if let Ok(mut parsed) = parse_json(s) {
+                expand(&mut parsed, path, skip);
+                *value = parsed;
+            }
+        }
+        JsonValue::Array(elements) => {
+            for (index, element) in elements.iter_mut().enumerate() {
+                path.push(index.to_string());
+                expand(element, path, skip);
+                path.pop();
+            }
+        }
+        JsonValue::Object(object) => {
+            for (key, member) in object.iter_mut() {
+                path.push(key.clone());
+                expand(member, path, skip);
+                path.pop();
+            }
+        }
+        JsonValue::Extension(tag, payload) => {
+            path.push(tag.clone());
+            expand(payload, path, skip);
+            path.pop();
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parse_json;
+
+    #[test]
+    fn test_parse_embedded() {
+        let value = parse_json(r#"{"body": "{\"id\": 1}", "n": 1}"#).unwrap();
+        let body = value.parse_embedded("/body").unwrap().unwrap();
+        assert_eq!(body["id"].as_i64(), Some(1));
+        assert!(value.parse_embedded("/n").is_none());
+    }
+
+    #[test]
+    fn test_expand_embedded_json() {
+        let mut value = parse_json(
+            r#"{"a": "[1, \"{\\\"b\\\": 2}\"]", "s": "42", "bad": "{nope", "keep": "{}"}"#,
+        )
+        .unwrap();
+        value.expand_embedded_json_except(&["/keep"]);
+        assert_eq!(
+            value.to_json_string(),
+            r#"{"a": [1, {"b": 2}], "s": "42", "bad": "{nope", "keep": "{}"}"#
+        );
+    }
+}
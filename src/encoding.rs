@@ -0,0 +1,264 @@
+//! Encoding - UTF-16 input for the reader-based parsers
+//!
+//! JSON exchanged between systems must be UTF-8 (RFC 8259), but files
+//! saved by Windows tools are often UTF-16. [`DecodingReader`] detects the
+//! encoding from the first bytes and transcodes UTF-16 to UTF-8 on the fly;
+//! `Parser::from_reader_with_options` puts one in front of the reader when
+//! `ParserOptions::detect_encoding` is set. A leading byte order mark is
+//! passed on as U+FEFF, which all parsers skip.
+//!
+//! Positions in errors count bytes of the UTF-8 text the parser sees, not
+//! of the UTF-16 input.
+//!
+//! # Examples
+//!
+//! ```
+//! use json_parser_rs::{Parser, ParserOptions, json};
+//!
+//! let text = "{\"name\": \"Grüße\"}";
+//! let utf16: Vec<u8> = text.encode_utf16().flat_map(u16::to_le_bytes).collect();
+//! let options = ParserOptions::default().detect_encoding(true);
+//! let value = Parser::from_reader_with_options(&utf16[..], options)
+//!     .and_then(|mut parser| parser.parse())
+//!     .unwrap();
+//! assert_eq!(value, json!({"name": "Grüße"}));
+//! ```
+
+use std::io::{self, BufRead, Read};
+
+/// Encoding of JSON input
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Encoding {
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+}
+
+/// Encoding of input starting with `prefix` (ideally its first four bytes)
+///
+/// A byte order mark decides; without one, the zero bytes in the first
+/// two characters, which are ASCII in any JSON text, tell UTF-16 apart as
+/// RFC 4627 section 3 describes (`00 xx` big endian, `xx 00` little
+/// endian). Anything else is taken to be UTF-8.
+pub fn detect_encoding(prefix: &[u8]) -> Encoding {
+    match prefix {
+        [0xFE, 0xFF, ..] => Encoding::Utf16Be,
+        [0xFF, 0xFE, ..] => Encoding::Utf16Le,
+        [0, a, 0, b, ..] if *a != 0 && *b != 0 => Encoding::Utf16Be,
+        [a, 0, b, 0, ..] if *a != 0 && *b != 0 => Encoding::Utf16Le,
+        [0, a] if *a != 0 => Encoding::Utf16Be,
+        [a, 0] if *a != 0 => Encoding::Utf16Le,
+        _ => Encoding::Utf8,
+    }
+}
+
+/// Reader yielding the UTF-8 form of a UTF-8 or UTF-16 input
+///
+/// The encoding is detected with [`detect_encoding`] on the first read.
+/// Malformed UTF-16 (an odd number of bytes or an unpaired surrogate) is
+/// an `io::ErrorKind::InvalidData` error; UTF-8 passes through unchecked,
+/// for the parser to validate.
+pub struct DecodingReader<R> {
+    reader: R,
+    encoding: Option<Encoding>,
+    /// Bytes read for detection and not yet decoded
+    prefix: Vec<u8>,
+    /// Decoded UTF-8 not yet consumed, from `position` on
+    decoded: Vec<u8>,
+    position: usize,
+    /// First byte of a code unit split across two reads
+    odd_byte: Option<u8>,
+    /// High surrogate waiting for the low surrogate of the next read
+    high_surrogate: Option<u16>,
+}
+
+impl<R: BufRead> DecodingReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            encoding: None,
+            prefix: Vec::new(),
+            decoded: Vec::new(),
+            position: 0,
+            odd_byte: None,
+            high_surrogate: None,
+        }
+    }
+
+    /// The detected encoding, once something has been read
+    pub fn encoding(&self) -> Option<Encoding> {
+        self.encoding
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    fn detect(&mut self) -> io::Result<Encoding> {
+        while self.prefix.len() < 4 {
+            let chunk = self.reader.fill_buf()?;
+            if chunk.is_empty() {
+                break;
+            }
+            let take = chunk.len().min(4 - self.prefix.len());
+            self.prefix.extend_from_slice(&chunk[..take]);
+            self.reader.consume(take);
+        }
+        Ok(detect_encoding(&self.prefix))
+    }
+
+    /// Decode the next chunk into `decoded`; `false` at the end of input
+    fn refill(&mut self) -> io::Result<bool> {
+        let encoding = match self.encoding {
+            Some(encoding) => encoding,
+            None => {
+                let encoding = self.detect()?;
+                self.encoding = Some(encoding);
+                encoding
+            }
+        };
+        self.decoded.clear();
+        self.position = 0;
+        let prefix = std::mem::take(&mut self.prefix);
+        let (bytes, consumed) = if prefix.is_empty() {
+            let chunk = self.reader.fill_buf()?;
+            (chunk, chunk.len())
+        } else {
+            (&prefix[..], 0)
+        };
+        if bytes.is_empty() {
+            if self.odd_byte.is_some() || self.high_surrogate.is_some() {
+                return Err(invalid_utf16());
+            }
+            return Ok(false);
+        }
+
+        let from_bytes: fn([u8; 2]) -> u16 = match encoding {
+            Encoding::Utf8 => {
+                self.decoded.extend_from_slice(bytes);
+                self.reader.consume(consumed);
+                return Ok(true);
+            }
+            Encoding::Utf16Le => u16::from_le_bytes,
+            Encoding::Utf16Be => u16::from_be_bytes,
+        };
+        let mut bytes = bytes.iter().copied();
+        let mut result = Ok(());
+        loop {
+            let first = match self.odd_byte.take() {
+                Some(byte) => byte,
+                None => match bytes.next() {
+                    Some(byte) => byte,
+                    None => break,
+                },
+            };
+            let Some(second) = bytes.next() else {
+                self.odd_byte = Some(first);
+                break;
+            };
+            let unit = from_bytes([first, second]);
+            let code_point = match (self.high_surrogate.take(), unit) {
+                (None, 0xD800..=0xDBFF) => {
+                    self.high_surrogate = Some(unit);
+                    continue;
+                }
+                (Some(high), 0xDC00..=0xDFFF) => {
+                    0x10000 + ((u32::from(high) - 0xD800) << 10) + (u32::from(unit) - 0xDC00)
+                }
+                (None, _) => u32::from(unit),
+                // A high surrogate not followed by a low one
+                (Some(_), _) => u32::MAX,
+            };
+            // `from_u32` rejects lone low surrogates and the marker above
+            let Some(ch) = char::from_u32(code_point) else {
+                result = Err(invalid_utf16());
+                break;
+            };
+            let mut buffer = [0; 4];
+            self.decoded
+                .extend_from_slice(ch.encode_utf8(&mut buffer).as_bytes());
+        }
+        self.reader.consume(consumed);
+        result.map(|()| true)
+    }
+}
+
+fn invalid_utf16() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "Invalid UTF-16 in input")
+}
+
+impl<R: BufRead> Read for DecodingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let available = self.fill_buf()?;
+        let len = available.len().min(buf.len());
+        buf[..len].copy_from_slice(&available[..len]);
+        self.consume(len);
+        Ok(len)
+    }
+}
+
+impl<R: BufRead> BufRead for DecodingReader<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        // A chunk may decode to nothing (a lone high surrogate or byte)
+        while self.position == self.decoded.len() {
+            if !self.refill()? {
+                break;
+            }
+        }
+        Ok(&self.decoded[self.position..])
+    }
+
+    fn consume(&mut self, amount: usize) {
+        self.position = (self.position + amount).min(self.decoded.len());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::options::ParserOptions;
+    use crate::parser::Parser;
+
+    #[test]
+    fn test_detect_encoding() {
+        let cases: [(&[u8], Encoding); 8] = [
+            (b"\xFE\xFF\0[", Encoding::Utf16Be),
+            (b"\xFF\xFE[\0", Encoding::Utf16Le),
+            (b"\0[\0]", Encoding::Utf16Be),
+            (b"[\0]\0", Encoding::Utf16Le),
+            (b"\x001", Encoding::Utf16Be),
+            (b"1\0", Encoding::Utf16Le),
+            (b"\xEF\xBB\xBF{", Encoding::Utf8),
+            (b"{\"a", Encoding::Utf8),
+        ];
+        for (prefix, encoding) in cases {
+            assert_eq!(detect_encoding(prefix), encoding, "{:?}", prefix);
+        }
+    }
+
+    #[test]
+    fn test_decoding_reader_across_small_reads() {
+        let text = "\u{feff}[\"日本\", \"😀\", 1]";
+        let utf16: Vec<u8> = text.encode_utf16().flat_map(u16::to_be_bytes).collect();
+        // One byte per read splits code units and surrogate pairs
+        let reader = io::BufReader::with_capacity(1, &utf16[..]);
+        let mut decoder = DecodingReader::new(reader);
+        let mut output = String::new();
+        decoder.read_to_string(&mut output).unwrap();
+        assert_eq!(output, text);
+        assert_eq!(decoder.encoding(), Some(Encoding::Utf16Be));
+
+        let options = ParserOptions::default().detect_encoding(true);
+        let value = Parser::from_reader_with_options(&utf16[..], options)
+            .and_then(|mut parser| parser.parse())
+            .unwrap();
+        assert_eq!(value[1].as_str(), Some("😀"));
+
+        let mut lone = DecodingReader::new(&b"\xFF\xFE[\0\x00\xD8"[..]);
+        let error = lone.read_to_end(&mut Vec::new()).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        let mut odd = DecodingReader::new(&b"\xFF\xFE[\0]"[..]);
+        assert!(odd.read_to_end(&mut Vec::new()).is_err());
+    }
+}
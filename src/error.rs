@@ -1,3 +1,15 @@
+use std::fmt;
+
+use crate::lexer::Token;
+
+/// Longest message, in characters, an error keeps; longer ones are cut and
+/// end in `…`
+///
+/// Messages quote the offending token, which may be arbitrarily large (think
+/// of a 10 MB string missing its closing quote). Capping them keeps logs
+/// readable and the message independent of the input size.
+pub const MAX_MESSAGE_CHARS: usize = 120;
+
 /// Custom error types for JSON parsing
 /// 
 /// Professional Rust developers use custom error types instead of string literals
@@ -6,23 +18,259 @@
 /// 2. Better error messages with context
 /// 3. Position information for debugging
 /// 4. Ability to chain errors
-
+///
+/// The struct is `#[non_exhaustive]`: construct it with `ParseError::new` and
+/// prefer the accessor methods. The public fields remain readable so existing
+/// code keeps compiling, but new information will only be exposed through
+/// accessors.
 #[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
 pub struct ParseError {
     pub message: String,
     pub position: usize,
     pub line: usize,
     pub column: usize,
+    kind: ErrorKind,
+}
+
+/// What went wrong, for callers that need to react to specific failures
+/// without matching on messages
+///
+/// The message text stays the human-readable description; the kind is the
+/// stable, programmatic part.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// A character that cannot start any token
+    UnexpectedCharacter,
+    /// A valid token in a place the grammar does not allow it
+    UnexpectedToken,
+    /// The input ended in the middle of a value
+    UnexpectedEof,
+    /// A string without its closing quote
+    UnterminatedString,
+    /// A block comment without its closing `*/`
+    UnterminatedComment,
+    /// A malformed escape sequence inside a string
+    InvalidEscape,
+    /// A malformed number literal
+    InvalidNumber,
+    /// A bare word other than `true`, `false` and `null`
+    InvalidKeyword,
+    /// A comma directly before `]` or `}`
+    TrailingComma,
+    /// More input after the complete document
+    TrailingCharacters,
+    /// An object key that is not a string
+    KeyMustBeString,
+    /// A repeated object key under `DuplicateKeyPolicy::Error`
+    DuplicateKey,
+    /// Arrays and objects are nested deeper than the configured limit
+    DepthLimitExceeded,
+    /// The parsed tree would exceed the configured memory budget
+    MemoryLimitExceeded,
+    /// The input is longer than the configured maximum size
+    InputTooLarge,
+    /// A string is longer than the configured maximum length
+    StringTooLong,
+    /// The document holds more values than the configured maximum
+    NodeLimitExceeded,
+    /// An array or object holds more elements than the configured maximum
+    TooManyElements,
+    /// A raw control character inside a string while they are rejected
+    ControlCharacter,
+    /// Input read from an `io::Read` is not valid UTF-8
+    InvalidUtf8,
+    /// Reading the input failed
+    Io,
+    /// The document is valid JSON but breaks a validation constraint
+    ConstraintViolation,
+    /// Any other error, e.g. raised by a serde `Deserialize` impl or built
+    /// with `ParseError::new`
+    Custom,
+}
+
+impl ErrorKind {
+    /// Whether the input itself is malformed, as opposed to exceeding a
+    /// limit or breaking a constraint
+    pub fn is_syntax(self) -> bool {
+        !matches!(
+            self,
+            ErrorKind::DepthLimitExceeded
+                | ErrorKind::MemoryLimitExceeded
+                | ErrorKind::InputTooLarge
+                | ErrorKind::StringTooLong
+                | ErrorKind::NodeLimitExceeded
+                | ErrorKind::TooManyElements
+                | ErrorKind::Io
+                | ErrorKind::ConstraintViolation
+                | ErrorKind::Custom
+        )
+    }
+
+    /// Stable snake_case name, e.g. `unexpected_token`, for machine-readable
+    /// output
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ErrorKind::UnexpectedCharacter => "unexpected_character",
+            ErrorKind::UnexpectedToken => "unexpected_token",
+            ErrorKind::UnexpectedEof => "unexpected_eof",
+            ErrorKind::UnterminatedString => "unterminated_string",
+            ErrorKind::UnterminatedComment => "unterminated_comment",
+            ErrorKind::InvalidEscape => "invalid_escape",
+            ErrorKind::InvalidNumber => "invalid_number",
+            ErrorKind::InvalidKeyword => "invalid_keyword",
+            ErrorKind::TrailingComma => "trailing_comma",
+            ErrorKind::TrailingCharacters => "trailing_characters",
+            ErrorKind::KeyMustBeString => "key_must_be_string",
+            ErrorKind::DuplicateKey => "duplicate_key",
+            ErrorKind::DepthLimitExceeded => "depth_limit_exceeded",
+            ErrorKind::MemoryLimitExceeded => "memory_limit_exceeded",
+            ErrorKind::InputTooLarge => "input_too_large",
+            ErrorKind::StringTooLong => "string_too_long",
+            ErrorKind::NodeLimitExceeded => "node_limit_exceeded",
+            ErrorKind::TooManyElements => "too_many_elements",
+            ErrorKind::ControlCharacter => "control_character",
+            ErrorKind::InvalidUtf8 => "invalid_utf8",
+            ErrorKind::Io => "io",
+            ErrorKind::ConstraintViolation => "constraint_violation",
+            ErrorKind::Custom => "custom",
+        }
+    }
+
+    /// Kind for finding `found` where something else was expected
+    pub(crate) fn unexpected(found: &Token) -> Self {
+        if *found == Token::Eof {
+            ErrorKind::UnexpectedEof
+        } else {
+            ErrorKind::UnexpectedToken
+        }
+    }
 }
 
 impl ParseError {
+    /// Error with kind `ErrorKind::Custom`; the parser attaches specific
+    /// kinds to the errors it raises
+    ///
+    /// Messages longer than [`MAX_MESSAGE_CHARS`] are truncated.
     pub fn new(message: impl Into<String>, position: usize, input: &str) -> Self {
         let (line, column) = Self::calculate_position(position, input);
         Self {
-            message: message.into(),
+            message: truncate(message.into()),
+            position,
+            line,
+            column,
+            kind: ErrorKind::Custom,
+        }
+    }
+
+    pub(crate) fn with_kind(mut self, kind: ErrorKind) -> Self {
+        self.kind = kind;
+        self
+    }
+
+    /// Error at a known line and column, for input that is not kept in
+    /// memory as a whole
+    pub(crate) fn at(message: impl Into<String>, position: usize, line: usize, column: usize) -> Self {
+        Self {
+            message: truncate(message.into()),
             position,
             line,
             column,
+            kind: ErrorKind::Custom,
+        }
+    }
+
+    /// Move an error raised on a slice of `input` starting at `offset` so it
+    /// points into the whole of `input`
+    pub(crate) fn relocate(self, offset: usize, input: &str) -> Self {
+        Self::new(self.message, offset + self.position, input).with_kind(self.kind)
+    }
+
+    /// Like `relocate`, for a slice starting at `offset`, `line` and `column`
+    /// of an input that is not available as a whole
+    pub(crate) fn relocate_to(mut self, offset: usize, line: usize, column: usize) -> Self {
+        self.position += offset;
+        if self.line == 1 {
+            self.column += column - 1;
+        }
+        self.line += line - 1;
+        self
+    }
+
+    /// Error without a known location (line and column 0), e.g. raised by
+    /// serde while deserializing; the deserializer attaches the position
+    /// before returning it
+    #[cfg(feature = "serde")]
+    pub(crate) fn without_position(message: impl Into<String>) -> Self {
+        Self {
+            message: truncate(message.into()),
+            position: 0,
+            line: 0,
+            column: 0,
+            kind: ErrorKind::Custom,
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    pub(crate) fn has_position(&self) -> bool {
+        self.line != 0
+    }
+
+    /// What kind of problem was found
+    pub fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+
+    /// Human-readable description of the problem
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Byte offset into the input
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// 1-based line number
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    /// 1-based byte column: bytes from the start of the line, plus one
+    ///
+    /// Non-ASCII text earlier on the line counts one per byte, so this is
+    /// not the character column an editor shows.
+    pub fn column(&self) -> usize {
+        self.column
+    }
+
+    /// Render the error like a compiler diagnostic: the message, then the
+    /// offending line of `source` with a `^` under the error position
+    ///
+    /// `source` must be the input that was parsed. Errors without a position
+    /// render as their plain message.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use json_parser_rs::parse_json;
+    ///
+    /// let source = "{\n  \"a\" 1\n}";
+    /// let error = parse_json(source).unwrap_err();
+    /// //  Parse error at line 2, column 7: Expected ':', found number 1
+    /// //    |
+    /// //  2 |   "a" 1
+    /// //    |       ^
+    /// assert_eq!(
+    ///     error.display_with_source(source).to_string(),
+    ///     "Parse error at line 2, column 7: Expected ':', found number 1\n  |\n2 |   \"a\" 1\n  |       ^"
+    /// );
+    /// ```
+    pub fn display_with_source<'a>(&'a self, source: &'a str) -> impl fmt::Display + 'a {
+        SourceSnippet {
+            error: self,
+            source,
         }
     }
 
@@ -38,8 +286,55 @@ impl ParseError {
     }
 }
 
-impl std::fmt::Display for ParseError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+fn truncate(mut message: String) -> String {
+    if let Some((cut, _)) = message.char_indices().nth(MAX_MESSAGE_CHARS) {
+        message.truncate(cut);
+        message.push('…');
+    }
+    message
+}
+
+struct SourceSnippet<'a> {
+    error: &'a ParseError,
+    source: &'a str,
+}
+
+impl fmt::Display for SourceSnippet<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.error)?;
+        if self.error.line == 0 {
+            return Ok(());
+        }
+
+        let position = self.error.position.min(self.source.len());
+        let Some(before) = self.source.get(..position) else {
+            return Ok(());
+        };
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+        let line_end = self.source[line_start..]
+            .find(['\n', '\r'])
+            .map_or(self.source.len(), |i| line_start + i);
+        let line = &self.source[line_start..line_end];
+
+        // Keep tabs so the caret lines up with the rendered source line
+        let padding: String = before[line_start..]
+            .chars()
+            .map(|ch| if ch == '\t' { '\t' } else { ' ' })
+            .collect();
+        let gutter = " ".repeat(self.error.line.to_string().len());
+        write!(
+            f,
+            "\n{gutter} |\n{} | {}\n{gutter} | {}^",
+            self.error.line, line, padding
+        )
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.line == 0 {
+            return write!(f, "Parse error: {}", self.message);
+        }
         write!(
             f,
             "Parse error at line {}, column {}: {}",
@@ -49,3 +344,64 @@ impl std::fmt::Display for ParseError {
 }
 
 impl std::error::Error for ParseError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_json;
+
+    #[test]
+    fn test_display_with_source_keeps_tabs_and_multibyte_columns() {
+        let source = "[\n\t\"ä\", 1 2]";
+        let error = parse_json(source).unwrap_err();
+        let rendered = error.display_with_source(source).to_string();
+        let lines: Vec<&str> = rendered.lines().collect();
+        assert_eq!(lines[2], "2 | \t\"ä\", 1 2]");
+        assert_eq!(lines[3], "  | \t       ^");
+    }
+
+    #[test]
+    fn test_caret_points_at_the_first_character_of_the_token() {
+        // Keywords, numbers and stray characters are reported where they
+        // start, not one character in
+        for (source, caret) in [
+            ("[true, nul]", "  |        ^"),
+            ("[ tru]", "  |   ^"),
+            ("[1, 1e400]", "  |     ^"),
+            ("{\"a\": @}", "  |       ^"),
+        ] {
+            let error = parse_json(source).unwrap_err();
+            assert_eq!(error.column(), error.position() + 1, "{}", source);
+            let rendered = error.display_with_source(source).to_string();
+            assert_eq!(rendered.lines().nth(3), Some(caret), "{}", source);
+        }
+    }
+
+    #[test]
+    fn test_column_counts_bytes() {
+        let source = "{\"a\": 1,\n \"é\": @}";
+        let error = parse_json(source).unwrap_err();
+        assert_eq!((error.line(), error.column()), (2, 8));
+        // The caret still lines up under the character
+        let rendered = error.display_with_source(source).to_string();
+        assert_eq!(rendered.lines().nth(3), Some("  |       ^"));
+    }
+
+    #[test]
+    fn test_message_size_does_not_depend_on_input_size() {
+        let messages: Vec<String> = [1_000, 10_000_000]
+            .iter()
+            .map(|&n| {
+                let input = format!("[1] \"{}\"", "ä".repeat(n));
+                parse_json(&input).unwrap_err().message
+            })
+            .collect();
+        assert_eq!(messages[0], messages[1]);
+        assert_eq!(messages[0].chars().count(), MAX_MESSAGE_CHARS + 1);
+        assert!(messages[0].starts_with("Unexpected token after JSON value: string \"ää"));
+        assert!(messages[0].ends_with('…'));
+
+        let error = parse_json("[1 2]").unwrap_err();
+        assert_eq!(error.message(), "Expected ',' or ']', found number 2");
+    }
+}
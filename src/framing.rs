@@ -0,0 +1,444 @@
+//! Framing - checksummed JSON messages over unreliable byte transports
+//!
+//! Serial links, radio modems and datagram sockets can drop, cut short or
+//! flip bytes. Each message is sent as a frame
+//!
+//! - the two sync bytes `JF`
+//! - the payload length as a little-endian `u32`
+//! - the CRC-32 of the payload as a little-endian `u32`
+//! - the payload, minified JSON text
+//!
+//! so [`FrameDecoder`] can reject a damaged message before parsing it and
+//! find the start of the next one. Only frames that pass the checksum are
+//! handed to the [`PushParser`].
+//!
+//! # Examples
+//!
+//! ```
+//! use json_parser_rs::framing::{FrameDecoder, FrameEncoder, FrameErrorKind};
+//! use json_parser_rs::json;
+//!
+//! let encoder = FrameEncoder::new();
+//! let mut wire = encoder.encode(&json!({"temp": 21.5})).unwrap();
+//! let mut damaged = encoder.encode(&json!({"temp": 22.0})).unwrap();
+//! let last = damaged.len() - 2;
+//! damaged[last] ^= 0x10; // bit flip in transit
+//! wire.extend_from_slice(&damaged);
+//! wire.extend_from_slice(&encoder.encode(&json!({"temp": 22.5})).unwrap());
+//!
+//! let mut decoder = FrameDecoder::new();
+//! let mut received = Vec::new();
+//! for chunk in wire.chunks(5) {
+//!     decoder.feed(chunk);
+//!     while let Some(frame) = decoder.decode() {
+//!         received.push(frame.map_err(|e| e.kind()));
+//!     }
+//! }
+//! assert_eq!(received[0], Ok(json!({"temp": 21.5})));
+//! assert_eq!(received[1], Err(FrameErrorKind::ChecksumMismatch));
+//! assert_eq!(received.last(), Some(&Ok(json!({"temp": 22.5}))));
+//! ```
+
+use std::fmt;
+
+use crate::journal::crc32;
+use crate::json::JsonValue;
+use crate::options::ParserOptions;
+use crate::push::{PushParser, PushStatus};
+
+/// Bytes starting every frame
+pub const SYNC: [u8; 2] = *b"JF";
+
+/// Sync bytes, length and CRC
+pub const HEADER_LEN: usize = 10;
+
+/// Default limit for the payload length, 1 MiB
+pub const DEFAULT_MAX_FRAME_LEN: usize = 1 << 20;
+
+/// A frame that was damaged or does not hold a JSON value
+#[derive(Debug, Clone, PartialEq)]
+pub struct FrameError {
+    kind: FrameErrorKind,
+    offset: u64,
+    message: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum FrameErrorKind {
+    /// Bytes not belonging to any frame were skipped
+    Garbage,
+    /// The length field exceeds the configured maximum
+    TooLarge,
+    /// The payload does not match its CRC
+    ChecksumMismatch,
+    /// The payload passed its checksum but is not a single JSON value
+    InvalidPayload,
+}
+
+impl FrameError {
+    fn new(kind: FrameErrorKind, offset: u64, message: impl fmt::Display) -> Self {
+        Self {
+            kind,
+            offset,
+            message: message.to_string(),
+        }
+    }
+
+    pub fn kind(&self) -> FrameErrorKind {
+        self.kind
+    }
+
+    /// Byte offset in the stream of the frame, or of the skipped bytes
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl fmt::Display for FrameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Frame at byte {}: {}", self.offset, self.message)
+    }
+}
+
+impl std::error::Error for FrameError {}
+
+/// Turns values into frames
+#[derive(Debug, Clone)]
+pub struct FrameEncoder {
+    max_frame_len: usize,
+}
+
+impl Default for FrameEncoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FrameEncoder {
+    pub fn new() -> Self {
+        Self {
+            max_frame_len: DEFAULT_MAX_FRAME_LEN,
+        }
+    }
+
+    /// Refuse payloads longer than `len` bytes; keep it equal to the
+    /// decoder's limit
+    pub fn max_frame_len(mut self, len: usize) -> Self {
+        self.max_frame_len = len;
+        self
+    }
+
+    pub fn encode(&self, value: &JsonValue) -> Result<Vec<u8>, FrameError> {
+        let mut frame = Vec::new();
+        self.encode_into(value, &mut frame)?;
+        Ok(frame)
+    }
+
+    /// Append the frame for `value` to `buffer`
+    pub fn encode_into(&self, value: &JsonValue, buffer: &mut Vec<u8>) -> Result<(), FrameError> {
+        let payload = value.to_json_string();
+        if payload.len() > self.max_frame_len {
+            return Err(FrameError::new(
+                FrameErrorKind::TooLarge,
+                0,
+                format!(
+                    "Payload of {} bytes exceeds the limit of {} bytes",
+                    payload.len(),
+                    self.max_frame_len
+                ),
+            ));
+        }
+        buffer.reserve(HEADER_LEN + payload.len());
+        buffer.extend_from_slice(&SYNC);
+        buffer.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+        buffer.extend_from_slice(&crc32(payload.as_bytes()).to_le_bytes());
+        buffer.extend_from_slice(payload.as_bytes());
+        Ok(())
+    }
+}
+
+/// Collects bytes from a transport and returns the values of complete,
+/// intact frames
+///
+/// After a damaged frame the decoder skips ahead to the next sync bytes, so
+/// one corrupted message costs at most that message. For datagram
+/// transports, where every packet is one frame, [`FrameDecoder::decode_frame`]
+/// checks a packet on its own.
+#[derive(Debug, Clone)]
+pub struct FrameDecoder {
+    options: ParserOptions,
+    max_frame_len: usize,
+    buffer: Vec<u8>,
+    /// Stream offset of `buffer[0]`
+    offset: u64,
+}
+
+impl Default for FrameDecoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FrameDecoder {
+    pub fn new() -> Self {
+        Self::with_options(ParserOptions::default())
+    }
+
+    /// Decoder parsing payloads with `options`
+    pub fn with_options(options: ParserOptions) -> Self {
+        Self {
+            options,
+            max_frame_len: DEFAULT_MAX_FRAME_LEN,
+            buffer: Vec::new(),
+            offset: 0,
+        }
+    }
+
+    /// Treat length fields above `len` as corruption instead of waiting for
+    /// that many bytes
+    pub fn max_frame_len(mut self, len: usize) -> Self {
+        self.max_frame_len = len;
+        self
+    }
+
+    /// Buffer bytes received from the transport
+    pub fn feed(&mut self, bytes: &[u8]) {
+        self.buffer.extend_from_slice(bytes);
+    }
+
+    /// The next frame in the buffered bytes; `None` until one is complete
+    ///
+    /// Errors do not end decoding: call again to continue after the damaged
+    /// part.
+    pub fn decode(&mut self) -> Option<Result<JsonValue, FrameError>> {
+        let skipped = find_sync(&self.buffer);
+        if skipped > 0 {
+            let error = FrameError::new(
+                FrameErrorKind::Garbage,
+                self.offset,
+                format!("Skipped {} bytes outside of a frame", skipped),
+            );
+            self.consume(skipped);
+            return Some(Err(error));
+        }
+        if self.buffer.len() < HEADER_LEN {
+            return None;
+        }
+
+        let len = u32::from_le_bytes(self.buffer[2..6].try_into().unwrap()) as usize;
+        if len > self.max_frame_len {
+            let error = FrameError::new(
+                FrameErrorKind::TooLarge,
+                self.offset,
+                format!(
+                    "Length {} exceeds the limit of {} bytes",
+                    len, self.max_frame_len
+                ),
+            );
+            // The length itself may be damaged; resynchronize after the sync bytes
+            self.consume(SYNC.len());
+            return Some(Err(error));
+        }
+        if self.buffer.len() < HEADER_LEN + len {
+            return None;
+        }
+
+        let result = self.check(&self.buffer[..HEADER_LEN + len]);
+        match &result {
+            Err(error) if error.kind == FrameErrorKind::ChecksumMismatch => {
+                self.consume(SYNC.len())
+            }
+            _ => self.consume(HEADER_LEN + len),
+        }
+        Some(result)
+    }
+
+    /// Decode a buffer holding exactly one frame, such as a datagram
+    pub fn decode_frame(&self, frame: &[u8]) -> Result<JsonValue, FrameError> {
+        let truncated = |message: &str| FrameError::new(FrameErrorKind::InvalidPayload, 0, message);
+        if frame.len() < HEADER_LEN || frame[..2] != SYNC {
+            return Err(truncated("Not a frame"));
+        }
+        let len = u32::from_le_bytes(frame[2..6].try_into().unwrap()) as usize;
+        if frame.len() != HEADER_LEN + len {
+            return Err(FrameError::new(
+                FrameErrorKind::InvalidPayload,
+                0,
+                format!(
+                    "Frame holds {} payload bytes, its header announces {}",
+                    frame.len() - HEADER_LEN,
+                    len
+                ),
+            ));
+        }
+        self.check(frame)
+    }
+
+    /// Number of bytes received but not yet decoded
+    pub fn buffered_len(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// Verify the CRC of a complete frame and parse its payload
+    fn check(&self, frame: &[u8]) -> Result<JsonValue, FrameError> {
+        let expected = u32::from_le_bytes(frame[6..10].try_into().unwrap());
+        let payload = &frame[HEADER_LEN..];
+        let actual = crc32(payload);
+        if actual != expected {
+            return Err(FrameError::new(
+                FrameErrorKind::ChecksumMismatch,
+                self.offset,
+                format!(
+                    "Checksum mismatch (expected {:08x}, found {:08x})",
+                    expected, actual
+                ),
+            ));
+        }
+
+        let mut parser = PushParser::with_options(self.options);
+        let value = parser
+            .feed(payload)
+            .and_then(|status| match status {
+                PushStatus::Value(value) => Ok(Some(value)),
+                PushStatus::NeedMoreData => parser.finish(),
+            })
+            .and_then(|value| parser.check_trailing().map(|()| value));
+        match value {
+            Ok(Some(value)) => Ok(value),
+            Ok(None) => Err(FrameError::new(
+                FrameErrorKind::InvalidPayload,
+                self.offset,
+                "Empty payload",
+            )),
+            Err(e) => Err(FrameError::new(
+                FrameErrorKind::InvalidPayload,
+                self.offset,
+                format!("Invalid payload: {}", e),
+            )),
+        }
+    }
+
+    fn consume(&mut self, len: usize) {
+        self.buffer.drain(..len);
+        self.offset += len as u64;
+    }
+}
+
+/// Index of the first possible frame start: the sync bytes, or a first
+/// sync byte at the very end that may be completed by the next chunk
+fn find_sync(buffer: &[u8]) -> usize {
+    buffer
+        .windows(2)
+        .position(|pair| pair == SYNC)
+        .unwrap_or_else(|| match buffer.last() {
+            Some(&byte) if byte == SYNC[0] => buffer.len() - 1,
+            _ => buffer.len(),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json;
+
+    #[test]
+    fn test_round_trip_in_small_chunks() {
+        let encoder = FrameEncoder::new();
+        let values = [
+            json!({"id": 1, "name": "Zoë"}),
+            json!([1, 2, 3]),
+            json!(42),
+            json!(null),
+        ];
+        let mut wire = Vec::new();
+        for value in &values {
+            encoder.encode_into(value, &mut wire).unwrap();
+        }
+
+        let mut decoder = FrameDecoder::new();
+        let mut decoded = Vec::new();
+        for byte in &wire {
+            decoder.feed(std::slice::from_ref(byte));
+            while let Some(frame) = decoder.decode() {
+                decoded.push(frame.unwrap());
+            }
+        }
+        assert_eq!(decoded, values);
+        assert_eq!(decoder.buffered_len(), 0);
+        assert_eq!(
+            decoder.decode_frame(&encoder.encode(&values[0]).unwrap()),
+            Ok(values[0].clone())
+        );
+    }
+
+    #[test]
+    fn test_resynchronizes_after_damage() {
+        let encoder = FrameEncoder::new();
+        let first = encoder.encode(&json!("first")).unwrap();
+        let mut wire = b"noise".to_vec();
+        wire.extend_from_slice(&first);
+        let mut damaged = encoder.encode(&json!({"lost": true})).unwrap();
+        damaged[5] = 0x7f; // length now far beyond the limit
+        wire.extend_from_slice(&damaged);
+        wire.extend_from_slice(&encoder.encode(&json!("last")).unwrap());
+
+        let mut decoder = FrameDecoder::new();
+        decoder.feed(&wire);
+        let frames: Vec<_> = std::iter::from_fn(|| decoder.decode()).collect();
+        let kinds: Vec<_> = frames
+            .iter()
+            .map(|frame| frame.as_ref().map_err(FrameError::kind))
+            .collect();
+        assert_eq!(
+            kinds,
+            [
+                Err(FrameErrorKind::Garbage),
+                Ok(&json!("first")),
+                Err(FrameErrorKind::TooLarge),
+                Err(FrameErrorKind::Garbage),
+                Ok(&json!("last")),
+            ]
+        );
+        let too_large = frames[2].as_ref().unwrap_err();
+        assert_eq!(too_large.offset(), (5 + first.len()) as u64);
+    }
+
+    #[test]
+    fn test_invalid_payloads() {
+        let frame = |payload: &[u8]| {
+            let mut frame = SYNC.to_vec();
+            frame.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+            frame.extend_from_slice(&crc32(payload).to_le_bytes());
+            frame.extend_from_slice(payload);
+            frame
+        };
+        let decoder = FrameDecoder::new();
+        for payload in [&b""[..], b"[1] 2", b"{\"a\":", b"\xff"] {
+            let error = decoder.decode_frame(&frame(payload)).unwrap_err();
+            assert_eq!(
+                error.kind(),
+                FrameErrorKind::InvalidPayload,
+                "{:?}",
+                payload
+            );
+        }
+        assert_eq!(decoder.decode_frame(&frame(b" 7 ")), Ok(json!(7)));
+        let mut truncated = frame(b"[1, 2]");
+        truncated.pop();
+        assert_eq!(
+            decoder.decode_frame(&truncated).unwrap_err().message(),
+            "Frame holds 5 payload bytes, its header announces 6"
+        );
+
+        let small = FrameEncoder::new().max_frame_len(4);
+        assert_eq!(
+            small.encode(&json!("long")).unwrap_err().kind(),
+            FrameErrorKind::TooLarge
+        );
+    }
+}
@@ -0,0 +1,204 @@
+//! Generators - arbitrary JSON documents for fuzzing and property tests
+//!
+//! With the `arbitrary` feature, [`JsonValue`], [`JsonNumber`] and
+//! [`JsonObject`] implement `arbitrary::Arbitrary`, so cargo-fuzz targets
+//! (see `fuzz/`) can take structured documents instead of raw bytes. With
+//! the `proptest` feature, [`json_value`] and friends are proptest
+//! strategies for the same trees.
+//!
+//! Both produce only values that JSON text can express: no `RawString`,
+//! `Extension` or `Tagged` values, finite numbers, and nesting well below
+//! [`DEFAULT_MAX_DEPTH`](crate::options::DEFAULT_MAX_DEPTH). Serializing a
+//! generated value and parsing the text back therefore gives an equal value.
+//!
+//! # Examples
+//!
+//! ```
+//! # #[cfg(feature = "proptest")] {
+//! use json_parser_rs::generate::json_value;
+//! use json_parser_rs::parse_json;
+//! use proptest::prelude::*;
+//!
+//! proptest!(|(value in json_value())| {
+//!     prop_assert_eq!(parse_json(&value.to_json_string()).unwrap(), value);
+//! });
+//! # }
+//! ```
+
+#[cfg(feature = "arbitrary")]
+use arbitrary::{Arbitrary, Unstructured};
+#[cfg(feature = "proptest")]
+use proptest::prelude::*;
+
+use crate::json::JsonValue;
+use crate::number::JsonNumber;
+use crate::object::JsonObject;
+
+/// Nesting depth of generated documents unless a caller asks for more
+pub const DEFAULT_DEPTH: u32 = 8;
+
+#[cfg(feature = "arbitrary")]
+impl<'a> Arbitrary<'a> for JsonNumber {
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+        Ok(match u.int_in_range(0..=2)? {
+            0 => u64::arbitrary(u)?.into(),
+            1 => i64::arbitrary(u)?.into(),
+            // NaN and infinities have no JSON form
+            _ => JsonNumber::from_f64(f64::arbitrary(u)?).unwrap_or_else(|| 0u64.into()),
+        })
+    }
+}
+
+#[cfg(feature = "arbitrary")]
+impl<'a> Arbitrary<'a> for JsonValue {
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+        arbitrary_value(u, DEFAULT_DEPTH)
+    }
+}
+
+#[cfg(feature = "arbitrary")]
+impl<'a> Arbitrary<'a> for JsonObject {
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+        arbitrary_object(u, DEFAULT_DEPTH - 1)
+    }
+}
+
+/// A value nested at most `depth` levels deep, for fuzz targets that need a
+/// different bound than the `Arbitrary` impl's [`DEFAULT_DEPTH`]
+#[cfg(feature = "arbitrary")]
+pub fn arbitrary_value(u: &mut Unstructured<'_>, depth: u32) -> arbitrary::Result<JsonValue> {
+    let max_kind = if depth == 0 { 3 } else { 5 };
+    Ok(match u.int_in_range(0..=max_kind)? {
+        0 => JsonValue::Null,
+        1 => JsonValue::Boolean(bool::arbitrary(u)?),
+        2 => JsonValue::Number(JsonNumber::arbitrary(u)?),
+        3 => JsonValue::String(String::arbitrary(u)?),
+        4 => {
+            let mut elements = Vec::new();
+            u.arbitrary_loop(None, Some(16), |u| {
+                elements.push(arbitrary_value(u, depth - 1)?);
+                Ok(std::ops::ControlFlow::Continue(()))
+            })?;
+            JsonValue::Array(elements)
+        }
+        _ => JsonValue::Object(arbitrary_object(u, depth - 1)?),
+    })
+}
+
+/// An object whose members are nested at most `depth` levels deep
+#[cfg(feature = "arbitrary")]
+fn arbitrary_object(u: &mut Unstructured<'_>, depth: u32) -> arbitrary::Result<JsonObject> {
+    let mut object = JsonObject::new();
+    u.arbitrary_loop(None, Some(16), |u| {
+        let key = String::arbitrary(u)?;
+        object.insert(key, arbitrary_value(u, depth)?);
+        Ok(std::ops::ControlFlow::Continue(()))
+    })?;
+    Ok(object)
+}
+
+/// Any finite number: unsigned and negative integers across the full 64-bit
+/// range, and floats including subnormals and `-0.0`
+#[cfg(feature = "proptest")]
+pub fn json_number() -> impl Strategy<Value = JsonNumber> {
+    prop_oneof![
+        any::<u64>().prop_map(JsonNumber::from),
+        any::<i64>().prop_map(JsonNumber::from),
+        any::<f64>().prop_filter_map("not finite", JsonNumber::from_f64),
+    ]
+}
+
+/// Short strings of arbitrary characters, including control characters,
+/// quotes, backslashes and characters outside the BMP
+#[cfg(feature = "proptest")]
+pub fn json_string() -> impl Strategy<Value = String> {
+    let character = prop_oneof![
+        3 => any::<char>(),
+        1 => prop::sample::select(vec!['"', '\\', '/', '\n', '\t', '\u{0}', '\u{1f}', '\u{7f}']),
+    ];
+    prop::collection::vec(character, 0..16).prop_map(String::from_iter)
+}
+
+/// Documents nested at most [`DEFAULT_DEPTH`] levels deep
+#[cfg(feature = "proptest")]
+pub fn json_value() -> impl Strategy<Value = JsonValue> {
+    json_value_with_depth(DEFAULT_DEPTH)
+}
+
+/// Documents nested at most `depth` levels deep, with containers of up to
+/// eight elements
+#[cfg(feature = "proptest")]
+pub fn json_value_with_depth(depth: u32) -> impl Strategy<Value = JsonValue> {
+    let leaf = prop_oneof![
+        Just(JsonValue::Null),
+        any::<bool>().prop_map(JsonValue::Boolean),
+        json_number().prop_map(JsonValue::Number),
+        json_string().prop_map(JsonValue::String),
+    ];
+    leaf.prop_recursive(depth, 256, 8, |inner| {
+        prop_oneof![
+            prop::collection::vec(inner.clone(), 0..8).prop_map(JsonValue::Array),
+            prop::collection::vec((json_string(), inner), 0..8)
+                .prop_map(|members| JsonValue::Object(members.into_iter().collect())),
+        ]
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_json;
+
+    #[cfg(feature = "arbitrary")]
+    #[test]
+    fn test_arbitrary_values_round_trip() {
+        let mut seed = 0x9e37_79b9_7f4a_7c15u64;
+        for _ in 0..200 {
+            let bytes: Vec<u8> = (0..512)
+                .map(|_| {
+                    seed = seed.wrapping_mul(6_364_136_223_846_793_005).wrapping_add(1);
+                    (seed >> 56) as u8
+                })
+                .collect();
+            let value = JsonValue::arbitrary(&mut Unstructured::new(&bytes)).unwrap();
+            assert_eq!(parse_json(&value.to_json_string()).unwrap(), value);
+        }
+        let empty = JsonValue::arbitrary(&mut Unstructured::new(&[])).unwrap();
+        assert_eq!(empty, JsonValue::Null);
+    }
+
+    #[cfg(feature = "proptest")]
+    proptest! {
+        #[test]
+        fn test_serialized_values_parse_back(value in json_value()) {
+            let text = value.to_json_string();
+            prop_assert_eq!(&parse_json(&text).unwrap(), &value);
+            prop_assert_eq!(&parse_json(&value.to_pretty_string()).unwrap(), &value);
+            let borrowed = crate::parse_json_ref(&text).unwrap();
+            prop_assert_eq!(&borrowed.into_owned(), &value);
+        }
+
+        #[test]
+        fn test_damaged_documents_do_not_panic(
+            value in json_value(),
+            cut in any::<prop::sample::Index>(),
+            byte in any::<u8>(),
+        ) {
+            let text = value.to_json_string();
+            let mut end = cut.index(text.len() + 1);
+            while !text.is_char_boundary(end) {
+                end -= 1;
+            }
+            let _ = parse_json(&text[..end]);
+            let mut damaged = text.into_bytes();
+            if !damaged.is_empty() {
+                let at = cut.index(damaged.len());
+                damaged[at] = byte;
+            }
+            if let Ok(damaged) = std::str::from_utf8(&damaged) {
+                let _ = parse_json(damaged);
+                let _ = crate::parse_json5(damaged);
+            }
+        }
+    }
+}
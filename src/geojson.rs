@@ -0,0 +1,729 @@
+//! GeoJSON - validated views of RFC 7946 documents
+//!
+//! [`GeoJson::from_value`] checks the structure of a parsed document once
+//! (object types, coordinate nesting, ring closure, `bbox` members) and
+//! returns borrowed wrappers whose accessors can then rely on it. The
+//! wrappers hold a reference into the `JsonValue` tree, so foreign members
+//! and properties stay available through [`Feature::as_value`] and friends.
+//!
+//! # Examples
+//!
+//! ```
+//! use json_parser_rs::geojson::{BoundingBox, GeoJson};
+//! use json_parser_rs::parse_json;
+//!
+//! let value = parse_json(r#"{
+//!     "type": "FeatureCollection",
+//!     "features": [
+//!         {"type": "Feature", "properties": {"name": "Dinagat"},
+//!          "geometry": {"type": "Point", "coordinates": [125.6, 10.1]}},
+//!         {"type": "Feature", "properties": null,
+//!          "geometry": {"type": "LineString", "coordinates": [[102.0, 0.0], [103.0, 1.0]]}}
+//!     ]
+//! }"#).unwrap();
+//!
+//! let GeoJson::FeatureCollection(collection) = GeoJson::from_value(&value).unwrap() else {
+//!     unreachable!()
+//! };
+//! let names: Vec<_> = collection.features().filter_map(|f| f.property("name")?.as_str()).collect();
+//! assert_eq!(names, ["Dinagat"]);
+//! assert_eq!(
+//!     collection.bbox(),
+//!     Some(BoundingBox { west: 102.0, south: 0.0, east: 125.6, north: 10.1 })
+//! );
+//!
+//! let invalid = parse_json(r#"{"type": "LineString", "coordinates": [[0, 0]]}"#).unwrap();
+//! assert_eq!(
+//!     GeoJson::from_value(&invalid).unwrap_err().to_string(),
+//!     "/coordinates: A LineString needs at least 2 positions"
+//! );
+//! ```
+
+use std::fmt;
+
+use crate::json::JsonValue;
+use crate::pointer::JsonPointer;
+use crate::view::{ArrayView, ObjectView};
+
+/// Structural problem in a GeoJSON document
+#[derive(Debug, Clone, PartialEq)]
+pub struct GeoJsonError {
+    /// Location of the offending value
+    pub path: JsonPointer,
+    pub message: String,
+}
+
+/// `/path: message`, with `(root)` for the document itself
+impl fmt::Display for GeoJsonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let path = self.path.to_string();
+        let path = if path.is_empty() { "(root)" } else { &path };
+        write!(f, "{}: {}", path, self.message)
+    }
+}
+
+impl std::error::Error for GeoJsonError {}
+
+/// The `type` of a geometry object
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GeometryType {
+    Point,
+    MultiPoint,
+    LineString,
+    MultiLineString,
+    Polygon,
+    MultiPolygon,
+    GeometryCollection,
+}
+
+impl GeometryType {
+    fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "Point" => GeometryType::Point,
+            "MultiPoint" => GeometryType::MultiPoint,
+            "LineString" => GeometryType::LineString,
+            "MultiLineString" => GeometryType::MultiLineString,
+            "Polygon" => GeometryType::Polygon,
+            "MultiPolygon" => GeometryType::MultiPolygon,
+            "GeometryCollection" => GeometryType::GeometryCollection,
+            _ => return None,
+        })
+    }
+
+    /// Nesting depth of the `coordinates` arrays around a position, `None`
+    /// for collections
+    fn coordinate_depth(self) -> Option<usize> {
+        match self {
+            GeometryType::Point => Some(0),
+            GeometryType::MultiPoint | GeometryType::LineString => Some(1),
+            GeometryType::MultiLineString | GeometryType::Polygon => Some(2),
+            GeometryType::MultiPolygon => Some(3),
+            GeometryType::GeometryCollection => None,
+        }
+    }
+}
+
+/// Longitude, latitude and optional altitude of a position
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Position {
+    pub lon: f64,
+    pub lat: f64,
+    pub alt: Option<f64>,
+}
+
+impl Position {
+    /// Position of a validated coordinate array
+    fn from_value(value: &JsonValue) -> Self {
+        let number = |index| value.get_index(index).and_then(JsonValue::as_f64);
+        Position {
+            lon: number(0).unwrap_or_default(),
+            lat: number(1).unwrap_or_default(),
+            alt: number(2),
+        }
+    }
+}
+
+/// Two-dimensional extent; computed boxes never cross the antimeridian
+/// (`west <= east` always holds)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoundingBox {
+    pub west: f64,
+    pub south: f64,
+    pub east: f64,
+    pub north: f64,
+}
+
+impl BoundingBox {
+    /// Smallest box around `positions`, `None` if there are none
+    pub fn from_positions(positions: impl IntoIterator<Item = Position>) -> Option<Self> {
+        positions.into_iter().fold(None, |bbox, p| {
+            let bbox = bbox.unwrap_or(BoundingBox {
+                west: p.lon,
+                south: p.lat,
+                east: p.lon,
+                north: p.lat,
+            });
+            Some(BoundingBox {
+                west: bbox.west.min(p.lon),
+                south: bbox.south.min(p.lat),
+                east: bbox.east.max(p.lon),
+                north: bbox.north.max(p.lat),
+            })
+        })
+    }
+
+    /// Smallest box containing both
+    pub fn union(self, other: BoundingBox) -> BoundingBox {
+        BoundingBox {
+            west: self.west.min(other.west),
+            south: self.south.min(other.south),
+            east: self.east.max(other.east),
+            north: self.north.max(other.north),
+        }
+    }
+
+    pub fn contains(&self, position: Position) -> bool {
+        (self.west..=self.east).contains(&position.lon)
+            && (self.south..=self.north).contains(&position.lat)
+    }
+
+    /// `[west, south, east, north]`, the form of a `bbox` member
+    pub fn to_json(&self) -> JsonValue {
+        JsonValue::Array(
+            [self.west, self.south, self.east, self.north]
+                .into_iter()
+                .map(JsonValue::from)
+                .collect(),
+        )
+    }
+}
+
+fn union_all(boxes: impl IntoIterator<Item = Option<BoundingBox>>) -> Option<BoundingBox> {
+    boxes.into_iter().flatten().reduce(BoundingBox::union)
+}
+
+/// A validated GeoJSON document
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GeoJson<'a> {
+    Geometry(Geometry<'a>),
+    Feature(Feature<'a>),
+    FeatureCollection(FeatureCollection<'a>),
+}
+
+impl<'a> GeoJson<'a> {
+    /// Validate `value` as any GeoJSON object
+    pub fn from_value(value: &'a JsonValue) -> Result<Self, GeoJsonError> {
+        match value.get("type").and_then(JsonValue::as_str) {
+            Some("Feature") => Feature::from_value(value).map(GeoJson::Feature),
+            Some("FeatureCollection") => {
+                FeatureCollection::from_value(value).map(GeoJson::FeatureCollection)
+            }
+            _ => Geometry::from_value(value).map(GeoJson::Geometry),
+        }
+    }
+
+    pub fn as_value(&self) -> &'a JsonValue {
+        match self {
+            GeoJson::Geometry(geometry) => geometry.value,
+            GeoJson::Feature(feature) => feature.value,
+            GeoJson::FeatureCollection(collection) => collection.value,
+        }
+    }
+
+    /// The `bbox` member if present, computed from the coordinates otherwise
+    pub fn bbox(&self) -> Option<BoundingBox> {
+        match self {
+            GeoJson::Geometry(geometry) => geometry.bbox(),
+            GeoJson::Feature(feature) => feature.bbox(),
+            GeoJson::FeatureCollection(collection) => collection.bbox(),
+        }
+    }
+}
+
+/// The first four numbers of a validated `bbox` member; altitudes of a
+/// six-number box are skipped
+fn declared_bbox(value: &JsonValue) -> Option<BoundingBox> {
+    let numbers: Vec<f64> = value
+        .get("bbox")?
+        .as_array()?
+        .iter()
+        .filter_map(JsonValue::as_f64)
+        .collect();
+    let dimensions = numbers.len() / 2;
+    Some(BoundingBox {
+        west: numbers[0],
+        south: numbers[1],
+        east: numbers[dimensions],
+        north: numbers[dimensions + 1],
+    })
+}
+
+/// A validated geometry object
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Geometry<'a> {
+    value: &'a JsonValue,
+}
+
+impl<'a> Geometry<'a> {
+    /// Validate `value` as a geometry object
+    pub fn from_value(value: &'a JsonValue) -> Result<Self, GeoJsonError> {
+        Validator::default().geometry(value)?;
+        Ok(Geometry { value })
+    }
+
+    pub fn as_value(&self) -> &'a JsonValue {
+        self.value
+    }
+
+    pub fn geometry_type(&self) -> GeometryType {
+        self.value
+            .get("type")
+            .and_then(JsonValue::as_str)
+            .and_then(GeometryType::from_name)
+            .expect("validated geometry type")
+    }
+
+    /// The raw `coordinates` member; `None` for a `GeometryCollection`
+    pub fn coordinates(&self) -> Option<&'a JsonValue> {
+        self.value.get("coordinates")
+    }
+
+    /// Members of a `GeometryCollection`, empty for other types
+    pub fn geometries(&self) -> impl Iterator<Item = Geometry<'a>> + use<'a> {
+        self.value
+            .get("geometries")
+            .and_then(JsonValue::as_array)
+            .into_iter()
+            .flatten()
+            .map(|value| Geometry { value })
+    }
+
+    /// The position of a `Point`
+    pub fn point(&self) -> Option<Position> {
+        if self.geometry_type() != GeometryType::Point {
+            return None;
+        }
+        self.coordinates().map(Position::from_value)
+    }
+
+    /// The positions of a `LineString`
+    pub fn line_string(&self) -> Option<Vec<Position>> {
+        if self.geometry_type() != GeometryType::LineString {
+            return None;
+        }
+        positions_of(self.coordinates()?)
+    }
+
+    /// The rings of a `Polygon`, exterior ring first
+    pub fn polygon(&self) -> Option<Vec<Vec<Position>>> {
+        if self.geometry_type() != GeometryType::Polygon {
+            return None;
+        }
+        self.coordinates()?
+            .as_array()?
+            .iter()
+            .map(positions_of)
+            .collect()
+    }
+
+    /// Every position of the geometry, including the members of a collection
+    pub fn positions(&self) -> Vec<Position> {
+        let mut positions = Vec::new();
+        self.collect_positions(&mut positions);
+        positions
+    }
+
+    fn collect_positions(&self, positions: &mut Vec<Position>) {
+        match self.geometry_type().coordinate_depth() {
+            Some(depth) => {
+                if let Some(coordinates) = self.coordinates() {
+                    flatten_positions(coordinates, depth, positions);
+                }
+            }
+            None => {
+                for geometry in self.geometries() {
+                    geometry.collect_positions(positions);
+                }
+            }
+        }
+    }
+
+    /// The `bbox` member if present, computed from the coordinates otherwise
+    pub fn bbox(&self) -> Option<BoundingBox> {
+        declared_bbox(self.value).or_else(|| BoundingBox::from_positions(self.positions()))
+    }
+}
+
+fn positions_of(value: &JsonValue) -> Option<Vec<Position>> {
+    Some(value.as_array()?.iter().map(Position::from_value).collect())
+}
+
+fn flatten_positions(value: &JsonValue, depth: usize, positions: &mut Vec<Position>) {
+    if depth == 0 {
+        positions.push(Position::from_value(value));
+    } else if let Some(elements) = value.as_array() {
+        for element in elements {
+            flatten_positions(element, depth - 1, positions);
+        }
+    }
+}
+
+/// A validated `Feature` object
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Feature<'a> {
+    value: &'a JsonValue,
+}
+
+impl<'a> Feature<'a> {
+    /// Validate `value` as a `Feature`
+    pub fn from_value(value: &'a JsonValue) -> Result<Self, GeoJsonError> {
+        Validator::default().feature(value)?;
+        Ok(Feature { value })
+    }
+
+    pub fn as_value(&self) -> &'a JsonValue {
+        self.value
+    }
+
+    /// The `id` member, a string or a number
+    pub fn id(&self) -> Option<&'a JsonValue> {
+        self.value.get("id")
+    }
+
+    /// `None` for an unlocated feature (`"geometry": null`)
+    pub fn geometry(&self) -> Option<Geometry<'a>> {
+        let value = self.value.get("geometry")?;
+        value.is_object().then_some(Geometry { value })
+    }
+
+    /// `None` if the member is missing or `null`
+    pub fn properties(&self) -> Option<ObjectView<'a>> {
+        self.value.get("properties")?.as_object()
+    }
+
+    pub fn property(&self, name: &str) -> Option<&'a JsonValue> {
+        self.properties()?.get(name)
+    }
+
+    /// The `bbox` member if present, computed from the geometry otherwise
+    pub fn bbox(&self) -> Option<BoundingBox> {
+        declared_bbox(self.value).or_else(|| self.geometry()?.bbox())
+    }
+}
+
+/// A validated `FeatureCollection` object
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FeatureCollection<'a> {
+    value: &'a JsonValue,
+}
+
+impl<'a> FeatureCollection<'a> {
+    /// Validate `value` as a `FeatureCollection`
+    pub fn from_value(value: &'a JsonValue) -> Result<Self, GeoJsonError> {
+        Validator::default().feature_collection(value)?;
+        Ok(FeatureCollection { value })
+    }
+
+    pub fn as_value(&self) -> &'a JsonValue {
+        self.value
+    }
+
+    fn members(&self) -> &'a [JsonValue] {
+        self.value
+            .get("features")
+            .and_then(JsonValue::as_array)
+            .map_or(&[], ArrayView::as_slice)
+    }
+
+    pub fn features(&self) -> impl Iterator<Item = Feature<'a>> + use<'a> {
+        self.members().iter().map(|value| Feature { value })
+    }
+
+    pub fn len(&self) -> usize {
+        self.members().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.members().is_empty()
+    }
+
+    /// The `bbox` member if present, the union of the feature boxes otherwise
+    pub fn bbox(&self) -> Option<BoundingBox> {
+        declared_bbox(self.value).or_else(|| union_all(self.features().map(|f| f.bbox())))
+    }
+}
+
+/// Structure checks with the current location for error messages
+#[derive(Default)]
+struct Validator {
+    path: Vec<String>,
+}
+
+impl Validator {
+    fn error(&self, message: impl Into<String>) -> GeoJsonError {
+        GeoJsonError {
+            path: JsonPointer::from_tokens(&self.path),
+            message: message.into(),
+        }
+    }
+
+    fn at<T>(
+        &mut self,
+        token: impl ToString,
+        check: impl FnOnce(&mut Self) -> Result<T, GeoJsonError>,
+    ) -> Result<T, GeoJsonError> {
+        self.path.push(token.to_string());
+        let result = check(self)?;
+        self.path.pop();
+        Ok(result)
+    }
+
+    /// The object's `type` member, which must equal one of `expected`
+    fn object_type<'v>(&self, value: &'v JsonValue, what: &str) -> Result<&'v str, GeoJsonError> {
+        if !value.is_object() {
+            return Err(self.error(format!("Expected a {} object", what)));
+        }
+        value
+            .get("type")
+            .and_then(JsonValue::as_str)
+            .ok_or_else(|| self.error("Missing \"type\" member"))
+    }
+
+    fn bbox(&mut self, value: &JsonValue) -> Result<(), GeoJsonError> {
+        let Some(bbox) = value.get("bbox") else {
+            return Ok(());
+        };
+        self.at("bbox", |v| {
+            let numbers = bbox.as_array().filter(|numbers| {
+                numbers.iter().all(JsonValue::is_number) && matches!(numbers.len(), 4 | 6)
+            });
+            match numbers {
+                Some(_) => Ok(()),
+                None => Err(v.error("A bbox must be an array of 4 or 6 numbers")),
+            }
+        })
+    }
+
+    fn geometry(&mut self, value: &JsonValue) -> Result<(), GeoJsonError> {
+        let name = self.object_type(value, "geometry")?;
+        let geometry_type = GeometryType::from_name(name)
+            .ok_or_else(|| self.error(format!("Unknown geometry type \"{}\"", name)))?;
+        self.bbox(value)?;
+        if geometry_type == GeometryType::GeometryCollection {
+            let geometries = value
+                .get("geometries")
+                .ok_or_else(|| self.error("Missing \"geometries\" member"))?;
+            return self.at("geometries", |v| {
+                let members = geometries
+                    .as_array()
+                    .ok_or_else(|| v.error("Expected an array of geometries"))?;
+                for (index, member) in members.iter().enumerate() {
+                    v.at(index, |v| v.geometry(member))?;
+                }
+                Ok(())
+            });
+        }
+        let coordinates = value
+            .get("coordinates")
+            .ok_or_else(|| self.error("Missing \"coordinates\" member"))?;
+        self.at("coordinates", |v| match geometry_type {
+            GeometryType::Point => v.position(coordinates),
+            GeometryType::MultiPoint => v.each(coordinates, Self::position),
+            GeometryType::LineString => v.line_string(coordinates),
+            GeometryType::MultiLineString => v.each(coordinates, Self::line_string),
+            GeometryType::Polygon => v.polygon(coordinates),
+            GeometryType::MultiPolygon => v.each(coordinates, Self::polygon),
+            GeometryType::GeometryCollection => unreachable!(),
+        })
+    }
+
+    fn each(
+        &mut self,
+        value: &JsonValue,
+        check: fn(&mut Self, &JsonValue) -> Result<(), GeoJsonError>,
+    ) -> Result<(), GeoJsonError> {
+        let elements = value
+            .as_array()
+            .ok_or_else(|| self.error("Expected an array"))?;
+        for (index, element) in elements.iter().enumerate() {
+            self.at(index, |v| check(v, element))?;
+        }
+        Ok(())
+    }
+
+    fn position(&mut self, value: &JsonValue) -> Result<(), GeoJsonError> {
+        match value.as_array() {
+            Some(numbers) if numbers.len() >= 2 && numbers.iter().all(JsonValue::is_number) => {
+                Ok(())
+            }
+            _ => Err(self.error("A position must be an array of at least 2 numbers")),
+        }
+    }
+
+    fn line_string(&mut self, value: &JsonValue) -> Result<(), GeoJsonError> {
+        self.each(value, Self::position)?;
+        if value.as_array().map_or(0, |elements| elements.len()) < 2 {
+            return Err(self.error("A LineString needs at least 2 positions"));
+        }
+        Ok(())
+    }
+
+    fn polygon(&mut self, value: &JsonValue) -> Result<(), GeoJsonError> {
+        self.each(value, |v, ring| {
+            v.each(ring, Self::position)?;
+            let positions = ring.as_array().map_or(&[][..], ArrayView::as_slice);
+            if positions.len() < 4 {
+                return Err(v.error("A linear ring needs at least 4 positions"));
+            }
+            if positions.first() != positions.last() {
+                return Err(v.error("A linear ring must end at its first position"));
+            }
+            Ok(())
+        })
+    }
+
+    fn feature(&mut self, value: &JsonValue) -> Result<(), GeoJsonError> {
+        if self.object_type(value, "Feature")? != "Feature" {
+            return Err(self.error("Expected \"type\": \"Feature\""));
+        }
+        self.bbox(value)?;
+        if let Some(id) = value.get("id")
+            && !(id.is_string() || id.is_number())
+        {
+            return self.at("id", |v| {
+                Err(v.error("A feature id must be a string or a number"))
+            });
+        }
+        if let Some(properties) = value.get("properties")
+            && !(properties.is_object() || properties.is_null())
+        {
+            return self.at("properties", |v| Err(v.error("Expected an object or null")));
+        }
+        match value.get("geometry") {
+            None => Err(self.error("Missing \"geometry\" member")),
+            Some(JsonValue::Null) => Ok(()),
+            Some(geometry) => self.at("geometry", |v| v.geometry(geometry)),
+        }
+    }
+
+    fn feature_collection(&mut self, value: &JsonValue) -> Result<(), GeoJsonError> {
+        if self.object_type(value, "FeatureCollection")? != "FeatureCollection" {
+            return Err(self.error("Expected \"type\": \"FeatureCollection\""));
+        }
+        self.bbox(value)?;
+        let features = value
+            .get("features")
+            .ok_or_else(|| self.error("Missing \"features\" member"))?;
+        self.at("features", |v| v.each(features, Self::feature))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json;
+
+    #[test]
+    fn test_geometry_accessors() {
+        let polygon = json!({
+            "type": "Polygon",
+            "coordinates": [[[0, 0], [4, 0], [4, 3], [0, 0]], [[1, 1], [2, 1], [2, 2], [1, 1]]]
+        });
+        let geometry = Geometry::from_value(&polygon).unwrap();
+        assert_eq!(geometry.geometry_type(), GeometryType::Polygon);
+        assert_eq!(geometry.polygon().unwrap().len(), 2);
+        assert_eq!(geometry.point(), None);
+        assert_eq!(
+            geometry.bbox(),
+            Some(BoundingBox {
+                west: 0.0,
+                south: 0.0,
+                east: 4.0,
+                north: 3.0
+            })
+        );
+
+        let collection = json!({
+            "type": "GeometryCollection",
+            "geometries": [
+                {"type": "Point", "coordinates": [-1, 5, 120]},
+                {"type": "MultiPoint", "coordinates": [[2, 2], [3, -1]]}
+            ]
+        });
+        let geometry = Geometry::from_value(&collection).unwrap();
+        let point = geometry.geometries().next().unwrap().point().unwrap();
+        assert_eq!(
+            point,
+            Position {
+                lon: -1.0,
+                lat: 5.0,
+                alt: Some(120.0)
+            }
+        );
+        assert_eq!(geometry.positions().len(), 3);
+        assert_eq!(
+            geometry.bbox().unwrap().to_json(),
+            json!([-1.0, -1.0, 3.0, 5.0])
+        );
+
+        let declared = json!({"type": "Point", "coordinates": [1, 1], "bbox": [0, 0, 0, 2, 2, 9]});
+        let bbox = Geometry::from_value(&declared).unwrap().bbox().unwrap();
+        assert_eq!(
+            bbox,
+            BoundingBox {
+                west: 0.0,
+                south: 0.0,
+                east: 2.0,
+                north: 2.0
+            }
+        );
+    }
+
+    #[test]
+    fn test_features() {
+        let value = json!({
+            "type": "FeatureCollection",
+            "features": [
+                {"type": "Feature", "id": 7, "geometry": null, "properties": {"a": 1}},
+                {"type": "Feature", "geometry": {"type": "Point", "coordinates": [3, 4]}}
+            ]
+        });
+        let collection = FeatureCollection::from_value(&value).unwrap();
+        assert_eq!(collection.len(), 2);
+        let first = collection.features().next().unwrap();
+        assert_eq!(first.id(), Some(&json!(7)));
+        assert_eq!(first.geometry(), None);
+        assert_eq!(first.property("a"), Some(&json!(1)));
+        assert_eq!(
+            collection.bbox().unwrap().to_json(),
+            json!([3.0, 4.0, 3.0, 4.0])
+        );
+        assert!(matches!(
+            GeoJson::from_value(&value),
+            Ok(GeoJson::FeatureCollection(_))
+        ));
+    }
+
+    #[test]
+    fn test_validation_errors() {
+        let cases = [
+            (json!([1, 2]), "(root): Expected a geometry object"),
+            (
+                json!({"type": "Circle"}),
+                "(root): Unknown geometry type \"Circle\"",
+            ),
+            (
+                json!({"type": "Point", "coordinates": [1]}),
+                "/coordinates: A position must be an array of at least 2 numbers",
+            ),
+            (
+                json!({"type": "MultiPoint", "coordinates": [[1, 2], [1, "x"]]}),
+                "/coordinates/1: A position must be an array of at least 2 numbers",
+            ),
+            (
+                json!({"type": "Polygon", "coordinates": [[[0, 0], [1, 0], [1, 1], [0, 1]]]}),
+                "/coordinates/0: A linear ring must end at its first position",
+            ),
+            (
+                json!({"type": "Point", "coordinates": [0, 0], "bbox": [0, 0, 1]}),
+                "/bbox: A bbox must be an array of 4 or 6 numbers",
+            ),
+            (
+                json!({"type": "Feature", "properties": {}}),
+                "(root): Missing \"geometry\" member",
+            ),
+            (
+                json!({"type": "FeatureCollection", "features": [
+                    {"type": "Feature", "geometry": {"type": "GeometryCollection", "geometries": [{}]}}
+                ]}),
+                "/features/0/geometry/geometries/0: Missing \"type\" member",
+            ),
+        ];
+        for (value, message) in cases {
+            assert_eq!(
+                GeoJson::from_value(&value).unwrap_err().to_string(),
+                message
+            );
+        }
+    }
+}
@@ -0,0 +1,248 @@
+//! HAL - `application/hal+json` resources
+//!
+//! A HAL resource is a JSON object whose `_links` member maps relation
+//! names to link objects (or arrays of them) and whose `_embedded` member
+//! maps relation names to nested resources. All other members are the
+//! resource's state.
+//!
+//! # Examples
+//!
+//! ```
+//! use json_parser_rs::hypermedia::hal::HalResource;
+//! use json_parser_rs::parse_json;
+//!
+//! let value = parse_json(r#"{
+//!     "_links": {
+//!         "self": {"href": "/orders"},
+//!         "curies": [{"name": "acme", "href": "https://docs.acme.com/rels/{rel}", "templated": true}],
+//!         "acme:find": {"href": "/orders{?id}", "templated": true}
+//!     },
+//!     "_embedded": {
+//!         "acme:order": [
+//!             {"_links": {"self": {"href": "/orders/123"}}, "total": 30.0},
+//!             {"_links": {"self": {"href": "/orders/124"}}, "total": 20.0}
+//!         ]
+//!     },
+//!     "count": 2
+//! }"#).unwrap();
+//!
+//! let orders = HalResource::from_value(&value).unwrap();
+//! assert_eq!(orders.self_href(), Some("/orders"));
+//! assert_eq!(orders.property("count").and_then(|c| c.as_u64()), Some(2));
+//! assert_eq!(orders.link("acme:find").unwrap().expand(&[("id", "7")]), "/orders?id=7");
+//! assert_eq!(
+//!     orders.relation_url("acme:order").as_deref(),
+//!     Some("https://docs.acme.com/rels/order")
+//! );
+//!
+//! let totals: Vec<f64> = orders
+//!     .embedded("acme:order")
+//!     .iter()
+//!     .filter_map(|order| order.property("total")?.as_f64())
+//!     .collect();
+//! assert_eq!(totals, [30.0, 20.0]);
+//! ```
+
+use super::{Checker, HypermediaError, Link};
+use crate::json::JsonValue;
+use crate::view::ArrayView;
+
+/// A validated HAL resource
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HalResource<'a> {
+    value: &'a JsonValue,
+}
+
+impl<'a> HalResource<'a> {
+    /// Validate `value` and its embedded resources
+    ///
+    /// `_links` must map relations to link objects with an `href` (or
+    /// arrays of them) and `_embedded` must map relations to resource
+    /// objects (or arrays of them).
+    pub fn from_value(value: &'a JsonValue) -> Result<Self, HypermediaError> {
+        check_resource(&mut Checker::default(), value)?;
+        Ok(HalResource { value })
+    }
+
+    pub fn as_value(&self) -> &'a JsonValue {
+        self.value
+    }
+
+    /// Names of the link relations, in document order
+    pub fn relations(&self) -> impl Iterator<Item = &'a str> + use<'a> {
+        self.value
+            .get("_links")
+            .and_then(JsonValue::as_object)
+            .into_iter()
+            .flat_map(|links| links.keys().map(String::as_str))
+    }
+
+    /// All links of relation `rel`
+    pub fn links(&self, rel: &str) -> Vec<Link<'a>> {
+        one_or_many(self.value.get("_links").and_then(|links| links.get(rel)))
+            .iter()
+            .filter_map(Link::new)
+            .collect()
+    }
+
+    /// The first link of relation `rel`
+    pub fn link(&self, rel: &str) -> Option<Link<'a>> {
+        self.links(rel).into_iter().next()
+    }
+
+    /// `href` of the `self` link
+    pub fn self_href(&self) -> Option<&'a str> {
+        Some(self.link("self")?.href())
+    }
+
+    /// Documentation URL of a relation written as a CURIE (`prefix:rel`),
+    /// from the `curies` link named `prefix`
+    pub fn relation_url(&self, rel: &str) -> Option<String> {
+        let (prefix, reference) = rel.split_once(':')?;
+        let curie = self
+            .links("curies")
+            .into_iter()
+            .find(|curie| curie.get("name").and_then(JsonValue::as_str) == Some(prefix))?;
+        Some(curie.expand(&[("rel", reference)]))
+    }
+
+    /// Names of the embedded relations, in document order
+    pub fn embedded_relations(&self) -> impl Iterator<Item = &'a str> + use<'a> {
+        self.value
+            .get("_embedded")
+            .and_then(JsonValue::as_object)
+            .into_iter()
+            .flat_map(|embedded| embedded.keys().map(String::as_str))
+    }
+
+    /// Resources embedded under relation `rel`
+    pub fn embedded(&self, rel: &str) -> Vec<HalResource<'a>> {
+        one_or_many(
+            self.value
+                .get("_embedded")
+                .and_then(|embedded| embedded.get(rel)),
+        )
+        .iter()
+        .map(|value| HalResource { value })
+        .collect()
+    }
+
+    /// State members, without `_links` and `_embedded`
+    pub fn properties(&self) -> impl Iterator<Item = (&'a str, &'a JsonValue)> + use<'a> {
+        self.value
+            .as_object()
+            .into_iter()
+            .flat_map(|object| object.iter())
+            .filter(|(key, _)| *key != "_links" && *key != "_embedded")
+            .map(|(key, value)| (key.as_str(), value))
+    }
+
+    pub fn property(&self, name: &str) -> Option<&'a JsonValue> {
+        match name {
+            "_links" | "_embedded" => None,
+            _ => self.value.get(name),
+        }
+    }
+}
+
+fn one_or_many(value: Option<&JsonValue>) -> &[JsonValue] {
+    match value {
+        None => &[],
+        Some(value) => value
+            .as_array()
+            .map_or(std::slice::from_ref(value), ArrayView::as_slice),
+    }
+}
+
+fn check_resource(checker: &mut Checker, value: &JsonValue) -> Result<(), HypermediaError> {
+    checker.object(value, "a resource")?;
+    checker.member(value, "_links", |c, links| {
+        c.object(links, "a _links")?;
+        for (rel, link) in links.as_object().into_iter().flatten() {
+            c.at(rel, |c| {
+                each(c, link, |c, link| {
+                    c.object(link, "a link")?;
+                    c.string(link, "href")
+                })
+            })?;
+        }
+        Ok(())
+    })?;
+    checker.member(value, "_embedded", |c, embedded| {
+        c.object(embedded, "an _embedded")?;
+        for (rel, resource) in embedded.as_object().into_iter().flatten() {
+            c.at(rel, |c| each(c, resource, check_resource))?;
+        }
+        Ok(())
+    })
+}
+
+/// Check a value that may be a single item or an array of items
+fn each(
+    checker: &mut Checker,
+    value: &JsonValue,
+    check: fn(&mut Checker, &JsonValue) -> Result<(), HypermediaError>,
+) -> Result<(), HypermediaError> {
+    match value.as_array() {
+        Some(items) => {
+            for (index, item) in items.iter().enumerate() {
+                checker.at(index, |c| check(c, item))?;
+            }
+            Ok(())
+        }
+        None => check(checker, value),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json;
+
+    #[test]
+    fn test_links_and_embedded() {
+        let value = json!({
+            "_links": {"self": {"href": "/a"}, "item": [{"href": "/a/1"}, {"href": "/a/2"}]},
+            "_embedded": {"author": {"name": "Ada", "_links": {"self": {"href": "/people/ada"}}}},
+            "title": "A"
+        });
+        let resource = HalResource::from_value(&value).unwrap();
+        assert_eq!(resource.relations().collect::<Vec<_>>(), ["self", "item"]);
+        let items: Vec<&str> = resource.links("item").iter().map(Link::href).collect();
+        assert_eq!(items, ["/a/1", "/a/2"]);
+        assert_eq!(resource.link("missing"), None);
+        let author = resource.embedded("author")[0];
+        assert_eq!(author.self_href(), Some("/people/ada"));
+        assert_eq!(author.property("_links"), None);
+        assert_eq!(
+            resource.properties().map(|(k, _)| k).collect::<Vec<_>>(),
+            ["title"]
+        );
+        assert_eq!(resource.relation_url("item"), None);
+    }
+
+    #[test]
+    fn test_validation_errors() {
+        let cases = [
+            (json!([]), "(root): Expected a resource object, found []"),
+            (
+                json!({"_links": {"self": {}}}),
+                "/_links/self: Missing \"href\" member",
+            ),
+            (
+                json!({"_links": {"item": [{"href": "/1"}, {"href": 2}]}}),
+                "/_links/item/1: \"href\" must be a string, found 2",
+            ),
+            (
+                json!({"_embedded": {"items": [{}, 1]}}),
+                "/_embedded/items/1: Expected a resource object, found 1",
+            ),
+        ];
+        for (value, message) in cases {
+            assert_eq!(
+                HalResource::from_value(&value).unwrap_err().to_string(),
+                message
+            );
+        }
+    }
+}
@@ -0,0 +1,513 @@
+//! JSON:API - `application/vnd.api+json` documents
+//!
+//! A JSON:API document carries its primary `data` (one resource, an array
+//! of resources or `null`) and, in compound documents, the related
+//! resources in `included`. Resources refer to each other through
+//! `relationships` holding `{"type", "id"}` identifiers; [`Document`]
+//! indexes all resources so those identifiers can be resolved directly.
+//!
+//! # Examples
+//!
+//! ```
+//! use json_parser_rs::hypermedia::jsonapi::Document;
+//! use json_parser_rs::parse_json;
+//!
+//! let value = parse_json(r#"{
+//!     "data": [{
+//!         "type": "articles", "id": "1",
+//!         "attributes": {"title": "JSON:API paints my bikeshed!"},
+//!         "relationships": {"author": {"data": {"type": "people", "id": "9"}}},
+//!         "links": {"self": "http://example.com/articles/1"}
+//!     }],
+//!     "included": [{"type": "people", "id": "9", "attributes": {"name": "Dan"}}]
+//! }"#).unwrap();
+//!
+//! let document = Document::from_value(&value).unwrap();
+//! let article = document.resources()[0];
+//! assert_eq!(article.attribute("title").and_then(|t| t.as_str()), Some("JSON:API paints my bikeshed!"));
+//! assert_eq!(article.link("self").unwrap().href(), "http://example.com/articles/1");
+//!
+//! let author = document.related(&article, "author")[0];
+//! assert_eq!(author.attribute("name").and_then(|n| n.as_str()), Some("Dan"));
+//!
+//! let normalized = document.normalize();
+//! assert_eq!(normalized["articles"]["1"]["author"], parse_json(r#"{"type": "people", "id": "9"}"#).unwrap());
+//! assert_eq!(normalized["people"]["9"]["name"].as_str(), Some("Dan"));
+//! ```
+
+use std::collections::HashMap;
+
+use super::{Checker, HypermediaError, Link};
+use crate::json::JsonValue;
+use crate::object::JsonObject;
+use crate::view::{ArrayView, ObjectView};
+
+/// `type` and `id` of a resource
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ResourceId<'a> {
+    pub resource_type: &'a str,
+    pub id: &'a str,
+}
+
+impl<'a> ResourceId<'a> {
+    /// Identifier of a validated resource or resource identifier object
+    fn of(value: &'a JsonValue) -> Option<Self> {
+        Some(ResourceId {
+            resource_type: value.get("type")?.as_str()?,
+            id: value.get("id")?.as_str()?,
+        })
+    }
+
+    /// `{"type": ..., "id": ...}`
+    pub fn to_json(&self) -> JsonValue {
+        let mut object = JsonObject::new();
+        object.insert("type", JsonValue::from(self.resource_type));
+        object.insert("id", JsonValue::from(self.id));
+        JsonValue::Object(object)
+    }
+}
+
+/// Primary data of a document
+#[derive(Debug, Clone, PartialEq)]
+pub enum PrimaryData<'a> {
+    /// `"data": null` or no `data` member
+    None,
+    One(Resource<'a>),
+    Many(Vec<Resource<'a>>),
+}
+
+/// A validated JSON:API document
+#[derive(Debug, Clone)]
+pub struct Document<'a> {
+    value: &'a JsonValue,
+    /// Resources of `data` and `included` that have an id
+    index: HashMap<ResourceId<'a>, Resource<'a>>,
+}
+
+impl<'a> Document<'a> {
+    /// Validate the top-level structure, resource objects and relationships
+    pub fn from_value(value: &'a JsonValue) -> Result<Self, HypermediaError> {
+        check_document(&mut Checker::default(), value)?;
+        let mut document = Document {
+            value,
+            index: HashMap::new(),
+        };
+        let resources: Vec<Resource<'a>> = document
+            .resources()
+            .into_iter()
+            .chain(document.included())
+            .collect();
+        for resource in resources {
+            if let Some(id) = resource.identifier() {
+                document.index.entry(id).or_insert(resource);
+            }
+        }
+        Ok(document)
+    }
+
+    pub fn as_value(&self) -> &'a JsonValue {
+        self.value
+    }
+
+    pub fn data(&self) -> PrimaryData<'a> {
+        match self.value.get("data") {
+            Some(JsonValue::Array(resources)) => {
+                PrimaryData::Many(resources.iter().map(|value| Resource { value }).collect())
+            }
+            Some(value) if value.is_object() => PrimaryData::One(Resource { value }),
+            _ => PrimaryData::None,
+        }
+    }
+
+    /// Primary resources as a list, whatever the shape of `data`
+    pub fn resources(&self) -> Vec<Resource<'a>> {
+        match self.data() {
+            PrimaryData::None => Vec::new(),
+            PrimaryData::One(resource) => vec![resource],
+            PrimaryData::Many(resources) => resources,
+        }
+    }
+
+    /// Resources of a compound document's `included` member
+    pub fn included(&self) -> impl Iterator<Item = Resource<'a>> + use<'a> {
+        members(self.value, "included")
+            .iter()
+            .map(|value| Resource { value })
+    }
+
+    /// Error objects of an error document
+    pub fn errors(&self) -> &'a [JsonValue] {
+        members(self.value, "errors")
+    }
+
+    pub fn meta(&self) -> Option<ObjectView<'a>> {
+        self.value.get("meta")?.as_object()
+    }
+
+    /// Top-level link, e.g. `self` or `next` for pagination
+    pub fn link(&self, name: &str) -> Option<Link<'a>> {
+        Link::new(self.value.get("links")?.get(name)?)
+    }
+
+    /// The resource with identifier `id` from `data` or `included`
+    pub fn resolve(&self, id: ResourceId<'_>) -> Option<Resource<'a>> {
+        self.index.get(&id).copied()
+    }
+
+    /// Resources that `resource`'s relationship `name` refers to and that
+    /// are part of this document; identifiers of resources that were not
+    /// included are skipped
+    pub fn related(&self, resource: &Resource<'a>, name: &str) -> Vec<Resource<'a>> {
+        resource
+            .relationship(name)
+            .map(|relationship| relationship.ids())
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|id| self.resolve(id))
+            .collect()
+    }
+
+    /// All resources of the document as `{type: {id: resource}}`
+    ///
+    /// Each resource becomes one object with its `id`, `type`, attributes
+    /// and relationship linkage (an identifier, an array of identifiers or
+    /// `null`) side by side, so related resources are found with two
+    /// lookups. Attributes named like a relationship are shadowed by it.
+    pub fn normalize(&self) -> JsonValue {
+        let mut normalized = JsonObject::new();
+        let resources = self.resources().into_iter().chain(self.included());
+        for resource in resources {
+            let Some(ResourceId { resource_type, id }) = resource.identifier() else {
+                continue;
+            };
+            let mut flat = JsonObject::new();
+            flat.insert("id", JsonValue::from(id));
+            flat.insert("type", JsonValue::from(resource_type));
+            if let Some(attributes) = resource.attributes() {
+                flat.extend(
+                    attributes
+                        .iter()
+                        .map(|(key, value)| (key.as_str(), value.clone())),
+                );
+            }
+            for (name, relationship) in resource.relationships() {
+                if let Some(linkage) = relationship.value.get("data") {
+                    flat.insert(name, linkage.clone());
+                }
+            }
+
+            if !normalized.contains_key(resource_type) {
+                normalized.insert(resource_type, JsonValue::Object(JsonObject::new()));
+            }
+            if let Some(by_id) = normalized
+                .get_mut(resource_type)
+                .and_then(JsonValue::as_object_mut)
+                && !by_id.contains_key(id)
+            {
+                by_id.insert(id, JsonValue::Object(flat));
+            }
+        }
+        JsonValue::Object(normalized)
+    }
+}
+
+fn members<'a>(value: &'a JsonValue, key: &str) -> &'a [JsonValue] {
+    value
+        .get(key)
+        .and_then(JsonValue::as_array)
+        .map_or(&[], ArrayView::as_slice)
+}
+
+/// A resource object
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Resource<'a> {
+    value: &'a JsonValue,
+}
+
+impl<'a> Resource<'a> {
+    pub fn as_value(&self) -> &'a JsonValue {
+        self.value
+    }
+
+    pub fn resource_type(&self) -> &'a str {
+        self.value
+            .get("type")
+            .and_then(JsonValue::as_str)
+            .unwrap_or_default()
+    }
+
+    /// `None` for resources created by a client, which have no id yet
+    pub fn id(&self) -> Option<&'a str> {
+        self.value.get("id")?.as_str()
+    }
+
+    pub fn identifier(&self) -> Option<ResourceId<'a>> {
+        ResourceId::of(self.value)
+    }
+
+    pub fn attributes(&self) -> Option<ObjectView<'a>> {
+        self.value.get("attributes")?.as_object()
+    }
+
+    pub fn attribute(&self, name: &str) -> Option<&'a JsonValue> {
+        self.attributes()?.get(name)
+    }
+
+    pub fn relationships(&self) -> impl Iterator<Item = (&'a str, Relationship<'a>)> + use<'a> {
+        self.value
+            .get("relationships")
+            .and_then(JsonValue::as_object)
+            .into_iter()
+            .flat_map(|relationships| relationships.iter())
+            .map(|(name, value)| (name.as_str(), Relationship { value }))
+    }
+
+    pub fn relationship(&self, name: &str) -> Option<Relationship<'a>> {
+        let value = self.value.get("relationships")?.get(name)?;
+        Some(Relationship { value })
+    }
+
+    pub fn link(&self, name: &str) -> Option<Link<'a>> {
+        Link::new(self.value.get("links")?.get(name)?)
+    }
+
+    pub fn meta(&self) -> Option<ObjectView<'a>> {
+        self.value.get("meta")?.as_object()
+    }
+}
+
+/// Resource linkage of a relationship
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Linkage<'a> {
+    /// Empty to-one relationship (`"data": null`)
+    Null,
+    One(ResourceId<'a>),
+    Many(Vec<ResourceId<'a>>),
+}
+
+/// A relationship object
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Relationship<'a> {
+    value: &'a JsonValue,
+}
+
+impl<'a> Relationship<'a> {
+    pub fn as_value(&self) -> &'a JsonValue {
+        self.value
+    }
+
+    /// `None` if the relationship only has links or meta
+    pub fn data(&self) -> Option<Linkage<'a>> {
+        match self.value.get("data")? {
+            JsonValue::Array(ids) => Some(Linkage::Many(
+                ids.iter().filter_map(ResourceId::of).collect(),
+            )),
+            JsonValue::Null => Some(Linkage::Null),
+            id => ResourceId::of(id).map(Linkage::One),
+        }
+    }
+
+    /// Identifiers of the related resources, empty without linkage
+    pub fn ids(&self) -> Vec<ResourceId<'a>> {
+        match self.data() {
+            None | Some(Linkage::Null) => Vec::new(),
+            Some(Linkage::One(id)) => vec![id],
+            Some(Linkage::Many(ids)) => ids,
+        }
+    }
+
+    /// Relationship link, usually `self` or `related`
+    pub fn link(&self, name: &str) -> Option<Link<'a>> {
+        Link::new(self.value.get("links")?.get(name)?)
+    }
+
+    pub fn meta(&self) -> Option<ObjectView<'a>> {
+        self.value.get("meta")?.as_object()
+    }
+}
+
+fn check_document(checker: &mut Checker, value: &JsonValue) -> Result<(), HypermediaError> {
+    checker.object(value, "a document")?;
+    let has = |key| value.get(key).is_some();
+    if !has("data") && !has("errors") && !has("meta") {
+        return Err(checker.error("A document needs \"data\", \"errors\" or \"meta\""));
+    }
+    if has("data") && has("errors") {
+        return Err(checker.error("\"data\" and \"errors\" cannot both be present"));
+    }
+    checker.member(value, "data", |c, data| match data {
+        JsonValue::Null => Ok(()),
+        JsonValue::Array(resources) => each(c, resources, check_resource),
+        resource => check_resource(c, resource),
+    })?;
+    checker.member(value, "included", |c, included| match included.as_array() {
+        Some(resources) => each(c, resources.as_slice(), check_resource),
+        None => Err(c.error(format!(
+            "Expected an array of resources, found {}",
+            included
+        ))),
+    })?;
+    checker.member(value, "errors", |c, errors| match errors.as_array() {
+        Some(errors) => each(c, errors.as_slice(), |c, error| c.object(error, "an error")),
+        None => Err(c.error(format!("Expected an array of errors, found {}", errors))),
+    })?;
+    checker.member(value, "links", check_links)
+}
+
+fn check_resource(checker: &mut Checker, value: &JsonValue) -> Result<(), HypermediaError> {
+    checker.object(value, "a resource")?;
+    checker.string(value, "type")?;
+    if value.get("id").is_some() {
+        checker.string(value, "id")?;
+    }
+    checker.member(value, "attributes", |c, attributes| {
+        c.object(attributes, "an attributes")
+    })?;
+    checker.member(value, "relationships", |c, relationships| {
+        c.object(relationships, "a relationships")?;
+        for (name, relationship) in relationships.as_object().into_iter().flatten() {
+            c.at(name, |c| check_relationship(c, relationship))?;
+        }
+        Ok(())
+    })?;
+    checker.member(value, "links", check_links)
+}
+
+fn check_relationship(checker: &mut Checker, value: &JsonValue) -> Result<(), HypermediaError> {
+    checker.object(value, "a relationship")?;
+    if ["data", "links", "meta"]
+        .iter()
+        .all(|key| value.get(key).is_none())
+    {
+        return Err(checker.error("A relationship needs \"data\", \"links\" or \"meta\""));
+    }
+    let identifier = |c: &mut Checker, id: &JsonValue| {
+        c.object(id, "a resource identifier")?;
+        c.string(id, "type")?;
+        c.string(id, "id")
+    };
+    checker.member(value, "data", |c, data| match data {
+        JsonValue::Null => Ok(()),
+        JsonValue::Array(ids) => each(c, ids, identifier),
+        id => identifier(c, id),
+    })?;
+    checker.member(value, "links", check_links)
+}
+
+fn check_links(checker: &mut Checker, links: &JsonValue) -> Result<(), HypermediaError> {
+    checker.object(links, "a links")?;
+    for (name, link) in links.as_object().into_iter().flatten() {
+        if !link.is_null() && Link::new(link).is_none() {
+            return checker.at(name, |c| {
+                Err(c.error(format!(
+                    "A link must be a URL or an object with \"href\", found {}",
+                    link
+                )))
+            });
+        }
+    }
+    Ok(())
+}
+
+fn each(
+    checker: &mut Checker,
+    items: &[JsonValue],
+    check: impl Fn(&mut Checker, &JsonValue) -> Result<(), HypermediaError>,
+) -> Result<(), HypermediaError> {
+    for (index, item) in items.iter().enumerate() {
+        checker.at(index, |c| check(c, item))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json;
+
+    #[test]
+    fn test_relationships() {
+        let value = json!({
+            "data": {
+                "type": "articles", "id": "1",
+                "relationships": {
+                    "comments": {"data": [{"type": "comments", "id": "5"}, {"type": "comments", "id": "6"}]},
+                    "editor": {"data": null},
+                    "tags": {"links": {"related": "/articles/1/tags"}}
+                }
+            },
+            "included": [{"type": "comments", "id": "5", "attributes": {"body": "First!"}}]
+        });
+        let document = Document::from_value(&value).unwrap();
+        let PrimaryData::One(article) = document.data() else {
+            panic!("expected a single resource");
+        };
+        let comments = article.relationship("comments").unwrap();
+        assert_eq!(comments.ids().len(), 2);
+        let related = document.related(&article, "comments");
+        assert_eq!(related.len(), 1);
+        assert_eq!(related[0].attribute("body"), Some(&json!("First!")));
+        assert_eq!(
+            article.relationship("editor").unwrap().data(),
+            Some(Linkage::Null)
+        );
+        let tags = article.relationship("tags").unwrap();
+        assert_eq!(tags.data(), None);
+        assert_eq!(tags.link("related").unwrap().href(), "/articles/1/tags");
+
+        let id = ResourceId {
+            resource_type: "articles",
+            id: "1",
+        };
+        assert_eq!(document.resolve(id), Some(article));
+        assert_eq!(
+            document.normalize()["articles"]["1"],
+            json!({
+                "id": "1",
+                "type": "articles",
+                "comments": [{"type": "comments", "id": "5"}, {"type": "comments", "id": "6"}],
+                "editor": null
+            })
+        );
+    }
+
+    #[test]
+    fn test_validation_errors() {
+        let cases = [
+            (
+                json!({}),
+                "(root): A document needs \"data\", \"errors\" or \"meta\"",
+            ),
+            (
+                json!({"data": null, "errors": []}),
+                "(root): \"data\" and \"errors\" cannot both be present",
+            ),
+            (
+                json!({"data": [{"id": "1"}]}),
+                "/data/0: Missing \"type\" member",
+            ),
+            (
+                json!({"data": {"type": "a", "id": 1}}),
+                "/data: \"id\" must be a string, found 1",
+            ),
+            (
+                json!({"data": {"type": "a", "relationships": {"b": {}}}}),
+                "/data/relationships/b: A relationship needs \"data\", \"links\" or \"meta\"",
+            ),
+            (
+                json!({"data": {"type": "a", "relationships": {"b": {"data": [{"type": "b"}]}}}}),
+                "/data/relationships/b/data/0: Missing \"id\" member",
+            ),
+            (
+                json!({"meta": {}, "links": {"next": {"title": "x"}}}),
+                "/links/next: A link must be a URL or an object with \"href\", found {\"title\": \"x\"}",
+            ),
+        ];
+        for (value, message) in cases {
+            assert_eq!(
+                Document::from_value(&value).unwrap_err().to_string(),
+                message
+            );
+        }
+    }
+}
@@ -0,0 +1,232 @@
+//! Hypermedia - typed views of HAL and JSON:API documents
+//!
+//! Both conventions wrap resources in a fixed envelope (`_links` and
+//! `_embedded` for HAL; `data`, `included`, `relationships` and `links` for
+//! JSON:API). The views in [`hal`] and [`jsonapi`] check that envelope once
+//! and then give direct access to links, embedded or included resources and
+//! relationships, borrowing from the parsed `JsonValue`.
+
+pub mod hal;
+pub mod jsonapi;
+
+use std::fmt;
+
+use crate::json::JsonValue;
+use crate::pointer::JsonPointer;
+
+/// Structural problem in a HAL or JSON:API document
+#[derive(Debug, Clone, PartialEq)]
+pub struct HypermediaError {
+    /// Location of the offending value
+    pub path: JsonPointer,
+    pub message: String,
+}
+
+/// `/path: message`, with `(root)` for the document itself
+impl fmt::Display for HypermediaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let path = self.path.to_string();
+        let path = if path.is_empty() { "(root)" } else { &path };
+        write!(f, "{}: {}", path, self.message)
+    }
+}
+
+impl std::error::Error for HypermediaError {}
+
+/// A link: a bare URL string or a link object with an `href` member
+///
+/// # Examples
+///
+/// ```
+/// use json_parser_rs::hypermedia::Link;
+/// use json_parser_rs::json;
+///
+/// let value = json!({"href": "/orders{?page,size}", "templated": true});
+/// let link = Link::new(&value).unwrap();
+/// assert!(link.is_templated());
+/// assert_eq!(link.expand(&[("page", "2")]), "/orders?page=2");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Link<'a> {
+    value: &'a JsonValue,
+    href: &'a str,
+}
+
+impl<'a> Link<'a> {
+    /// View of a link string or link object; `None` without an `href`
+    pub fn new(value: &'a JsonValue) -> Option<Self> {
+        let href = match value.as_str() {
+            Some(href) => href,
+            None => value.get("href")?.as_str()?,
+        };
+        Some(Link { value, href })
+    }
+
+    pub fn as_value(&self) -> &'a JsonValue {
+        self.value
+    }
+
+    pub fn href(&self) -> &'a str {
+        self.href
+    }
+
+    /// Another member of a link object, such as `title`, `type` or `meta`
+    pub fn get(&self, name: &str) -> Option<&'a JsonValue> {
+        self.value.get(name)
+    }
+
+    /// `true` if the link object says its `href` is a URI template
+    pub fn is_templated(&self) -> bool {
+        self.get("templated").and_then(JsonValue::as_bool) == Some(true)
+    }
+
+    /// The `href` with its URI template expressions expanded
+    ///
+    /// Supports the RFC 6570 expressions up to level 3 (`{var}`, `{+var}`,
+    /// `{#var}`, `{/var}`, `{?var}` and `{&var}`, each with several
+    /// comma-separated variables). Variables missing from `variables` are
+    /// left out, as the RFC prescribes.
+    pub fn expand(&self, variables: &[(&str, &str)]) -> String {
+        expand_template(self.href, variables)
+    }
+}
+
+fn expand_template(template: &str, variables: &[(&str, &str)]) -> String {
+    let mut expanded = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let Some(length) = rest[start..].find('}') else {
+            break;
+        };
+        expanded.push_str(&rest[..start]);
+        expand_expression(&rest[start + 1..start + length], variables, &mut expanded);
+        rest = &rest[start + length + 1..];
+    }
+    expanded.push_str(rest);
+    expanded
+}
+
+fn expand_expression(expression: &str, variables: &[(&str, &str)], out: &mut String) {
+    let (operator, names) = match expression.chars().next() {
+        Some(op @ ('+' | '#' | '/' | '?' | '&')) => (op, &expression[1..]),
+        _ => (' ', expression),
+    };
+    let (prefix, separator, named, reserved) = match operator {
+        '+' => ("", ",", false, true),
+        '#' => ("#", ",", false, true),
+        '/' => ("/", "/", false, false),
+        '?' => ("?", "&", true, false),
+        '&' => ("&", "&", true, false),
+        _ => ("", ",", false, false),
+    };
+    let mut first = true;
+    for name in names.split(',') {
+        let name = name.trim_end_matches('*');
+        let Some(&(_, value)) = variables.iter().find(|(variable, _)| *variable == name) else {
+            continue;
+        };
+        out.push_str(if first { prefix } else { separator });
+        first = false;
+        if named {
+            out.push_str(name);
+            out.push('=');
+        }
+        percent_encode(value, reserved, out);
+    }
+}
+
+fn percent_encode(value: &str, reserved: bool, out: &mut String) {
+    for byte in value.bytes() {
+        let unreserved = byte.is_ascii_alphanumeric() || b"-._~".contains(&byte);
+        if unreserved || (reserved && b":/?#[]@!$&'()*+,;=".contains(&byte)) {
+            out.push(char::from(byte));
+        } else {
+            out.push_str(&format!("%{:02X}", byte));
+        }
+    }
+}
+
+/// Structure checks with the current location for error messages
+#[derive(Default)]
+struct Checker {
+    path: Vec<String>,
+}
+
+impl Checker {
+    fn error(&self, message: impl Into<String>) -> HypermediaError {
+        HypermediaError {
+            path: JsonPointer::from_tokens(&self.path),
+            message: message.into(),
+        }
+    }
+
+    fn at<T>(
+        &mut self,
+        token: impl ToString,
+        check: impl FnOnce(&mut Self) -> Result<T, HypermediaError>,
+    ) -> Result<T, HypermediaError> {
+        self.path.push(token.to_string());
+        let result = check(self)?;
+        self.path.pop();
+        Ok(result)
+    }
+
+    /// Check `value[key]` if present
+    fn member(
+        &mut self,
+        value: &JsonValue,
+        key: &str,
+        check: impl FnOnce(&mut Self, &JsonValue) -> Result<(), HypermediaError>,
+    ) -> Result<(), HypermediaError> {
+        match value.get(key) {
+            Some(member) => self.at(key, |c| check(c, member)),
+            None => Ok(()),
+        }
+    }
+
+    fn object(&self, value: &JsonValue, what: &str) -> Result<(), HypermediaError> {
+        if value.is_object() {
+            Ok(())
+        } else {
+            Err(self.error(format!("Expected {} object, found {}", what, value)))
+        }
+    }
+
+    fn string(&self, value: &JsonValue, key: &str) -> Result<(), HypermediaError> {
+        match value.get(key) {
+            Some(member) if member.is_string() => Ok(()),
+            Some(member) => {
+                Err(self.error(format!("\"{}\" must be a string, found {}", key, member)))
+            }
+            None => Err(self.error(format!("Missing \"{}\" member", key))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand_template() {
+        let variables = [("id", "a b"), ("path", "x/y"), ("q", "1"), ("r", "2")];
+        let cases = [
+            ("/users/{id}", "/users/a%20b"),
+            ("{+path}/here", "x/y/here"),
+            ("/files{/path}", "/files/x%2Fy"),
+            ("/search{?q,missing,r}", "/search?q=1&r=2"),
+            ("/search?fixed=1{&q}", "/search?fixed=1&q=1"),
+            ("/docs{#path}", "/docs#x/y"),
+            ("/none{?missing}", "/none"),
+            ("/broken{id", "/broken{id"),
+        ];
+        for (template, expected) in cases {
+            assert_eq!(
+                expand_template(template, &variables),
+                expected,
+                "{}",
+                template
+            );
+        }
+    }
+}
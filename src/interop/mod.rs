@@ -0,0 +1,28 @@
+//! Interop - conversions between `JsonValue` and the value trees of other
+//! configuration formats and libraries
+//!
+//! Each format lives behind its own feature flag (`toml`, `yaml`) and
+//! provides `from_*`/`to_*` functions plus a [`Format`](crate::convert::Format)
+//! implementation that [`FormatRegistry::new`](crate::convert::FormatRegistry::new)
+//! registers automatically. The `protobuf` feature adds the proto3 JSON
+//! mapping for prost messages.
+
+#[cfg(feature = "protobuf")]
+pub mod protobuf;
+#[cfg(feature = "toml")]
+pub mod toml;
+#[cfg(feature = "yaml")]
+pub mod yaml;
+
+#[cfg(any(feature = "toml", feature = "yaml"))]
+use crate::pointer::JsonPointer;
+
+/// `JsonPointer` text for an error message about the value at `path`
+#[cfg(any(feature = "toml", feature = "yaml"))]
+fn location(path: &[String]) -> String {
+    if path.is_empty() {
+        "the root".to_string()
+    } else {
+        JsonPointer::from_tokens(path).to_string()
+    }
+}
@@ -0,0 +1,612 @@
+//! Protobuf interop - the proto3 JSON mapping for prost messages
+//!
+//! prost messages carry no JSON support of their own. [`ProtoJson`] is
+//! the bridge: it is implemented here for the well-known types of
+//! `prost_types` and can be implemented for generated messages with the
+//! field helpers below, which follow the proto3 conventions: 64 bit
+//! integers as strings, `bytes` as base64, field names in lowerCamelCase.
+//!
+//! # Examples
+//!
+//! ```
+//! use json_parser_rs::interop::protobuf::{ProtoJson, int64_from_json, int64_to_json, json_name};
+//! use json_parser_rs::{ConvertError, JsonObject, JsonValue, json};
+//! use prost_types::Timestamp;
+//!
+//! struct Event {
+//!     event_id: i64,
+//!     created: Timestamp,
+//! }
+//!
+//! impl ProtoJson for Event {
+//!     fn to_proto_json(&self) -> Result<JsonValue, ConvertError> {
+//!         let mut object = JsonObject::new();
+//!         object.insert(json_name("event_id"), int64_to_json(self.event_id));
+//!         object.insert(json_name("created"), self.created.to_proto_json()?);
+//!         Ok(JsonValue::Object(object))
+//!     }
+//!
+//!     fn from_proto_json(value: &JsonValue) -> Result<Self, ConvertError> {
+//!         let field = |name| value.get(name).ok_or(ConvertError::decode(format!("missing {}", name)));
+//!         Ok(Event {
+//!             event_id: int64_from_json(field("eventId")?)?,
+//!             created: Timestamp::from_proto_json(field("created")?)?,
+//!         })
+//!     }
+//! }
+//!
+//! let event = Event { event_id: 1 << 60, created: Timestamp { seconds: 0, nanos: 5_000_000 } };
+//! let value = event.to_proto_json().unwrap();
+//! assert_eq!(
+//!     value,
+//!     json!({"eventId": "1152921504606846976", "created": "1970-01-01T00:00:00.005Z"})
+//! );
+//! assert_eq!(Event::from_proto_json(&value).unwrap().event_id, 1 << 60);
+//! ```
+
+use prost_types::value::Kind;
+use prost_types::{Duration, FieldMask, ListValue, NullValue, Struct, Timestamp, Value};
+
+use crate::convert::ConvertError;
+use crate::json::JsonValue;
+use crate::object::JsonObject;
+
+/// Message with a proto3 JSON form
+pub trait ProtoJson: Sized {
+    fn to_proto_json(&self) -> Result<JsonValue, ConvertError>;
+
+    fn from_proto_json(value: &JsonValue) -> Result<Self, ConvertError>;
+}
+
+/// JSON name of a proto field: `snake_case` to `lowerCamelCase`
+pub fn json_name(field: &str) -> String {
+    let mut name = String::with_capacity(field.len());
+    let mut upper = false;
+    for c in field.chars() {
+        match c {
+            '_' => upper = true,
+            c if upper => {
+                name.push(c.to_ascii_uppercase());
+                upper = false;
+            }
+            c => name.push(c),
+        }
+    }
+    name
+}
+
+/// Proto field name for a JSON name: `lowerCamelCase` to `snake_case`
+pub fn field_name(json_name: &str) -> String {
+    let mut field = String::with_capacity(json_name.len() + 4);
+    for c in json_name.chars() {
+        if c.is_ascii_uppercase() {
+            field.push('_');
+            field.push(c.to_ascii_lowercase());
+        } else {
+            field.push(c);
+        }
+    }
+    field
+}
+
+/// `int64` fields are written as decimal strings, which JSON numbers
+/// (doubles to many readers) cannot hold exactly
+pub fn int64_to_json(n: i64) -> JsonValue {
+    JsonValue::String(n.to_string())
+}
+
+/// `uint64` fields, see [`int64_to_json`]
+pub fn uint64_to_json(n: u64) -> JsonValue {
+    JsonValue::String(n.to_string())
+}
+
+/// Read an `int64` field from a string or an integral number
+pub fn int64_from_json(value: &JsonValue) -> Result<i64, ConvertError> {
+    integer_from_json(value, "int64")
+}
+
+/// Read a `uint64` field from a string or an integral number
+pub fn uint64_from_json(value: &JsonValue) -> Result<u64, ConvertError> {
+    integer_from_json(value, "uint64")
+}
+
+fn integer_from_json<T: TryFrom<i64> + TryFrom<u64> + std::str::FromStr>(
+    value: &JsonValue,
+    type_name: &str,
+) -> Result<T, ConvertError> {
+    let invalid =
+        || ConvertError::decode(format!("Expected an {} value, found {}", type_name, value));
+    if let Some(text) = value.as_str() {
+        return text.parse().map_err(|_| invalid());
+    }
+    let n = value
+        .as_number()
+        .filter(|n| !n.is_f64())
+        .ok_or_else(invalid)?;
+    match (n.as_u64(), n.as_i64()) {
+        (Some(n), _) => T::try_from(n).map_err(|_| invalid()),
+        (None, Some(n)) => T::try_from(n).map_err(|_| invalid()),
+        (None, None) => Err(invalid()),
+    }
+}
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// `bytes` fields are written as padded standard base64
+pub fn bytes_to_json(bytes: &[u8]) -> JsonValue {
+    let mut text = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &byte)| n | u32::from(byte) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                text.push(char::from(BASE64[(n >> (18 - 6 * i)) as usize & 0x3f]));
+            } else {
+                text.push('=');
+            }
+        }
+    }
+    JsonValue::String(text)
+}
+
+/// Read a `bytes` field; standard and URL-safe base64 are accepted, with
+/// or without padding
+pub fn bytes_from_json(value: &JsonValue) -> Result<Vec<u8>, ConvertError> {
+    let text = value.as_str().ok_or_else(|| {
+        ConvertError::decode(format!("Expected a base64 string, found {}", value))
+    })?;
+    let invalid = || ConvertError::decode(format!("Invalid base64 '{}'", text));
+    let digits = text.trim_end_matches('=').as_bytes();
+    if digits.len() % 4 == 1 || text.len() - digits.len() > 2 {
+        return Err(invalid());
+    }
+    let mut bytes = Vec::with_capacity(digits.len() * 3 / 4);
+    for chunk in digits.chunks(4) {
+        let mut n = 0u32;
+        for (i, &digit) in chunk.iter().enumerate() {
+            let sextet = match digit {
+                b'A'..=b'Z' => digit - b'A',
+                b'a'..=b'z' => digit - b'a' + 26,
+                b'0'..=b'9' => digit - b'0' + 52,
+                b'+' | b'-' => 62,
+                b'/' | b'_' => 63,
+                _ => return Err(invalid()),
+            };
+            n |= u32::from(sextet) << (18 - 6 * i);
+        }
+        bytes.extend(&n.to_be_bytes()[1..chunk.len()]);
+    }
+    Ok(bytes)
+}
+
+/// Seconds from 0001-01-01T00:00:00Z and to 9999-12-31T23:59:59Z, the
+/// range proto3 allows for timestamps
+const MIN_TIMESTAMP: i64 = -62_135_596_800;
+const MAX_TIMESTAMP: i64 = 253_402_300_799;
+
+/// Seconds of the longest proto3 duration (10,000 years)
+const MAX_DURATION: i64 = 315_576_000_000;
+
+/// Fraction of a second with 0, 3, 6 or 9 digits, as proto3 writes them
+fn fraction(nanos: u32) -> String {
+    match nanos {
+        0 => String::new(),
+        n if n % 1_000_000 == 0 => format!(".{:03}", n / 1_000_000),
+        n if n % 1_000 == 0 => format!(".{:06}", n / 1_000),
+        n => format!(".{:09}", n),
+    }
+}
+
+/// Nanoseconds of up to nine fraction digits
+fn parse_fraction(digits: &str) -> Option<i32> {
+    if digits.is_empty() || digits.len() > 9 || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    format!("{:0<9}", digits).parse().ok()
+}
+
+/// Days since 1970-01-01 to (year, month, day), proleptic Gregorian
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+/// Inverse of [`civil_from_days`]
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Seconds and nanoseconds of an RFC 3339 date-time
+fn parse_rfc3339(text: &str) -> Option<(i64, i32)> {
+    let number = |range: std::ops::Range<usize>| -> Option<i64> {
+        let digits = text.get(range)?;
+        digits
+            .bytes()
+            .all(|b| b.is_ascii_digit())
+            .then(|| digits.parse().ok())?
+    };
+    let bytes = text.as_bytes();
+    let separators = [(4, b'-'), (7, b'-'), (10, b'T'), (13, b':'), (16, b':')];
+    if !separators
+        .iter()
+        .all(|&(i, c)| bytes.get(i).is_some_and(|b| b.eq_ignore_ascii_case(&c)))
+    {
+        return None;
+    }
+    let (year, month, day) = (number(0..4)?, number(5..7)?, number(8..10)?);
+    let (hour, minute, second) = (number(11..13)?, number(14..16)?, number(17..19)?);
+    let days_in_month = match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        1..=12 => 31,
+        _ => return None,
+    };
+    if !(1..=days_in_month).contains(&day) || hour > 23 || minute > 59 || second > 59 {
+        return None;
+    }
+
+    let mut rest = &text[19..];
+    let mut nanos = 0;
+    if let Some(after_dot) = rest.strip_prefix('.') {
+        let end = after_dot
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(after_dot.len());
+        nanos = parse_fraction(&after_dot[..end])?;
+        rest = &after_dot[end..];
+    }
+    let offset = match rest.as_bytes() {
+        [b'Z' | b'z'] => 0,
+        [sign @ (b'+' | b'-'), _, _, b':', _, _] => {
+            let hours: i64 = rest[1..3].parse().ok()?;
+            let minutes: i64 = rest[4..6].parse().ok()?;
+            if hours > 23 || minutes > 59 {
+                return None;
+            }
+            let offset = hours * 3_600 + minutes * 60;
+            if *sign == b'-' { -offset } else { offset }
+        }
+        _ => return None,
+    };
+    let seconds =
+        days_from_civil(year, month, day) * 86_400 + hour * 3_600 + minute * 60 + second - offset;
+    Some((seconds, nanos))
+}
+
+/// RFC 3339 in UTC, e.g. `"1972-01-01T10:00:20.021Z"`
+impl ProtoJson for Timestamp {
+    fn to_proto_json(&self) -> Result<JsonValue, ConvertError> {
+        let nanos = u32::try_from(self.nanos)
+            .ok()
+            .filter(|&n| n < 1_000_000_000);
+        let (Some(nanos), MIN_TIMESTAMP..=MAX_TIMESTAMP) = (nanos, self.seconds) else {
+            return Err(ConvertError::encode(format!(
+                "Timestamp {}s {}ns is outside the proto3 range",
+                self.seconds, self.nanos
+            )));
+        };
+        let (year, month, day) = civil_from_days(self.seconds.div_euclid(86_400));
+        let time = self.seconds.rem_euclid(86_400);
+        Ok(JsonValue::String(format!(
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}{}Z",
+            year,
+            month,
+            day,
+            time / 3_600,
+            time % 3_600 / 60,
+            time % 60,
+            fraction(nanos)
+        )))
+    }
+
+    fn from_proto_json(value: &JsonValue) -> Result<Self, ConvertError> {
+        value
+            .as_str()
+            .and_then(parse_rfc3339)
+            .filter(|(seconds, _)| (MIN_TIMESTAMP..=MAX_TIMESTAMP).contains(seconds))
+            .map(|(seconds, nanos)| Timestamp { seconds, nanos })
+            .ok_or_else(|| {
+                ConvertError::decode(format!("Expected an RFC 3339 timestamp, found {}", value))
+            })
+    }
+}
+
+/// Seconds with a fraction and an `s` suffix, e.g. `"-1.5s"`
+impl ProtoJson for Duration {
+    fn to_proto_json(&self) -> Result<JsonValue, ConvertError> {
+        let consistent =
+            (self.seconds >= 0 && self.nanos >= 0) || (self.seconds <= 0 && self.nanos <= 0);
+        if !consistent || self.seconds.abs() > MAX_DURATION || self.nanos.abs() >= 1_000_000_000 {
+            return Err(ConvertError::encode(format!(
+                "Duration {}s {}ns is outside the proto3 range",
+                self.seconds, self.nanos
+            )));
+        }
+        let sign = if self.seconds < 0 || self.nanos < 0 {
+            "-"
+        } else {
+            ""
+        };
+        Ok(JsonValue::String(format!(
+            "{}{}{}s",
+            sign,
+            self.seconds.unsigned_abs(),
+            fraction(self.nanos.unsigned_abs())
+        )))
+    }
+
+    fn from_proto_json(value: &JsonValue) -> Result<Self, ConvertError> {
+        let parse = |text: &str| -> Option<Duration> {
+            let text = text.strip_suffix('s')?;
+            let (negative, text) = match text.strip_prefix('-') {
+                Some(text) => (true, text),
+                None => (false, text),
+            };
+            let (whole, nanos) = match text.split_once('.') {
+                Some((whole, digits)) => (whole, parse_fraction(digits)?),
+                None => (text, 0),
+            };
+            if whole.is_empty() || !whole.bytes().all(|b| b.is_ascii_digit()) {
+                return None;
+            }
+            let seconds: i64 = whole.parse().ok().filter(|&s| s <= MAX_DURATION)?;
+            Some(if negative {
+                Duration {
+                    seconds: -seconds,
+                    nanos: -nanos,
+                }
+            } else {
+                Duration { seconds, nanos }
+            })
+        };
+        value.as_str().and_then(parse).ok_or_else(|| {
+            ConvertError::decode(format!(
+                "Expected a duration such as \"1.5s\", found {}",
+                value
+            ))
+        })
+    }
+}
+
+/// Comma-separated lowerCamelCase paths, e.g. `"user.displayName,photo"`
+impl ProtoJson for FieldMask {
+    fn to_proto_json(&self) -> Result<JsonValue, ConvertError> {
+        let paths: Vec<String> = self
+            .paths
+            .iter()
+            .map(|path| path.split('.').map(json_name).collect::<Vec<_>>().join("."))
+            .collect();
+        Ok(JsonValue::String(paths.join(",")))
+    }
+
+    fn from_proto_json(value: &JsonValue) -> Result<Self, ConvertError> {
+        let text = value.as_str().ok_or_else(|| {
+            ConvertError::decode(format!("Expected a field mask string, found {}", value))
+        })?;
+        let paths = text
+            .split(',')
+            .filter(|path| !path.is_empty())
+            .map(|path| {
+                path.split('.')
+                    .map(field_name)
+                    .collect::<Vec<_>>()
+                    .join(".")
+            })
+            .collect();
+        Ok(FieldMask { paths })
+    }
+}
+
+/// Any JSON value; numbers become doubles
+impl ProtoJson for Value {
+    fn to_proto_json(&self) -> Result<JsonValue, ConvertError> {
+        let value = match &self.kind {
+            None | Some(Kind::NullValue(_)) => JsonValue::Null,
+            Some(Kind::NumberValue(n)) => JsonValue::from(*n),
+            Some(Kind::StringValue(s)) => JsonValue::String(s.clone()),
+            Some(Kind::BoolValue(b)) => JsonValue::Boolean(*b),
+            Some(Kind::StructValue(object)) => object.to_proto_json()?,
+            Some(Kind::ListValue(list)) => list.to_proto_json()?,
+        };
+        Ok(value)
+    }
+
+    fn from_proto_json(value: &JsonValue) -> Result<Self, ConvertError> {
+        let kind = match value.untagged() {
+            JsonValue::Null => Kind::NullValue(NullValue::NullValue.into()),
+            JsonValue::Boolean(b) => Kind::BoolValue(*b),
+            JsonValue::Number(n) => Kind::NumberValue(n.as_f64()),
+            JsonValue::Array(_) => Kind::ListValue(ListValue::from_proto_json(value)?),
+            JsonValue::Object(_) | JsonValue::Extension(..) => {
+                Kind::StructValue(Struct::from_proto_json(value)?)
+            }
+            string => Kind::StringValue(string.to_str_lossy().unwrap_or_default().into_owned()),
+        };
+        Ok(Value { kind: Some(kind) })
+    }
+}
+
+/// JSON object; `Struct` keeps its fields sorted by name
+impl ProtoJson for Struct {
+    fn to_proto_json(&self) -> Result<JsonValue, ConvertError> {
+        let mut object = JsonObject::with_capacity(self.fields.len());
+        for (key, field) in &self.fields {
+            object.insert(key.clone(), field.to_proto_json()?);
+        }
+        Ok(JsonValue::Object(object))
+    }
+
+    fn from_proto_json(value: &JsonValue) -> Result<Self, ConvertError> {
+        let fields = match value.untagged() {
+            JsonValue::Object(object) => object
+                .iter()
+                .map(|(key, member)| Ok((key.clone(), Value::from_proto_json(member)?)))
+                .collect::<Result<_, ConvertError>>()?,
+            JsonValue::Extension(tag, payload) => [(tag.clone(), Value::from_proto_json(payload)?)]
+                .into_iter()
+                .collect(),
+            _ => {
+                return Err(ConvertError::decode(format!(
+                    "Expected an object for a Struct, found {}",
+                    value
+                )));
+            }
+        };
+        Ok(Struct { fields })
+    }
+}
+
+/// JSON array
+impl ProtoJson for ListValue {
+    fn to_proto_json(&self) -> Result<JsonValue, ConvertError> {
+        let elements = self
+            .values
+            .iter()
+            .map(ProtoJson::to_proto_json)
+            .collect::<Result<_, _>>()?;
+        Ok(JsonValue::Array(elements))
+    }
+
+    fn from_proto_json(value: &JsonValue) -> Result<Self, ConvertError> {
+        let elements = value.as_array().ok_or_else(|| {
+            ConvertError::decode(format!(
+                "Expected an array for a ListValue, found {}",
+                value
+            ))
+        })?;
+        let values = elements
+            .iter()
+            .map(Value::from_proto_json)
+            .collect::<Result<_, _>>()?;
+        Ok(ListValue { values })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json;
+
+    #[test]
+    fn test_timestamps() {
+        let cases = [
+            (0, 0, "1970-01-01T00:00:00Z"),
+            (63_072_020, 21_000_000, "1972-01-01T00:00:20.021Z"),
+            (-1, 999_999_999, "1969-12-31T23:59:59.999999999Z"),
+            (951_782_400, 1_000, "2000-02-29T00:00:00.000001Z"),
+            (MIN_TIMESTAMP, 0, "0001-01-01T00:00:00Z"),
+            (MAX_TIMESTAMP, 0, "9999-12-31T23:59:59Z"),
+        ];
+        for (seconds, nanos, text) in cases {
+            let timestamp = Timestamp { seconds, nanos };
+            assert_eq!(timestamp.to_proto_json().unwrap(), json!(text));
+            assert_eq!(Timestamp::from_proto_json(&json!(text)).unwrap(), timestamp);
+        }
+        let offset = Timestamp::from_proto_json(&json!("1970-01-01T01:30:00.5+01:00")).unwrap();
+        assert_eq!(
+            offset,
+            Timestamp {
+                seconds: 1_800,
+                nanos: 500_000_000
+            }
+        );
+        for invalid in [
+            "1970-02-30T00:00:00Z",
+            "1970-01-01 00:00:00Z",
+            "1970-01-01T00:00:00",
+        ] {
+            assert!(
+                Timestamp::from_proto_json(&json!(invalid)).is_err(),
+                "{}",
+                invalid
+            );
+        }
+        assert!(
+            Timestamp {
+                seconds: MAX_TIMESTAMP + 1,
+                nanos: 0
+            }
+            .to_proto_json()
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_durations() {
+        let cases = [
+            (1, 0, "1s"),
+            (1, 340_012, "1.000340012s"),
+            (0, -500_000_000, "-0.500s"),
+            (-3, 0, "-3s"),
+        ];
+        for (seconds, nanos, text) in cases {
+            let duration = Duration { seconds, nanos };
+            assert_eq!(duration.to_proto_json().unwrap(), json!(text));
+            assert_eq!(Duration::from_proto_json(&json!(text)).unwrap(), duration);
+        }
+        assert!(Duration::from_proto_json(&json!("1.5")).is_err());
+        assert!(
+            Duration {
+                seconds: 1,
+                nanos: -1
+            }
+            .to_proto_json()
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_struct_and_scalars() {
+        let value = json!({"a": [1.5, "x", true, null], "b": {"c": {}}});
+        let proto = Struct::from_proto_json(&value).unwrap();
+        assert_eq!(proto.to_proto_json().unwrap(), value);
+
+        assert_eq!(
+            int64_from_json(&json!("-9007199254740993")).unwrap(),
+            -9_007_199_254_740_993
+        );
+        assert_eq!(uint64_from_json(&json!(42)).unwrap(), 42);
+        assert!(uint64_from_json(&json!(-1)).is_err());
+        assert!(int64_from_json(&json!(1.5)).is_err());
+
+        for bytes in [&b""[..], b"f", b"fo", b"foo", b"\xfb\xff"] {
+            let encoded = bytes_to_json(bytes);
+            assert_eq!(bytes_from_json(&encoded).unwrap(), bytes);
+        }
+        assert_eq!(bytes_to_json(b"\xfb\xff"), json!("+/8="));
+        assert_eq!(bytes_from_json(&json!("-_8")).unwrap(), b"\xfb\xff");
+        assert!(bytes_from_json(&json!("a")).is_err());
+
+        let mask = FieldMask {
+            paths: vec!["user.display_name".into(), "photo".into()],
+        };
+        assert_eq!(
+            mask.to_proto_json().unwrap(),
+            json!("user.displayName,photo")
+        );
+        assert_eq!(
+            FieldMask::from_proto_json(&json!("user.displayName,photo")).unwrap(),
+            mask
+        );
+    }
+}
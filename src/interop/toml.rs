@@ -0,0 +1,155 @@
+//! TOML interop - `JsonValue` to and from `toml::Value`
+//!
+//! TOML has no null and a document is always a table, so not every JSON
+//! value converts; datetimes become strings in their RFC 3339 form.
+
+use toml::{Table, Value};
+
+use super::location;
+use crate::convert::{ConvertError, Format};
+use crate::json::JsonValue;
+use crate::object::JsonObject;
+
+/// Convert a TOML value; NaN and infinite floats become `Null`, as with
+/// `JsonValue::from(f64)`
+pub fn from_toml(value: &Value) -> JsonValue {
+    match value {
+        Value::String(s) => JsonValue::String(s.clone()),
+        Value::Integer(n) => JsonValue::from(*n),
+        Value::Float(f) => JsonValue::from(*f),
+        Value::Boolean(b) => JsonValue::Boolean(*b),
+        Value::Datetime(datetime) => JsonValue::String(datetime.to_string()),
+        Value::Array(elements) => JsonValue::Array(elements.iter().map(from_toml).collect()),
+        Value::Table(table) => JsonValue::Object(from_table(table)),
+    }
+}
+
+fn from_table(table: &Table) -> JsonObject {
+    table
+        .iter()
+        .map(|(key, value)| (key.as_str(), from_toml(value)))
+        .collect()
+}
+
+/// Convert to a TOML value; fails for `null` and for integers above `i64::MAX`
+///
+/// Extension values become single-member tables, like their JSON form.
+pub fn to_toml(value: &JsonValue) -> Result<Value, ConvertError> {
+    to_toml_at(value, &mut Vec::new())
+}
+
+fn to_toml_at(value: &JsonValue, path: &mut Vec<String>) -> Result<Value, ConvertError> {
+    let value = match value.untagged() {
+        JsonValue::Null => {
+            return Err(ConvertError::encode(format!(
+                "TOML has no null (at {})",
+                location(path)
+            )));
+        }
+        JsonValue::Boolean(b) => Value::Boolean(*b),
+        JsonValue::Number(n) if n.is_f64() => Value::Float(n.as_f64()),
+        JsonValue::Number(n) => Value::Integer(n.as_i64().ok_or_else(|| {
+            ConvertError::encode(format!(
+                "{} is too large for a TOML integer (at {})",
+                n,
+                location(path)
+            ))
+        })?),
+        string @ (JsonValue::String(_) | JsonValue::RawString(_)) => {
+            Value::String(string.to_str_lossy().unwrap_or_default().into_owned())
+        }
+        JsonValue::Array(elements) => {
+            let mut array = toml::value::Array::with_capacity(elements.len());
+            for (index, element) in elements.iter().enumerate() {
+                path.push(index.to_string());
+                array.push(to_toml_at(element, path)?);
+                path.pop();
+            }
+            Value::Array(array)
+        }
+        JsonValue::Object(object) => {
+            let mut table = Table::new();
+            for (key, member) in object {
+                path.push(key.clone());
+                table.insert(key.clone(), to_toml_at(member, path)?);
+                path.pop();
+            }
+            Value::Table(table)
+        }
+        JsonValue::Extension(tag, payload) => {
+            path.push(tag.clone());
+            let payload = to_toml_at(payload, path)?;
+            path.pop();
+            Value::Table(Table::from_iter([(tag.clone(), payload)]))
+        }
+        #[cfg(feature = "tagged")]
+        JsonValue::Tagged(..) => unreachable!("untagged() strips tags"),
+    };
+    Ok(value)
+}
+
+/// TOML documents (`.toml`); only objects can be encoded
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Toml;
+
+impl Format for Toml {
+    fn name(&self) -> &str {
+        "toml"
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["toml"]
+    }
+
+    fn decode(&self, input: &[u8]) -> Result<JsonValue, ConvertError> {
+        let text = std::str::from_utf8(input)
+            .map_err(|e| ConvertError::decode(format!("Input is not UTF-8: {}", e)))?;
+        let table: Table = text.parse().map_err(ConvertError::decode)?;
+        Ok(JsonValue::Object(from_table(&table)))
+    }
+
+    fn encode(&self, value: &JsonValue) -> Result<Vec<u8>, ConvertError> {
+        match to_toml(value)? {
+            Value::Table(table) => Ok(toml::to_string(&table)
+                .map_err(ConvertError::encode)?
+                .into_bytes()),
+            _ => Err(ConvertError::encode("A TOML document must be an object")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json;
+
+    #[test]
+    fn test_toml_round_trip() {
+        let input = "title = \"demo\"\nports = [80, 443]\nratio = 0.5\n\n[owner]\nname = \"Ann\"\nsince = 1979-05-27T07:32:00Z\n";
+        let value = Toml.decode(input.as_bytes()).unwrap();
+        assert_eq!(
+            value,
+            json!({
+                "title": "demo",
+                "ports": [80, 443],
+                "ratio": 0.5,
+                "owner": {"name": "Ann", "since": "1979-05-27T07:32:00Z"}
+            })
+        );
+        let encoded = Toml.encode(&value).unwrap();
+        assert_eq!(Toml.decode(&encoded).unwrap(), value);
+    }
+
+    #[test]
+    fn test_unrepresentable_values() {
+        let error = to_toml(&json!({"a": [1, null]})).unwrap_err();
+        assert_eq!(error.message(), "TOML has no null (at /a/1)");
+        let error = to_toml(&json!({"big": u64::MAX})).unwrap_err();
+        assert!(
+            error
+                .message()
+                .starts_with("18446744073709551615 is too large")
+        );
+        assert!(Toml.encode(&json!([1, 2])).is_err());
+    }
+}
@@ -0,0 +1,179 @@
+//! YAML interop - `JsonValue` to and from `serde_yaml::Value`
+//!
+//! YAML mappings may have any value as key; scalar keys are turned into
+//! their text, sequences and mappings as keys are rejected. Tags (`!Thing`)
+//! are kept as `JsonValue::Tagged` with the `tagged` feature and dropped
+//! otherwise.
+
+use serde_yaml::{Mapping, Number, Value};
+
+use super::location;
+use crate::convert::{ConvertError, Format};
+use crate::json::JsonValue;
+use crate::object::JsonObject;
+
+/// Convert a YAML value; NaN and infinite floats become `Null`, as with
+/// `JsonValue::from(f64)`
+pub fn from_yaml(value: &Value) -> Result<JsonValue, ConvertError> {
+    from_yaml_at(value, &mut Vec::new())
+}
+
+fn from_yaml_at(value: &Value, path: &mut Vec<String>) -> Result<JsonValue, ConvertError> {
+    let value = match value {
+        Value::Null => JsonValue::Null,
+        Value::Bool(b) => JsonValue::Boolean(*b),
+        Value::Number(n) => from_number(n),
+        Value::String(s) => JsonValue::String(s.clone()),
+        Value::Sequence(elements) => {
+            let mut array = Vec::with_capacity(elements.len());
+            for (index, element) in elements.iter().enumerate() {
+                path.push(index.to_string());
+                array.push(from_yaml_at(element, path)?);
+                path.pop();
+            }
+            JsonValue::Array(array)
+        }
+        Value::Mapping(mapping) => {
+            let mut object = JsonObject::with_capacity(mapping.len());
+            for (key, member) in mapping {
+                let key = key_text(key).ok_or_else(|| {
+                    ConvertError::decode(format!(
+                        "Mapping keys must be scalars (at {})",
+                        location(path)
+                    ))
+                })?;
+                path.push(key.clone());
+                object.insert(key, from_yaml_at(member, path)?);
+                path.pop();
+            }
+            JsonValue::Object(object)
+        }
+        #[cfg(feature = "tagged")]
+        Value::Tagged(tagged) => JsonValue::tagged(
+            tagged.tag.to_string().trim_start_matches('!'),
+            from_yaml_at(&tagged.value, path)?,
+        ),
+        #[cfg(not(feature = "tagged"))]
+        Value::Tagged(tagged) => from_yaml_at(&tagged.value, path)?,
+    };
+    Ok(value)
+}
+
+fn from_number(n: &Number) -> JsonValue {
+    if let Some(n) = n.as_u64() {
+        JsonValue::from(n)
+    } else if let Some(n) = n.as_i64() {
+        JsonValue::from(n)
+    } else {
+        JsonValue::from(n.as_f64().unwrap_or(f64::NAN))
+    }
+}
+
+fn key_text(key: &Value) -> Option<String> {
+    match key {
+        Value::Null => Some("null".to_string()),
+        Value::Bool(b) => Some(b.to_string()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::String(s) => Some(s.clone()),
+        Value::Tagged(tagged) => key_text(&tagged.value),
+        Value::Sequence(_) | Value::Mapping(_) => None,
+    }
+}
+
+/// Convert to a YAML value; every JSON value has a YAML form
+///
+/// Extension values become single-member mappings, like their JSON form.
+pub fn to_yaml(value: &JsonValue) -> Value {
+    match value {
+        JsonValue::Null => Value::Null,
+        JsonValue::Boolean(b) => Value::Bool(*b),
+        JsonValue::Number(n) if n.is_f64() => Value::Number(n.as_f64().into()),
+        JsonValue::Number(n) => match n.as_u64() {
+            Some(n) => Value::Number(n.into()),
+            None => Value::Number(n.as_i64().unwrap_or_default().into()),
+        },
+        string @ (JsonValue::String(_) | JsonValue::RawString(_)) => {
+            Value::String(string.to_str_lossy().unwrap_or_default().into_owned())
+        }
+        JsonValue::Array(elements) => Value::Sequence(elements.iter().map(to_yaml).collect()),
+        JsonValue::Object(object) => Value::Mapping(
+            object
+                .iter()
+                .map(|(key, member)| (Value::String(key.clone()), to_yaml(member)))
+                .collect(),
+        ),
+        JsonValue::Extension(tag, payload) => {
+            let mut mapping = Mapping::with_capacity(1);
+            mapping.insert(Value::String(tag.clone()), to_yaml(payload));
+            Value::Mapping(mapping)
+        }
+        #[cfg(feature = "tagged")]
+        JsonValue::Tagged(tag, payload) if tag.trim_start_matches('!').is_empty() => {
+            to_yaml(payload)
+        }
+        #[cfg(feature = "tagged")]
+        JsonValue::Tagged(tag, payload) => {
+            Value::Tagged(Box::new(serde_yaml::value::TaggedValue {
+                tag: serde_yaml::value::Tag::new(tag.as_str()),
+                value: to_yaml(payload),
+            }))
+        }
+    }
+}
+
+/// YAML documents (`.yaml`, `.yml`); a single document per input
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Yaml;
+
+impl Format for Yaml {
+    fn name(&self) -> &str {
+        "yaml"
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["yaml", "yml"]
+    }
+
+    fn decode(&self, input: &[u8]) -> Result<JsonValue, ConvertError> {
+        let value: Value = serde_yaml::from_slice(input).map_err(ConvertError::decode)?;
+        from_yaml(&value)
+    }
+
+    fn encode(&self, value: &JsonValue) -> Result<Vec<u8>, ConvertError> {
+        serde_yaml::to_string(&to_yaml(value))
+            .map(String::into_bytes)
+            .map_err(ConvertError::encode)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json;
+
+    #[test]
+    fn test_yaml_round_trip() {
+        let input =
+            "name: demo\nports:\n  - 80\n  - 443\nratio: 0.5\nenabled: true\nowner: ~\n1: one\n";
+        let value = Yaml.decode(input.as_bytes()).unwrap();
+        assert_eq!(
+            value,
+            json!({
+                "name": "demo",
+                "ports": [80, 443],
+                "ratio": 0.5,
+                "enabled": true,
+                "owner": null,
+                "1": "one"
+            })
+        );
+        let encoded = Yaml.encode(&value).unwrap();
+        assert_eq!(Yaml.decode(&encoded).unwrap(), value);
+    }
+
+    #[test]
+    fn test_complex_keys_rejected() {
+        let error = Yaml.decode(b"a:\n  ? [1, 2]\n  : x\n").unwrap_err();
+        assert_eq!(error.message(), "Mapping keys must be scalars (at /a)");
+    }
+}
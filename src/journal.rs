@@ -0,0 +1,435 @@
+//! Journal - append-only logs of JSON records
+//!
+//! A journal stores one record per append, each with a CRC-32 of its
+//! encoded bytes, so a reader can tell a record that was fully written from
+//! one cut short by a crash. Two layouts are available:
+//!
+//! - [`JournalFormat::LengthPrefixed`]: a little-endian `u32` length, a
+//!   little-endian `u32` CRC and the minified JSON text
+//! - [`JournalFormat::Ndjson`]: one line per record,
+//!   `{"crc":<crc>,"data":<record>}`, which line-oriented tools can still
+//!   read
+//!
+//! [`JournalReader`] stops quietly at a torn final record and reports
+//! [`JournalReader::valid_len`], the length to truncate the file to before
+//! appending again.
+//!
+//! # Examples
+//!
+//! ```
+//! use json_parser_rs::journal::{JournalFormat, JournalReader, JournalWriter};
+//! use json_parser_rs::json;
+//!
+//! let mut writer = JournalWriter::new(Vec::new(), JournalFormat::Ndjson);
+//! writer.append(&json!({"event": "created", "id": 1})).unwrap();
+//! writer.append(&json!({"event": "paid", "id": 1})).unwrap();
+//! let mut bytes = writer.into_inner();
+//! let complete = bytes.len();
+//! bytes.extend_from_slice(br#"{"crc":1234,"data":{"ev"#); // crashed mid-append
+//!
+//! let mut reader = JournalReader::new(&bytes[..], JournalFormat::Ndjson);
+//! let events: Vec<_> = reader.by_ref().map(|record| record.unwrap()["event"].clone()).collect();
+//! assert_eq!(events, [json!("created"), json!("paid")]);
+//! assert!(reader.is_torn());
+//! assert_eq!(reader.valid_len(), complete as u64);
+//! ```
+
+use std::fmt;
+use std::io::{self, BufRead, Read, Write};
+
+use crate::json::JsonValue;
+
+/// How records are laid out in a journal
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum JournalFormat {
+    /// Binary frames: `u32` length, `u32` CRC (both little-endian), payload
+    #[default]
+    LengthPrefixed,
+    /// `{"crc":<crc>,"data":<record>}` lines
+    Ndjson,
+}
+
+/// Why a journal record could not be read
+#[derive(Debug)]
+pub struct JournalError {
+    kind: JournalErrorKind,
+    offset: u64,
+    message: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum JournalErrorKind {
+    /// Reading from the underlying reader failed
+    Io,
+    /// A complete record does not match its checksum or framing
+    Corrupt,
+    /// A record passed its checksum but is not valid JSON
+    InvalidRecord,
+}
+
+impl JournalError {
+    fn new(kind: JournalErrorKind, offset: u64, message: impl fmt::Display) -> Self {
+        Self {
+            kind,
+            offset,
+            message: message.to_string(),
+        }
+    }
+
+    pub fn kind(&self) -> JournalErrorKind {
+        self.kind
+    }
+
+    /// Byte offset of the record in the journal
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl fmt::Display for JournalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Journal record at byte {}: {}",
+            self.offset, self.message
+        )
+    }
+}
+
+impl std::error::Error for JournalError {}
+
+/// Appends records to a journal
+///
+/// Each record is encoded in full and handed to the writer with a single
+/// `write_all`. Durability is up to the caller: flush, and for a `File`
+/// call `sync_data` through [`JournalWriter::get_mut`], when a record has
+/// to survive a crash.
+#[derive(Debug)]
+pub struct JournalWriter<W> {
+    writer: W,
+    format: JournalFormat,
+    buffer: Vec<u8>,
+}
+
+impl<W: Write> JournalWriter<W> {
+    /// Writer appending to `writer`, which should be positioned at the end
+    /// of the journal (a `File` opened with `append(true)`)
+    pub fn new(writer: W, format: JournalFormat) -> Self {
+        Self {
+            writer,
+            format,
+            buffer: Vec::new(),
+        }
+    }
+
+    pub fn append(&mut self, record: &JsonValue) -> io::Result<()> {
+        let data = record.to_minified_string();
+        let crc = crc32(data.as_bytes());
+        self.buffer.clear();
+        match self.format {
+            JournalFormat::LengthPrefixed => {
+                let length = u32::try_from(data.len()).map_err(|_| {
+                    io::Error::new(io::ErrorKind::InvalidInput, "Record larger than 4 GiB")
+                })?;
+                self.buffer.extend_from_slice(&length.to_le_bytes());
+                self.buffer.extend_from_slice(&crc.to_le_bytes());
+                self.buffer.extend_from_slice(data.as_bytes());
+            }
+            JournalFormat::Ndjson => {
+                writeln!(self.buffer, "{{\"crc\":{},\"data\":{}}}", crc, data)?;
+            }
+        }
+        self.writer.write_all(&self.buffer)
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+
+    pub fn get_ref(&self) -> &W {
+        &self.writer
+    }
+
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.writer
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+/// Iterator over the records of a journal
+///
+/// A final record cut short (missing bytes, or for NDJSON a last line
+/// without its newline) ends the iteration without an error; see
+/// [`JournalReader::is_torn`]. A complete record with a wrong checksum is
+/// an error; NDJSON journals continue with the next line after it, while
+/// length-prefixed ones end there, since the following frame boundaries
+/// cannot be trusted.
+#[derive(Debug)]
+pub struct JournalReader<R> {
+    reader: R,
+    format: JournalFormat,
+    /// Byte offset of the next record
+    offset: u64,
+    /// Byte offset just past the last record read successfully
+    valid_len: u64,
+    torn: bool,
+    done: bool,
+}
+
+impl<R: BufRead> JournalReader<R> {
+    pub fn new(reader: R, format: JournalFormat) -> Self {
+        Self {
+            reader,
+            format,
+            offset: 0,
+            valid_len: 0,
+            torn: false,
+            done: false,
+        }
+    }
+
+    /// Length of the journal up to the end of the last intact record
+    ///
+    /// Records after a corrupt one are not counted, so truncating the file
+    /// to this length drops exactly the damaged tail.
+    pub fn valid_len(&self) -> u64 {
+        self.valid_len
+    }
+
+    /// `true` once the iteration ended at an incomplete final record
+    pub fn is_torn(&self) -> bool {
+        self.torn
+    }
+
+    fn read_record(&mut self) -> Option<Result<JsonValue, JournalError>> {
+        let start = self.offset;
+        let frame = match self.format {
+            JournalFormat::LengthPrefixed => self.read_frame(),
+            JournalFormat::Ndjson => self.read_line(),
+        };
+        let data = match frame {
+            Ok(Some(data)) => data,
+            Ok(None) => {
+                self.done = true;
+                return None;
+            }
+            Err(error) => {
+                if self.format == JournalFormat::LengthPrefixed
+                    || error.kind != JournalErrorKind::Corrupt
+                {
+                    self.done = true;
+                }
+                return Some(Err(error));
+            }
+        };
+        let record = std::str::from_utf8(&data)
+            .map_err(|e| e.to_string())
+            .and_then(|text| crate::parse_json(text).map_err(|e| e.to_string()))
+            .map_err(|message| JournalError::new(JournalErrorKind::InvalidRecord, start, message));
+        if record.is_ok() && self.valid_len == start {
+            self.valid_len = self.offset;
+        }
+        Some(record)
+    }
+
+    /// The payload of the next length-prefixed frame, checksum verified;
+    /// `None` at the end of the journal or at a torn frame
+    fn read_frame(&mut self) -> Result<Option<Vec<u8>>, JournalError> {
+        let start = self.offset;
+        let mut header = [0; 8];
+        let read = self.read_fully(&mut header)?;
+        if read < header.len() {
+            return Ok(self.end(read));
+        }
+        let length = u32::from_le_bytes([header[0], header[1], header[2], header[3]]);
+        let crc = u32::from_le_bytes([header[4], header[5], header[6], header[7]]);
+        let mut data = Vec::new();
+        let read = (&mut self.reader)
+            .take(u64::from(length))
+            .read_to_end(&mut data)
+            .map_err(|e| JournalError::new(JournalErrorKind::Io, start, e))?;
+        self.offset += read as u64;
+        if read < length as usize {
+            return Ok(self.end(read + header.len()));
+        }
+        if crc32(&data) != crc {
+            return Err(JournalError::new(
+                JournalErrorKind::Corrupt,
+                start,
+                "Checksum mismatch",
+            ));
+        }
+        Ok(Some(data))
+    }
+
+    /// The record of the next NDJSON line, checksum verified
+    fn read_line(&mut self) -> Result<Option<Vec<u8>>, JournalError> {
+        let start = self.offset;
+        let mut line = Vec::new();
+        let read = self
+            .reader
+            .read_until(b'\n', &mut line)
+            .map_err(|e| JournalError::new(JournalErrorKind::Io, start, e))?;
+        self.offset += read as u64;
+        if line.pop() != Some(b'\n') {
+            return Ok(self.end(read));
+        }
+        let corrupt = |message| JournalError::new(JournalErrorKind::Corrupt, start, message);
+        let rest = line
+            .strip_prefix(b"{\"crc\":")
+            .ok_or_else(|| corrupt("Expected a {\"crc\": ..., \"data\": ...} line"))?;
+        let digits = rest.iter().take_while(|b| b.is_ascii_digit()).count();
+        let crc = std::str::from_utf8(&rest[..digits])
+            .ok()
+            .and_then(|digits| digits.parse::<u32>().ok())
+            .ok_or_else(|| corrupt("Invalid checksum"))?;
+        let data = rest[digits..]
+            .strip_prefix(b",\"data\":")
+            .and_then(|data| data.strip_suffix(b"}"))
+            .ok_or_else(|| corrupt("Expected a {\"crc\": ..., \"data\": ...} line"))?;
+        if crc32(data) != crc {
+            return Err(corrupt("Checksum mismatch"));
+        }
+        Ok(Some(data.to_vec()))
+    }
+
+    /// Fill `buffer` as far as the reader allows, returning the bytes read
+    fn read_fully(&mut self, buffer: &mut [u8]) -> Result<usize, JournalError> {
+        let mut filled = 0;
+        while filled < buffer.len() {
+            match self.reader.read(&mut buffer[filled..]) {
+                Ok(0) => break,
+                Ok(n) => filled += n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(JournalError::new(JournalErrorKind::Io, self.offset, e)),
+            }
+        }
+        self.offset += filled as u64;
+        Ok(filled)
+    }
+
+    /// End of the journal, after `read` bytes of an incomplete record
+    fn end(&mut self, read: usize) -> Option<Vec<u8>> {
+        self.torn = read > 0;
+        None
+    }
+}
+
+impl<R: BufRead> Iterator for JournalReader<R> {
+    type Item = Result<JsonValue, JournalError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        self.read_record()
+    }
+}
+
+/// CRC-32 (IEEE 802.3, as used by zlib and PNG)
+pub fn crc32(bytes: &[u8]) -> u32 {
+    !bytes.iter().fold(!0, |crc, &byte| {
+        CRC32_TABLE[((crc ^ u32::from(byte)) & 0xff) as usize] ^ (crc >> 8)
+    })
+}
+
+const CRC32_TABLE: [u32; 256] = {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json;
+
+    fn journal(format: JournalFormat, records: &[JsonValue]) -> Vec<u8> {
+        let mut writer = JournalWriter::new(Vec::new(), format);
+        for record in records {
+            writer.append(record).unwrap();
+        }
+        writer.into_inner()
+    }
+
+    #[test]
+    fn test_crc32() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+    }
+
+    #[test]
+    fn test_length_prefixed_round_trip_and_torn_tail() {
+        let records = [json!({"a": [1, "x\ny"]}), json!(null), json!("é")];
+        let bytes = journal(JournalFormat::LengthPrefixed, &records);
+        // The last frame: 8 header bytes and `"é"`
+        let last = bytes.len() - 12;
+        for (cut, complete) in [
+            (bytes.len(), 3),
+            (bytes.len() - 1, 2),
+            (last + 4, 2),
+            (last, 2),
+        ] {
+            let mut reader = JournalReader::new(&bytes[..cut], JournalFormat::LengthPrefixed);
+            let read: Vec<JsonValue> = reader.by_ref().map(Result::unwrap).collect();
+            assert_eq!(read, records[..complete]);
+            assert_eq!(reader.is_torn(), cut != bytes.len() && cut != last);
+            assert_eq!(
+                reader.valid_len(),
+                cut.min(if complete == 3 { cut } else { last }) as u64
+            );
+        }
+    }
+
+    #[test]
+    fn test_corrupt_records() {
+        let records = [json!(1), json!(2), json!(3)];
+        let mut bytes = journal(JournalFormat::LengthPrefixed, &records);
+        bytes[9 + 8] = b'7';
+        let results: Vec<_> =
+            JournalReader::new(&bytes[..], JournalFormat::LengthPrefixed).collect();
+        assert_eq!(results.len(), 2);
+        let error = results[1].as_ref().unwrap_err();
+        assert_eq!(
+            (error.kind(), error.offset()),
+            (JournalErrorKind::Corrupt, 9)
+        );
+
+        let mut bytes = journal(JournalFormat::Ndjson, &records);
+        let line = bytes.iter().position(|&b| b == b'\n').unwrap() + 1;
+        let second = line + bytes[line..].iter().position(|&b| b == b'\n').unwrap();
+        bytes[second - 2] = b'7';
+        let mut reader = JournalReader::new(&bytes[..], JournalFormat::Ndjson);
+        let results: Vec<_> = reader.by_ref().collect();
+        assert_eq!(results.len(), 3);
+        assert_eq!(
+            results[1].as_ref().unwrap_err().to_string(),
+            format!("Journal record at byte {}: Checksum mismatch", line)
+        );
+        assert_eq!(results[2].as_ref().unwrap(), &json!(3));
+        assert_eq!(reader.valid_len(), line as u64);
+        assert!(!reader.is_torn());
+    }
+}
@@ -1,3 +1,12 @@
+use std::borrow::Cow;
+use std::fmt;
+
+use crate::error::{ErrorKind, ParseError};
+use crate::number::JsonNumber;
+use crate::object::{Entry, JsonObject};
+use crate::pointer::JsonPointer;
+use crate::view::{ArrayView, ObjectView};
+
 /// JSON value representation
 /// 
 /// Professional Rust developers use enums for representing data structures
@@ -6,62 +15,1116 @@
 /// 2. Pattern matching - elegant handling of different value types
 /// 3. Memory efficiency - enum variants share the same memory space
 /// 4. No runtime type errors - types are checked at compile time
-
-#[derive(Debug, Clone, PartialEq)]
+///
+/// The enum is `#[non_exhaustive]` so new value kinds can be added without a
+/// breaking release; downstream `match`es need a wildcard arm.
+#[derive(Debug, Clone, PartialEq, Default)]
+#[non_exhaustive]
 pub enum JsonValue {
-    /// JSON null value
+    /// JSON null value, also the `Default`
+    #[default]
     Null,
     /// JSON boolean value
     Boolean(bool),
-    /// JSON number (using f64 to handle both integers and floats)
-    Number(f64),
+    /// JSON number (integers are kept exact, see `JsonNumber`)
+    Number(JsonNumber),
     /// JSON string value
     String(String),
+    /// String containing unpaired surrogates (`"\uD800"`), which a Rust
+    /// `String` cannot hold; kept as the escaped text between the quotes, as
+    /// written in the input. Only produced with
+    /// `ParserOptions::preserve_lone_surrogates`.
+    RawString(String),
     /// JSON array
     Array(Vec<JsonValue>),
-    /// JSON object (using Vec for simplicity, HashMap would be better for lookups)
-    Object(Vec<(String, JsonValue)>),
+    /// JSON object (insertion-ordered, with O(1) key lookup)
+    Object(JsonObject),
+    /// Dialect value produced by a `ValuePlugin`: the wrapper key
+    /// (e.g. `$date`) and its payload
+    Extension(String, Box<JsonValue>),
+    /// Value carrying a tag from a binary format (CBOR tag, MessagePack
+    /// ext type). JSON text has no tag syntax, so only the payload is
+    /// written when serializing.
+    #[cfg(feature = "tagged")]
+    Tagged(String, Box<JsonValue>),
 }
 
 impl JsonValue {
+    /// An empty object
+    pub fn new_object() -> Self {
+        JsonValue::Object(JsonObject::new())
+    }
+
+    /// An empty array
+    pub fn new_array() -> Self {
+        JsonValue::Array(Vec::new())
+    }
+
+    /// An empty object with room for `capacity` members
+    pub fn object_with_capacity(capacity: usize) -> Self {
+        JsonValue::Object(JsonObject::with_capacity(capacity))
+    }
+
+    /// An empty array with room for `capacity` elements
+    pub fn array_with_capacity(capacity: usize) -> Self {
+        JsonValue::Array(Vec::with_capacity(capacity))
+    }
+
+    /// Wrap a value with a tag
+    #[cfg(feature = "tagged")]
+    pub fn tagged(tag: impl Into<String>, value: JsonValue) -> Self {
+        JsonValue::Tagged(tag.into(), Box::new(value))
+    }
+
+    /// Strip any `Tagged` wrappers, returning the innermost payload
+    pub fn untagged(&self) -> &JsonValue {
+        #[cfg(feature = "tagged")]
+        if let JsonValue::Tagged(_, payload) = self {
+            return payload.untagged();
+        }
+        self
+    }
+
+    /// Mutable variant of [`JsonValue::untagged`]
+    pub fn untagged_mut(&mut self) -> &mut JsonValue {
+        #[cfg(feature = "tagged")]
+        if let JsonValue::Tagged(_, payload) = self {
+            return payload.untagged_mut();
+        }
+        self
+    }
+
+    pub fn is_null(&self) -> bool {
+        matches!(self.untagged(), JsonValue::Null)
+    }
+
+    pub fn is_boolean(&self) -> bool {
+        matches!(self.untagged(), JsonValue::Boolean(_))
+    }
+
+    pub fn is_number(&self) -> bool {
+        matches!(self.untagged(), JsonValue::Number(_))
+    }
+
+    /// `true` for `String` and `RawString`
+    pub fn is_string(&self) -> bool {
+        matches!(self.untagged(), JsonValue::String(_) | JsonValue::RawString(_))
+    }
+
+    pub fn is_array(&self) -> bool {
+        matches!(self.untagged(), JsonValue::Array(_))
+    }
+
+    pub fn is_object(&self) -> bool {
+        matches!(self.untagged(), JsonValue::Object(_))
+    }
+
+    pub fn is_extension(&self) -> bool {
+        matches!(self.untagged(), JsonValue::Extension(..))
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self.untagged() {
+            JsonValue::Boolean(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    pub fn as_number(&self) -> Option<&JsonNumber> {
+        match self.untagged() {
+            JsonValue::Number(n) => Some(n),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        self.as_number().map(JsonNumber::as_f64)
+    }
+
+    /// Number as `i64`, only if it is integral and within range
+    pub fn as_i64(&self) -> Option<i64> {
+        self.as_number()?.as_i64()
+    }
+
+    /// Number as `u64`, only if it is integral and within range
+    pub fn as_u64(&self) -> Option<u64> {
+        self.as_number()?.as_u64()
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self.untagged() {
+            JsonValue::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// Escaped content of a `RawString`
+    pub fn as_raw_str(&self) -> Option<&str> {
+        match self.untagged() {
+            JsonValue::RawString(raw) => Some(raw),
+            _ => None,
+        }
+    }
+
+    /// Content of a `String`, or of a `RawString` with unpaired surrogates
+    /// replaced by U+FFFD
+    pub fn to_str_lossy(&self) -> Option<Cow<'_, str>> {
+        match self.untagged() {
+            JsonValue::String(s) => Some(Cow::Borrowed(s)),
+            JsonValue::RawString(raw) => Some(
+                unescape_content(raw, '"', true)
+                    .map(|(unescaped, _)| unescaped)
+                    .unwrap_or(Cow::Borrowed(raw)),
+            ),
+            _ => None,
+        }
+    }
+
+    /// Typed read access to the elements (see [`ArrayView`])
+    pub fn as_array(&self) -> Option<ArrayView<'_>> {
+        match self.untagged() {
+            JsonValue::Array(elements) => Some(ArrayView::new(elements)),
+            _ => None,
+        }
+    }
+
+    pub fn as_array_mut(&mut self) -> Option<&mut Vec<JsonValue>> {
+        match self.untagged_mut() {
+            JsonValue::Array(elements) => Some(elements),
+            _ => None,
+        }
+    }
+
+    /// Typed read access to the members (see [`ObjectView`])
+    pub fn as_object(&self) -> Option<ObjectView<'_>> {
+        match self.untagged() {
+            JsonValue::Object(object) => Some(ObjectView::new(object)),
+            _ => None,
+        }
+    }
+
+    pub fn as_object_mut(&mut self) -> Option<&mut JsonObject> {
+        match self.untagged_mut() {
+            JsonValue::Object(object) => Some(object),
+            _ => None,
+        }
+    }
+
+    /// Look up an object member by key
+    pub fn get(&self, key: &str) -> Option<&JsonValue> {
+        self.as_object()?.get(key)
+    }
+
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut JsonValue> {
+        self.as_object_mut()?.get_mut(key)
+    }
+
+    /// Look up an array element by index
+    pub fn get_index(&self, index: usize) -> Option<&JsonValue> {
+        self.as_array()?.get(index)
+    }
+
+    pub fn get_index_mut(&mut self, index: usize) -> Option<&mut JsonValue> {
+        self.as_array_mut()?.get_mut(index)
+    }
+
+    /// Recursively merge `other` into `self`
+    ///
+    /// Members of two objects are merged key by key, new keys being appended;
+    /// in every other case `other` replaces `self` (arrays are not
+    /// concatenated, and `null` is stored like any other value).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use json_parser_rs::parse_json;
+    ///
+    /// let mut config = parse_json(r#"{"db": {"host": "a", "port": 1}, "tags": [1]}"#).unwrap();
+    /// config.deep_merge(parse_json(r#"{"db": {"port": 2}, "tags": [2], "debug": true}"#).unwrap());
+    /// assert_eq!(
+    ///     config.to_json_string(),
+    ///     r#"{"db": {"host": "a", "port": 2}, "tags": [2], "debug": true}"#
+    /// );
+    /// ```
+    pub fn deep_merge(&mut self, other: JsonValue) {
+        self.merge(other, MergeStrategy::Deep);
+    }
+
+    /// Merge `other` into `self` as `strategy` describes
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use json_parser_rs::{MergeStrategy, json};
+    ///
+    /// let mut settings = json!({"theme": "dark", "plugins": ["a"], "ui": {"size": 12}});
+    /// let defaults = json!({"theme": "light", "plugins": ["b"], "ui": {"font": "mono"}});
+    ///
+    /// let mut filled = settings.clone();
+    /// filled.merge(defaults.clone(), MergeStrategy::KeepExisting);
+    /// assert_eq!(filled["theme"], json!("dark"));
+    /// assert_eq!(filled["ui"], json!({"size": 12, "font": "mono"}));
+    ///
+    /// settings.merge(defaults, MergeStrategy::ConcatArrays);
+    /// assert_eq!(settings["plugins"], json!(["a", "b"]));
+    /// assert_eq!(settings["theme"], json!("light"));
+    /// ```
+    pub fn merge(&mut self, other: JsonValue, strategy: MergeStrategy) {
+        match (self.untagged_mut(), other) {
+            (JsonValue::Object(object), JsonValue::Object(other)) => {
+                for (key, value) in other {
+                    match object.get_mut(&key) {
+                        Some(existing) if strategy == MergeStrategy::Shallow => *existing = value,
+                        Some(existing) => existing.merge(value, strategy),
+                        None => {
+                            object.insert(key, value);
+                        }
+                    }
+                }
+            }
+            (JsonValue::Array(elements), JsonValue::Array(other))
+                if strategy == MergeStrategy::ConcatArrays =>
+            {
+                elements.extend(other);
+            }
+            (_, _) if strategy == MergeStrategy::KeepExisting => {}
+            (_, other) => *self = other,
+        }
+    }
+
+    /// Set the member `key` of an object, returning the previous value
+    ///
+    /// A `Null` value is turned into an empty object first.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the value is neither an object nor `Null`.
+    pub fn insert(&mut self, key: impl Into<String>, value: JsonValue) -> Option<JsonValue> {
+        self.object_for_update("insert into").insert(key, value)
+    }
+
+    /// Remove the member `key` of an object; `None` if it is missing or the
+    /// value is not an object
+    pub fn remove(&mut self, key: &str) -> Option<JsonValue> {
+        self.as_object_mut()?.remove(key)
+    }
+
+    /// In-place access to the member `key` of an object (see
+    /// [`JsonObject::entry`])
+    ///
+    /// A `Null` value is turned into an empty object first.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the value is neither an object nor `Null`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use json_parser_rs::{JsonValue, json};
+    ///
+    /// let mut index = JsonValue::Null;
+    /// for (tag, post) in [("rust", 1), ("json", 1), ("rust", 2)] {
+    ///     index.entry(tag).or_insert(json!([])).push(JsonValue::from(post));
+    /// }
+    /// assert_eq!(index, json!({"rust": [1, 2], "json": [1]}));
+    /// ```
+    pub fn entry(&mut self, key: impl Into<String>) -> Entry<'_> {
+        self.object_for_update("take an entry of").entry(key)
+    }
+
+    /// Append an element to an array
+    ///
+    /// A `Null` value is turned into an empty array first.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the value is neither an array nor `Null`.
+    pub fn push(&mut self, value: JsonValue) {
+        if self.is_null() {
+            *self.untagged_mut() = JsonValue::Array(Vec::new());
+        }
+        match self.as_array_mut() {
+            Some(elements) => elements.push(value),
+            None => panic!("cannot push onto non-array JSON value"),
+        }
+    }
+
+    /// The object to modify, turning `Null` into an empty one first
+    fn object_for_update(&mut self, action: &str) -> &mut JsonObject {
+        if self.is_null() {
+            *self.untagged_mut() = JsonValue::Object(JsonObject::new());
+        }
+        match self.as_object_mut() {
+            Some(object) => object,
+            None => panic!("cannot {} non-object JSON value", action),
+        }
+    }
+
     /// Format JSON value back to JSON string (for debugging/output)
+    ///
+    /// Same as `to_string()`; see `write_json` to write to an `io::Write`
+    /// without building the string.
     pub fn to_json_string(&self) -> String {
-        match self {
-            JsonValue::Null => "null".to_string(),
-            JsonValue::Boolean(b) => b.to_string(),
-            JsonValue::Number(n) => {
-                // Format numbers without unnecessary decimal points
-                if n.fract() == 0.0 {
-                    format!("{}", *n as i64)
-                } else {
-                    n.to_string()
+        self.to_string()
+    }
+}
+
+/// How [`JsonValue::merge`] combines two values
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum MergeStrategy {
+    /// Merge objects recursively; otherwise the new value replaces the old
+    /// one (as `deep_merge`)
+    #[default]
+    Deep,
+    /// Only the top-level members of two objects are merged; a member
+    /// present in both is replaced as a whole
+    Shallow,
+    /// As `Deep`, but two arrays are concatenated
+    ConcatArrays,
+    /// As `Deep`, but existing values win: the new value only adds members
+    /// that are missing (e.g. to fill in defaults)
+    KeepExisting,
+}
+
+/// `"[1, 2]".parse::<JsonValue>()`, same as `parse_json`
+impl std::str::FromStr for JsonValue {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, ParseError> {
+        crate::parse_json(s)
+    }
+}
+
+impl From<bool> for JsonValue {
+    fn from(b: bool) -> Self {
+        JsonValue::Boolean(b)
+    }
+}
+
+impl From<JsonNumber> for JsonValue {
+    fn from(n: JsonNumber) -> Self {
+        JsonValue::Number(n)
+    }
+}
+
+macro_rules! impl_from_int {
+    ($($ty:ty),*) => {
+        $(
+            impl From<$ty> for JsonValue {
+                fn from(n: $ty) -> Self {
+                    JsonValue::Number(n.into())
                 }
             }
-            JsonValue::String(s) => format!("\"{}\"", escape_string(s)),
-            JsonValue::Array(arr) => {
-                let items: Vec<String> = arr.iter().map(|v| v.to_json_string()).collect();
-                format!("[{}]", items.join(", "))
+        )*
+    };
+}
+
+impl_from_int!(u8, u16, u32, u64, i8, i16, i32, i64);
+
+/// NaN and infinities have no JSON representation and become `Null`
+impl From<f64> for JsonValue {
+    fn from(f: f64) -> Self {
+        JsonNumber::from_f64(f).map_or(JsonValue::Null, JsonValue::Number)
+    }
+}
+
+impl From<&str> for JsonValue {
+    fn from(s: &str) -> Self {
+        JsonValue::String(s.to_string())
+    }
+}
+
+impl From<String> for JsonValue {
+    fn from(s: String) -> Self {
+        JsonValue::String(s)
+    }
+}
+
+impl From<Vec<JsonValue>> for JsonValue {
+    fn from(elements: Vec<JsonValue>) -> Self {
+        JsonValue::Array(elements)
+    }
+}
+
+impl From<JsonObject> for JsonValue {
+    fn from(object: JsonObject) -> Self {
+        JsonValue::Object(object)
+    }
+}
+
+/// Collect values into an array
+impl FromIterator<JsonValue> for JsonValue {
+    fn from_iter<I: IntoIterator<Item = JsonValue>>(iter: I) -> Self {
+        JsonValue::Array(iter.into_iter().collect())
+    }
+}
+
+/// Collect key/value pairs into an object; later duplicates replace earlier
+/// values
+impl<K: Into<String>> FromIterator<(K, JsonValue)> for JsonValue {
+    fn from_iter<I: IntoIterator<Item = (K, JsonValue)>>(iter: I) -> Self {
+        JsonValue::Object(iter.into_iter().collect())
+    }
+}
+
+static NULL: JsonValue = JsonValue::Null;
+
+/// `value["key"]` - missing keys and non-objects yield `Null`
+impl std::ops::Index<&str> for JsonValue {
+    type Output = JsonValue;
+
+    fn index(&self, key: &str) -> &JsonValue {
+        self.get(key).unwrap_or(&NULL)
+    }
+}
+
+/// `value[0]` - out-of-range indices and non-arrays yield `Null`
+impl std::ops::Index<usize> for JsonValue {
+    type Output = JsonValue;
+
+    fn index(&self, index: usize) -> &JsonValue {
+        self.get_index(index).unwrap_or(&NULL)
+    }
+}
+
+/// `value["key"] = ...` - inserts a `Null` member if the key is missing and
+/// turns a `Null` value into an empty object first
+///
+/// # Panics
+///
+/// Panics if the value is neither an object nor `Null`.
+impl std::ops::IndexMut<&str> for JsonValue {
+    fn index_mut(&mut self, key: &str) -> &mut JsonValue {
+        if self.is_null() {
+            *self.untagged_mut() = JsonValue::Object(JsonObject::new());
+        }
+        let object = match self.as_object_mut() {
+            Some(object) => object,
+            None => panic!("cannot index into non-object JSON value with key {:?}", key),
+        };
+        if !object.contains_key(key) {
+            object.insert(key, JsonValue::Null);
+        }
+        object.get_mut(key).unwrap()
+    }
+}
+
+/// `value[0] = ...`
+///
+/// # Panics
+///
+/// Panics if the value is not an array or the index is out of bounds.
+impl std::ops::IndexMut<usize> for JsonValue {
+    fn index_mut(&mut self, index: usize) -> &mut JsonValue {
+        match self.as_array_mut() {
+            Some(elements) => {
+                let len = elements.len();
+                elements.get_mut(index).unwrap_or_else(|| {
+                    panic!("index {} out of bounds for JSON array of length {}", index, len)
+                })
             }
-            JsonValue::Object(obj) => {
-                let pairs: Vec<String> = obj
-                    .iter()
-                    .map(|(k, v)| format!("\"{}\": {}", escape_string(k), v.to_json_string()))
-                    .collect();
-                format!("{{{}}}", pairs.join(", "))
+            None => panic!("cannot index into non-array JSON value with {}", index),
+        }
+    }
+}
+
+/// `a + b`: concatenate two arrays or merge two objects
+///
+/// Objects are merged recursively as by [`MergeStrategy::ConcatArrays`]:
+/// members only in `b` are added, two object members are merged, two
+/// array members concatenated, and any other member of `b` replaces the
+/// one in `a`. `Null` on either side is the identity, so optional parts
+/// can be added unconditionally; for other mismatched values `b` wins.
+///
+/// # Examples
+///
+/// ```
+/// use json_parser_rs::json;
+///
+/// let page = json!({"items": [1, 2], "meta": {"page": 1}})
+///     + json!({"items": [3], "meta": {"total": 3}});
+/// assert_eq!(page, json!({"items": [1, 2, 3], "meta": {"page": 1, "total": 3}}));
+/// assert_eq!(json!([1]) + json!([2, 3]) + json!(null), json!([1, 2, 3]));
+/// ```
+impl std::ops::Add for JsonValue {
+    type Output = JsonValue;
+
+    fn add(mut self, other: JsonValue) -> JsonValue {
+        self += other;
+        self
+    }
+}
+
+/// `a += b`, the in-place form of `a + b`
+impl std::ops::AddAssign for JsonValue {
+    fn add_assign(&mut self, other: JsonValue) {
+        if !other.is_null() {
+            self.merge(other, MergeStrategy::ConcatArrays);
+        }
+    }
+}
+
+/// The JSON Schema type of a value; integral numbers are `integer`
+pub(crate) fn type_name(value: &JsonValue) -> &'static str {
+    match value.untagged() {
+        JsonValue::Null => "null",
+        JsonValue::Boolean(_) => "boolean",
+        JsonValue::Number(n) if n.as_f64().fract() == 0.0 => "integer",
+        JsonValue::Number(_) => "number",
+        JsonValue::String(_) | JsonValue::RawString(_) => "string",
+        JsonValue::Array(_) => "array",
+        JsonValue::Object(_) | JsonValue::Extension(..) => "object",
+        #[cfg(feature = "tagged")]
+        JsonValue::Tagged(..) => unreachable!("untagged() strips tags"),
+    }
+}
+
+/// Escape string content for use between JSON quotes
+///
+/// Quotes, backslashes and all control characters (U+0000 to U+001F) are
+/// escaped, using the short forms `\b`, `\f`, `\n`, `\r` and `\t` where
+/// JSON has them and `\u00XX` otherwise. Returns the input unchanged
+/// (borrowed) when nothing needs escaping.
+pub fn escape(s: &str) -> Cow<'_, str> {
+    escape_content(s, false)
+}
+
+/// Like [`escape`], but also writes every non-ASCII character as a `\uXXXX`
+/// escape (a surrogate pair outside the Basic Multilingual Plane), so the
+/// result is pure ASCII
+///
+/// # Examples
+///
+/// ```
+/// use json_parser_rs::json::escape_ascii;
+///
+/// assert_eq!(escape_ascii("Grüße 😀\u{1}"), r"Gr\u00fc\u00dfe \ud83d\ude00\u0001");
+/// ```
+pub fn escape_ascii(s: &str) -> Cow<'_, str> {
+    escape_content(s, true)
+}
+
+fn escape_content(s: &str, ascii_only: bool) -> Cow<'_, str> {
+    let needs_escape =
+        |b: u8| b < 0x20 || b == b'"' || b == b'\\' || (ascii_only && !b.is_ascii());
+    let Some(first) = s.bytes().position(needs_escape) else {
+        return Cow::Borrowed(s);
+    };
+
+    let mut escaped = String::with_capacity(s.len() + 8);
+    escaped.push_str(&s[..first]);
+    for ch in s[first..].chars() {
+        match ch {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            '\u{8}' => escaped.push_str("\\b"),
+            '\u{c}' => escaped.push_str("\\f"),
+            ch if ch < ' ' || (ascii_only && !ch.is_ascii()) => {
+                push_unicode_escape(&mut escaped, ch)
             }
+            _ => escaped.push(ch),
         }
     }
+    Cow::Owned(escaped)
 }
 
-fn escape_string(s: &str) -> String {
-    s.chars()
-        .flat_map(|c| match c {
-            '"' => vec!['\\', '"'],
-            '\\' => vec!['\\', '\\'],
-            '\n' => vec!['\\', 'n'],
-            '\r' => vec!['\\', 'r'],
-            '\t' => vec!['\\', 't'],
-            _ => vec![c],
+/// Append `ch` as one or two (surrogate pair) `\uXXXX` escapes
+pub(crate) fn push_unicode_escape(out: &mut String, ch: char) {
+    for unit in ch.encode_utf16(&mut [0; 2]) {
+        fmt::Write::write_fmt(out, format_args!("\\u{:04x}", unit))
+            .expect("writing to a String cannot fail");
+    }
+}
+
+/// Decode the escape sequences in JSON string content (without the quotes)
+///
+/// Supports all RFC 8259 escapes including `\uXXXX` and surrogate pairs.
+/// Returns the input unchanged (borrowed) when it contains no escapes.
+/// Error positions are byte offsets into `s`.
+pub fn unescape(s: &str) -> Result<Cow<'_, str>, ParseError> {
+    unescape_content(s, '"', false)
+        .map(|(unescaped, _)| unescaped)
+        .map_err(|(message, offset)| {
+            ParseError::new(message, offset, s).with_kind(ErrorKind::InvalidEscape)
         })
-        .collect()
+}
+
+/// Shared decoder for `unescape` and the lexer; errors carry a message and
+/// the byte offset of the offending escape within `s`
+///
+/// `quote` is the delimiter of the string; inside single-quoted (JSON5)
+/// strings `\'` is an escape as well. With `lone_surrogates` unpaired
+/// surrogates decode to U+FFFD instead of failing; the returned flag tells
+/// whether that happened.
+pub(crate) fn unescape_content(
+    s: &str,
+    quote: char,
+    lone_surrogates: bool,
+) -> Result<(Cow<'_, str>, bool), (String, usize)> {
+    if !s.contains('\\') {
+        return Ok((Cow::Borrowed(s), false));
+    }
+    let mut unescaped = String::with_capacity(s.len());
+    let (_, replaced) = walk_escapes(s, quote, lone_surrogates, Some(&mut unescaped))?;
+    Ok((Cow::Owned(unescaped), replaced))
+}
+
+/// Check the escapes in `s` like `unescape_content` without building the
+/// unescaped string; returns its length in bytes and the replacement flag
+pub(crate) fn check_escapes(
+    s: &str,
+    quote: char,
+    lone_surrogates: bool,
+) -> Result<(usize, bool), (String, usize)> {
+    walk_escapes(s, quote, lone_surrogates, None)
+}
+
+/// Decode the escapes in `s`, appending the result to `out` if given
+fn walk_escapes(
+    s: &str,
+    quote: char,
+    lone_surrogates: bool,
+    mut out: Option<&mut String>,
+) -> Result<(usize, bool), (String, usize)> {
+    let mut len = 0;
+    let mut push = |text: &str| {
+        len += text.len();
+        if let Some(out) = out.as_deref_mut() {
+            out.push_str(text);
+        }
+    };
+    let mut i = 0;
+    let mut replaced = false;
+    let mut next = s.find('\\');
+    while let Some(at) = next {
+        push(&s[i..at]);
+        let Some(&code) = s.as_bytes().get(at + 1) else {
+            return Err(("Unterminated escape sequence".to_string(), at));
+        };
+        i = at + 2;
+        let ch = match code {
+            b'"' => '"',
+            b'\'' if quote == '\'' => '\'',
+            b'\\' => '\\',
+            b'/' => '/',
+            b'b' => '\u{8}',
+            b'f' => '\u{c}',
+            b'n' => '\n',
+            b'r' => '\r',
+            b't' => '\t',
+            b'u' => {
+                let (ch, len) = decode_unicode_escape(s, at)?;
+                i = at + len;
+                match ch {
+                    Some(ch) => ch,
+                    None if lone_surrogates => {
+                        replaced = true;
+                        char::REPLACEMENT_CHARACTER
+                    }
+                    None => {
+                        let message = "Unpaired surrogate in unicode escape".to_string();
+                        return Err((message, at));
+                    }
+                }
+            }
+            _ => {
+                let ch = s[at + 1..].chars().next().unwrap_or_default();
+                return Err((format!("Invalid escape sequence: \\{}", ch), at));
+            }
+        };
+        push(ch.encode_utf8(&mut [0; 4]));
+        next = s[i..].find('\\').map(|rel| i + rel);
+    }
+    push(&s[i..]);
+    Ok((len, replaced))
+}
+
+/// Decode `\uXXXX` (or a `\uD8XX\uDCXX` surrogate pair) starting at `at`,
+/// returning the character (`None` for an unpaired surrogate) and the length
+/// of the escape in bytes
+fn decode_unicode_escape(s: &str, at: usize) -> Result<(Option<char>, usize), (String, usize)> {
+    let hex4 = |start: usize| {
+        s.get(start..start + 4)
+            .filter(|digits| digits.bytes().all(|b| b.is_ascii_hexdigit()))
+            .and_then(|digits| u32::from_str_radix(digits, 16).ok())
+    };
+    let invalid = || ("Invalid unicode escape".to_string(), at);
+
+    let high = hex4(at + 2).ok_or_else(invalid)?;
+    match high {
+        0xD800..=0xDBFF => {
+            let low = s[at + 6..]
+                .starts_with("\\u")
+                .then(|| hex4(at + 8))
+                .flatten()
+                .filter(|low| (0xDC00..=0xDFFF).contains(low));
+            match low {
+                Some(low) => {
+                    let code = 0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00);
+                    Ok((Some(char::from_u32(code).ok_or_else(invalid)?), 12))
+                }
+                None => Ok((None, 6)),
+            }
+        }
+        0xDC00..=0xDFFF => Ok((None, 6)),
+        _ => Ok((Some(char::from_u32(high).ok_or_else(invalid)?), 6)),
+    }
+}
+
+/// One difference between two documents, as reported by [`diff`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum DiffEntry {
+    /// A member or array element only present in the new document
+    Added { path: JsonPointer, value: JsonValue },
+    /// A member or array element only present in the old document
+    Removed { path: JsonPointer, value: JsonValue },
+    /// A value that differs between the documents; containers are only
+    /// reported as changed when their kinds differ
+    Changed {
+        path: JsonPointer,
+        old: JsonValue,
+        new: JsonValue,
+    },
+}
+
+impl DiffEntry {
+    /// Location of the difference
+    pub fn path(&self) -> &JsonPointer {
+        match self {
+            DiffEntry::Added { path, .. }
+            | DiffEntry::Removed { path, .. }
+            | DiffEntry::Changed { path, .. } => path,
+        }
+    }
+}
+
+/// One line per entry: `+ /path: value`, `- /path: value` or
+/// `~ /path: old -> new`, with values in compact JSON
+impl fmt::Display for DiffEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let path = |path: &JsonPointer| {
+            let text = path.to_string();
+            if text.is_empty() { "(root)".to_string() } else { text }
+        };
+        match self {
+            DiffEntry::Added { path: at, value } => write!(f, "+ {}: {}", path(at), value),
+            DiffEntry::Removed { path: at, value } => write!(f, "- {}: {}", path(at), value),
+            DiffEntry::Changed { path: at, old, new } => {
+                write!(f, "~ {}: {} -> {}", path(at), old, new)
+            }
+        }
+    }
+}
+
+/// Structural differences between two documents
+///
+/// Objects are compared member by member and arrays index by index, so a
+/// changed leaf is reported at its own path instead of as a change of the
+/// whole document. Entries come in document order: removed members of an
+/// object before added ones. Use `patch::diff` for an applicable RFC 6902
+/// patch instead.
+///
+/// # Examples
+///
+/// ```
+/// use json_parser_rs::json::{DiffEntry, diff};
+/// use json_parser_rs::{JsonPointer, json};
+///
+/// let old = json!({"name": "api", "tags": ["a", "b"], "port": 80});
+/// let new = json!({"name": "api", "tags": ["a"], "port": 8080, "tls": true});
+/// let entries = diff(&old, &new);
+/// assert_eq!(entries.len(), 3);
+/// assert_eq!(entries[0].path(), &JsonPointer::parse("/tags/1").unwrap());
+/// assert_eq!(entries[1].to_string(), "~ /port: 80 -> 8080");
+/// assert!(matches!(&entries[2], DiffEntry::Added { value, .. } if value.as_bool() == Some(true)));
+/// ```
+pub fn diff(old: &JsonValue, new: &JsonValue) -> Vec<DiffEntry> {
+    let mut entries = Vec::new();
+    diff_into(old, new, &mut Vec::new(), &mut entries);
+    entries
+}
+
+fn diff_into(old: &JsonValue, new: &JsonValue, path: &mut Vec<String>, entries: &mut Vec<DiffEntry>) {
+    if old == new {
+        return;
+    }
+    let at = |path: &[String]| JsonPointer::from_tokens(path);
+    match (old, new) {
+        (JsonValue::Object(old), JsonValue::Object(new)) => {
+            for (key, old_value) in old {
+                path.push(key.clone());
+                match new.get(key) {
+                    Some(new_value) => diff_into(old_value, new_value, path, entries),
+                    None => entries.push(DiffEntry::Removed {
+                        path: at(path),
+                        value: old_value.clone(),
+                    }),
+                }
+                path.pop();
+            }
+            for (key, new_value) in new {
+                if !old.contains_key(key) {
+                    path.push(key.clone());
+                    entries.push(DiffEntry::Added {
+                        path: at(path),
+                        value: new_value.clone(),
+                    });
+                    path.pop();
+                }
+            }
+        }
+        (JsonValue::Array(old), JsonValue::Array(new)) => {
+            for (i, (old_value, new_value)) in old.iter().zip(new).enumerate() {
+                path.push(i.to_string());
+                diff_into(old_value, new_value, path, entries);
+                path.pop();
+            }
+            for (i, old_value) in old.iter().enumerate().skip(new.len()) {
+                path.push(i.to_string());
+                entries.push(DiffEntry::Removed {
+                    path: at(path),
+                    value: old_value.clone(),
+                });
+                path.pop();
+            }
+            for (i, new_value) in new.iter().enumerate().skip(old.len()) {
+                path.push(i.to_string());
+                entries.push(DiffEntry::Added {
+                    path: at(path),
+                    value: new_value.clone(),
+                });
+                path.pop();
+            }
+        }
+        _ => entries.push(DiffEntry::Changed {
+            path: at(path),
+            old: old.clone(),
+            new: new.clone(),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_json;
+
+    #[test]
+    fn test_accessors() {
+        let value = parse_json(r#"{"name": "Ada", "age": 36, "tags": [true, null, 1.5]}"#).unwrap();
+        assert!(value.is_object());
+        assert_eq!(value.get("name").and_then(JsonValue::as_str), Some("Ada"));
+        assert_eq!(value.get("age").and_then(JsonValue::as_i64), Some(36));
+        assert_eq!(value.get("missing"), None);
+
+        let tags = value.get("tags").unwrap();
+        assert_eq!(tags.as_array().map(|tags| tags.len()), Some(3));
+        assert_eq!(tags.get_index(0).and_then(JsonValue::as_bool), Some(true));
+        assert!(tags.get_index(1).unwrap().is_null());
+        assert_eq!(tags.get_index(2).and_then(JsonValue::as_i64), None);
+        assert_eq!(tags.get_index(2).and_then(JsonValue::as_f64), Some(1.5));
+    }
+
+    #[test]
+    fn test_escape_borrows_when_possible() {
+        assert!(matches!(escape("plain"), Cow::Borrowed("plain")));
+        assert_eq!(escape("a\"b\\c\n"), "a\\\"b\\\\c\\n");
+        assert_eq!(escape("\u{1}\u{8}\u{c}\u{1f}\u{7f}"), "\\u0001\\b\\f\\u001f\u{7f}");
+        assert!(matches!(escape("grün"), Cow::Borrowed("grün")));
+        assert_eq!(unescape(&escape_ascii("\u{0}é\u{10ffff}")).unwrap(), "\u{0}é\u{10ffff}");
+    }
+
+    #[test]
+    fn test_unescape() {
+        assert!(matches!(unescape("plain"), Ok(Cow::Borrowed("plain"))));
+        assert_eq!(
+            unescape(r"tab\t slash\/ \u00e9 \ud83d\ude00 \b\f").unwrap(),
+            "tab\t slash/ é 😀 \u{8}\u{c}"
+        );
+        assert!(unescape(r"\q").is_err());
+        assert!(unescape(r"\u12").is_err());
+        assert!(unescape(r"\ud83d alone").is_err());
+        assert!(unescape(r"\ude00").is_err());
+        assert_eq!(unescape(r"ab\x").unwrap_err().position, 2);
+    }
+
+    #[test]
+    fn test_large_integer_round_trip() {
+        let input = r#"{"id": 9007199254740993, "big": -9223372036854775807, "f": 0.5}"#;
+        let value = parse_json(input).unwrap();
+        assert_eq!(value["id"].as_u64(), Some(9007199254740993));
+        assert_eq!(value.to_json_string(), input);
+    }
+
+    #[test]
+    fn test_get_mut() {
+        let mut value = parse_json(r#"{"list": [1]}"#).unwrap();
+        value
+            .get_mut("list")
+            .and_then(JsonValue::as_array_mut)
+            .unwrap()
+            .push(JsonValue::Null);
+        assert_eq!(value.to_json_string(), r#"{"list": [1, null]}"#);
+    }
+
+    #[test]
+    fn test_index() {
+        let value = parse_json(r#"{"users": [{"name": "Ada"}]}"#).unwrap();
+        assert_eq!(value["users"][0]["name"].as_str(), Some("Ada"));
+        assert!(value["users"][1]["name"].is_null());
+        assert!(value["missing"]["deeper"][3].is_null());
+    }
+
+    #[test]
+    fn test_index_mut() {
+        let mut value = JsonValue::Null;
+        value["config"]["debug"] = JsonValue::Boolean(true);
+        value["list"] = JsonValue::Array(vec![JsonValue::Null]);
+        value["list"][0] = JsonValue::Number(1.into());
+        assert_eq!(value.to_json_string(), r#"{"config": {"debug": true}, "list": [1]}"#);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_index_mut_out_of_bounds() {
+        let mut value = JsonValue::Array(vec![]);
+        value[0] = JsonValue::Null;
+    }
+
+    #[test]
+    #[cfg(feature = "tagged")]
+    fn test_tagged_serializes_payload() {
+        let value = JsonValue::tagged("1", JsonValue::Number(1363896240.into()));
+        assert_eq!(value.to_json_string(), "1363896240");
+        assert_eq!(value.untagged(), &JsonValue::Number(1363896240.into()));
+    }
+
+    #[test]
+    fn test_lone_surrogates_round_trip() {
+        use crate::{Parser, ParserOptions};
+
+        let input = r#"["\ud800x", "\uDC00\n", "😀"]"#;
+        assert!(parse_json(input).is_err());
+
+        let options = ParserOptions::default().preserve_lone_surrogates(true);
+        let value = Parser::with_options(input, options).unwrap().parse().unwrap();
+        assert_eq!(value[0].as_raw_str(), Some(r"\ud800x"));
+        assert!(value[0].is_string() && value[0].as_str().is_none());
+        assert_eq!(value[1].to_str_lossy().as_deref(), Some("\u{FFFD}\n"));
+        assert_eq!(value[2].as_str(), Some("\u{1F600}"));
+        assert_eq!(value.to_json_string(), r#"["\ud800x", "\uDC00\n", "😀"]"#);
+
+        let key = Parser::with_options(r#"{"\ud800": 1}"#, options).unwrap().parse();
+        assert_eq!(key.unwrap_err().kind(), ErrorKind::InvalidEscape);
+    }
+
+    #[test]
+    fn test_conversions() {
+        let parsed: JsonValue = r#"{"a": [1, -2, 1.5, "x", true, null]}"#.parse().unwrap();
+        let built: JsonValue = [(
+            "a",
+            JsonValue::from(vec![
+                1u8.into(),
+                (-2i64).into(),
+                1.5.into(),
+                "x".into(),
+                true.into(),
+                JsonValue::Null,
+            ]),
+        )]
+        .into_iter()
+        .collect();
+        assert_eq!(parsed, built);
+
+        assert_eq!(JsonValue::from(f64::NAN), JsonValue::Null);
+        let squares: JsonValue = (1..=3).map(|n: u32| JsonValue::from(n * n)).collect();
+        assert_eq!(squares.to_json_string(), "[1, 4, 9]");
+        assert!("[1,".parse::<JsonValue>().is_err());
+    }
+
+    #[test]
+    fn test_diff_reports_leaf_paths() {
+        let old = parse_json(r#"{"a": {"b": 1, "c": [1, 2]}, "d": null, "e": "x"}"#).unwrap();
+        let new = parse_json(r#"{"a": {"b": 2, "c": [1, 2, 3]}, "d": {"f": 1}, "g": []}"#).unwrap();
+        let lines: Vec<String> = diff(&old, &new).iter().map(ToString::to_string).collect();
+        assert_eq!(
+            lines,
+            [
+                "~ /a/b: 1 -> 2",
+                "+ /a/c/2: 3",
+                "~ /d: null -> {\"f\": 1}",
+                "- /e: \"x\"",
+                "+ /g: []",
+            ]
+        );
+        assert!(diff(&old, &old).is_empty());
+        assert_eq!(diff(&old, &JsonValue::Null)[0].to_string().split(':').next(), Some("~ (root)"));
+    }
+
+    #[test]
+    fn test_merge_strategies() {
+        let base = parse_json(r#"{"a": {"b": 1, "c": [1]}, "d": 1}"#).unwrap();
+        let patch = parse_json(r#"{"a": {"c": [2], "e": 3}, "f": 4}"#).unwrap();
+        let cases = [
+            (MergeStrategy::Deep, r#"{"a": {"b": 1, "c": [2], "e": 3}, "d": 1, "f": 4}"#),
+            (MergeStrategy::Shallow, r#"{"a": {"c": [2], "e": 3}, "d": 1, "f": 4}"#),
+            (
+                MergeStrategy::ConcatArrays,
+                r#"{"a": {"b": 1, "c": [1, 2], "e": 3}, "d": 1, "f": 4}"#,
+            ),
+            (MergeStrategy::KeepExisting, r#"{"a": {"b": 1, "c": [1], "e": 3}, "d": 1, "f": 4}"#),
+        ];
+        for (strategy, expected) in cases {
+            let mut merged = base.clone();
+            merged.merge(patch.clone(), strategy);
+            assert_eq!(merged, parse_json(expected).unwrap(), "{:?}", strategy);
+        }
+    }
+
+    #[test]
+    fn test_default_and_constructors() {
+        assert_eq!(JsonValue::default(), JsonValue::Null);
+        let mut object = JsonValue::object_with_capacity(4);
+        object.insert("items", JsonValue::new_array());
+        object["items"].push(JsonValue::new_object());
+        assert_eq!(object, parse_json(r#"{"items": [{}]}"#).unwrap());
+        assert!(JsonValue::array_with_capacity(8).as_array_mut().unwrap().capacity() >= 8);
+        let values: Vec<JsonValue> = vec![Default::default(); 2];
+        assert_eq!(values, [JsonValue::Null, JsonValue::Null]);
+    }
+
+    #[test]
+    fn test_add_operators() {
+        let mut response = parse_json(r#"{"data": [1], "links": {"self": "/a"}}"#).unwrap();
+        response += parse_json(r#"{"data": [2], "links": {"next": "/b"}, "total": 2}"#).unwrap();
+        let expected = r#"{"data": [1, 2], "links": {"self": "/a", "next": "/b"}, "total": 2}"#;
+        assert_eq!(response, parse_json(expected).unwrap());
+
+        let list = JsonValue::Null + JsonValue::from(vec![JsonValue::from(1)]);
+        assert_eq!(list, parse_json("[1]").unwrap());
+        assert_eq!(list.clone() + JsonValue::Null, list);
+        assert_eq!(list + JsonValue::from("x"), JsonValue::from("x"));
+    }
+
+    #[test]
+    fn test_insert_remove_push() {
+        let mut value = JsonValue::Null;
+        assert_eq!(value.insert("a", JsonValue::from(1)), None);
+        value.entry("list").or_default().push(JsonValue::Boolean(true));
+        value.entry("a").and_modify(|a| *a = JsonValue::from(2)).or_insert(JsonValue::Null);
+        assert_eq!(value, parse_json(r#"{"a": 2, "list": [true]}"#).unwrap());
+        assert_eq!(value.remove("a"), Some(JsonValue::from(2)));
+        assert_eq!(value["list"].remove("a"), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "cannot push onto non-array JSON value")]
+    fn test_push_onto_object_panics() {
+        JsonValue::Object(JsonObject::new()).push(JsonValue::Null);
+    }
 }
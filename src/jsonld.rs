@@ -0,0 +1,973 @@
+//! JSON-LD - `@context`-driven expansion and compaction
+//!
+//! Linked-data documents can say the same thing with different keys:
+//! `"name"`, `"schema:name"` and `"http://schema.org/name"` may all be the
+//! same property. [`expand`] rewrites a document into JSON-LD expanded form
+//! (full IRIs as keys, every property value an array of value or node
+//! objects), which no longer depends on a context and can be compared or
+//! stored as is; [`compact`] turns any form back into short keys under a
+//! chosen [`Context`].
+//!
+//! This implements a subset of the JSON-LD 1.1 algorithms: terms, compact
+//! IRIs, `@vocab`, keyword aliases, embedded contexts, type coercion
+//! (`"@type": "@id"`, `"@vocab"` or a datatype IRI) and `@list`/`@set`
+//! containers. Remote contexts, `@base` resolution of relative IRIs,
+//! `@language`, `@reverse`, scoped contexts and framing are not supported;
+//! contexts that use them are rejected with [`JsonLdErrorKind::Unsupported`].
+//!
+//! # Examples
+//!
+//! ```
+//! use json_parser_rs::jsonld::{Context, compact, expand};
+//! use json_parser_rs::json;
+//!
+//! let short = json!({
+//!     "@context": {"schema": "http://schema.org/", "name": "schema:name"},
+//!     "name": "Ada"
+//! });
+//! let long = json!({"http://schema.org/name": "Ada"});
+//! assert_eq!(expand(&short).unwrap(), expand(&long).unwrap());
+//! assert_eq!(
+//!     expand(&long).unwrap(),
+//!     json!([{"http://schema.org/name": [{"@value": "Ada"}]}])
+//! );
+//!
+//! let context = Context::parse(&json!({"@vocab": "http://schema.org/"})).unwrap();
+//! assert_eq!(
+//!     compact(&short, &context).unwrap(),
+//!     json!({"@context": {"@vocab": "http://schema.org/"}, "name": "Ada"})
+//! );
+//! ```
+
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+use crate::json::JsonValue;
+use crate::object::JsonObject;
+use crate::view::ArrayView;
+
+const KEYWORDS: &[&str] = &[
+    "@base",
+    "@container",
+    "@context",
+    "@direction",
+    "@graph",
+    "@id",
+    "@import",
+    "@included",
+    "@index",
+    "@json",
+    "@language",
+    "@list",
+    "@nest",
+    "@none",
+    "@prefix",
+    "@propagate",
+    "@protected",
+    "@reverse",
+    "@set",
+    "@type",
+    "@value",
+    "@version",
+    "@vocab",
+];
+
+fn is_keyword(s: &str) -> bool {
+    KEYWORDS.contains(&s)
+}
+
+/// Why a context or document could not be processed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JsonLdError {
+    kind: JsonLdErrorKind,
+    message: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum JsonLdErrorKind {
+    /// A context or term definition is malformed
+    InvalidContext,
+    /// A keyword such as `@id` has a value of the wrong type
+    InvalidKeywordValue,
+    /// The document uses a JSON-LD feature outside the supported subset
+    Unsupported,
+}
+
+impl JsonLdError {
+    fn new(kind: JsonLdErrorKind, message: impl fmt::Display) -> Self {
+        Self {
+            kind,
+            message: message.to_string(),
+        }
+    }
+
+    pub fn kind(&self) -> JsonLdErrorKind {
+        self.kind
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl fmt::Display for JsonLdError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for JsonLdError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Container {
+    List,
+    Set,
+}
+
+/// Definition of one term of a context
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Term {
+    /// Expanded IRI or keyword; `None` for a term mapped to `null`, which
+    /// keeps its key from being expanded with `@vocab`
+    iri: Option<String>,
+    /// `@id`, `@vocab` or a datatype IRI
+    type_mapping: Option<String>,
+    container: Option<Container>,
+}
+
+impl Term {
+    fn is_simple(&self) -> bool {
+        self.type_mapping.is_none() && self.container.is_none()
+    }
+}
+
+/// The `@id` entry of a term definition
+enum IdMapping<'a> {
+    Iri(&'a str),
+    Null,
+    /// No `@id`: the term itself is a compact IRI or is relative to `@vocab`
+    Implicit,
+}
+
+/// Active context: the term definitions and `@vocab` in effect
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Context {
+    vocab: Option<String>,
+    terms: BTreeMap<String, Term>,
+    /// The local contexts applied so far, written out by `compact`
+    sources: Vec<JsonValue>,
+}
+
+impl Context {
+    /// Context without any terms
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Context from a `@context` value: an object, `null` or an array of
+    /// these
+    pub fn parse(value: &JsonValue) -> Result<Self, JsonLdError> {
+        Context::new().with(value)
+    }
+
+    /// This context with the local context `value` applied on top
+    pub fn with(&self, value: &JsonValue) -> Result<Self, JsonLdError> {
+        let mut context = self.clone();
+        match value.untagged() {
+            JsonValue::Null => context = Context::new(),
+            JsonValue::Array(locals) => {
+                for local in locals {
+                    context = context.with(local)?;
+                }
+            }
+            JsonValue::Object(definitions) => {
+                context.define(definitions)?;
+                context.sources.push(value.clone());
+            }
+            JsonValue::String(url) => {
+                return Err(JsonLdError::new(
+                    JsonLdErrorKind::Unsupported,
+                    format!("Remote context '{}' cannot be loaded", url),
+                ));
+            }
+            other => {
+                return Err(JsonLdError::new(
+                    JsonLdErrorKind::InvalidContext,
+                    format!(
+                        "A context must be an object, an array or null, found {}",
+                        other
+                    ),
+                ));
+            }
+        }
+        Ok(context)
+    }
+
+    /// The `@context` value that recreates this context, `None` for an
+    /// empty one
+    pub fn to_value(&self) -> Option<JsonValue> {
+        match &self.sources[..] {
+            [] => None,
+            [source] => Some(source.clone()),
+            sources => Some(JsonValue::Array(sources.to_vec())),
+        }
+    }
+
+    pub fn vocab(&self) -> Option<&str> {
+        self.vocab.as_deref()
+    }
+
+    /// The IRI (or keyword) a term stands for
+    pub fn term_iri(&self, term: &str) -> Option<&str> {
+        self.terms.get(term)?.iri.as_deref()
+    }
+
+    /// Expand a term, compact IRI or (with `vocab`) vocabulary-relative
+    /// name to an IRI
+    ///
+    /// Absolute and relative IRIs are returned unchanged; `None` means the
+    /// term is explicitly mapped to `null`.
+    pub fn expand_iri(&self, value: &str, vocab: bool) -> Option<String> {
+        if is_keyword(value) {
+            return Some(value.to_string());
+        }
+        if vocab && let Some(term) = self.terms.get(value) {
+            return term.iri.clone();
+        }
+        if let Some((prefix, suffix)) = value.split_once(':') {
+            if prefix == "_" || suffix.starts_with("//") {
+                return Some(value.to_string());
+            }
+            if let Some(Term { iri: Some(iri), .. }) = self.terms.get(prefix) {
+                return Some(format!("{}{}", iri, suffix));
+            }
+            return Some(value.to_string());
+        }
+        match &self.vocab {
+            Some(base) if vocab => Some(format!("{}{}", base, value)),
+            _ => Some(value.to_string()),
+        }
+    }
+
+    /// Shortest form of `iri` that expands back to it: a term, a
+    /// vocabulary-relative name (with `vocab`), a compact IRI or the IRI
+    /// itself
+    pub fn compact_iri(&self, iri: &str, vocab: bool) -> String {
+        if vocab {
+            let term = self
+                .terms
+                .iter()
+                .filter(|(_, term)| term.is_simple() && term.iri.as_deref() == Some(iri))
+                .map(|(name, _)| name)
+                .min_by(|a, b| a.len().cmp(&b.len()).then(a.cmp(b)));
+            if let Some(term) = term {
+                return term.clone();
+            }
+            if let Some(suffix) = self
+                .vocab
+                .as_deref()
+                .and_then(|base| iri.strip_prefix(base))
+                && !suffix.is_empty()
+                && !suffix.contains(':')
+                && !self.terms.contains_key(suffix)
+            {
+                return suffix.to_string();
+            }
+        }
+        if is_keyword(iri) {
+            return iri.to_string();
+        }
+        self.terms
+            .iter()
+            .filter(|(name, term)| {
+                !name.contains(':')
+                    && term.is_simple()
+                    && term.iri.as_deref().is_some_and(|prefix| {
+                        prefix.ends_with(['/', '#', ':', '?', '[', ']', '@'])
+                            && iri.len() > prefix.len()
+                            && iri.starts_with(prefix)
+                    })
+            })
+            .map(|(name, term)| {
+                let prefix = term.iri.as_deref().unwrap_or_default();
+                format!("{}:{}", name, &iri[prefix.len()..])
+            })
+            .filter(|compact| !self.terms.contains_key(compact))
+            .min_by(|a, b| a.len().cmp(&b.len()).then(a.cmp(b)))
+            .unwrap_or_else(|| iri.to_string())
+    }
+
+    fn define(&mut self, definitions: &JsonObject) -> Result<(), JsonLdError> {
+        let mut defined = HashMap::new();
+        for (key, value) in definitions.iter() {
+            match key.as_str() {
+                "@vocab" => self.vocab = self.vocab_mapping(value)?,
+                "@version" => {}
+                keyword if keyword.starts_with('@') => {
+                    return Err(unsupported_or_invalid(keyword, "in a context"));
+                }
+                term => self.define_term(definitions, term, &mut defined)?,
+            }
+        }
+        Ok(())
+    }
+
+    fn vocab_mapping(&self, value: &JsonValue) -> Result<Option<String>, JsonLdError> {
+        match value.untagged() {
+            JsonValue::Null => Ok(None),
+            JsonValue::String(vocab) => match self.expand_iri(vocab, true) {
+                Some(iri) if iri.contains(':') => Ok(Some(iri)),
+                _ => Err(JsonLdError::new(
+                    JsonLdErrorKind::InvalidContext,
+                    format!("@vocab must be an absolute IRI, found '{}'", vocab),
+                )),
+            },
+            other => Err(JsonLdError::new(
+                JsonLdErrorKind::InvalidContext,
+                format!("@vocab must be a string or null, found {}", other),
+            )),
+        }
+    }
+
+    /// Define `term` from `definitions`, first defining the terms of the
+    /// same local context that its IRIs refer to
+    fn define_term(
+        &mut self,
+        definitions: &JsonObject,
+        term: &str,
+        defined: &mut HashMap<String, bool>,
+    ) -> Result<(), JsonLdError> {
+        match defined.get(term) {
+            Some(true) => return Ok(()),
+            Some(false) => {
+                return Err(JsonLdError::new(
+                    JsonLdErrorKind::InvalidContext,
+                    format!("Cyclic IRI mapping for term '{}'", term),
+                ));
+            }
+            None => {}
+        }
+        defined.insert(term.to_string(), false);
+        let invalid = |message: String| JsonLdError::new(JsonLdErrorKind::InvalidContext, message);
+
+        let definition = definitions.get(term).unwrap_or(&JsonValue::Null);
+        let (id, type_mapping, container) = match definition.untagged() {
+            JsonValue::Null => (IdMapping::Null, None, None),
+            JsonValue::String(iri) => (IdMapping::Iri(iri), None, None),
+            JsonValue::Object(entries) => {
+                if let Some(key) = entries
+                    .keys()
+                    .find(|key| !matches!(key.as_str(), "@id" | "@type" | "@container"))
+                {
+                    return Err(unsupported_or_invalid(
+                        key,
+                        &format!("in the definition of '{}'", term),
+                    ));
+                }
+                let id = match entries.get("@id").map(JsonValue::untagged) {
+                    None => IdMapping::Implicit,
+                    Some(JsonValue::Null) => IdMapping::Null,
+                    Some(JsonValue::String(iri)) => IdMapping::Iri(iri),
+                    Some(other) => {
+                        return Err(invalid(format!(
+                            "@id of '{}' must be a string or null, found {}",
+                            term, other
+                        )));
+                    }
+                };
+                let container = match entries.get("@container").map(JsonValue::untagged) {
+                    None | Some(JsonValue::Null) => None,
+                    Some(JsonValue::String(c)) if c == "@list" => Some(Container::List),
+                    Some(JsonValue::String(c)) if c == "@set" => Some(Container::Set),
+                    Some(other) => {
+                        return Err(JsonLdError::new(
+                            JsonLdErrorKind::Unsupported,
+                            format!("@container {} of '{}' is not supported", other, term),
+                        ));
+                    }
+                };
+                (id, entries.get("@type"), container)
+            }
+            other => {
+                return Err(invalid(format!(
+                    "The definition of '{}' must be a string, an object or null, found {}",
+                    term, other
+                )));
+            }
+        };
+
+        let iri = match id {
+            IdMapping::Null => None,
+            IdMapping::Iri(value) => {
+                self.define_dependencies(definitions, value, defined)?;
+                match self.expand_iri(value, true) {
+                    Some(iri) if iri == "@context" => {
+                        return Err(invalid("@context cannot be aliased".to_string()));
+                    }
+                    Some(iri) if is_keyword(&iri) || iri.contains(':') => Some(iri),
+                    _ => {
+                        return Err(invalid(format!(
+                            "Invalid IRI mapping '{}' for term '{}'",
+                            value, term
+                        )));
+                    }
+                }
+            }
+            IdMapping::Implicit if term.contains(':') => {
+                self.define_dependencies(definitions, term, defined)?;
+                self.expand_iri(term, false)
+            }
+            IdMapping::Implicit => match &self.vocab {
+                Some(vocab) => Some(format!("{}{}", vocab, term)),
+                None => {
+                    return Err(invalid(format!(
+                        "Term '{}' has no @id and the context has no @vocab",
+                        term
+                    )));
+                }
+            },
+        };
+
+        let type_mapping = match type_mapping.map(JsonValue::untagged) {
+            None => None,
+            Some(JsonValue::String(t)) if t == "@id" || t == "@vocab" => Some(t.clone()),
+            Some(JsonValue::String(t)) => {
+                self.define_dependencies(definitions, t, defined)?;
+                match self.expand_iri(t, true) {
+                    Some(iri) if iri.contains(':') => Some(iri),
+                    _ => return Err(invalid(format!("Invalid @type '{}' of '{}'", t, term))),
+                }
+            }
+            Some(other) => {
+                return Err(invalid(format!(
+                    "@type of '{}' must be a string, found {}",
+                    term, other
+                )));
+            }
+        };
+
+        self.terms.insert(
+            term.to_string(),
+            Term {
+                iri,
+                type_mapping,
+                container,
+            },
+        );
+        defined.insert(term.to_string(), true);
+        Ok(())
+    }
+
+    /// Define the term or compact IRI prefix that `value` starts with, if
+    /// the local context defines it
+    fn define_dependencies(
+        &mut self,
+        definitions: &JsonObject,
+        value: &str,
+        defined: &mut HashMap<String, bool>,
+    ) -> Result<(), JsonLdError> {
+        let prefix = value.split_once(':').map_or(value, |(prefix, _)| prefix);
+        if !prefix.starts_with('@') && definitions.contains_key(prefix) {
+            self.define_term(definitions, prefix, defined)?;
+        }
+        Ok(())
+    }
+}
+
+fn unsupported_or_invalid(key: &str, place: &str) -> JsonLdError {
+    if is_keyword(key) {
+        JsonLdError::new(
+            JsonLdErrorKind::Unsupported,
+            format!("{} {} is not supported", key, place),
+        )
+    } else {
+        JsonLdError::new(
+            JsonLdErrorKind::InvalidContext,
+            format!("Unknown keyword {} {}", key, place),
+        )
+    }
+}
+
+/// Expand a document using the contexts it embeds
+///
+/// The result is always an array of node objects. Keys that do not expand
+/// to an IRI and `null` values are dropped, as JSON-LD prescribes.
+pub fn expand(document: &JsonValue) -> Result<JsonValue, JsonLdError> {
+    expand_with(document, &Context::new())
+}
+
+/// Expand with `context` as the initial context, for documents whose
+/// context is supplied separately
+pub fn expand_with(document: &JsonValue, context: &Context) -> Result<JsonValue, JsonLdError> {
+    let nodes = match expand_element(context, None, document)? {
+        None => Vec::new(),
+        Some(JsonValue::Array(nodes)) => nodes,
+        Some(JsonValue::Object(mut node)) if node.len() == 1 && node.contains_key("@graph") => {
+            match node.remove("@graph") {
+                Some(JsonValue::Array(nodes)) => nodes,
+                _ => Vec::new(),
+            }
+        }
+        Some(node) => vec![node],
+    };
+    Ok(JsonValue::Array(nodes))
+}
+
+fn expand_element(
+    context: &Context,
+    term: Option<&Term>,
+    element: &JsonValue,
+) -> Result<Option<JsonValue>, JsonLdError> {
+    match element.untagged() {
+        JsonValue::Null => Ok(None),
+        JsonValue::Array(items) => {
+            let mut expanded = Vec::with_capacity(items.len());
+            for item in items {
+                match expand_element(context, term, item)? {
+                    Some(JsonValue::Array(inner)) => expanded.extend(inner),
+                    Some(item) => expanded.push(item),
+                    None => {}
+                }
+            }
+            Ok(Some(JsonValue::Array(expanded)))
+        }
+        JsonValue::Object(object) => expand_object(context, term, object),
+        JsonValue::Extension(tag, payload) => {
+            let object: JsonObject = [(tag.clone(), (**payload).clone())].into_iter().collect();
+            expand_object(context, term, &object)
+        }
+        scalar => Ok(Some(expand_value(context, term, scalar))),
+    }
+}
+
+/// Value object for a scalar, applying the term's type coercion
+fn expand_value(context: &Context, term: Option<&Term>, value: &JsonValue) -> JsonValue {
+    let mut object = JsonObject::new();
+    match (term.and_then(|t| t.type_mapping.as_deref()), value.as_str()) {
+        (Some(coercion @ ("@id" | "@vocab")), Some(id)) => {
+            let iri = context.expand_iri(id, coercion == "@vocab");
+            object.insert(
+                "@id",
+                JsonValue::from(iri.unwrap_or_else(|| id.to_string())),
+            );
+        }
+        (Some("@id" | "@vocab"), None) | (None, _) => {
+            object.insert("@value", value.clone());
+        }
+        (Some(datatype), _) => {
+            object.insert("@value", value.clone());
+            object.insert("@type", JsonValue::from(datatype));
+        }
+    }
+    JsonValue::Object(object)
+}
+
+fn expand_object(
+    context: &Context,
+    term: Option<&Term>,
+    object: &JsonObject,
+) -> Result<Option<JsonValue>, JsonLdError> {
+    let context = match object.get("@context") {
+        Some(local) => Cow::Owned(context.with(local)?),
+        None => Cow::Borrowed(context),
+    };
+    let is_value = object
+        .keys()
+        .any(|key| context.expand_iri(key, true).as_deref() == Some("@value"));
+
+    let mut result = JsonObject::new();
+    for (key, value) in object.iter() {
+        if key == "@context" {
+            continue;
+        }
+        let Some(iri) = context.expand_iri(key, true) else {
+            continue;
+        };
+        if is_keyword(&iri) {
+            let expanded = expand_keyword(&context, term, &iri, value, is_value)?;
+            result.insert(iri, expanded);
+            continue;
+        }
+        if !iri.contains(':') {
+            continue;
+        }
+        let property = context.terms.get(key.as_str());
+        let mut items = match expand_element(&context, property, value)? {
+            None => continue,
+            Some(JsonValue::Array(items)) => items,
+            Some(item) => vec![item],
+        };
+        let is_list = |items: &[JsonValue]| matches!(items, [item] if item.get("@list").is_some());
+        if property.is_some_and(|p| p.container == Some(Container::List)) && !is_list(&items) {
+            let mut list = JsonObject::new();
+            list.insert("@list", JsonValue::Array(items));
+            items = vec![JsonValue::Object(list)];
+        }
+        match result.get_mut(&iri) {
+            Some(JsonValue::Array(existing)) => existing.extend(items),
+            _ => {
+                result.insert(iri, JsonValue::Array(items));
+            }
+        }
+    }
+
+    if is_value {
+        return Ok(result
+            .get("@value")
+            .is_some_and(|value| !value.is_null())
+            .then_some(JsonValue::Object(result)));
+    }
+    if let Some(set) = result.remove("@set") {
+        return Ok(Some(set));
+    }
+    Ok(Some(JsonValue::Object(result)))
+}
+
+fn expand_keyword(
+    context: &Context,
+    term: Option<&Term>,
+    keyword: &str,
+    value: &JsonValue,
+    is_value: bool,
+) -> Result<JsonValue, JsonLdError> {
+    let invalid = |expected: &str| {
+        JsonLdError::new(
+            JsonLdErrorKind::InvalidKeywordValue,
+            format!("{} must be {}, found {}", keyword, expected, value),
+        )
+    };
+    let expanded = match keyword {
+        "@id" => {
+            let id = value.as_str().ok_or_else(|| invalid("a string"))?;
+            JsonValue::from(
+                context
+                    .expand_iri(id, false)
+                    .unwrap_or_else(|| id.to_string()),
+            )
+        }
+        "@type" => {
+            let expand_type = |t: &JsonValue| {
+                let t = t
+                    .as_str()
+                    .ok_or_else(|| invalid("a string or an array of strings"))?;
+                Ok(JsonValue::from(
+                    context.expand_iri(t, true).unwrap_or_else(|| t.to_string()),
+                ))
+            };
+            match value.as_array() {
+                Some(types) if !is_value => {
+                    JsonValue::Array(types.iter().map(expand_type).collect::<Result<_, _>>()?)
+                }
+                _ if is_value => expand_type(value)?,
+                _ => JsonValue::Array(vec![expand_type(value)?]),
+            }
+        }
+        "@value" => {
+            if value.is_array() || value.is_object() {
+                return Err(invalid("a scalar or null"));
+            }
+            value.clone()
+        }
+        "@index" => value.clone(),
+        "@list" | "@set" | "@graph" => {
+            let term = if keyword == "@graph" { None } else { term };
+            match expand_element(context, term, value)? {
+                None => JsonValue::Array(Vec::new()),
+                Some(JsonValue::Array(items)) => JsonValue::Array(items),
+                Some(item) => JsonValue::Array(vec![item]),
+            }
+        }
+        other => return Err(unsupported_or_invalid(other, "in a document")),
+    };
+    Ok(expanded)
+}
+
+/// Compact a document of any form under `context`
+///
+/// The document is expanded first (using the contexts it embeds), then
+/// written with the shortest keys and values `context` allows. A single
+/// node becomes the result object; several nodes go into `@graph`. The
+/// result starts with `context` as its `@context`.
+pub fn compact(document: &JsonValue, context: &Context) -> Result<JsonValue, JsonLdError> {
+    let expanded = expand(document)?;
+    let mut nodes: Vec<JsonValue> = expanded
+        .as_array()
+        .map(|nodes| {
+            nodes
+                .iter()
+                .map(|node| compact_item(context, None, node))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let mut result = JsonObject::new();
+    if let Some(source) = context.to_value() {
+        result.insert("@context", source);
+    }
+    match nodes.len() {
+        0 => {}
+        1 => {
+            if let Some(JsonValue::Object(node)) = nodes.pop() {
+                result.extend(node);
+            }
+        }
+        _ => {
+            result.insert(context.compact_iri("@graph", true), JsonValue::Array(nodes));
+        }
+    }
+    Ok(JsonValue::Object(result))
+}
+
+/// The term to write a value of the property `iri` under, preferring terms
+/// whose type coercion and container fit the value; `None` if a compact or
+/// full IRI has to be used
+fn select_term<'c>(
+    context: &'c Context,
+    iri: &str,
+    item: &JsonValue,
+) -> Option<(&'c str, &'c Term)> {
+    let object = item.as_object();
+    let get = |key: &str| object.and_then(|object| object.get(key));
+    let len = object.map_or(0, |object| object.len());
+    let is_list = get("@list").is_some() && len == 1;
+    let is_reference = get("@id").is_some() && len == 1;
+    let value_type = get("@value").and(get("@type")).and_then(JsonValue::as_str);
+
+    context
+        .terms
+        .iter()
+        .filter(|(_, term)| term.iri.as_deref() == Some(iri))
+        .filter_map(|(name, term)| {
+            let fits_container = match term.container {
+                Some(Container::List) => is_list,
+                _ => !is_list || term.type_mapping.is_none(),
+            };
+            let fits_type = match term.type_mapping.as_deref() {
+                None => true,
+                Some("@id" | "@vocab") => is_reference,
+                Some(datatype) => value_type == Some(datatype) && len == 2,
+            };
+            let score = u8::from(term.type_mapping.is_some()) + u8::from(term.container.is_some());
+            (fits_container && fits_type).then_some((score, name.as_str(), term))
+        })
+        .min_by(|(a_score, a, _), (b_score, b, _)| {
+            b_score
+                .cmp(a_score)
+                .then(a.len().cmp(&b.len()))
+                .then(a.cmp(b))
+        })
+        .map(|(_, name, term)| (name, term))
+}
+
+fn compact_item(context: &Context, term: Option<&Term>, item: &JsonValue) -> JsonValue {
+    let Some(object) = item.as_object() else {
+        return item.clone();
+    };
+    let type_mapping = term.and_then(|t| t.type_mapping.as_deref());
+
+    if let Some(value) = object.get("@value") {
+        let value_type = object.get("@type").and_then(JsonValue::as_str);
+        if object.len() == 1 && type_mapping.is_none()
+            || object.len() == 2 && value_type.is_some() && value_type == type_mapping
+        {
+            return value.clone();
+        }
+        let mut compacted = JsonObject::new();
+        for (key, member) in object.iter() {
+            let member = match (key.as_str(), member.as_str()) {
+                ("@type", Some(t)) => JsonValue::from(context.compact_iri(t, true)),
+                _ => member.clone(),
+            };
+            compacted.insert(context.compact_iri(key, true), member);
+        }
+        return JsonValue::Object(compacted);
+    }
+
+    if object.len() == 1 {
+        if let (Some(id), Some(coercion @ ("@id" | "@vocab"))) =
+            (object.get("@id").and_then(JsonValue::as_str), type_mapping)
+        {
+            return JsonValue::from(context.compact_iri(id, coercion == "@vocab"));
+        }
+        if let Some(list) = object.get("@list").and_then(JsonValue::as_array) {
+            let items = list
+                .iter()
+                .map(|item| compact_item(context, term, item))
+                .collect();
+            if term.is_some_and(|t| t.container == Some(Container::List)) {
+                return JsonValue::Array(items);
+            }
+            let mut compacted = JsonObject::new();
+            compacted.insert(context.compact_iri("@list", true), JsonValue::Array(items));
+            return JsonValue::Object(compacted);
+        }
+    }
+
+    let mut compacted = JsonObject::new();
+    let mut properties: Vec<(String, bool, Vec<JsonValue>)> = Vec::new();
+    for (key, values) in object.iter() {
+        match (key.as_str(), values) {
+            ("@id", JsonValue::String(id)) => {
+                compacted.insert(
+                    context.compact_iri("@id", true),
+                    JsonValue::from(context.compact_iri(id, false)),
+                );
+            }
+            ("@type", JsonValue::Array(types)) => {
+                let mut types: Vec<JsonValue> = types
+                    .iter()
+                    .filter_map(JsonValue::as_str)
+                    .map(|t| JsonValue::from(context.compact_iri(t, true)))
+                    .collect();
+                let types = match types.len() {
+                    1 => types.remove(0),
+                    _ => JsonValue::Array(types),
+                };
+                compacted.insert(context.compact_iri("@type", true), types);
+            }
+            (keyword, JsonValue::Array(items)) if is_keyword(keyword) => {
+                let items = items
+                    .iter()
+                    .map(|item| compact_item(context, None, item))
+                    .collect();
+                compacted.insert(context.compact_iri(keyword, true), JsonValue::Array(items));
+            }
+            (keyword, value) if is_keyword(keyword) => {
+                compacted.insert(context.compact_iri(keyword, true), value.clone());
+            }
+            (iri, values) => {
+                let items = values
+                    .as_array()
+                    .map_or(std::slice::from_ref(values), ArrayView::as_slice);
+                for item in items {
+                    let (name, term) = match select_term(context, iri, item) {
+                        Some((name, term)) => (name.to_string(), Some(term)),
+                        None => (context.compact_iri(iri, true), None),
+                    };
+                    let keep_array = term.is_some_and(|t| t.container == Some(Container::Set));
+                    let item = compact_item(context, term, item);
+                    match properties.iter_mut().find(|(key, _, _)| *key == name) {
+                        Some((_, _, group)) => group.push(item),
+                        None => properties.push((name, keep_array, vec![item])),
+                    }
+                }
+            }
+        }
+    }
+    for (name, keep_array, mut items) in properties {
+        let value = if items.len() == 1 && !keep_array {
+            items.remove(0)
+        } else {
+            JsonValue::Array(items)
+        };
+        compacted.insert(name, value);
+    }
+    JsonValue::Object(compacted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json;
+
+    fn context() -> JsonValue {
+        json!({
+            "@vocab": "http://schema.org/",
+            "xsd": "http://www.w3.org/2001/XMLSchema#",
+            "id": "@id",
+            "type": "@type",
+            "knows": {"@type": "@id"},
+            "born": {"@id": "birthDate", "@type": "xsd:date"},
+            "tags": {"@id": "keywords", "@container": "@list"},
+            "colors": {"@container": "@set"},
+            "ignored": null
+        })
+    }
+
+    #[test]
+    fn test_expand() {
+        let document = json!({
+            "@context": context(),
+            "id": "http://example.com/ada",
+            "type": "Person",
+            "name": "Ada",
+            "born": "1815-12-10",
+            "knows": "http://example.com/charles",
+            "tags": ["math", "poetry"],
+            "colors": "green",
+            "ignored": 1,
+            "homepage": {"@context": {"url": {"@id": "@id"}}, "url": "https://ada.example"}
+        });
+        let expected = json!([{
+            "@id": "http://example.com/ada",
+            "@type": ["http://schema.org/Person"],
+            "http://schema.org/name": [{"@value": "Ada"}],
+            "http://schema.org/birthDate": [
+                {"@value": "1815-12-10", "@type": "http://www.w3.org/2001/XMLSchema#date"}
+            ],
+            "http://schema.org/knows": [{"@id": "http://example.com/charles"}],
+            "http://schema.org/keywords": [{"@list": [{"@value": "math"}, {"@value": "poetry"}]}],
+            "http://schema.org/colors": [{"@value": "green"}],
+            "http://schema.org/homepage": [{"@id": "https://ada.example"}]
+        }]);
+        assert_eq!(expand(&document).unwrap(), expected);
+        assert_eq!(expand(&expected).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_compact_round_trip() {
+        let document = json!({
+            "@context": context(),
+            "id": "http://example.com/ada",
+            "type": "Person",
+            "name": "Ada",
+            "born": "1815-12-10",
+            "knows": "http://example.com/charles",
+            "tags": ["math"],
+            "colors": ["green"]
+        });
+        let context = Context::parse(&context()).unwrap();
+        assert_eq!(compact(&document, &context).unwrap(), document);
+
+        let unknown =
+            json!({"http://example.com/ns#size": 3, "http://schema.org/knows": {"@id": "_:b0"}});
+        let compacted = compact(&unknown, &context).unwrap();
+        assert_eq!(compacted["http://example.com/ns#size"], json!(3));
+        assert_eq!(compacted["knows"], json!("_:b0"));
+
+        let graph = json!([{"@id": "_:a"}, {"@id": "_:b"}]);
+        let compacted = compact(&graph, &Context::new()).unwrap();
+        assert_eq!(
+            compacted,
+            json!({"@graph": [{"@id": "_:a"}, {"@id": "_:b"}]})
+        );
+    }
+
+    #[test]
+    fn test_context_errors() {
+        let kind = |context: JsonValue| Context::parse(&context).unwrap_err().kind();
+        assert_eq!(
+            kind(json!("https://schema.org/")),
+            JsonLdErrorKind::Unsupported
+        );
+        assert_eq!(
+            kind(json!({"@language": "en"})),
+            JsonLdErrorKind::Unsupported
+        );
+        assert_eq!(
+            kind(json!({"a": "b:x", "b": "a:y"})),
+            JsonLdErrorKind::InvalidContext
+        );
+        assert_eq!(
+            kind(json!({"name": {"@type": "@id"}})),
+            JsonLdErrorKind::InvalidContext
+        );
+        assert_eq!(kind(json!(5)), JsonLdErrorKind::InvalidContext);
+
+        let error = expand(&json!({"@id": 5})).unwrap_err();
+        assert_eq!(error.kind(), JsonLdErrorKind::InvalidKeywordValue);
+        assert_eq!(error.to_string(), "@id must be a string, found 5");
+    }
+}
@@ -0,0 +1,558 @@
+//! Key paths - `users[2].address.city` lookups and updates
+//!
+//! The syntax configuration files and tools like `jq` or lodash made
+//! familiar: keys separated by `.`, array indices in brackets, and keys with
+//! special characters quoted in brackets (`headers["content-type"]`). Unlike
+//! a JSON Pointer, a key path says whether a step is a key or an index, so
+//! [`JsonValue::set_path`] knows whether to create an object or an array.
+
+use std::fmt;
+
+use crate::json::JsonValue;
+use crate::object::JsonObject;
+use crate::pointer::JsonPointer;
+use crate::view::{AccessError, AccessLocation};
+
+/// One step of a [`KeyPath`]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Segment {
+    /// `.name` or `["name"]`
+    Key(String),
+    /// `[3]`
+    Index(usize),
+}
+
+/// A parsed key path such as `users[2].address.city`
+///
+/// # Examples
+///
+/// ```
+/// use json_parser_rs::keypath::{KeyPath, Segment};
+///
+/// let path = KeyPath::parse(r#"servers[0].headers["x.auth"]"#).unwrap();
+/// assert_eq!(path.segments().nth(1), Some(&Segment::Index(0)));
+/// assert_eq!(path.to_pointer().to_string(), "/servers/0/headers/x.auth");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct KeyPath {
+    /// Each segment with the byte offset where it starts, for errors
+    segments: Vec<(Segment, usize)>,
+}
+
+/// Why a key path is malformed, or cannot be set, and where
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyPathError {
+    kind: KeyPathErrorKind,
+    position: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum KeyPathErrorKind {
+    /// Nothing between two dots, or after the last one
+    EmptyKey,
+    /// `[` without a matching `]`, or a quoted key without its closing quote
+    UnclosedBracket,
+    /// Brackets holding neither a non-negative integer nor a quoted key
+    InvalidIndex,
+    /// A character that cannot follow `]`
+    UnexpectedCharacter,
+    /// `set_path` would need to look up a key in a value that is not an
+    /// object
+    NotAnObject,
+    /// `set_path` would need to index a value that is not an array
+    NotAnArray,
+}
+
+impl KeyPathError {
+    pub fn kind(&self) -> KeyPathErrorKind {
+        self.kind
+    }
+
+    /// Byte offset into the path text
+    pub fn position(&self) -> usize {
+        self.position
+    }
+}
+
+impl fmt::Display for KeyPathError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let problem = match self.kind {
+            KeyPathErrorKind::EmptyKey => "expected a key",
+            KeyPathErrorKind::UnclosedBracket => "unclosed '['",
+            KeyPathErrorKind::InvalidIndex => "expected an index or a quoted key in '[...]'",
+            KeyPathErrorKind::UnexpectedCharacter => "expected '.' or '[' after ']'",
+            KeyPathErrorKind::NotAnObject => {
+                return write!(
+                    f,
+                    "Cannot set key at position {}: parent is not an object",
+                    self.position
+                );
+            }
+            KeyPathErrorKind::NotAnArray => {
+                return write!(
+                    f,
+                    "Cannot set index at position {}: parent is not an array",
+                    self.position
+                );
+            }
+        };
+        write!(
+            f,
+            "Invalid key path at position {}: {}",
+            self.position, problem
+        )
+    }
+}
+
+impl std::error::Error for KeyPathError {}
+
+impl KeyPath {
+    /// Parse path text; the empty path refers to the whole document
+    pub fn parse(path: &str) -> Result<Self, KeyPathError> {
+        let error = |kind, position| KeyPathError { kind, position };
+        let bytes = path.as_bytes();
+        let mut segments = Vec::new();
+        let mut i = 0;
+        while i < bytes.len() {
+            if bytes[i] == b'[' {
+                let start = i;
+                let (segment, end) =
+                    parse_bracket(path, i + 1).map_err(|kind| error(kind, start))?;
+                segments.push((segment, start));
+                i = end;
+                match bytes.get(i) {
+                    None | Some(b'[') => {}
+                    Some(b'.') if i + 1 < bytes.len() => i += 1,
+                    Some(b'.') => return Err(error(KeyPathErrorKind::EmptyKey, i + 1)),
+                    Some(_) => return Err(error(KeyPathErrorKind::UnexpectedCharacter, i)),
+                }
+            } else {
+                let end = path[i..]
+                    .find(['.', '['])
+                    .map_or(path.len(), |length| i + length);
+                if end == i {
+                    return Err(error(KeyPathErrorKind::EmptyKey, i));
+                }
+                segments.push((Segment::Key(path[i..end].to_string()), i));
+                i = end;
+                if bytes.get(i) == Some(&b'.') {
+                    i += 1;
+                    if i == bytes.len() {
+                        return Err(error(KeyPathErrorKind::EmptyKey, i));
+                    }
+                }
+            }
+        }
+        Ok(Self { segments })
+    }
+
+    pub fn segments(&self) -> impl Iterator<Item = &Segment> {
+        self.segments.iter().map(|(segment, _)| segment)
+    }
+
+    /// The equivalent JSON Pointer
+    pub fn to_pointer(&self) -> JsonPointer {
+        JsonPointer::from_tokens(self.segments().map(|segment| match segment {
+            Segment::Key(key) => key.clone(),
+            Segment::Index(index) => index.to_string(),
+        }))
+    }
+}
+
+/// The segment inside brackets starting at `start`, and the offset after
+/// the closing `]`
+fn parse_bracket(path: &str, start: usize) -> Result<(Segment, usize), KeyPathErrorKind> {
+    let rest = &path[start..];
+    match rest.chars().next() {
+        Some(quote @ ('"' | '\'')) => {
+            let mut key = String::new();
+            let mut chars = rest.char_indices().skip(1);
+            while let Some((i, ch)) = chars.next() {
+                match ch {
+                    '\\' => match chars.next() {
+                        Some((_, escaped)) => key.push(escaped),
+                        None => break,
+                    },
+                    _ if ch == quote => {
+                        return match rest[i + 1..].starts_with(']') {
+                            true => Ok((Segment::Key(key), start + i + 2)),
+                            false => Err(KeyPathErrorKind::UnclosedBracket),
+                        };
+                    }
+                    _ => key.push(ch),
+                }
+            }
+            Err(KeyPathErrorKind::UnclosedBracket)
+        }
+        _ => {
+            let length = rest.find(']').ok_or(KeyPathErrorKind::UnclosedBracket)?;
+            let digits = &rest[..length];
+            if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+                return Err(KeyPathErrorKind::InvalidIndex);
+            }
+            let index = digits.parse().map_err(|_| KeyPathErrorKind::InvalidIndex)?;
+            Ok((Segment::Index(index), start + length + 1))
+        }
+    }
+}
+
+impl fmt::Display for KeyPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, segment) in self.segments().enumerate() {
+            match segment {
+                Segment::Index(index) => write!(f, "[{}]", index)?,
+                Segment::Key(key) if is_plain_key(key) => {
+                    if i > 0 {
+                        f.write_str(".")?;
+                    }
+                    f.write_str(key)?;
+                }
+                Segment::Key(key) => {
+                    let escaped = key.replace('\\', "\\\\").replace('"', "\\\"");
+                    write!(f, "[\"{}\"]", escaped)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Key path text of the first `end` segments
+fn prefix(segments: &[(Segment, usize)], end: usize) -> String {
+    let path = KeyPath {
+        segments: segments[..end].to_vec(),
+    };
+    path.to_string()
+}
+
+fn location(segment: &Segment) -> AccessLocation {
+    match segment {
+        Segment::Key(key) => AccessLocation::Key(key.clone()),
+        Segment::Index(index) => AccessLocation::Index(*index),
+    }
+}
+
+/// Error for `current`, reached by the first `i` segments, not being the
+/// object or array that segment `i` needs: a wrong type at the previous
+/// segment, or a missing key or index if `current` is the document itself
+fn wrong_container(segments: &[(Segment, usize)], i: usize, current: &JsonValue) -> AccessError {
+    let segment = &segments[i].0;
+    let Some(parent) = i.checked_sub(1) else {
+        return AccessError::missing(location(segment));
+    };
+    let expected = match segment {
+        Segment::Key(_) => "object",
+        Segment::Index(_) => "array",
+    };
+    AccessError::wrong_type(location(&segments[parent].0), expected, current)
+        .within(prefix(segments, parent))
+}
+
+fn is_plain_key(key: &str) -> bool {
+    !key.is_empty() && !key.contains(['.', '[', ']', '"', '\''])
+}
+
+impl std::str::FromStr for KeyPath {
+    type Err = KeyPathError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+impl JsonValue {
+    /// The value at a key path such as `users[2].address.city`
+    ///
+    /// A key segment also selects an array element when it is a number
+    /// (`users.2`), as in a JSON Pointer. Returns `None` if the path is
+    /// malformed or does not resolve.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use json_parser_rs::json;
+    ///
+    /// let config = json!({"users": [{"address": {"city": "Berlin"}}]});
+    /// assert_eq!(config.get_path("users[0].address.city"), Some(&json!("Berlin")));
+    /// assert_eq!(config.get_path("users[1].address"), None);
+    /// ```
+    pub fn get_path(&self, path: &str) -> Option<&JsonValue> {
+        let mut current = self;
+        for segment in KeyPath::parse(path).ok()?.segments() {
+            current = match (current.untagged(), segment) {
+                (JsonValue::Object(object), Segment::Key(key)) => object.get(key)?,
+                (JsonValue::Array(elements), Segment::Index(index)) => elements.get(*index)?,
+                (JsonValue::Array(elements), Segment::Key(key)) => {
+                    elements.get(crate::pointer::parse_index(key)?)?
+                }
+                _ => return None,
+            };
+        }
+        Some(current)
+    }
+
+    /// The value at a key path, or an error saying which step failed
+    ///
+    /// Resolves like [`JsonValue::get_path`]. When a key is missing, the
+    /// error holds the path of the object it was looked up in, the keys
+    /// that object has and the ones close to the missing key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use json_parser_rs::json;
+    ///
+    /// let config = json!({"servers": [{"host": "a", "port": 80}]});
+    /// assert_eq!(config.require_path("servers[0].port"), Ok(&json!(80)));
+    ///
+    /// let error = config.require_path("servers[0].hots").unwrap_err();
+    /// assert_eq!(error.to_string(), r#"Missing key "hots" in servers[0] (did you mean "host"?)"#);
+    /// ```
+    pub fn require_path(&self, path: &str) -> Result<&JsonValue, AccessError> {
+        let path = KeyPath::parse(path).map_err(|e| AccessError::invalid_path(path, e))?;
+        let segments = &path.segments;
+        let mut current = self;
+        for (i, (segment, _)) in segments.iter().enumerate() {
+            let missing = |location| AccessError::missing(location).within(prefix(segments, i));
+            let next = match (current.untagged(), segment) {
+                (JsonValue::Object(object), Segment::Key(key)) => object
+                    .get(key)
+                    .ok_or_else(|| missing(location(segment)).with_keys(object)),
+                (JsonValue::Array(elements), Segment::Index(index)) => elements
+                    .get(*index)
+                    .ok_or_else(|| missing(location(segment))),
+                (JsonValue::Array(elements), Segment::Key(key)) => {
+                    match crate::pointer::parse_index(key) {
+                        Some(index) => elements
+                            .get(index)
+                            .ok_or_else(|| missing(AccessLocation::Index(index))),
+                        None => Err(wrong_container(segments, i, current)),
+                    }
+                }
+                _ => Err(wrong_container(segments, i, current)),
+            };
+            current = next?;
+        }
+        Ok(current)
+    }
+
+    /// Mutable variant of [`JsonValue::get_path`]
+    pub fn get_path_mut(&mut self, path: &str) -> Option<&mut JsonValue> {
+        let mut current = self;
+        for segment in KeyPath::parse(path).ok()?.segments() {
+            current = match (current.untagged_mut(), segment) {
+                (JsonValue::Object(object), Segment::Key(key)) => object.get_mut(key)?,
+                (JsonValue::Array(elements), Segment::Index(index)) => elements.get_mut(*index)?,
+                (JsonValue::Array(elements), Segment::Key(key)) => {
+                    elements.get_mut(crate::pointer::parse_index(key)?)?
+                }
+                _ => return None,
+            };
+        }
+        Some(current)
+    }
+
+    /// Set the value at a key path, returning the value it replaced, if any
+    ///
+    /// Missing steps are created along the way: an object for a key, an
+    /// array for an index, and `null` for `Null` values met on the way.
+    /// Indices past the end of an array pad it with `null`s. Stepping into
+    /// any other scalar is an error, and so is indexing an object or using a
+    /// non-numeric key on an array; the document is left unchanged then.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use json_parser_rs::{JsonValue, json};
+    ///
+    /// let mut config = JsonValue::Null;
+    /// config.set_path("server.ports[1]", json!(8443)).unwrap();
+    /// config.set_path("server.name", json!("edge")).unwrap();
+    /// assert_eq!(config, json!({"server": {"ports": [null, 8443], "name": "edge"}}));
+    ///
+    /// let error = config.set_path("server.name.first", json!("x")).unwrap_err();
+    /// assert_eq!(error.to_string(), "Cannot set key at position 12: parent is not an object");
+    /// ```
+    pub fn set_path(
+        &mut self,
+        path: &str,
+        value: JsonValue,
+    ) -> Result<Option<JsonValue>, KeyPathError> {
+        let path = KeyPath::parse(path)?;
+        let existed = self.check_settable(&path)?;
+        let mut current = self;
+        for (segment, _) in &path.segments {
+            if current.is_null() {
+                *current.untagged_mut() = match segment {
+                    Segment::Key(_) => JsonValue::Object(JsonObject::new()),
+                    Segment::Index(_) => JsonValue::Array(Vec::new()),
+                };
+            }
+            current = match (current.untagged_mut(), segment) {
+                (JsonValue::Object(object), Segment::Key(key)) => {
+                    object.entry(key.as_str()).or_default()
+                }
+                (JsonValue::Array(elements), segment) => {
+                    let index = match segment {
+                        Segment::Index(index) => *index,
+                        Segment::Key(key) => crate::pointer::parse_index(key).unwrap_or_default(),
+                    };
+                    if index >= elements.len() {
+                        elements.resize(index + 1, JsonValue::Null);
+                    }
+                    &mut elements[index]
+                }
+                _ => unreachable!("checked by check_settable"),
+            };
+        }
+        let previous = std::mem::replace(current, value);
+        Ok(existed.then_some(previous))
+    }
+
+    /// Check that `set_path` can follow `path` without changing a
+    /// non-`null` scalar, so that a failed update leaves the value intact;
+    /// `true` if the whole path already exists
+    fn check_settable(&self, path: &KeyPath) -> Result<bool, KeyPathError> {
+        let error = |kind, position| KeyPathError { kind, position };
+        let mut current = Some(self);
+        for (segment, position) in &path.segments {
+            let Some(value) = current.filter(|value| !value.is_null()) else {
+                return Ok(false);
+            };
+            current = match (value.untagged(), segment) {
+                (JsonValue::Object(object), Segment::Key(key)) => object.get(key),
+                (JsonValue::Array(elements), Segment::Index(index)) => elements.get(*index),
+                (JsonValue::Array(elements), Segment::Key(key)) => {
+                    let index = crate::pointer::parse_index(key)
+                        .ok_or(error(KeyPathErrorKind::NotAnObject, *position))?;
+                    elements.get(index)
+                }
+                (_, Segment::Key(_)) => {
+                    return Err(error(KeyPathErrorKind::NotAnObject, *position));
+                }
+                (_, Segment::Index(_)) => {
+                    return Err(error(KeyPathErrorKind::NotAnArray, *position));
+                }
+            };
+        }
+        Ok(current.is_some())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json;
+
+    #[test]
+    fn test_parse_and_display() {
+        let path = KeyPath::parse(r#"a[0]["b.c"]['d\'e'][12].f"#).unwrap();
+        let segments: Vec<&Segment> = path.segments().collect();
+        assert_eq!(
+            segments,
+            [
+                &Segment::Key("a".to_string()),
+                &Segment::Index(0),
+                &Segment::Key("b.c".to_string()),
+                &Segment::Key("d'e".to_string()),
+                &Segment::Index(12),
+                &Segment::Key("f".to_string()),
+            ]
+        );
+        assert_eq!(path.to_string(), r#"a[0]["b.c"]["d'e"][12].f"#);
+        assert_eq!(KeyPath::parse("").unwrap().segments().count(), 0);
+        assert_eq!(KeyPath::parse("[1]").unwrap().to_string(), "[1]");
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let cases = [
+            ("a..b", KeyPathErrorKind::EmptyKey, 2),
+            ("a.", KeyPathErrorKind::EmptyKey, 2),
+            ("a[0].", KeyPathErrorKind::EmptyKey, 5),
+            ("a[0", KeyPathErrorKind::UnclosedBracket, 1),
+            ("a[\"b]", KeyPathErrorKind::UnclosedBracket, 1),
+            ("a[-1]", KeyPathErrorKind::InvalidIndex, 1),
+            ("a[]", KeyPathErrorKind::InvalidIndex, 1),
+            ("a[0]b", KeyPathErrorKind::UnexpectedCharacter, 4),
+        ];
+        for (path, kind, position) in cases {
+            let error = KeyPath::parse(path).unwrap_err();
+            assert_eq!(
+                (error.kind(), error.position()),
+                (kind, position),
+                "{}",
+                path
+            );
+        }
+    }
+
+    #[test]
+    fn test_set_path() {
+        let mut value = json!({"list": [1], "s": "x"});
+        assert_eq!(value.set_path("list[0]", json!(2)), Ok(Some(json!(1))));
+        assert_eq!(value.set_path("list.2", json!(3)), Ok(None));
+        assert_eq!(value.set_path("new[0].k", json!(true)), Ok(None));
+        assert_eq!(
+            value,
+            json!({"list": [2, null, 3], "s": "x", "new": [{"k": true}]})
+        );
+
+        let before = value.clone();
+        let error = value.set_path("list.x", json!(0)).unwrap_err();
+        assert_eq!(
+            (error.kind(), error.position()),
+            (KeyPathErrorKind::NotAnObject, 5)
+        );
+        let error = value.set_path("s[0]", json!(0)).unwrap_err();
+        assert_eq!(
+            (error.kind(), error.position()),
+            (KeyPathErrorKind::NotAnArray, 1)
+        );
+        assert_eq!(value, before);
+
+        *value.get_path_mut("new[0].k").unwrap() = json!(false);
+        assert_eq!(value.get_path("new.0.k"), Some(&json!(false)));
+    }
+
+    #[test]
+    fn test_require_path_errors() {
+        let value = json!({"servers": [{"host": "a", "port": 80}], "name": "edge"});
+        assert_eq!(value.require_path("servers.0.host"), Ok(&json!("a")));
+
+        let error = value.require_path("servers[0].prot").unwrap_err();
+        assert_eq!(error.path, "servers[0]");
+        assert_eq!(error.suggestions(), ["port"]);
+        assert_eq!(error.available_keys, ["host", "port"]);
+        assert_eq!(
+            error.to_string(),
+            r#"Missing key "prot" in servers[0] (did you mean "port"?)"#
+        );
+        assert_eq!(
+            value.require_path("severs").unwrap_err().to_string(),
+            r#"Missing key "severs" (did you mean "servers"?)"#
+        );
+
+        let cases = [
+            ("servers[3]", "Index 3 out of bounds in servers"),
+            (
+                "servers.x",
+                r#"Key "servers": expected object, found array"#,
+            ),
+            ("name[0]", r#"Key "name": expected array, found string"#),
+            (
+                "servers[0].port.n",
+                r#"Key "port" in servers[0]: expected object, found integer"#,
+            ),
+            ("a..b", "Invalid key path at position 2: expected a key"),
+        ];
+        for (path, message) in cases {
+            assert_eq!(value.require_path(path).unwrap_err().to_string(), message);
+        }
+        assert_eq!(
+            json!([]).require_path("a").unwrap_err().to_string(),
+            r#"Missing key "a""#
+        );
+    }
+}
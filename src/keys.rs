@@ -0,0 +1,222 @@
+//! Key checks - reject unexpected keys in configuration objects
+//!
+//! A misspelled option such as `"timout": 30` is valid JSON and would
+//! otherwise be silently ignored. [`JsonValue::check_only_keys`] checks one
+//! object against the keys it may hold; [`AllowedKeys`] does the same for
+//! every object in a document, with the allowed keys given per location.
+//! Each unexpected key is reported with its path and the allowed keys close
+//! to it. Documents described by a JSON Schema get the same check from
+//! `"additionalProperties": false`, see [`crate::schema`].
+
+use std::fmt;
+
+use crate::json::JsonValue;
+use crate::pointer::{JsonPointer, pattern_matches};
+use crate::suggest::suggest;
+
+/// A key that is not allowed where it occurs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownKey {
+    /// Location of the member with the key
+    pub path: JsonPointer,
+    pub key: String,
+    /// Allowed keys close to `key`, closest first
+    pub suggestions: Vec<String>,
+}
+
+/// `/path: Unknown key "key"`, with `(did you mean ...?)` when an allowed
+/// key is close
+impl fmt::Display for UnknownKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: Unknown key {:?}", self.path, self.key)?;
+        if !self.suggestions.is_empty() {
+            let keys: Vec<_> = self
+                .suggestions
+                .iter()
+                .map(|k| format!("{:?}", k))
+                .collect();
+            write!(f, " (did you mean {}?)", keys.join(" or "))?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for UnknownKey {}
+
+/// Allowed keys keyed by JSON Pointer patterns (`*` matches any single key
+/// or index)
+///
+/// Objects at locations without a rule are not checked, so free-form parts
+/// of a document (maps of user-chosen names) simply get no rule.
+///
+/// # Examples
+///
+/// ```
+/// use json_parser_rs::json;
+/// use json_parser_rs::keys::AllowedKeys;
+///
+/// let keys = AllowedKeys::new()
+///     .allow("", &["server", "users"])
+///     .allow("/server", &["host", "port"])
+///     .allow("/users/*", &["name", "admin"]);
+///
+/// let config = json!({
+///     "server": {"host": "localhost", "prot": 8080},
+///     "users": [{"name": "ada", "admin": true}, {"nmae": "bob"}]
+/// });
+/// let errors = keys.check(&config).unwrap_err();
+/// let lines: Vec<String> = errors.iter().map(ToString::to_string).collect();
+/// assert_eq!(lines, [
+///     "/server/prot: Unknown key \"prot\" (did you mean \"port\"?)",
+///     "/users/1/nmae: Unknown key \"nmae\" (did you mean \"name\"?)",
+/// ]);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct AllowedKeys {
+    rules: Vec<(String, Vec<String>)>,
+}
+
+impl AllowedKeys {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Objects at locations matching `pattern` may only hold `keys`; with
+    /// several matching rules, a key allowed by any of them is allowed
+    pub fn allow(mut self, pattern: impl Into<String>, keys: &[&str]) -> Self {
+        let keys = keys.iter().map(|key| key.to_string()).collect();
+        self.rules.push((pattern.into(), keys));
+        self
+    }
+
+    /// Every unexpected key in `value`, in document order
+    pub fn check(&self, value: &JsonValue) -> Result<(), Vec<UnknownKey>> {
+        let mut errors = Vec::new();
+        self.check_at(value, &mut Vec::new(), &mut errors);
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    fn check_at(&self, value: &JsonValue, path: &mut Vec<String>, errors: &mut Vec<UnknownKey>) {
+        match value.untagged() {
+            JsonValue::Object(object) => {
+                let mut allowed: Option<Vec<&str>> = None;
+                for (pattern, keys) in &self.rules {
+                    if pattern_matches(pattern, path) {
+                        let all = allowed.get_or_insert_with(Vec::new);
+                        all.extend(keys.iter().map(String::as_str));
+                    }
+                }
+                for (key, member) in object.iter() {
+                    path.push(key.clone());
+                    if let Some(allowed) = &allowed
+                        && !allowed.contains(&key.as_str())
+                    {
+                        errors.push(unknown_key(path, key, allowed));
+                    }
+                    self.check_at(member, path, errors);
+                    path.pop();
+                }
+            }
+            JsonValue::Array(elements) => {
+                for (index, element) in elements.iter().enumerate() {
+                    path.push(index.to_string());
+                    self.check_at(element, path, errors);
+                    path.pop();
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn unknown_key(path: &[String], key: &str, allowed: &[&str]) -> UnknownKey {
+    UnknownKey {
+        path: JsonPointer::from_tokens(path),
+        key: key.to_string(),
+        suggestions: suggest(key, allowed.iter().copied())
+            .into_iter()
+            .map(str::to_string)
+            .collect(),
+    }
+}
+
+impl JsonValue {
+    /// Check that this object holds no keys besides `allowed`
+    ///
+    /// Nested objects are not checked; use [`AllowedKeys`] for those. Values
+    /// other than objects pass.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use json_parser_rs::json;
+    ///
+    /// let options = json!({"timout": 30, "retries": 3});
+    /// let errors = options.check_only_keys(&["timeout", "retries"]).unwrap_err();
+    /// assert_eq!(errors[0].key, "timout");
+    /// assert_eq!(errors[0].suggestions, ["timeout"]);
+    /// ```
+    pub fn check_only_keys(&self, allowed: &[&str]) -> Result<(), Vec<UnknownKey>> {
+        let JsonValue::Object(object) = self.untagged() else {
+            return Ok(());
+        };
+        let errors: Vec<UnknownKey> = object
+            .keys()
+            .filter(|key| !allowed.contains(&key.as_str()))
+            .map(|key| unknown_key(std::slice::from_ref(key), key, allowed))
+            .collect();
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json;
+
+    #[test]
+    fn test_check_only_keys() {
+        let value = json!({"host": "x", "hots": "y", "verbose": true, "nested": {"any": 1}});
+        let errors = value
+            .check_only_keys(&["host", "port", "nested"])
+            .unwrap_err();
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[0].path.to_string(), "/hots");
+        assert_eq!(errors[0].suggestions, ["host"]);
+        assert_eq!(errors[1].to_string(), "/verbose: Unknown key \"verbose\"");
+
+        assert!(
+            value
+                .check_only_keys(&["host", "hots", "verbose", "nested"])
+                .is_ok()
+        );
+        assert!(json!([{"x": 1}]).check_only_keys(&[]).is_ok());
+    }
+
+    #[test]
+    fn test_allowed_keys_rules() {
+        let keys = AllowedKeys::new()
+            .allow("", &["a"])
+            .allow("/a/*", &["id"])
+            .allow("/a/*", &["name", "tags"])
+            .allow("/a/*/tags", &[]);
+        let value = json!({"a": {"x": {"id": 1, "name": "n", "tags": {"k/ey": 1}}, "y": {}}});
+        let errors = keys.check(&value).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].path.to_string(), "/a/x/tags/k~1ey");
+        assert_eq!(errors[0].key, "k/ey");
+
+        // No rule for the root: its keys are not checked
+        let nested_only = AllowedKeys::new().allow("/a", &[]);
+        assert!(nested_only.check(&json!({"b": {"c": 1}})).is_ok());
+        assert!(nested_only.check(&json!({"a": {"c": 1}})).is_err());
+    }
+}
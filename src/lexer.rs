@@ -5,10 +5,21 @@
 /// 2. Easier to test and debug
 /// 3. Can reuse lexer for different parsers
 /// 4. Better error messages - know exactly which token caused the issue
+use std::borrow::Cow;
+use std::fmt;
 
-use crate::error::ParseError;
+use crate::cst::{Span, Trivia, TriviaKind};
+use crate::error::{ErrorKind, ParseError};
+use crate::json::{check_escapes, escape, unescape_content};
+use crate::number::JsonNumber;
+use crate::options::ParserOptions;
+
+/// U+FEFF at the start of the input, as Windows tools write it; skipped
+/// like whitespace (RFC 8259 section 8.1 allows ignoring it)
+pub(crate) const BYTE_ORDER_MARK: char = '\u{feff}';
 
 #[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
 pub enum Token {
     // Structural tokens
     LeftBrace,    // {
@@ -20,14 +31,203 @@ pub enum Token {
     
     // Value tokens
     String(String),
-    Number(f64),
+    // String with unpaired surrogates, as escaped source text; only produced
+    // when lone surrogates are preserved
+    RawString(String),
+    Number(JsonNumber),
     Boolean(bool),
     Null,
+
+    // Bare word that is not a keyword; only produced when unquoted keys are
+    // allowed
+    Identifier(String),
     
     // End of input
     Eof,
 }
 
+/// How error messages name a token: `':'`, `number 1`, `string "a"`,
+/// `end of input`
+///
+/// Unlike the `Debug` form, this wording is part of the error messages and
+/// does not follow changes to the enum.
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::LeftBrace => f.write_str("'{'"),
+            Token::RightBrace => f.write_str("'}'"),
+            Token::LeftBracket => f.write_str("'['"),
+            Token::RightBracket => f.write_str("']'"),
+            Token::Comma => f.write_str("','"),
+            Token::Colon => f.write_str("':'"),
+            Token::String(s) => write!(f, "string \"{}\"", escape(s)),
+            // Already escaped
+            Token::RawString(s) => write!(f, "string \"{}\"", s),
+            Token::Number(n) => write!(f, "number {}", n),
+            Token::Boolean(b) => write!(f, "'{}'", b),
+            Token::Null => f.write_str("'null'"),
+            Token::Identifier(name) => write!(f, "identifier '{}'", name),
+            Token::Eof => f.write_str("end of input"),
+        }
+    }
+}
+
+/// A token with its location in the input
+///
+/// `start..end` is the byte range of the token's text (for `Eof`, the empty
+/// range at the end of the input); `line` and `column` are 1-based and
+/// count like `ParseError`'s, with the column in bytes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpannedToken {
+    pub token: Token,
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+/// Iterator over the spanned tokens of a lexer, see [`Lexer::spanned`]
+pub struct SpannedTokens<'a> {
+    lexer: Lexer<'a>,
+    done: bool,
+}
+
+impl Iterator for SpannedTokens<'_> {
+    type Item = Result<SpannedToken, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let result = self.lexer.next_spanned_token();
+        match &result {
+            Ok(spanned) if spanned.token == Token::Eof => {
+                self.done = true;
+                return None;
+            }
+            Err(_) => self.done = true,
+            Ok(_) => {}
+        }
+        Some(result)
+    }
+}
+
+/// Tokens up to (not including) `Eof`; iteration ends after the first
+/// error, since the lexer cannot know where the broken token ends
+///
+/// # Examples
+///
+/// ```
+/// use json_parser_rs::lexer::{Lexer, Token};
+///
+/// let tokens: Result<Vec<Token>, _> = Lexer::new("[true]").collect();
+/// assert_eq!(tokens.unwrap(), [Token::LeftBracket, Token::Boolean(true), Token::RightBracket]);
+/// ```
+impl Iterator for Lexer<'_> {
+    type Item = Result<Token, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+        match self.next_token() {
+            Ok(Token::Eof) => {
+                self.finished = true;
+                None
+            }
+            Err(error) => {
+                self.finished = true;
+                Some(Err(error))
+            }
+            token => Some(token),
+        }
+    }
+}
+
+/// Token whose string payload may borrow from the input, as produced by
+/// `Lexer::next_token_ref`
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum TokenRef<'a> {
+    /// Quoted string; borrowed unless it contained escape sequences
+    Str(Cow<'a, str>),
+    /// Any other token
+    Token(Token),
+}
+
+impl fmt::Display for TokenRef<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TokenRef::Str(s) => write!(f, "string \"{}\"", escape(s)),
+            TokenRef::Token(token) => token.fmt(f),
+        }
+    }
+}
+
+/// A token as passed to a [`TokenSink`], with its text borrowed from the
+/// input
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[non_exhaustive]
+pub enum SliceToken<'a> {
+    LeftBrace,
+    RightBrace,
+    LeftBracket,
+    RightBracket,
+    Comma,
+    Colon,
+    String(StrSlice<'a>),
+    /// The literal as written, already checked against the grammar
+    Number(&'a str),
+    Boolean(bool),
+    Null,
+    /// Bare word that is not a keyword; only produced when unquoted keys
+    /// are allowed
+    Identifier(&'a str),
+}
+
+/// A quoted string as it appears in the input
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StrSlice<'a> {
+    /// The text between the quotes, escapes and all
+    pub content: &'a str,
+    /// Whether `content` holds escape sequences, i.e. differs from the
+    /// string's value
+    pub needs_unescape: bool,
+    quote: char,
+    /// Length of the value in bytes, known from checking the escapes
+    len: usize,
+}
+
+impl<'a> StrSlice<'a> {
+    /// The string's value; borrowed unless it needs unescaping
+    ///
+    /// Unpaired surrogates, which only pass the lexer when they are
+    /// preserved, become U+FFFD.
+    pub fn unescape(&self) -> Cow<'a, str> {
+        match unescape_content(self.content, self.quote, true) {
+            Ok((string, _)) => string,
+            Err(_) => unreachable!("escapes are checked when the token is read"),
+        }
+    }
+
+    /// Length of the string's value in bytes, without unescaping it
+    pub(crate) fn value_len(&self) -> usize {
+        self.len
+    }
+}
+
+/// Receiver of the tokens of [`Lexer::tokenize_into`]
+///
+/// Implemented for closures taking the token and its byte range.
+pub trait TokenSink<'a> {
+    fn token(&mut self, token: SliceToken<'a>, span: Span);
+}
+
+impl<'a, F: FnMut(SliceToken<'a>, Span)> TokenSink<'a> for F {
+    fn token(&mut self, token: SliceToken<'a>, span: Span) {
+        self(token, span)
+    }
+}
+
 /// Lexer that converts input string into tokens
 /// 
 /// Uses iterator pattern - professional Rust developers prefer iterators
@@ -35,23 +235,98 @@ pub enum Token {
 /// 1. Lazy - only process what you need
 /// 2. Composable - can chain operations
 /// 3. Memory efficient - no need to store all tokens at once
+///
+/// The public `input` and `position` fields are deprecated in favor of the
+/// [`Lexer::input`] and [`Lexer::position`] accessors. They remain readable
+/// for this release so existing code keeps compiling, and will become
+/// private in the next one.
 pub struct Lexer<'a> {
+    #[deprecated(note = "use `Lexer::input()`; the field will become private")]
     pub input: &'a str,
+    /// Writing it leaves the lexer's current character stale
+    #[deprecated(note = "use `Lexer::position()`; the field will become private")]
     pub position: usize,
+    /// Byte offset where the most recently read token starts
+    pub(crate) token_start: usize,
     current_char: Option<char>,
+    allow_comments: bool,
+    allow_single_quotes: bool,
+    allow_unquoted_keys: bool,
+    allow_lenient_numbers: bool,
+    max_string_length: Option<usize>,
+    reject_control_characters: bool,
+    preserve_lone_surrogates: bool,
+    /// Whether the input starts the document, so a leading byte order mark
+    /// is skipped
+    at_document_start: bool,
+    /// Line counting state for spanned tokens: lines have been counted up
+    /// to `line_offset`, which lies on line `line` starting at `line_start`
+    line_offset: usize,
+    line: usize,
+    line_start: usize,
+    /// Token read ahead by `peek`, with its start offset
+    peeked: Option<(Result<Token, ParseError>, usize)>,
+    /// Set once iteration has returned `None`
+    finished: bool,
 }
 
+// The lexer's state lives in the deprecated public fields until they
+// become private
+#[allow(deprecated)]
 impl<'a> Lexer<'a> {
     pub fn new(input: &'a str) -> Self {
+        Self::with_options(input, &ParserOptions::default())
+    }
+
+    /// Lexer honoring the token-level settings of `options` (comments,
+    /// single quotes, identifiers, string limits)
+    pub fn with_options(input: &'a str, options: &ParserOptions) -> Self {
         let mut lexer = Self {
             input,
             position: 0,
+            token_start: 0,
             current_char: None,
+            allow_comments: options.allow_comments,
+            allow_single_quotes: options.allow_single_quotes,
+            allow_unquoted_keys: options.allow_unquoted_keys,
+            allow_lenient_numbers: options.allow_lenient_numbers,
+            max_string_length: options.max_string_length,
+            reject_control_characters: options.reject_control_characters,
+            preserve_lone_surrogates: options.preserve_lone_surrogates,
+            at_document_start: true,
+            line_offset: 0,
+            line: 1,
+            line_start: 0,
+            peeked: None,
+            finished: false,
         };
         lexer.advance();
         lexer
     }
 
+    /// Lexer for text cut from the middle of a larger input, where a byte
+    /// order mark is an unexpected character like any other
+    pub(crate) fn fragment(input: &'a str, options: &ParserOptions) -> Self {
+        let mut lexer = Self::with_options(input, options);
+        lexer.at_document_start = false;
+        lexer
+    }
+
+    /// The complete input being tokenized
+    pub fn input(&self) -> &'a str {
+        self.input
+    }
+
+    /// Byte offset just past the current character
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// Byte offset where the most recently read token starts
+    pub fn token_start(&self) -> usize {
+        self.token_start
+    }
+
     fn advance(&mut self) {
         if self.position < self.input.len() {
             self.current_char = self.input[self.position..].chars().next();
@@ -61,21 +336,150 @@ impl<'a> Lexer<'a> {
         }
     }
 
-    fn skip_whitespace(&mut self) {
+    /// Like `next_token`, but records errors in `errors` and carries on:
+    /// invalid characters are skipped and malformed literals are replaced by
+    /// placeholder tokens of the same type
+    pub(crate) fn next_token_recovering(&mut self, errors: &mut Vec<ParseError>) -> Token {
+        loop {
+            let error = match self.next_token() {
+                Ok(token) => return token,
+                Err(error) => error,
+            };
+            let kind = error.kind();
+            errors.push(error);
+            match kind {
+                ErrorKind::InvalidNumber => return Token::Number(0.into()),
+                ErrorKind::InvalidKeyword => return Token::Null,
+                ErrorKind::UnterminatedString
+                | ErrorKind::InvalidEscape
+                | ErrorKind::ControlCharacter
+                | ErrorKind::StringTooLong => {
+                    // The current character is still the opening quote
+                    let quote = self.current_char.unwrap_or('"');
+                    self.position = self
+                        .string_end(quote)
+                        .map_or(self.input.len(), |end| end + 1);
+                    self.advance();
+                    return Token::String(String::new());
+                }
+                ErrorKind::UnterminatedComment => {
+                    self.position = self.input.len();
+                    self.advance();
+                }
+                _ => self.advance(),
+            }
+        }
+    }
+
+    fn skip_whitespace(&mut self) -> Result<(), ParseError> {
+        self.skip_trivia(None)
+    }
+
+    /// Skip whitespace and (if allowed) comments, recording each run of
+    /// whitespace and each comment in `trivia`
+    fn skip_trivia(&mut self, mut trivia: Option<&mut Vec<Trivia>>) -> Result<(), ParseError> {
         while let Some(ch) = self.current_char {
-            if ch.is_whitespace() {
+            let start = self.offset();
+            let byte_order_mark = ch == BYTE_ORDER_MARK && start == 0 && self.at_document_start;
+            let kind = if ch.is_whitespace() || byte_order_mark {
                 self.advance();
+                while self.current_char.is_some_and(char::is_whitespace) {
+                    self.advance();
+                }
+                TriviaKind::Whitespace
+            } else if ch == '/' && self.allow_comments {
+                self.skip_comment()?
             } else {
                 break;
+            };
+            if let Some(trivia) = trivia.as_deref_mut() {
+                let span = Span { start, end: self.offset() };
+                trivia.push(Trivia { kind, span });
             }
         }
+        Ok(())
+    }
+
+    /// Like `next_token`, but the whitespace and comments before the token
+    /// are appended to `trivia` instead of being dropped
+    ///
+    /// Trivia before a token that was already peeked at has been dropped.
+    pub(crate) fn next_token_with_trivia(
+        &mut self,
+        trivia: &mut Vec<Trivia>,
+    ) -> Result<Token, ParseError> {
+        if self.peeked.is_none() {
+            self.skip_trivia(Some(trivia))?;
+        }
+        self.next_token()
+    }
+
+    /// Skip a `// line` or `/* block */` comment starting at the current `/`
+    fn skip_comment(&mut self) -> Result<TriviaKind, ParseError> {
+        let start_pos = self.position - 1;
+        let rest = &self.input[self.position..];
+        let (end, kind) = if rest.starts_with('/') {
+            let end = rest.find('\n').map_or(self.input.len(), |i| self.position + i);
+            (end, TriviaKind::LineComment)
+        } else if let Some(body) = rest.strip_prefix('*') {
+            match body.find("*/") {
+                Some(i) => (self.position + 1 + i + 2, TriviaKind::BlockComment),
+                None => {
+                    return Err(
+                        ParseError::new("Unterminated comment", start_pos, self.input)
+                            .with_kind(ErrorKind::UnterminatedComment),
+                    );
+                }
+            }
+        } else {
+            return Err(
+                ParseError::new("Unexpected character: '/'", start_pos, self.input)
+                    .with_kind(ErrorKind::UnexpectedCharacter),
+            );
+        };
+        self.position = end;
+        self.advance();
+        Ok(kind)
     }
 
     /// Read the next token from input
     pub fn next_token(&mut self) -> Result<Token, ParseError> {
-        self.skip_whitespace();
+        if let Some((token, start)) = self.peeked.take() {
+            self.token_start = start;
+            return token;
+        }
+        self.lex_token()
+    }
 
-        let start_pos = self.position;
+    /// Look at the next token without consuming it; the following
+    /// `next_token` returns it (or the error) again
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use json_parser_rs::lexer::{Lexer, Token};
+    ///
+    /// let mut lexer = Lexer::new("[1]");
+    /// assert_eq!(lexer.peek().unwrap(), &Token::LeftBracket);
+    /// assert_eq!(lexer.next_token().unwrap(), Token::LeftBracket);
+    /// assert_eq!(lexer.next_token().unwrap(), Token::Number(1.into()));
+    /// ```
+    pub fn peek(&mut self) -> Result<&Token, ParseError> {
+        if self.peeked.is_none() {
+            let token = self.lex_token();
+            self.peeked = Some((token, self.token_start));
+        }
+        match &self.peeked {
+            Some((Ok(token), _)) => Ok(token),
+            Some((Err(error), _)) => Err(error.clone()),
+            None => unreachable!("just filled"),
+        }
+    }
+
+    fn lex_token(&mut self) -> Result<Token, ParseError> {
+        self.skip_whitespace()?;
+
+        self.token_start = self.offset();
 
         match self.current_char {
             None => Ok(Token::Eof),
@@ -103,127 +507,409 @@ impl<'a> Lexer<'a> {
                 self.advance();
                 Ok(Token::Colon)
             }
-            Some('"') => self.read_string(),
+            Some('"') => self.read_string('"'),
+            Some('\'') if self.allow_single_quotes => self.read_string('\''),
             Some(ch) if ch.is_ascii_digit() || ch == '-' => self.read_number(),
             Some(ch) if ch.is_alphabetic() => self.read_keyword(),
-            Some(ch) => Err(ParseError::new(
-                format!("Unexpected character: '{}'", ch),
-                start_pos,
-                self.input,
-            )),
+            Some('_' | '$') if self.allow_unquoted_keys => self.read_keyword(),
+            Some(ch) => Err(self.unexpected_character(ch)),
         }
     }
 
-    fn read_string(&mut self) -> Result<Token, ParseError> {
+    /// Error for a character that cannot start a token at `token_start`
+    fn unexpected_character(&self, ch: char) -> ParseError {
+        ParseError::new(
+            format!("Unexpected character: '{}'", ch),
+            self.token_start,
+            self.input,
+        )
+        .with_kind(ErrorKind::UnexpectedCharacter)
+    }
+
+    /// Like `next_token`, but with the token's byte range, line and column
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use json_parser_rs::lexer::{Lexer, Token};
+    ///
+    /// let mut lexer = Lexer::new("{\n  \"a\": 1\n}");
+    /// lexer.next_spanned_token().unwrap();
+    /// let key = lexer.next_spanned_token().unwrap();
+    /// assert_eq!(key.token, Token::String("a".to_string()));
+    /// assert_eq!((key.start, key.end), (4, 7));
+    /// assert_eq!((key.line, key.column), (2, 3));
+    /// ```
+    pub fn next_spanned_token(&mut self) -> Result<SpannedToken, ParseError> {
+        let token = self.next_token()?;
+        let start = self.token_start;
+        let (line, column) = self.line_column(start);
+        Ok(SpannedToken {
+            token,
+            start,
+            end: self.offset(),
+            line,
+            column,
+        })
+    }
+
+    /// Iterate over the remaining tokens with their locations, e.g. for a
+    /// syntax highlighter
+    ///
+    /// Ends before `Eof`, or after yielding the first error.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use json_parser_rs::lexer::Lexer;
+    ///
+    /// let input = "[true, null]";
+    /// let words: Vec<&str> = Lexer::new(input)
+    ///     .spanned()
+    ///     .map(|token| {
+    ///         let token = token.unwrap();
+    ///         &input[token.start..token.end]
+    ///     })
+    ///     .collect();
+    /// assert_eq!(words, ["[", "true", ",", "null", "]"]);
+    /// ```
+    pub fn spanned(self) -> SpannedTokens<'a> {
+        SpannedTokens {
+            lexer: self,
+            done: false,
+        }
+    }
+
+    /// Line and column of `offset`, counting lines incrementally from the
+    /// previous call so that walking the whole input stays linear
+    fn line_column(&mut self, offset: usize) -> (usize, usize) {
+        if offset < self.line_offset {
+            self.line_offset = 0;
+            self.line = 1;
+            self.line_start = 0;
+        }
+        let scanned = &self.input.as_bytes()[self.line_offset..offset];
+        for (i, _) in scanned.iter().enumerate().filter(|(_, b)| **b == b'\n') {
+            self.line += 1;
+            self.line_start = self.line_offset + i + 1;
+        }
+        self.line_offset = offset;
+        (self.line, offset - self.line_start + 1)
+    }
+
+    /// Byte offset of the current (not yet consumed) character
+    pub(crate) fn offset(&self) -> usize {
+        match self.current_char {
+            Some(ch) => self.position - ch.len_utf8(),
+            None => self.input.len(),
+        }
+    }
+
+    /// Like `next_token`, but quoted strings without escapes are returned as
+    /// slices of the input instead of fresh allocations
+    pub(crate) fn next_token_ref(&mut self) -> Result<TokenRef<'a>, ParseError> {
+        if self.peeked.is_some() {
+            return Ok(match self.next_token()? {
+                Token::String(s) => TokenRef::Str(Cow::Owned(s)),
+                token => TokenRef::Token(token),
+            });
+        }
+        self.skip_whitespace()?;
+        let quote = match self.current_char {
+            Some('"') => '"',
+            Some('\'') if self.allow_single_quotes => '\'',
+            _ => return self.next_token().map(TokenRef::Token),
+        };
+        self.token_start = self.position - 1;
+        match self.read_str(quote)? {
+            (_, Some(raw)) => Ok(TokenRef::Token(Token::RawString(raw.to_string()))),
+            (string, None) => Ok(TokenRef::Str(string)),
+        }
+    }
+
+    fn read_string(&mut self, quote: char) -> Result<Token, ParseError> {
+        Ok(match self.read_str(quote)? {
+            (_, Some(raw)) => Token::RawString(raw.to_string()),
+            (string, None) => Token::String(string.into_owned()),
+        })
+    }
+
+    /// Read the string opened by the current character; the second element
+    /// is the escaped source text when unpaired surrogates were replaced
+    fn read_str(&mut self, quote: char) -> Result<(Cow<'a, str>, Option<&'a str>), ParseError> {
+        let (start_pos, end) = self.string_content(quote)?;
+        let content = &self.input[start_pos..end];
+        let lone_surrogates = self.preserve_lone_surrogates && quote == '"';
+        let (string, replaced) = unescape_content(content, quote, lone_surrogates)
+            .map_err(|error| self.escape_error(error, start_pos))?;
+        self.check_string_length(string.len(), start_pos)?;
+
+        // Continue after the closing quote
+        self.position = end + 1;
+        self.advance();
+        Ok((string, replaced.then_some(content)))
+    }
+
+    /// Like `read_str`, but the escapes are only checked, not decoded
+    fn scan_str(&mut self, quote: char) -> Result<StrSlice<'a>, ParseError> {
+        let (start_pos, end) = self.string_content(quote)?;
+        let content = &self.input[start_pos..end];
+        let lone_surrogates = self.preserve_lone_surrogates && quote == '"';
+        let (len, _) = check_escapes(content, quote, lone_surrogates)
+            .map_err(|error| self.escape_error(error, start_pos))?;
+        self.check_string_length(len, start_pos)?;
+
+        self.position = end + 1;
+        self.advance();
+        Ok(StrSlice {
+            content,
+            needs_unescape: content.contains('\\'),
+            quote,
+            len,
+        })
+    }
+
+    /// Byte range of the content of the string opened by the current
+    /// character, checked for raw control characters
+    fn string_content(&self, quote: char) -> Result<(usize, usize), ParseError> {
+        // The opening quote is the current character, so `position` is
+        // already the first byte of the content
         let start_pos = self.position;
-        self.advance(); // Skip opening quote
+        let Some(end) = self.string_end(quote) else {
+            return Err(
+                ParseError::new("Unterminated string", start_pos, self.input)
+                    .with_kind(ErrorKind::UnterminatedString),
+            );
+        };
+
+        if self.reject_control_characters
+            && let Some(i) = self.input[start_pos..end].bytes().position(|b| b < 0x20)
+        {
+            return Err(ParseError::new(
+                format!(
+                    "Control character U+{:04X} in string",
+                    self.input.as_bytes()[start_pos + i]
+                ),
+                start_pos + i,
+                self.input,
+            )
+            .with_kind(ErrorKind::ControlCharacter));
+        }
+        Ok((start_pos, end))
+    }
 
-        let mut string = String::new();
-        let mut escaped = false;
+    fn escape_error(&self, (message, offset): (String, usize), start_pos: usize) -> ParseError {
+        ParseError::new(message, start_pos + offset, self.input).with_kind(ErrorKind::InvalidEscape)
+    }
 
-        while let Some(ch) = self.current_char {
-            if escaped {
-                match ch {
-                    '"' => string.push('"'),
-                    '\\' => string.push('\\'),
-                    'n' => string.push('\n'),
-                    'r' => string.push('\r'),
-                    't' => string.push('\t'),
-                    _ => {
-                        return Err(ParseError::new(
-                            format!("Invalid escape sequence: \\{}", ch),
-                            self.position,
-                            self.input,
-                        ))
-                    }
-                }
-                escaped = false;
-                self.advance();
-            } else if ch == '\\' {
-                escaped = true;
-                self.advance();
-            } else if ch == '"' {
-                self.advance(); // Skip closing quote
-                return Ok(Token::String(string));
-            } else {
-                string.push(ch);
-                self.advance();
-            }
+    fn check_string_length(&self, len: usize, start_pos: usize) -> Result<(), ParseError> {
+        match self.max_string_length {
+            Some(max) if len > max => Err(ParseError::new(
+                format!("String of {} bytes exceeds the limit of {}", len, max),
+                start_pos,
+                self.input,
+            )
+            .with_kind(ErrorKind::StringTooLong)),
+            _ => Ok(()),
         }
+    }
 
-        Err(ParseError::new(
-            "Unterminated string",
-            start_pos,
-            self.input,
-        ))
+    /// Byte offset of the quote closing the string opened by the current
+    /// character, stepping over escaped characters
+    fn string_end(&self, quote: char) -> Option<usize> {
+        let bytes = self.input.as_bytes();
+        let mut end = self.position;
+        loop {
+            match *bytes.get(end)? {
+                b if b == quote as u8 => return Some(end),
+                b'\\' => end += 2,
+                _ => end += 1,
+            }
+        }
     }
 
     fn read_number(&mut self) -> Result<Token, ParseError> {
-        let start_pos = self.position;
-        let mut number_str = String::new();
-        let mut has_dot = false;
+        let literal = self.scan_number()?;
+        JsonNumber::from_literal(literal)
+            .map(Token::Number)
+            .ok_or_else(|| {
+                ParseError::new("Invalid number", self.token_start, self.input)
+                    .with_kind(ErrorKind::InvalidNumber)
+            })
+    }
+
+    /// Consume the number literal at the current position and check it
+    /// against the RFC 8259 grammar
+    fn scan_number(&mut self) -> Result<&'a str, ParseError> {
+        let start_pos = self.token_start;
+        // The first violation of the grammar and its offset; the whole
+        // literal is consumed regardless, so that recovering after the
+        // error resumes behind it
+        let mut problem = None;
 
         // Handle negative sign
         if self.current_char == Some('-') {
-            number_str.push('-');
             self.advance();
         }
 
         // Read digits before decimal point
-        while let Some(ch) = self.current_char {
-            if ch.is_ascii_digit() {
-                number_str.push(ch);
-                self.advance();
-            } else if ch == '.' && !has_dot {
-                number_str.push('.');
-                has_dot = true;
-                self.advance();
-            } else {
-                break;
+        let integer_start = self.offset();
+        let integer_digits = self.read_digits();
+        if integer_digits == 0 {
+            problem = Some(("expected digit after '-'", integer_start));
+        } else if integer_digits > 1
+            && self.input[integer_start..].starts_with('0')
+            && !self.allow_lenient_numbers
+        {
+            problem = Some(("leading zeros not allowed", integer_start));
+        }
+
+        // Read fraction
+        if self.current_char == Some('.') {
+            self.advance();
+            let fraction_start = self.offset();
+            if self.read_digits() == 0 && !self.allow_lenient_numbers {
+                problem = problem.or(Some(("expected digit after '.'", fraction_start)));
             }
         }
 
-        // Read digits after decimal point
-        if has_dot {
-            while let Some(ch) = self.current_char {
-                if ch.is_ascii_digit() {
-                    number_str.push(ch);
-                    self.advance();
-                } else {
-                    break;
-                }
+        // Read exponent
+        if let Some('e' | 'E') = self.current_char {
+            self.advance();
+            if let Some('+' | '-') = self.current_char {
+                self.advance();
+            }
+            let exponent_start = self.offset();
+            if self.read_digits() == 0 {
+                problem = problem.or(Some(("expected digit in exponent", exponent_start)));
             }
         }
 
-        number_str
-            .parse::<f64>()
-            .map(Token::Number)
-            .map_err(|_| ParseError::new("Invalid number", start_pos, self.input))
+        if let Some((message, position)) = problem {
+            return Err(ParseError::new(
+                format!("Invalid number: {}", message),
+                position,
+                self.input,
+            )
+            .with_kind(ErrorKind::InvalidNumber));
+        }
+        Ok(&self.input[start_pos..self.offset()])
+    }
+
+    /// Consume the digits at the current position, returning how many
+    fn read_digits(&mut self) -> usize {
+        let mut count = 0;
+        while self.current_char.is_some_and(|ch| ch.is_ascii_digit()) {
+            self.advance();
+            count += 1;
+        }
+        count
     }
 
     fn read_keyword(&mut self) -> Result<Token, ParseError> {
-        let start_pos = self.position;
-        let mut keyword = String::new();
+        Ok(match self.scan_keyword()? {
+            SliceToken::Boolean(b) => Token::Boolean(b),
+            SliceToken::Null => Token::Null,
+            SliceToken::Identifier(name) => Token::Identifier(name.to_string()),
+            _ => unreachable!("bare words are keywords or identifiers"),
+        })
+    }
 
+    /// Consume the bare word at the current position: a keyword, or an
+    /// identifier when unquoted keys are allowed
+    fn scan_keyword(&mut self) -> Result<SliceToken<'a>, ParseError> {
+        let start_pos = self.token_start;
         while let Some(ch) = self.current_char {
-            if ch.is_alphanumeric() {
-                keyword.push(ch);
+            let identifier_char = self.allow_unquoted_keys && (ch == '_' || ch == '$');
+            if ch.is_alphanumeric() || identifier_char {
                 self.advance();
             } else {
                 break;
             }
         }
 
-        match keyword.as_str() {
-            "true" => Ok(Token::Boolean(true)),
-            "false" => Ok(Token::Boolean(false)),
-            "null" => Ok(Token::Null),
-            _ => Err(ParseError::new(
+        match &self.input[start_pos..self.offset()] {
+            "true" => Ok(SliceToken::Boolean(true)),
+            "false" => Ok(SliceToken::Boolean(false)),
+            "null" => Ok(SliceToken::Null),
+            keyword if self.allow_unquoted_keys => Ok(SliceToken::Identifier(keyword)),
+            keyword => Err(ParseError::new(
                 format!("Unexpected keyword: {}", keyword),
                 start_pos,
                 self.input,
-            )),
+            )
+            .with_kind(ErrorKind::InvalidKeyword)),
+        }
+    }
+
+    /// Pass the remaining tokens to `sink`, up to (not including) `Eof`
+    ///
+    /// Nothing is allocated per token: strings, numbers and identifiers
+    /// arrive as slices of the input. Tokens are checked as thoroughly as
+    /// by `next_token` (including escape sequences and the options' string
+    /// limit), so the first malformed one ends tokenizing with its error.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use json_parser_rs::lexer::{Lexer, SliceToken};
+    ///
+    /// let input = r#"{"id": 7, "na\u006de": ["x", 1.5]}"#;
+    /// let mut strings = Vec::new();
+    /// let mut numbers = Vec::new();
+    /// Lexer::new(input)
+    ///     .tokenize_into(&mut |token, _span| match token {
+    ///         SliceToken::String(s) => strings.push((s.content, s.needs_unescape)),
+    ///         SliceToken::Number(literal) => numbers.push(literal),
+    ///         _ => {}
+    ///     })
+    ///     .unwrap();
+    /// assert_eq!(strings, [("id", false), ("na\\u006de", true), ("x", false)]);
+    /// assert_eq!(numbers, ["7", "1.5"]);
+    /// ```
+    pub fn tokenize_into(&mut self, sink: &mut impl TokenSink<'a>) -> Result<(), ParseError> {
+        while let Some((token, span)) = self.next_slice_token()? {
+            sink.token(token, span);
+        }
+        Ok(())
+    }
+
+    /// The next token for `tokenize_into`; `None` at the end of the input
+    pub(crate) fn next_slice_token(
+        &mut self,
+    ) -> Result<Option<(SliceToken<'a>, Span)>, ParseError> {
+        // A peeked token was lexed into an owned `Token`; lex it again
+        if let Some((_, start)) = self.peeked.take() {
+            self.position = start;
+            self.advance();
         }
+        self.skip_whitespace()?;
+        self.token_start = self.offset();
+        let token = match self.current_char {
+            None => return Ok(None),
+            Some(ch @ ('{' | '}' | '[' | ']' | ',' | ':')) => {
+                self.advance();
+                match ch {
+                    '{' => SliceToken::LeftBrace,
+                    '}' => SliceToken::RightBrace,
+                    '[' => SliceToken::LeftBracket,
+                    ']' => SliceToken::RightBracket,
+                    ',' => SliceToken::Comma,
+                    _ => SliceToken::Colon,
+                }
+            }
+            Some('"') => SliceToken::String(self.scan_str('"')?),
+            Some('\'') if self.allow_single_quotes => SliceToken::String(self.scan_str('\'')?),
+            Some(ch) if ch.is_ascii_digit() || ch == '-' => SliceToken::Number(self.scan_number()?),
+            Some(ch) if ch.is_alphabetic() => self.scan_keyword()?,
+            Some('_' | '$') if self.allow_unquoted_keys => self.scan_keyword()?,
+            Some(ch) => return Err(self.unexpected_character(ch)),
+        };
+        let span = Span {
+            start: self.token_start,
+            end: self.offset(),
+        };
+        Ok(Some((token, span)))
     }
 }
 
@@ -231,6 +917,52 @@ impl<'a> Lexer<'a> {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_token_display() {
+        let tokens = [
+            (Token::Colon, "':'"),
+            (Token::RightBrace, "'}'"),
+            (Token::Number(JsonNumber::from(1)), "number 1"),
+            (Token::Number(JsonNumber::from_f64(2.5).unwrap()), "number 2.5"),
+            (Token::String("a\"b\n".into()), r#"string "a\"b\n""#),
+            (Token::RawString(r"\ud800".into()), r#"string "\ud800""#),
+            (Token::Boolean(false), "'false'"),
+            (Token::Null, "'null'"),
+            (Token::Identifier("key".into()), "identifier 'key'"),
+            (Token::Eof, "end of input"),
+        ];
+        for (token, text) in tokens {
+            assert_eq!(token.to_string(), text);
+        }
+        assert_eq!(TokenRef::Str(Cow::Borrowed("x")).to_string(), r#"string "x""#);
+        assert_eq!(
+            crate::parse_json("[1, ").unwrap_err().message(),
+            "Unexpected token: end of input"
+        );
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_position_accessors() {
+        let input = "  [\"é\", 12]";
+        let mut lexer = Lexer::new(input);
+        assert_eq!(lexer.input(), input);
+        assert_eq!((lexer.position(), lexer.token_start()), (1, 0));
+
+        assert_eq!(lexer.next_token().unwrap(), Token::LeftBracket);
+        assert_eq!((lexer.token_start(), lexer.position()), (2, 4));
+        lexer.next_token().unwrap();
+        assert_eq!((lexer.token_start(), lexer.position()), (3, 8));
+        lexer.next_token().unwrap();
+        assert_eq!(lexer.next_token().unwrap(), Token::Number(12.into()));
+        assert_eq!(lexer.token_start(), 9);
+        assert_eq!(lexer.next_token().unwrap(), Token::RightBracket);
+        assert_eq!(lexer.next_token().unwrap(), Token::Eof);
+        assert_eq!((lexer.token_start(), lexer.position()), (input.len(), input.len()));
+        // The deprecated fields read the same values
+        assert_eq!((lexer.input, lexer.position), (lexer.input(), lexer.position()));
+    }
+
     #[test]
     fn test_lexer_basic_tokens() {
         let mut lexer = Lexer::new("{}[],:");
@@ -255,10 +987,51 @@ mod tests {
     #[test]
     fn test_lexer_number() {
         let mut lexer = Lexer::new("123");
-        assert_eq!(lexer.next_token().unwrap(), Token::Number(123.0));
+        assert_eq!(lexer.next_token().unwrap(), Token::Number(123.into()));
 
         let mut lexer = Lexer::new("-42.5");
-        assert_eq!(lexer.next_token().unwrap(), Token::Number(-42.5));
+        assert_eq!(
+            lexer.next_token().unwrap(),
+            Token::Number(JsonNumber::from_f64(-42.5).unwrap())
+        );
+
+        let mut lexer = Lexer::new("1.5E+3 2e-2");
+        let expected = [1500.0, 0.02].map(|f| Token::Number(JsonNumber::from_f64(f).unwrap()));
+        assert_eq!(lexer.next_token().unwrap(), expected[0]);
+        assert_eq!(lexer.next_token().unwrap(), expected[1]);
+    }
+
+    #[test]
+    fn test_invalid_number_forms() {
+        let cases = [
+            ("007", "leading zeros not allowed", 0),
+            ("-01.5", "leading zeros not allowed", 1),
+            ("-", "expected digit after '-'", 1),
+            ("--5", "expected digit after '-'", 1),
+            ("-x", "expected digit after '-'", 1),
+            ("1.", "expected digit after '.'", 2),
+            ("1.e5", "expected digit after '.'", 2),
+            ("2e", "expected digit in exponent", 2),
+            ("2E+", "expected digit in exponent", 3),
+        ];
+        for (input, message, position) in cases {
+            let error = Lexer::new(input).next_token().unwrap_err();
+            assert_eq!(error.kind(), ErrorKind::InvalidNumber, "{}", input);
+            assert_eq!(error.message, format!("Invalid number: {}", message), "{}", input);
+            assert_eq!(error.position(), position, "{}", input);
+        }
+        for valid in ["0", "-0", "0.5", "-0e0", "10", "1e05"] {
+            assert!(Lexer::new(valid).next_token().is_ok(), "{}", valid);
+        }
+
+        let lenient = ParserOptions::default().allow_lenient_numbers(true);
+        let mut lexer = Lexer::with_options("007 1. -00.5e1", &lenient);
+        let float = |f| Token::Number(JsonNumber::from_f64(f).unwrap());
+        assert_eq!(lexer.next_token().unwrap(), Token::Number(7.into()));
+        assert_eq!(lexer.next_token().unwrap(), float(1.0));
+        assert_eq!(lexer.next_token().unwrap(), float(-5.0));
+        let error = Lexer::with_options("-", &lenient).next_token().unwrap_err();
+        assert_eq!(error.message, "Invalid number: expected digit after '-'");
     }
 
     #[test]
@@ -272,4 +1045,115 @@ mod tests {
         let mut lexer = Lexer::new("null");
         assert_eq!(lexer.next_token().unwrap(), Token::Null);
     }
+
+    #[test]
+    fn test_spanned_tokens_track_lines() {
+        let input = "[\n  \"é\", -1.5,\n\n  {\"k\": false}\n]";
+        let tokens: Vec<SpannedToken> = Lexer::new(input).spanned().map(Result::unwrap).collect();
+        let located: Vec<(&str, usize, usize)> = tokens
+            .iter()
+            .map(|t| (&input[t.start..t.end], t.line, t.column))
+            .collect();
+        assert_eq!(
+            located,
+            [
+                ("[", 1, 1),
+                ("\"é\"", 2, 3),
+                (",", 2, 7),
+                ("-1.5", 2, 9),
+                (",", 2, 13),
+                ("{", 4, 3),
+                ("\"k\"", 4, 4),
+                (":", 4, 7),
+                ("false", 4, 9),
+                ("}", 4, 14),
+                ("]", 5, 1),
+            ]
+        );
+
+        let mut tokens = Lexer::new("[1, @]").spanned();
+        assert_eq!(tokens.by_ref().filter(Result::is_ok).count(), 3);
+        assert!(tokens.next().is_none());
+    }
+
+    #[test]
+    fn test_tokenize_into_matches_next_token() {
+        let json5 = ParserOptions::json5();
+        let inputs = [
+            ("{\"a\\n\": [1, -2.5e3, true, null], 'b': x_1}", &json5),
+            ("\u{feff} [\"\\ud83d\\ude00\", \"é\", 0]", &ParserOptions::default()),
+        ];
+        for (input, options) in inputs {
+            let mut sliced = Vec::new();
+            Lexer::with_options(input, options)
+                .tokenize_into(&mut |token: SliceToken<'_>, span: Span| {
+                    let owned = match token {
+                        SliceToken::String(s) => Token::String(s.unescape().into_owned()),
+                        SliceToken::Number(literal) => {
+                            Token::Number(JsonNumber::from_literal(literal).unwrap())
+                        }
+                        SliceToken::Identifier(name) => Token::Identifier(name.to_string()),
+                        SliceToken::Boolean(b) => Token::Boolean(b),
+                        SliceToken::Null => Token::Null,
+                        SliceToken::LeftBrace => Token::LeftBrace,
+                        SliceToken::RightBrace => Token::RightBrace,
+                        SliceToken::LeftBracket => Token::LeftBracket,
+                        SliceToken::RightBracket => Token::RightBracket,
+                        SliceToken::Comma => Token::Comma,
+                        SliceToken::Colon => Token::Colon,
+                    };
+                    sliced.push((owned, span.start, span.end));
+                })
+                .unwrap();
+            let spanned: Vec<_> = Lexer::with_options(input, options)
+                .spanned()
+                .map(|t| t.map(|t| (t.token, t.start, t.end)).unwrap())
+                .collect();
+            assert_eq!(sliced, spanned, "{}", input);
+        }
+
+        // Errors are the ones `next_token` reports, after the tokens before
+        for input in ["[1, 01]", "[\"a\\x\"]", "[\"a\u{1}\"]", "[nope]", "[1] @", "[\"open"] {
+            let mut count = 0;
+            let error = Lexer::new(input)
+                .tokenize_into(&mut |_, _| count += 1)
+                .unwrap_err();
+            let expected = Lexer::new(input).find_map(Result::err).unwrap();
+            assert_eq!(error, expected, "{}", input);
+            assert_eq!(count, Lexer::new(input).take_while(Result::is_ok).count());
+        }
+        let limited = ParserOptions::default().max_string_length(Some(2));
+        let error = Lexer::with_options(r#""a\u0062c""#, &limited)
+            .tokenize_into(&mut |_, _| {})
+            .unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::StringTooLong);
+
+        // A peeked token is passed on too
+        let mut lexer = Lexer::new("[1]");
+        lexer.peek().unwrap();
+        let mut count = 0;
+        lexer.tokenize_into(&mut |_, _| count += 1).unwrap();
+        assert_eq!(count, 3);
+    }
+
+    #[test]
+    fn test_peek_then_iterate() {
+        let mut lexer = Lexer::new(" [1, \"a\" @");
+        assert_eq!(lexer.peek().unwrap(), &Token::LeftBracket);
+        assert_eq!(lexer.peek().unwrap(), &Token::LeftBracket);
+        assert_eq!(lexer.token_start(), 1);
+        assert_eq!(lexer.next(), Some(Ok(Token::LeftBracket)));
+        assert_eq!(lexer.next_token().unwrap(), Token::Number(1.into()));
+
+        let tokens: Vec<_> = lexer.by_ref().collect();
+        assert_eq!(tokens.len(), 3);
+        assert_eq!(tokens[1], Ok(Token::String("a".to_string())));
+        let error = tokens[2].as_ref().unwrap_err();
+        assert_eq!(error.position(), 9);
+        assert_eq!(lexer.next(), None);
+
+        let mut lexer = Lexer::new("@");
+        let peeked = lexer.peek().unwrap_err();
+        assert_eq!(lexer.next_token().unwrap_err(), peeked);
+    }
 }
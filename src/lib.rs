@@ -6,14 +6,91 @@
 /// - Separation of concerns (lexer, parser, error handling)
 /// - Comprehensive documentation
 /// - Iterator-based parsing
+#[cfg(feature = "bumpalo")]
+pub mod arena;
+#[cfg(feature = "tokio")]
+pub mod async_io;
+#[cfg(any(feature = "msgpack", feature = "cbor"))]
+pub mod binary;
+pub mod borrowed;
+pub mod convert;
+pub mod cst;
+pub mod csv;
+#[cfg(feature = "serde")]
+pub mod de;
+pub mod embedded;
+pub mod encoding;
 pub mod error;
+pub mod framing;
+#[cfg(any(feature = "arbitrary", feature = "proptest"))]
+pub mod generate;
+#[cfg(feature = "geojson")]
+pub mod geojson;
+pub mod hypermedia;
+#[cfg(any(feature = "toml", feature = "yaml", feature = "protobuf"))]
+pub mod interop;
+pub mod journal;
 pub mod json;
+pub mod jsonld;
+pub mod keypath;
+pub mod keys;
 pub mod lexer;
+pub mod lsp;
+mod macros;
+pub mod mapping;
+pub mod ndjson;
+pub mod number;
+pub mod object;
+pub mod openapi;
+pub mod options;
+pub mod paginate;
+#[cfg(feature = "rayon")]
+pub mod parallel;
 pub mod parser;
+pub mod patch;
+pub mod path;
+pub mod plugin;
+pub mod pointer;
+pub mod push;
+pub mod reader;
+pub mod reference;
+pub mod schema;
+#[cfg(feature = "serde")]
+pub mod ser;
+pub mod stats;
+pub mod stream;
+pub mod suggest;
+pub mod tape;
+pub mod validate;
+pub mod view;
+pub mod writer;
+pub mod workload;
+pub mod workspace;
 
-pub use error::ParseError;
-pub use json::JsonValue;
+pub use borrowed::{JsonValueRef, parse_json_ref};
+pub use convert::{ConvertError, Format, FormatRegistry};
+pub use cst::parse_json_cst;
+pub use csv::{CsvOptions, from_csv, from_csv_str, to_csv};
+pub use error::{ErrorKind, ParseError};
+pub use json::{JsonValue, MergeStrategy};
+pub use keypath::{KeyPath, KeyPathError};
+pub use ndjson::parse_ndjson;
+pub use number::JsonNumber;
+pub use object::{DuplicateKeyPolicy, Entry, JsonObject};
+pub use options::ParserOptions;
 pub use parser::Parser;
+pub use patch::PatchError;
+pub use path::{JsonPath, PathError, UnboundVariable, validate_path};
+#[cfg(feature = "serde")]
+pub use de::from_str;
+#[cfg(feature = "serde")]
+pub use ser::{SerializeError, to_string};
+pub use plugin::{KeyedExtensionPlugin, ValuePlugin};
+pub use pointer::{JsonPointer, PointerError};
+pub use reader::{ReaderLexer, parse_json_reader};
+pub use schema::{Schema, SchemaViolation};
+pub use stats::ValueStats;
+pub use view::{AccessError, ArrayView, ObjectView};
 
 /// Parse a JSON string into a JsonValue
 ///
@@ -29,3 +106,22 @@ pub fn parse_json(input: &str) -> Result<JsonValue, ParseError> {
     let mut parser = Parser::new(input)?;
     parser.parse()
 }
+
+/// Parse a human-authored JSON5-style document
+///
+/// Accepts comments, trailing commas, single-quoted strings and unquoted
+/// keys on top of standard JSON (see `ParserOptions::json5`). Plain
+/// `parse_json` stays strict.
+///
+/// # Examples
+///
+/// ```
+/// use json_parser_rs::parse_json5;
+///
+/// let config = parse_json5("{\n  // port to listen on\n  port: 8080,\n  host: 'localhost',\n}").unwrap();
+/// assert_eq!(config["port"].as_u64(), Some(8080));
+/// ```
+pub fn parse_json5(input: &str) -> Result<JsonValue, ParseError> {
+    let mut parser = Parser::with_options(input, ParserOptions::json5())?;
+    parser.parse()
+}
@@ -0,0 +1,385 @@
+//! LSP - parse diagnostics for editors over the Language Server Protocol
+//!
+//! [`serve`] speaks just enough of LSP 3.17 on a byte stream (stdin and
+//! stdout for the `lsp` subcommand) for an editor to show this crate's
+//! parse errors while a JSON document is edited: `initialize`, full text
+//! synchronization (`didOpen`, `didChange`, `didClose`), `shutdown` and
+//! `exit`. Whenever a document changes, every syntax error in it is
+//! published as a diagnostic, found with the error-recovering
+//! [`validate_all`]; the documents themselves are kept in a [`Workspace`].
+//!
+//! Messages are JSON-RPC 2.0, each preceded by a `Content-Length` header.
+//! [`LanguageServer`] handles decoded messages without any I/O, for hosts
+//! that bring their own transport.
+//!
+//! # Examples
+//!
+//! ```
+//! use json_parser_rs::json;
+//! use json_parser_rs::lsp::LanguageServer;
+//!
+//! let mut server = LanguageServer::new();
+//! server.handle(&json!({"jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {}}));
+//! let sent = server.handle(&json!({
+//!     "jsonrpc": "2.0",
+//!     "method": "textDocument/didOpen",
+//!     "params": {"textDocument": {
+//!         "uri": "file:///config.json", "languageId": "json", "version": 1,
+//!         "text": "{\"port\": 80,}"
+//!     }}
+//! }));
+//! let diagnostic = &sent[0]["params"]["diagnostics"][0];
+//! assert_eq!(diagnostic["message"].as_str(), Some("Trailing comma not allowed"));
+//! assert_eq!(diagnostic["range"]["start"], json!({"line": 0, "character": 12}));
+//! ```
+
+use std::io::{self, BufRead, Write};
+
+use crate::error::ParseError;
+use crate::json;
+use crate::json::JsonValue;
+use crate::options::ParserOptions;
+use crate::parse_json;
+use crate::validate::validate_all;
+use crate::workspace::Workspace;
+
+/// JSON-RPC error codes
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+/// `TextDocumentSyncKind.Full`: every change sends the whole text
+const FULL_SYNC: u64 = 1;
+
+/// `DiagnosticSeverity.Error`
+const SEVERITY_ERROR: u64 = 1;
+
+/// Server state between messages
+#[derive(Debug, Default)]
+pub struct LanguageServer {
+    /// Open documents by URI
+    documents: Workspace,
+    shutdown: bool,
+    exit_code: Option<i32>,
+}
+
+impl LanguageServer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Handle one decoded message, returning the responses and
+    /// notifications to send back, in order
+    pub fn handle(&mut self, message: &JsonValue) -> Vec<JsonValue> {
+        let id = message.get("id").cloned();
+        let Some(method) = message["method"].as_str() else {
+            // Responses to requests of ours; the server sends none
+            if id.is_some() {
+                return Vec::new();
+            }
+            return vec![error_response(
+                JsonValue::Null,
+                INVALID_REQUEST,
+                "Not a request",
+            )];
+        };
+        let params = &message["params"];
+        let Some(id) = id else {
+            return self.notification(method, params);
+        };
+        let result = match method {
+            "initialize" => Ok(json!({
+                "capabilities": {"textDocumentSync": FULL_SYNC},
+                "serverInfo": {"name": "json-parser-rs", "version": env!("CARGO_PKG_VERSION")},
+            })),
+            "shutdown" => {
+                self.shutdown = true;
+                Ok(JsonValue::Null)
+            }
+            _ => Err((METHOD_NOT_FOUND, format!("Unknown method '{}'", method))),
+        };
+        vec![match result {
+            Ok(result) => json!({"jsonrpc": "2.0", "id": id, "result": result}),
+            Err((code, message)) => error_response(id, code, &message),
+        }]
+    }
+
+    fn notification(&mut self, method: &str, params: &JsonValue) -> Vec<JsonValue> {
+        let document = &params["textDocument"];
+        let Some(uri) = document["uri"].as_str() else {
+            if method == "exit" {
+                self.exit_code = Some(if self.shutdown { 0 } else { 1 });
+            }
+            // Other notifications (`initialized`, `$/...`) need no answer
+            return Vec::new();
+        };
+        let version = &document["version"];
+        match method {
+            "textDocument/didOpen" => {
+                let text = document["text"].as_str().unwrap_or_default();
+                self.documents.insert(uri, text);
+            }
+            "textDocument/didChange" => {
+                // With full sync the last change holds the whole text
+                let changes = params["contentChanges"].as_array();
+                let Some(text) = changes
+                    .and_then(|c| c.as_slice().last())
+                    .and_then(|c| c["text"].as_str())
+                else {
+                    return vec![error_response(
+                        JsonValue::Null,
+                        INVALID_PARAMS,
+                        "didChange without text",
+                    )];
+                };
+                self.documents.insert(uri, text);
+            }
+            "textDocument/didClose" => {
+                self.documents.remove(uri);
+                return vec![publish(uri, &JsonValue::Null, Vec::new())];
+            }
+            _ => return Vec::new(),
+        }
+        let text = self.documents.source(uri).unwrap_or_default();
+        let diagnostics = validate_all(text)
+            .iter()
+            .map(|error| diagnostic(text, error))
+            .collect();
+        vec![publish(uri, version, diagnostics)]
+    }
+
+    /// The process exit code, once `exit` was received: 0 after a
+    /// `shutdown` request, 1 otherwise
+    pub fn exit_code(&self) -> Option<i32> {
+        self.exit_code
+    }
+}
+
+/// Run a server on `input` and `output` until `exit` or the end of input,
+/// returning the exit code
+///
+/// A message body that is not JSON is answered with a JSON-RPC parse
+/// error; malformed headers end the session with an
+/// `io::ErrorKind::InvalidData` error.
+pub fn serve(mut input: impl BufRead, mut output: impl Write) -> io::Result<i32> {
+    let mut server = LanguageServer::new();
+    while let Some(body) = read_message(&mut input)? {
+        let replies = match parse_json(&body) {
+            Ok(message) => server.handle(&message),
+            Err(e) => vec![error_response(JsonValue::Null, PARSE_ERROR, &e.to_string())],
+        };
+        for reply in &replies {
+            write_message(&mut output, reply)?;
+        }
+        output.flush()?;
+        if let Some(code) = server.exit_code() {
+            return Ok(code);
+        }
+    }
+    // The client went away without `exit`
+    Ok(if server.shutdown { 0 } else { 1 })
+}
+
+/// Read the next message body; `None` at the end of input
+///
+/// Bodies larger than the hardened parser's input limit are rejected before
+/// anything is allocated for them.
+pub fn read_message(input: &mut impl BufRead) -> io::Result<Option<String>> {
+    let mut content_length = None;
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if input.read_line(&mut line)? == 0 {
+            if content_length.is_none() {
+                return Ok(None);
+            }
+            return Err(invalid_data("End of input in message header"));
+        }
+        let header = line.trim_end_matches(['\r', '\n']);
+        if header.is_empty() {
+            break;
+        }
+        let (name, value) = header
+            .split_once(':')
+            .ok_or_else(|| invalid_data("Malformed message header"))?;
+        if name.trim().eq_ignore_ascii_case("Content-Length") {
+            let length = value.trim().parse::<usize>();
+            content_length = Some(length.map_err(|_| invalid_data("Invalid Content-Length"))?);
+        }
+    }
+    let length = content_length.ok_or_else(|| invalid_data("Missing Content-Length"))?;
+    if ParserOptions::hardened()
+        .max_input_size
+        .is_some_and(|max| length > max)
+    {
+        return Err(invalid_data("Content-Length exceeds the maximum message size"));
+    }
+    let mut body = vec![0; length];
+    input.read_exact(&mut body)?;
+    String::from_utf8(body)
+        .map(Some)
+        .map_err(|_| invalid_data("Message body is not UTF-8"))
+}
+
+/// Write `message` with its `Content-Length` header
+pub fn write_message(output: &mut impl Write, message: &JsonValue) -> io::Result<()> {
+    let body = message.to_json_string();
+    write!(output, "Content-Length: {}\r\n\r\n{}", body.len(), body)
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn error_response(id: JsonValue, code: i64, message: &str) -> JsonValue {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": {"code": code, "message": message},
+    })
+}
+
+fn publish(uri: &str, version: &JsonValue, diagnostics: Vec<JsonValue>) -> JsonValue {
+    let mut params = json!({"uri": uri, "diagnostics": diagnostics});
+    if !version.is_null() {
+        params["version"] = version.clone();
+    }
+    json!({
+        "jsonrpc": "2.0",
+        "method": "textDocument/publishDiagnostics",
+        "params": params,
+    })
+}
+
+/// An LSP `Diagnostic` for `error`, covering the character it points at
+fn diagnostic(text: &str, error: &ParseError) -> JsonValue {
+    let mut start = error.position.min(text.len());
+    while !text.is_char_boundary(start) {
+        start -= 1;
+    }
+    let end = match text[start..].chars().next() {
+        Some(ch) if ch != '\n' && ch != '\r' => start + ch.len_utf8(),
+        _ => start,
+    };
+    json!({
+        "range": {"start": position(text, start), "end": position(text, end)},
+        "severity": SEVERITY_ERROR,
+        "code": error.kind().as_str(),
+        "source": "json-parser-rs",
+        "message": error.message.as_str(),
+    })
+}
+
+/// An LSP `Position`: zero-based line, and column in UTF-16 code units
+fn position(text: &str, offset: usize) -> JsonValue {
+    let before = &text[..offset];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    json!({
+        "line": before.matches('\n').count() as u64,
+        "character": before[line_start..].encode_utf16().count() as u64,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(id: u64, method: &str) -> JsonValue {
+        json!({"jsonrpc": "2.0", "id": id, "method": method})
+    }
+
+    #[test]
+    fn test_document_lifecycle() {
+        let mut server = LanguageServer::new();
+        let reply = server.handle(&request(1, "initialize"));
+        assert_eq!(
+            reply[0]["result"]["capabilities"]["textDocumentSync"],
+            json!(1)
+        );
+
+        // The emoji is two UTF-16 code units
+        let text = "{\"a\": \"😀\", \"b\" 1,\n  \"c\": tru}";
+        let open = json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/didOpen",
+            "params": {"textDocument": {"uri": "file:///a.json", "version": 3, "text": text}}
+        });
+        let sent = server.handle(&open);
+        let params = &sent[0]["params"];
+        assert_eq!(params["version"], json!(3));
+        let diagnostics = params["diagnostics"].as_array().unwrap();
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(
+            diagnostics[0]["range"],
+            json!({"start": {"line": 0, "character": 16}, "end": {"line": 0, "character": 17}})
+        );
+        assert_eq!(diagnostics[0]["code"].as_str(), Some("unexpected_token"));
+        assert_eq!(
+            diagnostics[1]["range"]["start"],
+            json!({"line": 1, "character": 7})
+        );
+
+        let change = json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/didChange",
+            "params": {
+                "textDocument": {"uri": "file:///a.json", "version": 4},
+                "contentChanges": [{"text": "[1]"}]
+            }
+        });
+        let sent = server.handle(&change);
+        assert_eq!(sent[0]["params"]["diagnostics"], json!([]));
+        assert_eq!(sent[0]["params"]["version"], json!(4));
+
+        let close = json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/didClose",
+            "params": {"textDocument": {"uri": "file:///a.json"}}
+        });
+        assert_eq!(server.handle(&close)[0]["params"]["diagnostics"], json!([]));
+        assert!(server.documents.source("file:///a.json").is_none());
+
+        let reply = server.handle(&request(2, "textDocument/hover"));
+        assert_eq!(reply[0]["error"]["code"], json!(METHOD_NOT_FOUND));
+        assert!(
+            server
+                .handle(&json!({"jsonrpc": "2.0", "method": "initialized"}))
+                .is_empty()
+        );
+        assert_eq!(
+            server.handle(&request(3, "shutdown"))[0]["result"],
+            JsonValue::Null
+        );
+        server.handle(&json!({"jsonrpc": "2.0", "method": "exit"}));
+        assert_eq!(server.exit_code(), Some(0));
+    }
+
+    #[test]
+    fn test_serve_framing() {
+        let mut input = Vec::new();
+        write_message(&mut input, &request(1, "initialize")).unwrap();
+        input.extend_from_slice(
+            b"Content-Type: application/vscode-jsonrpc\r\ncontent-length: 5\r\n\r\n{oops",
+        );
+        write_message(&mut input, &json!({"jsonrpc": "2.0", "method": "exit"})).unwrap();
+        write_message(&mut input, &request(2, "never read")).unwrap();
+
+        let mut output = Vec::new();
+        assert_eq!(serve(&input[..], &mut output).unwrap(), 1);
+        let mut output = &output[..];
+        let first = parse_json(&read_message(&mut output).unwrap().unwrap()).unwrap();
+        assert_eq!(first["id"], json!(1));
+        let second = parse_json(&read_message(&mut output).unwrap().unwrap()).unwrap();
+        assert_eq!(second["error"]["code"], json!(PARSE_ERROR));
+        assert!(read_message(&mut output).unwrap().is_none());
+
+        let truncated = b"Content-Length: 10\r\n";
+        assert!(read_message(&mut &truncated[..]).is_err());
+        // A huge length is refused instead of allocated
+        let huge = format!("Content-Length: {}\r\n\r\n{{}}", usize::MAX);
+        let error = read_message(&mut huge.as_bytes()).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert!(error.to_string().contains("maximum message size"));
+    }
+}
@@ -0,0 +1,168 @@
+//! The `json!` macro - `JsonValue` literals in JSON syntax
+//!
+//! Building trees through the enum variants gets verbose quickly. `json!`
+//! accepts JSON text with Rust expressions in value and key positions; every
+//! interpolated value is converted with `JsonValue::from`.
+
+/// Build a `JsonValue` from JSON-like syntax
+///
+/// Values can be any expression with a `From` conversion into `JsonValue`
+/// (including `JsonValue` itself); keys can be any expression convertible
+/// into `String`. Keys made of several tokens can be wrapped in parentheses.
+///
+/// # Examples
+///
+/// ```
+/// use json_parser_rs::json;
+///
+/// let name = "Ada";
+/// let value = json!({
+///     "name": name,
+///     "age": 30 + 6,
+///     "tags": [1, 2, 3],
+///     "address": {"city": null, "verified": true},
+///     (format!("{}_id", "user")): 7,
+/// });
+/// assert_eq!(
+///     value.to_json_string(),
+///     r#"{"name": "Ada", "age": 36, "tags": [1, 2, 3], "address": {"city": null, "verified": true}, "user_id": 7}"#
+/// );
+/// ```
+#[macro_export]
+macro_rules! json {
+    ($($json:tt)+) => {
+        $crate::__json_internal!($($json)+)
+    };
+}
+
+#[macro_export]
+#[doc(hidden)]
+macro_rules! __json_internal {
+    // Array elements: `@array [converted elements] remaining tokens`
+
+    (@array [$($elems:expr,)*]) => {
+        vec![$($elems,)*]
+    };
+    (@array [$($elems:expr),*]) => {
+        vec![$($elems),*]
+    };
+    (@array [$($elems:expr,)*] null $($rest:tt)*) => {
+        $crate::__json_internal!(@array [$($elems,)* $crate::__json_internal!(null)] $($rest)*)
+    };
+    (@array [$($elems:expr,)*] true $($rest:tt)*) => {
+        $crate::__json_internal!(@array [$($elems,)* $crate::__json_internal!(true)] $($rest)*)
+    };
+    (@array [$($elems:expr,)*] false $($rest:tt)*) => {
+        $crate::__json_internal!(@array [$($elems,)* $crate::__json_internal!(false)] $($rest)*)
+    };
+    (@array [$($elems:expr,)*] [$($array:tt)*] $($rest:tt)*) => {
+        $crate::__json_internal!(@array [$($elems,)* $crate::__json_internal!([$($array)*])] $($rest)*)
+    };
+    (@array [$($elems:expr,)*] {$($object:tt)*} $($rest:tt)*) => {
+        $crate::__json_internal!(@array [$($elems,)* $crate::__json_internal!({$($object)*})] $($rest)*)
+    };
+    (@array [$($elems:expr,)*] $next:expr, $($rest:tt)*) => {
+        $crate::__json_internal!(@array [$($elems,)* $crate::__json_internal!($next),] $($rest)*)
+    };
+    (@array [$($elems:expr,)*] $last:expr) => {
+        $crate::__json_internal!(@array [$($elems,)* $crate::__json_internal!($last)])
+    };
+    // The comma after an element that was matched as a single token
+    (@array [$($elems:expr),*] , $($rest:tt)*) => {
+        $crate::__json_internal!(@array [$($elems,)*] $($rest)*)
+    };
+
+    // Object members: `@object name (key tokens) (remaining) (remaining)`;
+    // the remaining tokens are passed twice so one copy can be inspected
+    // without consuming the other
+
+    (@object $object:ident () () ()) => {};
+    (@object $object:ident [$($key:tt)+] ($value:expr) , $($rest:tt)*) => {
+        let _ = $object.insert(($($key)+), $value);
+        $crate::__json_internal!(@object $object () ($($rest)*) ($($rest)*));
+    };
+    (@object $object:ident [$($key:tt)+] ($value:expr)) => {
+        let _ = $object.insert(($($key)+), $value);
+    };
+    (@object $object:ident ($($key:tt)+) (: null $($rest:tt)*) $copy:tt) => {
+        $crate::__json_internal!(@object $object [$($key)+] ($crate::__json_internal!(null)) $($rest)*);
+    };
+    (@object $object:ident ($($key:tt)+) (: true $($rest:tt)*) $copy:tt) => {
+        $crate::__json_internal!(@object $object [$($key)+] ($crate::__json_internal!(true)) $($rest)*);
+    };
+    (@object $object:ident ($($key:tt)+) (: false $($rest:tt)*) $copy:tt) => {
+        $crate::__json_internal!(@object $object [$($key)+] ($crate::__json_internal!(false)) $($rest)*);
+    };
+    (@object $object:ident ($($key:tt)+) (: [$($array:tt)*] $($rest:tt)*) $copy:tt) => {
+        $crate::__json_internal!(@object $object [$($key)+] ($crate::__json_internal!([$($array)*])) $($rest)*);
+    };
+    (@object $object:ident ($($key:tt)+) (: {$($map:tt)*} $($rest:tt)*) $copy:tt) => {
+        $crate::__json_internal!(@object $object [$($key)+] ($crate::__json_internal!({$($map)*})) $($rest)*);
+    };
+    (@object $object:ident ($($key:tt)+) (: $value:expr , $($rest:tt)*) $copy:tt) => {
+        $crate::__json_internal!(@object $object [$($key)+] ($crate::__json_internal!($value)) , $($rest)*);
+    };
+    (@object $object:ident ($($key:tt)+) (: $value:expr) $copy:tt) => {
+        $crate::__json_internal!(@object $object [$($key)+] ($crate::__json_internal!($value)));
+    };
+    (@object $object:ident () (($key:expr) : $($rest:tt)*) $copy:tt) => {
+        $crate::__json_internal!(@object $object ($key) (: $($rest)*) (: $($rest)*));
+    };
+    (@object $object:ident ($($key:tt)*) ($tt:tt $($rest:tt)*) $copy:tt) => {
+        $crate::__json_internal!(@object $object ($($key)* $tt) ($($rest)*) ($($rest)*));
+    };
+
+    // Values
+
+    (null) => {
+        $crate::JsonValue::Null
+    };
+    (true) => {
+        $crate::JsonValue::Boolean(true)
+    };
+    (false) => {
+        $crate::JsonValue::Boolean(false)
+    };
+    ([]) => {
+        $crate::JsonValue::Array(vec![])
+    };
+    ([ $($tt:tt)+ ]) => {
+        $crate::JsonValue::Array($crate::__json_internal!(@array [] $($tt)+))
+    };
+    ({}) => {
+        $crate::JsonValue::Object($crate::JsonObject::new())
+    };
+    ({ $($tt:tt)+ }) => {
+        $crate::JsonValue::Object({
+            let mut object = $crate::JsonObject::new();
+            $crate::__json_internal!(@object object () ($($tt)+) ($($tt)+));
+            object
+        })
+    };
+    ($other:expr) => {
+        $crate::JsonValue::from($other)
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{JsonValue, parse_json};
+
+    #[test]
+    fn test_json_macro_matches_parser() {
+        let inner = json!([]);
+        let value = json!({
+            "a": [null, true, false, [], {}, [1, [2]], {"b": -1.5}],
+            "c": inner,
+            "d": "text".to_string(),
+            "e": Some(1).map_or(0, |n| n + 1),
+        });
+        let parsed = parse_json(
+            r#"{"a": [null, true, false, [], {}, [1, [2]], {"b": -1.5}], "c": [], "d": "text", "e": 2}"#,
+        )
+        .unwrap();
+        assert_eq!(value, parsed);
+        assert_eq!(json!(null), JsonValue::Null);
+        assert_eq!(json!([1, 2,]), parse_json("[1, 2]").unwrap());
+    }
+}
@@ -6,53 +6,45 @@
 /// 3. Exit codes following Unix conventions (0 = success, 1-255 = error)
 /// 4. Minimal allocations in hot paths
 use std::env;
-use std::fs;
 use std::process;
 
-use json_parser_rs::parse_json;
+mod cli;
 
 fn main() {
     let args: Vec<String> = env::args().collect();
 
-    if args.len() != 2 {
-        eprintln!("Usage: {} <file_path>", args[0]);
-        process::exit(1);
+    if let Some(code) = cli::run(&args) {
+        process::exit(code);
     }
 
-    let file_path = &args[1];
-
-    // Read file content
-    let content = match fs::read_to_string(file_path) {
-        Ok(content) => content,
-        Err(e) => {
-            eprintln!("Error reading file '{}': {}", file_path, e);
-            process::exit(1);
-        }
+    let Some(code) = cli::validate(&args[1..]) else {
+        eprintln!("Usage: {} [--ndjson] [<file_path>|<glob>|-]... [--output human|json]", args[0]);
+        eprintln!("       {} check --schema <schema_file> <file_path>... [--report human|json]", args[0]);
+        eprintln!("       {} concat <file_path>... [--out <file_path>] [--as-array|--deep-merge]", args[0]);
+        eprintln!("       {} convert <input_file>|- <output_file>|- [--from <format>] [--to <format>]", args[0]);
+        eprintln!("       {} dedupe [<file_path>|-] [--key <pointer>] [--lru <entries>]", args[0]);
+        eprintln!("       {} diff <old_file> <new_file> [--no-color]", args[0]);
+        eprintln!("       {} fmt [<file_path>|-] [--indent <spaces>] [--minify] [--sort-keys] [--in-place]", args[0]);
+        eprintln!("       {} get <pointer-or-path> [<file_path>|-] [--raw]", args[0]);
+        eprintln!("       {} head|tail [<file_path>|-] [-n <count>] [--ndjson]", args[0]);
+        eprintln!("       {} keys [<file_path>|-] [--max-depth <levels>] [--counts]", args[0]);
+        eprintln!("       {} lsp", args[0]);
+        eprintln!("       {} patch <target_file> <patch_file> [--merge-patch] [--in-place] [--dry-run]", args[0]);
+        eprintln!("       {} query <file_path>|- <jsonpath> [--output json|ndjson]", args[0]);
+        eprintln!("       {} repl <file_path>", args[0]);
+        eprintln!("       {} sample [<file_path>|-] --n <records> [--seed <number>]", args[0]);
+        eprintln!("       {} sort [<file_path>|-] --key <pointer> [--reverse] [--chunk <records>]", args[0]);
+        eprintln!("       {} split <file_path> --by <elements> --out <pattern with {{}}>", args[0]);
+        eprintln!("       {} stats [<file_path>|-] [--output human|json]", args[0]);
+        process::exit(1);
     };
-
-    // Parse JSON
-    match parse_json(&content) {
-        Ok(_json_value) => {
-            // For valid JSON, exit with code 0 (success)
-            // Optionally print the parsed value for debugging
-            if env::var("DEBUG").is_ok() {
-                println!("Valid JSON");
-                dbg!(_json_value);
-            }
-            process::exit(0);
-        }
-        Err(e) => {
-            // For invalid JSON, print error and exit with code 1 (error)
-            eprintln!("{}", e);
-            process::exit(1);
-        }
-    }
+    process::exit(code);
 }
 
 #[cfg(test)]
 mod tests {
-    use super::*;
     use json_parser_rs::parse_json;
+    use std::fs;
 
     fn read_file(file_name: &str) -> String {
         fs::read_to_string(file_name).expect("Failed to read test file")
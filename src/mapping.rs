@@ -0,0 +1,604 @@
+//! Mapping - serde-style field attributes for typed extraction, at runtime
+//!
+//! [`FromJson`] converts a [`JsonValue`] into a Rust value. For structs, a
+//! [`Mapping`] describes how fields are found in an object the way serde's
+//! attributes do: `rename_all` and per-field `rename`, aliases, defaults for
+//! missing fields, `flatten` and `deny_unknown_fields`. The mapping is
+//! plain data interpreted when a document is converted, so it needs no
+//! proc macros and can even be built from configuration.
+//!
+//! Errors carry the JSON Pointer of the offending value, built up as the
+//! conversion descends, so nested failures read like
+//! `/servers/1/port: Expected integer, found string`.
+//!
+//! # Examples
+//!
+//! ```
+//! use json_parser_rs::mapping::{Field, FromJson, Mapping, MappingError, RenameRule};
+//! use json_parser_rs::{JsonValue, json};
+//!
+//! struct Server {
+//!     host: String,
+//!     max_connections: u32,
+//!     tags: Vec<String>,
+//! }
+//!
+//! impl FromJson for Server {
+//!     fn from_json(value: &JsonValue) -> Result<Self, MappingError> {
+//!         let fields = Mapping::new()
+//!             .rename_all(RenameRule::CamelCase)
+//!             .field(Field::new("host").alias("hostname"))
+//!             .field(Field::new("max_connections").default_value(json!(100)))
+//!             .field(Field::new("tags").default_value(json!([])))
+//!             .deny_unknown_fields(true)
+//!             .apply(value)?;
+//!         Ok(Server {
+//!             host: fields.get("host")?,
+//!             max_connections: fields.get("max_connections")?,
+//!             tags: fields.get("tags")?,
+//!         })
+//!     }
+//! }
+//!
+//! let server = Server::from_json(&json!({"hostname": "db", "maxConnections": 8})).unwrap();
+//! assert_eq!((server.host.as_str(), server.max_connections), ("db", 8));
+//! assert!(server.tags.is_empty());
+//!
+//! let error = Vec::<Server>::from_json(&json!([{"host": "a", "maxConections": 1}]))
+//!     .err()
+//!     .unwrap();
+//! assert_eq!(
+//!     error.to_string(),
+//!     r#"/0/maxConections: Unknown field "maxConections" (did you mean "maxConnections"?)"#
+//! );
+//! ```
+
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+
+use crate::json::{JsonValue, type_name};
+use crate::object::JsonObject;
+use crate::pointer::JsonPointer;
+use crate::suggest::suggest;
+
+/// Conversion from a JSON value
+pub trait FromJson: Sized {
+    fn from_json(value: &JsonValue) -> Result<Self, MappingError>;
+
+    /// The value of a struct field whose key is absent and has no default;
+    /// `None` makes the field required. `Option<T>` becomes `None`.
+    fn from_missing() -> Option<Self> {
+        None
+    }
+}
+
+/// Why a value could not be converted, and where
+#[derive(Debug, Clone, PartialEq)]
+pub struct MappingError {
+    kind: MappingErrorKind,
+    /// Reference tokens from the innermost value outwards
+    reversed_path: Vec<String>,
+    message: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum MappingErrorKind {
+    /// A required field is absent; the path is the object's
+    MissingField,
+    /// A member that no field maps to, with `deny_unknown_fields`
+    UnknownField,
+    /// The value has another JSON type than the target needs
+    WrongType,
+    /// The type fits but the value does not, e.g. an integer out of range
+    InvalidValue,
+}
+
+impl MappingError {
+    fn new(kind: MappingErrorKind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            reversed_path: Vec::new(),
+            message: message.into(),
+        }
+    }
+
+    /// `Expected <expected>, found <type of value>`
+    pub fn wrong_type(expected: &str, value: &JsonValue) -> Self {
+        let message = format!("Expected {}, found {}", expected, type_name(value));
+        Self::new(MappingErrorKind::WrongType, message)
+    }
+
+    /// An error for values the target rejects, for use in `FromJson` impls
+    pub fn invalid_value(message: impl fmt::Display) -> Self {
+        Self::new(MappingErrorKind::InvalidValue, message.to_string())
+    }
+
+    pub fn kind(&self) -> MappingErrorKind {
+        self.kind
+    }
+
+    /// Location of the offending value in the converted document
+    pub fn path(&self) -> JsonPointer {
+        JsonPointer::from_tokens(self.reversed_path.iter().rev())
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Prefix the path with the key or index the value was found under
+    fn within(mut self, token: impl ToString) -> Self {
+        self.reversed_path.push(token.to_string());
+        self
+    }
+}
+
+/// `/path: message`, with `(root)` for the document itself
+impl fmt::Display for MappingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let path = self.path().to_string();
+        let path = if path.is_empty() { "(root)" } else { &path };
+        write!(f, "{}: {}", path, self.message)
+    }
+}
+
+impl std::error::Error for MappingError {}
+
+/// How field names written in `snake_case` become JSON keys
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum RenameRule {
+    /// `maxconnections`
+    LowerCase,
+    /// `MAXCONNECTIONS`
+    UpperCase,
+    /// `maxConnections`
+    CamelCase,
+    /// `MaxConnections`
+    PascalCase,
+    /// `max_connections`
+    SnakeCase,
+    /// `MAX_CONNECTIONS`
+    ScreamingSnakeCase,
+    /// `max-connections`
+    KebabCase,
+    /// `MAX-CONNECTIONS`
+    ScreamingKebabCase,
+}
+
+impl RenameRule {
+    /// The key for the field `name`
+    pub fn apply(self, name: &str) -> String {
+        let capitalize = |word: &str| {
+            let mut chars = word.chars();
+            chars.next().map_or(String::new(), |first| {
+                first.to_uppercase().chain(chars).collect()
+            })
+        };
+        match self {
+            RenameRule::LowerCase => name.replace('_', "").to_lowercase(),
+            RenameRule::UpperCase => name.replace('_', "").to_uppercase(),
+            RenameRule::CamelCase => {
+                let mut words = name.split('_');
+                let first = words.next().unwrap_or_default().to_string();
+                first + &words.map(capitalize).collect::<String>()
+            }
+            RenameRule::PascalCase => name.split('_').map(capitalize).collect(),
+            RenameRule::SnakeCase => name.to_string(),
+            RenameRule::ScreamingSnakeCase => name.to_uppercase(),
+            RenameRule::KebabCase => name.replace('_', "-"),
+            RenameRule::ScreamingKebabCase => name.replace('_', "-").to_uppercase(),
+        }
+    }
+}
+
+/// One struct field of a [`Mapping`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Field {
+    name: String,
+    rename: Option<String>,
+    aliases: Vec<String>,
+    default: Option<JsonValue>,
+    flatten: bool,
+}
+
+impl Field {
+    /// A field read from the key `name`, subject to the mapping's
+    /// `rename_all`
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            rename: None,
+            aliases: Vec::new(),
+            default: None,
+            flatten: false,
+        }
+    }
+
+    /// Read the field from exactly `key`
+    pub fn rename(mut self, key: impl Into<String>) -> Self {
+        self.rename = Some(key.into());
+        self
+    }
+
+    /// Also accept `key`, if the primary key is absent
+    pub fn alias(mut self, key: impl Into<String>) -> Self {
+        self.aliases.push(key.into());
+        self
+    }
+
+    /// Value used when the key is absent
+    pub fn default_value(mut self, value: JsonValue) -> Self {
+        self.default = Some(value);
+        self
+    }
+
+    /// Read the field from the enclosing object itself: it receives an
+    /// object of all members no other field maps to
+    pub fn flatten(mut self, flatten: bool) -> Self {
+        self.flatten = flatten;
+        self
+    }
+}
+
+/// How the fields of a struct are found in a JSON object
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Mapping {
+    fields: Vec<Field>,
+    rename_all: Option<RenameRule>,
+    deny_unknown_fields: bool,
+}
+
+impl Mapping {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn field(mut self, field: Field) -> Self {
+        self.fields.push(field);
+        self
+    }
+
+    /// Derive keys from field names with `rule`, unless renamed
+    pub fn rename_all(mut self, rule: RenameRule) -> Self {
+        self.rename_all = Some(rule);
+        self
+    }
+
+    /// Reject members that no field maps to
+    ///
+    /// Has no effect if a field is flattened, since that field takes every
+    /// remaining member.
+    pub fn deny_unknown_fields(mut self, deny: bool) -> Self {
+        self.deny_unknown_fields = deny;
+        self
+    }
+
+    /// The key a field is read from
+    pub fn key(&self, field: &Field) -> String {
+        match (&field.rename, self.rename_all) {
+            (Some(key), _) => key.clone(),
+            (None, Some(rule)) => rule.apply(&field.name),
+            (None, None) => field.name.clone(),
+        }
+    }
+
+    /// Find every field's value in `value`, which must be an object
+    pub fn apply<'a>(&self, value: &'a JsonValue) -> Result<MappedFields<'a>, MappingError> {
+        let Some(object) = value.untagged().as_object() else {
+            return Err(MappingError::wrong_type("object", value));
+        };
+        let mut claimed = HashSet::new();
+        let mut fields = Vec::with_capacity(self.fields.len());
+        for field in self.fields.iter().filter(|field| !field.flatten) {
+            let key = self.key(field);
+            let found = std::iter::once(&key)
+                .chain(&field.aliases)
+                .find_map(|key| Some((key.clone(), object.get(key)?)));
+            let value = match found {
+                Some((key, value)) => {
+                    claimed.insert(key.clone());
+                    Some((key, Cow::Borrowed(value)))
+                }
+                None => field
+                    .default
+                    .clone()
+                    .map(|value| (key.clone(), Cow::Owned(value))),
+            };
+            fields.push(MappedField {
+                name: field.name.clone(),
+                key,
+                value,
+            });
+        }
+
+        let remaining = || object.iter().filter(|(key, _)| !claimed.contains(*key));
+        let flattened: Vec<&Field> = self.fields.iter().filter(|field| field.flatten).collect();
+        if flattened.is_empty()
+            && self.deny_unknown_fields
+            && let Some((key, _)) = remaining().next()
+        {
+            return Err(self.unknown_field(key).within(key));
+        }
+        for field in flattened {
+            let rest: JsonObject = remaining()
+                .map(|(key, value)| (key.clone(), value.clone()))
+                .collect();
+            fields.push(MappedField {
+                name: field.name.clone(),
+                key: String::new(),
+                value: Some((String::new(), Cow::Owned(JsonValue::Object(rest)))),
+            });
+        }
+        Ok(MappedFields { fields })
+    }
+
+    fn unknown_field(&self, key: &str) -> MappingError {
+        let keys: Vec<String> = self.fields.iter().map(|field| self.key(field)).collect();
+        let quoted = |keys: &[&str]| {
+            let keys: Vec<String> = keys.iter().map(|key| format!("{:?}", key)).collect();
+            keys
+        };
+        let suggestions = suggest(key, keys.iter().map(String::as_str));
+        let message = if !suggestions.is_empty() {
+            let suggestions = quoted(&suggestions).join(" or ");
+            format!("Unknown field {:?} (did you mean {}?)", key, suggestions)
+        } else {
+            let keys: Vec<&str> = keys.iter().map(String::as_str).collect();
+            let expected = quoted(&keys).join(", ");
+            format!("Unknown field {:?}, expected one of {}", key, expected)
+        };
+        MappingError::new(MappingErrorKind::UnknownField, message)
+    }
+}
+
+/// The values a [`Mapping`] found, ready for conversion field by field
+#[derive(Debug, Clone)]
+pub struct MappedFields<'a> {
+    fields: Vec<MappedField<'a>>,
+}
+
+#[derive(Debug, Clone)]
+struct MappedField<'a> {
+    name: String,
+    /// The primary key, for errors about the field being absent
+    key: String,
+    /// The key the value was found under (empty if flattened) and the
+    /// value, or the default
+    value: Option<(String, Cow<'a, JsonValue>)>,
+}
+
+impl MappedFields<'_> {
+    /// Convert the field `name`
+    ///
+    /// An absent field without default is `T::from_missing()`, or an error
+    /// of kind [`MappingErrorKind::MissingField`].
+    ///
+    /// # Panics
+    ///
+    /// If the mapping has no field `name`.
+    pub fn get<T: FromJson>(&self, name: &str) -> Result<T, MappingError> {
+        let field = self.field(name);
+        match &field.value {
+            Some((key, value)) if key.is_empty() => T::from_json(value),
+            Some((key, value)) => T::from_json(value).map_err(|e| e.within(key)),
+            None => T::from_missing().ok_or_else(|| {
+                let message = format!("Missing field {:?}", field.key);
+                MappingError::new(MappingErrorKind::MissingField, message)
+            }),
+        }
+    }
+
+    /// The raw value of the field `name`, if present or defaulted
+    ///
+    /// # Panics
+    ///
+    /// If the mapping has no field `name`.
+    pub fn value(&self, name: &str) -> Option<&JsonValue> {
+        self.field(name).value.as_ref().map(|(_, value)| &**value)
+    }
+
+    fn field(&self, name: &str) -> &MappedField<'_> {
+        self.fields
+            .iter()
+            .find(|field| field.name == name)
+            .unwrap_or_else(|| panic!("no field {:?} in the mapping", name))
+    }
+}
+
+impl FromJson for JsonValue {
+    fn from_json(value: &JsonValue) -> Result<Self, MappingError> {
+        Ok(value.clone())
+    }
+}
+
+impl FromJson for bool {
+    fn from_json(value: &JsonValue) -> Result<Self, MappingError> {
+        value
+            .as_bool()
+            .ok_or_else(|| MappingError::wrong_type("boolean", value))
+    }
+}
+
+impl FromJson for String {
+    fn from_json(value: &JsonValue) -> Result<Self, MappingError> {
+        value
+            .as_str()
+            .map(String::from)
+            .ok_or_else(|| MappingError::wrong_type("string", value))
+    }
+}
+
+impl FromJson for f64 {
+    fn from_json(value: &JsonValue) -> Result<Self, MappingError> {
+        value
+            .as_f64()
+            .ok_or_else(|| MappingError::wrong_type("number", value))
+    }
+}
+
+macro_rules! impl_from_json_for_integer {
+    ($($ty:ty),*) => {
+        $(
+            impl FromJson for $ty {
+                fn from_json(value: &JsonValue) -> Result<Self, MappingError> {
+                    let n = value
+                        .as_number()
+                        .filter(|n| n.as_i64().is_some() || n.as_u64().is_some())
+                        .ok_or_else(|| MappingError::wrong_type("integer", value))?;
+                    let converted = match n.as_i64() {
+                        Some(n) => <$ty>::try_from(n).ok(),
+                        None => n.as_u64().and_then(|n| <$ty>::try_from(n).ok()),
+                    };
+                    converted.ok_or_else(|| {
+                        MappingError::invalid_value(format!(
+                            "Integer {} out of range for {}",
+                            n,
+                            stringify!($ty)
+                        ))
+                    })
+                }
+            }
+        )*
+    };
+}
+
+impl_from_json_for_integer!(i8, i16, i32, i64, isize, u8, u16, u32, u64, usize);
+
+impl<T: FromJson> FromJson for Option<T> {
+    fn from_json(value: &JsonValue) -> Result<Self, MappingError> {
+        match value {
+            JsonValue::Null => Ok(None),
+            value => T::from_json(value).map(Some),
+        }
+    }
+
+    fn from_missing() -> Option<Self> {
+        Some(None)
+    }
+}
+
+impl<T: FromJson> FromJson for Vec<T> {
+    fn from_json(value: &JsonValue) -> Result<Self, MappingError> {
+        let elements = value
+            .as_array()
+            .ok_or_else(|| MappingError::wrong_type("array", value))?;
+        elements
+            .iter()
+            .enumerate()
+            .map(|(index, element)| T::from_json(element).map_err(|e| e.within(index)))
+            .collect()
+    }
+}
+
+impl<T: FromJson> FromJson for BTreeMap<String, T> {
+    fn from_json(value: &JsonValue) -> Result<Self, MappingError> {
+        map_entries(value)
+    }
+}
+
+impl<T: FromJson> FromJson for HashMap<String, T> {
+    fn from_json(value: &JsonValue) -> Result<Self, MappingError> {
+        map_entries(value)
+    }
+}
+
+/// The members of an object converted to `T`, collected into a map
+fn map_entries<T, M>(value: &JsonValue) -> Result<M, MappingError>
+where
+    T: FromJson,
+    M: FromIterator<(String, T)>,
+{
+    let object = value
+        .as_object()
+        .ok_or_else(|| MappingError::wrong_type("object", value))?;
+    object
+        .iter()
+        .map(|(key, value)| {
+            let value = T::from_json(value).map_err(|e| e.within(key))?;
+            Ok((key.clone(), value))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json;
+
+    #[test]
+    fn test_rename_rules() {
+        let cases = [
+            (RenameRule::LowerCase, "maxconnections"),
+            (RenameRule::UpperCase, "MAXCONNECTIONS"),
+            (RenameRule::CamelCase, "maxConnections"),
+            (RenameRule::PascalCase, "MaxConnections"),
+            (RenameRule::SnakeCase, "max_connections"),
+            (RenameRule::ScreamingSnakeCase, "MAX_CONNECTIONS"),
+            (RenameRule::KebabCase, "max-connections"),
+            (RenameRule::ScreamingKebabCase, "MAX-CONNECTIONS"),
+        ];
+        for (rule, key) in cases {
+            assert_eq!(rule.apply("max_connections"), key, "{:?}", rule);
+        }
+        assert_eq!(RenameRule::CamelCase.apply("id"), "id");
+    }
+
+    #[test]
+    fn test_defaults_flatten_and_unknown_fields() {
+        let mapping = Mapping::new()
+            .field(Field::new("name").rename("Name"))
+            .field(Field::new("retries").default_value(json!(3)))
+            .field(Field::new("comment"))
+            .field(Field::new("extra").flatten(true));
+        let document = json!({"Name": "job", "owner": "ops", "labels": {"tier": "1"}});
+        let fields = mapping.apply(&document).unwrap();
+        assert_eq!(fields.get::<String>("name").unwrap(), "job");
+        assert_eq!(fields.get::<u8>("retries").unwrap(), 3);
+        assert_eq!(fields.get::<Option<String>>("comment").unwrap(), None);
+        let extra: BTreeMap<String, JsonValue> = fields.get("extra").unwrap();
+        assert_eq!(extra.keys().collect::<Vec<_>>(), ["labels", "owner"]);
+
+        let error = fields.get::<String>("comment").unwrap_err();
+        assert_eq!(error.kind(), MappingErrorKind::MissingField);
+        assert_eq!(error.to_string(), r#"(root): Missing field "comment""#);
+
+        let strict = Mapping::new()
+            .field(Field::new("name"))
+            .field(Field::new("owner"))
+            .deny_unknown_fields(true);
+        let error = strict.apply(&json!({"name": 1, "tier": 2})).unwrap_err();
+        assert_eq!(error.kind(), MappingErrorKind::UnknownField);
+        assert_eq!(
+            error.to_string(),
+            r#"/tier: Unknown field "tier", expected one of "name", "owner""#
+        );
+        assert!(strict.apply(&json!({"name": 1})).is_ok());
+        assert_eq!(
+            strict.apply(&json!([])).unwrap_err().to_string(),
+            "(root): Expected object, found array"
+        );
+    }
+
+    #[test]
+    fn test_error_paths_and_ranges() {
+        let value = json!({"a": [1, 2, 300], "b": [true, "x"]});
+        let error = BTreeMap::<String, Vec<u8>>::from_json(&value).unwrap_err();
+        assert_eq!(error.kind(), MappingErrorKind::InvalidValue);
+        assert_eq!(error.path().to_string(), "/a/2");
+        assert_eq!(error.message(), "Integer 300 out of range for u8");
+
+        let error = HashMap::<String, Vec<bool>>::from_json(&value).unwrap_err();
+        assert_eq!(error.to_string(), "/a/0: Expected boolean, found integer");
+
+        assert_eq!(i64::from_json(&json!(-5)), Ok(-5));
+        assert_eq!(u64::from_json(&json!(u64::MAX)), Ok(u64::MAX));
+        assert_eq!(
+            i32::from_json(&json!(1.5)).unwrap_err().kind(),
+            MappingErrorKind::WrongType
+        );
+        assert_eq!(Option::<f64>::from_json(&JsonValue::Null), Ok(None));
+    }
+}
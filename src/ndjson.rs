@@ -0,0 +1,91 @@
+//! NDJSON / JSON Lines - one JSON document per line
+//!
+//! Log pipelines commonly emit newline-delimited records. `parse_ndjson`
+//! parses them one line at a time; errors are reported with positions in
+//! the whole input (not the line), so they point at the right place in the
+//! file. With the `zstd` feature, the `zstd` module compresses single records
+//! against a trained dictionary.
+
+#[cfg(feature = "zstd")]
+pub mod zstd;
+
+use crate::error::ParseError;
+use crate::json::JsonValue;
+use crate::parse_json;
+
+/// Iterate over the records of a newline-delimited JSON document
+///
+/// Blank lines (only whitespace) are skipped. A malformed record yields an
+/// error and iteration continues with the next line.
+///
+/// # Examples
+///
+/// ```
+/// use json_parser_rs::parse_ndjson;
+///
+/// let input = "{\"level\": \"info\"}\n\n{\"level\": oops}\n[1]\n";
+/// let records: Vec<_> = parse_ndjson(input).collect();
+/// assert_eq!(records.len(), 3);
+/// assert!(records[0].is_ok());
+/// assert_eq!(records[1].as_ref().unwrap_err().line(), 3);
+/// assert!(records[2].is_ok());
+/// ```
+pub fn parse_ndjson(input: &str) -> NdjsonRecords<'_> {
+    NdjsonRecords { input, offset: 0 }
+}
+
+/// Iterator returned by [`parse_ndjson`]
+#[derive(Debug, Clone)]
+pub struct NdjsonRecords<'a> {
+    input: &'a str,
+    /// Byte offset of the next unread line
+    offset: usize,
+}
+
+impl Iterator for NdjsonRecords<'_> {
+    type Item = Result<JsonValue, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.offset < self.input.len() {
+            let start = self.offset;
+            let rest = &self.input[start..];
+            let line = match rest.find('\n') {
+                Some(end) => {
+                    self.offset += end + 1;
+                    &rest[..end]
+                }
+                None => {
+                    self.offset = self.input.len();
+                    rest
+                }
+            };
+            if line.trim().is_empty() {
+                continue;
+            }
+            return Some(parse_json(line).map_err(|error| error.relocate(start, self.input)));
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ndjson_positions_are_file_relative() {
+        let input = "1\r\n\"two\"\n  \n{\"x\": }\n[3]";
+        let records: Vec<_> = parse_ndjson(input).collect();
+        assert_eq!(records.len(), 4);
+        assert_eq!(records[0].as_ref().unwrap().as_u64(), Some(1));
+        assert_eq!(records[1].as_ref().unwrap().as_str(), Some("two"));
+        let error = records[2].as_ref().unwrap_err();
+        let standalone = parse_json("{\"x\": }").unwrap_err();
+        assert_eq!(error.line(), 4);
+        assert_eq!(error.column(), standalone.column());
+        assert_eq!(error.position(), 12 + standalone.position());
+        assert_eq!(records[3].as_ref().unwrap().to_json_string(), "[3]");
+
+        assert_eq!(parse_ndjson("").count(), 0);
+    }
+}
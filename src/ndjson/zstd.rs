@@ -0,0 +1,292 @@
+//! Zstandard compression of single NDJSON records with a shared dictionary
+//!
+//! Log lines and events are too short to compress well one at a time: each
+//! record spells out the same field names and common values again. A
+//! dictionary trained on sample records holds those shared parts once, so
+//! every record can still be compressed on its own and read back without
+//! its neighbours, for storage in database rows, cache entries or queue
+//! messages. Writer and reader need the same dictionary.
+//!
+//! # Examples
+//!
+//! ```
+//! use json_parser_rs::ndjson::zstd::{RecordCompressor, RecordDecompressor, train_dictionary};
+//! use json_parser_rs::json;
+//!
+//! let samples: Vec<_> = (0..500)
+//!     .map(|i| json!({"level": "info", "service": "checkout", "request": i, "status": 200}))
+//!     .collect();
+//! let dictionary = train_dictionary(&samples, 4096).unwrap();
+//!
+//! let mut compressor = RecordCompressor::new(&dictionary, 3).unwrap();
+//! let record = json!({"level": "warn", "service": "checkout", "request": 9001, "status": 503});
+//! let compressed = compressor.compress(&record).unwrap();
+//! assert!(compressed.len() < record.to_json_string().len());
+//!
+//! let mut decompressor = RecordDecompressor::new(&dictionary).unwrap();
+//! assert_eq!(decompressor.decompress(&compressed).unwrap(), record);
+//! ```
+
+use std::fmt;
+
+use zstd::bulk::{Compressor, Decompressor};
+
+use crate::json::JsonValue;
+use crate::ndjson::parse_ndjson;
+use crate::parse_json;
+
+/// Why records could not be compressed or restored
+#[derive(Debug)]
+pub struct CompressionError {
+    kind: CompressionErrorKind,
+    message: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum CompressionErrorKind {
+    /// The samples are unusable for training, the dictionary is invalid or
+    /// zstd failed to compress
+    Dictionary,
+    /// NDJSON input holds a malformed record
+    Parse,
+    /// Data is not a zstd frame made with this dictionary
+    Corrupt,
+    /// A record decompressed fine but is not valid JSON
+    InvalidRecord,
+}
+
+impl CompressionError {
+    fn new(kind: CompressionErrorKind, message: impl fmt::Display) -> Self {
+        Self {
+            kind,
+            message: message.to_string(),
+        }
+    }
+
+    pub fn kind(&self) -> CompressionErrorKind {
+        self.kind
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl fmt::Display for CompressionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for CompressionError {}
+
+/// Train a dictionary of at most `max_size` bytes on sample records
+///
+/// Samples are encoded as minified JSON, the form [`RecordCompressor`]
+/// compresses. Training needs a reasonable number of samples (hundreds);
+/// with too few zstd gives up and an error of kind
+/// [`CompressionErrorKind::Dictionary`] is returned.
+pub fn train_dictionary(
+    samples: &[JsonValue],
+    max_size: usize,
+) -> Result<Vec<u8>, CompressionError> {
+    let samples: Vec<String> = samples.iter().map(JsonValue::to_json_string).collect();
+    zstd::dict::from_samples(&samples, max_size).map_err(|e| {
+        CompressionError::new(
+            CompressionErrorKind::Dictionary,
+            format!("Dictionary training failed: {}", e),
+        )
+    })
+}
+
+/// [`train_dictionary`] on the records of an NDJSON document
+pub fn train_dictionary_from_ndjson(
+    input: &str,
+    max_size: usize,
+) -> Result<Vec<u8>, CompressionError> {
+    let samples = parse_ndjson(input)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| CompressionError::new(CompressionErrorKind::Parse, e))?;
+    train_dictionary(&samples, max_size)
+}
+
+/// Compresses records one by one with a dictionary
+pub struct RecordCompressor {
+    compressor: Compressor<'static>,
+}
+
+impl RecordCompressor {
+    /// Compressor using `dictionary` at zstd `level` (1-22, 3 is zstd's
+    /// default)
+    pub fn new(dictionary: &[u8], level: i32) -> Result<Self, CompressionError> {
+        let compressor = Compressor::with_dictionary(level, dictionary).map_err(|e| {
+            CompressionError::new(
+                CompressionErrorKind::Dictionary,
+                format!("Invalid dictionary: {}", e),
+            )
+        })?;
+        Ok(Self { compressor })
+    }
+
+    /// One zstd frame holding the minified JSON text of `record`
+    pub fn compress(&mut self, record: &JsonValue) -> Result<Vec<u8>, CompressionError> {
+        self.compressor
+            .compress(record.to_json_string().as_bytes())
+            .map_err(|e| {
+                CompressionError::new(
+                    CompressionErrorKind::Dictionary,
+                    format!("Compression failed: {}", e),
+                )
+            })
+    }
+
+    /// Compress every record of an NDJSON document, skipping blank lines
+    ///
+    /// A malformed line fails the whole call with its position in `input`.
+    pub fn compress_ndjson(&mut self, input: &str) -> Result<Vec<Vec<u8>>, CompressionError> {
+        parse_ndjson(input)
+            .map(|record| {
+                let record =
+                    record.map_err(|e| CompressionError::new(CompressionErrorKind::Parse, e))?;
+                self.compress(&record)
+            })
+            .collect()
+    }
+}
+
+/// Restores records made by a [`RecordCompressor`] with the same dictionary
+pub struct RecordDecompressor {
+    decompressor: Decompressor<'static>,
+}
+
+impl RecordDecompressor {
+    pub fn new(dictionary: &[u8]) -> Result<Self, CompressionError> {
+        let decompressor = Decompressor::with_dictionary(dictionary).map_err(|e| {
+            CompressionError::new(
+                CompressionErrorKind::Dictionary,
+                format!("Invalid dictionary: {}", e),
+            )
+        })?;
+        Ok(Self { decompressor })
+    }
+
+    pub fn decompress(&mut self, data: &[u8]) -> Result<JsonValue, CompressionError> {
+        let text = self.decompress_text(data)?;
+        parse_json(&text).map_err(|e| {
+            CompressionError::new(
+                CompressionErrorKind::InvalidRecord,
+                format!("Invalid record: {}", e),
+            )
+        })
+    }
+
+    /// Decompress `records` into an NDJSON document, one line per record
+    pub fn decompress_to_ndjson<I>(&mut self, records: I) -> Result<String, CompressionError>
+    where
+        I: IntoIterator,
+        I::Item: AsRef<[u8]>,
+    {
+        let mut output = String::new();
+        for record in records {
+            output.push_str(&self.decompress(record.as_ref())?.to_json_string());
+            output.push('\n');
+        }
+        Ok(output)
+    }
+
+    fn decompress_text(&mut self, data: &[u8]) -> Result<String, CompressionError> {
+        let corrupt =
+            |message: String| CompressionError::new(CompressionErrorKind::Corrupt, message);
+        // Frames from `Compressor` always record their decompressed size
+        let capacity = Decompressor::upper_bound(data)
+            .ok_or_else(|| corrupt("Not a zstd frame with a known size".to_string()))?;
+        let bytes = self
+            .decompressor
+            .decompress(data, capacity)
+            .map_err(|e| corrupt(format!("Decompression failed: {}", e)))?;
+        String::from_utf8(bytes).map_err(|_| {
+            CompressionError::new(
+                CompressionErrorKind::InvalidRecord,
+                "Invalid record: not UTF-8",
+            )
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json;
+
+    fn samples() -> Vec<JsonValue> {
+        (0..300)
+            .map(|i| {
+                json!({
+                    "timestamp": format!("2024-05-01T12:{:02}:{:02}Z", i / 60, i % 60),
+                    "level": if i % 7 == 0 { "error" } else { "info" },
+                    "service": "payments",
+                    "message": "request handled",
+                    "duration_ms": i * 3 % 250
+                })
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_dictionary_round_trip() {
+        let records = samples();
+        let dictionary = train_dictionary(&records, 2048).unwrap();
+        let mut compressor = RecordCompressor::new(&dictionary, 3).unwrap();
+        let mut decompressor = RecordDecompressor::new(&dictionary).unwrap();
+
+        let record = &records[42];
+        let compressed = compressor.compress(record).unwrap();
+        let plain = zstd::bulk::compress(record.to_json_string().as_bytes(), 3).unwrap();
+        assert!(compressed.len() < plain.len());
+        assert_eq!(&decompressor.decompress(&compressed).unwrap(), record);
+
+        let input: String = records[..5]
+            .iter()
+            .map(|record| record.to_json_string() + "\n\n")
+            .collect();
+        let frames = compressor.compress_ndjson(&input).unwrap();
+        assert_eq!(frames.len(), 5);
+        assert_eq!(
+            decompressor.decompress_to_ndjson(&frames).unwrap(),
+            input.replace("\n\n", "\n")
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        let dictionary = train_dictionary_from_ndjson(
+            &samples()
+                .iter()
+                .map(|r| r.to_json_string() + "\n")
+                .collect::<String>(),
+            2048,
+        )
+        .unwrap();
+        let mut compressor = RecordCompressor::new(&dictionary, 3).unwrap();
+        let mut decompressor = RecordDecompressor::new(&dictionary).unwrap();
+
+        let error = compressor.compress_ndjson("{}\n{\"a\": }\n").unwrap_err();
+        assert_eq!(error.kind(), CompressionErrorKind::Parse);
+        assert_eq!(
+            train_dictionary_from_ndjson("[", 2048).unwrap_err().kind(),
+            CompressionErrorKind::Parse
+        );
+
+        let error = decompressor.decompress(b"not zstd").unwrap_err();
+        assert_eq!(error.kind(), CompressionErrorKind::Corrupt);
+
+        let frame = zstd::bulk::Compressor::with_dictionary(3, &dictionary)
+            .unwrap()
+            .compress(b"{\"a\": tru")
+            .unwrap();
+        let error = decompressor.decompress(&frame).unwrap_err();
+        assert_eq!(error.kind(), CompressionErrorKind::InvalidRecord);
+        assert!(error.message().starts_with("Invalid record: "));
+    }
+}
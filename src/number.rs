@@ -0,0 +1,181 @@
+//! JSON number representation
+//!
+//! JSON itself does not limit number precision, but storing everything as
+//! `f64` silently rounds integers above 2^53 (`9007199254740993` would come
+//! back as `9007199254740992`). Integers are therefore kept as `u64`/`i64`
+//! whenever they fit, and only numbers with a fraction or exponent (or
+//! integers outside the 64-bit range) become floats.
+
+use std::fmt;
+
+/// A JSON number: unsigned integer, negative integer or float
+#[derive(Clone, Copy, PartialEq)]
+pub struct JsonNumber(N);
+
+#[derive(Clone, Copy, PartialEq)]
+enum N {
+    PosInt(u64),
+    /// Always less than zero
+    NegInt(i64),
+    /// Always finite
+    Float(f64),
+}
+
+impl JsonNumber {
+    /// Float number; `None` for NaN and infinities, which JSON cannot express
+    pub fn from_f64(f: f64) -> Option<Self> {
+        f.is_finite().then_some(JsonNumber(N::Float(f)))
+    }
+
+    /// Parse the text of a JSON number literal, keeping integers exact
+    pub(crate) fn from_literal(literal: &str) -> Option<Self> {
+        let is_integer = !literal.contains(['.', 'e', 'E']);
+        if is_integer {
+            if let Ok(n) = literal.parse::<u64>() {
+                return Some(n.into());
+            }
+            if let Ok(n) = literal.parse::<i64>() {
+                return Some(n.into());
+            }
+        }
+        literal.parse::<f64>().ok().and_then(Self::from_f64)
+    }
+
+    pub fn is_i64(&self) -> bool {
+        match self.0 {
+            N::PosInt(n) => n <= i64::MAX as u64,
+            N::NegInt(_) => true,
+            N::Float(_) => false,
+        }
+    }
+
+    pub fn is_u64(&self) -> bool {
+        matches!(self.0, N::PosInt(_))
+    }
+
+    pub fn is_f64(&self) -> bool {
+        matches!(self.0, N::Float(_))
+    }
+
+    /// Value as `i64`; floats qualify only if they are integral and in range
+    pub fn as_i64(&self) -> Option<i64> {
+        match self.0 {
+            N::PosInt(n) => i64::try_from(n).ok(),
+            N::NegInt(n) => Some(n),
+            N::Float(f) if f.fract() == 0.0 && f >= i64::MIN as f64 && f < i64::MAX as f64 => {
+                Some(f as i64)
+            }
+            N::Float(_) => None,
+        }
+    }
+
+    /// Value as `u64`; floats qualify only if they are integral and in range
+    pub fn as_u64(&self) -> Option<u64> {
+        match self.0 {
+            N::PosInt(n) => Some(n),
+            N::NegInt(_) => None,
+            N::Float(f) if f.fract() == 0.0 && f >= 0.0 && f < u64::MAX as f64 => Some(f as u64),
+            N::Float(_) => None,
+        }
+    }
+
+    /// Value as `f64` (may round large integers)
+    pub fn as_f64(&self) -> f64 {
+        match self.0 {
+            N::PosInt(n) => n as f64,
+            N::NegInt(n) => n as f64,
+            N::Float(f) => f,
+        }
+    }
+}
+
+impl From<u64> for JsonNumber {
+    fn from(n: u64) -> Self {
+        JsonNumber(N::PosInt(n))
+    }
+}
+
+impl From<i64> for JsonNumber {
+    fn from(n: i64) -> Self {
+        if n < 0 {
+            JsonNumber(N::NegInt(n))
+        } else {
+            JsonNumber(N::PosInt(n as u64))
+        }
+    }
+}
+
+macro_rules! impl_from_small_int {
+    ($($ty:ty => $via:ty),*) => {
+        $(
+            impl From<$ty> for JsonNumber {
+                fn from(n: $ty) -> Self {
+                    <$via>::from(n).into()
+                }
+            }
+        )*
+    };
+}
+
+impl_from_small_int!(u8 => u64, u16 => u64, u32 => u64, i8 => i64, i16 => i64, i32 => i64);
+
+/// Integers print exactly; floats print the shortest text that parses back to
+/// the same `f64` and always contain a `.` or exponent, so they stay floats
+impl fmt::Display for JsonNumber {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            N::PosInt(n) => write!(f, "{}", n),
+            N::NegInt(n) => write!(f, "{}", n),
+            N::Float(x) => {
+                let magnitude = x.abs();
+                if magnitude != 0.0 && !(1e-6..1e16).contains(&magnitude) {
+                    write!(f, "{:e}", x)
+                } else if x.fract() == 0.0 {
+                    write!(f, "{:.1}", x)
+                } else {
+                    write!(f, "{}", x)
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Debug for JsonNumber {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Number({})", self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_large_integers_stay_exact() {
+        let n = JsonNumber::from_literal("9007199254740993").unwrap();
+        assert_eq!(n.as_u64(), Some(9007199254740993));
+        assert_eq!(n.to_string(), "9007199254740993");
+
+        let n = JsonNumber::from_literal("-9223372036854775808").unwrap();
+        assert_eq!(n.as_i64(), Some(i64::MIN));
+
+        let n = JsonNumber::from_literal("18446744073709551616").unwrap();
+        assert!(n.is_f64());
+    }
+
+    #[test]
+    fn test_float_display_round_trips() {
+        for literal in ["1.5", "-0.25", "1e300", "2.5e-7", "3.0", "1e16"] {
+            let n = JsonNumber::from_literal(literal).unwrap();
+            let printed = n.to_string();
+            assert_eq!(JsonNumber::from_literal(&printed), Some(n), "{}", printed);
+            assert!(printed.contains(['.', 'e']), "{}", printed);
+        }
+    }
+
+    #[test]
+    fn test_non_finite_floats_rejected() {
+        assert!(JsonNumber::from_f64(f64::NAN).is_none());
+        assert!(JsonNumber::from_f64(f64::INFINITY).is_none());
+    }
+}
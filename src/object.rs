@@ -0,0 +1,362 @@
+//! JSON object representation
+//!
+//! Objects keep their members in insertion order (so serializing a parsed
+//! document reproduces the original member order) while a hash index on the
+//! side gives O(1) key lookup. Keys are unique: inserting an existing key
+//! replaces the value in place.
+//!
+//! Order contract, which every method of `JsonObject` upholds:
+//! - iteration (`iter`, `keys`, `values`, `into_iter`, serialization) visits
+//!   members in their current order;
+//! - a new key is appended at the end, replacing a value keeps its position;
+//! - `remove` and `retain` keep the relative order of the remaining members;
+//! - only the explicit reordering methods (`sort_unstable_by_key`, `reverse`)
+//!   change the order.
+
+use std::collections::HashMap;
+
+use crate::json::JsonValue;
+
+/// Insertion-ordered map from keys to JSON values
+///
+/// Members are visited in the order they were first inserted. The members
+/// are private, so the order contract in the module docs cannot be broken
+/// from outside: new keys are appended, replaced values keep their place,
+/// removals keep the order of the rest, and only `sort_unstable_by_key` and
+/// `reverse` reorder.
+#[derive(Clone, Default)]
+pub struct JsonObject {
+    entries: Vec<(String, JsonValue)>,
+    index: HashMap<String, usize>,
+}
+
+/// What the parser does when an object repeats a key
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DuplicateKeyPolicy {
+    /// Reject the document with a parse error
+    Error,
+    /// Keep the first occurrence and ignore later ones
+    First,
+    /// Later occurrences overwrite earlier ones (the common behavior of
+    /// JavaScript and most JSON libraries)
+    #[default]
+    Last,
+}
+
+impl JsonObject {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            entries: Vec::with_capacity(capacity),
+            index: HashMap::with_capacity(capacity),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.index.contains_key(key)
+    }
+
+    pub fn get(&self, key: &str) -> Option<&JsonValue> {
+        self.index.get(key).map(|&i| &self.entries[i].1)
+    }
+
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut JsonValue> {
+        self.index.get(key).map(|&i| &mut self.entries[i].1)
+    }
+
+    /// Insert a member, returning the previous value for the key
+    ///
+    /// A new key is appended at the end; an existing key keeps its position.
+    pub fn insert(&mut self, key: impl Into<String>, value: JsonValue) -> Option<JsonValue> {
+        let key = key.into();
+        match self.index.get(&key) {
+            Some(&i) => Some(std::mem::replace(&mut self.entries[i].1, value)),
+            None => {
+                self.index.insert(key.clone(), self.entries.len());
+                self.entries.push((key, value));
+                None
+            }
+        }
+    }
+
+    /// In-place access to the member `key`, which may not exist yet
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use json_parser_rs::{JsonObject, JsonValue};
+    ///
+    /// let mut counts = JsonObject::new();
+    /// for word in ["a", "b", "a"] {
+    ///     let count = counts.entry(word).or_insert(JsonValue::from(0));
+    ///     *count = JsonValue::from(count.as_u64().unwrap() + 1);
+    /// }
+    /// assert_eq!(counts.get("a"), Some(&JsonValue::from(2)));
+    /// ```
+    pub fn entry(&mut self, key: impl Into<String>) -> Entry<'_> {
+        Entry {
+            object: self,
+            key: key.into(),
+        }
+    }
+
+    /// Remove a member, preserving the order of the remaining ones (O(n))
+    pub fn remove(&mut self, key: &str) -> Option<JsonValue> {
+        let i = self.index.remove(key)?;
+        let (_, value) = self.entries.remove(i);
+        self.reindex_from(i);
+        Some(value)
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.index.clear();
+    }
+
+    pub fn keys(&self) -> impl DoubleEndedIterator<Item = &String> + ExactSizeIterator {
+        self.entries.iter().map(|(key, _)| key)
+    }
+
+    pub fn values(&self) -> impl DoubleEndedIterator<Item = &JsonValue> + ExactSizeIterator {
+        self.entries.iter().map(|(_, value)| value)
+    }
+
+    pub fn values_mut(
+        &mut self,
+    ) -> impl DoubleEndedIterator<Item = &mut JsonValue> + ExactSizeIterator {
+        self.entries.iter_mut().map(|(_, value)| value)
+    }
+
+    /// Members in insertion order
+    pub fn iter(
+        &self,
+    ) -> impl DoubleEndedIterator<Item = (&String, &JsonValue)> + ExactSizeIterator {
+        self.entries.iter().map(|(key, value)| (key, value))
+    }
+
+    pub fn iter_mut(
+        &mut self,
+    ) -> impl DoubleEndedIterator<Item = (&String, &mut JsonValue)> + ExactSizeIterator {
+        self.entries.iter_mut().map(|(key, value)| (&*key, value))
+    }
+
+    /// Members as a slice of `(key, value)` pairs
+    pub fn as_slice(&self) -> &[(String, JsonValue)] {
+        &self.entries
+    }
+
+    /// Keep only the members for which `keep` returns `true`, preserving
+    /// their order
+    pub fn retain(&mut self, mut keep: impl FnMut(&String, &mut JsonValue) -> bool) {
+        self.entries.retain_mut(|(key, value)| keep(key, value));
+        self.index.clear();
+        self.reindex_from(0);
+    }
+
+    /// Reorder the members by a key derived from each member (e.g. sort by
+    /// name with `|key, _| key.clone()`); equal members may be swapped
+    pub fn sort_unstable_by_key<K: Ord>(
+        &mut self,
+        mut sort_key: impl FnMut(&String, &JsonValue) -> K,
+    ) {
+        self.entries
+            .sort_unstable_by_key(|(key, value)| sort_key(key, value));
+        self.reindex_from(0);
+    }
+
+    /// Reverse the member order
+    pub fn reverse(&mut self) {
+        self.entries.reverse();
+        self.reindex_from(0);
+    }
+
+    fn reindex_from(&mut self, start: usize) {
+        for (i, (key, _)) in self.entries.iter().enumerate().skip(start) {
+            match self.index.get_mut(key) {
+                Some(slot) => *slot = i,
+                None => {
+                    self.index.insert(key.clone(), i);
+                }
+            }
+        }
+    }
+}
+
+/// A member of a [`JsonObject`] that may be missing, from
+/// [`JsonObject::entry`]
+pub struct Entry<'a> {
+    object: &'a mut JsonObject,
+    key: String,
+}
+
+impl<'a> Entry<'a> {
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// The member's value, after appending `default` if it was missing
+    pub fn or_insert(self, default: JsonValue) -> &'a mut JsonValue {
+        self.or_insert_with(|| default)
+    }
+
+    /// As `or_insert`, computing the value only when it is needed
+    pub fn or_insert_with(self, default: impl FnOnce() -> JsonValue) -> &'a mut JsonValue {
+        let i = match self.object.index.get(&self.key) {
+            Some(&i) => i,
+            None => {
+                self.object.insert(self.key, default());
+                self.object.entries.len() - 1
+            }
+        };
+        &mut self.object.entries[i].1
+    }
+
+    /// As `or_insert`, with `null` as the default
+    pub fn or_default(self) -> &'a mut JsonValue {
+        self.or_insert(JsonValue::default())
+    }
+
+    /// Modify the value if the member exists
+    pub fn and_modify(self, modify: impl FnOnce(&mut JsonValue)) -> Self {
+        if let Some(value) = self.object.get_mut(&self.key) {
+            modify(value);
+        }
+        self
+    }
+}
+
+/// Objects compare like maps: same keys with equal values, in any order
+impl PartialEq for JsonObject {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len()
+            && self
+                .iter()
+                .all(|(key, value)| other.get(key) == Some(value))
+    }
+}
+
+impl std::fmt::Debug for JsonObject {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+/// Later duplicates overwrite earlier ones
+impl<K: Into<String>> FromIterator<(K, JsonValue)> for JsonObject {
+    fn from_iter<I: IntoIterator<Item = (K, JsonValue)>>(iter: I) -> Self {
+        let mut object = JsonObject::new();
+        object.extend(iter);
+        object
+    }
+}
+
+impl<K: Into<String>> Extend<(K, JsonValue)> for JsonObject {
+    fn extend<I: IntoIterator<Item = (K, JsonValue)>>(&mut self, iter: I) {
+        for (key, value) in iter {
+            self.insert(key, value);
+        }
+    }
+}
+
+impl IntoIterator for JsonObject {
+    type Item = (String, JsonValue);
+    type IntoIter = std::vec::IntoIter<(String, JsonValue)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a JsonObject {
+    type Item = (&'a String, &'a JsonValue);
+    type IntoIter = std::iter::Map<
+        std::slice::Iter<'a, (String, JsonValue)>,
+        fn(&'a (String, JsonValue)) -> (&'a String, &'a JsonValue),
+    >;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.iter().map(|(key, value)| (key, value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_insert_keeps_order_and_replaces_in_place() {
+        let mut object = JsonObject::new();
+        object.insert("b", JsonValue::Null);
+        object.insert("a", JsonValue::Boolean(false));
+        assert_eq!(
+            object.insert("b", JsonValue::Boolean(true)),
+            Some(JsonValue::Null)
+        );
+        assert_eq!(object.keys().collect::<Vec<_>>(), ["b", "a"]);
+        assert_eq!(object.get("b"), Some(&JsonValue::Boolean(true)));
+    }
+
+    #[test]
+    fn test_remove_reindexes() {
+        let mut object: JsonObject = [
+            ("a", JsonValue::Null),
+            ("b", JsonValue::Null),
+            ("c", JsonValue::Null),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(object.remove("a"), Some(JsonValue::Null));
+        assert_eq!(object.remove("a"), None);
+        assert!(object.get("c").is_some());
+        object.insert("c", JsonValue::Boolean(true));
+        assert_eq!(
+            object.as_slice()[1],
+            ("c".to_string(), JsonValue::Boolean(true))
+        );
+    }
+
+    #[test]
+    fn test_reordering_keeps_lookups_consistent() {
+        let mut object: JsonObject = ["c", "a", "d", "b"]
+            .into_iter()
+            .enumerate()
+            .map(|(i, key)| (key, JsonValue::Number((i as u64).into())))
+            .collect();
+
+        object.sort_unstable_by_key(|key, _| key.clone());
+        assert_eq!(object.keys().collect::<Vec<_>>(), ["a", "b", "c", "d"]);
+        assert_eq!(object.get("c").and_then(JsonValue::as_u64), Some(0));
+
+        object.reverse();
+        assert_eq!(object.keys().collect::<Vec<_>>(), ["d", "c", "b", "a"]);
+
+        object.retain(|key, _| key != "c");
+        assert_eq!(object.keys().collect::<Vec<_>>(), ["d", "b", "a"]);
+        assert!(!object.contains_key("c"));
+        object.remove("b");
+        assert_eq!(object.get("a").and_then(JsonValue::as_u64), Some(1));
+        assert_eq!(object.as_slice()[1].0, "a");
+    }
+
+    #[test]
+    fn test_equality_ignores_order() {
+        let a: JsonObject = [("x", JsonValue::Null), ("y", JsonValue::Null)]
+            .into_iter()
+            .collect();
+        let b: JsonObject = [("y", JsonValue::Null), ("x", JsonValue::Null)]
+            .into_iter()
+            .collect();
+        assert_eq!(a, b);
+    }
+}
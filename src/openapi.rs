@@ -0,0 +1,788 @@
+//! OpenAPI - load API specifications with their `$ref`s resolved
+//!
+//! [`OpenApi`] accepts OpenAPI 3.x and Swagger 2.0 documents, replaces the
+//! internal `$ref`s with their targets (see [`reference::dereference`]) and
+//! then offers lookups of paths, operations and schemas on the resolved
+//! tree, so callers never have to follow a reference themselves.
+//!
+//! # Examples
+//!
+//! ```
+//! use json_parser_rs::openapi::OpenApi;
+//!
+//! let api = OpenApi::parse(r##"{
+//!     "openapi": "3.1.0",
+//!     "info": {"title": "Pets", "version": "1.0"},
+//!     "paths": {
+//!         "/pets/{id}": {
+//!             "parameters": [{"name": "id", "in": "path", "required": true}],
+//!             "get": {
+//!                 "operationId": "getPet",
+//!                 "responses": {"200": {"content": {"application/json": {
+//!                     "schema": {"$ref": "#/components/schemas/Pet"}
+//!                 }}}}
+//!             }
+//!         }
+//!     },
+//!     "components": {"schemas": {"Pet": {"type": "object", "required": ["name"]}}}
+//! }"##).unwrap();
+//!
+//! assert_eq!(api.title(), Some("Pets"));
+//! let get_pet = api.operation_by_id("getPet").unwrap();
+//! assert_eq!((get_pet.method(), get_pet.path()), ("get", "/pets/{id}"));
+//! assert_eq!(get_pet.parameters().len(), 1);
+//! assert_eq!(
+//!     get_pet.response_schema("200", "application/json"),
+//!     api.schema("Pet")
+//! );
+//! ```
+
+use std::fmt;
+
+use crate::error::ParseError;
+use crate::json::JsonValue;
+use crate::pointer::JsonPointer;
+use crate::reference::{self, RefError};
+use crate::schema::{Schema, SchemaViolation};
+use crate::view::ArrayView;
+
+/// HTTP methods that can appear as operations in a path item, in the order
+/// the specification lists them
+pub const METHODS: [&str; 8] = [
+    "get", "put", "post", "delete", "options", "head", "patch", "trace",
+];
+
+/// Why a specification could not be loaded
+#[derive(Debug, Clone, PartialEq)]
+pub struct OpenApiError {
+    kind: OpenApiErrorKind,
+    message: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum OpenApiErrorKind {
+    /// The text is not valid JSON
+    Parse,
+    /// A `$ref` does not resolve
+    Reference,
+    /// The document is not shaped like an OpenAPI specification
+    InvalidDocument,
+}
+
+impl OpenApiError {
+    fn new(kind: OpenApiErrorKind, message: impl fmt::Display) -> Self {
+        Self {
+            kind,
+            message: message.to_string(),
+        }
+    }
+
+    pub fn kind(&self) -> OpenApiErrorKind {
+        self.kind
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl fmt::Display for OpenApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for OpenApiError {}
+
+impl From<ParseError> for OpenApiError {
+    fn from(error: ParseError) -> Self {
+        Self::new(OpenApiErrorKind::Parse, error)
+    }
+}
+
+impl From<RefError> for OpenApiError {
+    fn from(error: RefError) -> Self {
+        Self::new(OpenApiErrorKind::Reference, error)
+    }
+}
+
+/// A dereferenced OpenAPI 3.x or Swagger 2.0 specification
+#[derive(Debug, Clone, PartialEq)]
+pub struct OpenApi {
+    /// The document as loaded, with its `$ref`s
+    source: JsonValue,
+    document: JsonValue,
+}
+
+impl OpenApi {
+    /// Parse specification text
+    pub fn parse(input: &str) -> Result<Self, OpenApiError> {
+        Self::from_value(crate::parse_json(input)?)
+    }
+
+    /// Check the outline of `document` and resolve its `$ref`s
+    ///
+    /// The document must be an object with an `openapi` or `swagger`
+    /// version string; `paths` must map paths to path item objects.
+    /// Recursive schemas keep their inner `$ref`, as
+    /// [`reference::dereference`] describes.
+    pub fn from_value(document: JsonValue) -> Result<Self, OpenApiError> {
+        let invalid =
+            |message: String| OpenApiError::new(OpenApiErrorKind::InvalidDocument, message);
+        if !document.is_object() {
+            return Err(invalid(format!(
+                "(root): Expected a specification object, found {}",
+                document
+            )));
+        }
+        let version = document.get("openapi").or_else(|| document.get("swagger"));
+        if !version.is_some_and(JsonValue::is_string) {
+            return Err(invalid(
+                "(root): Missing \"openapi\" or \"swagger\" version string".to_string(),
+            ));
+        }
+        if let Some(paths) = document.get("paths") {
+            let Some(paths) = paths.as_object() else {
+                return Err(invalid(format!(
+                    "/paths: Expected an object, found {}",
+                    paths
+                )));
+            };
+            for (path, item) in paths {
+                if !item.is_object() {
+                    return Err(invalid(format!(
+                        "/paths/{}: Expected a path item object, found {}",
+                        crate::pointer::escape_token(path),
+                        item
+                    )));
+                }
+            }
+        }
+        Ok(Self {
+            document: reference::dereference(&document)?,
+            source: document,
+        })
+    }
+
+    /// The resolved document
+    pub fn as_value(&self) -> &JsonValue {
+        &self.document
+    }
+
+    /// The document as loaded, before resolving `$ref`s
+    pub fn source(&self) -> &JsonValue {
+        &self.source
+    }
+
+    pub fn into_value(self) -> JsonValue {
+        self.document
+    }
+
+    /// `openapi` (or `swagger`) version, such as `3.1.0`
+    pub fn version(&self) -> &str {
+        self.document
+            .get("openapi")
+            .or_else(|| self.document.get("swagger"))
+            .and_then(JsonValue::as_str)
+            .unwrap_or_default()
+    }
+
+    /// `true` for Swagger 2.0 documents
+    pub fn is_swagger(&self) -> bool {
+        self.document.get("swagger").is_some()
+    }
+
+    /// `info.title`
+    pub fn title(&self) -> Option<&str> {
+        self.document.get("info")?.get("title")?.as_str()
+    }
+
+    /// `info.version`, the version of the API rather than of the format
+    pub fn api_version(&self) -> Option<&str> {
+        self.document.get("info")?.get("version")?.as_str()
+    }
+
+    /// Paths in document order
+    pub fn paths(&self) -> impl Iterator<Item = &str> {
+        self.document
+            .get("paths")
+            .and_then(JsonValue::as_object)
+            .into_iter()
+            .flat_map(|paths| paths.keys().map(String::as_str))
+    }
+
+    /// All operations, by path and then in [`METHODS`] order
+    pub fn operations(&self) -> impl Iterator<Item = Operation<'_>> {
+        let swagger = self.is_swagger();
+        self.document
+            .get("paths")
+            .and_then(JsonValue::as_object)
+            .into_iter()
+            .flat_map(|paths| paths.iter())
+            .flat_map(move |(path, item)| {
+                METHODS.iter().filter_map(move |&method| {
+                    Some(Operation {
+                        path,
+                        method,
+                        item,
+                        value: item.get(method)?,
+                        swagger,
+                    })
+                })
+            })
+    }
+
+    /// The operation for `method` (any case) on `path`, as written in the
+    /// document (with its `{parameter}` placeholders)
+    pub fn operation(&self, method: &str, path: &str) -> Option<Operation<'_>> {
+        self.operations().find(|operation| {
+            operation.path == path && operation.method.eq_ignore_ascii_case(method)
+        })
+    }
+
+    pub fn operation_by_id(&self, operation_id: &str) -> Option<Operation<'_>> {
+        self.operations()
+            .find(|operation| operation.operation_id() == Some(operation_id))
+    }
+
+    /// Named schemas (`components.schemas`, or `definitions` for Swagger 2.0)
+    pub fn schemas(&self) -> impl Iterator<Item = (&str, &JsonValue)> {
+        self.schema_container()
+            .and_then(JsonValue::as_object)
+            .into_iter()
+            .flat_map(|schemas| schemas.iter().map(|(name, schema)| (name.as_str(), schema)))
+    }
+
+    pub fn schema(&self, name: &str) -> Option<&JsonValue> {
+        self.schema_container()?.get(name)
+    }
+
+    fn schema_container(&self) -> Option<&JsonValue> {
+        if self.is_swagger() {
+            self.document.get("definitions")
+        } else {
+            self.document.get("components")?.get("schemas")
+        }
+    }
+}
+
+/// One operation of a path item, from [`OpenApi::operations`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Operation<'a> {
+    path: &'a str,
+    method: &'a str,
+    /// The enclosing path item, for parameters shared by its operations
+    item: &'a JsonValue,
+    value: &'a JsonValue,
+    swagger: bool,
+}
+
+impl<'a> Operation<'a> {
+    pub fn path(&self) -> &'a str {
+        self.path
+    }
+
+    /// Lowercase HTTP method, as in [`METHODS`]
+    pub fn method(&self) -> &'a str {
+        self.method
+    }
+
+    pub fn as_value(&self) -> &'a JsonValue {
+        self.value
+    }
+
+    pub fn operation_id(&self) -> Option<&'a str> {
+        self.value.get("operationId")?.as_str()
+    }
+
+    pub fn summary(&self) -> Option<&'a str> {
+        self.value.get("summary")?.as_str()
+    }
+
+    /// Parameters of the path item and the operation; an operation
+    /// parameter replaces a path item parameter with the same `name` and `in`
+    pub fn parameters(&self) -> Vec<&'a JsonValue> {
+        let list = |value: &'a JsonValue| {
+            value
+                .get("parameters")
+                .and_then(JsonValue::as_array)
+                .map(ArrayView::as_slice)
+                .unwrap_or_default()
+        };
+        let key = |parameter: &'a JsonValue| (parameter.get("name"), parameter.get("in"));
+        let own = list(self.value);
+        list(self.item)
+            .iter()
+            .filter(|shared| !own.iter().any(|parameter| key(parameter) == key(shared)))
+            .chain(own)
+            .collect()
+    }
+
+    /// Schema of the request body for `media_type` (for Swagger 2.0, of the
+    /// `in: body` parameter, whatever the media type)
+    pub fn request_body_schema(&self, media_type: &str) -> Option<&'a JsonValue> {
+        if self.swagger {
+            return self
+                .parameters()
+                .into_iter()
+                .find(|parameter| parameter.get("in").and_then(JsonValue::as_str) == Some("body"))?
+                .get("schema");
+        }
+        self.value
+            .get("requestBody")?
+            .get("content")?
+            .get(media_type)?
+            .get("schema")
+    }
+
+    /// The response object for `status` (such as `"200"`), falling back to
+    /// the `XX` range (`"2XX"`) and then to `default`
+    pub fn response(&self, status: &str) -> Option<&'a JsonValue> {
+        let responses = self.value.get("responses")?;
+        let range = status.get(..1).map(|class| format!("{}XX", class));
+        responses
+            .get(status)
+            .or_else(|| responses.get(range.as_deref()?))
+            .or_else(|| responses.get("default"))
+    }
+
+    /// Schema of the response body for `status` and `media_type` (for
+    /// Swagger 2.0, the response `schema`, whatever the media type)
+    pub fn response_schema(&self, status: &str, media_type: &str) -> Option<&'a JsonValue> {
+        let response = self.response(status)?;
+        if self.swagger {
+            return response.get("schema");
+        }
+        response.get("content")?.get(media_type)?.get("schema")
+    }
+}
+
+/// Check every `example` and `examples` entry of `spec` against its schema
+///
+/// Covers examples of schemas (3.0 `example` and 3.1 `examples` arrays),
+/// of parameters, headers and media types (`example` and the `value` of
+/// each example object), and Swagger 2.0 response `examples`, both inline
+/// and in `components` (`definitions`, `parameters` and `responses` for
+/// Swagger 2.0). Each example is checked where it is written; an object
+/// that is a `$ref` is checked at its target instead.
+///
+/// The `instance_path` of a violation locates the offending value in the
+/// specification, and its `schema_path` the failed keyword.
+///
+/// # Examples
+///
+/// ```
+/// use json_parser_rs::openapi::{self, OpenApi};
+///
+/// let api = OpenApi::parse(r##"{
+///     "openapi": "3.0.3",
+///     "paths": {"/pets": {"get": {"parameters": [
+///         {"name": "limit", "in": "query", "schema": {"type": "integer"}, "example": "ten"}
+///     ]}}},
+///     "components": {"schemas": {"Pet": {
+///         "type": "object",
+///         "properties": {"name": {"type": "string"}},
+///         "example": {"name": 7}
+///     }}}
+/// }"##).unwrap();
+///
+/// let violations = openapi::validate_examples(&api).unwrap_err();
+/// let lines: Vec<String> = violations.iter().map(ToString::to_string).collect();
+/// assert_eq!(lines, [
+///     "/paths/~1pets/get/parameters/0/example: Expected integer, found string [type]",
+///     "/components/schemas/Pet/example/name: Expected string, found integer [type]",
+/// ]);
+/// ```
+pub fn validate_examples(spec: &OpenApi) -> Result<(), Vec<SchemaViolation>> {
+    let mut checker = ExampleChecker {
+        schema: Schema::new(spec.source.clone()),
+        swagger: spec.is_swagger(),
+        path: Vec::new(),
+        violations: Vec::new(),
+    };
+    let source = &spec.source;
+    for key in ["paths", "webhooks"] {
+        checker.member(source, key, |c, items| {
+            c.each(items, |c, item| {
+                for (key, value) in item.as_object().into_iter().flatten() {
+                    match key.as_str() {
+                        "parameters" => c.at(key, |c| c.each(value, ExampleChecker::holder)),
+                        _ if METHODS.contains(&key.as_str()) => c.at(key, |c| c.operation(value)),
+                        _ => {}
+                    }
+                }
+            })
+        });
+    }
+    checker.member(source, "definitions", |c, schemas| {
+        c.each(schemas, ExampleChecker::schema)
+    });
+    checker.member(source, "parameters", |c, parameters| {
+        c.each(parameters, ExampleChecker::holder)
+    });
+    checker.member(source, "responses", |c, responses| {
+        c.each(responses, ExampleChecker::response)
+    });
+    checker.member(source, "components", |c, components| {
+        c.member(components, "schemas", |c, schemas| {
+            c.each(schemas, ExampleChecker::schema)
+        });
+        for key in ["parameters", "headers"] {
+            c.member(components, key, |c, holders| {
+                c.each(holders, ExampleChecker::holder)
+            });
+        }
+        c.member(components, "requestBodies", |c, bodies| {
+            c.each(bodies, ExampleChecker::content)
+        });
+        c.member(components, "responses", |c, responses| {
+            c.each(responses, ExampleChecker::response)
+        });
+    });
+
+    if checker.violations.is_empty() {
+        Ok(())
+    } else {
+        Err(checker.violations)
+    }
+}
+
+/// Walk over a specification collecting example violations
+struct ExampleChecker {
+    /// The whole specification, so that `$ref`s in schemas resolve
+    schema: Schema,
+    swagger: bool,
+    path: Vec<String>,
+    violations: Vec<SchemaViolation>,
+}
+
+impl ExampleChecker {
+    fn at(&mut self, token: impl ToString, check: impl FnOnce(&mut Self)) {
+        self.path.push(token.to_string());
+        check(self);
+        self.path.pop();
+    }
+
+    /// Check `value[key]` if present
+    fn member(&mut self, value: &JsonValue, key: &str, check: impl FnOnce(&mut Self, &JsonValue)) {
+        if let Some(member) = value.get(key) {
+            self.at(key, |c| check(c, member));
+        }
+    }
+
+    /// Check every member of an object or element of an array, skipping
+    /// `$ref`s, which are checked at their target
+    fn each(&mut self, value: &JsonValue, check: impl Fn(&mut Self, &JsonValue)) {
+        let members: Vec<(String, &JsonValue)> = match value.untagged() {
+            JsonValue::Object(object) => object.iter().map(|(k, v)| (k.clone(), v)).collect(),
+            JsonValue::Array(elements) => elements
+                .iter()
+                .enumerate()
+                .map(|(i, v)| (i.to_string(), v))
+                .collect(),
+            _ => return,
+        };
+        for (token, member) in members {
+            if member.get("$ref").is_none() {
+                self.at(token, |c| check(c, member));
+            }
+        }
+    }
+
+    /// Check the example at `example_tokens` (relative to the current
+    /// location) against the schema at the current location plus
+    /// `schema_tokens`
+    fn example(&mut self, example_tokens: &[&str], example: &JsonValue, schema_tokens: &[&str]) {
+        let example_path = self.pointer(example_tokens);
+        let schema_path = self.pointer(schema_tokens);
+        self.check(&example_path, example, &schema_path);
+    }
+
+    fn check(&mut self, example_path: &JsonPointer, example: &JsonValue, schema: &JsonPointer) {
+        let Err(violations) = self.schema.validate_at(schema, example) else {
+            return;
+        };
+        for mut violation in violations {
+            let tokens = example_path
+                .tokens()
+                .iter()
+                .chain(violation.instance_path.tokens());
+            violation.instance_path = JsonPointer::from_tokens(tokens);
+            self.violations.push(violation);
+        }
+    }
+
+    fn pointer(&self, tokens: &[&str]) -> JsonPointer {
+        JsonPointer::from_tokens(
+            self.path
+                .iter()
+                .map(String::as_str)
+                .chain(tokens.iter().copied()),
+        )
+    }
+
+    fn operation(&mut self, operation: &JsonValue) {
+        self.member(operation, "parameters", |c, parameters| {
+            c.each(parameters, Self::holder)
+        });
+        self.member(operation, "requestBody", |c, body| {
+            if body.get("$ref").is_none() {
+                c.content(body);
+            }
+        });
+        self.member(operation, "responses", |c, responses| {
+            c.each(responses, Self::response)
+        });
+    }
+
+    fn response(&mut self, response: &JsonValue) {
+        self.content(response);
+        self.member(response, "headers", |c, headers| {
+            c.each(headers, Self::holder)
+        });
+        if self.swagger {
+            self.member(response, "schema", Self::schema);
+            if response.get("schema").is_some() {
+                let examples = response.get("examples").and_then(JsonValue::as_object);
+                for (media_type, example) in examples.into_iter().flatten() {
+                    self.example(&["examples", media_type], example, &["schema"]);
+                }
+            }
+        }
+    }
+
+    /// The media types of a request body or response
+    fn content(&mut self, value: &JsonValue) {
+        self.member(value, "content", |c, content| c.each(content, Self::holder));
+    }
+
+    /// A parameter, header or media type: `example` and `examples` checked
+    /// against its `schema`, or against the media types of its `content`
+    fn holder(&mut self, holder: &JsonValue) {
+        self.content(holder);
+        let Some(schema) = holder.get("schema") else {
+            return;
+        };
+        self.at("schema", |c| c.schema(schema));
+        if let Some(example) = holder.get("example") {
+            self.example(&["example"], example, &["schema"]);
+        }
+        let examples = holder.get("examples").and_then(JsonValue::as_object);
+        for (name, example) in examples.into_iter().flatten() {
+            match example.get("$ref").and_then(JsonValue::as_str) {
+                Some(reference) => {
+                    // A shared example is reported where it is written
+                    let value = reference::resolve(self.schema.as_value(), reference)
+                        .and_then(|example| example.get("value"))
+                        .cloned();
+                    let target = reference
+                        .strip_prefix('#')
+                        .and_then(|pointer| JsonPointer::parse(pointer).ok());
+                    if let (Some(value), Some(target)) = (value, target) {
+                        let mut tokens = target.tokens().to_vec();
+                        tokens.push("value".to_string());
+                        let schema_path = self.pointer(&["schema"]);
+                        self.check(&JsonPointer::from_tokens(tokens), &value, &schema_path);
+                    }
+                }
+                None => {
+                    if let Some(value) = example.get("value") {
+                        self.example(&["examples", name, "value"], value, &["schema"]);
+                    }
+                }
+            }
+        }
+    }
+
+    /// A schema and its subschemas; `example` and `examples` are checked
+    /// against the schema they belong to
+    fn schema(&mut self, schema: &JsonValue) {
+        if !schema.is_object() || schema.get("$ref").is_some() {
+            return;
+        }
+        if let Some(example) = schema.get("example") {
+            self.example(&["example"], example, &[]);
+        }
+        if let Some(examples) = schema.get("examples").and_then(JsonValue::as_array) {
+            for (index, example) in examples.iter().enumerate() {
+                self.example(&["examples", &index.to_string()], example, &[]);
+            }
+        }
+        for key in [
+            "items",
+            "additionalProperties",
+            "not",
+            "contains",
+            "if",
+            "then",
+            "else",
+        ] {
+            self.member(schema, key, Self::schema);
+        }
+        for key in ["properties", "patternProperties", "$defs", "definitions"] {
+            self.member(schema, key, |c, schemas| c.each(schemas, Self::schema));
+        }
+        for key in ["allOf", "anyOf", "oneOf", "prefixItems"] {
+            self.member(schema, key, |c, schemas| c.each(schemas, Self::schema));
+        }
+        if let Some(items) = schema.get("items").filter(|items| items.is_array()) {
+            self.at("items", |c| c.each(items, Self::schema));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json;
+
+    #[test]
+    fn test_operations_and_swagger() {
+        let api = OpenApi::from_value(json!({
+            "swagger": "2.0",
+            "paths": {
+                "/pets": {
+                    "parameters": [{"name": "limit", "in": "query"}, {"name": "x", "in": "header"}],
+                    "post": {
+                        "parameters": [
+                            {"name": "x", "in": "header", "required": true},
+                            {"name": "pet", "in": "body", "schema": {"$ref": "#/definitions/Pet"}}
+                        ],
+                        "responses": {"2XX": {"schema": {"type": "string"}}}
+                    },
+                    "get": {"responses": {"default": {"description": "Anything"}}}
+                }
+            },
+            "definitions": {"Pet": {"type": "object"}}
+        }))
+        .unwrap();
+        assert!(api.is_swagger());
+        assert_eq!(api.version(), "2.0");
+        let methods: Vec<&str> = api.operations().map(|o| o.method()).collect();
+        assert_eq!(methods, ["get", "post"]);
+
+        let post = api.operation("POST", "/pets").unwrap();
+        let names: Vec<_> = post
+            .parameters()
+            .iter()
+            .map(|p| p["name"].clone())
+            .collect();
+        assert_eq!(names, [json!("limit"), json!("x"), json!("pet")]);
+        assert_eq!(post.parameters()[1]["required"], json!(true));
+        assert_eq!(
+            post.request_body_schema("application/json"),
+            api.schema("Pet")
+        );
+        assert_eq!(
+            post.response_schema("201", "*/*"),
+            Some(&json!({"type": "string"}))
+        );
+        assert_eq!(post.response("404"), None);
+        assert!(
+            api.operation("get", "/pets")
+                .unwrap()
+                .response("404")
+                .is_some()
+        );
+        assert!(api.operation("delete", "/pets").is_none());
+    }
+
+    #[test]
+    fn test_load_errors() {
+        let cases = [
+            (
+                "[]",
+                OpenApiErrorKind::InvalidDocument,
+                "(root): Expected a specification object, found []",
+            ),
+            (
+                "{}",
+                OpenApiErrorKind::InvalidDocument,
+                "(root): Missing \"openapi\" or \"swagger\" version string",
+            ),
+            (
+                r#"{"openapi": "3.0.3", "paths": {"/a/b": []}}"#,
+                OpenApiErrorKind::InvalidDocument,
+                "/paths/~1a~1b: Expected a path item object, found []",
+            ),
+            (
+                r##"{"openapi": "3.0.3", "paths": {"/a": {"$ref": "#/x"}}}"##,
+                OpenApiErrorKind::Reference,
+                "/paths/~1a: Cannot resolve $ref \"#/x\"",
+            ),
+        ];
+        for (input, kind, message) in cases {
+            let error = OpenApi::parse(input).unwrap_err();
+            assert_eq!((error.kind(), error.message()), (kind, message));
+        }
+        assert_eq!(
+            OpenApi::parse("{").unwrap_err().kind(),
+            OpenApiErrorKind::Parse
+        );
+    }
+
+    #[test]
+    fn test_validate_examples() {
+        let api = OpenApi::from_value(json!({
+            "openapi": "3.1.0",
+            "paths": {"/pets": {"post": {
+                "requestBody": {"content": {"application/json": {
+                    "schema": {"$ref": "#/components/schemas/Pet"},
+                    "examples": {
+                        "good": {"value": {"name": "Rex"}},
+                        "bad": {"value": {}},
+                        "shared": {"$ref": "#/components/examples/nameless"}
+                    }
+                }}},
+                "responses": {"200": {"$ref": "#/components/responses/Ok"}}
+            }}},
+            "components": {
+                "schemas": {"Pet": {
+                    "type": "object",
+                    "required": ["name"],
+                    "properties": {"example": {"type": "string", "examples": ["a", 1]}}
+                }},
+                "examples": {"nameless": {"value": {"example": "x"}}},
+                "responses": {"Ok": {"headers": {"X-Rate": {
+                    "schema": {"type": "integer", "minimum": 0}, "example": -1
+                }}}}
+            }
+        }))
+        .unwrap();
+        let violations = validate_examples(&api).unwrap_err();
+        let lines: Vec<String> = violations.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            lines,
+            [
+                "/paths/~1pets/post/requestBody/content/application~1json/examples/bad/value: \
+                 Missing required property \"name\" [required]",
+                "/components/examples/nameless/value: \
+                 Missing required property \"name\" [required]",
+                "/components/schemas/Pet/properties/example/examples/1: \
+                 Expected string, found integer [type]",
+                "/components/responses/Ok/headers/X-Rate/example: \
+                 Value must be at least 0 [minimum]",
+            ]
+        );
+        assert_eq!(
+            violations[0].schema_path.to_string(),
+            "/paths/~1pets/post/requestBody/content/application~1json/schema/$ref/required"
+        );
+
+        let swagger = OpenApi::from_value(json!({
+            "swagger": "2.0",
+            "paths": {"/a": {"get": {"responses": {"200": {
+                "schema": {"type": "array"},
+                "examples": {"application/json": [1], "text/plain": "x"}
+            }}}}}
+        }))
+        .unwrap();
+        let violations = validate_examples(&swagger).unwrap_err();
+        assert_eq!(violations.len(), 1);
+        assert_eq!(
+            violations[0].instance_path.to_string(),
+            "/paths/~1a/get/responses/200/examples/text~1plain"
+        );
+    }
+}
@@ -0,0 +1,243 @@
+//! Parser options - one parser, several levels of strictness
+//!
+//! The default options accept exactly RFC 8259 JSON. Services parsing
+//! untrusted API payloads usually want to tighten limits, while tools reading
+//! hand-written config files want to accept comments and trailing commas.
+//! Both are expressed as a `ParserOptions` value handed to
+//! `Parser::with_options`.
+
+use crate::object::DuplicateKeyPolicy;
+
+/// Nesting limit applied unless configured otherwise
+///
+/// Deep enough for any realistic document while keeping the recursive
+/// descent far away from the end of the stack.
+pub const DEFAULT_MAX_DEPTH: usize = 128;
+
+/// Settings controlling what the parser accepts
+///
+/// Built with chained setters starting from `ParserOptions::default()`
+/// (strict JSON, nesting limited to [`DEFAULT_MAX_DEPTH`]) or one of the
+/// presets.
+///
+/// # Examples
+///
+/// ```
+/// use json_parser_rs::{DuplicateKeyPolicy, Parser, ParserOptions};
+///
+/// let options = ParserOptions::default()
+///     .max_depth(Some(32))
+///     .duplicate_keys(DuplicateKeyPolicy::Error)
+///     .allow_comments(true);
+///
+/// let value = Parser::with_options("[1, /* two */ 2]", options)
+///     .and_then(|mut parser| parser.parse())
+///     .unwrap();
+/// assert_eq!(value.to_json_string(), "[1, 2]");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[non_exhaustive]
+pub struct ParserOptions {
+    /// Maximum nesting of arrays and objects
+    ///
+    /// `None` removes the limit; deeply nested input may then overflow the
+    /// stack, so only do this for trusted input.
+    pub max_depth: Option<usize>,
+    /// Maximum input length in bytes (`None`: unlimited)
+    pub max_input_size: Option<usize>,
+    /// Approximate budget in bytes for the parsed tree (`None`: unlimited)
+    ///
+    /// Every value is charged its in-memory size plus the length of its
+    /// string data, so the count tracks real allocations closely without
+    /// being exact.
+    pub max_memory: Option<usize>,
+    /// Maximum length of a single string (key or value) in bytes after
+    /// unescaping (`None`: unlimited)
+    pub max_string_length: Option<usize>,
+    /// Maximum number of values in the document, counting every array,
+    /// object and scalar (`None`: unlimited)
+    pub max_nodes: Option<usize>,
+    /// Maximum number of elements of a single array, or members of a single
+    /// object (`None`: unlimited)
+    pub max_elements: Option<usize>,
+    /// Reject raw control characters (U+0000 to U+001F) inside strings, as
+    /// RFC 8259 requires; they must be written as escapes. On by default;
+    /// turn it off to accept documents from producers that emit literal tabs
+    /// or newlines.
+    pub reject_control_characters: bool,
+    /// How repeated keys inside one object are handled
+    pub duplicate_keys: DuplicateKeyPolicy,
+    /// Accept a comma after the last array element or object member
+    pub allow_trailing_commas: bool,
+    /// Accept `// line` and `/* block */` comments wherever whitespace is allowed
+    pub allow_comments: bool,
+    /// Accept strings delimited by `'` (JSON5)
+    pub allow_single_quotes: bool,
+    /// Accept object keys written as bare identifiers, e.g. `{name: 1}` (JSON5)
+    pub allow_unquoted_keys: bool,
+    /// Accept numbers with leading zeros (`007`) or a decimal point not
+    /// followed by digits (`1.`)
+    pub allow_lenient_numbers: bool,
+    /// Keep double-quoted strings with unpaired surrogate escapes
+    /// (`"\uD800"`) as `JsonValue::RawString` instead of rejecting them, so
+    /// they are written back exactly as read. Object keys with unpaired
+    /// surrogates are still rejected.
+    pub preserve_lone_surrogates: bool,
+    /// Reader-based parsing only: detect UTF-16 input by its byte order
+    /// mark or its zero bytes and transcode it, see [`crate::encoding`]
+    pub detect_encoding: bool,
+}
+
+impl Default for ParserOptions {
+    fn default() -> Self {
+        Self {
+            max_depth: Some(DEFAULT_MAX_DEPTH),
+            max_input_size: None,
+            max_memory: None,
+            max_string_length: None,
+            max_nodes: None,
+            max_elements: None,
+            reject_control_characters: true,
+            duplicate_keys: DuplicateKeyPolicy::default(),
+            allow_trailing_commas: false,
+            allow_comments: false,
+            allow_single_quotes: false,
+            allow_unquoted_keys: false,
+            allow_lenient_numbers: false,
+            preserve_lone_surrogates: false,
+            detect_encoding: false,
+        }
+    }
+}
+
+impl ParserOptions {
+    /// Recommended limits for parsing untrusted input, e.g. request bodies of
+    /// an internet-facing service
+    ///
+    /// Bounds everything an attacker controls: nesting, input size, string
+    /// length and number of values. Duplicate keys are rejected, because
+    /// components that resolve them differently can be played against each
+    /// other. No relaxations are enabled.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use json_parser_rs::{DuplicateKeyPolicy, ParserOptions};
+    ///
+    /// let options = ParserOptions::hardened();
+    /// assert_eq!(options.max_depth, Some(64));
+    /// assert_eq!(options.max_input_size, Some(10 * 1024 * 1024));
+    /// assert_eq!(options.max_string_length, Some(1024 * 1024));
+    /// assert_eq!(options.max_nodes, Some(1_000_000));
+    /// assert_eq!(options.duplicate_keys, DuplicateKeyPolicy::Error);
+    /// assert!(options.reject_control_characters);
+    /// assert!(!options.allow_comments && !options.allow_trailing_commas);
+    /// assert!(!options.allow_single_quotes && !options.allow_unquoted_keys);
+    /// ```
+    pub fn hardened() -> Self {
+        Self::default()
+            .max_depth(Some(64))
+            .max_input_size(Some(10 * 1024 * 1024))
+            .max_string_length(Some(1024 * 1024))
+            .max_nodes(Some(1_000_000))
+            .duplicate_keys(DuplicateKeyPolicy::Error)
+            .reject_control_characters(true)
+    }
+
+    /// Options for human-written files: comments, trailing commas and
+    /// lenient numbers allowed
+    pub fn relaxed() -> Self {
+        Self::default()
+            .allow_comments(true)
+            .allow_trailing_commas(true)
+            .allow_lenient_numbers(true)
+    }
+
+    /// Options for JSON5-style documents: everything `relaxed` accepts plus
+    /// single-quoted strings, unquoted keys and raw control characters in
+    /// strings
+    ///
+    /// Other JSON5 extensions (hexadecimal numbers, `Infinity`, `NaN`, leading
+    /// `+` or `.` in numbers, multi-line strings) are not supported.
+    pub fn json5() -> Self {
+        Self::relaxed()
+            .allow_single_quotes(true)
+            .allow_unquoted_keys(true)
+            .reject_control_characters(false)
+    }
+
+    pub fn max_depth(mut self, max_depth: Option<usize>) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    pub fn max_input_size(mut self, max_input_size: Option<usize>) -> Self {
+        self.max_input_size = max_input_size;
+        self
+    }
+
+    pub fn max_memory(mut self, max_memory: Option<usize>) -> Self {
+        self.max_memory = max_memory;
+        self
+    }
+
+    pub fn max_string_length(mut self, max_string_length: Option<usize>) -> Self {
+        self.max_string_length = max_string_length;
+        self
+    }
+
+    pub fn max_nodes(mut self, max_nodes: Option<usize>) -> Self {
+        self.max_nodes = max_nodes;
+        self
+    }
+
+    pub fn max_elements(mut self, max_elements: Option<usize>) -> Self {
+        self.max_elements = max_elements;
+        self
+    }
+
+    pub fn reject_control_characters(mut self, reject: bool) -> Self {
+        self.reject_control_characters = reject;
+        self
+    }
+
+    pub fn duplicate_keys(mut self, policy: DuplicateKeyPolicy) -> Self {
+        self.duplicate_keys = policy;
+        self
+    }
+
+    pub fn allow_trailing_commas(mut self, allow: bool) -> Self {
+        self.allow_trailing_commas = allow;
+        self
+    }
+
+    pub fn allow_comments(mut self, allow: bool) -> Self {
+        self.allow_comments = allow;
+        self
+    }
+
+    pub fn allow_single_quotes(mut self, allow: bool) -> Self {
+        self.allow_single_quotes = allow;
+        self
+    }
+
+    pub fn allow_unquoted_keys(mut self, allow: bool) -> Self {
+        self.allow_unquoted_keys = allow;
+        self
+    }
+
+    pub fn allow_lenient_numbers(mut self, allow: bool) -> Self {
+        self.allow_lenient_numbers = allow;
+        self
+    }
+
+    pub fn preserve_lone_surrogates(mut self, preserve: bool) -> Self {
+        self.preserve_lone_surrogates = preserve;
+        self
+    }
+
+    pub fn detect_encoding(mut self, detect: bool) -> Self {
+        self.detect_encoding = detect;
+        self
+    }
+}
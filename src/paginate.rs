@@ -0,0 +1,73 @@
+//! Pagination - serve a large array one page at a time
+//!
+//! APIs proxying big result sets commonly answer with a window of the array
+//! plus enough metadata for the client to request the next page.
+
+use crate::json::JsonValue;
+use crate::object::JsonObject;
+
+impl JsonValue {
+    /// Cut page `page` (1-based) of `per_page` elements out of the array at
+    /// `pointer`
+    ///
+    /// The result is an object with the members `items`, `page`, `per_page`,
+    /// `total` (number of elements in the whole array) and `pages`. A page
+    /// past the end yields an empty `items` array. Returns `None` if the
+    /// pointer does not resolve to an array or `page`/`per_page` is zero.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use json_parser_rs::parse_json;
+    ///
+    /// let value = parse_json(r#"{"results": [1, 2, 3, 4, 5]}"#).unwrap();
+    /// let page = value.paginate("/results", 2, 2).unwrap();
+    /// assert_eq!(
+    ///     page.to_json_string(),
+    ///     r#"{"items": [3, 4], "page": 2, "per_page": 2, "total": 5, "pages": 3}"#
+    /// );
+    /// ```
+    pub fn paginate(&self, pointer: &str, page: usize, per_page: usize) -> Option<JsonValue> {
+        let elements = self.pointer(pointer)?.as_array()?;
+        if page == 0 || per_page == 0 {
+            return None;
+        }
+
+        let total = elements.len();
+        let start = (page - 1).saturating_mul(per_page).min(total);
+        let end = start.saturating_add(per_page).min(total);
+
+        let mut result = JsonObject::with_capacity(5);
+        result.insert("items", JsonValue::Array(elements[start..end].to_vec()));
+        result.insert("page", count(page));
+        result.insert("per_page", count(per_page));
+        result.insert("total", count(total));
+        result.insert("pages", count(total.div_ceil(per_page)));
+        Some(JsonValue::Object(result))
+    }
+}
+
+fn count(n: usize) -> JsonValue {
+    JsonValue::Number((n as u64).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parse_json;
+
+    #[test]
+    fn test_paginate_bounds() {
+        let value = parse_json(r#"{"a": [1, 2, 3]}"#).unwrap();
+
+        let last = value.paginate("/a", 2, 2).unwrap();
+        assert_eq!(last.pointer("/items").unwrap().to_json_string(), "[3]");
+        assert_eq!(last.pointer("/pages").unwrap().as_u64(), Some(2));
+
+        let beyond = value.paginate("/a", 9, 2).unwrap();
+        assert_eq!(beyond.pointer("/items").unwrap().to_json_string(), "[]");
+
+        assert!(value.paginate("/a", 0, 2).is_none());
+        assert!(value.paginate("/a", 1, 0).is_none());
+        assert!(value.paginate("", 1, 2).is_none());
+    }
+}
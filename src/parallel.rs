@@ -0,0 +1,286 @@
+//! Parallel parsing - large top-level arrays on the rayon thread pool
+//!
+//! Exports and ETL inputs are often one huge array of independent records.
+//! [`parse_parallel`] finds the boundaries between the array's elements with
+//! a quick scan that only tracks strings and bracket depth, cuts the
+//! elements into chunks of roughly equal size and parses the chunks on
+//! rayon's global pool. The result equals what `parse_json` returns.
+//!
+//! Everything else is parsed on the calling thread: documents whose root is
+//! not an array, inputs below [`MIN_PARALLEL_LEN`], options allowing
+//! comments or single-quoted strings, which the scan does not understand,
+//! and any input when the pool has a single thread.
+//! A malformed document is parsed again sequentially, so that the error is
+//! the one `parse_json` reports; errors are the slow path.
+
+use std::ops::Range;
+
+use rayon::prelude::*;
+
+use crate::error::ParseError;
+use crate::json::JsonValue;
+use crate::lexer::{BYTE_ORDER_MARK, Lexer, Token};
+use crate::options::ParserOptions;
+use crate::parser::{Parser, Tokens};
+
+/// Inputs shorter than this are parsed sequentially, since splitting them
+/// costs more than it gains
+pub const MIN_PARALLEL_LEN: usize = 1 << 20;
+
+/// Chunks are at least this long, so every task does a worthwhile amount of
+/// work
+const MIN_CHUNK_LEN: usize = 1 << 16;
+
+/// Parse `input` like `parse_json`, splitting a large top-level array across
+/// threads
+///
+/// # Examples
+///
+/// ```
+/// use json_parser_rs::parallel::parse_parallel;
+/// use json_parser_rs::parse_json;
+///
+/// let records: Vec<String> = (0..50_000)
+///     .map(|i| format!(r#"{{"id": {}, "tags": ["a", "b"], "note": "x,]}}"}}"#, i))
+///     .collect();
+/// let input = format!("[{}]", records.join(",\n"));
+/// assert_eq!(parse_parallel(&input).unwrap(), parse_json(&input).unwrap());
+/// ```
+pub fn parse_parallel(input: &str) -> Result<JsonValue, ParseError> {
+    parse_parallel_with_options(input, ParserOptions::default())
+}
+
+/// [`parse_parallel`] with explicit limits and relaxations
+///
+/// Limits apply to the whole document as they do for `Parser`: `max_depth`
+/// counts the root array, and `max_nodes` and `max_memory` are checked
+/// against the sum over all chunks.
+pub fn parse_parallel_with_options(
+    input: &str,
+    options: ParserOptions,
+) -> Result<JsonValue, ParseError> {
+    let sequential = || Parser::with_options(input, options)?.parse();
+    let splittable = input.len() >= MIN_PARALLEL_LEN
+        && rayon::current_num_threads() > 1
+        && !options.allow_comments
+        && !options.allow_single_quotes
+        && options.max_input_size.is_none_or(|max| input.len() <= max);
+    let Some(elements) = splittable
+        .then(|| split_elements(input, &options))
+        .flatten()
+    else {
+        return sequential();
+    };
+    if options.max_elements.is_some_and(|max| elements.len() > max) {
+        return sequential();
+    }
+
+    let chunks = chunk_elements(&elements, input.len());
+    let parsed: Result<Vec<_>, ParseError> = chunks
+        .par_iter()
+        .map(|range| parse_chunk(&input[range.clone()], options))
+        .collect();
+    let Ok(parsed) = parsed else {
+        return sequential();
+    };
+
+    // The root array counts as one value, as it does for `Parser`
+    let (mut nodes, mut memory) = (1, std::mem::size_of::<JsonValue>());
+    let mut values = Vec::with_capacity(elements.len());
+    for (chunk, (chunk_nodes, chunk_memory)) in parsed {
+        nodes += chunk_nodes;
+        memory += chunk_memory;
+        values.extend(chunk);
+    }
+    if options.max_nodes.is_some_and(|max| nodes > max)
+        || options.max_memory.is_some_and(|max| memory > max)
+    {
+        return sequential();
+    }
+    Ok(JsonValue::Array(values))
+}
+
+/// Byte ranges of the elements of the top-level array, or `None` if the
+/// root is not an array or the scan finds the document malformed
+fn split_elements(input: &str, options: &ParserOptions) -> Option<Vec<Range<usize>>> {
+    let bytes = input.as_bytes();
+    let body = input.strip_prefix(BYTE_ORDER_MARK).unwrap_or(input);
+    let open = input.len() - body.trim_start().len();
+    if bytes.get(open) != Some(&b'[') {
+        return None;
+    }
+
+    let mut elements = Vec::new();
+    let mut start = open + 1;
+    let mut depth = 0usize;
+    let mut i = start;
+    let close = loop {
+        match *bytes.get(i)? {
+            b'"' => {
+                // Skip the string, stepping over escaped characters
+                i += 1;
+                loop {
+                    match *bytes.get(i)? {
+                        b'"' => break,
+                        b'\\' => i += 2,
+                        _ => i += 1,
+                    }
+                }
+            }
+            b'[' | b'{' => depth += 1,
+            b']' | b'}' if depth > 0 => depth -= 1,
+            b']' => break i,
+            b',' if depth == 0 => {
+                elements.push(start..i);
+                start = i + 1;
+            }
+            b'}' => return None,
+            _ => {}
+        }
+        i += 1;
+    };
+    if !input[close + 1..].trim_start().is_empty() {
+        return None;
+    }
+
+    let last = start..close;
+    let last_is_empty = input[last.clone()].trim_start().is_empty();
+    if !last_is_empty {
+        elements.push(last);
+    } else if !elements.is_empty() && !options.allow_trailing_commas {
+        return None;
+    }
+    // An element holding only whitespace is a stray comma
+    if elements
+        .iter()
+        .any(|range| input[range.clone()].trim_start().is_empty())
+    {
+        return None;
+    }
+    Some(elements)
+}
+
+/// Group consecutive elements into ranges of about equal length, a few per
+/// thread so that uneven chunks even out
+fn chunk_elements(elements: &[Range<usize>], input_len: usize) -> Vec<Range<usize>> {
+    let target = (input_len / (4 * rayon::current_num_threads())).max(MIN_CHUNK_LEN);
+    let mut chunks: Vec<Range<usize>> = Vec::new();
+    for element in elements {
+        match chunks.last_mut() {
+            Some(chunk) if chunk.len() < target => chunk.end = element.end,
+            _ => chunks.push(element.clone()),
+        }
+    }
+    chunks
+}
+
+/// Parse the comma-separated elements in `text`, returning them with the
+/// number of values and bytes charged
+fn parse_chunk(
+    text: &str,
+    options: ParserOptions,
+) -> Result<(Vec<JsonValue>, (usize, usize)), ParseError> {
+    let lexer = Lexer::fragment(text, &options);
+    let mut parser = Parser::with_tokens(Tokens::Str(lexer), options)?;
+    // The elements sit inside the root array
+    parser.set_depth(1);
+    let mut values = Vec::new();
+    loop {
+        values.push(parser.parse_value()?);
+        match parser.current_token().into_owned() {
+            Token::Comma => parser.advance()?,
+            Token::Eof => break,
+            _ => return Err(parser.error("Expected ',' or ']'")),
+        }
+    }
+    Ok((values, parser.usage()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_json;
+
+    /// An array of `count` records whose strings hold brackets and commas
+    fn records(count: usize) -> String {
+        let records: Vec<String> = (0..count)
+            .map(|i| {
+                format!(
+                    r#"{{"id": {}, "name": "rec\"ord,[{}]", "nested": [[{{}}], {{"a": [1, 2]}}]}}"#,
+                    i, i
+                )
+            })
+            .collect();
+        format!(" \n[{}]\n", records.join(" ,\n"))
+    }
+
+    /// Run `f` on a pool with several threads, whatever the machine has
+    fn on_pool<T: Send>(f: impl FnOnce() -> T + Send) -> T {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(4)
+            .build()
+            .unwrap()
+            .install(f)
+    }
+
+    #[test]
+    fn test_matches_sequential_parsing() {
+        on_pool(|| {
+            let input = records(20_000);
+            assert!(input.len() > MIN_PARALLEL_LEN);
+            let elements = split_elements(&input, &ParserOptions::default()).unwrap();
+            assert_eq!(elements.len(), 20_000);
+            assert!(chunk_elements(&elements, input.len()).len() > 1);
+            assert_eq!(parse_parallel(&input).unwrap(), parse_json(&input).unwrap());
+
+            let trailing = format!("{},]", input.trim_end().trim_end_matches(']'));
+            assert!(parse_parallel(&trailing).is_err());
+            let relaxed = ParserOptions::default().allow_trailing_commas(true);
+            let value = parse_parallel_with_options(&trailing, relaxed).unwrap();
+            assert_eq!(value.as_array().unwrap().len(), 20_000);
+
+            // Roots other than arrays and small inputs take the sequential path
+            assert_eq!(parse_parallel("[]").unwrap(), JsonValue::Array(Vec::new()));
+            assert_eq!(
+                parse_parallel("{\"a\": 1}").unwrap(),
+                parse_json("{\"a\": 1}").unwrap()
+            );
+        });
+    }
+
+    #[test]
+    fn test_errors_and_limits_match_sequential_parsing() {
+        on_pool(|| {
+            let input = records(20_000);
+            let broken = [
+                input.replacen("\"id\": 7,", "\"id\": 7", 1),
+                input.replacen("\"id\": 19000,", "\"id\": 19000,,", 1),
+                input.replacen(" ,\n", " ,\n,", 1),
+                format!("{}x", input),
+                input.trim_end().trim_end_matches(']').to_string(),
+                input.replacen("[[{}]", "[[{]", 1),
+            ];
+            for text in &broken {
+                assert_eq!(
+                    parse_parallel(text).unwrap_err(),
+                    parse_json(text).unwrap_err()
+                );
+            }
+
+            let limits = [
+                ParserOptions::default().max_depth(Some(3)),
+                ParserOptions::default().max_nodes(Some(100_000)),
+                ParserOptions::default().max_elements(Some(19_999)),
+                ParserOptions::default().max_memory(Some(1 << 20)),
+            ];
+            for options in limits {
+                let expected = Parser::with_options(&input, options)
+                    .unwrap()
+                    .parse()
+                    .unwrap_err();
+                let error = parse_parallel_with_options(&input, options).unwrap_err();
+                assert_eq!(error, expected);
+            }
+        });
+    }
+}
@@ -5,216 +5,692 @@
 /// 2. Directly mirrors the grammar structure
 /// 3. Good error messages - can pinpoint exact location
 /// 4. No external dependencies needed
+use crate::error::{ErrorKind, ParseError};
+use crate::json::{JsonValue, unescape_content};
+use crate::lexer::{Lexer, SpannedToken, Token, TokenRef};
+use crate::number::JsonNumber;
+use crate::object::{DuplicateKeyPolicy, JsonObject};
+use crate::options::ParserOptions;
+use crate::plugin::ValuePlugin;
+use crate::reader::ReaderLexer;
+use std::borrow::Cow;
+use std::io::BufRead;
+use std::sync::Arc;
 
-use crate::error::ParseError;
-use crate::json::JsonValue;
-use crate::lexer::{Lexer, Token};
+/// Where the parser's tokens come from
+pub(crate) enum Tokens<'a> {
+    Str(Lexer<'a>),
+    Reader(Box<ReaderLexer<Box<dyn BufRead + Send + 'a>>>),
+    Buffer(TokenBuffer),
+}
+
+/// Tokens lexed before the parser was created, see `Parser::from_tokens`
+pub(crate) struct TokenBuffer {
+    tokens: std::vec::IntoIter<SpannedToken>,
+    /// Offset, line and column where the token last handed out starts
+    start: (usize, usize, usize),
+    /// Offset, line and column where it ends, which is where `Eof` is
+    /// placed when the buffer does not end with one
+    end: (usize, usize, usize),
+}
+
+impl TokenBuffer {
+    fn next_token(&mut self) -> Token {
+        match self.tokens.next() {
+            Some(spanned) => {
+                let width = spanned.end.saturating_sub(spanned.start);
+                self.start = (spanned.start, spanned.line, spanned.column);
+                self.end = (spanned.end, spanned.line, spanned.column + width);
+                spanned.token
+            }
+            None => {
+                self.start = self.end;
+                Token::Eof
+            }
+        }
+    }
+}
+
+/// Start of a token; reader input also records line and column, since the
+/// text before the token is no longer available to compute them
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct TokenStart {
+    offset: usize,
+    line_column: Option<(usize, usize)>,
+}
+
+impl<'a> Tokens<'a> {
+    /// Next token; strings of `Str` input borrow from it where possible
+    fn next_token(&mut self) -> Result<TokenRef<'a>, ParseError> {
+        let token = match self {
+            Tokens::Str(lexer) => return lexer.next_token_ref(),
+            Tokens::Reader(lexer) => lexer.next_token()?,
+            Tokens::Buffer(buffer) => buffer.next_token(),
+        };
+        Ok(match token {
+            Token::String(s) => TokenRef::Str(Cow::Owned(s)),
+            token => TokenRef::Token(token),
+        })
+    }
+
+    /// Start of the token returned by the last `next_token`
+    fn token_start(&self) -> TokenStart {
+        match self {
+            Tokens::Str(lexer) => TokenStart {
+                offset: lexer.token_start,
+                line_column: None,
+            },
+            Tokens::Reader(lexer) => TokenStart {
+                offset: lexer.token_start,
+                line_column: Some((lexer.token_line, lexer.token_column)),
+            },
+            Tokens::Buffer(buffer) => {
+                let (offset, line, column) = buffer.start;
+                TokenStart {
+                    offset,
+                    line_column: Some((line, column)),
+                }
+            }
+        }
+    }
+
+    fn error_at(&self, message: impl Into<String>, at: TokenStart) -> ParseError {
+        match (self, at.line_column) {
+            (Tokens::Str(lexer), _) => ParseError::new(message, at.offset, lexer.input()),
+            (Tokens::Reader(_) | Tokens::Buffer(_), Some((line, column))) => {
+                ParseError::at(message, at.offset, line, column)
+            }
+            (Tokens::Reader(_) | Tokens::Buffer(_), None) => {
+                unreachable!("reader and buffered tokens carry their line")
+            }
+        }
+    }
+}
+
+/// A scalar value as read by the parser
+pub(crate) enum Scalar<'a> {
+    Null,
+    Boolean(bool),
+    Number(JsonNumber),
+    /// Borrowed from the input unless it contained escape sequences
+    String(Cow<'a, str>),
+    /// Escaped content of a string with unpaired surrogates, only read with
+    /// `preserve_lone_surrogates`
+    RawString(String),
+}
+
+/// Assembles the values the parser reads into a tree
+///
+/// The parser checks the grammar, the options and the limits and hands
+/// the builder nothing but valid values, so its methods cannot fail. This
+/// lets the borrowed and arena DOMs share the parser with `JsonValue`.
+pub(crate) trait Builder<'a> {
+    type Value;
+    /// Array under construction
+    type Array;
+    /// Object under construction
+    type Object;
+
+    fn scalar(&mut self, scalar: Scalar<'a>) -> Self::Value;
+
+    fn begin_array(&mut self) -> Self::Array;
+
+    fn push_element(&mut self, array: &mut Self::Array, element: Self::Value);
+
+    fn end_array(&mut self, array: Self::Array) -> Self::Value;
+
+    fn begin_object(&mut self) -> Self::Object;
+
+    /// Number of members added to `object` so far
+    fn object_len(&self, object: &Self::Object) -> usize;
+
+    /// Whether `object` has a member `key`; only asked when the duplicate
+    /// key policy is not `Last`
+    fn contains_key(&mut self, object: &mut Self::Object, key: &str) -> bool;
+
+    /// Add a member; a repeated key must read as the new value afterwards
+    fn insert_member(&mut self, object: &mut Self::Object, key: Cow<'a, str>, value: Self::Value);
+
+    fn end_object(&mut self, object: Self::Object) -> Self::Value;
+
+    /// Bytes an object key of `len` bytes takes up, for `max_memory`
+    fn key_size(len: usize) -> usize;
+}
+
+/// Text of a raw string with its unpaired surrogates replaced by U+FFFD,
+/// for trees without a raw string value
+pub(crate) fn lossy_string(raw: String) -> String {
+    match unescape_content(&raw, '"', true) {
+        Ok((unescaped, _)) => unescaped.into_owned(),
+        Err(_) => raw,
+    }
+}
+
+/// Builds `JsonValue` trees, offering every object to the plugins
+#[derive(Default)]
+pub(crate) struct JsonBuilder {
+    plugins: Vec<Arc<dyn ValuePlugin>>,
+}
+
+impl<'a> Builder<'a> for JsonBuilder {
+    type Value = JsonValue;
+    type Array = Vec<JsonValue>;
+    type Object = JsonObject;
+
+    fn scalar(&mut self, scalar: Scalar<'a>) -> JsonValue {
+        match scalar {
+            Scalar::Null => JsonValue::Null,
+            Scalar::Boolean(b) => JsonValue::Boolean(b),
+            Scalar::Number(n) => JsonValue::Number(n),
+            Scalar::String(s) => JsonValue::String(s.into_owned()),
+            Scalar::RawString(raw) => JsonValue::RawString(raw),
+        }
+    }
+
+    fn begin_array(&mut self) -> Vec<JsonValue> {
+        Vec::new()
+    }
+
+    fn push_element(&mut self, array: &mut Vec<JsonValue>, element: JsonValue) {
+        array.push(element);
+    }
+
+    fn end_array(&mut self, array: Vec<JsonValue>) -> JsonValue {
+        JsonValue::Array(array)
+    }
+
+    fn begin_object(&mut self) -> JsonObject {
+        JsonObject::new()
+    }
+
+    fn object_len(&self, object: &JsonObject) -> usize {
+        object.len()
+    }
+
+    fn contains_key(&mut self, object: &mut JsonObject, key: &str) -> bool {
+        object.contains_key(key)
+    }
+
+    fn insert_member(&mut self, object: &mut JsonObject, key: Cow<'a, str>, value: JsonValue) {
+        object.insert(key.into_owned(), value);
+    }
+
+    fn end_object(&mut self, object: JsonObject) -> JsonValue {
+        self.plugins
+            .iter()
+            .find_map(|plugin| plugin.convert(&object))
+            .unwrap_or(JsonValue::Object(object))
+    }
+
+    fn key_size(len: usize) -> usize {
+        // Keys are stored twice: in the member list and in the lookup index
+        2 * (std::mem::size_of::<String>() + len)
+    }
+}
 
 pub struct Parser<'a> {
-    lexer: Lexer<'a>,
-    current_token: Token,
-    peek_token: Option<Token>,
+    lexer: Tokens<'a>,
+    current_token: TokenRef<'a>,
+    /// Where `current_token` starts, used to locate errors
+    current_start: TokenStart,
+    plugins: Vec<Arc<dyn ValuePlugin>>,
+    options: ParserOptions,
+    depth: usize,
+    memory_used: usize,
+    nodes: usize,
 }
 
 impl<'a> Parser<'a> {
     pub fn new(input: &'a str) -> Result<Self, ParseError> {
-        let mut lexer = Lexer::new(input);
-        let current_token = lexer.next_token()?;
-        let peek_token = if current_token != Token::Eof {
-            Some(lexer.next_token().unwrap_or(Token::Eof))
-        } else {
-            Some(Token::Eof)
+        Self::with_options(input, ParserOptions::default())
+    }
+
+    /// Create a parser with explicit limits and relaxations
+    pub fn with_options(input: &'a str, options: ParserOptions) -> Result<Self, ParseError> {
+        if let Some(max) = options.max_input_size
+            && input.len() > max
+        {
+            return Err(ParseError::new(
+                format!("Input size of {} bytes exceeds the limit of {}", input.len(), max),
+                0,
+                input,
+            )
+            .with_kind(ErrorKind::InputTooLarge));
+        }
+
+        Self::with_tokens(Tokens::Str(Lexer::with_options(input, &options)), options)
+    }
+
+    /// Parser over tokens lexed earlier, e.g. by a syntax highlighter that
+    /// already walked `Lexer::spanned`
+    ///
+    /// Parsing stops at the first `Eof` token, or after the last token if
+    /// there is none. Errors are located with the tokens' offsets, lines and
+    /// columns.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use json_parser_rs::lexer::{Lexer, SpannedToken};
+    /// use json_parser_rs::{Parser, json};
+    ///
+    /// let input = r#"{"tags": ["a", "b"]}"#;
+    /// let tokens: Result<Vec<SpannedToken>, _> = Lexer::new(input).spanned().collect();
+    /// let tokens = tokens.unwrap();
+    /// // ... highlight `input` using `tokens` ...
+    /// let value = Parser::from_tokens(tokens).and_then(|mut parser| parser.parse()).unwrap();
+    /// assert_eq!(value, json!({"tags": ["a", "b"]}));
+    /// ```
+    pub fn from_tokens(tokens: Vec<SpannedToken>) -> Result<Self, ParseError> {
+        Self::from_tokens_with_options(tokens, ParserOptions::default())
+    }
+
+    /// Token-buffer parser with explicit options
+    ///
+    /// Options deciding how text is lexed (comments, quotes, string limits)
+    /// were up to the lexer; the structural ones, such as trailing commas,
+    /// unquoted keys, duplicate key handling and the depth, node and memory
+    /// limits, apply as usual.
+    pub fn from_tokens_with_options(
+        tokens: Vec<SpannedToken>,
+        options: ParserOptions,
+    ) -> Result<Self, ParseError> {
+        let buffer = TokenBuffer {
+            tokens: tokens.into_iter(),
+            start: (0, 1, 1),
+            end: (0, 1, 1),
         };
+        Self::with_tokens(Tokens::Buffer(buffer), options)
+    }
+
+    /// Parser over an arbitrary token source
+    pub(crate) fn with_tokens(
+        mut lexer: Tokens<'a>,
+        options: ParserOptions,
+    ) -> Result<Self, ParseError> {
+        let current_token = lexer.next_token()?;
+        let current_start = lexer.token_start();
 
         Ok(Self {
             lexer,
             current_token,
-            peek_token,
+            current_start,
+            plugins: Vec::new(),
+            options,
+            depth: 0,
+            memory_used: 0,
+            nodes: 0,
         })
     }
 
-    fn advance(&mut self) -> Result<(), ParseError> {
-        self.current_token = self.peek_token.take().unwrap_or(Token::Eof);
-        if self.current_token != Token::Eof {
-            self.peek_token = Some(self.lexer.next_token().unwrap_or(Token::Eof));
-        } else {
-            self.peek_token = Some(Token::Eof);
+    /// Register a plugin that may replace parsed objects with custom values
+    ///
+    /// Plugins are consulted in registration order; the first one returning
+    /// a value wins.
+    pub fn register_plugin(&mut self, plugin: Arc<dyn ValuePlugin>) {
+        self.plugins.push(plugin);
+    }
+
+    /// Choose how repeated keys inside one object are handled
+    /// (default: the last occurrence wins)
+    pub fn set_duplicate_key_policy(&mut self, policy: DuplicateKeyPolicy) {
+        self.options.duplicate_keys = policy;
+    }
+
+    /// Track entering an array or object, enforcing `max_depth`
+    fn enter_nested(&mut self) -> Result<(), ParseError> {
+        self.depth += 1;
+        match self.options.max_depth {
+            Some(max) if self.depth > max => Err(self.error(
+                format!("Maximum nesting depth of {} exceeded", max),
+            )
+            .with_kind(ErrorKind::DepthLimitExceeded)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Check that a container holding `len` elements may take another one,
+    /// enforcing `max_elements`
+    fn check_elements(&self, len: usize, container: &str) -> Result<(), ParseError> {
+        match self.options.max_elements {
+            Some(max) if len >= max => Err(self
+                .error(format!("{} has more than {} elements", container, max))
+                .with_kind(ErrorKind::TooManyElements)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Account for `bytes` of the parsed tree, enforcing `max_memory`
+    fn charge(&mut self, bytes: usize) -> Result<(), ParseError> {
+        self.memory_used += bytes;
+        match self.options.max_memory {
+            Some(max) if self.memory_used > max => Err(self
+                .error(format!("Memory limit of {} bytes exceeded", max))
+                .with_kind(ErrorKind::MemoryLimitExceeded)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Start a fresh memory budget (used when values are handed out one by one)
+    pub(crate) fn reset_memory(&mut self) {
+        self.memory_used = 0;
+    }
+
+    /// The current token as a `Token`, for messages and error kinds
+    pub(crate) fn current_token(&self) -> Cow<'_, Token> {
+        match &self.current_token {
+            TokenRef::Str(s) => Cow::Owned(Token::String(s.to_string())),
+            TokenRef::Token(token) => Cow::Borrowed(token),
         }
-        Ok(())
     }
 
-    fn expect_token(&mut self, expected: Token) -> Result<(), ParseError> {
-        if std::mem::discriminant(&self.current_token) == std::mem::discriminant(&expected) {
+    fn at(&self, token: &Token) -> bool {
+        matches!(&self.current_token, TokenRef::Token(current) if current == token)
+    }
+
+    /// Error located at the start of the current token
+    pub(crate) fn error(&self, message: impl Into<String>) -> ParseError {
+        self.lexer.error_at(message, self.current_start)
+    }
+
+    /// Move to the next token; lexer errors surface here with their own
+    /// position, the current token is left untouched
+    pub(crate) fn advance(&mut self) -> Result<(), ParseError> {
+        self.take_token().map(drop)
+    }
+
+    /// Move to the next token like `advance`, returning the current one
+    fn take_token(&mut self) -> Result<TokenRef<'a>, ParseError> {
+        if self.at(&Token::Eof) {
+            return Ok(TokenRef::Token(Token::Eof));
+        }
+        let next = self.lexer.next_token()?;
+        self.current_start = self.lexer.token_start();
+        Ok(std::mem::replace(&mut self.current_token, next))
+    }
+
+    pub(crate) fn expect_token(&mut self, expected: Token) -> Result<(), ParseError> {
+        let current = self.current_token();
+        if std::mem::discriminant(&*current) == std::mem::discriminant(&expected) {
             self.advance()?;
             Ok(())
         } else {
-            Err(ParseError::new(
+            Err(self.error(
                 format!(
-                    "Expected {:?}, found {:?}",
-                    expected, self.current_token
+                    "Expected {}, found {}",
+                    expected, current
                 ),
-                self.lexer.position,
-                self.lexer.input,
-            ))
+            )
+            .with_kind(ErrorKind::unexpected(&current)))
         }
     }
 
+    /// Run `parse` with the builder of `JsonValue` trees, which holds the
+    /// registered plugins
+    fn with_json_builder<T>(&mut self, parse: impl FnOnce(&mut Self, &mut JsonBuilder) -> T) -> T {
+        let mut builder = JsonBuilder {
+            plugins: std::mem::take(&mut self.plugins),
+        };
+        let result = parse(self, &mut builder);
+        self.plugins = builder.plugins;
+        result
+    }
+
     /// Parse JSON value from input
     pub fn parse(&mut self) -> Result<JsonValue, ParseError> {
-        let value = self.parse_value()?;
+        self.with_json_builder(|parser, builder| parser.parse_with(builder))
+    }
+
+    /// Parse the whole input into the tree `builder` assembles
+    pub(crate) fn parse_with<B: Builder<'a>>(
+        &mut self,
+        builder: &mut B,
+    ) -> Result<B::Value, ParseError> {
+        let value = self.parse_value_with(builder)?;
         
         // Ensure we've consumed all input
-        if self.current_token != Token::Eof {
-            return Err(ParseError::new(
-                format!("Unexpected token after JSON value: {:?}", self.current_token),
-                self.lexer.position,
-                self.lexer.input,
-            ));
+        if !self.at(&Token::Eof) {
+            return Err(self.error(
+                format!("Unexpected token after JSON value: {}", self.current_token()),
+            )
+            .with_kind(ErrorKind::TrailingCharacters));
         }
         
         Ok(value)
     }
 
-    fn parse_value(&mut self) -> Result<JsonValue, ParseError> {
+    pub(crate) fn parse_value(&mut self) -> Result<JsonValue, ParseError> {
+        self.with_json_builder(|parser, builder| parser.parse_value_with(builder))
+    }
+
+    fn parse_value_with<B: Builder<'a>>(
+        &mut self,
+        builder: &mut B,
+    ) -> Result<B::Value, ParseError> {
+        self.nodes += 1;
+        if let Some(max) = self.options.max_nodes
+            && self.nodes > max
+        {
+            return Err(self
+                .error(format!("Document has more than {} values", max))
+                .with_kind(ErrorKind::NodeLimitExceeded));
+        }
+
+        let size = std::mem::size_of::<B::Value>()
+            + match &self.current_token {
+                TokenRef::Str(s) => s.len(),
+                TokenRef::Token(Token::RawString(s)) => s.len(),
+                _ => 0,
+            };
+        self.charge(size)?;
+
         match &self.current_token {
-            Token::String(s) => {
-                let value = JsonValue::String(s.clone());
-                self.advance()?;
-                Ok(value)
-            }
-            Token::Number(n) => {
-                let value = JsonValue::Number(*n);
-                self.advance()?;
-                Ok(value)
+            TokenRef::Str(_)
+            | TokenRef::Token(
+                Token::RawString(_) | Token::Number(_) | Token::Boolean(_) | Token::Null,
+            ) => {
+                let scalar = match self.take_token()? {
+                    TokenRef::Str(s) => Scalar::String(s),
+                    TokenRef::Token(Token::RawString(raw)) => Scalar::RawString(raw),
+                    TokenRef::Token(Token::Number(n)) => Scalar::Number(n),
+                    TokenRef::Token(Token::Boolean(b)) => Scalar::Boolean(b),
+                    _ => Scalar::Null,
+                };
+                Ok(builder.scalar(scalar))
             }
-            Token::Boolean(b) => {
-                let value = JsonValue::Boolean(*b);
-                self.advance()?;
-                Ok(value)
+            TokenRef::Token(Token::LeftBrace) => {
+                self.enter_nested()?;
+                let value = self.parse_object(builder);
+                self.depth -= 1;
+                value
             }
-            Token::Null => {
-                self.advance()?;
-                Ok(JsonValue::Null)
+            TokenRef::Token(Token::LeftBracket) => {
+                self.enter_nested()?;
+                let value = self.parse_array(builder);
+                self.depth -= 1;
+                value
             }
-            Token::LeftBrace => self.parse_object(),
-            Token::LeftBracket => self.parse_array(),
-            _ => Err(ParseError::new(
-                format!("Unexpected token: {:?}", self.current_token),
-                self.lexer.position,
-                self.lexer.input,
-            )),
+            _ => Err(self.error(
+                format!("Unexpected token: {}", self.current_token()),
+            )
+            .with_kind(ErrorKind::unexpected(&self.current_token()))),
         }
     }
 
-    fn parse_object(&mut self) -> Result<JsonValue, ParseError> {
+    fn parse_object<B: Builder<'a>>(&mut self, builder: &mut B) -> Result<B::Value, ParseError> {
         self.expect_token(Token::LeftBrace)?;
 
-        let mut pairs = Vec::new();
+        let mut object = builder.begin_object();
 
         // Handle empty object
-        if matches!(self.current_token, Token::RightBrace) {
+        if self.at(&Token::RightBrace) {
             self.advance()?;
-            return Ok(JsonValue::Object(pairs));
+            return Ok(builder.end_object(object));
         }
 
         loop {
-            // Parse key (must be a string)
-            let key = match &self.current_token {
-                Token::String(s) => {
-                    let key = s.clone();
-                    self.advance()?;
-                    key
-                }
-                _ => {
-                    return Err(ParseError::new(
-                        "Object key must be a string",
-                        self.lexer.position,
-                        self.lexer.input,
-                    ))
-                }
-            };
+            self.check_elements(builder.object_len(&object), "Object")?;
+            let key_start = self.current_start;
+            let key = self.parse_key_for::<B>()?;
 
             // Expect colon
             self.expect_token(Token::Colon)?;
 
-            // Parse value
-            let value = self.parse_value()?;
-            pairs.push((key, value));
+            // Reject a duplicate before its value is parsed, so that an error
+            // in the value cannot hide it
+            let policy = self.options.duplicate_keys;
+            let duplicate =
+                policy != DuplicateKeyPolicy::Last && builder.contains_key(&mut object, &key);
+            if duplicate && policy == DuplicateKeyPolicy::Error {
+                return Err(self.lexer.error_at(
+                    format!("Duplicate key: \"{}\"", key),
+                    key_start,
+                )
+                .with_kind(ErrorKind::DuplicateKey));
+            }
+
+            // Parse value; under `First` a duplicate's value is dropped
+            let value = self.parse_value_with(builder)?;
+            if !duplicate {
+                builder.insert_member(&mut object, key, value);
+            }
 
             // Check for comma or closing brace
-            match self.current_token {
-                Token::Comma => {
+            match &self.current_token {
+                TokenRef::Token(Token::Comma) => {
                     self.advance()?;
                     // Check for trailing comma
-                    if matches!(self.current_token, Token::RightBrace) {
-                        return Err(ParseError::new(
+                    if self.at(&Token::RightBrace) {
+                        if self.options.allow_trailing_commas {
+                            self.advance()?;
+                            break;
+                        }
+                        return Err(self.error(
                             "Trailing comma not allowed",
-                            self.lexer.position,
-                            self.lexer.input,
-                        ));
+                        )
+                        .with_kind(ErrorKind::TrailingComma));
                     }
                 }
-                Token::RightBrace => {
+                TokenRef::Token(Token::RightBrace) => {
                     self.advance()?;
                     break;
                 }
                 _ => {
-                    return Err(ParseError::new(
-                        format!("Expected ',' or '}}', found {:?}", self.current_token),
-                        self.lexer.position,
-                        self.lexer.input,
-                    ))
+                    return Err(self.error(
+                        format!("Expected ',' or '}}', found {}", self.current_token()),
+                    )
+                    .with_kind(ErrorKind::unexpected(&self.current_token())))
                 }
             }
         }
 
-        Ok(JsonValue::Object(pairs))
+        Ok(builder.end_object(object))
+    }
+
+    /// Parse an object key (must be a string, or an identifier in JSON5 mode)
+    pub(crate) fn parse_key(&mut self) -> Result<String, ParseError> {
+        self.parse_key_for::<JsonBuilder>().map(Cow::into_owned)
+    }
+
+    /// Parse an object key, charging its size in the trees `B` builds
+    fn parse_key_for<B: Builder<'a>>(&mut self) -> Result<Cow<'a, str>, ParseError> {
+        let key = match &self.current_token {
+            TokenRef::Str(s) => s.clone(),
+            TokenRef::Token(Token::Identifier(name)) if self.options.allow_unquoted_keys => {
+                Cow::Owned(name.clone())
+            }
+            TokenRef::Token(Token::Boolean(b)) if self.options.allow_unquoted_keys => {
+                Cow::Owned(b.to_string())
+            }
+            TokenRef::Token(Token::Null) if self.options.allow_unquoted_keys => {
+                Cow::Borrowed("null")
+            }
+            TokenRef::Token(Token::RawString(_)) => {
+                return Err(self
+                    .error("Unpaired surrogate in object key")
+                    .with_kind(ErrorKind::InvalidEscape));
+            }
+            TokenRef::Token(Token::Eof) => {
+                return Err(self
+                    .error("Object key must be a string")
+                    .with_kind(ErrorKind::UnexpectedEof));
+            }
+            _ => {
+                return Err(self
+                    .error("Object key must be a string")
+                    .with_kind(ErrorKind::KeyMustBeString));
+            }
+        };
+        self.charge(B::key_size(key.len()))?;
+        self.advance()?;
+        Ok(key)
+    }
+
+    pub(crate) fn options(&self) -> &ParserOptions {
+        &self.options
     }
 
-    fn parse_array(&mut self) -> Result<JsonValue, ParseError> {
+    /// Mark that the parser is inside one (streamed) container
+    pub(crate) fn set_depth(&mut self, depth: usize) {
+        self.depth = depth;
+    }
+
+    /// Values parsed and bytes charged so far, for callers combining the
+    /// budgets of several parsers
+    #[cfg(feature = "rayon")]
+    pub(crate) fn usage(&self) -> (usize, usize) {
+        (self.nodes, self.memory_used)
+    }
+
+    fn parse_array<B: Builder<'a>>(&mut self, builder: &mut B) -> Result<B::Value, ParseError> {
         self.expect_token(Token::LeftBracket)?;
 
-        let mut elements = Vec::new();
+        let mut elements = builder.begin_array();
+        let mut len = 0;
 
         // Handle empty array
-        if matches!(self.current_token, Token::RightBracket) {
+        if self.at(&Token::RightBracket) {
             self.advance()?;
-            return Ok(JsonValue::Array(elements));
+            return Ok(builder.end_array(elements));
         }
 
         loop {
             // Parse element
-            let element = self.parse_value()?;
-            elements.push(element);
+            self.check_elements(len, "Array")?;
+            let element = self.parse_value_with(builder)?;
+            builder.push_element(&mut elements, element);
+            len += 1;
 
             // Check for comma or closing bracket
-            match self.current_token {
-                Token::Comma => {
+            match &self.current_token {
+                TokenRef::Token(Token::Comma) => {
                     self.advance()?;
                     // Check for trailing comma
-                    if matches!(self.current_token, Token::RightBracket) {
-                        return Err(ParseError::new(
+                    if self.at(&Token::RightBracket) {
+                        if self.options.allow_trailing_commas {
+                            self.advance()?;
+                            break;
+                        }
+                        return Err(self.error(
                             "Trailing comma not allowed",
-                            self.lexer.position,
-                            self.lexer.input,
-                        ));
+                        )
+                        .with_kind(ErrorKind::TrailingComma));
                     }
                 }
-                Token::RightBracket => {
+                TokenRef::Token(Token::RightBracket) => {
                     self.advance()?;
                     break;
                 }
                 _ => {
-                    return Err(ParseError::new(
-                        format!("Expected ',' or ']', found {:?}", self.current_token),
-                        self.lexer.position,
-                        self.lexer.input,
-                    ))
+                    return Err(self.error(
+                        format!("Expected ',' or ']', found {}", self.current_token()),
+                    )
+                    .with_kind(ErrorKind::unexpected(&self.current_token())))
                 }
             }
         }
 
-        Ok(JsonValue::Array(elements))
+        Ok(builder.end_array(elements))
     }
 }
 
@@ -226,7 +702,7 @@ mod tests {
     fn test_parse_empty_object() {
         let mut parser = Parser::new("{}").unwrap();
         let result = parser.parse().unwrap();
-        assert_eq!(result, JsonValue::Object(vec![]));
+        assert_eq!(result, JsonValue::Object(JsonObject::new()));
     }
 
     #[test]
@@ -234,12 +710,231 @@ mod tests {
         let mut parser = Parser::new(r#"{"key": "value"}"#).unwrap();
         let result = parser.parse().unwrap();
         match result {
-            JsonValue::Object(pairs) => {
-                assert_eq!(pairs.len(), 1);
-                assert_eq!(pairs[0].0, "key");
-                assert_eq!(pairs[0].1, JsonValue::String("value".to_string()));
+            JsonValue::Object(object) => {
+                assert_eq!(object.len(), 1);
+                assert_eq!(object.get("key"), Some(&JsonValue::String("value".to_string())));
             }
             _ => panic!("Expected object"),
         }
     }
+
+    #[test]
+    fn test_byte_order_mark() {
+        assert_eq!(crate::parse_json("\u{feff}{}").unwrap(), JsonValue::Object(JsonObject::new()));
+        assert_eq!(crate::parse_json("\u{feff} [1]").unwrap()[0].as_i64(), Some(1));
+        // Only a leading mark is skipped
+        assert!(crate::parse_json("[\u{feff}1]").is_err());
+        assert!(crate::parse_json(" \u{feff}[1]").is_err());
+    }
+
+    #[test]
+    fn test_parse_from_tokens() {
+        let spanned = |input| -> Vec<SpannedToken> {
+            Lexer::with_options(input, &ParserOptions::json5())
+                .spanned()
+                .map(Result::unwrap)
+                .collect()
+        };
+        let input = "{a: [1, 'x', {\"b\": null},],\n \"c\": true}";
+        let options = ParserOptions::json5();
+        let mut parser = Parser::from_tokens_with_options(spanned(input), options).unwrap();
+        assert_eq!(parser.parse().unwrap(), crate::parse_json5(input).unwrap());
+
+        // Errors are located like those of a parser over the text
+        for input in ["[1 2]", "{\"a\": 1,\n \"b\"}", "[1,]", "[[1]", "{\"a\": 1} 2"] {
+            let expected = crate::parse_json(input).unwrap_err();
+            let error = Parser::from_tokens(spanned(input))
+                .and_then(|mut parser| parser.parse())
+                .unwrap_err();
+            assert_eq!(
+                (error.kind(), error.position(), error.line(), error.column()),
+                (expected.kind(), expected.position(), expected.line(), expected.column()),
+                "{}",
+                input
+            );
+        }
+
+        // Hand-made tokens drive the parser without any text
+        let token = |token| SpannedToken {
+            token,
+            start: 0,
+            end: 0,
+            line: 1,
+            column: 1,
+        };
+        let tokens = vec![
+            token(Token::LeftBracket),
+            token(Token::Boolean(true)),
+            token(Token::RightBracket),
+            token(Token::Eof),
+            token(Token::Null),
+        ];
+        let value = Parser::from_tokens(tokens).unwrap().parse().unwrap();
+        assert_eq!(value, JsonValue::Array(vec![JsonValue::Boolean(true)]));
+        assert!(Parser::from_tokens(Vec::new()).unwrap().parse().is_err());
+    }
+
+    #[test]
+    fn test_duplicate_key_policies() {
+        let input = r#"{"a": 1, "b": 2, "a": 3}"#;
+
+        let result = Parser::new(input).unwrap().parse().unwrap();
+        assert_eq!(result.to_json_string(), r#"{"a": 3, "b": 2}"#);
+
+        let mut parser = Parser::new(input).unwrap();
+        parser.set_duplicate_key_policy(DuplicateKeyPolicy::First);
+        assert_eq!(parser.parse().unwrap().to_json_string(), r#"{"a": 1, "b": 2}"#);
+
+        let mut parser = Parser::new(input).unwrap();
+        parser.set_duplicate_key_policy(DuplicateKeyPolicy::Error);
+        assert!(parser.parse().is_err());
+
+        // The duplicate is reported at its key, before the value is read
+        let input = r#"{"a": 1, "a": [1, 2,"#;
+        let mut parser = Parser::new(input).unwrap();
+        parser.set_duplicate_key_policy(DuplicateKeyPolicy::Error);
+        let error = parser.parse().unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::DuplicateKey);
+        assert_eq!(error.position(), 9);
+
+        // Under `First` the dropped value must still be valid
+        let mut parser = Parser::new(r#"{"a": 1, "a": {"b": [2]}}"#).unwrap();
+        parser.set_duplicate_key_policy(DuplicateKeyPolicy::First);
+        assert_eq!(parser.parse().unwrap().to_json_string(), r#"{"a": 1}"#);
+        let mut parser = Parser::new(r#"{"a": 1, "a": [2,]}"#).unwrap();
+        parser.set_duplicate_key_policy(DuplicateKeyPolicy::First);
+        assert_eq!(parser.parse().unwrap_err().kind(), ErrorKind::TrailingComma);
+    }
+
+    #[test]
+    fn test_error_kinds() {
+        let cases = [
+            ("@", ErrorKind::UnexpectedCharacter),
+            ("[1 2]", ErrorKind::UnexpectedToken),
+            ("[1, ", ErrorKind::UnexpectedEof),
+            ("{\"a\": ", ErrorKind::UnexpectedEof),
+            ("\"abc", ErrorKind::UnterminatedString),
+            (r#""\x""#, ErrorKind::InvalidEscape),
+            ("-", ErrorKind::InvalidNumber),
+            ("nul", ErrorKind::InvalidKeyword),
+            ("[1,]", ErrorKind::TrailingComma),
+            ("{} {}", ErrorKind::TrailingCharacters),
+            ("{1: 2}", ErrorKind::KeyMustBeString),
+        ];
+        for (input, kind) in cases {
+            let error = crate::parse_json(input).unwrap_err();
+            assert_eq!(error.kind(), kind, "{}", input);
+            assert!(error.kind().is_syntax());
+        }
+        assert_eq!(
+            crate::parse_json("nul").unwrap_err().to_string(),
+            "Parse error at line 1, column 1: Unexpected keyword: nul"
+        );
+    }
+
+    #[test]
+    fn test_options_relaxations() {
+        let input = "{\"a\": [1, 2,], // note\n /* b */ \"b\": 3,}";
+        assert!(Parser::new(input).unwrap().parse().is_err());
+
+        let mut parser = Parser::with_options(input, ParserOptions::relaxed()).unwrap();
+        assert_eq!(parser.parse().unwrap().to_json_string(), r#"{"a": [1, 2], "b": 3}"#);
+
+        let options = ParserOptions::default().allow_comments(true);
+        assert!(Parser::with_options("[1,]", options).unwrap().parse().is_err());
+        assert!(Parser::with_options("[1 /* open", options).unwrap().parse().is_err());
+    }
+
+    #[test]
+    fn test_json5_extensions() {
+        let input = "{name: 'it\\'s', $id: 1, null: [true,], _x1: \"y\",}";
+        let mut parser = Parser::with_options(input, ParserOptions::json5()).unwrap();
+        assert_eq!(
+            parser.parse().unwrap().to_json_string(),
+            r#"{"name": "it's", "$id": 1, "null": [true], "_x1": "y"}"#
+        );
+
+        // Identifiers are keys, never values
+        let mut parser = Parser::with_options("{a: b}", ParserOptions::json5()).unwrap();
+        assert!(parser.parse().is_err());
+        assert!(Parser::new("{a: 1}").unwrap().parse().is_err());
+        assert!(Parser::new("['a']").unwrap().parse().is_err());
+    }
+
+    #[test]
+    fn test_hardened_limits() {
+        let parse = |input: &str, options| Parser::with_options(input, options)?.parse();
+        let hardened = ParserOptions::hardened();
+
+        assert!(parse(r#"{"a": ["b", 1, null]}"#, hardened).is_ok());
+        let cases = [
+            ("\"a\u{1}b\"", ErrorKind::ControlCharacter),
+            (r#"{"a": 1, "a": 2}"#, ErrorKind::DuplicateKey),
+            ("// c\n[1]", ErrorKind::UnexpectedCharacter),
+        ];
+        for (input, kind) in cases {
+            assert_eq!(parse(input, hardened).unwrap_err().kind(), kind, "{}", input);
+        }
+        let lenient = ParserOptions::default().reject_control_characters(false);
+        assert!(parse("\"a\u{1}b\"", lenient).is_ok());
+
+        let small = hardened.max_string_length(Some(3)).max_nodes(Some(3));
+        assert!(parse(r#"["abc"]"#, small).is_ok());
+        let error = parse(r#""abcd""#, small).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::StringTooLong);
+        let error = parse("[1, 2, 3]", small).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::NodeLimitExceeded);
+
+        let few = ParserOptions::default().max_elements(Some(2));
+        assert!(parse(r#"[[1, 2], {"a": 1, "b": 2}]"#, few).is_ok());
+        let error = parse("[1, 2, 3]", few).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::TooManyElements);
+        assert_eq!(error.message(), "Array has more than 2 elements");
+        assert_eq!(error.position(), 7);
+        let error = parse(r#"{"a": 1, "b": 2, "c": 3}"#, few).unwrap_err();
+        assert_eq!(error.message(), "Object has more than 2 elements");
+        // A full object refuses further members, even repeated keys
+        let last = few.duplicate_keys(DuplicateKeyPolicy::Last);
+        assert!(parse(r#"{"a": 1, "b": 2, "a": 3}"#, last).is_err());
+    }
+
+    #[test]
+    fn test_control_characters_rejected_by_default() {
+        let error = crate::parse_json("\n  \"tab\there\"").unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::ControlCharacter);
+        assert_eq!(error.message(), "Control character U+0009 in string");
+        assert_eq!((error.line(), error.column()), (2, 7));
+        assert!(crate::parse_json(r#""tab\there""#).is_ok());
+    }
+
+    #[test]
+    fn test_lexer_errors_after_first_token() {
+        let input = "{\"a\": [1,\n  \"b\tc\"]}";
+        let error = crate::parse_json(input).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::ControlCharacter);
+        assert_eq!((error.line(), error.column()), (2, 5));
+        let error = crate::parse_json_reader(input.as_bytes()).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::ControlCharacter);
+        assert_eq!((error.line(), error.column()), (2, 5));
+
+        let error = crate::parse_json("[1]\n x").unwrap_err();
+        assert_eq!(error.message(), "Unexpected keyword: x");
+        assert!(crate::parse_json_reader("[1]\n x".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_options_limits() {
+        let options = ParserOptions::default().max_depth(Some(2));
+        assert!(Parser::with_options("[[1]]", options).unwrap().parse().is_ok());
+        let error = Parser::with_options("[[[1]]]", options).unwrap().parse().unwrap_err();
+        assert_eq!(error.message(), "Maximum nesting depth of 2 exceeded");
+
+        let deep = "[".repeat(100_000);
+        let error = Parser::new(&deep).unwrap().parse().unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::DepthLimitExceeded);
+
+        let options = ParserOptions::default().max_input_size(Some(4));
+        assert!(Parser::with_options("[1]", options).is_ok());
+        assert!(Parser::with_options("[1, 2]", options).is_err());
+    }
 }
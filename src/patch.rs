@@ -0,0 +1,532 @@
+//! JSON Patch (RFC 6902) and JSON Merge Patch (RFC 7386)
+//!
+//! A JSON Patch is an array of operations (`add`, `remove`, `replace`,
+//! `move`, `copy`, `test`) addressing values by JSON Pointer. A merge patch
+//! is a partial document: its members replace those of the target, and
+//! `null` removes them. [`diff`] produces a JSON Patch turning one document
+//! into another.
+
+use std::fmt;
+
+use crate::json::JsonValue;
+use crate::object::JsonObject;
+use crate::path::values_equal;
+use crate::pointer::{JsonPointer, parse_index};
+
+/// Why a JSON Patch could not be applied, and which operation failed
+///
+/// # Examples
+///
+/// ```
+/// use json_parser_rs::json;
+/// use json_parser_rs::patch::PatchErrorKind;
+///
+/// let mut doc = json!({"a": 1});
+/// let patch = json!([
+///     {"op": "replace", "path": "/a", "value": 2},
+///     {"op": "remove", "path": "/missing"},
+/// ]);
+/// let error = doc.apply_patch(&patch).unwrap_err();
+/// assert_eq!(error.kind(), PatchErrorKind::PathNotFound);
+/// assert_eq!(error.operation(), 1);
+/// // Patches are atomic: the successful first operation was rolled back
+/// assert_eq!(doc, json!({"a": 1}));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PatchError {
+    kind: PatchErrorKind,
+    operation: usize,
+    message: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum PatchErrorKind {
+    /// The patch is not an array of well-formed operation objects
+    InvalidOperation,
+    /// `path` or `from` is not a valid JSON Pointer
+    InvalidPointer,
+    /// A location the operation needs does not exist
+    PathNotFound,
+    /// `move` into one of the moved value's own children
+    MoveIntoChild,
+    /// A `test` operation found a different value
+    TestFailed,
+}
+
+impl PatchError {
+    fn new(kind: PatchErrorKind, operation: usize, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            operation,
+            message: message.into(),
+        }
+    }
+
+    pub fn kind(&self) -> PatchErrorKind {
+        self.kind
+    }
+
+    /// Index of the failed operation within the patch
+    pub fn operation(&self) -> usize {
+        self.operation
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl fmt::Display for PatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "JSON Patch operation {} failed: {}",
+            self.operation, self.message
+        )
+    }
+}
+
+impl std::error::Error for PatchError {}
+
+impl JsonValue {
+    /// Apply an RFC 6902 JSON Patch
+    ///
+    /// The patch is atomic: if any operation fails, `self` is left
+    /// unchanged.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use json_parser_rs::json;
+    ///
+    /// let mut doc = json!({"name": "svc", "replicas": 1, "tags": ["a"]});
+    /// doc.apply_patch(&json!([
+    ///     {"op": "test", "path": "/name", "value": "svc"},
+    ///     {"op": "replace", "path": "/replicas", "value": 3},
+    ///     {"op": "add", "path": "/tags/-", "value": "b"},
+    ///     {"op": "move", "from": "/name", "path": "/id"},
+    /// ]))
+    /// .unwrap();
+    /// assert_eq!(doc, json!({"replicas": 3, "tags": ["a", "b"], "id": "svc"}));
+    /// ```
+    pub fn apply_patch(&mut self, patch: &JsonValue) -> Result<(), PatchError> {
+        let Some(operations) = patch.as_array() else {
+            return Err(PatchError::new(
+                PatchErrorKind::InvalidOperation,
+                0,
+                "patch must be an array of operations",
+            ));
+        };
+        let mut patched = self.clone();
+        for (index, operation) in operations.iter().enumerate() {
+            apply_operation(&mut patched, operation)
+                .map_err(|(kind, message)| PatchError::new(kind, index, message))?;
+        }
+        *self = patched;
+        Ok(())
+    }
+
+    /// Apply an RFC 7386 JSON Merge Patch
+    ///
+    /// Object members of `patch` are merged into `self` recursively; `null`
+    /// members remove the key. Any other patch value replaces `self`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use json_parser_rs::json;
+    ///
+    /// let mut config = json!({"db": {"host": "a", "port": 1}, "debug": true});
+    /// config.merge_patch(&json!({"db": {"port": 2}, "debug": null}));
+    /// assert_eq!(config, json!({"db": {"host": "a", "port": 2}}));
+    /// ```
+    pub fn merge_patch(&mut self, patch: &JsonValue) {
+        let Some(members) = patch.as_object() else {
+            *self = patch.clone();
+            return;
+        };
+        if !self.is_object() {
+            *self = JsonValue::Object(JsonObject::new());
+        }
+        let target = self.as_object_mut().expect("made an object above");
+        for (key, value) in members {
+            if value.is_null() {
+                target.remove(key);
+            } else if let Some(existing) = target.get_mut(key) {
+                existing.merge_patch(value);
+            } else {
+                let mut new = JsonValue::Null;
+                new.merge_patch(value);
+                target.insert(key.clone(), new);
+            }
+        }
+    }
+}
+
+/// Generate a JSON Patch that turns `from` into `to`
+///
+/// Objects and arrays are compared member by member, so unchanged parts do
+/// not appear in the patch. Array elements are compared by position; extra
+/// elements are appended or removed at the end.
+///
+/// # Examples
+///
+/// ```
+/// use json_parser_rs::json;
+/// use json_parser_rs::patch::diff;
+///
+/// let old = json!({"a": 1, "b": [1, 2], "c": true});
+/// let new = json!({"a": 2, "b": [1], "d": null});
+/// let patch = diff(&old, &new);
+/// assert_eq!(
+///     patch,
+///     json!([
+///         {"op": "replace", "path": "/a", "value": 2},
+///         {"op": "remove", "path": "/b/1"},
+///         {"op": "remove", "path": "/c"},
+///         {"op": "add", "path": "/d", "value": null},
+///     ])
+/// );
+///
+/// let mut patched = old.clone();
+/// patched.apply_patch(&patch).unwrap();
+/// assert_eq!(patched, new);
+/// ```
+pub fn diff(from: &JsonValue, to: &JsonValue) -> JsonValue {
+    let mut operations = Vec::new();
+    diff_into(from, to, &mut Vec::new(), &mut operations);
+    JsonValue::Array(operations)
+}
+
+fn diff_into(
+    from: &JsonValue,
+    to: &JsonValue,
+    path: &mut Vec<String>,
+    operations: &mut Vec<JsonValue>,
+) {
+    if from == to {
+        return;
+    }
+    match (from, to) {
+        (JsonValue::Object(old), JsonValue::Object(new)) => {
+            for (key, old_value) in old {
+                path.push(key.clone());
+                match new.get(key) {
+                    Some(new_value) => diff_into(old_value, new_value, path, operations),
+                    None => operations.push(operation("remove", path, None)),
+                }
+                path.pop();
+            }
+            for (key, new_value) in new {
+                if !old.contains_key(key) {
+                    path.push(key.clone());
+                    operations.push(operation("add", path, Some(new_value)));
+                    path.pop();
+                }
+            }
+        }
+        (JsonValue::Array(old), JsonValue::Array(new)) => {
+            for (i, (old_value, new_value)) in old.iter().zip(new).enumerate() {
+                path.push(i.to_string());
+                diff_into(old_value, new_value, path, operations);
+                path.pop();
+            }
+            // Remove from the back so earlier indices stay valid
+            for i in (new.len()..old.len()).rev() {
+                path.push(i.to_string());
+                operations.push(operation("remove", path, None));
+                path.pop();
+            }
+            for new_value in new.iter().skip(old.len()) {
+                path.push("-".to_string());
+                operations.push(operation("add", path, Some(new_value)));
+                path.pop();
+            }
+        }
+        _ => operations.push(operation("replace", path, Some(to))),
+    }
+}
+
+fn operation(op: &str, path: &[String], value: Option<&JsonValue>) -> JsonValue {
+    let mut object = JsonObject::new();
+    object.insert("op", JsonValue::from(op));
+    object.insert(
+        "path",
+        JsonValue::from(JsonPointer::from_tokens(path).to_string()),
+    );
+    if let Some(value) = value {
+        object.insert("value", value.clone());
+    }
+    JsonValue::Object(object)
+}
+
+type OperationError = (PatchErrorKind, String);
+
+fn apply_operation(document: &mut JsonValue, operation: &JsonValue) -> Result<(), OperationError> {
+    let invalid = |message: String| (PatchErrorKind::InvalidOperation, message);
+    let member = |name: &str| {
+        operation
+            .get(name)
+            .ok_or_else(|| invalid(format!("missing \"{}\"", name)))
+    };
+    let pointer = |name: &str| -> Result<Vec<String>, OperationError> {
+        let text = member(name)?
+            .as_str()
+            .ok_or_else(|| invalid(format!("\"{}\" must be a string", name)))?;
+        JsonPointer::parse(text)
+            .map(|pointer| pointer.tokens().to_vec())
+            .map_err(|e| (PatchErrorKind::InvalidPointer, e.to_string()))
+    };
+
+    let op = member("op")?
+        .as_str()
+        .ok_or_else(|| invalid("\"op\" must be a string".to_string()))?;
+    let path = pointer("path")?;
+    match op {
+        "add" => add(document, &path, member("value")?.clone()),
+        "remove" => remove(document, &path).map(drop),
+        "replace" => {
+            let target = resolve(document, &path)?;
+            *target = member("value")?.clone();
+            Ok(())
+        }
+        "move" => {
+            let from = pointer("from")?;
+            if path.len() > from.len() && path.starts_with(&from) {
+                return Err((
+                    PatchErrorKind::MoveIntoChild,
+                    format!(
+                        "cannot move {} into its own child {}",
+                        display(&from),
+                        display(&path)
+                    ),
+                ));
+            }
+            let value = remove(document, &from)?;
+            add(document, &path, value)
+        }
+        "copy" => {
+            let value = resolve(document, &pointer("from")?)?.clone();
+            add(document, &path, value)
+        }
+        "test" => {
+            // RFC 6902 compares numbers by value: 1 passes a test for 1.0
+            let expected = member("value")?;
+            if values_equal(resolve(document, &path)?, expected) {
+                Ok(())
+            } else {
+                Err((
+                    PatchErrorKind::TestFailed,
+                    format!("value at {} is not {}", display(&path), expected),
+                ))
+            }
+        }
+        other => Err(invalid(format!("unknown operation \"{}\"", other))),
+    }
+}
+
+fn display(tokens: &[String]) -> String {
+    format!("\"{}\"", JsonPointer::from_tokens(tokens))
+}
+
+fn not_found(tokens: &[String]) -> OperationError {
+    (
+        PatchErrorKind::PathNotFound,
+        format!("no value at {}", display(tokens)),
+    )
+}
+
+/// The existing value at `tokens`
+fn resolve<'v>(
+    document: &'v mut JsonValue,
+    tokens: &[String],
+) -> Result<&'v mut JsonValue, OperationError> {
+    let pointer = JsonPointer::from_tokens(tokens).to_string();
+    document
+        .pointer_mut(&pointer)
+        .ok_or_else(|| not_found(tokens))
+}
+
+fn add(
+    document: &mut JsonValue,
+    tokens: &[String],
+    value: JsonValue,
+) -> Result<(), OperationError> {
+    let Some((last, parent)) = tokens.split_last() else {
+        *document = value;
+        return Ok(());
+    };
+    match resolve(document, parent)?.untagged_mut() {
+        JsonValue::Object(object) => {
+            object.insert(last.clone(), value);
+            Ok(())
+        }
+        JsonValue::Array(elements) => {
+            let index = match last.as_str() {
+                "-" => elements.len(),
+                token => parse_index(token)
+                    .filter(|&i| i <= elements.len())
+                    .ok_or_else(|| not_found(tokens))?,
+            };
+            elements.insert(index, value);
+            Ok(())
+        }
+        _ => Err(not_found(tokens)),
+    }
+}
+
+fn remove(document: &mut JsonValue, tokens: &[String]) -> Result<JsonValue, OperationError> {
+    let Some((last, parent)) = tokens.split_last() else {
+        return Ok(std::mem::replace(document, JsonValue::Null));
+    };
+    let removed = match resolve(document, parent)?.untagged_mut() {
+        JsonValue::Object(object) => object.remove(last),
+        JsonValue::Array(elements) => parse_index(last)
+            .filter(|&i| i < elements.len())
+            .map(|i| elements.remove(i)),
+        _ => None,
+    };
+    removed.ok_or_else(|| not_found(tokens))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json;
+
+    #[test]
+    fn test_operations() {
+        let mut doc = json!({"a": {"b": [1, 2]}});
+        doc.apply_patch(&json!([
+            {"op": "add", "path": "/a/b/0", "value": 0},
+            {"op": "copy", "from": "/a/b", "path": "/c"},
+            {"op": "remove", "path": "/a/b/2"},
+            {"op": "move", "from": "/c", "path": "/a/d"},
+            {"op": "test", "path": "/a/d/2", "value": 2},
+            {"op": "add", "path": "", "value": {"root": true}},
+            {"op": "add", "path": "/root", "value": false},
+        ]))
+        .unwrap();
+        assert_eq!(doc, json!({"root": false}));
+    }
+
+    #[test]
+    fn test_test_compares_numbers_by_value() {
+        let doc = json!({"n": 1, "a": [1, {"x": 2.0}], "o": {"b": 10, "c": 100}});
+        let test = |path: &str, value: JsonValue| {
+            let mut doc = doc.clone();
+            doc.apply_patch(&json!([{"op": "test", "path": path, "value": value}]))
+                .map_err(|e| e.kind())
+        };
+        assert_eq!(test("/n", json!(1.0)), Ok(()));
+        assert_eq!(test("/a", json!([1.0, {"x": 2}])), Ok(()));
+        assert_eq!(test("/o", json!({"c": 1e2, "b": 10.0})), Ok(()));
+        assert_eq!(test("/n", json!(1.5)), Err(PatchErrorKind::TestFailed));
+        assert_eq!(test("/n", json!("1")), Err(PatchErrorKind::TestFailed));
+        assert_eq!(test("/a", json!([1])), Err(PatchErrorKind::TestFailed));
+        assert_eq!(test("/o", json!({"b": 10})), Err(PatchErrorKind::TestFailed));
+    }
+
+    #[test]
+    fn test_errors() {
+        let cases = [
+            (
+                json!({"op": "add", "value": 1}),
+                PatchErrorKind::InvalidOperation,
+            ),
+            (
+                json!({"op": "jump", "path": ""}),
+                PatchErrorKind::InvalidOperation,
+            ),
+            (
+                json!({"op": "remove", "path": "a"}),
+                PatchErrorKind::InvalidPointer,
+            ),
+            (
+                json!({"op": "add", "path": "/x/y", "value": 1}),
+                PatchErrorKind::PathNotFound,
+            ),
+            (
+                json!({"op": "add", "path": "/l/3", "value": 1}),
+                PatchErrorKind::PathNotFound,
+            ),
+            (
+                json!({"op": "remove", "path": "/l/01"}),
+                PatchErrorKind::PathNotFound,
+            ),
+            (
+                json!({"op": "move", "from": "/l", "path": "/l/0"}),
+                PatchErrorKind::MoveIntoChild,
+            ),
+            (
+                json!({"op": "test", "path": "/l", "value": [1]}),
+                PatchErrorKind::TestFailed,
+            ),
+        ];
+        for (operation, kind) in cases {
+            let mut doc = json!({"l": [1, 2]});
+            let error = doc.apply_patch(&json!([operation])).unwrap_err();
+            assert_eq!(error.kind(), kind, "{}", error);
+            assert_eq!(doc, json!({"l": [1, 2]}));
+        }
+    }
+
+    #[test]
+    fn test_merge_patch_rfc_examples() {
+        let cases = [
+            (json!({"a": "b"}), json!({"a": "c"}), json!({"a": "c"})),
+            (
+                json!({"a": "b"}),
+                json!({"b": "c"}),
+                json!({"a": "b", "b": "c"}),
+            ),
+            (json!({"a": "b"}), json!({"a": null}), json!({})),
+            (
+                json!({"a": [{"b": "c"}]}),
+                json!({"a": [1]}),
+                json!({"a": [1]}),
+            ),
+            (json!(["a", "b"]), json!(["c", "d"]), json!(["c", "d"])),
+            (json!({"a": "foo"}), json!("bar"), json!("bar")),
+            (
+                json!({"e": null}),
+                json!({"a": 1}),
+                json!({"e": null, "a": 1}),
+            ),
+            (
+                json!([1, 2]),
+                json!({"a": "b", "c": null}),
+                json!({"a": "b"}),
+            ),
+            (
+                json!({}),
+                json!({"a": {"bb": {"ccc": null}}}),
+                json!({"a": {"bb": {}}}),
+            ),
+        ];
+        for (mut target, patch, expected) in cases {
+            target.merge_patch(&patch);
+            assert_eq!(target, expected);
+        }
+    }
+
+    #[test]
+    fn test_diff_round_trips() {
+        let pairs = [
+            (
+                json!({"a": [1, {"b": 2}], "c": "x"}),
+                json!({"a": [1, {"b": 3}, 4, 5], "d": []}),
+            ),
+            (json!([1, 2, 3, 4]), json!([1])),
+            (json!({"k/~": 1}), json!({"k/~": 2})),
+            (json!(1), json!("1")),
+        ];
+        for (from, to) in pairs {
+            let mut patched = from.clone();
+            patched.apply_patch(&diff(&from, &to)).unwrap();
+            assert_eq!(patched, to);
+        }
+        assert_eq!(diff(&json!({"a": 1}), &json!({"a": 1})), json!([]));
+    }
+}
@@ -0,0 +1,1496 @@
+//! JSONPath (RFC 9535) queries
+//!
+//! A path such as `$.store.book[0:2].title` is compiled once into a
+//! `JsonPath` and can then be evaluated against any number of documents.
+//! Supported are name (`.a`, `['a']`), wildcard (`.*`, `[*]`), index (`[0]`,
+//! `[-1]`) and slice (`[1:5:2]`) selectors, selector lists (`['a', 0]`),
+//! descendant segments (`..a`) and filters (`[?@.price < 10 && @.tags]`)
+//! with comparisons, `&&`, `||`, `!` and existence tests. Function
+//! extensions such as `length(@)` are rejected with
+//! `PathErrorKind::UnsupportedFilter`.
+//!
+//! Filters may also refer to variables, `$name` followed by segments as in
+//! `$env.region`, whose values are supplied when the query runs with
+//! [`JsonValue::query_with`]. Values are bound as data, so a parameterized
+//! query needs no string interpolation and cannot be injected into.
+//!
+//! Compilation errors carry the byte offset into the path text, so a path
+//! supplied by a user can be checked up front with `validate_path` and
+//! reported precisely.
+
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::collections::{BTreeSet, HashMap, VecDeque};
+use std::fmt;
+use std::io::BufRead;
+
+use crate::error::ParseError;
+use crate::json::{JsonValue, escape, unescape_content};
+use crate::number::JsonNumber;
+use crate::stream::ElementStream;
+
+/// A compiled JSONPath query
+///
+/// # Examples
+///
+/// ```
+/// use json_parser_rs::{JsonPath, parse_json};
+///
+/// let value = parse_json(r#"{"books": [{"title": "A"}, {"title": "B"}, {"title": "C"}]}"#).unwrap();
+/// let path = JsonPath::compile("$.books[-2:].title").unwrap();
+/// let titles: Vec<&str> = value.query(&path).iter().filter_map(|v| v.as_str()).collect();
+/// assert_eq!(titles, ["B", "C"]);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JsonPath {
+    segments: Vec<Segment>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Segment {
+    /// `..`: apply the selectors to the node and all its descendants
+    descendant: bool,
+    selectors: Vec<Selector>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Selector {
+    Name(String),
+    Wildcard,
+    Index(i64),
+    Slice {
+        start: Option<i64>,
+        end: Option<i64>,
+        step: i64,
+    },
+    Filter(Box<Filter>),
+}
+
+/// A filter expression, the part of `[?...]` after the `?`
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Filter {
+    Or(Vec<Filter>),
+    And(Vec<Filter>),
+    Not(Box<Filter>),
+    /// Whether the query selects anything
+    Exists(Query),
+    Compare(Comparable, CompareOp, Comparable),
+}
+
+/// A query inside a filter
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Query {
+    root: QueryRoot,
+    segments: Vec<Segment>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum QueryRoot {
+    /// `@`, the value the filter is testing
+    Current,
+    /// `$`, the document
+    Document,
+    /// `$name`, a value bound by the caller
+    Variable(String),
+}
+
+/// A side of a comparison; queries are singular
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Comparable {
+    Literal(Literal),
+    Query(Query),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Literal {
+    Null,
+    Boolean(bool),
+    /// Normalized number text, so paths stay comparable with `Eq`
+    Number(String),
+    String(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CompareOp {
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+/// Why a JSONPath expression does not compile, and where
+///
+/// # Examples
+///
+/// ```
+/// use json_parser_rs::path::{PathErrorKind, validate_path};
+///
+/// let error = validate_path("$.items[1:x]").unwrap_err();
+/// assert_eq!(error.kind(), PathErrorKind::UnexpectedCharacter('x'));
+/// assert_eq!(error.position(), 10);
+/// assert_eq!(
+///     error.to_string(),
+///     "Invalid JSONPath at position 10: unexpected character 'x'"
+/// );
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PathError {
+    kind: PathErrorKind,
+    position: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum PathErrorKind {
+    /// The path does not start with `$`
+    MissingRoot,
+    /// The path ends in the middle of a segment
+    UnexpectedEnd,
+    /// A character that cannot appear at this point
+    UnexpectedCharacter(char),
+    /// A quoted name without its closing quote
+    UnterminatedString,
+    /// A malformed escape sequence inside a quoted name
+    InvalidEscape,
+    /// An index or slice bound that is not an integer in the range RFC 9535
+    /// allows (no leading zeros, at most 2^53 - 1 in magnitude), or a
+    /// malformed number in a filter
+    InvalidNumber,
+    /// A function extension in a filter (`length(@)`, `match(...)`)
+    UnsupportedFilter,
+    /// A query in a comparison that can select more than one value
+    NonSingularQuery,
+}
+
+impl PathError {
+    pub fn kind(&self) -> PathErrorKind {
+        self.kind
+    }
+
+    /// Byte offset into the path text
+    pub fn position(&self) -> usize {
+        self.position
+    }
+}
+
+impl fmt::Display for PathError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid JSONPath at position {}: ", self.position)?;
+        match &self.kind {
+            PathErrorKind::MissingRoot => write!(f, "a path must start with '$'"),
+            PathErrorKind::UnexpectedEnd => write!(f, "unexpected end of path"),
+            PathErrorKind::UnexpectedCharacter(ch) => write!(f, "unexpected character {:?}", ch),
+            PathErrorKind::UnterminatedString => write!(f, "unterminated string"),
+            PathErrorKind::InvalidEscape => write!(f, "invalid escape sequence"),
+            PathErrorKind::InvalidNumber => write!(f, "invalid integer"),
+            PathErrorKind::UnsupportedFilter => write!(f, "filter functions are not supported"),
+            PathErrorKind::NonSingularQuery => {
+                write!(f, "only singular queries can be compared")
+            }
+        }
+    }
+}
+
+impl std::error::Error for PathError {}
+
+/// Check that `path` is a JSONPath expression this crate can evaluate
+pub fn validate_path(path: &str) -> Result<(), PathError> {
+    JsonPath::compile(path).map(|_| ())
+}
+
+/// Largest integer RFC 9535 allows in indices and slices (2^53 - 1)
+const MAX_INTEGER: i64 = (1 << 53) - 1;
+
+impl JsonPath {
+    pub fn compile(path: &str) -> Result<Self, PathError> {
+        Compiler { path, position: 0 }.compile()
+    }
+}
+
+impl fmt::Display for JsonPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "$")?;
+        write_segments(f, &self.segments)
+    }
+}
+
+fn write_segments(f: &mut fmt::Formatter<'_>, segments: &[Segment]) -> fmt::Result {
+    for segment in segments {
+        if segment.descendant {
+            write!(f, "..")?;
+        }
+        write!(f, "[")?;
+        for (i, selector) in segment.selectors.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}", selector)?;
+        }
+        write!(f, "]")?;
+    }
+    Ok(())
+}
+
+impl fmt::Display for Selector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Selector::Name(name) => write!(f, "\"{}\"", escape(name)),
+            Selector::Wildcard => write!(f, "*"),
+            Selector::Index(index) => write!(f, "{}", index),
+            Selector::Slice { start, end, step } => {
+                if let Some(start) = start {
+                    write!(f, "{}", start)?;
+                }
+                write!(f, ":")?;
+                if let Some(end) = end {
+                    write!(f, "{}", end)?;
+                }
+                if *step != 1 {
+                    write!(f, ":{}", step)?;
+                }
+                Ok(())
+            }
+            Selector::Filter(filter) => write!(f, "?{}", filter),
+        }
+    }
+}
+
+impl fmt::Display for Filter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Filter::Or(terms) | Filter::And(terms) => {
+                let operator = if matches!(self, Filter::Or(_)) {
+                    " || "
+                } else {
+                    " && "
+                };
+                for (i, term) in terms.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, "{}", operator)?;
+                    }
+                    match term {
+                        Filter::Or(_) | Filter::And(_) => write!(f, "({})", term)?,
+                        _ => write!(f, "{}", term)?,
+                    }
+                }
+                Ok(())
+            }
+            Filter::Not(inner) => match &**inner {
+                Filter::Exists(query) => write!(f, "!{}", query),
+                inner => write!(f, "!({})", inner),
+            },
+            Filter::Exists(query) => write!(f, "{}", query),
+            Filter::Compare(left, op, right) => {
+                let op = match op {
+                    CompareOp::Equal => "==",
+                    CompareOp::NotEqual => "!=",
+                    CompareOp::Less => "<",
+                    CompareOp::LessOrEqual => "<=",
+                    CompareOp::Greater => ">",
+                    CompareOp::GreaterOrEqual => ">=",
+                };
+                write!(f, "{} {} {}", left, op, right)
+            }
+        }
+    }
+}
+
+impl fmt::Display for Query {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.root {
+            QueryRoot::Current => write!(f, "@")?,
+            QueryRoot::Document => write!(f, "$")?,
+            QueryRoot::Variable(name) => write!(f, "${}", name)?,
+        }
+        write_segments(f, &self.segments)
+    }
+}
+
+impl fmt::Display for Comparable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Comparable::Query(query) => write!(f, "{}", query),
+            Comparable::Literal(Literal::Null) => write!(f, "null"),
+            Comparable::Literal(Literal::Boolean(b)) => write!(f, "{}", b),
+            Comparable::Literal(Literal::Number(n)) => write!(f, "{}", n),
+            Comparable::Literal(Literal::String(s)) => write!(f, "\"{}\"", escape(s)),
+        }
+    }
+}
+
+struct Compiler<'a> {
+    path: &'a str,
+    position: usize,
+}
+
+impl Compiler<'_> {
+    fn compile(mut self) -> Result<JsonPath, PathError> {
+        if self.peek() != Some('$') {
+            return Err(self.error(PathErrorKind::MissingRoot));
+        }
+        self.position += 1;
+        let segments = self.segments()?;
+        if let Some(ch) = self.peek() {
+            return Err(self.error(PathErrorKind::UnexpectedCharacter(ch)));
+        }
+        Ok(JsonPath { segments })
+    }
+
+    /// The segments from here up to the first character that cannot start
+    /// one
+    fn segments(&mut self) -> Result<Vec<Segment>, PathError> {
+        let mut segments = Vec::new();
+        loop {
+            let segment = match self.peek() {
+                Some('[') => Segment {
+                    descendant: false,
+                    selectors: self.bracketed()?,
+                },
+                Some('.') if self.path[self.position..].starts_with("..") => {
+                    self.position += 2;
+                    let selectors = match self.peek() {
+                        Some('[') => self.bracketed()?,
+                        _ => vec![self.shorthand()?],
+                    };
+                    Segment {
+                        descendant: true,
+                        selectors,
+                    }
+                }
+                Some('.') => {
+                    self.position += 1;
+                    Segment {
+                        descendant: false,
+                        selectors: vec![self.shorthand()?],
+                    }
+                }
+                _ => return Ok(segments),
+            };
+            segments.push(segment);
+        }
+    }
+
+    /// `*` or a member name after `.` or `..`
+    fn shorthand(&mut self) -> Result<Selector, PathError> {
+        match self.peek() {
+            Some('*') => {
+                self.position += 1;
+                Ok(Selector::Wildcard)
+            }
+            Some(ch) if is_name_start(ch) => Ok(Selector::Name(self.name())),
+            _ => Err(self.unexpected()),
+        }
+    }
+
+    /// `[selector, ...]`
+    fn bracketed(&mut self) -> Result<Vec<Selector>, PathError> {
+        self.position += 1;
+        let mut selectors = Vec::new();
+        loop {
+            self.skip_blank();
+            selectors.push(self.selector()?);
+            self.skip_blank();
+            match self.peek() {
+                Some(',') => self.position += 1,
+                Some(']') => {
+                    self.position += 1;
+                    return Ok(selectors);
+                }
+                _ => return Err(self.unexpected()),
+            }
+        }
+    }
+
+    fn selector(&mut self) -> Result<Selector, PathError> {
+        match self.peek() {
+            Some(quote @ ('\'' | '"')) => self.quoted(quote).map(Selector::Name),
+            Some('*') => {
+                self.position += 1;
+                Ok(Selector::Wildcard)
+            }
+            Some('?') => {
+                self.position += 1;
+                self.skip_blank();
+                Ok(Selector::Filter(Box::new(self.logical_or()?)))
+            }
+            Some(ch) if ch == '-' || ch == ':' || ch.is_ascii_digit() => self.index_or_slice(),
+            _ => Err(self.unexpected()),
+        }
+    }
+
+    /// `a || b || ...`
+    fn logical_or(&mut self) -> Result<Filter, PathError> {
+        let mut terms = vec![self.logical_and()?];
+        while self.skip_operator("||") {
+            terms.push(self.logical_and()?);
+        }
+        Ok(match terms.len() {
+            1 => terms.remove(0),
+            _ => Filter::Or(terms),
+        })
+    }
+
+    /// `a && b && ...`
+    fn logical_and(&mut self) -> Result<Filter, PathError> {
+        let mut terms = vec![self.basic()?];
+        while self.skip_operator("&&") {
+            terms.push(self.basic()?);
+        }
+        Ok(match terms.len() {
+            1 => terms.remove(0),
+            _ => Filter::And(terms),
+        })
+    }
+
+    /// Skip `operator` and the blanks around it, if it comes next
+    fn skip_operator(&mut self, operator: &str) -> bool {
+        let before = self.position;
+        self.skip_blank();
+        if self.path[self.position..].starts_with(operator) {
+            self.position += operator.len();
+            self.skip_blank();
+            return true;
+        }
+        self.position = before;
+        false
+    }
+
+    /// A parenthesized expression, a comparison or an existence test, each
+    /// possibly negated
+    fn basic(&mut self) -> Result<Filter, PathError> {
+        if self.peek() == Some('!') {
+            self.position += 1;
+            self.skip_blank();
+            let negated = match self.peek() {
+                Some('(') => self.parenthesized()?,
+                _ => {
+                    let start = self.position;
+                    match self.comparable()? {
+                        Comparable::Query(query) => Filter::Exists(query),
+                        Comparable::Literal(_) => {
+                            self.position = start;
+                            return Err(self.unexpected());
+                        }
+                    }
+                }
+            };
+            return Ok(Filter::Not(Box::new(negated)));
+        }
+        if self.peek() == Some('(') {
+            return self.parenthesized();
+        }
+
+        let left_start = self.position;
+        let left = self.comparable()?;
+        let before = self.position;
+        self.skip_blank();
+        let Some(op) = self.compare_op() else {
+            self.position = before;
+            return match left {
+                Comparable::Query(query) => Ok(Filter::Exists(query)),
+                // A literal alone is not a test
+                Comparable::Literal(_) => {
+                    self.skip_blank();
+                    Err(self.unexpected())
+                }
+            };
+        };
+        self.skip_blank();
+        let right_start = self.position;
+        let right = self.comparable()?;
+        for (side, start) in [(&left, left_start), (&right, right_start)] {
+            if let Comparable::Query(query) = side
+                && !query.is_singular()
+            {
+                return Err(PathError {
+                    kind: PathErrorKind::NonSingularQuery,
+                    position: start,
+                });
+            }
+        }
+        Ok(Filter::Compare(left, op, right))
+    }
+
+    /// `( expression )`
+    fn parenthesized(&mut self) -> Result<Filter, PathError> {
+        self.position += 1;
+        self.skip_blank();
+        let inner = self.logical_or()?;
+        self.skip_blank();
+        if self.peek() != Some(')') {
+            return Err(self.unexpected());
+        }
+        self.position += 1;
+        Ok(inner)
+    }
+
+    fn compare_op(&mut self) -> Option<CompareOp> {
+        let rest = &self.path[self.position..];
+        let (op, len) = if rest.starts_with("==") {
+            (CompareOp::Equal, 2)
+        } else if rest.starts_with("!=") {
+            (CompareOp::NotEqual, 2)
+        } else if rest.starts_with("<=") {
+            (CompareOp::LessOrEqual, 2)
+        } else if rest.starts_with(">=") {
+            (CompareOp::GreaterOrEqual, 2)
+        } else if rest.starts_with('<') {
+            (CompareOp::Less, 1)
+        } else if rest.starts_with('>') {
+            (CompareOp::Greater, 1)
+        } else {
+            return None;
+        };
+        self.position += len;
+        Some(op)
+    }
+
+    /// A literal or a query starting with `@`, `$` or `$name`
+    fn comparable(&mut self) -> Result<Comparable, PathError> {
+        let root = match self.peek() {
+            Some('@') => {
+                self.position += 1;
+                QueryRoot::Current
+            }
+            Some('$') => {
+                self.position += 1;
+                match self.peek() {
+                    Some(ch) if is_name_start(ch) => QueryRoot::Variable(self.name()),
+                    _ => QueryRoot::Document,
+                }
+            }
+            Some(quote @ ('\'' | '"')) => {
+                return self
+                    .quoted(quote)
+                    .map(|s| Comparable::Literal(Literal::String(s)));
+            }
+            Some(ch) if ch == '-' || ch.is_ascii_digit() => {
+                return self.number().map(Comparable::Literal);
+            }
+            Some(ch) if ch.is_ascii_alphabetic() => {
+                let start = self.position;
+                let literal = match self.name().as_str() {
+                    "true" => Literal::Boolean(true),
+                    "false" => Literal::Boolean(false),
+                    "null" => Literal::Null,
+                    _ if self.peek() == Some('(') => {
+                        self.position = start;
+                        return Err(self.error(PathErrorKind::UnsupportedFilter));
+                    }
+                    _ => {
+                        self.position = start;
+                        return Err(self.unexpected());
+                    }
+                };
+                return Ok(Comparable::Literal(literal));
+            }
+            _ => return Err(self.unexpected()),
+        };
+        Ok(Comparable::Query(Query {
+            root,
+            segments: self.segments()?,
+        }))
+    }
+
+    /// A member name or variable name
+    fn name(&mut self) -> String {
+        let start = self.position;
+        while self
+            .peek()
+            .is_some_and(|ch| is_name_start(ch) || ch.is_ascii_digit())
+        {
+            self.bump();
+        }
+        self.path[start..self.position].to_string()
+    }
+
+    /// A JSON number literal
+    fn number(&mut self) -> Result<Literal, PathError> {
+        let start = self.position;
+        let bytes = self.path.as_bytes();
+        let digits = |mut i: usize| {
+            while bytes.get(i).is_some_and(u8::is_ascii_digit) {
+                i += 1;
+            }
+            i
+        };
+        let mut end = start + usize::from(bytes[start] == b'-');
+        let int_start = end;
+        end = digits(end);
+        let mut valid = end > int_start && (bytes[int_start] != b'0' || end == int_start + 1);
+        if bytes.get(end) == Some(&b'.') {
+            let fraction = end + 1;
+            end = digits(fraction);
+            valid &= end > fraction;
+        }
+        if let Some(b'e' | b'E') = bytes.get(end) {
+            end += 1;
+            if let Some(b'+' | b'-') = bytes.get(end) {
+                end += 1;
+            }
+            let exponent = end;
+            end = digits(exponent);
+            valid &= end > exponent;
+        }
+        match JsonNumber::from_literal(&self.path[start..end]) {
+            Some(number) if valid => {
+                self.position = end;
+                Ok(Literal::Number(number.to_string()))
+            }
+            _ => Err(self.error(PathErrorKind::InvalidNumber)),
+        }
+    }
+
+    fn quoted(&mut self, quote: char) -> Result<String, PathError> {
+        let start = self.position;
+        let content_start = start + 1;
+        let bytes = self.path.as_bytes();
+        let mut end = content_start;
+        loop {
+            match bytes.get(end) {
+                None => {
+                    self.position = start;
+                    return Err(self.error(PathErrorKind::UnterminatedString));
+                }
+                Some(&b) if b == quote as u8 => break,
+                Some(b'\\') => end += 2,
+                Some(_) => end += 1,
+            }
+        }
+        let name = unescape_content(&self.path[content_start..end], quote, false)
+            .map_err(|(_, offset)| PathError {
+                kind: PathErrorKind::InvalidEscape,
+                position: content_start + offset,
+            })?
+            .0
+            .into_owned();
+        self.position = end + 1;
+        Ok(name)
+    }
+
+    fn index_or_slice(&mut self) -> Result<Selector, PathError> {
+        let start = self.integer()?;
+        self.skip_blank();
+        if self.peek() != Some(':') {
+            // Only reached with a leading digit or `-`, so `start` is set
+            return Ok(Selector::Index(start.unwrap_or_default()));
+        }
+        self.position += 1;
+        self.skip_blank();
+        let end = self.integer()?;
+        self.skip_blank();
+        let mut step = None;
+        if self.peek() == Some(':') {
+            self.position += 1;
+            self.skip_blank();
+            step = self.integer()?;
+        }
+        Ok(Selector::Slice {
+            start,
+            end,
+            step: step.unwrap_or(1),
+        })
+    }
+
+    /// An optional integer; `None` if none starts here
+    fn integer(&mut self) -> Result<Option<i64>, PathError> {
+        let start = self.position;
+        if self.peek() == Some('-') {
+            self.position += 1;
+        }
+        let digits = self.position;
+        while self.peek().is_some_and(|ch| ch.is_ascii_digit()) {
+            self.position += 1;
+        }
+        let text = &self.path[start..self.position];
+        if self.position == digits {
+            if text.is_empty() {
+                return Ok(None);
+            }
+            return Err(self.unexpected());
+        }
+
+        let leading_zero = self.path[digits..self.position].starts_with('0') && text != "0";
+        match text.parse::<i64>() {
+            Ok(n) if !leading_zero && n.abs() <= MAX_INTEGER => Ok(Some(n)),
+            _ => {
+                self.position = start;
+                Err(self.error(PathErrorKind::InvalidNumber))
+            }
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.path[self.position..].chars().next()
+    }
+
+    fn bump(&mut self) {
+        self.position += self.peek().map_or(0, char::len_utf8);
+    }
+
+    fn skip_blank(&mut self) {
+        while let Some(' ' | '\t' | '\n' | '\r') = self.peek() {
+            self.position += 1;
+        }
+    }
+
+    fn unexpected(&self) -> PathError {
+        match self.peek() {
+            Some(ch) => self.error(PathErrorKind::UnexpectedCharacter(ch)),
+            None => self.error(PathErrorKind::UnexpectedEnd),
+        }
+    }
+
+    fn error(&self, kind: PathErrorKind) -> PathError {
+        PathError {
+            kind,
+            position: self.position,
+        }
+    }
+}
+
+fn is_name_start(ch: char) -> bool {
+    ch.is_ascii_alphabetic() || ch == '_' || !ch.is_ascii()
+}
+
+/// A variable a query refers to but the caller did not bind
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnboundVariable {
+    pub name: String,
+}
+
+impl fmt::Display for UnboundVariable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Unbound variable ${}", self.name)
+    }
+}
+
+impl std::error::Error for UnboundVariable {}
+
+impl JsonValue {
+    /// All values selected by `path`, in document order per selector
+    ///
+    /// Variables in filters are unbound and select nothing; see
+    /// [`JsonValue::query_with`].
+    pub fn query(&self, path: &JsonPath) -> Vec<&JsonValue> {
+        let context = Context {
+            root: self,
+            variables: None,
+        };
+        evaluate(&path.segments, self, &context)
+    }
+
+    /// [`JsonValue::query`] with values for the variables in the path's
+    /// filters
+    ///
+    /// A variable holding an object acts as a namespace: `$env.region`
+    /// selects the `region` member of the value bound to `env`. Every
+    /// variable the path refers to must be bound.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::HashMap;
+    /// use json_parser_rs::{JsonPath, json};
+    ///
+    /// let servers = json!([
+    ///     {"name": "a", "region": "eu", "load": 0.2},
+    ///     {"name": "b", "region": "us", "load": 0.9},
+    ///     {"name": "c", "region": "eu", "load": 0.7}
+    /// ]);
+    /// let path = JsonPath::compile("$[?@.region == $env.region && @.load < $max].name").unwrap();
+    /// let variables = HashMap::from([
+    ///     ("env".to_string(), json!({"region": "eu"})),
+    ///     ("max".to_string(), json!(0.5)),
+    /// ]);
+    /// let names = servers.query_with(&path, &variables).unwrap();
+    /// assert_eq!(names, [&json!("a")]);
+    ///
+    /// let error = servers.query_with(&path, &HashMap::new()).unwrap_err();
+    /// assert_eq!(error.to_string(), "Unbound variable $env");
+    /// ```
+    pub fn query_with<'a>(
+        &'a self,
+        path: &JsonPath,
+        variables: &HashMap<String, JsonValue>,
+    ) -> Result<Vec<&'a JsonValue>, UnboundVariable> {
+        if let Some(name) = path
+            .variables()
+            .into_iter()
+            .find(|name| !variables.contains_key(*name))
+        {
+            return Err(UnboundVariable {
+                name: name.to_string(),
+            });
+        }
+        let context = Context {
+            root: self,
+            variables: Some(variables),
+        };
+        Ok(evaluate(&path.segments, self, &context))
+    }
+}
+
+impl JsonPath {
+    /// Names of the variables the path's filters refer to, sorted
+    pub fn variables(&self) -> Vec<&str> {
+        let mut names = BTreeSet::new();
+        for_each_query(&self.segments, &mut |query| {
+            if let QueryRoot::Variable(name) = &query.root {
+                names.insert(name.as_str());
+            }
+        });
+        names.into_iter().collect()
+    }
+}
+
+/// Call `f` with every query in the filters of `segments`, nested ones
+/// included
+fn for_each_query<'a>(segments: &'a [Segment], f: &mut dyn FnMut(&'a Query)) {
+    for selector in segments.iter().flat_map(|segment| &segment.selectors) {
+        if let Selector::Filter(filter) = selector {
+            filter.for_each_query(f);
+        }
+    }
+}
+
+impl Filter {
+    fn for_each_query<'a>(&'a self, f: &mut dyn FnMut(&'a Query)) {
+        let visit = |query: &'a Query, f: &mut dyn FnMut(&'a Query)| {
+            f(query);
+            for_each_query(&query.segments, f);
+        };
+        match self {
+            Filter::Or(terms) | Filter::And(terms) => {
+                for term in terms {
+                    term.for_each_query(f);
+                }
+            }
+            Filter::Not(inner) => inner.for_each_query(f),
+            Filter::Exists(query) => visit(query, f),
+            Filter::Compare(left, _, right) => {
+                for side in [left, right] {
+                    if let Comparable::Query(query) = side {
+                        visit(query, f);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Whether `current` passes the filter
+    fn test(&self, current: &JsonValue, context: &Context) -> bool {
+        match self {
+            Filter::Or(terms) => terms.iter().any(|term| term.test(current, context)),
+            Filter::And(terms) => terms.iter().all(|term| term.test(current, context)),
+            Filter::Not(inner) => !inner.test(current, context),
+            Filter::Exists(query) => !query.evaluate(current, context).is_empty(),
+            Filter::Compare(left, op, right) => {
+                let left = left.value(current, context);
+                let right = right.value(current, context);
+                compare(left.as_deref(), *op, right.as_deref())
+            }
+        }
+    }
+}
+
+impl Query {
+    /// Whether the query selects at most one value: only name and index
+    /// selectors, one per segment
+    fn is_singular(&self) -> bool {
+        self.segments.iter().all(|segment| {
+            !segment.descendant
+                && matches!(
+                    segment.selectors.as_slice(),
+                    [Selector::Name(_) | Selector::Index(_)]
+                )
+        })
+    }
+
+    fn evaluate<'a>(&self, current: &'a JsonValue, context: &Context<'a>) -> Vec<&'a JsonValue> {
+        let start = match &self.root {
+            QueryRoot::Current => current,
+            QueryRoot::Document => context.root,
+            QueryRoot::Variable(name) => match context.variables.and_then(|v| v.get(name)) {
+                Some(value) => value,
+                None => return Vec::new(),
+            },
+        };
+        evaluate(&self.segments, start, context)
+    }
+}
+
+impl Comparable {
+    /// The compared value; `None` if a query selects nothing
+    fn value<'a>(
+        &self,
+        current: &'a JsonValue,
+        context: &Context<'a>,
+    ) -> Option<Cow<'a, JsonValue>> {
+        let literal = match self {
+            Comparable::Query(query) => {
+                return query
+                    .evaluate(current, context)
+                    .first()
+                    .map(|v| Cow::Borrowed(*v));
+            }
+            Comparable::Literal(literal) => literal,
+        };
+        Some(Cow::Owned(match literal {
+            Literal::Null => JsonValue::Null,
+            Literal::Boolean(b) => JsonValue::Boolean(*b),
+            Literal::Number(n) => JsonValue::Number(JsonNumber::from_literal(n)?),
+            Literal::String(s) => JsonValue::String(s.clone()),
+        }))
+    }
+}
+
+/// RFC 9535 comparison: a missing value only equals another missing
+/// value, and order is defined between numbers and between strings
+fn compare(left: Option<&JsonValue>, op: CompareOp, right: Option<&JsonValue>) -> bool {
+    let equal = || match (left, right) {
+        (Some(left), Some(right)) => values_equal(left, right),
+        (left, right) => left.is_none() && right.is_none(),
+    };
+    let less = |a: Option<&JsonValue>, b: Option<&JsonValue>| match (a, b) {
+        (Some(a), Some(b)) => match (a.untagged(), b.untagged()) {
+            (JsonValue::Number(a), JsonValue::Number(b)) => {
+                compare_numbers(a, b) == Some(Ordering::Less)
+            }
+            (JsonValue::String(a), JsonValue::String(b)) => a < b,
+            _ => false,
+        },
+        _ => false,
+    };
+    match op {
+        CompareOp::Equal => equal(),
+        CompareOp::NotEqual => !equal(),
+        CompareOp::Less => less(left, right),
+        CompareOp::LessOrEqual => less(left, right) || equal(),
+        CompareOp::Greater => less(right, left),
+        CompareOp::GreaterOrEqual => less(right, left) || equal(),
+    }
+}
+
+/// Deep equality with numbers compared by value, so `1 == 1.0`
+pub(crate) fn values_equal(left: &JsonValue, right: &JsonValue) -> bool {
+    match (left.untagged(), right.untagged()) {
+        (JsonValue::Number(a), JsonValue::Number(b)) => {
+            compare_numbers(a, b) == Some(Ordering::Equal)
+        }
+        (JsonValue::Array(a), JsonValue::Array(b)) => {
+            a.len() == b.len() && a.iter().zip(b).all(|(a, b)| values_equal(a, b))
+        }
+        (JsonValue::Object(a), JsonValue::Object(b)) => {
+            a.len() == b.len()
+                && a.iter()
+                    .all(|(key, a)| b.get(key).is_some_and(|b| values_equal(a, b)))
+        }
+        (a, b) => a == b,
+    }
+}
+
+/// Integers are compared exactly, anything involving a float as `f64`
+fn compare_numbers(a: &JsonNumber, b: &JsonNumber) -> Option<Ordering> {
+    let integer = |n: &JsonNumber| match (n.is_f64(), n.as_i64(), n.as_u64()) {
+        (false, Some(i), _) => Some(i128::from(i)),
+        (false, _, Some(u)) => Some(i128::from(u)),
+        _ => None,
+    };
+    match (integer(a), integer(b)) {
+        (Some(a), Some(b)) => Some(a.cmp(&b)),
+        _ => a.as_f64().partial_cmp(&b.as_f64()),
+    }
+}
+
+/// What a query is evaluated against
+struct Context<'a> {
+    /// The document, for `$` inside filters
+    root: &'a JsonValue,
+    variables: Option<&'a HashMap<String, JsonValue>>,
+}
+
+impl JsonPath {
+    /// Evaluate the path against the JSON text `input`, yielding matches as
+    /// they are found
+    ///
+    /// When the first segment selects top-level elements by a single name,
+    /// index or wildcard (as in `$.items`, `$[*].id` or `$[3]`), the
+    /// document is read one top-level element at a time and only that
+    /// element is held in memory; matches inside it are yielded before the
+    /// next element is parsed. Other paths parse the whole document first.
+    /// A syntax error ends the iteration after being yielded.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use json_parser_rs::JsonPath;
+    ///
+    /// let path = JsonPath::compile("$[*].id").unwrap();
+    /// let ids: Vec<u64> = path
+    ///     .stream(r#"[{"id": 1}, {"id": 2}, {"name": "x"}]"#)
+    ///     .map(|found| found.unwrap().as_u64().unwrap())
+    ///     .collect();
+    /// assert_eq!(ids, [1, 2]);
+    /// ```
+    pub fn stream<'a>(&'a self, input: &'a str) -> QueryStream<'a> {
+        let state = match ElementStream::new(input) {
+            Ok(elements) if self.is_streamable() => State::Streaming {
+                elements: Box::new(elements),
+                index: 0,
+            },
+            _ => self.buffered(crate::parse_json(input)),
+        };
+        self.query_stream(state)
+    }
+
+    /// Like [`stream`](Self::stream), reading the JSON text from `reader`
+    ///
+    /// For the paths that stream, only the current top-level element is held
+    /// in memory, never the text of the document. Other paths, and scalar
+    /// documents, are parsed as a whole first.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use json_parser_rs::JsonPath;
+    ///
+    /// let path = JsonPath::compile("$.users[*].name").unwrap();
+    /// let input = r#"{"users": [{"name": "a"}, {"name": "b"}]}"#.as_bytes();
+    /// let names: Vec<String> = path
+    ///     .stream_reader(input)
+    ///     .map(|found| found.unwrap().as_str().unwrap().to_string())
+    ///     .collect();
+    /// assert_eq!(names, ["a", "b"]);
+    /// ```
+    pub fn stream_reader<'a, R: BufRead + Send + 'a>(&'a self, mut reader: R) -> QueryStream<'a> {
+        let container = loop {
+            match reader.fill_buf() {
+                Ok([first, ..]) if first.is_ascii_whitespace() => reader.consume(1),
+                Ok(buffer) => break matches!(buffer.first(), Some(b'[' | b'{')),
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+                // Reported by the parser, which reads again and locates it
+                Err(_) => break false,
+            }
+        };
+        if !(container && self.is_streamable()) {
+            return self.query_stream(self.buffered(crate::parse_json_reader(reader)));
+        }
+        let state = match ElementStream::from_reader(reader) {
+            Ok(elements) => State::Streaming {
+                elements: Box::new(elements),
+                index: 0,
+            },
+            Err(error) => State::Failed(Some(error)),
+        };
+        self.query_stream(state)
+    }
+
+    /// Whether the first segment selects top-level elements one at a time,
+    /// so the document can be streamed
+    fn is_streamable(&self) -> bool {
+        // `$` inside a filter needs the whole document
+        let mut uses_document = false;
+        for_each_query(&self.segments, &mut |query| {
+            uses_document |= query.root == QueryRoot::Document;
+        });
+        !uses_document
+            && match self.segments.first() {
+                Some(Segment {
+                    descendant: false,
+                    selectors,
+                }) => matches!(
+                    selectors.as_slice(),
+                    [Selector::Name(_) | Selector::Wildcard] | [Selector::Index(0..)]
+                ),
+                _ => false,
+            }
+    }
+
+    /// State yielding all matches in the parsed document
+    fn buffered<'a>(&self, parsed: Result<JsonValue, ParseError>) -> State<'a> {
+        match parsed {
+            Ok(value) => {
+                let found: Vec<JsonValue> = value.query(self).into_iter().cloned().collect();
+                State::Buffered(found.into_iter())
+            }
+            Err(error) => State::Failed(Some(error)),
+        }
+    }
+
+    fn query_stream<'a>(&'a self, state: State<'a>) -> QueryStream<'a> {
+        QueryStream {
+            path: self,
+            state,
+            pending: VecDeque::new(),
+        }
+    }
+}
+
+/// Iterator returned by [`JsonPath::stream`]
+pub struct QueryStream<'a> {
+    path: &'a JsonPath,
+    state: State<'a>,
+    /// Matches inside the current top-level element not yet yielded
+    pending: VecDeque<JsonValue>,
+}
+
+enum State<'a> {
+    Streaming {
+        elements: Box<ElementStream<'a>>,
+        /// Index of the next top-level element
+        index: usize,
+    },
+    Buffered(std::vec::IntoIter<JsonValue>),
+    Failed(Option<ParseError>),
+}
+
+impl Iterator for QueryStream<'_> {
+    type Item = Result<JsonValue, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(found) = self.pending.pop_front() {
+                return Some(Ok(found));
+            }
+            let (elements, index) = match &mut self.state {
+                State::Streaming { elements, index } => (elements, index),
+                State::Buffered(found) => return found.next().map(Ok),
+                State::Failed(error) => return error.take().map(Err),
+            };
+            let element = match elements.next()? {
+                Ok(element) => element,
+                Err(error) => return Some(Err(error)),
+            };
+            let selected = match (&self.path.segments[0].selectors[0], &element.key) {
+                (Selector::Wildcard, _) => true,
+                (Selector::Name(name), Some(key)) => name == key,
+                (Selector::Index(i), None) => *i as usize == *index,
+                _ => false,
+            };
+            *index += 1;
+            if selected {
+                let context = Context {
+                    root: &element.value,
+                    variables: None,
+                };
+                let found = evaluate(&self.path.segments[1..], &element.value, &context);
+                self.pending.extend(found.into_iter().cloned());
+            }
+        }
+    }
+}
+
+fn evaluate<'a>(
+    segments: &[Segment],
+    root: &'a JsonValue,
+    context: &Context,
+) -> Vec<&'a JsonValue> {
+    let mut nodes = vec![root];
+    for segment in segments {
+        if segment.descendant {
+            nodes = nodes.into_iter().flat_map(descendants).collect();
+        }
+        nodes = nodes
+            .into_iter()
+            .flat_map(|node| {
+                segment
+                    .selectors
+                    .iter()
+                    .flat_map(move |selector| select(node, selector, context))
+            })
+            .collect();
+    }
+    nodes
+}
+
+/// `node` and everything below it, parents before children
+fn descendants(node: &JsonValue) -> Vec<&JsonValue> {
+    let mut found = Vec::new();
+    let mut stack = vec![node];
+    while let Some(node) = stack.pop() {
+        found.push(node);
+        let children = children(node);
+        stack.extend(children.into_iter().rev());
+    }
+    found
+}
+
+fn children(node: &JsonValue) -> Vec<&JsonValue> {
+    match node.untagged() {
+        JsonValue::Object(object) => object.values().collect(),
+        JsonValue::Extension(_, payload) => vec![payload],
+        JsonValue::Array(elements) => elements.iter().collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn select<'a>(node: &'a JsonValue, selector: &Selector, context: &Context) -> Vec<&'a JsonValue> {
+    if let Selector::Filter(filter) = selector {
+        return match node.untagged() {
+            JsonValue::Object(_) | JsonValue::Array(_) => children(node)
+                .into_iter()
+                .filter(|child| filter.test(child, context))
+                .collect(),
+            _ => Vec::new(),
+        };
+    }
+    match (node.untagged(), selector) {
+        (JsonValue::Object(object), Selector::Name(name)) => object.get(name).into_iter().collect(),
+        (JsonValue::Extension(tag, payload), Selector::Name(name)) if tag == name => {
+            vec![payload]
+        }
+        (_, Selector::Wildcard) => children(node),
+        (JsonValue::Array(elements), Selector::Index(index)) => {
+            let len = elements.len() as i64;
+            let index = if *index < 0 { len + index } else { *index };
+            usize::try_from(index)
+                .ok()
+                .and_then(|index| elements.get(index))
+                .into_iter()
+                .collect()
+        }
+        (JsonValue::Array(elements), Selector::Slice { start, end, step }) => {
+            slice_indices(elements.len() as i64, *start, *end, *step)
+                .map(|i| &elements[i as usize])
+                .collect()
+        }
+        _ => Vec::new(),
+    }
+}
+
+/// Indices selected by a slice, following RFC 9535 section 2.3.4.2
+fn slice_indices(
+    len: i64,
+    start: Option<i64>,
+    end: Option<i64>,
+    step: i64,
+) -> impl Iterator<Item = i64> {
+    let normalize = |i: i64| if i >= 0 { i } else { len + i };
+    let (mut i, lower, upper) = if step >= 0 {
+        let lower = normalize(start.unwrap_or(0)).clamp(0, len);
+        let upper = normalize(end.unwrap_or(len)).clamp(0, len);
+        (lower, lower, upper)
+    } else {
+        let upper = normalize(start.unwrap_or(len - 1)).clamp(-1, len - 1);
+        let lower = normalize(end.unwrap_or(-len - 1)).clamp(-1, len - 1);
+        (upper, lower, upper)
+    };
+    std::iter::from_fn(move || {
+        let inside = match step {
+            0 => false,
+            s if s > 0 => i < upper,
+            _ => i > lower,
+        };
+        if !inside {
+            return None;
+        }
+        let current = i;
+        i += step;
+        Some(current)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{json, parse_json};
+
+    fn query(document: &str, path: &str) -> String {
+        let value = parse_json(document).unwrap();
+        let path = JsonPath::compile(path).unwrap();
+        let found: Vec<String> = value
+            .query(&path)
+            .iter()
+            .map(|v| v.to_json_string())
+            .collect();
+        found.join(" ")
+    }
+
+    #[test]
+    fn test_query_selectors() {
+        let doc = r#"{"a": [0, 1, 2, 3, 4], "b": {"c": {"a": 5}}, "d e": 6}"#;
+        assert_eq!(query(doc, "$.a[1]"), "1");
+        assert_eq!(query(doc, "$.a[-1]"), "4");
+        assert_eq!(query(doc, "$.a[9]"), "");
+        assert_eq!(query(doc, "$.a[1:3]"), "1 2");
+        assert_eq!(query(doc, "$.a[::2]"), "0 2 4");
+        assert_eq!(query(doc, "$.a[::-1]"), "4 3 2 1 0");
+        assert_eq!(query(doc, "$.a[3:1:-1]"), "3 2");
+        assert_eq!(query(doc, "$.a[0, -1, 0]"), "0 4 0");
+        assert_eq!(query(doc, "$['d e']"), "6");
+        assert_eq!(query(doc, "$.b.*"), r#"{"a": 5}"#);
+        assert_eq!(query(doc, "$..a"), r#"[0, 1, 2, 3, 4] 5"#);
+        assert_eq!(query(doc, "$"), doc);
+    }
+
+    #[test]
+    fn test_filters() {
+        let doc = r#"[
+            {"id": 1, "price": 8.95, "tags": ["a"], "author": {"name": "x"}},
+            {"id": 2, "price": 12, "tags": [], "isbn": null},
+            {"id": 3, "price": 8, "title": "b", "author": {"name": "y"}},
+            "4", 5
+        ]"#;
+        assert_eq!(query(doc, "$[?@.price < 10].id"), "1 3");
+        assert_eq!(query(doc, "$[?@.price >= 12.0].id"), "2");
+        assert_eq!(query(doc, "$[?@.isbn].id"), "2");
+        assert_eq!(query(doc, "$[?!@.isbn && @.id].id"), "1 3");
+        assert_eq!(query(doc, "$[?@.isbn == null].id"), "2");
+        assert_eq!(
+            query(doc, "$[?@.title == 'b' || @.tags[0] == \"a\"].id"),
+            "1 3"
+        );
+        assert_eq!(query(doc, "$[?@.author.name > 'x'].id"), "3");
+        assert_eq!(query(doc, "$[?@ == '4' || @ == 5]"), r#""4" 5"#);
+        assert_eq!(query(doc, "$[?@.missing == @.other].id"), "1 2 3");
+        assert_eq!(query(doc, "$[?@.missing < 1]"), "");
+        assert_eq!(query(doc, "$[?@.id == $[0].id].price"), "8.95");
+        assert_eq!(
+            query(doc, "$[?(@.id == 1 || @.id == 2) && !(@.price > 10)].id"),
+            "1"
+        );
+        assert_eq!(query(doc, "$..[?@.name == 'y'].name"), r#""y""#);
+        assert_eq!(
+            query(r#"{"a": {"v": 1}, "b": {"v": 2}}"#, "$[?@.v != 1]"),
+            r#"{"v": 2}"#
+        );
+        // An unbound variable is missing, like the `id` of the last two
+        assert_eq!(query(doc, "$[?@.id == $x]"), r#""4" 5"#);
+    }
+
+    #[test]
+    fn test_query_with_variables() {
+        let value = parse_json(r#"[{"n": 1, "r": "eu"}, {"n": 2, "r": "us"}, {"n": 3}]"#).unwrap();
+        let path = JsonPath::compile("$[?@.r == $env.region || @.n > $min[0] || $all].n").unwrap();
+        assert_eq!(path.variables(), ["all", "env", "min"]);
+        let mut variables = HashMap::from([
+            ("env".to_string(), json!({"region": "us"})),
+            ("min".to_string(), json!([2])),
+        ]);
+        assert_eq!(
+            value.query_with(&path, &variables).unwrap_err(),
+            UnboundVariable {
+                name: "all".to_string()
+            }
+        );
+        // Bound to `false` still exists, which is all the test needs
+        variables.insert("all".to_string(), json!(false));
+        let found = value.query_with(&path, &variables).unwrap();
+        assert_eq!(found, [&json!(1), &json!(2), &json!(3)]);
+
+        let path = JsonPath::compile("$[?@.r == $env.region || @.n > $min[0]].n").unwrap();
+        assert_eq!(
+            value.query_with(&path, &variables).unwrap(),
+            [&json!(2), &json!(3)]
+        );
+        // In `query` variables select nothing, like the missing `r` of n = 3
+        assert_eq!(value.query(&path), [&json!(3)]);
+    }
+
+    #[test]
+    fn test_compile_errors() {
+        let cases = [
+            ("a", PathErrorKind::MissingRoot, 0),
+            ("$.", PathErrorKind::UnexpectedEnd, 2),
+            ("$.a[", PathErrorKind::UnexpectedEnd, 4),
+            ("$[]", PathErrorKind::UnexpectedCharacter(']'), 2),
+            ("$[1 2]", PathErrorKind::UnexpectedCharacter('2'), 4),
+            ("$['a]", PathErrorKind::UnterminatedString, 2),
+            (r"$['\x']", PathErrorKind::InvalidEscape, 3),
+            ("$[01]", PathErrorKind::InvalidNumber, 2),
+            ("$[-]", PathErrorKind::UnexpectedCharacter(']'), 3),
+            ("$[9007199254740992]", PathErrorKind::InvalidNumber, 2),
+            ("$[?length(@) > 1]", PathErrorKind::UnsupportedFilter, 3),
+            ("$[?@..a == 1]", PathErrorKind::NonSingularQuery, 3),
+            ("$[?1 == @[*]]", PathErrorKind::NonSingularQuery, 8),
+            ("$[?@.a == 01]", PathErrorKind::InvalidNumber, 10),
+            ("$[?@.a == 1.]", PathErrorKind::InvalidNumber, 10),
+            ("$[?1]", PathErrorKind::UnexpectedCharacter(']'), 4),
+            (
+                "$[?@.a == nul]",
+                PathErrorKind::UnexpectedCharacter('n'),
+                10,
+            ),
+            ("$[?(@.a]", PathErrorKind::UnexpectedCharacter(']'), 7),
+            ("$[?@.a &&]", PathErrorKind::UnexpectedCharacter(']'), 9),
+            ("$a", PathErrorKind::UnexpectedCharacter('a'), 1),
+        ];
+        for (path, kind, position) in cases {
+            let error = validate_path(path).unwrap_err();
+            assert_eq!(
+                (error.kind(), error.position()),
+                (kind, position),
+                "{}",
+                path
+            );
+        }
+    }
+
+    #[test]
+    fn test_display_round_trips() {
+        for path in [
+            "$",
+            "$.a..b[0, 'x'][1:2][::-1]",
+            "$[*]..*",
+            "$[?@.a == 'x' || !(@.b < 1e3 && $env.c) && (!@..d || @.e >= -0.5)]",
+            "$..[?@[?@ != null]][?!$.x && $x[0] == true]",
+        ] {
+            let compiled = JsonPath::compile(path).unwrap();
+            assert_eq!(JsonPath::compile(&compiled.to_string()).unwrap(), compiled);
+        }
+    }
+
+    #[test]
+    fn test_stream_matches_query() {
+        let doc = r#"{"items": [{"id": 1}, {"id": 2}], "other": {"id": 3}, "more": [4, 5]}"#;
+        let value = parse_json(doc).unwrap();
+        for path in [
+            "$.items[*].id",
+            "$.*.id",
+            "$.more[1]",
+            "$..id",
+            "$['more', 'items']",
+            "$",
+            "$.items[?@.id > 1]",
+            "$[*][?@.id == $.other.id]",
+        ] {
+            let path = JsonPath::compile(path).unwrap();
+            let streamed: Vec<JsonValue> = path.stream(doc).map(Result::unwrap).collect();
+            let queried: Vec<JsonValue> = value.query(&path).into_iter().cloned().collect();
+            assert_eq!(streamed, queried, "{}", path);
+        }
+
+        let path = JsonPath::compile("$[*]").unwrap();
+        let results: Vec<_> = path.stream("[1, 2 3]").collect();
+        assert_eq!(results.len(), 2);
+        assert!(results[1].is_err());
+        assert!(path.stream("[1,").last().unwrap().is_err());
+    }
+
+    #[test]
+    fn test_stream_reader_matches_stream() {
+        let doc = r#" {"items": [{"id": 1}, {"id": 2}], "more": [4, 5]}"#;
+        for path in ["$.items[*].id", "$.*[1]", "$..id", "$", "$[?@ == 4]"] {
+            let path = JsonPath::compile(path).unwrap();
+            let streamed: Vec<JsonValue> = path.stream(doc).map(Result::unwrap).collect();
+            let read: Vec<JsonValue> =
+                path.stream_reader(doc.as_bytes()).map(Result::unwrap).collect();
+            assert_eq!(read, streamed, "{}", path);
+        }
+
+        // Scalar documents and errors
+        let path = JsonPath::compile("$[0]").unwrap();
+        assert_eq!(path.stream_reader(&b" 7"[..]).count(), 0);
+        let path = JsonPath::compile("$").unwrap();
+        let found: Vec<_> = path.stream_reader(&b"7"[..]).map(Result::unwrap).collect();
+        assert_eq!(found, [JsonValue::from(7)]);
+        assert!(path.stream_reader(&b""[..]).last().unwrap().is_err());
+        let path = JsonPath::compile("$[*]").unwrap();
+        let results: Vec<_> = path.stream_reader(&b"[1, 2 3]"[..]).collect();
+        assert_eq!(results.len(), 2);
+        assert!(results[1].is_err());
+    }
+}
@@ -0,0 +1,107 @@
+//! Value plugins - recognize embedded dialects while parsing
+//!
+//! Many JSON producers layer a small dialect on top of plain JSON, e.g. MongoDB
+//! Extended JSON encodes dates as `{"$date": ...}` and object ids as `{"$oid": ...}`.
+//! Instead of post-processing the finished tree, plugins are consulted by the
+//! parser for every object it builds and may replace it with another value
+//! (usually a `JsonValue::Extension`). This keeps the core grammar untouched
+//! while letting one parser serve several dialects.
+
+use crate::json::JsonValue;
+use crate::object::JsonObject;
+
+/// Hook that may replace a freshly parsed object with a custom value
+///
+/// Plugins are shared between parsers through `Arc`, so they must be
+/// `Send + Sync` and should not rely on per-document state.
+pub trait ValuePlugin: Send + Sync {
+    /// Inspect a parsed object and return its replacement,
+    /// or `None` to keep the object as it is.
+    fn convert(&self, object: &JsonObject) -> Option<JsonValue>;
+}
+
+/// Plugin turning single-member objects with a known key into extensions
+///
+/// `{"$oid": "5f1d..."}` becomes `JsonValue::Extension("$oid", "5f1d...")`
+/// when `$oid` is one of the registered keys. Objects with more than one
+/// member or an unknown key are left alone.
+#[derive(Debug, Clone, Default)]
+pub struct KeyedExtensionPlugin {
+    keys: Vec<String>,
+}
+
+impl KeyedExtensionPlugin {
+    pub fn new<I, S>(keys: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self {
+            keys: keys.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    /// Plugin recognizing the type wrappers of MongoDB Extended JSON
+    pub fn mongodb_extended_json() -> Self {
+        Self::new([
+            "$oid",
+            "$date",
+            "$numberInt",
+            "$numberLong",
+            "$numberDouble",
+            "$numberDecimal",
+            "$binary",
+            "$regularExpression",
+            "$timestamp",
+            "$symbol",
+            "$code",
+            "$minKey",
+            "$maxKey",
+            "$undefined",
+        ])
+    }
+}
+
+impl ValuePlugin for KeyedExtensionPlugin {
+    fn convert(&self, object: &JsonObject) -> Option<JsonValue> {
+        match object.as_slice() {
+            [(key, value)] if self.keys.iter().any(|k| k == key) => {
+                Some(JsonValue::Extension(key.clone(), Box::new(value.clone())))
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_mongodb_extended_json() {
+        let mut parser = Parser::new(r#"{"_id": {"$oid": "abc"}, "n": {"a": 1}}"#).unwrap();
+        parser.register_plugin(Arc::new(KeyedExtensionPlugin::mongodb_extended_json()));
+        let value = parser.parse().unwrap();
+        match value {
+            JsonValue::Object(object) => {
+                assert_eq!(
+                    object.get("_id"),
+                    Some(&JsonValue::Extension(
+                        "$oid".to_string(),
+                        Box::new(JsonValue::String("abc".to_string()))
+                    ))
+                );
+                assert!(object.get("n").unwrap().is_object());
+            }
+            _ => panic!("Expected object"),
+        }
+    }
+
+    #[test]
+    fn test_extension_serializes_as_wrapper_object() {
+        let value = JsonValue::Extension("$date".to_string(), Box::new(JsonValue::Number(0.into())));
+        assert_eq!(value.to_json_string(), r#"{"$date": 0}"#);
+    }
+}
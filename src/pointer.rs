@@ -0,0 +1,500 @@
+//! JSON Pointer (RFC 6901) lookups
+//!
+//! A pointer such as `/users/0/name` is a sequence of reference tokens
+//! separated by `/`. Inside a token `~1` stands for `/` and `~0` for `~`.
+//! The empty string refers to the whole document.
+
+use std::fmt;
+
+use crate::json::JsonValue;
+
+/// A parsed JSON Pointer: its reference tokens, already unescaped
+///
+/// Parsing once up front avoids re-splitting and re-unescaping the pointer
+/// text on every lookup, and lets [`JsonValue::get_many`] compare pointers
+/// token by token.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+pub struct JsonPointer {
+    tokens: Vec<String>,
+}
+
+/// Why pointer text is malformed, and where
+///
+/// # Examples
+///
+/// ```
+/// use json_parser_rs::pointer::{JsonPointer, PointerErrorKind};
+///
+/// let error = JsonPointer::parse("/a/b~2").unwrap_err();
+/// assert_eq!(error.kind(), PointerErrorKind::InvalidEscape);
+/// assert_eq!(error.position(), 4);
+/// assert_eq!(
+///     error.to_string(),
+///     "Invalid JSON Pointer at position 4: '~' must be followed by '0' or '1'"
+/// );
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PointerError {
+    kind: PointerErrorKind,
+    position: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum PointerErrorKind {
+    /// A non-empty pointer must start with `/`
+    MissingLeadingSlash,
+    /// `~` not followed by `0` or `1`
+    InvalidEscape,
+}
+
+impl PointerError {
+    pub fn kind(&self) -> PointerErrorKind {
+        self.kind
+    }
+
+    /// Byte offset into the pointer text
+    pub fn position(&self) -> usize {
+        self.position
+    }
+}
+
+impl fmt::Display for PointerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let problem = match self.kind {
+            PointerErrorKind::MissingLeadingSlash => "a pointer must be empty or start with '/'",
+            PointerErrorKind::InvalidEscape => "'~' must be followed by '0' or '1'",
+        };
+        write!(
+            f,
+            "Invalid JSON Pointer at position {}: {}",
+            self.position, problem
+        )
+    }
+}
+
+impl std::error::Error for PointerError {}
+
+impl JsonPointer {
+    /// Parse pointer text such as `/users/0/name`
+    pub fn parse(pointer: &str) -> Result<Self, PointerError> {
+        let error = |kind, position| PointerError { kind, position };
+        let tokens =
+            split_pointer(pointer).ok_or(error(PointerErrorKind::MissingLeadingSlash, 0))?;
+        let mut parsed = Vec::new();
+        // Byte offset of the current token; each is preceded by a `/`
+        let mut offset = 1;
+        for token in tokens {
+            let unescaped = unescape_token(token).ok_or_else(|| {
+                let tilde = token
+                    .match_indices('~')
+                    .map(|(i, _)| i)
+                    .find(|&i| !matches!(token.as_bytes().get(i + 1), Some(b'0' | b'1')))
+                    .unwrap_or(0);
+                error(PointerErrorKind::InvalidEscape, offset + tilde)
+            })?;
+            parsed.push(unescaped);
+            offset += token.len() + 1;
+        }
+        Ok(Self { tokens: parsed })
+    }
+
+    /// Pointer made of the given (unescaped) tokens
+    pub fn from_tokens<I, S>(tokens: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self {
+            tokens: tokens.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    /// The unescaped reference tokens
+    pub fn tokens(&self) -> &[String] {
+        &self.tokens
+    }
+}
+
+/// Prints the escaped pointer text
+impl fmt::Display for JsonPointer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for token in &self.tokens {
+            write!(f, "/{}", escape_token(token))?;
+        }
+        Ok(())
+    }
+}
+
+impl JsonValue {
+    /// Resolve an RFC 6901 JSON Pointer against this value
+    ///
+    /// Returns `None` if the pointer is malformed or does not resolve.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use json_parser_rs::parse_json;
+    ///
+    /// let value = parse_json(r#"{"users": [{"name": "Ada"}]}"#).unwrap();
+    /// let name = value.pointer("/users/0/name").unwrap();
+    /// assert_eq!(name.to_json_string(), r#""Ada""#);
+    /// ```
+    pub fn pointer(&self, pointer: &str) -> Option<&JsonValue> {
+        let mut current = self;
+        for token in split_pointer(pointer)? {
+            current = step(current, &unescape_token(token)?)?;
+        }
+        Some(current)
+    }
+
+    /// Resolve many pointers in one traversal
+    ///
+    /// Pointers are visited in sorted order so that a shared prefix such as
+    /// `/data/items` is walked once rather than once per pointer. Results come
+    /// back in the order of `pointers`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use json_parser_rs::{parse_json, pointer::JsonPointer};
+    ///
+    /// let value = parse_json(r#"{"a": {"b": 1, "c": 2}}"#).unwrap();
+    /// let b = JsonPointer::parse("/a/b").unwrap();
+    /// let x = JsonPointer::parse("/a/x").unwrap();
+    /// let found = value.get_many(&[&b, &x]);
+    /// assert_eq!(found[0].unwrap().to_json_string(), "1");
+    /// assert!(found[1].is_none());
+    /// ```
+    pub fn get_many(&self, pointers: &[&JsonPointer]) -> Vec<Option<&JsonValue>> {
+        let mut order: Vec<usize> = (0..pointers.len()).collect();
+        order.sort_unstable_by(|&a, &b| pointers[a].tokens.cmp(&pointers[b].tokens));
+
+        let mut results = vec![None; pointers.len()];
+        // stack[d] is the value reached after the first d tokens of `previous`
+        let mut stack = vec![self];
+        let mut previous: &[String] = &[];
+        for i in order {
+            let tokens = pointers[i].tokens();
+            let shared = tokens
+                .iter()
+                .zip(previous)
+                .take_while(|(a, b)| a == b)
+                .count();
+            stack.truncate(shared.min(stack.len() - 1) + 1);
+            while stack.len() <= tokens.len() {
+                let current = stack[stack.len() - 1];
+                match step(current, &tokens[stack.len() - 1]) {
+                    Some(next) => stack.push(next),
+                    None => break,
+                }
+            }
+            if stack.len() == tokens.len() + 1 {
+                results[i] = Some(stack[tokens.len()]);
+            }
+            previous = tokens;
+        }
+        results
+    }
+
+    /// Mutable variant of [`JsonValue::pointer`]
+    pub fn pointer_mut(&mut self, pointer: &str) -> Option<&mut JsonValue> {
+        let mut current = self;
+        for token in split_pointer(pointer)? {
+            let token = unescape_token(token)?;
+            current = match current.untagged_mut() {
+                JsonValue::Object(object) => object.get_mut(&token)?,
+                JsonValue::Array(elements) => elements.get_mut(parse_index(&token)?)?,
+                JsonValue::Extension(tag, payload) if *tag == token => payload,
+                _ => return None,
+            };
+        }
+        Some(current)
+    }
+}
+
+/// Follow one unescaped reference token from `current`
+fn step<'a>(current: &'a JsonValue, token: &str) -> Option<&'a JsonValue> {
+    match current.untagged() {
+        JsonValue::Object(object) => object.get(token),
+        JsonValue::Array(elements) => elements.get(parse_index(token)?),
+        JsonValue::Extension(tag, payload) if tag == token => Some(payload),
+        _ => None,
+    }
+}
+
+/// Escape a single reference token (`~` -> `~0`, `/` -> `~1`)
+pub fn escape_token(token: &str) -> String {
+    token.replace('~', "~0").replace('/', "~1")
+}
+
+/// Unescape a single reference token, rejecting invalid `~` sequences
+pub fn unescape_token(token: &str) -> Option<String> {
+    let mut result = String::with_capacity(token.len());
+    let mut chars = token.chars();
+    while let Some(ch) = chars.next() {
+        if ch == '~' {
+            match chars.next() {
+                Some('0') => result.push('~'),
+                Some('1') => result.push('/'),
+                _ => return None,
+            }
+        } else {
+            result.push(ch);
+        }
+    }
+    Some(result)
+}
+
+/// Build an escaped JSON Pointer from literal tokens at compile time
+///
+/// String literals and unsuffixed decimal integer literals (array indices)
+/// are accepted; any other literal, such as `1.5` or `true`, fails to
+/// compile. `~` and `/` inside tokens are escaped while compiling, so the
+/// result is a `&'static str` usable in `const` items and hot paths without
+/// any runtime formatting.
+///
+/// # Examples
+///
+/// ```
+/// use json_parser_rs::pointer;
+///
+/// const NAME: &str = pointer!("users", 0, "first/last");
+/// assert_eq!(NAME, "/users/0/first~1last");
+/// assert_eq!(pointer!(), "");
+/// ```
+///
+/// ```compile_fail
+/// let index = json_parser_rs::pointer!("items", 1.5);
+/// ```
+///
+/// ```compile_fail
+/// let flag = json_parser_rs::pointer!("enabled", true);
+/// ```
+#[macro_export]
+macro_rules! pointer {
+    () => {
+        ""
+    };
+    ($($token:literal),+ $(,)?) => {{
+        $(const _: () = $crate::pointer::__check_token(::core::stringify!($token));)+
+        const TOKENS: &[&str] = &[$(::core::concat!($token)),+];
+        const LEN: usize = $crate::pointer::__encoded_len(TOKENS);
+        const BYTES: [u8; LEN] = $crate::pointer::__encode::<LEN>(TOKENS);
+        const POINTER: &str = match ::core::str::from_utf8(&BYTES) {
+            Ok(pointer) => pointer,
+            Err(_) => panic!("pointer! produced invalid UTF-8"),
+        };
+        POINTER
+    }};
+}
+
+/// Fail compilation unless `literal` (as written in the source) is a string
+/// or unsuffixed decimal integer literal
+#[doc(hidden)]
+pub const fn __check_token(literal: &str) {
+    let bytes = literal.as_bytes();
+    let string = matches!(bytes, [b'"', ..] | [b'r', b'"' | b'#', ..]);
+    let mut integer = !bytes.is_empty();
+    let mut i = 0;
+    while i < bytes.len() {
+        integer &= bytes[i].is_ascii_digit();
+        i += 1;
+    }
+    if !string && !integer {
+        panic!("pointer! tokens must be string or integer literals");
+    }
+}
+
+#[doc(hidden)]
+pub const fn __encoded_len(tokens: &[&str]) -> usize {
+    let mut len = 0;
+    let mut i = 0;
+    while i < tokens.len() {
+        let bytes = tokens[i].as_bytes();
+        len += 1 + bytes.len();
+        let mut j = 0;
+        while j < bytes.len() {
+            if bytes[j] == b'~' || bytes[j] == b'/' {
+                len += 1;
+            }
+            j += 1;
+        }
+        i += 1;
+    }
+    len
+}
+
+#[doc(hidden)]
+pub const fn __encode<const LEN: usize>(tokens: &[&str]) -> [u8; LEN] {
+    let mut out = [0u8; LEN];
+    let mut at = 0;
+    let mut i = 0;
+    while i < tokens.len() {
+        out[at] = b'/';
+        at += 1;
+        let bytes = tokens[i].as_bytes();
+        let mut j = 0;
+        while j < bytes.len() {
+            match bytes[j] {
+                b'~' => {
+                    out[at] = b'~';
+                    out[at + 1] = b'0';
+                    at += 2;
+                }
+                b'/' => {
+                    out[at] = b'~';
+                    out[at + 1] = b'1';
+                    at += 2;
+                }
+                byte => {
+                    out[at] = byte;
+                    at += 1;
+                }
+            }
+            j += 1;
+        }
+        i += 1;
+    }
+    out
+}
+
+/// Check whether a path of unescaped tokens matches a pointer pattern,
+/// where a `*` token matches any single key or index
+pub fn pattern_matches(pattern: &str, path: &[String]) -> bool {
+    let Some(tokens) = split_pointer(pattern) else {
+        return false;
+    };
+    let mut remaining = path.iter();
+    for token in tokens {
+        let Some(actual) = remaining.next() else {
+            return false;
+        };
+        if token != "*" && unescape_token(token).as_ref() != Some(actual) {
+            return false;
+        }
+    }
+    remaining.next().is_none()
+}
+
+fn split_pointer(pointer: &str) -> Option<impl Iterator<Item = &str>> {
+    // The empty pointer yields no tokens, i.e. the whole document
+    if !pointer.is_empty() && !pointer.starts_with('/') {
+        return None;
+    }
+    Some(pointer.split('/').skip(1))
+}
+
+/// Array indices are decimal digits without leading zeros; `-` (the element
+/// after the last one) never resolves for lookups.
+pub(crate) fn parse_index(token: &str) -> Option<usize> {
+    if token.is_empty()
+        || !token.bytes().all(|b| b.is_ascii_digit())
+        || (token.len() > 1 && token.starts_with('0'))
+    {
+        return None;
+    }
+    token.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parse_json;
+
+    #[test]
+    fn test_pointer_rfc6901_examples() {
+        let doc = parse_json(
+            r#"{"foo": ["bar", "baz"], "": 0, "a/b": 1, "c%d": 2, "e^f": 3,
+                "g|h": 4, "i\\j": 5, "k\"l": 6, " ": 7, "m~n": 8}"#,
+        )
+        .unwrap();
+
+        assert_eq!(doc.pointer(""), Some(&doc));
+        assert_eq!(doc.pointer("/foo/0").unwrap().to_json_string(), r#""bar""#);
+        assert_eq!(doc.pointer("/").unwrap().to_json_string(), "0");
+        assert_eq!(doc.pointer("/a~1b").unwrap().to_json_string(), "1");
+        assert_eq!(doc.pointer("/i\\j").unwrap().to_json_string(), "5");
+        assert_eq!(doc.pointer("/ ").unwrap().to_json_string(), "7");
+        assert_eq!(doc.pointer("/m~0n").unwrap().to_json_string(), "8");
+    }
+
+    #[test]
+    fn test_pointer_misses() {
+        let doc = parse_json(r#"{"a": [1, 2]}"#).unwrap();
+        assert!(doc.pointer("a").is_none());
+        assert!(doc.pointer("/a/2").is_none());
+        assert!(doc.pointer("/a/01").is_none());
+        assert!(doc.pointer("/a/-").is_none());
+        assert!(doc.pointer("/a~2").is_none());
+    }
+
+    #[test]
+    fn test_pointer_macro() {
+        const POINTER: &str = pointer!("a/b", "m~n", 3, "é");
+        assert_eq!(POINTER, "/a~1b/m~0n/3/é");
+
+        let doc = parse_json(r#"{"a/b": {"m~n": [0, 1, 2, {"é": true}]}}"#).unwrap();
+        assert!(doc.pointer(POINTER).is_some());
+        assert_eq!(pointer!(r"x\y", 10), "/x\\y/10");
+
+        // What `pointer!` runs on each token while compiling
+        for accepted in ["\"a\"", "r\"a\"", "r#\"a\"#", "0", "42"] {
+            super::__check_token(accepted);
+        }
+        for rejected in ["1.5", "1e3", "true", "'a'", "b\"a\"", "0u8", "-1", "0x10"] {
+            let panicked = std::panic::catch_unwind(|| super::__check_token(rejected)).is_err();
+            assert!(panicked, "{}", rejected);
+        }
+    }
+
+    #[test]
+    fn test_pattern_matches() {
+        let path = ["events".to_string(), "3".to_string(), "a/b".to_string()];
+        assert!(super::pattern_matches("/events/*/a~1b", &path));
+        assert!(!super::pattern_matches("/events/*", &path));
+        assert!(!super::pattern_matches("/events/*/a/b", &path));
+        assert!(super::pattern_matches("", &[]));
+    }
+
+    #[test]
+    fn test_get_many_matches_pointer() {
+        use super::{JsonPointer, PointerErrorKind};
+
+        let doc =
+            parse_json(r#"{"data": {"items": [{"id": 1}, {"id": 2}], "total": 2}, "a~b": null}"#)
+                .unwrap();
+        let texts = [
+            "/data/items/1/id",
+            "/data/total",
+            "",
+            "/data/items/0/id",
+            "/data/items/7/id",
+            "/data/items/1/id",
+            "/a~0b",
+            "/data/total/x",
+        ];
+        let pointers: Vec<JsonPointer> = texts
+            .iter()
+            .map(|t| JsonPointer::parse(t).unwrap())
+            .collect();
+        let refs: Vec<&JsonPointer> = pointers.iter().collect();
+
+        let found = doc.get_many(&refs);
+        for (text, result) in texts.iter().zip(found) {
+            assert_eq!(result, doc.pointer(text), "{}", text);
+        }
+        assert_eq!(pointers[6].to_string(), "/a~0b");
+        assert_eq!(JsonPointer::parse("/a~2").unwrap_err().position(), 2);
+        assert_eq!(
+            JsonPointer::parse("a").unwrap_err().kind(),
+            PointerErrorKind::MissingLeadingSlash
+        );
+    }
+
+    #[test]
+    fn test_pointer_mut() {
+        let mut doc = parse_json(r#"{"a": [1, 2]}"#).unwrap();
+        *doc.pointer_mut("/a/1").unwrap() = crate::JsonValue::Null;
+        assert_eq!(doc.to_json_string(), r#"{"a": [1, null]}"#);
+    }
+}
@@ -0,0 +1,698 @@
+//! Push parsing - JSON arriving in chunks
+//!
+//! `Parser` pulls tokens from a complete string or a blocking reader. A
+//! [`PushParser`] is driven the other way around: the caller hands it
+//! bytes as they arrive (from a socket, an HTTP body or a message queue)
+//! and it parses as far as they allow, keeping only the text of an
+//! unfinished token and the partly built values. Chunks may split tokens
+//! and UTF-8 sequences anywhere.
+
+use crate::error::{ErrorKind, ParseError};
+use crate::json::JsonValue;
+use crate::lexer::{BYTE_ORDER_MARK, Lexer, Token};
+use crate::object::{DuplicateKeyPolicy, JsonObject};
+use crate::options::ParserOptions;
+
+/// What a [`PushParser`] could make of the input so far
+#[derive(Debug, Clone, PartialEq)]
+pub enum PushStatus {
+    /// The buffered input ends inside a value (or holds none)
+    NeedMoreData,
+    /// A complete top-level value; more may follow in the buffered input
+    Value(JsonValue),
+}
+
+/// Incremental parser fed with byte chunks
+///
+/// The input may hold several top-level values one after another,
+/// separated by whitespace (as NDJSON or concatenated JSON streams do);
+/// each is returned as soon as its last token is complete. A number at the
+/// very end of the input is only known to be complete once
+/// [`PushParser::finish`] is called.
+///
+/// Syntax relaxations, `max_depth`, `max_nodes` (per value), `max_elements`,
+/// `max_string_length` and `max_input_size` (for all bytes fed) of the
+/// options are honored; `max_memory` is not. After an error the parser
+/// keeps returning that error.
+///
+/// # Examples
+///
+/// ```
+/// use json_parser_rs::push::{PushParser, PushStatus};
+/// use json_parser_rs::json;
+///
+/// let mut parser = PushParser::new();
+/// assert_eq!(parser.feed(br#"{"id": 1, "na"#).unwrap(), PushStatus::NeedMoreData);
+/// assert_eq!(
+///     parser.feed(b"me\": \"Ada\"}\n[tr").unwrap(),
+///     PushStatus::Value(json!({"id": 1, "name": "Ada"}))
+/// );
+/// assert_eq!(parser.poll().unwrap(), PushStatus::NeedMoreData);
+/// assert_eq!(parser.feed(b"ue] 4").unwrap(), PushStatus::Value(json!([true])));
+/// assert_eq!(parser.poll().unwrap(), PushStatus::NeedMoreData);
+/// assert_eq!(parser.finish().unwrap(), Some(json!(4)));
+/// ```
+#[derive(Debug)]
+pub struct PushParser {
+    options: ParserOptions,
+    /// Decoded text; everything before `start` has been consumed
+    window: String,
+    start: usize,
+    /// Byte offset, line and column of `window[start]` in the whole input
+    offset: usize,
+    line: usize,
+    column: usize,
+    /// Bytes of a UTF-8 sequence split across two chunks
+    incomplete: Vec<u8>,
+    bytes_fed: usize,
+    /// Where scanning for the end of an unfinished string resumes
+    string_scan: usize,
+    /// Containers being built, innermost last
+    stack: Vec<Frame>,
+    nodes: usize,
+    finished: bool,
+    failed: Option<ParseError>,
+}
+
+/// A container being built and what it expects next
+#[derive(Debug)]
+enum Frame {
+    Array(Vec<JsonValue>, Expect),
+    /// The pending key with its location, for duplicate key errors
+    Object(JsonObject, Option<(String, Location)>, Expect),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Expect {
+    /// A value or key, or the end of the container
+    First,
+    /// `:` after a key
+    Colon,
+    /// A value after `:`
+    Value,
+    /// `,` or the end of the container
+    Separator,
+    /// A value or key after `,` (or the end, with trailing commas allowed)
+    Next,
+}
+
+/// Byte offset, line and column of a token
+#[derive(Debug, Clone, Copy)]
+struct Location {
+    offset: usize,
+    line: usize,
+    column: usize,
+}
+
+impl Default for PushParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PushParser {
+    pub fn new() -> Self {
+        Self::with_options(ParserOptions::default())
+    }
+
+    pub fn with_options(options: ParserOptions) -> Self {
+        Self {
+            options,
+            window: String::new(),
+            start: 0,
+            offset: 0,
+            line: 1,
+            column: 1,
+            incomplete: Vec::new(),
+            bytes_fed: 0,
+            string_scan: 0,
+            stack: Vec::new(),
+            nodes: 0,
+            finished: false,
+            failed: None,
+        }
+    }
+
+    /// Buffer `chunk` and parse as far as possible, as [`PushParser::poll`]
+    pub fn feed(&mut self, chunk: &[u8]) -> Result<PushStatus, ParseError> {
+        self.buffer(chunk)?;
+        self.poll()
+    }
+
+    /// Buffer `chunk` without parsing it yet
+    pub(crate) fn buffer(&mut self, chunk: &[u8]) -> Result<(), ParseError> {
+        self.check_failed()?;
+        if self.finished {
+            return self.fail(self.error_at_end("Input fed after finish"));
+        }
+        self.bytes_fed += chunk.len();
+        if let Some(max) = self.options.max_input_size
+            && self.bytes_fed > max
+        {
+            let error = ParseError::at(
+                format!("Input size exceeds the limit of {} bytes", max),
+                0,
+                1,
+                1,
+            );
+            return self.fail(error.with_kind(ErrorKind::InputTooLarge));
+        }
+        self.incomplete.extend_from_slice(chunk);
+        let valid = match std::str::from_utf8(&self.incomplete) {
+            Ok(text) => text.len(),
+            // A sequence cut off by the end of the chunk waits for the next
+            Err(e) if e.error_len().is_none() => e.valid_up_to(),
+            Err(e) => {
+                let valid = e.valid_up_to();
+                self.push_decoded(valid);
+                let error = self.error_at_end("Invalid UTF-8 in input");
+                return self.fail(error.with_kind(ErrorKind::InvalidUtf8));
+            }
+        };
+        self.push_decoded(valid);
+        Ok(())
+    }
+
+    /// Parse the buffered input up to the end of the next top-level value
+    ///
+    /// Call this until it returns `NeedMoreData` to get every value a chunk
+    /// completed.
+    pub fn poll(&mut self) -> Result<PushStatus, ParseError> {
+        self.check_failed()?;
+        loop {
+            let token = match self.next_token() {
+                Ok(Some(token)) => token,
+                Ok(None) => return Ok(PushStatus::NeedMoreData),
+                Err(error) => return self.fail(error),
+            };
+            if token.0 == Token::Eof && self.stack.is_empty() {
+                return Ok(PushStatus::NeedMoreData);
+            }
+            match self.accept(token) {
+                Ok(Some(value)) => return Ok(PushStatus::Value(value)),
+                Ok(None) => {}
+                Err(error) => return self.fail(error),
+            }
+        }
+    }
+
+    /// Mark the end of the input and return the value still pending, if any
+    ///
+    /// Fails if the input ends inside a value. If several values were left
+    /// in the buffer, `finish` returns the first and [`PushParser::poll`]
+    /// the others.
+    pub fn finish(&mut self) -> Result<Option<JsonValue>, ParseError> {
+        self.check_failed()?;
+        if !self.incomplete.is_empty() {
+            let error = self.error_at_end("Invalid UTF-8 in input");
+            return self.fail(error.with_kind(ErrorKind::InvalidUtf8));
+        }
+        self.finished = true;
+        match self.poll()? {
+            PushStatus::Value(value) => Ok(Some(value)),
+            PushStatus::NeedMoreData => Ok(None),
+        }
+    }
+
+    /// `true` while a value has been started but not completed
+    pub fn is_inside_value(&self) -> bool {
+        !self.stack.is_empty() || !self.window[self.start..].trim_start().is_empty()
+    }
+
+    /// Total number of bytes fed so far
+    pub fn bytes_fed(&self) -> usize {
+        self.bytes_fed
+    }
+
+    /// Fail if anything but whitespace (and allowed comments) follows the
+    /// value returned last, for callers expecting a single document
+    pub(crate) fn check_trailing(&mut self) -> Result<(), ParseError> {
+        if self.skip_whitespace() && self.start < self.window.len() {
+            let error = ParseError::at(
+                "Unexpected characters after JSON value",
+                self.offset,
+                self.line,
+                self.column,
+            );
+            return self.fail(error.with_kind(ErrorKind::TrailingCharacters));
+        }
+        Ok(())
+    }
+
+    fn check_failed(&self) -> Result<(), ParseError> {
+        match &self.failed {
+            Some(error) => Err(error.clone()),
+            None => Ok(()),
+        }
+    }
+
+    fn fail<T>(&mut self, error: ParseError) -> Result<T, ParseError> {
+        self.failed = Some(error.clone());
+        Err(error)
+    }
+
+    fn push_decoded(&mut self, valid: usize) {
+        // Safe to split: the prefix up to `valid` is UTF-8
+        let text = std::str::from_utf8(&self.incomplete[..valid]).unwrap();
+        self.window.push_str(text);
+        self.incomplete.drain(..valid);
+    }
+
+    /// The next complete token with its location; `None` if the buffered
+    /// input ends inside one (`Eof` only after `finish`)
+    fn next_token(&mut self) -> Result<Option<(Token, Location)>, ParseError> {
+        if !self.skip_whitespace() {
+            return Ok(None);
+        }
+        let location = Location {
+            offset: self.offset,
+            line: self.line,
+            column: self.column,
+        };
+        let Some(first) = self.window[self.start..].chars().next() else {
+            return Ok(self.finished.then_some((Token::Eof, location)));
+        };
+        let end = match first {
+            '"' => self.string_end(b'"'),
+            '\'' if self.options.allow_single_quotes => self.string_end(b'\''),
+            '{' | '}' | '[' | ']' | ',' | ':' => Some(self.start + 1),
+            // A malformed comment, which `Lexer` reports
+            '/' if self.options.allow_comments => Some(self.window.len()),
+            _ => self.word_end(),
+        };
+        let Some(end) = end else {
+            return Ok(None);
+        };
+
+        let text = &self.window[self.start..end];
+        let mut lexer = Lexer::fragment(text, &self.options);
+        match lexer.next_token() {
+            Ok(token) => {
+                self.consume(lexer.offset());
+                Ok(Some((token, location)))
+            }
+            Err(error) => Err(error.relocate_to(self.offset, self.line, self.column)),
+        }
+    }
+
+    /// Skip whitespace and, if allowed, comments; `false` if the buffered
+    /// input ends inside a comment
+    fn skip_whitespace(&mut self) -> bool {
+        loop {
+            let rest = &self.window[self.start..];
+            let Some(ch) = rest.chars().next() else {
+                return true;
+            };
+            if ch.is_whitespace() || (ch == BYTE_ORDER_MARK && self.offset == 0) {
+                self.consume(ch.len_utf8());
+                continue;
+            }
+            if ch != '/' || !self.options.allow_comments {
+                return true;
+            }
+            let end = match rest.as_bytes().get(1) {
+                Some(b'/') => rest.find('\n'),
+                Some(b'*') => rest[2..].find("*/").map(|i| i + 4),
+                Some(_) => return true,
+                None => None,
+            };
+            match end {
+                Some(end) => self.consume(end),
+                // A line comment may end with the input
+                None if self.finished && rest.starts_with("//") => self.consume(rest.len()),
+                // Left for `Lexer` to report
+                None if self.finished => return true,
+                None => return false,
+            }
+        }
+    }
+
+    /// End of the string opened at `start`, just past the closing quote
+    fn string_end(&mut self, quote: u8) -> Option<usize> {
+        let bytes = self.window.as_bytes();
+        let mut i = self.string_scan.max(self.start + 1);
+        while i < bytes.len() {
+            match bytes[i] {
+                b'\\' if i + 1 == bytes.len() => break,
+                b'\\' => i += 2,
+                byte if byte == quote => return Some(i + 1),
+                _ => i += 1,
+            }
+        }
+        self.string_scan = i;
+        // Unterminated: let `Lexer` report it once the input has ended
+        self.finished.then_some(bytes.len())
+    }
+
+    /// End of a number, keyword or stray character starting at `start`
+    fn word_end(&self) -> Option<usize> {
+        let rest = &self.window[self.start..];
+        match rest.find(|ch: char| ch.is_whitespace() || "{}[],:\"'/".contains(ch)) {
+            // At least one character, so stray ones get reported
+            Some(0) => Some(self.start + rest.chars().next().map_or(0, char::len_utf8)),
+            Some(length) => Some(self.start + length),
+            None => self.finished.then_some(self.window.len()),
+        }
+    }
+
+    /// Mark `len` bytes after `start` as consumed
+    fn consume(&mut self, len: usize) {
+        let text = &self.window[self.start..self.start + len];
+        match text.rfind('\n') {
+            Some(i) => {
+                self.line += text.matches('\n').count();
+                self.column = text.len() - i;
+            }
+            None => self.column += text.len(),
+        }
+        self.offset += len;
+        self.start += len;
+        self.string_scan = 0;
+        // Drop consumed text once it makes up half the window, which keeps
+        // the copying linear overall
+        if self.start > self.window.len() / 2 {
+            self.window.drain(..self.start);
+            self.start = 0;
+        }
+    }
+
+    /// Error located just after the text decoded so far
+    pub(crate) fn error_at_end(&self, message: impl Into<String>) -> ParseError {
+        let text = &self.window[self.start..];
+        let (line, column) = match text.rfind('\n') {
+            Some(i) => (self.line + text.matches('\n').count(), text.len() - i),
+            None => (self.line, self.column + text.len()),
+        };
+        ParseError::at(message, self.offset + text.len(), line, column)
+    }
+
+    /// Feed one token to the containers being built; a completed
+    /// top-level value is returned
+    fn accept(&mut self, (token, at): (Token, Location)) -> Result<Option<JsonValue>, ParseError> {
+        let error = |message: String, kind| {
+            ParseError::at(message, at.offset, at.line, at.column).with_kind(kind)
+        };
+        let (expect, in_object) = match self.stack.last() {
+            None => (Expect::Value, false),
+            Some(Frame::Array(_, expect)) => (*expect, false),
+            Some(Frame::Object(_, _, expect)) => (*expect, true),
+        };
+        let (closing, close_text) = match in_object {
+            true => (Token::RightBrace, "}"),
+            false => (Token::RightBracket, "]"),
+        };
+        match expect {
+            Expect::First if token == closing => return self.close(),
+            Expect::Next if token == closing => {
+                if self.options.allow_trailing_commas {
+                    return self.close();
+                }
+                return Err(error(
+                    "Trailing comma not allowed".to_string(),
+                    ErrorKind::TrailingComma,
+                ));
+            }
+            Expect::Separator => {
+                return match token {
+                    Token::Comma => {
+                        self.set_expect(Expect::Next);
+                        Ok(None)
+                    }
+                    _ if token == closing => self.close(),
+                    _ => Err(error(
+                        format!("Expected ',' or '{}', found {}", close_text, token),
+                        ErrorKind::unexpected(&token),
+                    )),
+                };
+            }
+            Expect::First | Expect::Next if in_object => {
+                if let Some(Frame::Object(object, _, _)) = self.stack.last() {
+                    self.check_elements(object.len(), "Object")
+                        .map_err(|(message, kind)| error(message, kind))?;
+                }
+                let key = self
+                    .key(token)
+                    .map_err(|(message, kind)| error(message, kind))?;
+                if let Some(Frame::Object(_, pending, expect)) = self.stack.last_mut() {
+                    *pending = Some((key, at));
+                    *expect = Expect::Colon;
+                }
+                return Ok(None);
+            }
+            Expect::Colon => {
+                if token != Token::Colon {
+                    return Err(error(
+                        format!("Expected {}, found {}", Token::Colon, token),
+                        ErrorKind::unexpected(&token),
+                    ));
+                }
+                self.set_expect(Expect::Value);
+                return Ok(None);
+            }
+            _ => {}
+        }
+
+        // A value is expected
+        if let Some(Frame::Array(elements, _)) = self.stack.last() {
+            self.check_elements(elements.len(), "Array")
+                .map_err(|(message, kind)| error(message, kind))?;
+        }
+        self.nodes += 1;
+        if let Some(max) = self.options.max_nodes
+            && self.nodes > max
+        {
+            return Err(error(
+                format!("Document has more than {} values", max),
+                ErrorKind::NodeLimitExceeded,
+            ));
+        }
+        let value = match token {
+            Token::String(s) => JsonValue::String(s),
+            Token::RawString(raw) => JsonValue::RawString(raw),
+            Token::Number(n) => JsonValue::Number(n),
+            Token::Boolean(b) => JsonValue::Boolean(b),
+            Token::Null => JsonValue::Null,
+            Token::LeftBrace | Token::LeftBracket => {
+                if let Some(max) = self.options.max_depth
+                    && self.stack.len() >= max
+                {
+                    return Err(error(
+                        format!("Maximum nesting depth of {} exceeded", max),
+                        ErrorKind::DepthLimitExceeded,
+                    ));
+                }
+                self.stack.push(match token {
+                    Token::LeftBrace => Frame::Object(JsonObject::new(), None, Expect::First),
+                    _ => Frame::Array(Vec::new(), Expect::First),
+                });
+                return Ok(None);
+            }
+            token => {
+                return Err(error(
+                    format!("Unexpected token: {}", token),
+                    ErrorKind::unexpected(&token),
+                ));
+            }
+        };
+        self.complete(value)
+    }
+
+    /// Check that a container holding `len` elements may take another one
+    fn check_elements(&self, len: usize, container: &str) -> Result<(), (String, ErrorKind)> {
+        match self.options.max_elements {
+            Some(max) if len >= max => Err((
+                format!("{} has more than {} elements", container, max),
+                ErrorKind::TooManyElements,
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// The object key a token stands for, as `Parser` accepts them
+    fn key(&self, token: Token) -> Result<String, (String, ErrorKind)> {
+        let unquoted = self.options.allow_unquoted_keys;
+        match token {
+            Token::String(s) => Ok(s),
+            Token::Identifier(name) if unquoted => Ok(name),
+            Token::Boolean(b) if unquoted => Ok(b.to_string()),
+            Token::Null if unquoted => Ok("null".to_string()),
+            Token::RawString(_) => Err((
+                "Unpaired surrogate in object key".to_string(),
+                ErrorKind::InvalidEscape,
+            )),
+            Token::Eof => Err((
+                "Object key must be a string".to_string(),
+                ErrorKind::UnexpectedEof,
+            )),
+            _ => Err((
+                "Object key must be a string".to_string(),
+                ErrorKind::KeyMustBeString,
+            )),
+        }
+    }
+
+    fn set_expect(&mut self, next: Expect) {
+        match self.stack.last_mut() {
+            Some(Frame::Array(_, expect) | Frame::Object(_, _, expect)) => *expect = next,
+            None => {}
+        }
+    }
+
+    /// Close the innermost container
+    fn close(&mut self) -> Result<Option<JsonValue>, ParseError> {
+        let value = match self.stack.pop() {
+            Some(Frame::Array(elements, _)) => JsonValue::Array(elements),
+            Some(Frame::Object(object, _, _)) => JsonValue::Object(object),
+            None => unreachable!("closing without an open container"),
+        };
+        self.complete(value)
+    }
+
+    /// Add a finished value to its container, or return it if it is a
+    /// top-level value
+    fn complete(&mut self, value: JsonValue) -> Result<Option<JsonValue>, ParseError> {
+        let policy = self.options.duplicate_keys;
+        match self.stack.last_mut() {
+            None => {
+                self.nodes = 0;
+                Ok(Some(value))
+            }
+            Some(Frame::Array(elements, expect)) => {
+                elements.push(value);
+                *expect = Expect::Separator;
+                Ok(None)
+            }
+            Some(Frame::Object(object, key, expect)) => {
+                let (key, at) = key.take().expect("a value in an object follows its key");
+                *expect = Expect::Separator;
+                if !object.contains_key(&key) || policy == DuplicateKeyPolicy::Last {
+                    object.insert(key, value);
+                } else if policy == DuplicateKeyPolicy::Error {
+                    return Err(ParseError::at(
+                        format!("Duplicate key: \"{}\"", key),
+                        at.offset,
+                        at.line,
+                        at.column,
+                    )
+                    .with_kind(ErrorKind::DuplicateKey));
+                }
+                Ok(None)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_json;
+    use crate::parser::Parser;
+
+    /// Feed `input` in chunks of `size` bytes, collecting every value
+    fn push_all(
+        input: &[u8],
+        size: usize,
+        options: ParserOptions,
+    ) -> Result<Vec<JsonValue>, ParseError> {
+        let mut parser = PushParser::with_options(options);
+        let mut values = Vec::new();
+        for chunk in input.chunks(size) {
+            let mut status = parser.feed(chunk)?;
+            while let PushStatus::Value(value) = status {
+                values.push(value);
+                status = parser.poll()?;
+            }
+        }
+        values.extend(parser.finish()?);
+        Ok(values)
+    }
+
+    #[test]
+    fn test_any_chunking_matches_parse_json() {
+        let input =
+            r#"{"name": "Zoë 🦀", "esc": "a\"b\\", "n": [-1.5e3, 0, true, null, {}], "e": []}"#;
+        let expected = parse_json(input).unwrap();
+        for size in 1..input.len() {
+            let values = push_all(input.as_bytes(), size, ParserOptions::default()).unwrap();
+            assert_eq!(
+                values,
+                std::slice::from_ref(&expected),
+                "chunk size {}",
+                size
+            );
+        }
+    }
+
+    #[test]
+    fn test_value_sequences_and_relaxations() {
+        let input = b"1 2\n[1,] // done\n{a: 'x', /* c */ b: 2,}";
+        let values = push_all(input, 3, ParserOptions::json5()).unwrap();
+        assert_eq!(values.len(), 4);
+        assert_eq!(values[2], parse_json("[1]").unwrap());
+        assert_eq!(values[3], parse_json(r#"{"a": "x", "b": 2}"#).unwrap());
+
+        let values = push_all("\u{feff}1 2".as_bytes(), 1, ParserOptions::default()).unwrap();
+        assert_eq!(values.len(), 2);
+    }
+
+    #[test]
+    fn test_errors_match_parser() {
+        let cases = [
+            "[1 2]",
+            "{\"a\" 1}",
+            "{\"a\": 1,}",
+            "[1,]",
+            "{1: 2}",
+            "[\"abc",
+            "{\"a\": [",
+            "[tru]",
+            "]",
+        ];
+        for input in cases {
+            let expected = parse_json(input).unwrap_err();
+            for size in [1, 2, input.len()] {
+                let error = push_all(input.as_bytes(), size, ParserOptions::default()).unwrap_err();
+                assert_eq!(
+                    (error.kind(), error.position(), error.line(), error.column()),
+                    (
+                        expected.kind(),
+                        expected.position(),
+                        expected.line(),
+                        expected.column()
+                    ),
+                    "{} in chunks of {}",
+                    input,
+                    size
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_limits_and_failed_state() {
+        let options = ParserOptions::default()
+            .max_depth(Some(2))
+            .duplicate_keys(DuplicateKeyPolicy::Error);
+        let error = push_all(b"[[[1]]]", 2, options).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::DepthLimitExceeded);
+        let error = push_all(b"{\"a\": 1, \"a\": 2}", 4, options).unwrap_err();
+        assert_eq!(
+            (error.kind(), error.position()),
+            (ErrorKind::DuplicateKey, 9)
+        );
+
+        let options = ParserOptions::default().max_elements(Some(2));
+        assert!(push_all(b"[1, 2] {\"a\": [3, 4]}", 3, options).is_ok());
+        for input in [&b"[1, 2, 3]"[..], b"{\"a\": 1, \"b\": 2, \"c\": 3}"] {
+            let error = push_all(input, 3, options).unwrap_err();
+            let expected = Parser::with_options(std::str::from_utf8(input).unwrap(), options)
+                .and_then(|mut parser| parser.parse())
+                .unwrap_err();
+            assert_eq!(error, expected);
+        }
+
+        let mut parser = PushParser::new();
+        assert!(parser.feed(&[b'"', 0xff]).is_err());
+        assert_eq!(parser.poll().unwrap_err().kind(), ErrorKind::InvalidUtf8);
+    }
+}
@@ -0,0 +1,433 @@
+//! Incremental input - lexing and parsing from an `io::BufRead`
+//!
+//! `Lexer` works on a `&str` holding the whole document. `ReaderLexer`
+//! instead pulls bytes from a reader as tokens are requested, validating
+//! UTF-8 on the way, and only keeps the text of the token being read in
+//! memory. Each complete token is handed to a `Lexer`, so tokens, limits and
+//! error messages are exactly those of in-memory parsing.
+
+use std::io::{self, BufRead};
+
+use crate::encoding::DecodingReader;
+use crate::error::{ErrorKind, ParseError};
+use crate::json::JsonValue;
+use crate::lexer::{BYTE_ORDER_MARK, Lexer, Token};
+use crate::options::ParserOptions;
+use crate::parser::{Parser, Tokens};
+
+/// Lexer reading its input incrementally from a buffered reader
+///
+/// Positions in tokens and errors are byte offsets into the whole stream,
+/// with line and column computed as for in-memory input.
+pub struct ReaderLexer<R> {
+    reader: R,
+    options: ParserOptions,
+    /// Decoded text; everything before `start` has been consumed
+    window: String,
+    start: usize,
+    /// Byte offset, line and column of `window[start]` in the whole input
+    offset: usize,
+    line: usize,
+    column: usize,
+    /// Bytes of a UTF-8 sequence split across two reads
+    incomplete: Vec<u8>,
+    bytes_read: usize,
+    eof: bool,
+    /// Byte offset, line and column where the most recently read token starts
+    pub(crate) token_start: usize,
+    pub(crate) token_line: usize,
+    pub(crate) token_column: usize,
+}
+
+impl<'a> Lexer<'a> {
+    /// Lexer pulling its input from `reader` instead of a string in memory
+    pub fn from_reader<R: BufRead>(reader: R) -> ReaderLexer<R> {
+        ReaderLexer::new(reader)
+    }
+}
+
+impl<R: BufRead> ReaderLexer<R> {
+    pub fn new(reader: R) -> Self {
+        Self::with_options(reader, &ParserOptions::default())
+    }
+
+    /// Lexer honoring the token-level settings of `options`, and
+    /// `max_input_size` for the number of bytes read
+    pub fn with_options(reader: R, options: &ParserOptions) -> Self {
+        Self {
+            reader,
+            options: *options,
+            window: String::new(),
+            start: 0,
+            offset: 0,
+            line: 1,
+            column: 1,
+            incomplete: Vec::new(),
+            bytes_read: 0,
+            eof: false,
+            token_start: 0,
+            token_line: 1,
+            token_column: 1,
+        }
+    }
+
+    /// Read the next token, pulling more input as needed
+    pub fn next_token(&mut self) -> Result<Token, ParseError> {
+        self.skip_whitespace()?;
+        self.token_start = self.offset;
+        self.token_line = self.line;
+        self.token_column = self.column;
+
+        let Some(first) = self.peek(0)? else {
+            return Ok(Token::Eof);
+        };
+        let end = match first {
+            '"' => self.string_end('"')?,
+            '\'' if self.options.allow_single_quotes => self.string_end('\'')?,
+            '{' | '}' | '[' | ']' | ',' | ':' => self.start + 1,
+            // A malformed comment; `Lexer` reports it from the rest of
+            // the input, which `skip_whitespace` has already read
+            '/' if self.options.allow_comments => self.window.len(),
+            _ => self.word_end()?,
+        };
+
+        let text = &self.window[self.start..end];
+        let mut lexer = Lexer::fragment(text, &self.options);
+        match lexer.next_token() {
+            Ok(token) => {
+                let consumed = lexer.offset();
+                self.consume(consumed);
+                Ok(token)
+            }
+            Err(error) => Err(error.relocate_to(self.offset, self.line, self.column)),
+        }
+    }
+
+    /// Skip whitespace and, if allowed, complete comments; anything else
+    /// (including malformed comments) is left for `Lexer` to report
+    fn skip_whitespace(&mut self) -> Result<(), ParseError> {
+        while let Some(ch) = self.peek(0)? {
+            if ch.is_whitespace() || (ch == BYTE_ORDER_MARK && self.offset == 0) {
+                self.consume(ch.len_utf8());
+            } else if ch == '/' && self.options.allow_comments {
+                let end = match self.peek(1)? {
+                    Some('/') => self.find_from(self.start + 2, "\n")?,
+                    Some('*') => match self.find_from(self.start + 2, "*/")? {
+                        Some(i) => Some(i + 2),
+                        None => return Ok(()),
+                    },
+                    _ => return Ok(()),
+                };
+                let end = end.unwrap_or(self.window.len());
+                self.consume(end - self.start);
+            } else {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// End of the string opened at `start`: just past the closing quote, or
+    /// the end of the input if there is none
+    fn string_end(&mut self, quote: char) -> Result<usize, ParseError> {
+        let mut i = self.start + 1;
+        loop {
+            while let Some(&byte) = self.window.as_bytes().get(i) {
+                match byte {
+                    b'\\' => i += 2,
+                    _ if byte == quote as u8 => return Ok(i + 1),
+                    _ => i += 1,
+                }
+            }
+            if !self.fill()? {
+                return Ok(self.window.len());
+            }
+        }
+    }
+
+    /// End of a number, keyword or stray character starting at `start`
+    fn word_end(&mut self) -> Result<usize, ParseError> {
+        let mut i = self.start;
+        loop {
+            while let Some(ch) = self.window[i..].chars().next() {
+                if ch.is_whitespace() || "{}[],:\"'/".contains(ch) {
+                    // At least one character, so stray ones get reported
+                    return Ok(if i == self.start {
+                        i + ch.len_utf8()
+                    } else {
+                        i
+                    });
+                }
+                i += ch.len_utf8();
+            }
+            if !self.fill()? {
+                return Ok(i);
+            }
+        }
+    }
+
+    /// Index of the first `needle` at or after `from`, reading as needed
+    fn find_from(&mut self, from: usize, needle: &str) -> Result<Option<usize>, ParseError> {
+        loop {
+            if let Some(i) = self.window.get(from..).and_then(|rest| rest.find(needle)) {
+                return Ok(Some(from + i));
+            }
+            if !self.fill()? {
+                return Ok(None);
+            }
+        }
+    }
+
+    /// The character `n` characters after `start`, reading as needed
+    fn peek(&mut self, n: usize) -> Result<Option<char>, ParseError> {
+        loop {
+            if let Some(ch) = self.window[self.start..].chars().nth(n) {
+                return Ok(Some(ch));
+            }
+            if !self.fill()? {
+                return Ok(None);
+            }
+        }
+    }
+
+    /// Mark `len` bytes after `start` as consumed
+    fn consume(&mut self, len: usize) {
+        let (line, column) = self.locate(self.start + len);
+        self.line = line;
+        self.column = column;
+        self.offset += len;
+        self.start += len;
+        // Drop consumed text once it makes up half the window, which keeps
+        // the copying linear overall
+        if self.start > self.window.len() / 2 {
+            self.window.drain(..self.start);
+            self.start = 0;
+        }
+    }
+
+    /// Line and column of `window[index]`, for `index >= start`
+    fn locate(&self, index: usize) -> (usize, usize) {
+        let text = &self.window[self.start..index];
+        match text.rfind('\n') {
+            Some(i) => (self.line + text.matches('\n').count(), text.len() - i),
+            None => (self.line, self.column + text.len()),
+        }
+    }
+
+    /// Append the next chunk of the reader to the window; `false` once the
+    /// input is exhausted
+    fn fill(&mut self) -> Result<bool, ParseError> {
+        if self.eof {
+            return Ok(false);
+        }
+
+        let chunk = loop {
+            match self.reader.fill_buf() {
+                Ok(chunk) => break chunk,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => {
+                    return Err(self
+                        .error_at_end(format!("Error reading input: {}", e))
+                        .with_kind(ErrorKind::Io));
+                }
+            }
+        };
+        if chunk.is_empty() {
+            self.eof = true;
+            if !self.incomplete.is_empty() {
+                return Err(self.invalid_utf8());
+            }
+            return Ok(false);
+        }
+
+        let len = chunk.len();
+        self.incomplete.extend_from_slice(chunk);
+        self.reader.consume(len);
+        self.bytes_read += len;
+        if let Some(max) = self.options.max_input_size
+            && self.bytes_read > max
+        {
+            return Err(ParseError::at(
+                format!("Input size exceeds the limit of {} bytes", max),
+                0,
+                1,
+                1,
+            )
+            .with_kind(ErrorKind::InputTooLarge));
+        }
+
+        let valid = match std::str::from_utf8(&self.incomplete) {
+            Ok(text) => text.len(),
+            Err(e) => {
+                let valid = e.valid_up_to();
+                // Safe to split: the prefix up to `valid_up_to` is UTF-8
+                self.window
+                    .push_str(std::str::from_utf8(&self.incomplete[..valid]).unwrap());
+                self.incomplete.drain(..valid);
+                if e.error_len().is_some() {
+                    return Err(self.invalid_utf8());
+                }
+                // A sequence cut off by the end of the chunk: keep it for
+                // the next read
+                return Ok(true);
+            }
+        };
+        self.window
+            .push_str(std::str::from_utf8(&self.incomplete[..valid]).unwrap());
+        self.incomplete.clear();
+        Ok(true)
+    }
+
+    fn invalid_utf8(&self) -> ParseError {
+        self.error_at_end("Invalid UTF-8 in input")
+            .with_kind(ErrorKind::InvalidUtf8)
+    }
+
+    /// Error located just after the text decoded so far
+    fn error_at_end(&self, message: impl Into<String>) -> ParseError {
+        let (line, column) = self.locate(self.window.len());
+        let position = self.offset + self.window.len() - self.start;
+        ParseError::at(message, position, line, column)
+    }
+}
+
+impl<'a> Parser<'a> {
+    /// Parser reading its input incrementally from `reader`
+    ///
+    /// The reader must be `Send` so the parser stays `Send`; wrap locked
+    /// handles such as `StdinLock` in a `BufReader` over the unlocked handle
+    /// instead.
+    pub fn from_reader<R: BufRead + Send + 'a>(reader: R) -> Result<Self, ParseError> {
+        Self::from_reader_with_options(reader, ParserOptions::default())
+    }
+
+    /// Reader-backed parser with explicit limits and relaxations;
+    /// `max_input_size` limits the number of bytes read
+    pub fn from_reader_with_options<R: BufRead + Send + 'a>(
+        reader: R,
+        options: ParserOptions,
+    ) -> Result<Self, ParseError> {
+        let reader: Box<dyn BufRead + Send + 'a> = if options.detect_encoding {
+            Box::new(DecodingReader::new(reader))
+        } else {
+            Box::new(reader)
+        };
+        let lexer = ReaderLexer::with_options(reader, &options);
+        Parser::with_tokens(Tokens::Reader(Box::new(lexer)), options)
+    }
+}
+
+/// Parse JSON from a reader without reading it into memory first
+///
+/// Only the tree being built and the token being read are held in memory.
+///
+/// # Examples
+///
+/// ```
+/// use std::io::BufReader;
+/// use json_parser_rs::parse_json_reader;
+///
+/// let file = BufReader::new(&br#"{"ids": [1, 2, 3]}"#[..]);
+/// let value = parse_json_reader(file).unwrap();
+/// assert_eq!(value["ids"][2].as_u64(), Some(3));
+/// ```
+pub fn parse_json_reader<R: BufRead + Send>(reader: R) -> Result<JsonValue, ParseError> {
+    Parser::from_reader(reader)?.parse()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_json;
+
+    /// Reader handing out at most `chunk` bytes per read, to split tokens
+    /// and UTF-8 sequences across reads
+    struct Chunked<'a> {
+        data: &'a [u8],
+        chunk: usize,
+    }
+
+    impl io::Read for Chunked<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let n = self.chunk.min(buf.len()).min(self.data.len());
+            buf[..n].copy_from_slice(&self.data[..n]);
+            self.data = &self.data[n..];
+            Ok(n)
+        }
+    }
+
+    fn parse_chunked(input: &str, chunk: usize) -> Result<JsonValue, ParseError> {
+        let reader = io::BufReader::with_capacity(
+            chunk,
+            Chunked {
+                data: input.as_bytes(),
+                chunk,
+            },
+        );
+        parse_json_reader(reader)
+    }
+
+    #[test]
+    fn test_matches_in_memory_parsing() {
+        let input = "{\"name\": \"Jürgen \\u00e9\", \"tags\": [true, false, null],\n \"n\": -12.5e3, \"e\": {}}";
+        for chunk in [1, 2, 3, 7, 64] {
+            assert_eq!(
+                parse_chunked(input, chunk).unwrap(),
+                parse_json(input).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn test_errors_match_in_memory_parsing() {
+        for input in [
+            "[1, 2,]",
+            "{\"a\":\n  tru}",
+            "\n  nul",
+            "\"open",
+            "[\"a\\q\"]",
+            "[1 2]",
+            "-",
+        ] {
+            let expected = parse_json(input).unwrap_err();
+            for chunk in [1, 4] {
+                let actual = parse_chunked(input, chunk).unwrap_err();
+                assert_eq!(actual, expected, "{:?} in chunks of {}", input, chunk);
+            }
+        }
+    }
+
+    #[test]
+    fn test_comments_and_options() {
+        let input = "{ // port\n  port: 8080, /* host */ host: 'x', }";
+        let options = ParserOptions::json5();
+        let reader = io::BufReader::with_capacity(2, input.as_bytes());
+        let value = Parser::from_reader_with_options(reader, options)
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert_eq!(value, crate::parse_json5(input).unwrap());
+
+        let limited = ParserOptions::default().max_input_size(Some(4));
+        let error = Parser::from_reader_with_options(&b"[1, 2, 3]"[..], limited)
+            .and_then(|mut parser| parser.parse())
+            .unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InputTooLarge);
+    }
+
+    #[test]
+    fn test_invalid_utf8() {
+        let error = parse_json_reader(&b"[\"a\xff\"]"[..]).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidUtf8);
+        assert_eq!(error.position(), 3);
+
+        // A multi-byte sequence cut off by the end of the input
+        let error = parse_json_reader(&b"\"\xc3"[..]).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidUtf8);
+
+        // A UTF-8 byte order mark is skipped, even split across reads
+        for chunk in [1, 2, 64] {
+            assert_eq!(parse_chunked("\u{feff}[\"x\"]", chunk).unwrap()[0].as_str(), Some("x"));
+        }
+    }
+}
@@ -0,0 +1,284 @@
+//! Reference - resolve local `$ref`s
+//!
+//! JSON Schema, OpenAPI and AsyncAPI documents share definitions through
+//! objects of the form `{"$ref": "#/components/schemas/Pet"}`, whose value is
+//! a URI fragment holding a JSON Pointer into the same document.
+//! [`resolve`] looks one such reference up and [`dereference`] replaces all
+//! of them with copies of their targets. References to other documents are
+//! not fetched.
+
+use std::fmt;
+
+use crate::json::JsonValue;
+use crate::object::JsonObject;
+use crate::pointer::JsonPointer;
+
+/// A `$ref` that cannot be resolved
+#[derive(Debug, Clone, PartialEq)]
+pub struct RefError {
+    /// Location of the object holding the `$ref`
+    pub path: JsonPointer,
+    pub message: String,
+}
+
+/// `/path: message`, with `(root)` for the document itself
+impl fmt::Display for RefError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let path = self.path.to_string();
+        let path = if path.is_empty() { "(root)" } else { &path };
+        write!(f, "{}: {}", path, self.message)
+    }
+}
+
+impl std::error::Error for RefError {}
+
+/// The value a local reference such as `#/$defs/name` points to
+///
+/// The fragment may be percent-encoded (`#/paths/~1pets~1%7Bid%7D`).
+/// Returns `None` for references to other documents and for pointers that
+/// do not resolve.
+///
+/// # Examples
+///
+/// ```
+/// use json_parser_rs::{json, reference};
+///
+/// let document = json!({"$defs": {"id": {"type": "integer"}}});
+/// assert_eq!(
+///     reference::resolve(&document, "#/$defs/id"),
+///     Some(&json!({"type": "integer"}))
+/// );
+/// assert_eq!(reference::resolve(&document, "other.json#/$defs/id"), None);
+/// ```
+pub fn resolve<'a>(document: &'a JsonValue, reference: &str) -> Option<&'a JsonValue> {
+    document.pointer(&percent_decode(reference.strip_prefix('#')?)?)
+}
+
+/// Copy of `document` with every local `$ref` object replaced by its target
+///
+/// Targets are dereferenced in turn. A reference back to a definition that
+/// is being expanded (a recursive schema) is left in place, so the result
+/// stays finite. Members next to `$ref` are kept, overriding members of an
+/// object target with the same name, as OpenAPI 3.1 allows for `summary`
+/// and `description`.
+///
+/// # Examples
+///
+/// ```
+/// use json_parser_rs::{json, reference};
+///
+/// let document = json!({
+///     "tree": {"$ref": "#/$defs/node"},
+///     "$defs": {"node": {"properties": {"children": {"items": {"$ref": "#/$defs/node"}}}}}
+/// });
+/// let resolved = reference::dereference(&document).unwrap();
+/// assert_eq!(
+///     resolved["tree"],
+///     json!({"properties": {"children": {"items": {"$ref": "#/$defs/node"}}}})
+/// );
+///
+/// let broken = json!({"a": [{"$ref": "#/missing"}]});
+/// assert_eq!(
+///     reference::dereference(&broken).unwrap_err().to_string(),
+///     "/a/0: Cannot resolve $ref \"#/missing\""
+/// );
+/// ```
+pub fn dereference(document: &JsonValue) -> Result<JsonValue, RefError> {
+    let lookup = |_: &str, reference: &str| {
+        if !reference.starts_with('#') {
+            return Err(format!("External $ref \"{}\" is not supported", reference));
+        }
+        resolve(document, reference)
+            .map(|target| ("", target))
+            .ok_or_else(|| format!("Cannot resolve $ref \"{}\"", reference))
+    };
+    dereference_with(document, "", &lookup).map_err(|(_, error)| error)
+}
+
+/// Finds the target of a `$ref` found in the named document: the name of
+/// the document holding the target and the target itself, or the message
+/// for a reference that cannot be followed
+pub(crate) type Lookup<'a> =
+    dyn Fn(&'a str, &'a str) -> Result<(&'a str, &'a JsonValue), String> + 'a;
+
+/// [`dereference`] for references that may point into other documents;
+/// errors come with the name of the document holding the bad `$ref`
+pub(crate) fn dereference_with<'a>(
+    document: &'a JsonValue,
+    name: &'a str,
+    lookup: &'a Lookup<'a>,
+) -> Result<JsonValue, (&'a str, RefError)> {
+    let mut dereferencer = Dereferencer {
+        lookup,
+        document: name,
+        path: Vec::new(),
+        expanding: Vec::new(),
+    };
+    dereferencer
+        .value(document)
+        .map_err(|error| (dereferencer.document, error))
+}
+
+struct Dereferencer<'a> {
+    lookup: &'a Lookup<'a>,
+    /// Name of the document being walked
+    document: &'a str,
+    /// Location in that document, for error messages
+    path: Vec<String>,
+    /// Targets whose expansion is in progress
+    expanding: Vec<*const JsonValue>,
+}
+
+impl<'a> Dereferencer<'a> {
+    fn error(&self, message: String) -> RefError {
+        RefError {
+            path: JsonPointer::from_tokens(&self.path),
+            message,
+        }
+    }
+
+    fn value(&mut self, value: &'a JsonValue) -> Result<JsonValue, RefError> {
+        match value.untagged() {
+            JsonValue::Object(object) => match object.get("$ref").and_then(JsonValue::as_str) {
+                Some(reference) => self.reference(object, reference),
+                None => {
+                    let mut copy = JsonObject::with_capacity(object.len());
+                    for (key, member) in object {
+                        self.path.push(key.clone());
+                        copy.insert(key.clone(), self.value(member)?);
+                        self.path.pop();
+                    }
+                    Ok(JsonValue::Object(copy))
+                }
+            },
+            JsonValue::Array(elements) => {
+                let mut copy = Vec::with_capacity(elements.len());
+                for (index, element) in elements.iter().enumerate() {
+                    self.path.push(index.to_string());
+                    copy.push(self.value(element)?);
+                    self.path.pop();
+                }
+                Ok(JsonValue::Array(copy))
+            }
+            _ => Ok(value.clone()),
+        }
+    }
+
+    fn reference(
+        &mut self,
+        object: &'a JsonObject,
+        reference: &'a str,
+    ) -> Result<JsonValue, RefError> {
+        let (document, target) =
+            (self.lookup)(self.document, reference).map_err(|message| self.error(message))?;
+        if self.expanding.contains(&(target as *const JsonValue)) {
+            return Ok(JsonValue::Object(object.clone()));
+        }
+
+        let tokens = reference
+            .split_once('#')
+            .and_then(|(_, fragment)| percent_decode(fragment))
+            .and_then(|pointer| JsonPointer::parse(&pointer).ok())
+            .map(|pointer| pointer.tokens().to_vec())
+            .unwrap_or_default();
+        let source = std::mem::replace(&mut self.path, tokens);
+        let source_document = std::mem::replace(&mut self.document, document);
+        self.expanding.push(target);
+        let resolved = self.value(target);
+        self.expanding.pop();
+        // On failure `document` stays the one holding the bad `$ref`
+        let mut resolved = resolved?;
+        self.path = source;
+        self.document = source_document;
+
+        if let JsonValue::Object(target) = &mut resolved {
+            for (key, member) in object.iter().filter(|(key, _)| *key != "$ref") {
+                self.path.push(key.clone());
+                target.insert(key.clone(), self.value(member)?);
+                self.path.pop();
+            }
+        }
+        Ok(resolved)
+    }
+}
+
+/// Decode `%XX` escapes; `None` if they are malformed or not UTF-8
+fn percent_decode(s: &str) -> Option<String> {
+    if !s.contains('%') {
+        return Some(s.to_string());
+    }
+    let mut bytes = Vec::with_capacity(s.len());
+    let mut rest = s.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        if byte == b'%' {
+            let hex = std::str::from_utf8(tail.get(..2)?).ok()?;
+            bytes.push(u8::from_str_radix(hex, 16).ok()?);
+            rest = &tail[2..];
+        } else {
+            bytes.push(byte);
+            rest = tail;
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json;
+
+    #[test]
+    fn test_resolve_decodes_fragment() {
+        let document = json!({"paths": {"/pets/{id}": {"get": {}}}, "a b": 1});
+        assert_eq!(
+            resolve(&document, "#/paths/~1pets~1%7Bid%7D/get"),
+            Some(&json!({}))
+        );
+        assert_eq!(resolve(&document, "#/a%20b"), Some(&json!(1)));
+        assert_eq!(resolve(&document, "#"), Some(&document));
+        assert_eq!(resolve(&document, "#/a%2"), None);
+        assert_eq!(resolve(&document, "/a b"), None);
+    }
+
+    #[test]
+    fn test_dereference_siblings_and_cycles() {
+        let document = json!({
+            "pet": {"$ref": "#/defs/pet", "description": "The pet"},
+            "defs": {
+                "pet": {"description": "A pet", "properties": {"owner": {"$ref": "#/defs/person"}}},
+                "person": {"properties": {"pets": {"items": {"$ref": "#/defs/pet"}}}}
+            }
+        });
+        let resolved = dereference(&document).unwrap();
+        assert_eq!(
+            resolved["pet"],
+            json!({
+                "description": "The pet",
+                "properties": {"owner": {"properties": {"pets": {"items": {"$ref": "#/defs/pet"}}}}}
+            })
+        );
+        // Outside a `$ref` the definition itself is not on the expansion stack
+        let pets = &resolved["defs"]["person"]["properties"]["pets"];
+        assert_eq!(
+            pets["items"]["properties"]["owner"]["properties"]["pets"]["items"],
+            json!({"$ref": "#/defs/pet"})
+        );
+    }
+
+    #[test]
+    fn test_dereference_errors() {
+        let cases = [
+            (
+                json!({"x": {"$ref": "other.json#/a"}}),
+                "/x: External $ref \"other.json#/a\" is not supported",
+            ),
+            (
+                json!({"x": {"$ref": "#/defs/a"}, "defs": {"a": {"b": {"$ref": "#/nope"}}}}),
+                "/defs/a/b: Cannot resolve $ref \"#/nope\"",
+            ),
+        ];
+        for (document, message) in cases {
+            assert_eq!(dereference(&document).unwrap_err().to_string(), message);
+        }
+    }
+}
@@ -0,0 +1,618 @@
+//! Schema - validate documents against a JSON Schema
+//!
+//! Covers the commonly used part of JSON Schema (draft 2020-12, with the
+//! draft-07 spellings `definitions` and array-valued `items` accepted too):
+//! `type`, `enum`, `const`, numeric and length bounds, `required`,
+//! `properties`, `additionalProperties`, `items`/`prefixItems`, `contains`,
+//! `uniqueItems`, the `allOf`/`anyOf`/`oneOf`/`not` and `if`/`then`/`else`
+//! combinators and local `$ref`s. Keywords needing a regular expression
+//! engine (`pattern`, `patternProperties`) and `format` are not checked;
+//! other unknown keywords are ignored, as the specification requires.
+
+use std::fmt;
+use std::mem;
+
+use crate::error::ParseError;
+use crate::json::{JsonValue, type_name};
+use crate::object::JsonObject;
+use crate::pointer::JsonPointer;
+use crate::reference;
+
+/// Deepest chain of `$ref`s followed before giving up, which also stops
+/// reference cycles that never reach the instance
+const MAX_REF_DEPTH: usize = 64;
+
+/// A JSON Schema document ready to validate instances
+///
+/// # Examples
+///
+/// ```
+/// use json_parser_rs::schema::Schema;
+/// use json_parser_rs::parse_json;
+///
+/// let schema = Schema::parse(r#"{
+///     "type": "object",
+///     "required": ["name"],
+///     "properties": {"name": {"type": "string"}, "age": {"minimum": 0}}
+/// }"#).unwrap();
+///
+/// assert!(schema.validate(&parse_json(r#"{"name": "Ada", "age": 36}"#).unwrap()).is_ok());
+/// let violations = schema.validate(&parse_json(r#"{"age": -1}"#).unwrap()).unwrap_err();
+/// let lines: Vec<String> = violations.iter().map(ToString::to_string).collect();
+/// assert_eq!(lines, [
+///     "(root): Missing required property \"name\" [required]",
+///     "/age: Value must be at least 0 [minimum]",
+/// ]);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Schema {
+    root: JsonValue,
+}
+
+/// One way in which an instance fails its schema
+#[derive(Debug, Clone, PartialEq)]
+pub struct SchemaViolation {
+    /// Location of the offending value in the instance
+    pub instance_path: JsonPointer,
+    /// Location of the failed keyword in the schema, following `$ref`s
+    pub schema_path: JsonPointer,
+    /// The failed keyword (rule), e.g. `required` or `type`
+    pub keyword: String,
+    pub message: String,
+}
+
+/// `/path: message [keyword]`, with `(root)` for the document itself
+impl fmt::Display for SchemaViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let path = self.instance_path.to_string();
+        let path = if path.is_empty() { "(root)" } else { &path };
+        write!(f, "{}: {} [{}]", path, self.message, self.keyword)
+    }
+}
+
+impl Schema {
+    /// Wrap a schema document; an object or a boolean
+    pub fn new(schema: JsonValue) -> Self {
+        Self { root: schema }
+    }
+
+    /// Parse schema text
+    pub fn parse(input: &str) -> Result<Self, ParseError> {
+        crate::parse_json(input).map(Self::new)
+    }
+
+    /// The schema document
+    pub fn as_value(&self) -> &JsonValue {
+        &self.root
+    }
+
+    /// Check `instance`, collecting every violation in document order
+    pub fn validate(&self, instance: &JsonValue) -> Result<(), Vec<SchemaViolation>> {
+        self.validate_at(&JsonPointer::default(), instance)
+    }
+
+    /// Check `instance` against the subschema at `pointer`, such as
+    /// `/$defs/address`
+    ///
+    /// `$ref`s inside the subschema resolve against the whole document, and
+    /// violations carry schema paths from the document root. This lets a
+    /// document that embeds schemas, like an OpenAPI specification, serve as
+    /// the schema.
+    pub fn validate_at(
+        &self,
+        pointer: &JsonPointer,
+        instance: &JsonValue,
+    ) -> Result<(), Vec<SchemaViolation>> {
+        let mut validator = Validator {
+            root: &self.root,
+            schema_path: pointer.tokens().to_vec(),
+            instance_path: Vec::new(),
+            violations: Vec::new(),
+            ref_depth: 0,
+        };
+        match self.root.pointer(&pointer.to_string()) {
+            Some(schema) => validator.check(schema, instance),
+            None => validator.report("$ref", format!("No schema at \"{}\"", pointer)),
+        }
+        if validator.violations.is_empty() {
+            Ok(())
+        } else {
+            Err(validator.violations)
+        }
+    }
+
+    pub fn is_valid(&self, instance: &JsonValue) -> bool {
+        self.validate(instance).is_ok()
+    }
+}
+
+struct Validator<'s> {
+    root: &'s JsonValue,
+    schema_path: Vec<String>,
+    instance_path: Vec<String>,
+    violations: Vec<SchemaViolation>,
+    ref_depth: usize,
+}
+
+impl<'s> Validator<'s> {
+    fn report(&mut self, keyword: &str, message: String) {
+        let mut schema_path = self.schema_path.clone();
+        schema_path.push(keyword.to_string());
+        self.violations.push(SchemaViolation {
+            instance_path: JsonPointer::from_tokens(&self.instance_path),
+            schema_path: JsonPointer::from_tokens(schema_path),
+            keyword: keyword.to_string(),
+            message,
+        });
+    }
+
+    /// Check `instance` against the subschema reached by `schema_tokens`
+    /// (relative to the current schema location), below `instance_token`
+    fn descend(
+        &mut self,
+        schema_tokens: &[&str],
+        schema: &'s JsonValue,
+        instance_token: Option<String>,
+        instance: &JsonValue,
+    ) {
+        let schema_len = self.schema_path.len();
+        let instance_len = self.instance_path.len();
+        self.schema_path
+            .extend(schema_tokens.iter().map(|token| token.to_string()));
+        self.instance_path.extend(instance_token);
+        self.check(schema, instance);
+        self.schema_path.truncate(schema_len);
+        self.instance_path.truncate(instance_len);
+    }
+
+    /// Whether `instance` satisfies `schema`, without reporting anything
+    fn passes(
+        &mut self,
+        schema_tokens: &[&str],
+        schema: &'s JsonValue,
+        instance: &JsonValue,
+    ) -> bool {
+        let reported = mem::take(&mut self.violations);
+        self.descend(schema_tokens, schema, None, instance);
+        let passed = self.violations.is_empty();
+        self.violations = reported;
+        passed
+    }
+
+    fn check(&mut self, schema: &'s JsonValue, instance: &JsonValue) {
+        let keywords = match schema {
+            JsonValue::Boolean(true) => return,
+            JsonValue::Boolean(false) => {
+                self.report("false", "No value is allowed here".to_string());
+                return;
+            }
+            JsonValue::Object(keywords) => keywords,
+            _ => {
+                self.report(
+                    "schema",
+                    "Schema must be an object or a boolean".to_string(),
+                );
+                return;
+            }
+        };
+
+        if let Some(reference) = keywords.get("$ref").and_then(JsonValue::as_str) {
+            self.check_ref(reference, instance);
+        }
+        self.check_type(keywords.get("type"), instance);
+        if let Some(allowed) = keywords.get("enum").and_then(JsonValue::as_array)
+            && !allowed.iter().any(|value| json_equal(value, instance))
+        {
+            self.report("enum", "Value is not one of the allowed values".to_string());
+        }
+        if let Some(expected) = keywords.get("const")
+            && !json_equal(expected, instance)
+        {
+            self.report("const", format!("Value must be {}", expected));
+        }
+
+        match instance.untagged() {
+            JsonValue::Number(n) => self.check_number(keywords, n.as_f64()),
+            JsonValue::String(s) => self.check_string(keywords, s),
+            JsonValue::Array(elements) => self.check_array(keywords, elements),
+            JsonValue::Object(_) => self.check_object(keywords, instance),
+            _ => {}
+        }
+        self.check_combinators(keywords, instance);
+    }
+
+    fn check_ref(&mut self, reference: &str, instance: &JsonValue) {
+        let Some(target) = reference::resolve(self.root, reference) else {
+            self.report("$ref", format!("Cannot resolve $ref \"{}\"", reference));
+            return;
+        };
+        if self.ref_depth == MAX_REF_DEPTH {
+            self.report("$ref", format!("$ref chain deeper than {}", MAX_REF_DEPTH));
+            return;
+        }
+        self.ref_depth += 1;
+        self.descend(&["$ref"], target, None, instance);
+        self.ref_depth -= 1;
+    }
+
+    fn check_type(&mut self, expected: Option<&JsonValue>, instance: &JsonValue) {
+        let names: Vec<&str> = match expected {
+            None => return,
+            Some(JsonValue::String(name)) => vec![name],
+            Some(JsonValue::Array(names)) => names.iter().filter_map(JsonValue::as_str).collect(),
+            Some(_) => {
+                return self.report("type", "\"type\" must be a string or an array".to_string());
+            }
+        };
+        let actual = type_name(instance);
+        let matches = |name: &&str| {
+            *name == actual
+                || (*name == "number" && actual == "integer")
+                || (*name == "integer" && instance.as_f64().is_some_and(|f| f.fract() == 0.0))
+        };
+        if !names.iter().any(matches) {
+            let expected = names.join(" or ");
+            self.report("type", format!("Expected {}, found {}", expected, actual));
+        }
+    }
+
+    fn check_number(&mut self, keywords: &JsonObject, value: f64) {
+        let bound = |name: &str| keywords.get(name).and_then(JsonValue::as_f64);
+        if let Some(min) = bound("minimum")
+            && value < min
+        {
+            self.report("minimum", format!("Value must be at least {}", min));
+        }
+        if let Some(min) = bound("exclusiveMinimum")
+            && value <= min
+        {
+            self.report(
+                "exclusiveMinimum",
+                format!("Value must be greater than {}", min),
+            );
+        }
+        if let Some(max) = bound("maximum")
+            && value > max
+        {
+            self.report("maximum", format!("Value must be at most {}", max));
+        }
+        if let Some(max) = bound("exclusiveMaximum")
+            && value >= max
+        {
+            self.report(
+                "exclusiveMaximum",
+                format!("Value must be less than {}", max),
+            );
+        }
+        if let Some(divisor) = bound("multipleOf")
+            && divisor > 0.0
+        {
+            let quotient = value / divisor;
+            if (quotient - quotient.round()).abs() > 1e-9 {
+                self.report(
+                    "multipleOf",
+                    format!("Value must be a multiple of {}", divisor),
+                );
+            }
+        }
+    }
+
+    fn check_string(&mut self, keywords: &JsonObject, value: &str) {
+        let length = value.chars().count() as u64;
+        if let Some(min) = keywords.get("minLength").and_then(JsonValue::as_u64)
+            && length < min
+        {
+            self.report(
+                "minLength",
+                format!("String is shorter than {} characters", min),
+            );
+        }
+        if let Some(max) = keywords.get("maxLength").and_then(JsonValue::as_u64)
+            && length > max
+        {
+            self.report(
+                "maxLength",
+                format!("String is longer than {} characters", max),
+            );
+        }
+    }
+
+    fn check_array(&mut self, keywords: &'s JsonObject, elements: &[JsonValue]) {
+        let len = elements.len() as u64;
+        if let Some(min) = keywords.get("minItems").and_then(JsonValue::as_u64)
+            && len < min
+        {
+            self.report("minItems", format!("Array has fewer than {} items", min));
+        }
+        if let Some(max) = keywords.get("maxItems").and_then(JsonValue::as_u64)
+            && len > max
+        {
+            self.report("maxItems", format!("Array has more than {} items", max));
+        }
+        if keywords.get("uniqueItems").and_then(JsonValue::as_bool) == Some(true) {
+            let duplicate = (0..elements.len()).find_map(|i| {
+                (i + 1..elements.len())
+                    .find(|&j| json_equal(&elements[i], &elements[j]))
+                    .map(|j| (i, j))
+            });
+            if let Some((i, j)) = duplicate {
+                self.report("uniqueItems", format!("Items {} and {} are equal", i, j));
+            }
+        }
+
+        // Tuple validation: `prefixItems`, or draft-07 `items` as an array
+        // followed by `additionalItems`
+        let (prefix_keyword, prefix, rest_keyword) = match keywords.get("items") {
+            Some(JsonValue::Array(prefix)) => ("items", &prefix[..], "additionalItems"),
+            _ => match keywords.get("prefixItems") {
+                Some(JsonValue::Array(prefix)) => ("prefixItems", &prefix[..], "items"),
+                _ => ("prefixItems", &[][..], "items"),
+            },
+        };
+        for (i, (schema, element)) in prefix.iter().zip(elements).enumerate() {
+            self.descend(
+                &[prefix_keyword, &i.to_string()],
+                schema,
+                Some(i.to_string()),
+                element,
+            );
+        }
+        if let Some(schema) = keywords.get(rest_keyword)
+            && !schema.is_array()
+        {
+            for (i, element) in elements.iter().enumerate().skip(prefix.len()) {
+                self.descend(&[rest_keyword], schema, Some(i.to_string()), element);
+            }
+        }
+
+        if let Some(schema) = keywords.get("contains")
+            && !elements
+                .iter()
+                .any(|element| self.passes(&["contains"], schema, element))
+        {
+            self.report(
+                "contains",
+                "No item matches the \"contains\" schema".to_string(),
+            );
+        }
+    }
+
+    fn check_object(&mut self, keywords: &'s JsonObject, instance: &JsonValue) {
+        let Some(members) = instance.as_object() else {
+            return;
+        };
+        if let Some(required) = keywords.get("required").and_then(JsonValue::as_array) {
+            for name in required.iter().filter_map(JsonValue::as_str) {
+                if !members.contains_key(name) {
+                    self.report(
+                        "required",
+                        format!("Missing required property \"{}\"", name),
+                    );
+                }
+            }
+        }
+        if let Some(dependencies) = keywords
+            .get("dependentRequired")
+            .and_then(JsonValue::as_object)
+        {
+            for (trigger, needed) in dependencies {
+                let Some(needed) = needed.as_array().filter(|_| members.contains_key(trigger))
+                else {
+                    continue;
+                };
+                for name in needed.iter().filter_map(JsonValue::as_str) {
+                    if !members.contains_key(name) {
+                        self.report(
+                            "dependentRequired",
+                            format!("Property \"{}\" requires \"{}\"", trigger, name),
+                        );
+                    }
+                }
+            }
+        }
+        let count = members.len() as u64;
+        if let Some(min) = keywords.get("minProperties").and_then(JsonValue::as_u64)
+            && count < min
+        {
+            self.report(
+                "minProperties",
+                format!("Object has fewer than {} properties", min),
+            );
+        }
+        if let Some(max) = keywords.get("maxProperties").and_then(JsonValue::as_u64)
+            && count > max
+        {
+            self.report(
+                "maxProperties",
+                format!("Object has more than {} properties", max),
+            );
+        }
+
+        let properties = keywords.get("properties").and_then(JsonValue::as_object);
+        // Without a regex engine we cannot tell which keys `patternProperties`
+        // covers, so `additionalProperties` is only applied without it
+        let additional = keywords
+            .get("additionalProperties")
+            .filter(|_| !keywords.contains_key("patternProperties"));
+        for (key, value) in members {
+            if let Some(schema) = properties.and_then(|properties| properties.get(key)) {
+                self.descend(&["properties", key], schema, Some(key.clone()), value);
+            } else if let Some(schema) = additional {
+                if schema.as_bool() == Some(false) {
+                    let len = self.instance_path.len();
+                    self.instance_path.push(key.clone());
+                    self.report(
+                        "additionalProperties",
+                        format!("Property \"{}\" is not allowed", key),
+                    );
+                    self.instance_path.truncate(len);
+                } else {
+                    self.descend(&["additionalProperties"], schema, Some(key.clone()), value);
+                }
+            }
+            if let Some(schema) = keywords.get("propertyNames") {
+                let name = JsonValue::String(key.clone());
+                self.descend(&["propertyNames"], schema, Some(key.clone()), &name);
+            }
+        }
+    }
+
+    fn check_combinators(&mut self, keywords: &'s JsonObject, instance: &JsonValue) {
+        if let Some(schemas) = keywords.get("allOf").and_then(JsonValue::as_array) {
+            for (i, schema) in schemas.iter().enumerate() {
+                self.descend(&["allOf", &i.to_string()], schema, None, instance);
+            }
+        }
+        if let Some(schemas) = keywords.get("anyOf").and_then(JsonValue::as_array) {
+            let any = schemas
+                .iter()
+                .enumerate()
+                .any(|(i, schema)| self.passes(&["anyOf", &i.to_string()], schema, instance));
+            if !any {
+                self.report(
+                    "anyOf",
+                    "Value matches none of the \"anyOf\" schemas".to_string(),
+                );
+            }
+        }
+        if let Some(schemas) = keywords.get("oneOf").and_then(JsonValue::as_array) {
+            let matching = schemas
+                .iter()
+                .enumerate()
+                .filter(|(i, schema)| self.passes(&["oneOf", &i.to_string()], schema, instance))
+                .count();
+            if matching != 1 {
+                self.report(
+                    "oneOf",
+                    format!(
+                        "Value matches {} of the \"oneOf\" schemas, expected exactly 1",
+                        matching
+                    ),
+                );
+            }
+        }
+        if let Some(schema) = keywords.get("not")
+            && self.passes(&["not"], schema, instance)
+        {
+            self.report("not", "Value must not match the \"not\" schema".to_string());
+        }
+        if let Some(condition) = keywords.get("if") {
+            let branch = if self.passes(&["if"], condition, instance) {
+                "then"
+            } else {
+                "else"
+            };
+            if let Some(schema) = keywords.get(branch) {
+                self.descend(&[branch], schema, None, instance);
+            }
+        }
+    }
+}
+
+/// Equality as JSON Schema defines it: numbers compare by value, so `1`
+/// equals `1.0`, and object member order does not matter
+fn json_equal(a: &JsonValue, b: &JsonValue) -> bool {
+    match (a.untagged(), b.untagged()) {
+        (JsonValue::Number(x), JsonValue::Number(y)) => {
+            x == y || (x.is_f64() || y.is_f64()) && x.as_f64() == y.as_f64()
+        }
+        (JsonValue::Array(x), JsonValue::Array(y)) => {
+            x.len() == y.len() && x.iter().zip(y).all(|(a, b)| json_equal(a, b))
+        }
+        (JsonValue::Object(x), JsonValue::Object(y)) => {
+            x.len() == y.len()
+                && x.iter()
+                    .all(|(key, a)| y.get(key).is_some_and(|b| json_equal(a, b)))
+        }
+        (a, b) => a == b,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_json;
+
+    fn violations(schema: &str, instance: &str) -> Vec<String> {
+        let schema = Schema::parse(schema).unwrap();
+        match schema.validate(&parse_json(instance).unwrap()) {
+            Ok(()) => Vec::new(),
+            Err(violations) => violations.iter().map(ToString::to_string).collect(),
+        }
+    }
+
+    #[test]
+    fn test_validate_at_subschema() {
+        let schema = Schema::parse(
+            r##"{"$defs": {"id": {"type": "integer"}, "ids": {"items": {"$ref": "#/$defs/id"}}}}"##,
+        )
+        .unwrap();
+        let pointer = JsonPointer::parse("/$defs/ids").unwrap();
+        let violations = schema.validate_at(&pointer, &parse_json("[1, \"2\"]").unwrap());
+        let violation = &violations.unwrap_err()[0];
+        assert_eq!(violation.instance_path.to_string(), "/1");
+        assert_eq!(violation.schema_path.to_string(), "/$defs/ids/items/$ref/type");
+
+        let missing = JsonPointer::parse("/nope").unwrap();
+        let violations = schema.validate_at(&missing, &JsonValue::Null).unwrap_err();
+        assert_eq!(violations[0].to_string(), "(root): No schema at \"/nope\" [$ref]");
+    }
+
+    #[test]
+    fn test_type_and_bounds() {
+        let schema = r#"{"type": "array", "maxItems": 3, "uniqueItems": true,
+            "items": {"type": ["integer", "null"], "exclusiveMaximum": 10, "multipleOf": 2}}"#;
+        assert!(violations(schema, "[2, 4.0, null]").is_empty());
+        assert_eq!(
+            violations(schema, r#"[1, "x", 10, 2, 2]"#),
+            [
+                "(root): Array has more than 3 items [maxItems]",
+                "(root): Items 3 and 4 are equal [uniqueItems]",
+                "/0: Value must be a multiple of 2 [multipleOf]",
+                "/1: Expected integer or null, found string [type]",
+                "/2: Value must be less than 10 [exclusiveMaximum]",
+            ]
+        );
+        assert_eq!(
+            violations(r#"{"enum": [1, "a"]}"#, "1.0"),
+            Vec::<String>::new()
+        );
+        assert_eq!(
+            violations("false", "null"),
+            ["(root): No value is allowed here [false]"]
+        );
+    }
+
+    #[test]
+    fn test_objects_refs_and_combinators() {
+        let schema = r##"{
+            "$defs": {"name": {"type": "string", "minLength": 1}},
+            "properties": {"name": {"$ref": "#/$defs/name"}, "tags": {"contains": {"const": "a"}}},
+            "additionalProperties": false,
+            "oneOf": [{"required": ["name"]}, {"required": ["tags"]}],
+            "if": {"required": ["tags"]}, "then": {"minProperties": 2}
+        }"##;
+        assert!(violations(schema, r#"{"name": "x"}"#).is_empty());
+        assert_eq!(
+            violations(schema, r#"{"name": "", "tags": ["b"], "x": 1}"#),
+            [
+                "/name: String is shorter than 1 characters [minLength]",
+                "/tags: No item matches the \"contains\" schema [contains]",
+                "/x: Property \"x\" is not allowed [additionalProperties]",
+                "(root): Value matches 2 of the \"oneOf\" schemas, expected exactly 1 [oneOf]",
+            ]
+        );
+        let error = Schema::parse(r##"{"$ref": "#/nowhere"}"##)
+            .unwrap()
+            .validate(&JsonValue::Null);
+        let violation = &error.unwrap_err()[0];
+        assert_eq!(violation.keyword, "$ref");
+        assert_eq!(violation.schema_path.to_string(), "/$ref");
+
+        let nested = r##"{"items": {"$ref": "#/$defs/n"}, "$defs": {"n": {"maximum": 1}}}"##;
+        let schema = Schema::parse(nested).unwrap();
+        let violation = &schema.validate(&parse_json("[0, 2]").unwrap()).unwrap_err()[0];
+        assert_eq!(violation.instance_path.to_string(), "/1");
+        assert_eq!(violation.schema_path.to_string(), "/items/$ref/maximum");
+    }
+}
@@ -0,0 +1,147 @@
+//! Stats - size and shape of a parsed document
+//!
+//! A first look at unfamiliar or unexpectedly large data: how many values
+//! of which kind it holds, how deeply it nests and which top-level members
+//! account for its size.
+
+use std::io;
+
+use crate::json::JsonValue;
+
+/// Number of values per JSON type
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TypeCounts {
+    pub null: usize,
+    pub boolean: usize,
+    pub number: usize,
+    /// Includes `RawString` values
+    pub string: usize,
+    pub array: usize,
+    pub object: usize,
+    /// `Extension` values produced by a `ValuePlugin`; their payloads are
+    /// counted separately
+    pub extension: usize,
+}
+
+/// Summary of a document, see [`JsonValue::stats`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ValueStats {
+    /// Number of values, including the document itself and all nested ones
+    pub nodes: usize,
+    /// Deepest container nesting; 0 for a scalar, 1 for a flat array
+    pub max_depth: usize,
+    pub types: TypeCounts,
+    /// Size of the minified document in bytes
+    pub size: usize,
+    /// Minified size of each member value of a top-level object, in document
+    /// order; empty for other documents
+    pub member_sizes: Vec<(String, usize)>,
+}
+
+impl JsonValue {
+    /// Count the values of this document by type and measure its nesting
+    /// and minified size
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use json_parser_rs::parse_json;
+    ///
+    /// let value = parse_json(r#"{"ids": [1, 2, 3], "name": "x"}"#).unwrap();
+    /// let stats = value.stats();
+    /// assert_eq!(stats.nodes, 6);
+    /// assert_eq!(stats.max_depth, 2);
+    /// assert_eq!(stats.types.number, 3);
+    /// assert_eq!(stats.size, r#"{"ids":[1,2,3],"name":"x"}"#.len());
+    /// assert_eq!(stats.member_sizes, [("ids".to_string(), 7), ("name".to_string(), 3)]);
+    /// ```
+    pub fn stats(&self) -> ValueStats {
+        let mut stats = ValueStats::default();
+        count(self, 0, &mut stats);
+        stats.size = minified_size(self);
+        if let JsonValue::Object(members) = self.untagged() {
+            stats.member_sizes = members
+                .iter()
+                .map(|(key, value)| (key.clone(), minified_size(value)))
+                .collect();
+        }
+        stats
+    }
+}
+
+fn count(value: &JsonValue, depth: usize, stats: &mut ValueStats) {
+    stats.nodes += 1;
+    let types = &mut stats.types;
+    match value.untagged() {
+        JsonValue::Null => types.null += 1,
+        JsonValue::Boolean(_) => types.boolean += 1,
+        JsonValue::Number(_) => types.number += 1,
+        JsonValue::String(_) | JsonValue::RawString(_) => types.string += 1,
+        JsonValue::Array(elements) => {
+            types.array += 1;
+            stats.max_depth = stats.max_depth.max(depth + 1);
+            for element in elements {
+                count(element, depth + 1, stats);
+            }
+        }
+        JsonValue::Object(members) => {
+            types.object += 1;
+            stats.max_depth = stats.max_depth.max(depth + 1);
+            for (_, member) in members {
+                count(member, depth + 1, stats);
+            }
+        }
+        JsonValue::Extension(_, payload) => {
+            types.extension += 1;
+            count(payload, depth, stats);
+        }
+        #[cfg(feature = "tagged")]
+        JsonValue::Tagged(..) => unreachable!("untagged() strips tags"),
+    }
+}
+
+/// Length of the minified serialization, without building the string
+fn minified_size(value: &JsonValue) -> usize {
+    struct Counter(usize);
+    impl io::Write for Counter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0 += buf.len();
+            Ok(buf.len())
+        }
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+    let mut counter = Counter(0);
+    value
+        .write_json_minified(&mut counter)
+        .expect("counting never fails");
+    counter.0
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parse_json;
+
+    #[test]
+    fn test_stats_counts_every_type() {
+        let value = parse_json(r#"[null, true, 1.5, "s", [], {"a": {"b": [0]}}]"#).unwrap();
+        let stats = value.stats();
+        assert_eq!(stats.nodes, 10);
+        assert_eq!(stats.max_depth, 4);
+        let types = stats.types;
+        assert_eq!(
+            (
+                types.null,
+                types.boolean,
+                types.number,
+                types.string,
+                types.array,
+                types.object
+            ),
+            (1, 1, 2, 1, 3, 2)
+        );
+        assert!(stats.member_sizes.is_empty());
+        assert_eq!(parse_json("7").unwrap().stats().max_depth, 0);
+    }
+}
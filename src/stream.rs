@@ -0,0 +1,282 @@
+//! Streaming access to the top-level container of a document
+//!
+//! Building the whole tree is convenient but needs memory proportional to the
+//! document. `ElementStream` instead hands out the elements of a top-level
+//! array (or the members of a top-level object) one at a time, so only one
+//! element needs to be in memory at once. `parse_adaptive` combines both:
+//! it builds the tree when it fits the memory budget and falls back to a
+//! stream otherwise.
+
+use std::io::BufRead;
+
+use crate::error::{ErrorKind, ParseError};
+use crate::json::JsonValue;
+use crate::lexer::Token;
+use crate::options::ParserOptions;
+use crate::parser::Parser;
+
+/// Result of [`parse_adaptive`]
+#[derive(Debug)]
+pub enum Adaptive<'a> {
+    /// The document fit the budget and was parsed completely
+    Complete(JsonValue),
+    /// The document was too large; its top-level elements are parsed lazily
+    Streaming(Box<ElementStream<'a>>),
+}
+
+/// One element of a streamed container
+#[derive(Debug, Clone, PartialEq)]
+pub struct StreamElement {
+    /// Member key when streaming an object, `None` for array elements
+    pub key: Option<String>,
+    pub value: JsonValue,
+}
+
+/// Parse `input` as a tree if it needs at most `mem_limit` bytes, otherwise
+/// return a stream over its top-level elements
+///
+/// In streaming mode every element gets the full budget on its own; an
+/// element that exceeds it is reported as an error by the stream. Documents
+/// whose root is a scalar cannot be streamed and fail with
+/// `ErrorKind::MemoryLimitExceeded`.
+///
+/// # Examples
+///
+/// ```
+/// use json_parser_rs::stream::{Adaptive, parse_adaptive};
+///
+/// let input = r#"[{"id": 1}, {"id": 2}, {"id": 3}]"#;
+/// match parse_adaptive(input, 400).unwrap() {
+///     Adaptive::Complete(_) => unreachable!("needs more than 400 bytes"),
+///     Adaptive::Streaming(stream) => {
+///         let ids: Vec<u64> = stream
+///             .map(|element| element.unwrap().value["id"].as_u64().unwrap())
+///             .collect();
+///         assert_eq!(ids, [1, 2, 3]);
+///     }
+/// }
+/// ```
+pub fn parse_adaptive(input: &str, mem_limit: usize) -> Result<Adaptive<'_>, ParseError> {
+    let options = ParserOptions::default().max_memory(Some(mem_limit));
+    match Parser::with_options(input, options)?.parse() {
+        Ok(value) => Ok(Adaptive::Complete(value)),
+        Err(error) if error.kind() == ErrorKind::MemoryLimitExceeded => {
+            ElementStream::with_options(input, options)
+                .map(|stream| Adaptive::Streaming(Box::new(stream)))
+        }
+        Err(error) => Err(error),
+    }
+}
+
+/// Iterator over the elements of a top-level array or object
+///
+/// Syntax errors end the stream after being yielded once. Duplicate keys are
+/// passed through as they appear, since detecting them would require keeping
+/// every key in memory.
+pub struct ElementStream<'a> {
+    parser: Parser<'a>,
+    is_object: bool,
+    first: bool,
+    done: bool,
+}
+
+impl<'a> ElementStream<'a> {
+    pub fn new(input: &'a str) -> Result<Self, ParseError> {
+        Self::with_options(input, ParserOptions::default())
+    }
+
+    /// Stream whose elements are parsed with `options`; `max_memory`, if
+    /// set, applies to each element separately
+    pub fn with_options(input: &'a str, options: ParserOptions) -> Result<Self, ParseError> {
+        Self::from_parser(Parser::with_options(input, options)?, options)
+    }
+
+    /// Stream over a document read incrementally from `reader`, so neither
+    /// the document nor its text has to fit into memory
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use json_parser_rs::stream::ElementStream;
+    ///
+    /// let file = std::io::BufReader::new(&b"[1, 2, 3"[..]);
+    /// let mut stream = ElementStream::from_reader(file).unwrap();
+    /// // Elements are available before the (here malformed) end is reached
+    /// assert_eq!(stream.next().unwrap().unwrap().value.as_u64(), Some(1));
+    /// assert_eq!(stream.next().unwrap().unwrap().value.as_u64(), Some(2));
+    /// ```
+    pub fn from_reader<R: BufRead + Send + 'a>(reader: R) -> Result<Self, ParseError> {
+        let options = ParserOptions::default();
+        Self::from_parser(Parser::from_reader_with_options(reader, options)?, options)
+    }
+
+    fn from_parser(mut parser: Parser<'a>, options: ParserOptions) -> Result<Self, ParseError> {
+        let is_object = match *parser.current_token() {
+            Token::LeftBracket => false,
+            Token::LeftBrace => true,
+            _ => {
+                let error = parser.error("Only arrays and objects can be streamed");
+                return Err(match options.max_memory {
+                    Some(_) => error.with_kind(ErrorKind::MemoryLimitExceeded),
+                    None => error.with_kind(ErrorKind::unexpected(&parser.current_token())),
+                });
+            }
+        };
+        parser.advance()?;
+        parser.set_depth(1);
+        Ok(Self {
+            parser,
+            is_object,
+            first: true,
+            done: false,
+        })
+    }
+
+    /// Whether the streamed container is an object (elements carry keys)
+    pub fn is_object(&self) -> bool {
+        self.is_object
+    }
+
+    fn closing_token(&self) -> Token {
+        if self.is_object {
+            Token::RightBrace
+        } else {
+            Token::RightBracket
+        }
+    }
+
+    /// Consume the closing token and make sure nothing follows it
+    fn finish(&mut self) -> Result<(), ParseError> {
+        self.parser.advance()?;
+        if *self.parser.current_token() != Token::Eof {
+            return Err(self
+                .parser
+                .error(format!(
+                    "Unexpected token after JSON value: {}",
+                    self.parser.current_token()
+                ))
+                .with_kind(ErrorKind::TrailingCharacters));
+        }
+        Ok(())
+    }
+
+    fn next_element(&mut self) -> Result<Option<StreamElement>, ParseError> {
+        let closing = self.closing_token();
+        if *self.parser.current_token() == closing {
+            self.finish()?;
+            return Ok(None);
+        }
+        if !self.first {
+            self.parser.expect_token(Token::Comma)?;
+            if *self.parser.current_token() == closing {
+                if !self.parser.options().allow_trailing_commas {
+                    return Err(self
+                        .parser
+                        .error("Trailing comma not allowed")
+                        .with_kind(ErrorKind::TrailingComma));
+                }
+                self.finish()?;
+                return Ok(None);
+            }
+        }
+        self.first = false;
+
+        self.parser.reset_memory();
+        let key = if self.is_object {
+            let key = self.parser.parse_key()?;
+            self.parser.expect_token(Token::Colon)?;
+            Some(key)
+        } else {
+            None
+        };
+        let value = self.parser.parse_value()?;
+
+        let current = self.parser.current_token();
+        if *current != Token::Comma && *current != closing {
+            let expected = if self.is_object { '}' } else { ']' };
+            return Err(self
+                .parser
+                .error(format!(
+                    "Expected ',' or '{}', found {}",
+                    expected, current
+                ))
+                .with_kind(ErrorKind::unexpected(&current)));
+        }
+        Ok(Some(StreamElement { key, value }))
+    }
+}
+
+impl std::fmt::Debug for ElementStream<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ElementStream")
+            .field("is_object", &self.is_object)
+            .field("done", &self.done)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Iterator for ElementStream<'_> {
+    type Item = Result<StreamElement, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        match self.next_element() {
+            Ok(Some(element)) => Some(Ok(element)),
+            Ok(None) => {
+                self.done = true;
+                None
+            }
+            Err(error) => {
+                self.done = true;
+                Some(Err(error))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_adaptive_switches_on_budget() {
+        let input = r#"{"a": [1, 2, 3], "b": "text"}"#;
+        assert!(matches!(
+            parse_adaptive(input, 10_000).unwrap(),
+            Adaptive::Complete(_)
+        ));
+
+        // Enough for either member on its own, but not for the whole tree
+        let budget = 5 * std::mem::size_of::<JsonValue>();
+        let Adaptive::Streaming(stream) = parse_adaptive(input, budget).unwrap() else {
+            panic!("expected a stream");
+        };
+        assert!(stream.is_object());
+        let elements: Vec<StreamElement> = stream.map(Result::unwrap).collect();
+        assert_eq!(elements.len(), 2);
+        assert_eq!(elements[0].key.as_deref(), Some("a"));
+        assert_eq!(elements[0].value.to_json_string(), "[1, 2, 3]");
+        assert_eq!(elements[1].value.as_str(), Some("text"));
+
+        let error = parse_adaptive(r#""a long string""#, 10).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::MemoryLimitExceeded);
+        assert!(parse_adaptive("[1,", 10_000).is_err());
+    }
+
+    #[test]
+    fn test_element_stream_errors() {
+        let results: Vec<_> = ElementStream::new("[1, 2,]").unwrap().collect();
+        assert_eq!(results.len(), 3);
+        assert!(results[2].is_err());
+
+        let results: Vec<_> = ElementStream::new("[1 2]").unwrap().collect();
+        assert_eq!(results.len(), 1);
+        assert!(results[0].is_err());
+
+        assert_eq!(ElementStream::new("[]").unwrap().count(), 0);
+        assert!(ElementStream::new("[] 1").unwrap().next().unwrap().is_err());
+        assert!(ElementStream::new("1").is_err());
+    }
+}
@@ -0,0 +1,105 @@
+//! Suggestions - "did you mean" candidates for mistyped keys
+//!
+//! Failed lookups through `require` and `require_path` compare the key with
+//! the keys that do exist and suggest the close ones, the way command line
+//! parsers suggest flags. Closeness is the edit distance counting
+//! insertions, deletions, substitutions and swaps of adjacent characters,
+//! ignoring case.
+//!
+//! # Examples
+//!
+//! ```
+//! use json_parser_rs::suggest::{edit_distance, suggest};
+//!
+//! assert_eq!(edit_distance("hsot", "host"), 1);
+//! assert_eq!(suggest("prot", ["host", "port", "protocol"]), ["port"]);
+//! assert!(suggest("timeout", ["host", "port"]).is_empty());
+//! ```
+
+/// At most this many suggestions are made for one key
+pub const MAX_SUGGESTIONS: usize = 3;
+
+/// Edit distance between `a` and `b` in characters (optimal string
+/// alignment: a swap of two adjacent characters counts as one edit)
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    // Three rows of the distance matrix: two rows back, previous, current
+    let mut before: Vec<usize> = vec![0; b.len() + 1];
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+    for i in 1..=a.len() {
+        current[0] = i;
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            current[j] = (previous[j] + 1)
+                .min(current[j - 1] + 1)
+                .min(previous[j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                current[j] = current[j].min(before[j - 2] + 1);
+            }
+        }
+        std::mem::swap(&mut before, &mut previous);
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous[b.len()]
+}
+
+/// Candidates close enough to `key` to be what was meant, closest first
+///
+/// A candidate qualifies if it differs from `key` by at most a third of
+/// `key`'s length in edits (at least one), ignoring case. Ties keep the
+/// order of `candidates`; at most [`MAX_SUGGESTIONS`] are returned.
+pub fn suggest<'a, I>(key: &str, candidates: I) -> Vec<&'a str>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    let key = key.to_lowercase();
+    let limit = (key.chars().count() / 3).max(1);
+    let mut close: Vec<(usize, &str)> = candidates
+        .into_iter()
+        .map(|candidate| (edit_distance(&key, &candidate.to_lowercase()), candidate))
+        .filter(|(distance, _)| *distance <= limit)
+        .collect();
+    close.sort_by_key(|(distance, _)| *distance);
+    close
+        .into_iter()
+        .take(MAX_SUGGESTIONS)
+        .map(|(_, candidate)| candidate)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_edit_distance() {
+        let cases = [
+            ("", "", 0),
+            ("", "abc", 3),
+            ("kitten", "sitting", 3),
+            ("port", "prot", 1),
+            ("ca", "abc", 3),
+            ("hostname", "hostnmae", 1),
+            ("grüße", "gruße", 1),
+        ];
+        for (a, b, distance) in cases {
+            assert_eq!(edit_distance(a, b), distance, "{} / {}", a, b);
+            assert_eq!(edit_distance(b, a), distance, "{} / {}", b, a);
+        }
+    }
+
+    #[test]
+    fn test_suggest() {
+        let keys = ["database_url", "database_user", "debug", "Port", "ports"];
+        assert_eq!(
+            suggest("database_ur", keys),
+            ["database_url", "database_user"]
+        );
+        assert_eq!(suggest("port", keys), ["Port", "ports"]);
+        assert_eq!(suggest("debgu", keys), ["debug"]);
+        assert!(suggest("x", keys).is_empty());
+        assert!(suggest("timeout", keys).is_empty());
+    }
+}
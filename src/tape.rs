@@ -0,0 +1,606 @@
+//! Tape - a structural index for lazy access
+//!
+//! Building the full tree allocates a `String` per key and string, a `Vec`
+//! per array and an object map per object, even when the caller reads two
+//! fields out of ten thousand. [`Tape::parse`] instead checks the document
+//! in one pass and records where each value lies in the input: one small
+//! entry per value and key, with containers pointing past their last
+//! entry so whole subtrees can be skipped. A [`LazyValue`] navigates the
+//! tape; strings are unescaped and numbers converted only when asked for,
+//! and [`LazyValue::to_value`] materializes just the subtree it points to.
+//!
+//! # Examples
+//!
+//! ```
+//! use json_parser_rs::tape::Tape;
+//!
+//! let input = r#"{"users": [{"name": "ada"}, {"name": "bob"}], "total": 2}"#;
+//! let tape = Tape::parse(input).unwrap();
+//! let root = tape.root();
+//! assert_eq!(root.get("total").and_then(|total| total.as_u64()), Some(2));
+//! let second = root.pointer("/users/1/name").unwrap();
+//! assert_eq!(second.as_str().as_deref(), Some("bob"));
+//! assert_eq!(root.get("users").unwrap().len(), Some(2));
+//! ```
+
+use std::borrow::Cow;
+use std::collections::HashSet;
+
+use crate::cst::Span;
+use crate::error::{ErrorKind, ParseError};
+use crate::json::{JsonValue, check_escapes, unescape_content};
+use crate::lexer::{Lexer, SliceToken, StrSlice};
+use crate::number::JsonNumber;
+use crate::object::{DuplicateKeyPolicy, JsonObject};
+use crate::options::ParserOptions;
+use crate::parser::Parser;
+use crate::pointer::{JsonPointer, parse_index};
+
+/// One value or key on the tape
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Entry {
+    Null,
+    Boolean(bool),
+    /// The literal, checked against the grammar
+    Number(Span),
+    /// The token: quoted, or a bare identifier key
+    String(Span),
+    /// `end` is the index just past the container's last entry, `span`
+    /// its text from bracket to bracket
+    Array {
+        len: usize,
+        end: usize,
+        span: Span,
+    },
+    /// Members are stored as a key entry followed by the value's entries
+    Object {
+        len: usize,
+        end: usize,
+        span: Span,
+    },
+}
+
+/// A checked document with the location of every value, see the
+/// [module documentation](self)
+#[derive(Debug, Clone)]
+pub struct Tape<'a> {
+    input: &'a str,
+    entries: Vec<Entry>,
+    duplicate_keys: DuplicateKeyPolicy,
+}
+
+impl<'a> Tape<'a> {
+    pub fn parse(input: &'a str) -> Result<Self, ParseError> {
+        Self::with_options(input, ParserOptions::default())
+    }
+
+    /// Index `input` with the relaxations and limits of `options`
+    ///
+    /// `max_memory` is not applied, since the tape holds no copies of the
+    /// document's data. Errors are the ones `Parser` reports for the same
+    /// input and options.
+    pub fn with_options(input: &'a str, options: ParserOptions) -> Result<Self, ParseError> {
+        let mut builder = Builder {
+            lexer: Lexer::with_options(input, &options),
+            entries: Vec::new(),
+            options,
+            depth: 0,
+            keys: 0,
+        };
+        let complete = options.max_input_size.is_none_or(|max| input.len() <= max)
+            && builder.document().is_some();
+        if !complete {
+            // Errors are rare, so rather than duplicating every message of
+            // the parser, let it find the error again
+            let error = Parser::with_options(input, options).and_then(|mut parser| parser.parse());
+            return Err(error.err().unwrap_or_else(|| {
+                ParseError::new("Invalid document", 0, input).with_kind(ErrorKind::Custom)
+            }));
+        }
+        Ok(Self {
+            input,
+            entries: builder.entries,
+            duplicate_keys: options.duplicate_keys,
+        })
+    }
+
+    /// The document's root value
+    pub fn root(&self) -> LazyValue<'_> {
+        LazyValue {
+            tape: self,
+            index: 0,
+        }
+    }
+
+    /// Number of entries, i.e. values plus object keys
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Index of the entry after the value starting at `index`
+    fn skip(&self, index: usize) -> usize {
+        match self.entries[index] {
+            Entry::Array { end, .. } | Entry::Object { end, .. } => end,
+            _ => index + 1,
+        }
+    }
+
+    /// The text of a string entry, unescaped
+    fn string(&self, span: Span) -> Cow<'a, str> {
+        self.unescape(span).0
+    }
+
+    /// The text of a string entry, unescaped, and whether unpaired
+    /// surrogates were replaced
+    fn unescape(&self, span: Span) -> (Cow<'a, str>, bool) {
+        let token = &self.input[span.start..span.end];
+        let quote = match token.as_bytes()[0] {
+            quote @ (b'"' | b'\'') => quote as char,
+            // Bare identifier keys have no escapes
+            _ => return (Cow::Borrowed(token), false),
+        };
+        match unescape_content(content(token), quote, true) {
+            Ok(unescaped) => unescaped,
+            Err(_) => unreachable!("escapes are checked when the tape is built"),
+        }
+    }
+}
+
+/// A value on a [`Tape`]; cheap to copy
+#[derive(Debug, Clone, Copy)]
+pub struct LazyValue<'t> {
+    tape: &'t Tape<'t>,
+    index: usize,
+}
+
+impl<'t> LazyValue<'t> {
+    fn entry(&self) -> Entry {
+        self.tape.entries[self.index]
+    }
+
+    fn at(&self, index: usize) -> LazyValue<'t> {
+        LazyValue {
+            tape: self.tape,
+            index,
+        }
+    }
+
+    /// Byte range of the value's text in the input; `None` for `null` and
+    /// booleans, whose text is always the same
+    pub fn span(&self) -> Option<Span> {
+        match self.entry() {
+            Entry::Number(span)
+            | Entry::String(span)
+            | Entry::Array { span, .. }
+            | Entry::Object { span, .. } => Some(span),
+            Entry::Null | Entry::Boolean(_) => None,
+        }
+    }
+
+    /// The value's text in the input, exactly as written
+    pub fn raw(&self) -> &'t str {
+        match (self.span(), self.entry()) {
+            (Some(span), _) => &self.tape.input[span.start..span.end],
+            (None, Entry::Boolean(true)) => "true",
+            (None, Entry::Boolean(false)) => "false",
+            (None, _) => "null",
+        }
+    }
+
+    pub fn is_null(&self) -> bool {
+        self.entry() == Entry::Null
+    }
+
+    pub fn is_array(&self) -> bool {
+        matches!(self.entry(), Entry::Array { .. })
+    }
+
+    pub fn is_object(&self) -> bool {
+        matches!(self.entry(), Entry::Object { .. })
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self.entry() {
+            Entry::Boolean(b) => Some(b),
+            _ => None,
+        }
+    }
+
+    pub fn as_number(&self) -> Option<JsonNumber> {
+        match self.entry() {
+            Entry::Number(span) => JsonNumber::from_literal(&self.tape.input[span.start..span.end]),
+            _ => None,
+        }
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        self.as_number()?.as_i64()
+    }
+
+    pub fn as_u64(&self) -> Option<u64> {
+        self.as_number()?.as_u64()
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        self.as_number().map(|number| number.as_f64())
+    }
+
+    /// The string's value; borrowed from the input unless it holds escapes
+    pub fn as_str(&self) -> Option<Cow<'t, str>> {
+        match self.entry() {
+            Entry::String(span) => Some(self.tape.string(span)),
+            _ => None,
+        }
+    }
+
+    /// Number of elements or members; `None` for scalars
+    pub fn len(&self) -> Option<usize> {
+        match self.entry() {
+            Entry::Array { len, .. } | Entry::Object { len, .. } => Some(len),
+            _ => None,
+        }
+    }
+
+    /// Whether the array or object has no elements or members; `None` for
+    /// scalars
+    pub fn is_empty(&self) -> Option<bool> {
+        self.len().map(|len| len == 0)
+    }
+
+    /// The array element at `index`, skipping the ones before it without
+    /// looking into them
+    pub fn index(&self, index: usize) -> Option<LazyValue<'t>> {
+        self.elements()?.nth(index)
+    }
+
+    /// The member value for `key`
+    ///
+    /// With repeated keys, the member the parse options' duplicate key
+    /// policy would keep is returned.
+    pub fn get(&self, key: &str) -> Option<LazyValue<'t>> {
+        let mut found = None;
+        for (member_key, value) in self.members()? {
+            if member_key == key {
+                found = Some(value);
+                if self.tape.duplicate_keys != DuplicateKeyPolicy::Last {
+                    break;
+                }
+            }
+        }
+        found
+    }
+
+    /// The value at a JSON Pointer such as `/users/0/name`
+    pub fn pointer(&self, pointer: &str) -> Option<LazyValue<'t>> {
+        let pointer = JsonPointer::parse(pointer).ok()?;
+        let mut current = *self;
+        for token in pointer.tokens() {
+            current = match current.entry() {
+                Entry::Array { .. } => current.index(parse_index(token)?)?,
+                Entry::Object { .. } => current.get(token)?,
+                _ => return None,
+            };
+        }
+        Some(current)
+    }
+
+    /// The array's elements; `None` for other values
+    pub fn elements(&self) -> Option<impl Iterator<Item = LazyValue<'t>> + 't> {
+        let Entry::Array { end, .. } = self.entry() else {
+            return None;
+        };
+        let this = *self;
+        let mut next = self.index + 1;
+        Some(std::iter::from_fn(move || {
+            (next < end).then(|| {
+                let element = this.at(next);
+                next = this.tape.skip(next);
+                element
+            })
+        }))
+    }
+
+    /// The object's members in document order, repeated keys included;
+    /// `None` for other values
+    pub fn members(&self) -> Option<impl Iterator<Item = (Cow<'t, str>, LazyValue<'t>)> + 't> {
+        let Entry::Object { end, .. } = self.entry() else {
+            return None;
+        };
+        let this = *self;
+        let mut next = self.index + 1;
+        Some(std::iter::from_fn(move || {
+            (next < end).then(|| {
+                let key = match this.tape.entries[next] {
+                    Entry::String(span) => this.tape.string(span),
+                    _ => unreachable!("object members start with a key"),
+                };
+                let value = this.at(next + 1);
+                next = this.tape.skip(next + 1);
+                (key, value)
+            })
+        }))
+    }
+
+    /// Build the tree for this value, as `parse_json` would have
+    pub fn to_value(&self) -> JsonValue {
+        match self.entry() {
+            Entry::Null => JsonValue::Null,
+            Entry::Boolean(b) => JsonValue::Boolean(b),
+            Entry::Number(_) => JsonValue::Number(self.as_number().expect("checked literal")),
+            Entry::String(span) => match self.tape.unescape(span) {
+                // Only accepted while lone surrogates are preserved; keep
+                // them as `Parser` does
+                (_, true) => {
+                    let token = &self.tape.input[span.start..span.end];
+                    JsonValue::RawString(content(token).to_string())
+                }
+                (string, false) => JsonValue::String(string.into_owned()),
+            },
+            Entry::Array { len, .. } => {
+                let mut elements = Vec::with_capacity(len);
+                elements.extend(self.elements().into_iter().flatten().map(|e| e.to_value()));
+                JsonValue::Array(elements)
+            }
+            Entry::Object { .. } => {
+                let mut object = JsonObject::new();
+                for (key, value) in self.members().into_iter().flatten() {
+                    if self.tape.duplicate_keys == DuplicateKeyPolicy::Last
+                        || !object.contains_key(&key)
+                    {
+                        object.insert(key, value.to_value());
+                    }
+                }
+                JsonValue::Object(object)
+            }
+        }
+    }
+}
+
+/// The text between the quotes of a string token
+fn content(token: &str) -> &str {
+    &token[1..token.len() - 1]
+}
+
+/// An object key as written
+enum Key<'a> {
+    Quoted(StrSlice<'a>),
+    Bare(&'a str),
+}
+
+/// Checks a document token by token, recording the entries; `None` on any
+/// error, which `Parser` then reports
+struct Builder<'a> {
+    lexer: Lexer<'a>,
+    entries: Vec<Entry>,
+    options: ParserOptions,
+    depth: usize,
+    /// Number of key entries
+    keys: usize,
+}
+
+impl<'a> Builder<'a> {
+    fn next(&mut self) -> Option<(SliceToken<'a>, Span)> {
+        self.lexer.next_slice_token().ok().flatten()
+    }
+
+    fn document(&mut self) -> Option<()> {
+        let first = self.next()?;
+        self.value(first)?;
+        match self.lexer.next_slice_token() {
+            Ok(None) => Some(()),
+            _ => None,
+        }
+    }
+
+    fn value(&mut self, (token, span): (SliceToken<'a>, Span)) -> Option<()> {
+        if self
+            .options
+            .max_nodes
+            .is_some_and(|max| self.nodes() >= max)
+        {
+            return None;
+        }
+        let entry = match token {
+            SliceToken::Null => Entry::Null,
+            SliceToken::Boolean(b) => Entry::Boolean(b),
+            SliceToken::Number(_) => Entry::Number(span),
+            SliceToken::String(_) => Entry::String(span),
+            SliceToken::LeftBracket => return self.container(span, false),
+            SliceToken::LeftBrace => return self.container(span, true),
+            _ => return None,
+        };
+        self.entries.push(entry);
+        Some(())
+    }
+
+    /// Values recorded so far; keys are entries but not values
+    fn nodes(&self) -> usize {
+        self.entries.len() - self.keys
+    }
+
+    /// Record the array or object opened at `open`
+    fn container(&mut self, open: Span, object: bool) -> Option<()> {
+        self.depth += 1;
+        if self.options.max_depth.is_some_and(|max| self.depth > max) {
+            return None;
+        }
+        let index = self.entries.len();
+        self.entries.push(Entry::Null);
+        let mut keys: HashSet<Cow<'a, str>> = HashSet::new();
+        let closing = if object {
+            SliceToken::RightBrace
+        } else {
+            SliceToken::RightBracket
+        };
+
+        let mut len = 0;
+        let mut token = self.next()?;
+        let close = loop {
+            if token.0 == closing && (len == 0 || self.options.allow_trailing_commas) {
+                break token.1;
+            }
+            if self.options.max_elements.is_some_and(|max| len >= max) {
+                return None;
+            }
+            let value = if object {
+                self.key(token, &mut keys)?;
+                if self.next()?.0 != SliceToken::Colon {
+                    return None;
+                }
+                self.next()?
+            } else {
+                token
+            };
+            self.value(value)?;
+            len += 1;
+            token = self.next()?;
+            match token.0 {
+                SliceToken::Comma => token = self.next()?,
+                _ if token.0 == closing => break token.1,
+                _ => return None,
+            }
+            // After a comma, only a trailing comma may be followed by the
+            // closing bracket
+            if token.0 == closing && !self.options.allow_trailing_commas {
+                return None;
+            }
+        };
+
+        let span = Span {
+            start: open.start,
+            end: close.end,
+        };
+        let end = self.entries.len();
+        self.entries[index] = match object {
+            true => Entry::Object { len, end, span },
+            false => Entry::Array { len, end, span },
+        };
+        self.depth -= 1;
+        Some(())
+    }
+
+    /// Record an object key, checking it like `Parser::parse_key` does
+    fn key(
+        &mut self,
+        (token, span): (SliceToken<'a>, Span),
+        keys: &mut HashSet<Cow<'a, str>>,
+    ) -> Option<()> {
+        let unquoted = self.options.allow_unquoted_keys;
+        let key = match token {
+            SliceToken::String(s) => {
+                if s.needs_unescape
+                    && self.options.preserve_lone_surrogates
+                    && check_escapes(s.content, '"', true).is_ok_and(|(_, replaced)| replaced)
+                {
+                    return None;
+                }
+                Key::Quoted(s)
+            }
+            SliceToken::Identifier(_) | SliceToken::Boolean(_) | SliceToken::Null if unquoted => {
+                Key::Bare(&self.lexer.input()[span.start..span.end])
+            }
+            _ => return None,
+        };
+        // Keys are only unescaped when repeated ones must be found
+        if self.options.duplicate_keys == DuplicateKeyPolicy::Error {
+            let key = match key {
+                Key::Quoted(s) => s.unescape(),
+                Key::Bare(name) => Cow::Borrowed(name),
+            };
+            if !keys.insert(key) {
+                return None;
+            }
+        }
+        self.entries.push(Entry::String(span));
+        self.keys += 1;
+        Some(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{json, parse_json};
+
+    #[test]
+    fn test_lazy_navigation() {
+        let input = r#" {"id": 7, "name": "a\"b", "tags": [1, [2, {"x": null}], 3.5, true],
+            "nested": {"k": "vé"}, "id": -8} "#;
+        let tape = Tape::parse(input).unwrap();
+        let root = tape.root();
+        assert_eq!(root.to_value(), parse_json(input).unwrap());
+        assert_eq!(root.len(), Some(5));
+        assert_eq!(root.pointer("/tags/1/1").unwrap().is_empty(), Some(false));
+        assert_eq!(root.get("id").unwrap().as_i64(), Some(-8));
+        assert_eq!(root.get("name").unwrap().as_str().unwrap(), "a\"b");
+        assert_eq!(root.get("name").unwrap().raw(), r#""a\"b""#);
+
+        let tags = root.get("tags").unwrap();
+        assert!(tags.is_array());
+        assert_eq!(tags.raw(), r#"[1, [2, {"x": null}], 3.5, true]"#);
+        assert_eq!(tags.index(2).unwrap().as_f64(), Some(3.5));
+        assert_eq!(tags.index(3).unwrap().as_bool(), Some(true));
+        assert!(tags.index(4).is_none());
+        assert_eq!(tags.index(1).unwrap().to_value(), json!([2, {"x": null}]));
+        assert!(root.pointer("/tags/1/1/x").unwrap().is_null());
+        assert_eq!(root.pointer("/nested/k").unwrap().as_str().unwrap(), "vé");
+        assert!(root.pointer("/tags/01").is_none());
+        assert!(root.get("missing").is_none() && tags.get("id").is_none());
+
+        let keys: Vec<_> = root.members().unwrap().map(|(key, _)| key).collect();
+        assert_eq!(keys, ["id", "name", "tags", "nested", "id"]);
+        assert_eq!(tags.elements().unwrap().count(), 4);
+        assert_eq!(tape.len(), 21);
+
+        let first = ParserOptions::default().duplicate_keys(DuplicateKeyPolicy::First);
+        let tape = Tape::with_options(input, first).unwrap();
+        assert_eq!(tape.root().get("id").unwrap().as_i64(), Some(7));
+        let expected = Parser::with_options(input, first).unwrap().parse().unwrap();
+        assert_eq!(tape.root().to_value(), expected);
+    }
+
+    #[test]
+    fn test_errors_match_parser() {
+        let json5 = ParserOptions::json5();
+        let lone = ParserOptions::default().preserve_lone_surrogates(true);
+        let cases = [
+            ("{a: 'x', b: [1, 2,], // c\n c: {d: true,},}", json5),
+            (r#"["\ud800", {"k": "\udc00x"}]"#, lone),
+            (r#"{"\ud800": 1}"#, lone),
+            ("[1, 2", ParserOptions::default()),
+            ("[1,, 2]", ParserOptions::default()),
+            ("[1, 2,]", ParserOptions::default()),
+            ("{\"a\" 1}", ParserOptions::default()),
+            ("{\"a\": 1,}", ParserOptions::default()),
+            ("{a: 1}", ParserOptions::default()),
+            ("[] []", ParserOptions::default()),
+            ("", ParserOptions::default()),
+            ("[01]", ParserOptions::default()),
+            ("[[[1]]]", ParserOptions::default().max_depth(Some(2))),
+            ("[1, [2, 3]]", ParserOptions::default().max_nodes(Some(4))),
+            ("[1, [2, 3]]", ParserOptions::default().max_nodes(Some(3))),
+            (
+                "{\"a\": [1, 2, 3]}",
+                ParserOptions::default().max_elements(Some(2)),
+            ),
+            (
+                r#"{"a": 1, "a": 2}"#,
+                ParserOptions::default().duplicate_keys(DuplicateKeyPolicy::Error),
+            ),
+            ("[1, 2]", ParserOptions::default().max_input_size(Some(3))),
+        ];
+        for (input, options) in cases {
+            let expected = Parser::with_options(input, options).and_then(|mut p| p.parse());
+            let tape = Tape::with_options(input, options);
+            match (tape, expected) {
+                (Ok(tape), Ok(expected)) => assert_eq!(tape.root().to_value(), expected),
+                (Err(error), Err(expected)) => assert_eq!(error, expected, "{}", input),
+                (tape, expected) => panic!("{}: {:?} / {:?}", input, tape.is_ok(), expected),
+            }
+        }
+    }
+}
@@ -0,0 +1,661 @@
+//! Streaming validation - check a document without building its tree
+//!
+//! The validator walks the token stream with an explicit stack instead of
+//! recursion, so neither memory use nor stack depth grows with the size of
+//! arrays and objects. Besides checking the syntax it enforces per-location
+//! size constraints, such as "strings under `/attachments/*` are at most
+//! 1 MiB" or "`/events` holds at most 10 000 elements", before the document
+//! is handed to code that would materialize it.
+//!
+//! `validate_all` serves linters instead: it recovers from syntax errors and
+//! reports every problem in the document.
+
+use crate::error::{ErrorKind, ParseError};
+use crate::lexer::{Lexer, SliceToken, Token};
+use crate::number::JsonNumber;
+use crate::pointer::{JsonPointer, pattern_matches};
+
+/// Size rules keyed by JSON Pointer patterns (`*` matches any single key or
+/// index)
+///
+/// # Examples
+///
+/// ```
+/// use json_parser_rs::validate::{Constraints, validate};
+///
+/// let constraints = Constraints::new()
+///     .max_string_len("/attachments/*", 8)
+///     .max_array_len("/events", 2);
+///
+/// assert!(validate(r#"{"attachments": ["small"], "events": [1, 2]}"#, &constraints).is_ok());
+/// assert!(validate(r#"{"attachments": ["far too large"]}"#, &constraints).is_err());
+/// assert!(validate(r#"{"events": [1, 2, 3]}"#, &constraints).is_err());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Constraints {
+    rules: Vec<Rule>,
+}
+
+#[derive(Debug, Clone)]
+struct Rule {
+    pattern: String,
+    limit: Limit,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Limit {
+    StringBytes(usize),
+    ArrayElements(usize),
+}
+
+impl Constraints {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Strings at locations matching `pattern` may hold at most `max_bytes`
+    /// bytes (after unescaping, UTF-8 encoded)
+    ///
+    /// The length is worked out while the string is scanned; an oversized
+    /// string is rejected without being unescaped or copied.
+    pub fn max_string_len(mut self, pattern: impl Into<String>, max_bytes: usize) -> Self {
+        self.rules.push(Rule {
+            pattern: pattern.into(),
+            limit: Limit::StringBytes(max_bytes),
+        });
+        self
+    }
+
+    /// Arrays at locations matching `pattern` may hold at most `max_elements`
+    /// elements
+    pub fn max_array_len(mut self, pattern: impl Into<String>, max_elements: usize) -> Self {
+        self.rules.push(Rule {
+            pattern: pattern.into(),
+            limit: Limit::ArrayElements(max_elements),
+        });
+        self
+    }
+
+    fn string_limit(&self, path: &[String]) -> Option<usize> {
+        self.limit(path, |limit| match limit {
+            Limit::StringBytes(max) => Some(max),
+            _ => None,
+        })
+    }
+
+    fn array_limit(&self, path: &[String]) -> Option<usize> {
+        self.limit(path, |limit| match limit {
+            Limit::ArrayElements(max) => Some(max),
+            _ => None,
+        })
+    }
+
+    /// The strictest matching limit of one kind
+    fn limit(&self, path: &[String], kind: impl Fn(Limit) -> Option<usize>) -> Option<usize> {
+        self.rules
+            .iter()
+            .filter(|rule| pattern_matches(&rule.pattern, path))
+            .filter_map(|rule| kind(rule.limit))
+            .min()
+    }
+}
+
+/// Check that `input` is valid JSON satisfying `constraints`
+///
+/// Stops at the first problem. Violated constraints are reported with
+/// `ErrorKind::ConstraintViolation`, syntax errors with the specific kind
+/// the parser would report.
+pub fn validate(input: &str, constraints: &Constraints) -> Result<(), ParseError> {
+    Validator {
+        lexer: Lexer::new(input),
+        constraints,
+        path: Vec::new(),
+        stack: Vec::new(),
+    }
+    .run()
+}
+
+enum Frame {
+    Array { len: usize, limit: Option<usize> },
+    Object,
+}
+
+struct Validator<'a, 'c> {
+    lexer: Lexer<'a>,
+    constraints: &'c Constraints,
+    /// Location of the value being validated
+    path: Vec<String>,
+    stack: Vec<Frame>,
+}
+
+impl<'a> Validator<'a, '_> {
+    fn run(&mut self) -> Result<(), ParseError> {
+        let mut token = self.next()?;
+        loop {
+            // `token` starts a value
+            token = match token {
+                Some(SliceToken::LeftBracket) => {
+                    let limit = self.constraints.array_limit(&self.path);
+                    self.stack.push(Frame::Array { len: 0, limit });
+                    let next = self.next()?;
+                    if next != Some(SliceToken::RightBracket) {
+                        self.path.push("0".to_string());
+                        token = next;
+                        continue;
+                    }
+                    self.stack.pop();
+                    self.next()?
+                }
+                Some(SliceToken::LeftBrace) => {
+                    self.stack.push(Frame::Object);
+                    let next = self.next()?;
+                    if next != Some(SliceToken::RightBrace) {
+                        token = self.enter_member(next)?;
+                        continue;
+                    }
+                    self.stack.pop();
+                    self.next()?
+                }
+                Some(SliceToken::String(s)) => {
+                    if let Some(max) = self.constraints.string_limit(&self.path)
+                        && s.value_len() > max
+                    {
+                        return Err(self.violation(format!(
+                            "String at {} is {} bytes long, the limit is {}",
+                            self.location(),
+                            s.value_len(),
+                            max
+                        )));
+                    }
+                    self.next()?
+                }
+                Some(SliceToken::Number(_) | SliceToken::Boolean(_) | SliceToken::Null) => {
+                    self.next()?
+                }
+                other => {
+                    let other = owned(other);
+                    return Err(self.error(
+                        ErrorKind::unexpected(&other),
+                        format!("Unexpected token: {}", other),
+                    ));
+                }
+            };
+
+            // A value is complete and `token` follows it: close containers
+            // until one continues with another element
+            loop {
+                match self.stack.last_mut() {
+                    None => {
+                        if token.is_some() {
+                            return Err(self.error(
+                                ErrorKind::TrailingCharacters,
+                                format!("Unexpected token after JSON value: {}", owned(token)),
+                            ));
+                        }
+                        return Ok(());
+                    }
+                    Some(Frame::Array { len, limit }) => {
+                        *len += 1;
+                        let (len, limit) = (*len, *limit);
+                        self.path.pop();
+                        if let Some(max) = limit
+                            && len > max
+                        {
+                            return Err(self.violation(format!(
+                                "Array at {} has more than {} elements",
+                                self.location(),
+                                max
+                            )));
+                        }
+                        match token {
+                            Some(SliceToken::Comma) => {
+                                token = self.next()?;
+                                if token == Some(SliceToken::RightBracket) {
+                                    return Err(self.error(
+                                        ErrorKind::TrailingComma,
+                                        "Trailing comma not allowed",
+                                    ));
+                                }
+                                self.path.push(len.to_string());
+                                break;
+                            }
+                            Some(SliceToken::RightBracket) => {
+                                self.stack.pop();
+                                token = self.next()?;
+                            }
+                            other => {
+                                let other = owned(other);
+                                return Err(self.error(
+                                    ErrorKind::unexpected(&other),
+                                    format!("Expected ',' or ']', found {}", other),
+                                ));
+                            }
+                        }
+                    }
+                    Some(Frame::Object) => {
+                        self.path.pop();
+                        match token {
+                            Some(SliceToken::Comma) => {
+                                let next = self.next()?;
+                                if next == Some(SliceToken::RightBrace) {
+                                    return Err(self.error(
+                                        ErrorKind::TrailingComma,
+                                        "Trailing comma not allowed",
+                                    ));
+                                }
+                                token = self.enter_member(next)?;
+                                break;
+                            }
+                            Some(SliceToken::RightBrace) => {
+                                self.stack.pop();
+                                token = self.next()?;
+                            }
+                            other => {
+                                let other = owned(other);
+                                return Err(self.error(
+                                    ErrorKind::unexpected(&other),
+                                    format!("Expected ',' or '}}', found {}", other),
+                                ));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// The next token, `None` at the end of the input
+    ///
+    /// Strings are scanned and their escapes checked, but not unescaped, so
+    /// an oversized string is rejected before any of it is copied.
+    fn next(&mut self) -> Result<Option<SliceToken<'a>>, ParseError> {
+        let token = self.lexer.next_slice_token()?.map(|(token, _)| token);
+        // Literals out of `f64` range pass the scan, but not `next_token`
+        if let Some(SliceToken::Number(literal)) = token
+            && JsonNumber::from_literal(literal).is_none()
+        {
+            return Err(self.error(ErrorKind::InvalidNumber, "Invalid number"));
+        }
+        Ok(token)
+    }
+
+    /// Consume `key :`, push the key onto the path and return the token
+    /// starting the member value
+    fn enter_member(
+        &mut self,
+        key: Option<SliceToken<'a>>,
+    ) -> Result<Option<SliceToken<'a>>, ParseError> {
+        let key = match key {
+            Some(SliceToken::String(key)) => key.unescape().into_owned(),
+            None => {
+                return Err(self.error(ErrorKind::UnexpectedEof, "Object key must be a string"));
+            }
+            _ => {
+                return Err(self.error(ErrorKind::KeyMustBeString, "Object key must be a string"));
+            }
+        };
+        self.path.push(key);
+        match self.next()? {
+            Some(SliceToken::Colon) => self.next(),
+            other => {
+                let other = owned(other);
+                Err(self.error(
+                    ErrorKind::unexpected(&other),
+                    format!("Expected ':', found {}", other),
+                ))
+            }
+        }
+    }
+
+    fn location(&self) -> String {
+        let pointer = JsonPointer::from_tokens(self.path.iter().map(String::as_str)).to_string();
+        if pointer.is_empty() {
+            "the root".to_string()
+        } else {
+            pointer
+        }
+    }
+
+    fn error(&self, kind: ErrorKind, message: impl Into<String>) -> ParseError {
+        ParseError::new(message, self.lexer.token_start(), self.lexer.input()).with_kind(kind)
+    }
+
+    fn violation(&self, message: String) -> ParseError {
+        self.error(ErrorKind::ConstraintViolation, message)
+    }
+}
+
+/// `token` as `next_token` would have returned it, for error messages
+fn owned(token: Option<SliceToken>) -> Token {
+    match token {
+        None => Token::Eof,
+        Some(SliceToken::LeftBrace) => Token::LeftBrace,
+        Some(SliceToken::RightBrace) => Token::RightBrace,
+        Some(SliceToken::LeftBracket) => Token::LeftBracket,
+        Some(SliceToken::RightBracket) => Token::RightBracket,
+        Some(SliceToken::Comma) => Token::Comma,
+        Some(SliceToken::Colon) => Token::Colon,
+        Some(SliceToken::String(s)) => Token::String(s.unescape().into_owned()),
+        Some(SliceToken::Number(literal)) => {
+            Token::Number(JsonNumber::from_literal(literal).expect("checked in `next`"))
+        }
+        Some(SliceToken::Boolean(b)) => Token::Boolean(b),
+        Some(SliceToken::Null) => Token::Null,
+        Some(SliceToken::Identifier(name)) => Token::Identifier(name.to_string()),
+    }
+}
+
+/// Check that `input` is valid JSON, reporting all syntax errors instead of
+/// only the first
+///
+/// After an error the checker skips ahead to the next `,`, `]` or `}` of the
+/// enclosing container and continues from there. Malformed tokens (bad
+/// numbers, unknown keywords, broken escapes) count as values of their type,
+/// so one typo produces one error. An empty result means the document is
+/// valid.
+///
+/// # Examples
+///
+/// ```
+/// use json_parser_rs::ErrorKind;
+/// use json_parser_rs::validate::validate_all;
+///
+/// let errors = validate_all(r#"{"a": tru, "b" 2, "c": [1 2],}"#);
+/// let kinds: Vec<ErrorKind> = errors.iter().map(|e| e.kind()).collect();
+/// assert_eq!(
+///     kinds,
+///     [
+///         ErrorKind::InvalidKeyword,
+///         ErrorKind::UnexpectedToken,
+///         ErrorKind::UnexpectedToken,
+///         ErrorKind::TrailingComma,
+///     ]
+/// );
+/// assert!(validate_all("[1, 2]").is_empty());
+/// ```
+pub fn validate_all(input: &str) -> Vec<ParseError> {
+    let mut checker = Recovering {
+        lexer: Lexer::new(input),
+        errors: Vec::new(),
+        stack: Vec::new(),
+    };
+    checker.run();
+    checker.errors
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Expect {
+    /// A value, or the closing bracket of an empty array
+    FirstElement,
+    /// A value after a comma or at the root
+    Value,
+    /// A key, or the closing brace of an empty object
+    FirstKey,
+    /// A key after a comma
+    Key,
+    Colon,
+    /// A comma or the end of the enclosing container
+    Separator,
+}
+
+struct Recovering<'a> {
+    lexer: Lexer<'a>,
+    errors: Vec<ParseError>,
+    /// `true` for objects, `false` for arrays
+    stack: Vec<bool>,
+}
+
+impl Recovering<'_> {
+    fn run(&mut self) {
+        let mut expect = Expect::Value;
+        loop {
+            let reported = self.errors.len();
+            let token = self.lexer.next_token_recovering(&mut self.errors);
+            // A token the lexer had to repair was already reported
+            let repaired = self.errors.len() > reported;
+            if token == Token::Eof {
+                if !self.stack.is_empty() || expect != Expect::Separator {
+                    self.end_of_input();
+                }
+                return;
+            }
+            expect = match (expect, token) {
+                (Expect::FirstElement, Token::RightBracket) => self.close(),
+                (Expect::FirstKey, Token::RightBrace) => self.close(),
+                (Expect::Value, Token::RightBracket) if self.stack.last() == Some(&false) => {
+                    self.report(ErrorKind::TrailingComma, "Trailing comma not allowed");
+                    self.close()
+                }
+                (Expect::Key, Token::RightBrace) if self.stack.last() == Some(&true) => {
+                    self.report(ErrorKind::TrailingComma, "Trailing comma not allowed");
+                    self.close()
+                }
+                (Expect::FirstElement | Expect::Value, Token::LeftBracket) => {
+                    self.stack.push(false);
+                    Expect::FirstElement
+                }
+                (Expect::FirstElement | Expect::Value, Token::LeftBrace) => {
+                    self.stack.push(true);
+                    Expect::FirstKey
+                }
+                (
+                    Expect::FirstElement | Expect::Value,
+                    Token::String(_) | Token::Number(_) | Token::Boolean(_) | Token::Null,
+                ) => Expect::Separator,
+                (Expect::FirstKey | Expect::Key, Token::String(_)) => Expect::Colon,
+                (Expect::Colon, Token::Colon) => Expect::Value,
+                (Expect::Separator, Token::Comma) if !self.stack.is_empty() => self.after_comma(),
+                (Expect::Separator, Token::RightBracket) if self.stack.last() == Some(&false) => {
+                    self.close()
+                }
+                (Expect::Separator, Token::RightBrace) if self.stack.last() == Some(&true) => {
+                    self.close()
+                }
+                (Expect::Separator, token) if self.stack.is_empty() => {
+                    if !repaired {
+                        self.report(
+                            ErrorKind::TrailingCharacters,
+                            format!("Unexpected token after JSON value: {}", token),
+                        );
+                    }
+                    return;
+                }
+                (expect, token) => {
+                    let (kind, message) = match expect {
+                        Expect::FirstKey | Expect::Key => (
+                            ErrorKind::KeyMustBeString,
+                            "Object key must be a string".to_string(),
+                        ),
+                        Expect::Colon => (
+                            ErrorKind::UnexpectedToken,
+                            format!("Expected ':', found {}", token),
+                        ),
+                        Expect::Separator => {
+                            let close = if self.stack.last() == Some(&true) {
+                                '}'
+                            } else {
+                                ']'
+                            };
+                            (
+                                ErrorKind::UnexpectedToken,
+                                format!("Expected ',' or '{}', found {}", close, token),
+                            )
+                        }
+                        Expect::FirstElement | Expect::Value => (
+                            ErrorKind::UnexpectedToken,
+                            format!("Unexpected token: {}", token),
+                        ),
+                    };
+                    if !repaired {
+                        self.report(kind, message);
+                    }
+                    match self.skip(token) {
+                        Some(expect) => expect,
+                        None => return,
+                    }
+                }
+            };
+        }
+    }
+
+    /// Skip tokens, starting with `token`, up to the next `,`, `]` or `}` of
+    /// the enclosing container and resume there; `None` if the input ends
+    /// first
+    fn skip(&mut self, mut token: Token) -> Option<Expect> {
+        if self.stack.is_empty() {
+            return None;
+        }
+        let mut nested = 0usize;
+        loop {
+            match token {
+                Token::Eof => {
+                    self.end_of_input();
+                    return None;
+                }
+                Token::LeftBracket | Token::LeftBrace => nested += 1,
+                Token::RightBracket | Token::RightBrace if nested > 0 => nested -= 1,
+                // Closes the enclosing container, even if it is the wrong
+                // kind of bracket
+                Token::RightBracket | Token::RightBrace => return Some(self.close()),
+                Token::Comma if nested == 0 => return Some(self.after_comma()),
+                _ => {}
+            }
+            token = self.lexer.next_token_recovering(&mut self.errors);
+        }
+    }
+
+    fn close(&mut self) -> Expect {
+        self.stack.pop();
+        Expect::Separator
+    }
+
+    fn after_comma(&self) -> Expect {
+        if self.stack.last() == Some(&true) {
+            Expect::Key
+        } else {
+            Expect::Value
+        }
+    }
+
+    /// Report the input ending early, unless an unterminated string or
+    /// comment already explains why
+    fn end_of_input(&mut self) {
+        let explained = self.errors.last().is_some_and(|error| {
+            matches!(
+                error.kind(),
+                ErrorKind::UnterminatedString | ErrorKind::UnterminatedComment
+            )
+        });
+        if !explained {
+            self.report(ErrorKind::UnexpectedEof, "Unexpected end of input");
+        }
+    }
+
+    fn report(&mut self, kind: ErrorKind, message: impl Into<String>) {
+        let error =
+            ParseError::new(message, self.lexer.token_start(), self.lexer.input()).with_kind(kind);
+        self.errors.push(error);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_constraints_by_location() {
+        let constraints = Constraints::new()
+            .max_string_len("/attachments/*/data", 4)
+            .max_array_len("/events", 3);
+
+        let ok = r#"{"attachments": [{"data": "abcd", "name": "longer name"}],
+                     "events": [[1, 2, 3, 4], {}, "x"], "other": [1, 2, 3, 4]}"#;
+        assert_eq!(validate(ok, &constraints), Ok(()));
+
+        let long_string = r#"{"attachments": [{"data": "abcd"}, {"data": "abcde"}]}"#;
+        let error = validate(long_string, &constraints).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::ConstraintViolation);
+        assert!(error.message().contains("/attachments/1/data"), "{}", error);
+
+        let error = validate(r#"{"events": [1, 2, 3, 4]}"#, &constraints).unwrap_err();
+        assert_eq!(error.message(), "Array at /events has more than 3 elements");
+
+        // Lengths count the unescaped value
+        let escaped = r#"{"attachments": [{"data": "\u00e9\u00e9"}, {"data": "\n\t\"\\x"}]}"#;
+        let error = validate(escaped, &constraints).unwrap_err();
+        assert_eq!(
+            error.message(),
+            "String at /attachments/1/data is 5 bytes long, the limit is 4"
+        );
+        assert_eq!(error.position(), escaped.rfind(": ").unwrap() + 2);
+    }
+
+    #[test]
+    fn test_validate_syntax() {
+        let none = Constraints::new();
+        for valid in [
+            "1",
+            "[]",
+            "{}",
+            r#"[{"a": [[]]}, null]"#,
+            r#"{"a": {"b": {}}}"#,
+        ] {
+            assert_eq!(validate(valid, &none), Ok(()), "{}", valid);
+        }
+        for invalid in [
+            "",
+            "[1,]",
+            "{\"a\" 1}",
+            "{\"a\": 1,}",
+            "[1 2]",
+            "[[]",
+            "{} 1",
+            "{1: 2}",
+            "[1e400]",
+            r#"["\x"]"#,
+        ] {
+            let error = validate(invalid, &none).unwrap_err();
+            assert!(error.kind().is_syntax(), "{}", invalid);
+        }
+
+        // No recursion, so nesting depth is not limited by the stack
+        let deep = format!("{}{}", "[".repeat(100_000), "]".repeat(100_000));
+        assert_eq!(validate(&deep, &none), Ok(()));
+    }
+
+    #[test]
+    fn test_validate_all_recovers() {
+        let errors = |input: &str| -> Vec<(ErrorKind, usize)> {
+            validate_all(input)
+                .iter()
+                .map(|error| (error.kind(), error.position()))
+                .collect()
+        };
+
+        for valid in ["1", "[]", "{}", r#"[{"a": [[]]}, null]"#] {
+            assert_eq!(errors(valid), [], "{}", valid);
+        }
+        assert_eq!(errors(""), [(ErrorKind::UnexpectedEof, 0)]);
+        assert_eq!(
+            errors("[1 2, @, 3"),
+            [
+                (ErrorKind::UnexpectedToken, 3),
+                (ErrorKind::UnexpectedCharacter, 6),
+                (ErrorKind::UnexpectedEof, 10),
+            ]
+        );
+        assert_eq!(
+            errors(r#"{"a": [1, {"b" 2}], 3: "\x", "c": 1} x"#),
+            [
+                (ErrorKind::UnexpectedToken, 15),
+                (ErrorKind::KeyMustBeString, 20),
+                (ErrorKind::InvalidEscape, 24),
+                (ErrorKind::InvalidKeyword, 37),
+            ]
+        );
+        // Only one error for a string that swallows the rest of the input
+        assert_eq!(errors(r#"["a", "b]"#), [(ErrorKind::UnterminatedString, 7)]);
+        // A mismatched bracket still closes the container
+        assert_eq!(errors("[[1}, 2]"), [(ErrorKind::UnexpectedToken, 3)]);
+    }
+}
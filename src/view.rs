@@ -0,0 +1,500 @@
+//! Views - typed read access to objects and arrays
+//!
+//! [`JsonValue::as_object`] and [`JsonValue::as_array`] return an
+//! [`ObjectView`] or [`ArrayView`] rather than a reference to the storage.
+//! The views dereference to [`JsonObject`] and `[JsonValue]`, so the usual
+//! `len`, `iter` and indexing work, and add typed lookups (`get_str`,
+//! `get_i64`, ...), typed iteration and `require` / `require_str` / ...,
+//! which report the key or index, the type found and the keys the object
+//! does have when a value is missing or has the wrong type.
+//! Code written against the views keeps compiling if the storage of
+//! objects and arrays changes.
+//!
+//! # Examples
+//!
+//! ```
+//! use json_parser_rs::json;
+//!
+//! let config = json!({"name": "api", "ports": [80, 443], "tls": {"enabled": true}});
+//! let config = config.as_object().unwrap();
+//! assert_eq!(config.get_str("name"), Some("api"));
+//! assert_eq!(config.get_object("tls").and_then(|tls| tls.get_bool("enabled")), Some(true));
+//!
+//! let ports: Result<Vec<i64>, _> = config.get_array("ports").unwrap().iter_i64().collect();
+//! assert_eq!(ports.unwrap(), [80, 443]);
+//!
+//! let error = config.require_u64("timeout").unwrap_err();
+//! assert_eq!(error.to_string(), r#"Missing key "timeout" (available: "name", "ports", "tls")"#);
+//! assert_eq!(config.require_str("name"), Ok("api"));
+//! ```
+
+use std::fmt;
+use std::ops::Deref;
+
+use crate::json::{JsonValue, type_name};
+use crate::keypath::KeyPathError;
+use crate::object::JsonObject;
+use crate::suggest::suggest;
+
+/// Where a failed lookup looked
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AccessLocation {
+    Key(String),
+    Index(usize),
+}
+
+impl fmt::Display for AccessLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AccessLocation::Key(key) => write!(f, "key {:?}", key),
+            AccessLocation::Index(index) => write!(f, "index {}", index),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum AccessErrorKind {
+    /// No member with the key, or the index is out of bounds
+    Missing,
+    /// The value exists but has another type
+    WrongType {
+        expected: &'static str,
+        found: &'static str,
+    },
+    /// The key path given to `require_path` is malformed
+    InvalidPath(KeyPathError),
+}
+
+/// A required value that is missing or has the wrong type
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccessError {
+    pub location: AccessLocation,
+    pub kind: AccessErrorKind,
+    /// Keys of the object that was searched, in order; empty for array
+    /// lookups
+    pub available_keys: Vec<String>,
+    /// Key path of the object or array that was searched, for errors from
+    /// `require_path`; empty when it is the value the lookup started from
+    pub path: String,
+}
+
+impl AccessError {
+    pub(crate) fn missing(location: AccessLocation) -> Self {
+        Self::new(location, AccessErrorKind::Missing)
+    }
+
+    pub(crate) fn wrong_type(
+        location: AccessLocation,
+        expected: &'static str,
+        value: &JsonValue,
+    ) -> Self {
+        let found = type_name(value);
+        Self::new(location, AccessErrorKind::WrongType { expected, found })
+    }
+
+    pub(crate) fn invalid_path(path: &str, error: KeyPathError) -> Self {
+        let location = AccessLocation::Key(path.to_string());
+        Self::new(location, AccessErrorKind::InvalidPath(error))
+    }
+
+    fn new(location: AccessLocation, kind: AccessErrorKind) -> Self {
+        Self {
+            location,
+            kind,
+            available_keys: Vec::new(),
+            path: String::new(),
+        }
+    }
+
+    pub(crate) fn with_keys(mut self, object: &JsonObject) -> Self {
+        self.available_keys = object.keys().cloned().collect();
+        self
+    }
+
+    /// Available keys close to a missing key, closest first; empty for
+    /// other errors
+    pub fn suggestions(&self) -> Vec<&str> {
+        match (&self.kind, &self.location) {
+            (AccessErrorKind::Missing, AccessLocation::Key(key)) => {
+                suggest(key, self.available_keys.iter().map(String::as_str))
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    pub(crate) fn within(mut self, path: String) -> Self {
+        self.path = path;
+        self
+    }
+
+    /// ` in servers[0]` after the key or index, if the error has a path
+    fn write_within(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.path.is_empty() {
+            return Ok(());
+        }
+        write!(f, " in {}", self.path)
+    }
+}
+
+/// `Missing key "hots" in servers[0] (did you mean "host"?)`,
+/// `Missing key "name" (available: "id", "title")`,
+/// `Index 2: expected string, found integer`
+impl fmt::Display for AccessError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.kind, &self.location) {
+            (AccessErrorKind::InvalidPath(error), _) => write!(f, "{}", error),
+            (AccessErrorKind::Missing, AccessLocation::Key(key)) => {
+                write!(f, "Missing key {:?}", key)?;
+                self.write_within(f)?;
+                let suggestions = self.suggestions();
+                if !suggestions.is_empty() {
+                    let keys: Vec<_> = suggestions.iter().map(|k| format!("{:?}", k)).collect();
+                    write!(f, " (did you mean {}?)", keys.join(" or "))?;
+                } else if !self.available_keys.is_empty() {
+                    let keys: Vec<_> = self
+                        .available_keys
+                        .iter()
+                        .map(|k| format!("{:?}", k))
+                        .collect();
+                    write!(f, " (available: {})", keys.join(", "))?;
+                }
+                Ok(())
+            }
+            (AccessErrorKind::Missing, AccessLocation::Index(index)) => {
+                write!(f, "Index {} out of bounds", index)?;
+                self.write_within(f)
+            }
+            (AccessErrorKind::WrongType { expected, found }, location) => {
+                match location {
+                    AccessLocation::Key(key) => write!(f, "Key {:?}", key)?,
+                    AccessLocation::Index(index) => write!(f, "Index {}", index)?,
+                }
+                self.write_within(f)?;
+                write!(f, ": expected {}, found {}", expected, found)
+            }
+        }
+    }
+}
+
+impl std::error::Error for AccessError {}
+
+/// Read access to a JSON object, dereferencing to [`JsonObject`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ObjectView<'a>(&'a JsonObject);
+
+impl<'a> ObjectView<'a> {
+    pub fn new(object: &'a JsonObject) -> Self {
+        Self(object)
+    }
+
+    /// The underlying object, for the full lifetime `'a`
+    pub fn as_json_object(self) -> &'a JsonObject {
+        self.0
+    }
+
+    pub fn get(self, key: &str) -> Option<&'a JsonValue> {
+        self.0.get(key)
+    }
+
+    /// Members in insertion order
+    pub fn iter(
+        self,
+    ) -> impl DoubleEndedIterator<Item = (&'a String, &'a JsonValue)> + ExactSizeIterator {
+        self.0.iter()
+    }
+
+    pub fn keys(self) -> impl DoubleEndedIterator<Item = &'a String> + ExactSizeIterator {
+        self.0.keys()
+    }
+
+    pub fn values(self) -> impl DoubleEndedIterator<Item = &'a JsonValue> + ExactSizeIterator {
+        self.0.values()
+    }
+
+    pub fn get_str(self, key: &str) -> Option<&'a str> {
+        self.get(key)?.as_str()
+    }
+
+    pub fn get_i64(self, key: &str) -> Option<i64> {
+        self.get(key)?.as_i64()
+    }
+
+    pub fn get_u64(self, key: &str) -> Option<u64> {
+        self.get(key)?.as_u64()
+    }
+
+    pub fn get_f64(self, key: &str) -> Option<f64> {
+        self.get(key)?.as_f64()
+    }
+
+    pub fn get_bool(self, key: &str) -> Option<bool> {
+        self.get(key)?.as_bool()
+    }
+
+    pub fn get_array(self, key: &str) -> Option<ArrayView<'a>> {
+        self.get(key)?.as_array()
+    }
+
+    pub fn get_object(self, key: &str) -> Option<ObjectView<'a>> {
+        self.get(key)?.as_object()
+    }
+
+    /// The member `key`, or an error naming it and the keys that exist
+    pub fn require(self, key: &str) -> Result<&'a JsonValue, AccessError> {
+        self.get(key).ok_or_else(|| {
+            AccessError::missing(AccessLocation::Key(key.to_string())).with_keys(self.0)
+        })
+    }
+
+    /// The member `key` as a string; missing or another type is an error
+    pub fn require_str(self, key: &str) -> Result<&'a str, AccessError> {
+        self.require_as(key, "string", JsonValue::as_str)
+    }
+
+    pub fn require_i64(self, key: &str) -> Result<i64, AccessError> {
+        self.require_as(key, "integer", JsonValue::as_i64)
+    }
+
+    pub fn require_u64(self, key: &str) -> Result<u64, AccessError> {
+        self.require_as(key, "unsigned integer", JsonValue::as_u64)
+    }
+
+    pub fn require_f64(self, key: &str) -> Result<f64, AccessError> {
+        self.require_as(key, "number", JsonValue::as_f64)
+    }
+
+    pub fn require_bool(self, key: &str) -> Result<bool, AccessError> {
+        self.require_as(key, "boolean", JsonValue::as_bool)
+    }
+
+    pub fn require_array(self, key: &str) -> Result<ArrayView<'a>, AccessError> {
+        self.require_as(key, "array", JsonValue::as_array)
+    }
+
+    pub fn require_object(self, key: &str) -> Result<ObjectView<'a>, AccessError> {
+        self.require_as(key, "object", JsonValue::as_object)
+    }
+
+    fn require_as<T>(
+        self,
+        key: &str,
+        expected: &'static str,
+        convert: impl FnOnce(&'a JsonValue) -> Option<T>,
+    ) -> Result<T, AccessError> {
+        let value = self.require(key)?;
+        convert(value).ok_or_else(|| {
+            AccessError::wrong_type(AccessLocation::Key(key.to_string()), expected, value)
+                .with_keys(self.0)
+        })
+    }
+}
+
+impl Deref for ObjectView<'_> {
+    type Target = JsonObject;
+
+    fn deref(&self) -> &JsonObject {
+        self.0
+    }
+}
+
+impl<'a> IntoIterator for ObjectView<'a> {
+    type Item = (&'a String, &'a JsonValue);
+    type IntoIter = <&'a JsonObject as IntoIterator>::IntoIter;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl PartialEq<JsonObject> for ObjectView<'_> {
+    fn eq(&self, other: &JsonObject) -> bool {
+        self.0 == other
+    }
+}
+
+/// Read access to a JSON array, dereferencing to `[JsonValue]`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ArrayView<'a>(&'a [JsonValue]);
+
+impl<'a> ArrayView<'a> {
+    pub fn new(elements: &'a [JsonValue]) -> Self {
+        Self(elements)
+    }
+
+    /// The elements, for the full lifetime `'a`
+    pub fn as_slice(self) -> &'a [JsonValue] {
+        self.0
+    }
+
+    pub fn get(self, index: usize) -> Option<&'a JsonValue> {
+        self.0.get(index)
+    }
+
+    pub fn iter(self) -> std::slice::Iter<'a, JsonValue> {
+        self.0.iter()
+    }
+
+    /// The element at `index`, or an error naming it
+    pub fn require(self, index: usize) -> Result<&'a JsonValue, AccessError> {
+        self.get(index)
+            .ok_or_else(|| AccessError::missing(AccessLocation::Index(index)))
+    }
+
+    /// The elements as strings; an element of another type yields an error
+    /// with its index
+    pub fn iter_str(self) -> impl ExactSizeIterator<Item = Result<&'a str, AccessError>> {
+        self.typed("string", JsonValue::as_str)
+    }
+
+    pub fn iter_i64(self) -> impl ExactSizeIterator<Item = Result<i64, AccessError>> {
+        self.typed("integer", JsonValue::as_i64)
+    }
+
+    pub fn iter_u64(self) -> impl ExactSizeIterator<Item = Result<u64, AccessError>> {
+        self.typed("unsigned integer", JsonValue::as_u64)
+    }
+
+    pub fn iter_f64(self) -> impl ExactSizeIterator<Item = Result<f64, AccessError>> {
+        self.typed("number", JsonValue::as_f64)
+    }
+
+    pub fn iter_bool(self) -> impl ExactSizeIterator<Item = Result<bool, AccessError>> {
+        self.typed("boolean", JsonValue::as_bool)
+    }
+
+    pub fn iter_objects(
+        self,
+    ) -> impl ExactSizeIterator<Item = Result<ObjectView<'a>, AccessError>> {
+        self.typed("object", JsonValue::as_object)
+    }
+
+    pub fn iter_arrays(self) -> impl ExactSizeIterator<Item = Result<ArrayView<'a>, AccessError>> {
+        self.typed("array", JsonValue::as_array)
+    }
+
+    fn typed<T>(
+        self,
+        expected: &'static str,
+        convert: impl Fn(&'a JsonValue) -> Option<T>,
+    ) -> impl ExactSizeIterator<Item = Result<T, AccessError>> {
+        self.0.iter().enumerate().map(move |(index, value)| {
+            convert(value).ok_or_else(|| {
+                AccessError::wrong_type(AccessLocation::Index(index), expected, value)
+            })
+        })
+    }
+}
+
+impl Deref for ArrayView<'_> {
+    type Target = [JsonValue];
+
+    fn deref(&self) -> &[JsonValue] {
+        self.0
+    }
+}
+
+impl<'a> IntoIterator for ArrayView<'a> {
+    type Item = &'a JsonValue;
+    type IntoIter = std::slice::Iter<'a, JsonValue>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
+impl PartialEq<[JsonValue]> for ArrayView<'_> {
+    fn eq(&self, other: &[JsonValue]) -> bool {
+        self.0 == other
+    }
+}
+
+impl PartialEq<Vec<JsonValue>> for ArrayView<'_> {
+    fn eq(&self, other: &Vec<JsonValue>) -> bool {
+        self.0 == other.as_slice()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_json;
+
+    #[test]
+    fn test_object_view() {
+        let value =
+            parse_json(r#"{"s": "x", "n": -3, "f": 1.5, "b": false, "a": [], "o": {}}"#).unwrap();
+        let object = value.as_object().unwrap();
+        assert_eq!(object.len(), 6);
+        assert_eq!(object.get_str("s"), Some("x"));
+        assert_eq!(object.get_i64("n"), Some(-3));
+        assert_eq!(object.get_u64("n"), None);
+        assert_eq!(object.get_f64("f"), Some(1.5));
+        assert_eq!(object.get_bool("b"), Some(false));
+        assert!(object.get_array("a").unwrap().is_empty());
+        assert!(object.get_object("o").unwrap().is_empty());
+        assert_eq!(object.get_str("n"), None);
+        assert_eq!(object.into_iter().count(), 6);
+        assert_eq!(object.require("s"), Ok(&JsonValue::from("x")));
+        assert_eq!(
+            object.require("missing").unwrap_err().location,
+            AccessLocation::Key("missing".into())
+        );
+    }
+
+    #[test]
+    fn test_require_typed_members() {
+        let value =
+            parse_json(r#"{"host": "db", "port": 5432, "replicas": [], "tls": {}}"#).unwrap();
+        let config = value.as_object().unwrap();
+        assert_eq!(config.require_str("host"), Ok("db"));
+        assert_eq!(config.require_i64("port"), Ok(5432));
+        assert_eq!(config.require_u64("port"), Ok(5432));
+        assert_eq!(config.require_f64("port"), Ok(5432.0));
+        assert!(config.require_array("replicas").unwrap().is_empty());
+        assert!(config.require_object("tls").unwrap().is_empty());
+
+        let error = config.require_bool("debug").unwrap_err();
+        assert_eq!(error.kind, AccessErrorKind::Missing);
+        assert_eq!(error.available_keys, ["host", "port", "replicas", "tls"]);
+        assert_eq!(
+            error.to_string(),
+            r#"Missing key "debug" (available: "host", "port", "replicas", "tls")"#
+        );
+
+        let error = config.require_str("port").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            r#"Key "port": expected string, found integer"#
+        );
+        assert_eq!(error.available_keys.len(), 4);
+
+        let empty = JsonObject::new();
+        let error = ObjectView::new(&empty).require("name").unwrap_err();
+        assert_eq!(error.to_string(), r#"Missing key "name""#);
+    }
+
+    #[test]
+    fn test_array_view_typed_iteration() {
+        let value = parse_json(r#"[1, 2, "three", 4.5]"#).unwrap();
+        let array = value.as_array().unwrap();
+        assert_eq!(array[0], JsonValue::from(1));
+        assert_eq!(array.first(), array.get(0));
+        let ints: Vec<_> = array.iter_i64().collect();
+        assert_eq!(ints[..2], [Ok(1), Ok(2)]);
+        let error = ints[2].clone().unwrap_err();
+        assert_eq!(error.to_string(), "Index 2: expected integer, found string");
+        assert_eq!(
+            array.iter_i64().nth(3).unwrap().unwrap_err().kind,
+            AccessErrorKind::WrongType {
+                expected: "integer",
+                found: "number"
+            }
+        );
+        assert_eq!(array.iter_f64().filter(Result::is_ok).count(), 3);
+        assert_eq!(
+            array.require(4).unwrap_err().to_string(),
+            "Index 4 out of bounds"
+        );
+    }
+}
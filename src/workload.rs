@@ -0,0 +1,262 @@
+//! Workloads - representative documents for measuring parser performance
+//!
+//! The documents are generated, not shipped, and the generators are
+//! deterministic: the same size always yields the same text, so timings
+//! from different runs, branches and machines can be compared. The
+//! criterion suite in `benches/` runs [`standard`]; the generators are
+//! public so applications can measure the shapes they care about.
+//!
+//! # Examples
+//!
+//! ```
+//! use json_parser_rs::workload;
+//!
+//! for workload in workload::standard() {
+//!     let value = workload.parse().unwrap();
+//!     assert!(!value.is_null(), "{}", workload.name);
+//! }
+//! let numbers = workload::numbers(1000);
+//! assert_eq!(json_parser_rs::parse_json(&numbers).unwrap().as_array().unwrap().len(), 1000);
+//! ```
+
+use std::fmt::Write;
+use std::time::{Duration, Instant};
+
+use crate::error::ParseError;
+use crate::json::JsonValue;
+use crate::options::DEFAULT_MAX_DEPTH;
+use crate::parse_json;
+
+/// A named input document
+#[derive(Debug, Clone)]
+pub struct Workload {
+    pub name: &'static str,
+    pub input: String,
+}
+
+impl Workload {
+    pub fn new(name: &'static str, input: String) -> Self {
+        Self { name, input }
+    }
+
+    /// Input size in bytes, for throughput figures
+    pub fn len(&self) -> usize {
+        self.input.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.input.is_empty()
+    }
+
+    pub fn parse(&self) -> Result<JsonValue, ParseError> {
+        parse_json(&self.input)
+    }
+
+    /// Average time of `iterations` calls of `parse`, for a quick check
+    /// without criterion
+    ///
+    /// # Panics
+    ///
+    /// If the input does not parse or `iterations` is zero.
+    pub fn time_parse(&self, iterations: u32) -> Duration {
+        assert!(iterations > 0, "iterations must be positive");
+        let start = Instant::now();
+        for _ in 0..iterations {
+            std::hint::black_box(self.parse().unwrap());
+        }
+        start.elapsed() / iterations
+    }
+}
+
+/// The benchmark suite's documents, each a few hundred KiB
+pub fn standard() -> Vec<Workload> {
+    vec![
+        Workload::new("numbers", numbers(50_000)),
+        Workload::new("strings", strings(5_000)),
+        Workload::new("nested", nested(DEFAULT_MAX_DEPTH - 1, 200)),
+        Workload::new("twitter", twitter(500)),
+    ]
+}
+
+/// Array of `count` integers and floats of varying magnitude and sign
+pub fn numbers(count: usize) -> String {
+    let mut random = Random::new(1);
+    let mut output = String::from("[");
+    for i in 0..count {
+        if i > 0 {
+            output.push(',');
+        }
+        let n = random.next();
+        let _ = match n % 4 {
+            0 => write!(output, "{}", n % 1000),
+            1 => write!(output, "-{}", n >> 12),
+            2 => write!(output, "{}.{:03}", n % 100_000, n % 1000),
+            _ => write!(output, "{}.{}e-{}", n % 10, n % 100_000, n % 20),
+        };
+    }
+    output.push(']');
+    output
+}
+
+/// Array of `count` objects dominated by string content: long text, escape
+/// sequences and non-ASCII characters
+pub fn strings(count: usize) -> String {
+    const WORDS: [&str; 8] = [
+        "lorem",
+        "ipsum",
+        "Grüße",
+        "naïve",
+        "日本語",
+        "tab\\t",
+        "q\\\"",
+        "x",
+    ];
+    let mut random = Random::new(2);
+    let mut output = String::from("[");
+    for i in 0..count {
+        if i > 0 {
+            output.push(',');
+        }
+        let words = 5 + random.next() as usize % 30;
+        let text: Vec<_> = (0..words)
+            .map(|_| WORDS[random.next() as usize % WORDS.len()])
+            .collect();
+        let _ = write!(
+            output,
+            concat!(
+                r#"{{"id":"item-{}","title":"{}","body":"{}","#,
+                r#""path":"C:\\data\\{}\\file.txt","emoji":"\ud83d\ude00"}}"#
+            ),
+            i,
+            text[..words.min(4)].join(" "),
+            text.join(" "),
+            i
+        );
+    }
+    output.push(']');
+    output
+}
+
+/// Array of `count` objects, each nested `depth` levels deep counting the
+/// array, with a few members on every level
+///
+/// # Panics
+///
+/// If `depth` is less than 2.
+pub fn nested(depth: usize, count: usize) -> String {
+    assert!(depth >= 2, "depth must be at least 2");
+    let mut document = String::from("{\"leaf\":true}");
+    for level in (1..depth - 1).rev() {
+        document = format!(
+            r#"{{"level":{},"name":"node{}","tags":["a","b"],"child":{}}}"#,
+            level, level, document
+        );
+    }
+    let mut output = String::from("[");
+    for i in 0..count {
+        if i > 0 {
+            output.push(',');
+        }
+        output.push_str(&document);
+    }
+    output.push(']');
+    output
+}
+
+/// `{"statuses": [...]}` with `count` tweets shaped like the Twitter API
+/// responses of the well-known `twitter.json` benchmark file: nested users,
+/// entities, nulls, booleans, large ids and mixed-script text
+pub fn twitter(count: usize) -> String {
+    const TEXTS: [&str; 4] = [
+        "Just landed in Berlin! #travel",
+        "@rustlang 2024 edition is out \\ud83c\\udf89 https://t.co/abc",
+        "RT @json: Parsing 1GB/s is the new normal",
+        "東京は今日も晴れです。散歩に行こう",
+    ];
+    let mut random = Random::new(3);
+    let mut output = String::from(r#"{"statuses":["#);
+    for i in 0..count {
+        if i > 0 {
+            output.push(',');
+        }
+        let id = 505_874_924_095_815_681 + random.next() % 1_000_000;
+        let user = random.next() % 100_000;
+        let text = TEXTS[i % TEXTS.len()];
+        let _ = write!(
+            output,
+            concat!(
+                r#"{{"metadata":{{"result_type":"recent","iso_language_code":"en"}},"#,
+                r#""created_at":"Sun Aug 31 00:29:15 +0000 2014","id":{},"id_str":"{}","#,
+                r#""text":"{}","truncated":false,"in_reply_to_status_id":null,"#,
+                r#""user":{{"id":{},"name":"user {}","screen_name":"u{}","location":"","#,
+                r#""description":"Lives on the internet","url":null,"followers_count":{},"#,
+                r#""friends_count":{},"verified":{},"profile_background_color":"C0DEED"}},"#,
+                r#""geo":null,"coordinates":null,"place":null,"retweet_count":{},"#,
+                r#""favorite_count":{},"entities":{{"hashtags":[{{"text":"travel","#,
+                r#""indices":[20,27]}}],"urls":[],"user_mentions":[{{"screen_name":"json","#,
+                r#""id":{},"indices":[3,8]}}]}},"favorited":false,"retweeted":{},"lang":"en"}}"#
+            ),
+            id,
+            id,
+            text,
+            user,
+            user,
+            user,
+            random.next() % 10_000,
+            random.next() % 1_000,
+            user.is_multiple_of(7),
+            random.next() % 50,
+            random.next() % 200,
+            random.next() % 100_000,
+            i.is_multiple_of(3)
+        );
+    }
+    output.push_str("]}");
+    output
+}
+
+/// Small deterministic generator (64-bit LCG, high bits), so workloads do
+/// not depend on a random number crate or the platform
+struct Random(u64);
+
+impl Random {
+    fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 = self
+            .0
+            .wrapping_mul(6_364_136_223_846_793_005)
+            .wrapping_add(1_442_695_040_888_963_407);
+        self.0 >> 33
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_workloads_are_valid_and_deterministic() {
+        for workload in standard() {
+            let value = workload
+                .parse()
+                .unwrap_or_else(|e| panic!("{}: {}", workload.name, e));
+            assert!(workload.len() > 100_000, "{}", workload.name);
+            assert!(value.as_array().or(value["statuses"].as_array()).is_some());
+        }
+        assert_eq!(numbers(100), numbers(100));
+        assert_eq!(twitter(3), twitter(3));
+
+        let deep = parse_json(&nested(DEFAULT_MAX_DEPTH, 1)).unwrap();
+        let mut depth = 1;
+        let mut node = &deep[0];
+        while let Some(child) = node.get("child") {
+            node = child;
+            depth += 1;
+        }
+        assert_eq!(depth + 1, DEFAULT_MAX_DEPTH);
+        assert!(parse_json(&nested(DEFAULT_MAX_DEPTH + 1, 1)).is_err());
+    }
+}
@@ -0,0 +1,449 @@
+//! Workspace - many named documents that refer to each other
+//!
+//! Schema bundles and OpenAPI descriptions are often split into files that
+//! point into each other with `{"$ref": "common.json#/$defs/id"}`. A
+//! [`Workspace`] holds such a set of documents by name (a path or URI),
+//! parses each one as it is added and follows `$ref`s between them.
+//! Reference paths are resolved against the name of the document they occur
+//! in, like relative URLs: `../common.json` from `api/pets.json` is
+//! `common.json`.
+//!
+//! Dereferenced documents are cached. Changing or removing a document drops
+//! the cached results of every document that refers to it, directly or
+//! through others, and reports their names, so an editor knows which
+//! diagnostics to refresh. Document names and reference targets are
+//! interned: each distinct name is stored once and shared by the
+//! dependency graph.
+//!
+//! # Examples
+//!
+//! ```
+//! use json_parser_rs::json;
+//! use json_parser_rs::workspace::Workspace;
+//!
+//! let mut workspace = Workspace::new();
+//! workspace.insert("defs/common.json", r#"{"id": {"type": "integer"}}"#);
+//! let pet = json!({"properties": {"id": {"$ref": "../defs/common.json#/id"}}});
+//! workspace.insert("api/pet.json", pet.to_string());
+//!
+//! let pet = workspace.dereference("api/pet.json").unwrap();
+//! assert_eq!(pet["properties"]["id"], json!({"type": "integer"}));
+//!
+//! let changed = workspace.insert("defs/common.json", r#"{"id": {"type": "string"}}"#);
+//! assert_eq!(changed.len(), 2);
+//! let pet = workspace.dereference("api/pet.json").unwrap();
+//! assert_eq!(pet["properties"]["id"], json!({"type": "string"}));
+//! ```
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fmt;
+use std::sync::Arc;
+
+use crate::error::ParseError;
+use crate::json::JsonValue;
+use crate::options::ParserOptions;
+use crate::parser::Parser;
+use crate::reference::{Lookup, RefError, dereference_with, resolve};
+
+/// Why a document cannot be dereferenced
+#[derive(Debug, Clone, PartialEq)]
+pub enum WorkspaceError {
+    /// No document with this name is in the workspace
+    NotFound(Arc<str>),
+    /// The document does not parse
+    Parse {
+        document: Arc<str>,
+        error: ParseError,
+    },
+    /// A `$ref` in `document` cannot be followed
+    Ref { document: Arc<str>, error: RefError },
+}
+
+/// `document: message`
+impl fmt::Display for WorkspaceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WorkspaceError::NotFound(document) => {
+                write!(f, "{}: Document not in workspace", document)
+            }
+            WorkspaceError::Parse { document, error } => write!(f, "{}: {}", document, error),
+            WorkspaceError::Ref { document, error } => write!(f, "{}: {}", document, error),
+        }
+    }
+}
+
+impl std::error::Error for WorkspaceError {}
+
+#[derive(Debug)]
+struct Document {
+    source: String,
+    parsed: Result<JsonValue, ParseError>,
+    /// Incremented by every change, starting at 1
+    version: u64,
+    /// Names of the documents this one refers to, present or not
+    references: BTreeSet<Arc<str>>,
+}
+
+/// A set of named documents, see the [module documentation](self)
+#[derive(Debug, Default)]
+pub struct Workspace {
+    options: ParserOptions,
+    documents: BTreeMap<Arc<str>, Document>,
+    /// Every name seen, as documents or as reference targets
+    names: HashSet<Arc<str>>,
+    dereferenced: HashMap<Arc<str>, Result<JsonValue, WorkspaceError>>,
+}
+
+impl Workspace {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A workspace parsing its documents with `options`
+    pub fn with_options(options: ParserOptions) -> Self {
+        Self {
+            options,
+            ..Self::default()
+        }
+    }
+
+    /// The shared copy of `name`
+    pub fn intern(&mut self, name: &str) -> Arc<str> {
+        if let Some(interned) = self.names.get(name) {
+            return interned.clone();
+        }
+        let interned: Arc<str> = Arc::from(name);
+        self.names.insert(interned.clone());
+        interned
+    }
+
+    /// Add or replace the document `name`, returning the names of all
+    /// documents whose dereferenced form may have changed: `name` and
+    /// every document referring to it, sorted
+    ///
+    /// A document that does not parse is kept with its error, see
+    /// [`Workspace::error`].
+    pub fn insert(&mut self, name: &str, source: impl Into<String>) -> Vec<Arc<str>> {
+        let source = source.into();
+        let parsed = Parser::with_options(&source, self.options).and_then(|mut p| p.parse());
+        let mut references = BTreeSet::new();
+        if let Ok(value) = &parsed {
+            let mut found = Vec::new();
+            collect_references(value, &mut found);
+            for reference in found {
+                let (path, _) = reference.split_once('#').unwrap_or((reference, ""));
+                if !path.is_empty() {
+                    references.insert(self.intern(&join_name(name, path)));
+                }
+            }
+        }
+
+        let name = self.intern(name);
+        let version = self.documents.get(&name).map_or(0, |d| d.version) + 1;
+        self.documents.insert(
+            name.clone(),
+            Document {
+                source,
+                parsed,
+                version,
+                references,
+            },
+        );
+        self.invalidate(name)
+    }
+
+    /// Remove the document `name`, returning the names of the documents
+    /// whose dereferenced form may have changed as for
+    /// [`Workspace::insert`]; empty if there was no such document
+    pub fn remove(&mut self, name: &str) -> Vec<Arc<str>> {
+        match self.documents.remove_entry(name) {
+            Some((name, _)) => self.invalidate(name),
+            None => Vec::new(),
+        }
+    }
+
+    /// Drop the cached results of `name` and the documents that depend on
+    /// it, returning their names
+    fn invalidate(&mut self, name: Arc<str>) -> Vec<Arc<str>> {
+        let mut affected = BTreeSet::from([name.clone()]);
+        let mut pending = vec![name];
+        while let Some(changed) = pending.pop() {
+            for (dependent, document) in &self.documents {
+                if document.references.contains(&changed) && affected.insert(dependent.clone()) {
+                    pending.push(dependent.clone());
+                }
+            }
+        }
+        for name in &affected {
+            self.dereferenced.remove(name);
+        }
+        affected.into_iter().collect()
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.documents.contains_key(name)
+    }
+
+    /// Names of the documents, sorted
+    pub fn names(&self) -> impl Iterator<Item = &Arc<str>> {
+        self.documents.keys()
+    }
+
+    pub fn source(&self, name: &str) -> Option<&str> {
+        Some(&self.documents.get(name)?.source)
+    }
+
+    /// Counts the inserts since the document was added, starting at 1, so
+    /// callers can tell stale results apart
+    pub fn version(&self, name: &str) -> Option<u64> {
+        Some(self.documents.get(name)?.version)
+    }
+
+    /// The parsed document; `None` if it is missing or does not parse
+    pub fn get(&self, name: &str) -> Option<&JsonValue> {
+        self.documents.get(name)?.parsed.as_ref().ok()
+    }
+
+    /// Why the document does not parse
+    pub fn error(&self, name: &str) -> Option<&ParseError> {
+        self.documents.get(name)?.parsed.as_ref().err()
+    }
+
+    /// Names of the documents `name` refers to, sorted; they need not be
+    /// in the workspace
+    pub fn references(&self, name: &str) -> impl Iterator<Item = &Arc<str>> {
+        self.documents
+            .get(name)
+            .into_iter()
+            .flat_map(|document| &document.references)
+    }
+
+    /// The value `reference` points to when it occurs in the document
+    /// `base`, with the name of the document holding it
+    pub fn resolve(&self, base: &str, reference: &str) -> Option<(&Arc<str>, &JsonValue)> {
+        let (path, fragment) = reference.split_once('#').unwrap_or((reference, ""));
+        let target = if path.is_empty() {
+            base.to_string()
+        } else {
+            join_name(base, path)
+        };
+        let (name, document) = self.documents.get_key_value(target.as_str())?;
+        let value = document.parsed.as_ref().ok()?;
+        Some((name, resolve(value, &format!("#{}", fragment))?))
+    }
+
+    /// The document `name` with every `$ref` replaced by its target, in
+    /// this or another document
+    ///
+    /// Recursive references are left in place, as by
+    /// [`reference::dereference`](crate::reference::dereference); their
+    /// text is relative to the document they were written in. The result
+    /// is cached until `name` or a document it refers to changes.
+    pub fn dereference(&mut self, name: &str) -> Result<&JsonValue, WorkspaceError> {
+        if !self.dereferenced.contains_key(name) {
+            let result = self.dereference_uncached(name);
+            let name = self.intern(name);
+            self.dereferenced.insert(name, result);
+        }
+        self.dereferenced[name].as_ref().map_err(Clone::clone)
+    }
+
+    fn dereference_uncached(&self, name: &str) -> Result<JsonValue, WorkspaceError> {
+        let Some((name, document)) = self.documents.get_key_value(name) else {
+            return Err(WorkspaceError::NotFound(Arc::from(name)));
+        };
+        let value = document
+            .parsed
+            .as_ref()
+            .map_err(|error| WorkspaceError::Parse {
+                document: name.clone(),
+                error: error.clone(),
+            })?;
+        let lookup = |base: &str, reference: &str| self.lookup(base, reference);
+        dereference_with(value, name, &lookup as &Lookup).map_err(|(document, error)| {
+            WorkspaceError::Ref {
+                document: Arc::from(document),
+                error,
+            }
+        })
+    }
+
+    /// The [`Lookup`] for references in this workspace
+    fn lookup<'a>(
+        &'a self,
+        base: &str,
+        reference: &str,
+    ) -> Result<(&'a str, &'a JsonValue), String> {
+        let (path, _) = reference.split_once('#').unwrap_or((reference, ""));
+        let target = join_name(base, path);
+        if !path.is_empty() {
+            match self.documents.get(target.as_str()) {
+                None => {
+                    return Err(format!(
+                        "$ref \"{}\": {} is not in the workspace",
+                        reference, target
+                    ));
+                }
+                Some(document) if document.parsed.is_err() => {
+                    return Err(format!("$ref \"{}\": {} does not parse", reference, target));
+                }
+                Some(_) => {}
+            }
+        }
+        self.resolve(base, reference)
+            .map(|(name, value)| (&**name, value))
+            .ok_or_else(|| format!("Cannot resolve $ref \"{}\"", reference))
+    }
+}
+
+/// The `$ref` strings in `value`, in document order
+fn collect_references<'a>(value: &'a JsonValue, found: &mut Vec<&'a str>) {
+    match value.untagged() {
+        JsonValue::Object(object) => {
+            if let Some(reference) = object.get("$ref").and_then(JsonValue::as_str) {
+                found.push(reference);
+            }
+            for (_, member) in object {
+                collect_references(member, found);
+            }
+        }
+        JsonValue::Array(elements) => {
+            for element in elements {
+                collect_references(element, found);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// The name of `path` as seen from the document `base`: relative paths
+/// are taken from `base`'s directory and `.` and `..` segments removed
+fn join_name(base: &str, path: &str) -> String {
+    if path.is_empty() {
+        return base.to_string();
+    }
+    let joined = if path.starts_with('/') || path.contains("://") {
+        path.to_string()
+    } else {
+        match base.rfind('/') {
+            Some(slash) => format!("{}{}", &base[..=slash], path),
+            None => path.to_string(),
+        }
+    };
+    // Keep a scheme and authority (`https://host`) out of the normalization
+    let (prefix, rest) = match joined.find("://") {
+        Some(at) => {
+            let authority_end = joined[at + 3..]
+                .find('/')
+                .map_or(joined.len(), |i| at + 3 + i);
+            joined.split_at(authority_end)
+        }
+        None => ("", joined.as_str()),
+    };
+    let mut segments: Vec<&str> = Vec::new();
+    for segment in rest.split('/') {
+        match segment {
+            "." => {}
+            ".." if segments
+                .last()
+                .is_some_and(|last| !last.is_empty() && *last != "..") =>
+            {
+                segments.pop();
+            }
+            _ => segments.push(segment),
+        }
+    }
+    format!("{}{}", prefix, segments.join("/"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json;
+
+    #[test]
+    fn test_join_name() {
+        let cases = [
+            ("api/pets.json", "common.json", "api/common.json"),
+            ("api/pets.json", "../common.json", "common.json"),
+            ("api/v1/pets.json", "./../defs/./a.json", "api/defs/a.json"),
+            ("pets.json", "../a.json", "../a.json"),
+            ("/srv/api/pets.json", "/etc/a.json", "/etc/a.json"),
+            ("/srv/api/pets.json", "../a.json", "/srv/a.json"),
+            (
+                "https://x.org/s/a.json",
+                "../b.json",
+                "https://x.org/b.json",
+            ),
+            ("a.json", "https://y.org/b.json", "https://y.org/b.json"),
+        ];
+        for (base, path, joined) in cases {
+            assert_eq!(join_name(base, path), joined, "{} + {}", base, path);
+        }
+    }
+
+    #[test]
+    fn test_cross_document_dereference() {
+        let mut workspace = Workspace::new();
+        workspace.insert(
+            "a.json",
+            json!({
+                "user": {"$ref": "b.json#/user", "description": "Owner"},
+                "local": {"$ref": "#/defs/x"},
+                "defs": {"x": 1}
+            })
+            .to_string(),
+        );
+        workspace.insert(
+            "b.json",
+            json!({"user": {"properties": {
+                "friends": {"items": {"$ref": "#/user"}},
+                "id": {"$ref": "c.json"}
+            }}})
+            .to_string(),
+        );
+        assert_eq!(
+            workspace.dereference("a.json").unwrap_err().to_string(),
+            "b.json: /user/properties/id: $ref \"c.json\": c.json is not in the workspace"
+        );
+
+        let changed = workspace.insert("c.json", "{\"type\": \"integer\"}");
+        assert_eq!(changed, ["a.json", "b.json", "c.json"].map(Arc::from));
+        let a = workspace.dereference("a.json").unwrap().clone();
+        assert_eq!(
+            a["user"],
+            json!({
+                "properties": {"friends": {"items": {"$ref": "#/user"}}, "id": {"type": "integer"}},
+                "description": "Owner"
+            })
+        );
+        assert_eq!(a["local"], json!(1));
+        let (name, value) = workspace.resolve("a.json", "c.json#/type").unwrap();
+        assert_eq!((&**name, value), ("c.json", &json!("integer")));
+
+        // A change reaches a.json through b.json
+        assert_eq!(workspace.version("c.json"), Some(1));
+        workspace.insert("c.json", "{\"type\": ");
+        assert_eq!(workspace.version("c.json"), Some(2));
+        assert!(workspace.error("c.json").is_some());
+        assert_eq!(
+            workspace.dereference("a.json").unwrap_err().to_string(),
+            "b.json: /user/properties/id: $ref \"c.json\": c.json does not parse"
+        );
+        assert!(matches!(
+            workspace.dereference("c.json"),
+            Err(WorkspaceError::Parse { .. })
+        ));
+
+        assert_eq!(workspace.remove("a.json"), [Arc::from("a.json")]);
+        assert!(workspace.remove("a.json").is_empty());
+        assert!(matches!(
+            workspace.dereference("a.json"),
+            Err(WorkspaceError::NotFound(_))
+        ));
+        let names: Vec<_> = workspace.names().map(|name| &**name).collect();
+        assert_eq!(names, ["b.json", "c.json"]);
+        let name = workspace.intern("c.json");
+        assert!(Arc::ptr_eq(&name, workspace.names().last().unwrap()));
+    }
+}
@@ -0,0 +1,699 @@
+//! Writer - pretty printing with line width control
+//!
+//! `to_json_string` puts a whole document on one line. `PrettyPrinter` gives
+//! every array element and object member its own line and keeps an eye on
+//! the line width: lines wider than a threshold can be reported, and long
+//! string values can be wrapped onto continuation lines. Widths are measured
+//! in characters of the escaped text as written, and wrapping never splits an
+//! escape sequence, so a base64 blob of several megabytes becomes a block of
+//! lines that a diff tool can still display.
+//!
+//! Object members are written in insertion order by default. For output
+//! that should not depend on how the tree was built, such as generated files
+//! kept under version control, `PrettyPrinter::key_order` sorts them.
+
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::{fmt, io};
+
+use crate::json::{JsonValue, escape, escape_ascii, push_unicode_escape};
+use crate::object::JsonObject;
+use crate::pointer::JsonPointer;
+
+/// Marks the end of a line that continues a wrapped string
+///
+/// JSON has no line continuation, so wrapped output is meant for reading and
+/// diffing only; it does not parse back.
+pub const WRAP_INDICATOR: char = '↩';
+
+/// Configurable pretty printer
+///
+/// # Examples
+///
+/// ```
+/// use json_parser_rs::parse_json;
+/// use json_parser_rs::writer::PrettyPrinter;
+///
+/// let value = parse_json(r#"{"id": 7, "data": "aGVsbG8gd29ybGQ="}"#).unwrap();
+/// let printer = PrettyPrinter::new().max_width(Some(20));
+///
+/// let long = printer.long_lines(&value);
+/// assert_eq!(long.len(), 1);
+/// assert_eq!(long[0].pointer, "/data");
+/// assert_eq!(long[0].line, 3);
+///
+/// assert_eq!(
+///     printer.wrap_strings(true).print(&value),
+///     "{\n  \"id\": 7,\n  \"data\": \"aGVsbG8g↩\n    d29ybGQ=\"\n}"
+/// );
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PrettyPrinter {
+    indent: usize,
+    max_width: Option<usize>,
+    wrap_strings: bool,
+    minify: bool,
+    key_order: KeyOrder,
+    escape_non_ascii: bool,
+}
+
+/// Order in which object members are written
+///
+/// # Examples
+///
+/// ```
+/// use json_parser_rs::parse_json;
+/// use json_parser_rs::writer::{KeyOrder, PrettyPrinter};
+///
+/// let value = parse_json(r#"{"b": 1, "id": 2, "a": {"y": 3, "x": 4}}"#).unwrap();
+/// let sorted = PrettyPrinter::new().minify(true).key_order(KeyOrder::Sorted);
+/// assert_eq!(sorted.print(&value), r#"{"a":{"x":4,"y":3},"b":1,"id":2}"#);
+///
+/// // "id" first, the rest alphabetically
+/// let id_first = KeyOrder::By(|a, b| (a != "id").cmp(&(b != "id")).then(a.cmp(b)));
+/// assert_eq!(
+///     sorted.key_order(id_first).print(&value),
+///     r#"{"id":2,"a":{"x":4,"y":3},"b":1}"#
+/// );
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub enum KeyOrder {
+    /// As stored in the object (the order of the source document for parsed
+    /// values)
+    #[default]
+    Insertion,
+    /// Lexicographically by key, comparing UTF-8 bytes
+    Sorted,
+    /// By a comparator; members with equal keys keep their relative order
+    By(fn(&str, &str) -> Ordering),
+}
+
+/// Comparators are equal if they are the same function (as far as
+/// `std::ptr::fn_addr_eq` can tell)
+impl PartialEq for KeyOrder {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (KeyOrder::Insertion, KeyOrder::Insertion) | (KeyOrder::Sorted, KeyOrder::Sorted) => {
+                true
+            }
+            (KeyOrder::By(a), KeyOrder::By(b)) => std::ptr::fn_addr_eq(*a, *b),
+            _ => false,
+        }
+    }
+}
+
+impl Eq for KeyOrder {}
+
+impl KeyOrder {
+    /// Call `write` with the index, key and value of each member, in order
+    fn for_each_member(
+        self,
+        object: &JsonObject,
+        mut write: impl FnMut(usize, &String, &JsonValue) -> fmt::Result,
+    ) -> fmt::Result {
+        let compare: fn(&str, &str) -> Ordering = match self {
+            KeyOrder::Insertion => {
+                return object
+                    .iter()
+                    .enumerate()
+                    .try_for_each(|(i, (key, member))| write(i, key, member));
+            }
+            KeyOrder::Sorted => str::cmp,
+            KeyOrder::By(compare) => compare,
+        };
+        let mut members: Vec<&(String, JsonValue)> = object.as_slice().iter().collect();
+        members.sort_by(|a, b| compare(&a.0, &b.0));
+        members
+            .into_iter()
+            .enumerate()
+            .try_for_each(|(i, (key, member))| write(i, key, member))
+    }
+}
+
+/// A line wider than the printer's `max_width`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LongLine {
+    /// JSON Pointer to the value written on the line
+    pub pointer: String,
+    /// 1-based line number in the pretty-printed output
+    pub line: usize,
+    /// Width of the line in characters
+    pub width: usize,
+}
+
+impl Default for PrettyPrinter {
+    fn default() -> Self {
+        Self {
+            indent: 2,
+            max_width: None,
+            wrap_strings: false,
+            minify: false,
+            key_order: KeyOrder::Insertion,
+            escape_non_ascii: false,
+        }
+    }
+}
+
+impl PrettyPrinter {
+    /// Printer indenting by two spaces, without a width limit
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Spaces per nesting level
+    pub fn indent(mut self, spaces: usize) -> Self {
+        self.indent = spaces;
+        self
+    }
+
+    /// Width threshold for `long_lines` and `wrap_strings` (`None`: no limit)
+    pub fn max_width(mut self, max_width: Option<usize>) -> Self {
+        self.max_width = max_width;
+        self
+    }
+
+    /// Wrap string values that would make their line wider than `max_width`
+    ///
+    /// Each wrapped line ends in [`WRAP_INDICATOR`] and continues one level
+    /// deeper. Lines that are too wide for other reasons, such as long keys,
+    /// are left alone.
+    pub fn wrap_strings(mut self, wrap: bool) -> Self {
+        self.wrap_strings = wrap;
+        self
+    }
+
+    /// Write everything on one line without insignificant whitespace (as
+    /// `to_minified_string`); indentation and width settings are ignored
+    pub fn minify(mut self, minify: bool) -> Self {
+        self.minify = minify;
+        self
+    }
+
+    /// Order of object members, insertion order by default
+    pub fn key_order(mut self, order: KeyOrder) -> Self {
+        self.key_order = order;
+        self
+    }
+
+    /// Write every non-ASCII character as a `\uXXXX` escape, for consumers
+    /// that only accept ASCII
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use json_parser_rs::json;
+    /// use json_parser_rs::writer::PrettyPrinter;
+    ///
+    /// let printer = PrettyPrinter::new().minify(true).escape_non_ascii(true);
+    /// assert_eq!(printer.print(&json!({"café": "☕"})), r#"{"caf\u00e9":"\u2615"}"#);
+    /// ```
+    pub fn escape_non_ascii(mut self, escape_non_ascii: bool) -> Self {
+        self.escape_non_ascii = escape_non_ascii;
+        self
+    }
+
+    /// Escaped content of a string value or key
+    fn escape<'s>(&self, s: &'s str) -> Cow<'s, str> {
+        if self.escape_non_ascii {
+            escape_ascii(s)
+        } else {
+            escape(s)
+        }
+    }
+
+    /// Content of a raw string, which is already escaped except for its
+    /// non-ASCII characters
+    fn raw<'s>(&self, raw: &'s str) -> Cow<'s, str> {
+        if !self.escape_non_ascii || raw.is_ascii() {
+            return Cow::Borrowed(raw);
+        }
+        let mut escaped = String::with_capacity(raw.len() + 8);
+        for ch in raw.chars() {
+            if ch.is_ascii() {
+                escaped.push(ch);
+            } else {
+                push_unicode_escape(&mut escaped, ch);
+            }
+        }
+        Cow::Owned(escaped)
+    }
+
+    /// Pretty-print `value`, without a trailing newline
+    pub fn print(&self, value: &JsonValue) -> String {
+        let mut out = String::new();
+        self.write_fmt(value, &mut out)
+            .expect("writing to a String cannot fail");
+        out
+    }
+
+    /// Pretty-print `value` straight into `w`, without building the output
+    /// in memory first
+    pub fn write_to<W: io::Write>(&self, value: &JsonValue, w: &mut W) -> io::Result<()> {
+        write_io(w, |out| self.write_fmt(value, out))
+    }
+
+    fn write_fmt<W: fmt::Write>(&self, value: &JsonValue, out: &mut W) -> fmt::Result {
+        if self.minify {
+            write_flat(value, out, Separators::MINIFIED, self)
+        } else {
+            Writer::new(self, out).value(value, 0, "", "")
+        }
+    }
+
+    /// Lines of the (unwrapped) output that exceed `max_width`
+    pub fn long_lines(&self, value: &JsonValue) -> Vec<LongLine> {
+        let unwrapped = Self {
+            wrap_strings: false,
+            minify: false,
+            ..*self
+        };
+        let mut writer = Writer::new(&unwrapped, Discard);
+        writer
+            .value(value, 0, "", "")
+            .expect("discarding output cannot fail");
+        writer.long_lines
+    }
+}
+
+impl JsonValue {
+    /// Multi-line representation indented by two spaces
+    ///
+    /// Shorthand for `PrettyPrinter::new().print(self)`.
+    pub fn to_pretty_string(&self) -> String {
+        PrettyPrinter::new().print(self)
+    }
+
+    /// Write the compact representation (as `to_json_string`) to `w`
+    ///
+    /// The output is streamed; no intermediate strings are built, so this
+    /// suits large documents going to files or sockets. Wrap unbuffered
+    /// writers in `io::BufWriter`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use json_parser_rs::parse_json;
+    ///
+    /// let value = parse_json(r#"{"a": [1, true]}"#).unwrap();
+    /// let mut out = Vec::new();
+    /// value.write_json(&mut out).unwrap();
+    /// assert_eq!(out, br#"{"a": [1, true]}"#);
+    /// ```
+    pub fn write_json<W: io::Write>(&self, w: &mut W) -> io::Result<()> {
+        write_io(w, |out| {
+            write_flat(self, out, Separators::COMPACT, &PrettyPrinter::new())
+        })
+    }
+
+    /// Write the pretty representation (as `to_pretty_string`) to `w`
+    pub fn write_json_pretty<W: io::Write>(&self, w: &mut W) -> io::Result<()> {
+        PrettyPrinter::new().write_to(self, w)
+    }
+
+    /// Representation without any insignificant whitespace
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use json_parser_rs::parse_json;
+    ///
+    /// let value = parse_json("{\n  \"a\": [1, true]\n}").unwrap();
+    /// assert_eq!(value.to_minified_string(), r#"{"a":[1,true]}"#);
+    /// ```
+    pub fn to_minified_string(&self) -> String {
+        let mut out = String::new();
+        write_flat(self, &mut out, Separators::MINIFIED, &PrettyPrinter::new())
+            .expect("writing to a String cannot fail");
+        out
+    }
+
+    /// Write the minified representation (as `to_minified_string`) to `w`
+    pub fn write_json_minified<W: io::Write>(&self, w: &mut W) -> io::Result<()> {
+        write_io(w, |out| {
+            write_flat(self, out, Separators::MINIFIED, &PrettyPrinter::new())
+        })
+    }
+}
+
+/// Compact JSON, as `to_json_string`; the alternate flag (`{:#}`) selects
+/// the pretty representation
+impl fmt::Display for JsonValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if f.alternate() {
+            Writer::new(&PrettyPrinter::new(), f).value(self, 0, "", "")
+        } else {
+            write_flat(self, f, Separators::COMPACT, &PrettyPrinter::new())
+        }
+    }
+}
+
+/// Separators of single-line output: after elements and after keys
+#[derive(Clone, Copy)]
+struct Separators(&'static str, &'static str);
+
+impl Separators {
+    const COMPACT: Self = Self(", ", ": ");
+    const MINIFIED: Self = Self(",", ":");
+}
+
+fn write_flat<W: fmt::Write>(
+    value: &JsonValue,
+    out: &mut W,
+    separators: Separators,
+    printer: &PrettyPrinter,
+) -> fmt::Result {
+    match value {
+        JsonValue::Null => out.write_str("null"),
+        JsonValue::Boolean(b) => write!(out, "{}", b),
+        JsonValue::Number(n) => write!(out, "{}", n),
+        JsonValue::String(s) => write!(out, "\"{}\"", printer.escape(s)),
+        JsonValue::RawString(raw) => write!(out, "\"{}\"", printer.raw(raw)),
+        JsonValue::Array(elements) => {
+            out.write_char('[')?;
+            for (i, element) in elements.iter().enumerate() {
+                if i > 0 {
+                    out.write_str(separators.0)?;
+                }
+                write_flat(element, out, separators, printer)?;
+            }
+            out.write_char(']')
+        }
+        JsonValue::Object(object) => {
+            out.write_char('{')?;
+            printer
+                .key_order
+                .for_each_member(object, |i, key, member| {
+                    if i > 0 {
+                        out.write_str(separators.0)?;
+                    }
+                    write!(out, "\"{}\"{}", printer.escape(key), separators.1)?;
+                    write_flat(member, out, separators, printer)
+                })?;
+            out.write_char('}')
+        }
+        JsonValue::Extension(tag, payload) => {
+            write!(out, "{{\"{}\"{}", printer.escape(tag), separators.1)?;
+            write_flat(payload, out, separators, printer)?;
+            out.write_char('}')
+        }
+        #[cfg(feature = "tagged")]
+        JsonValue::Tagged(_, payload) => write_flat(payload, out, separators, printer),
+    }
+}
+
+/// Run `write` against `w`, passing through the `io::Error` that made it fail
+fn write_io<W: io::Write>(
+    w: &mut W,
+    write: impl FnOnce(&mut IoAdapter<'_, W>) -> fmt::Result,
+) -> io::Result<()> {
+    let mut adapter = IoAdapter {
+        inner: w,
+        error: None,
+    };
+    match write(&mut adapter) {
+        Ok(()) => Ok(()),
+        Err(fmt::Error) => Err(adapter
+            .error
+            .unwrap_or_else(|| io::Error::other("formatting failed"))),
+    }
+}
+
+struct IoAdapter<'w, W> {
+    inner: &'w mut W,
+    error: Option<io::Error>,
+}
+
+impl<W: io::Write> fmt::Write for IoAdapter<'_, W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.inner.write_all(s.as_bytes()).map_err(|error| {
+            self.error = Some(error);
+            fmt::Error
+        })
+    }
+}
+
+/// Sink for walks that only collect `long_lines`
+struct Discard;
+
+impl fmt::Write for Discard {
+    fn write_str(&mut self, _: &str) -> fmt::Result {
+        Ok(())
+    }
+}
+
+struct Writer<'p, W> {
+    printer: &'p PrettyPrinter,
+    out: W,
+    /// Number of lines written so far
+    lines: usize,
+    path: Vec<String>,
+    long_lines: Vec<LongLine>,
+}
+
+impl<'p, W: fmt::Write> Writer<'p, W> {
+    fn new(printer: &'p PrettyPrinter, out: W) -> Self {
+        Self {
+            printer,
+            out,
+            lines: 0,
+            path: Vec::new(),
+            long_lines: Vec::new(),
+        }
+    }
+
+    /// Write `value` at nesting level `depth`; `prefix` (the member key) and
+    /// `suffix` (the separating comma) go on the same line
+    fn value(
+        &mut self,
+        value: &JsonValue,
+        depth: usize,
+        prefix: &str,
+        suffix: &str,
+    ) -> fmt::Result {
+        let indentation = " ".repeat(depth * self.printer.indent);
+        match value {
+            JsonValue::Array(elements) if !elements.is_empty() => {
+                self.line_of(format!("{}{}[", indentation, prefix))?;
+                for (i, element) in elements.iter().enumerate() {
+                    self.path.push(i.to_string());
+                    self.value(element, depth + 1, "", comma(i, elements.len()))?;
+                    self.path.pop();
+                }
+                self.line_of(format!("{}]{}", indentation, suffix))
+            }
+            JsonValue::Object(object) if !object.is_empty() => {
+                self.line_of(format!("{}{}{{", indentation, prefix))?;
+                self.printer
+                    .key_order
+                    .for_each_member(object, |i, key, member| {
+                        self.path.push(key.clone());
+                        let key = format!("\"{}\": ", self.printer.escape(key));
+                        self.value(member, depth + 1, &key, comma(i, object.len()))?;
+                        self.path.pop();
+                        Ok(())
+                    })?;
+                self.line_of(format!("{}}}{}", indentation, suffix))
+            }
+            JsonValue::Extension(tag, payload) => {
+                self.line_of(format!("{}{}{{", indentation, prefix))?;
+                self.path.push(tag.clone());
+                let key = format!("\"{}\": ", self.printer.escape(tag));
+                self.value(payload, depth + 1, &key, "")?;
+                self.path.pop();
+                self.line_of(format!("{}}}{}", indentation, suffix))
+            }
+            #[cfg(feature = "tagged")]
+            JsonValue::Tagged(_, payload) => self.value(payload, depth, prefix, suffix),
+            JsonValue::String(_) | JsonValue::RawString(_) => {
+                let content = match value {
+                    JsonValue::String(s) => self.printer.escape(s),
+                    _ => self.printer.raw(value.as_raw_str().unwrap_or_default()),
+                };
+                let line = format!("{}{}\"{}\"{}", indentation, prefix, content, suffix);
+                match self.printer.max_width {
+                    Some(max) if self.printer.wrap_strings && width(&line) > max => {
+                        let head = format!("{}{}\"", indentation, prefix);
+                        let continuation = " ".repeat((depth + 1) * self.printer.indent);
+                        self.wrapped(&head, &content, &continuation, suffix, max)
+                    }
+                    _ => self.line_of(line),
+                }
+            }
+            scalar => self.line_of(format!("{}{}{}{}", indentation, prefix, scalar, suffix)),
+        }
+    }
+
+    /// Write the escaped string `content` over as many lines as needed to
+    /// stay within `max` characters (at least one escape or character per
+    /// line)
+    fn wrapped(
+        &mut self,
+        head: &str,
+        content: &str,
+        continuation: &str,
+        suffix: &str,
+        max: usize,
+    ) -> fmt::Result {
+        let atoms: Vec<&str> = atoms(content).collect();
+        let mut rest: usize = atoms.iter().map(|atom| width(atom)).sum();
+        let closing = 1 + width(suffix);
+        let mut line = head.to_string();
+        let mut line_width = width(head);
+        let mut empty = true;
+        for atom in atoms {
+            let atom_width = width(atom);
+            // The last atom needs room for the closing quote, all others for
+            // the indicator in case the line breaks after them
+            let overflows = if rest == atom_width {
+                line_width + atom_width + closing > max
+            } else {
+                line_width + atom_width + 1 > max && line_width + rest + closing > max
+            };
+            if overflows && !empty {
+                line.push(WRAP_INDICATOR);
+                self.push(&line)?;
+                line = continuation.to_string();
+                line_width = width(continuation);
+            }
+            line.push_str(atom);
+            line_width += atom_width;
+            rest -= atom_width;
+            empty = false;
+        }
+        line.push('"');
+        line.push_str(suffix);
+        self.push(&line)
+    }
+
+    /// Write a complete line, noting it if it is too wide
+    fn line_of(&mut self, line: String) -> fmt::Result {
+        if let Some(max) = self.printer.max_width {
+            let width = width(&line);
+            if width > max {
+                let pointer = JsonPointer::from_tokens(self.path.iter().map(String::as_str));
+                self.long_lines.push(LongLine {
+                    pointer: pointer.to_string(),
+                    line: self.lines + 1,
+                    width,
+                });
+            }
+        }
+        self.push(&line)
+    }
+
+    fn push(&mut self, line: &str) -> fmt::Result {
+        if self.lines > 0 {
+            self.out.write_char('\n')?;
+        }
+        self.lines += 1;
+        self.out.write_str(line)
+    }
+}
+
+fn comma(index: usize, len: usize) -> &'static str {
+    if index + 1 < len { "," } else { "" }
+}
+
+fn width(s: &str) -> usize {
+    s.chars().count()
+}
+
+/// Split escaped string content into characters and whole escape sequences
+fn atoms(content: &str) -> impl Iterator<Item = &str> {
+    let mut rest = content;
+    std::iter::from_fn(move || {
+        let mut chars = rest.char_indices();
+        let (_, first) = chars.next()?;
+        let len = match first {
+            '\\' if rest[1..].starts_with('u') => 6.min(rest.len()),
+            '\\' => 1 + chars.next().map_or(0, |(_, ch)| ch.len_utf8()),
+            ch => ch.len_utf8(),
+        };
+        let (atom, tail) = rest.split_at(len);
+        rest = tail;
+        Some(atom)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_json;
+
+    #[test]
+    fn test_pretty_layout() {
+        let value = parse_json(r#"{"a": [1, {"b": null}], "c": [], "d": {}}"#).unwrap();
+        assert_eq!(
+            value.to_pretty_string(),
+            "{\n  \"a\": [\n    1,\n    {\n      \"b\": null\n    }\n  ],\n  \"c\": [],\n  \"d\": {}\n}"
+        );
+        assert_eq!(parse_json("\"x\"").unwrap().to_pretty_string(), "\"x\"");
+    }
+
+    #[test]
+    fn test_wrap_keeps_escapes_whole() {
+        let value = parse_json(r#"["\n\n\n\n\n\n\n\n", "short"]"#).unwrap();
+        let printer = PrettyPrinter::new().max_width(Some(10)).wrap_strings(true);
+        let printed = printer.print(&value);
+        for line in printed.lines() {
+            assert!(width(line) <= 10, "{:?}", line);
+            assert!(!line.trim_end_matches(WRAP_INDICATOR).ends_with("\\\\n"));
+        }
+        let unwrapped: String = printed
+            .lines()
+            .map(|line| line.trim_start().trim_end_matches(WRAP_INDICATOR))
+            .collect();
+        assert_eq!(unwrapped, r#"["\n\n\n\n\n\n\n\n","short"]"#);
+        assert!(printer.long_lines(&value)[0].width > 10);
+    }
+
+    #[test]
+    fn test_key_order() {
+        let value = parse_json(r#"[{"b": {"d": 1, "c": 2}, "a": []}]"#).unwrap();
+        let sorted = PrettyPrinter::new().key_order(KeyOrder::Sorted);
+        assert_eq!(
+            sorted.print(&value),
+            "[\n  {\n    \"a\": [],\n    \"b\": {\n      \"c\": 2,\n      \"d\": 1\n    }\n  }\n]"
+        );
+        let reversed = sorted.minify(true).key_order(KeyOrder::By(|a, b| b.cmp(a)));
+        assert_eq!(reversed.print(&value), r#"[{"b":{"d":1,"c":2},"a":[]}]"#);
+        assert_eq!(
+            PrettyPrinter::new().minify(true).print(&value),
+            value.to_minified_string()
+        );
+    }
+
+    #[test]
+    fn test_escape_non_ascii() {
+        let value = JsonValue::Array(vec![
+            JsonValue::from("\u{1}ü\n"),
+            JsonValue::RawString("\\ud800ü".to_string()),
+        ]);
+        assert_eq!(value.to_minified_string(), "[\"\\u0001ü\\n\",\"\\ud800ü\"]");
+        let ascii = PrettyPrinter::new().escape_non_ascii(true);
+        assert_eq!(
+            ascii.minify(true).print(&value),
+            r#"["\u0001\u00fc\n","\ud800\u00fc"]"#
+        );
+        assert!(ascii.print(&value).is_ascii());
+    }
+
+    #[test]
+    fn test_write_json_streams_same_output() {
+        let value = parse_json(r#"{"a": [1, "x\ty"], "b": {}}"#).unwrap();
+
+        let mut compact = Vec::new();
+        value.write_json(&mut compact).unwrap();
+        assert_eq!(String::from_utf8(compact).unwrap(), value.to_json_string());
+        assert_eq!(format!("{}", value), value.to_json_string());
+
+        let mut pretty = Vec::new();
+        value.write_json_pretty(&mut pretty).unwrap();
+        assert_eq!(String::from_utf8(pretty).unwrap(), value.to_pretty_string());
+        assert_eq!(format!("{:#}", value), value.to_pretty_string());
+
+        let mut full = [0u8; 8];
+        let error = value.write_json(&mut &mut full[..]).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::WriteZero);
+    }
+}
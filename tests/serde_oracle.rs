@@ -0,0 +1,220 @@
+//! Differential tests against serde_json
+//!
+//! serde_json serves as the reference implementation: every input is parsed
+//! by both crates and the outcomes are compared. A mismatch is either an
+//! acceptance mismatch (one parser accepts what the other rejects) or a value
+//! mismatch (both accept but build different trees).
+
+use std::fs;
+
+use json_parser_rs::{JsonNumber, JsonValue, parse_json};
+
+enum Mismatch {
+    Acceptance { ours: bool, serde: bool },
+    Value { ours: JsonValue, serde: JsonValue },
+}
+
+impl std::fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let verdict = |ok: &bool| if *ok { "accepted" } else { "rejected" };
+        match self {
+            Mismatch::Acceptance { ours, serde } => write!(
+                f,
+                "acceptance mismatch: we {}, serde_json {}",
+                verdict(ours),
+                verdict(serde)
+            ),
+            Mismatch::Value { ours, serde } => write!(
+                f,
+                "value mismatch: we built {}, serde_json built {}",
+                ours.to_json_string(),
+                serde.to_json_string()
+            ),
+        }
+    }
+}
+
+fn from_serde(value: serde_json::Value) -> JsonValue {
+    match value {
+        serde_json::Value::Null => JsonValue::Null,
+        serde_json::Value::Bool(b) => JsonValue::Boolean(b),
+        serde_json::Value::Number(n) => JsonValue::Number(if let Some(n) = n.as_u64() {
+            n.into()
+        } else if let Some(n) = n.as_i64() {
+            n.into()
+        } else {
+            JsonNumber::from_f64(n.as_f64().unwrap()).unwrap()
+        }),
+        serde_json::Value::String(s) => JsonValue::String(s),
+        serde_json::Value::Array(elements) => {
+            JsonValue::Array(elements.into_iter().map(from_serde).collect())
+        }
+        serde_json::Value::Object(map) => {
+            JsonValue::Object(map.into_iter().map(|(k, v)| (k, from_serde(v))).collect())
+        }
+    }
+}
+
+fn compare(input: &str) -> Option<Mismatch> {
+    let ours = parse_json(input);
+    let serde = serde_json::from_str::<serde_json::Value>(input);
+    match (ours, serde) {
+        (Ok(ours), Ok(serde)) => {
+            let serde = from_serde(serde);
+            (ours != serde).then_some(Mismatch::Value { ours, serde })
+        }
+        (Err(_), Err(_)) => None,
+        (ours, serde) => Some(Mismatch::Acceptance {
+            ours: ours.is_ok(),
+            serde: serde.is_ok(),
+        }),
+    }
+}
+
+fn assert_agreement<'a>(inputs: impl IntoIterator<Item = &'a str>) {
+    let report: Vec<String> = inputs
+        .into_iter()
+        .filter_map(|input| compare(input).map(|m| format!("{}\n  input: {}", m, input)))
+        .collect();
+    assert!(
+        report.is_empty(),
+        "serde_json mismatches:\n{}",
+        report.join("\n")
+    );
+}
+
+/// Small deterministic xorshift generator so failures are reproducible
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, n: u64) -> u64 {
+        self.next() % n
+    }
+}
+
+fn random_string(rng: &mut Rng) -> String {
+    const ALPHABET: &[char] = &[
+        'a', 'z', 'Q', '0', ' ', '"', '\\', '/', 'é', '€', '😀', '\n', '\t', '\u{1}', '\u{8}',
+        '\u{c}', '\u{1f}',
+    ];
+    (0..rng.below(8))
+        .map(|_| ALPHABET[rng.below(ALPHABET.len() as u64) as usize])
+        .collect()
+}
+
+fn random_value(rng: &mut Rng, depth: usize) -> serde_json::Value {
+    let kinds = if depth == 0 { 4 } else { 6 };
+    match rng.below(kinds) {
+        0 => serde_json::Value::Null,
+        1 => serde_json::Value::Bool(rng.below(2) == 0),
+        2 => match rng.below(3) {
+            0 => serde_json::json!(rng.next()),
+            1 => serde_json::json!(rng.next() as i64),
+            _ => {
+                // Any finite double, including ones printed with exponents
+                let f = f64::from_bits(rng.next());
+                serde_json::json!(if f.is_finite() { f } else { 0.5 })
+            }
+        },
+        3 => serde_json::Value::String(random_string(rng)),
+        4 => (0..rng.below(5))
+            .map(|_| random_value(rng, depth - 1))
+            .collect(),
+        _ => {
+            let mut map = serde_json::Map::new();
+            for i in 0..rng.below(5) {
+                map.insert(
+                    format!("{}{}", random_string(rng), i),
+                    random_value(rng, depth - 1),
+                );
+            }
+            serde_json::Value::Object(map)
+        }
+    }
+}
+
+#[test]
+fn test_oracle_fixture_corpus() {
+    let mut files = Vec::new();
+    for step in ["step1", "step2", "step3", "step4"] {
+        for entry in fs::read_dir(format!("tests/{}", step)).unwrap() {
+            files.push(fs::read_to_string(entry.unwrap().path()).unwrap());
+        }
+    }
+    assert_agreement(files.iter().map(String::as_str));
+}
+
+#[test]
+fn test_oracle_handwritten_corpus() {
+    assert_agreement([
+        "null",
+        "true",
+        " [1, -2, 3.25, \"x\"] ",
+        r#"{"a": {"b": [{}, []]}}"#,
+        r#""quote \" backslash \\ tab \t""#,
+        "",
+        "[",
+        "[1,]",
+        r#"{"a" 1}"#,
+        r#"{"a": 1,}"#,
+        "[1 2]",
+        "nul",
+        "tru",
+        r#""unterminated"#,
+        r#""bad \q escape""#,
+        r#""\u00e9\ud83d\ude00 \/ \b\f""#,
+        r#""\ud800""#,
+        r#""\udc00\ud800""#,
+        r#""\u12""#,
+        "{} {}",
+    ]);
+}
+
+#[test]
+fn test_oracle_random_documents() {
+    let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+    let documents: Vec<String> = (0..500)
+        .map(|i| {
+            let value = random_value(&mut rng, 4);
+            if i % 2 == 0 {
+                serde_json::to_string(&value).unwrap()
+            } else {
+                serde_json::to_string_pretty(&value).unwrap()
+            }
+        })
+        .collect();
+    assert_agreement(documents.iter().map(String::as_str));
+}
+
+/// Byte-level mutations of valid documents. Known divergences still exist
+/// (e.g. lenient number handling), so this reports
+/// instead of gating; run with `cargo test -- --ignored` to see the list.
+#[test]
+#[ignore]
+fn report_oracle_mutations() {
+    let mut rng = Rng(0x9e37_79b9_7f4a_7c15);
+    let mut mismatches = 0;
+    for _ in 0..2000 {
+        let mut bytes = serde_json::to_vec(&random_value(&mut rng, 3)).unwrap();
+        if bytes.is_empty() {
+            continue;
+        }
+        let index = rng.below(bytes.len() as u64) as usize;
+        const REPLACEMENTS: &[u8] = b" \t\n{}[],:\"\\-.0123456789eE+truefalsn";
+        bytes[index] = REPLACEMENTS[rng.below(REPLACEMENTS.len() as u64) as usize];
+        if let Ok(input) = std::str::from_utf8(&bytes)
+            && let Some(mismatch) = compare(input)
+        {
+            mismatches += 1;
+            println!("{}\n  input: {}", mismatch, input);
+        }
+    }
+    println!("{} mismatches", mismatches);
+}